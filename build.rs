@@ -1,11 +1,14 @@
 use diesel::prelude::*;
 use diesel_migrations::run_pending_migrations;
 use std::env;
+use std::fs;
+use std::path::Path;
 
 fn main() {
     println!("cargo:rerun-if-env-changed=TEST_DATABASE_URL");
     println!("cargo:rerun-if-changed=.env");
     println!("cargo:rerun-if-changed=migrations/");
+    write_migration_versions();
     if env::var("PROFILE") == Ok("debug".into()) {
         if let Ok(database_url) = dotenv::var("TEST_DATABASE_URL") {
             let connection = PgConnection::establish(&database_url)
@@ -14,3 +17,25 @@ fn main() {
         }
     }
 }
+
+/// Lists the versions of the migrations for `src/migrations.rs`, the same way as Diesel does.
+fn write_migration_versions() {
+    let mut versions = fs::read_dir("migrations")
+        .expect("Could not read the migrations directory")
+        .map(|entry| entry.expect("Could not read the migrations directory"))
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.split('_').next().unwrap_or_default().replace('-', "")
+        })
+        .collect::<Vec<_>>();
+    versions.sort();
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let contents = format!(
+        "/// The versions of all the migrations, in the order they run\npub const MIGRATIONS: &[&str] = &{:?};\n",
+        versions
+    );
+    fs::write(Path::new(&out_dir).join("migrations.rs"), contents)
+        .expect("Could not write the list of migrations");
+}
@@ -0,0 +1,7 @@
+CREATE TABLE version_changelogs (
+    version_id INTEGER PRIMARY KEY REFERENCES versions(id) ON DELETE CASCADE,
+    file_name VARCHAR NOT NULL,
+    html TEXT NOT NULL,
+    section_html TEXT,
+    rendered_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,3 @@
+ALTER TABLE versions
+    DROP COLUMN vcs_sha1,
+    DROP COLUMN vcs_dirty;
@@ -0,0 +1,3 @@
+ALTER TABLE versions
+    ADD COLUMN vcs_sha1 VARCHAR,
+    ADD COLUMN vcs_dirty BOOLEAN;
@@ -0,0 +1,3 @@
+DROP TABLE version_rebuilds;
+
+ALTER TABLE versions DROP COLUMN checksum;
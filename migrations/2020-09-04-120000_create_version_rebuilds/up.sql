@@ -0,0 +1,11 @@
+ALTER TABLE versions ADD COLUMN checksum VARCHAR;
+
+CREATE TABLE version_rebuilds (
+    id SERIAL PRIMARY KEY,
+    version_id INTEGER NOT NULL REFERENCES versions(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    checksum VARCHAR NOT NULL,
+    matches BOOLEAN NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (version_id, user_id)
+);
@@ -0,0 +1,3 @@
+DROP TABLE crate_pageview_visitors;
+DROP TABLE pageview_salts;
+DROP TABLE crate_pageviews;
@@ -0,0 +1,21 @@
+CREATE TABLE crate_pageviews (
+    crate_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    views INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (crate_id, date)
+);
+
+-- A random salt is generated every day to hash the identity of visitors, so
+-- they can be counted once per day without being traceable. Salts and
+-- visitor hashes are deleted once the day is over.
+CREATE TABLE pageview_salts (
+    date DATE PRIMARY KEY DEFAULT CURRENT_DATE,
+    salt BYTEA NOT NULL
+);
+
+CREATE TABLE crate_pageview_visitors (
+    crate_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    visitor BYTEA NOT NULL,
+    PRIMARY KEY (crate_id, date, visitor)
+);
@@ -0,0 +1,31 @@
+-- Populated by the `reconcile_dependents_counts` background job
+ALTER TABLE crates ADD COLUMN dependents_count INTEGER NOT NULL DEFAULT 0;
+CREATE INDEX index_crates_dependents_count ON crates (dependents_count DESC);
+
+-- Like `set_updated_at_ignore_downloads`, but the cached dependents count
+-- doesn't touch the timestamp either
+CREATE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_dependents_count integer;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_dependents_count := NEW.dependents_count;
+    OLD.downloads := NEW.downloads;
+    OLD.dependents_count := NEW.dependents_count;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.dependents_count := new_dependents_count;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER trigger_crates_set_updated_at ON crates;
+CREATE TRIGGER trigger_crates_set_updated_at BEFORE UPDATE
+ON crates
+FOR EACH ROW EXECUTE PROCEDURE set_crates_updated_at();
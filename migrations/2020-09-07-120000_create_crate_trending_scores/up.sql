@@ -0,0 +1,10 @@
+CREATE TABLE crate_trending_scores (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates(id) ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL,
+    recent_downloads BIGINT NOT NULL,
+    baseline_downloads BIGINT NOT NULL,
+    new_dependents INTEGER NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX index_crate_trending_scores_score ON crate_trending_scores (score DESC);
@@ -0,0 +1,2 @@
+DROP TABLE crate_quality_scores;
+ALTER TABLE versions DROP COLUMN rust_version;
@@ -0,0 +1,14 @@
+-- Populated from the `rust-version` field of the manifest in the `.crate` file
+ALTER TABLE versions ADD COLUMN rust_version VARCHAR;
+
+CREATE TABLE crate_quality_scores (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates(id) ON DELETE CASCADE,
+    formula_version INTEGER NOT NULL,
+    docs DOUBLE PRECISION NOT NULL,
+    recent_release DOUBLE PRECISION NOT NULL,
+    tests DOUBLE PRECISION NOT NULL,
+    msrv DOUBLE PRECISION NOT NULL,
+    not_yanked DOUBLE PRECISION NOT NULL,
+    total DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT now()
+);
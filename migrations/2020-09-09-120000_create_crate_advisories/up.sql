@@ -0,0 +1,12 @@
+CREATE TABLE crate_advisories (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    -- The identifier of the advisory, e.g. `RUSTSEC-2020-0001`
+    identifier VARCHAR NOT NULL,
+    title VARCHAR NOT NULL,
+    url VARCHAR,
+    -- Semver requirements matching the affected versions
+    affected_versions TEXT[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (crate_id, identifier)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE crate_publish_policies (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates(id) ON DELETE CASCADE,
+    -- The API tokens new versions may be published with, or NULL to allow any
+    allowed_token_ids INTEGER[],
+    -- Whether members of owning teams are prevented from publishing
+    require_user_owner BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
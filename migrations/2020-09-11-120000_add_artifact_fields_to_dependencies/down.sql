@@ -0,0 +1,3 @@
+ALTER TABLE dependencies DROP COLUMN lib;
+ALTER TABLE dependencies DROP COLUMN bindep_target;
+ALTER TABLE dependencies DROP COLUMN artifact;
@@ -0,0 +1,4 @@
+-- The artifact kinds requested by artifact dependencies, e.g. `bin` or `cdylib`
+ALTER TABLE dependencies ADD COLUMN artifact TEXT[];
+ALTER TABLE dependencies ADD COLUMN bindep_target VARCHAR;
+ALTER TABLE dependencies ADD COLUMN lib BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- The targets listed as platform hints in `package.metadata.docs.rs`
+ALTER TABLE versions ADD COLUMN metadata_targets TEXT[];
@@ -0,0 +1,8 @@
+-- Versions which were mirrored from the upstream registry instead of being
+-- published to this instance
+CREATE TABLE upstream_versions (
+    version_id INTEGER PRIMARY KEY REFERENCES versions(id) ON DELETE CASCADE,
+    -- When the `.crate` file was copied to the local storage, or NULL if it has
+    -- not been downloaded yet
+    crate_file_cached_at TIMESTAMP
+);
@@ -0,0 +1,2 @@
+DROP TABLE replication_cursors;
+DROP TABLE registry_events;
@@ -0,0 +1,19 @@
+-- A log of the changes to crates, which other instances can replay to follow
+-- this one. Crates are referenced by name so that events outlive them.
+CREATE TABLE registry_events (
+    id BIGSERIAL PRIMARY KEY,
+    kind INTEGER NOT NULL,
+    crate_name VARCHAR NOT NULL,
+    version VARCHAR,
+    owner_kind INTEGER,
+    owner_login VARCHAR,
+    owner_github_id INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- The last event of another instance which was replayed by this instance
+CREATE TABLE replication_cursors (
+    upstream_url VARCHAR PRIMARY KEY,
+    last_event_id BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
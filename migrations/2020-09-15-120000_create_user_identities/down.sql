@@ -0,0 +1,4 @@
+DROP TABLE user_identities;
+
+DROP INDEX teams_github_id;
+ALTER TABLE teams ADD CONSTRAINT teams_github_id_key UNIQUE (github_id);
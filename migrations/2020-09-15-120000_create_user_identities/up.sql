@@ -0,0 +1,17 @@
+-- Teams of single sign-on providers have no GitHub ID and use `0` instead
+ALTER TABLE teams DROP CONSTRAINT teams_github_id_key;
+CREATE UNIQUE INDEX teams_github_id ON teams (github_id) WHERE github_id > 0;
+
+CREATE TABLE user_identities (
+    -- The name of the authentication provider, e.g. `oidc`
+    provider VARCHAR NOT NULL,
+    -- The identifier of the user at the provider
+    subject VARCHAR NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- The groups of the user at the provider, as of their last login
+    groups TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (provider, subject)
+);
+
+CREATE INDEX user_identities_user_id ON user_identities (user_id);
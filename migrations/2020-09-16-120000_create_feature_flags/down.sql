@@ -0,0 +1,3 @@
+DROP TABLE feature_flags;
+
+ALTER TABLE users DROP COLUMN is_admin;
@@ -0,0 +1,7 @@
+CREATE TABLE crate_notable_dependents (
+    crate_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    dependent_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    downloads BIGINT NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (crate_id, dependent_id)
+);
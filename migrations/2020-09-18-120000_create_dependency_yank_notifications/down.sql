@@ -0,0 +1 @@
+DROP TABLE dependency_yank_notifications;
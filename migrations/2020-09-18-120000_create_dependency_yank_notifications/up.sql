@@ -0,0 +1,9 @@
+-- The owners who were told that a version their crate depends on was yanked,
+-- so that they are told only once per yanked version and dependent crate.
+CREATE TABLE dependency_yank_notifications (
+    version_id INTEGER NOT NULL REFERENCES versions(id) ON DELETE CASCADE,
+    crate_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (version_id, crate_id, user_id)
+);
@@ -0,0 +1,9 @@
+-- Domains blocked or allowed by the admins, in addition to the bundled list of
+-- disposable email providers.
+CREATE TABLE email_domain_rules (
+    domain VARCHAR NOT NULL PRIMARY KEY,
+    blocked BOOLEAN NOT NULL,
+    -- The admin who last changed the rule
+    updated_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,12 @@
+-- The new versions which look like spam, held for review by the admins before
+-- being added to the index.
+CREATE TABLE quarantined_versions (
+    version_id INTEGER PRIMARY KEY REFERENCES versions(id) ON DELETE CASCADE,
+    spam_score INTEGER NOT NULL,
+    reasons TEXT[] NOT NULL,
+    -- The entry added to the index once the version is approved
+    index_entry JSONB NOT NULL,
+    -- The READMEs and changelog rendered once the version is approved
+    renderings JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
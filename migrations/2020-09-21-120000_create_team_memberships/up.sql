@@ -0,0 +1,10 @@
+-- The result of the last check of whether a user belongs to a team which owns
+-- crates, so that the members of a team can be listed without asking the
+-- authentication provider.
+CREATE TABLE team_memberships (
+    team_id INTEGER NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    active BOOLEAN NOT NULL,
+    checked_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (team_id, user_id)
+);
@@ -0,0 +1,2 @@
+DROP TABLE category_stats;
+DROP TABLE keyword_stats;
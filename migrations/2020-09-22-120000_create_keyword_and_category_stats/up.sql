@@ -0,0 +1,17 @@
+-- The number of crates and the downloads of each keyword and category per day,
+-- recorded by the `update_usage_stats` job, to show which areas are growing.
+CREATE TABLE keyword_stats (
+    keyword_id INTEGER NOT NULL REFERENCES keywords(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    crates_cnt INTEGER NOT NULL,
+    downloads BIGINT NOT NULL,
+    PRIMARY KEY (keyword_id, date)
+);
+
+CREATE TABLE category_stats (
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    crates_cnt INTEGER NOT NULL,
+    downloads BIGINT NOT NULL,
+    PRIMARY KEY (category_id, date)
+);
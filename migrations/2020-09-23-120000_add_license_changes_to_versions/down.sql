@@ -0,0 +1,3 @@
+ALTER TABLE versions
+    DROP COLUMN license_changed,
+    DROP COLUMN previous_license;
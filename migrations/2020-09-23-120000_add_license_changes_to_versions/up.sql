@@ -0,0 +1,5 @@
+-- Whether the license of a version differs from the one of the version
+-- published before it, and what the license was then
+ALTER TABLE versions
+    ADD COLUMN license_changed BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN previous_license VARCHAR;
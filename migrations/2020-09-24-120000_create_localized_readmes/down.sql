@@ -0,0 +1 @@
+DROP TABLE localized_readmes;
@@ -0,0 +1,9 @@
+-- The localized READMEs of a version which were rendered, in addition to its
+-- main README
+CREATE TABLE localized_readmes (
+    version_id INTEGER NOT NULL REFERENCES versions(id) ON DELETE CASCADE,
+    language VARCHAR NOT NULL,
+    file_name VARCHAR NOT NULL,
+    rendered_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (version_id, language)
+);
@@ -0,0 +1,12 @@
+-- The result of the last check of the documentation, homepage and repository
+-- URLs of each crate by the `check_crate_links` background job.
+CREATE TABLE crate_link_checks (
+    crate_id INTEGER NOT NULL REFERENCES crates(id) ON DELETE CASCADE,
+    field VARCHAR NOT NULL,
+    url VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    checked_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (crate_id, field)
+);
+
+CREATE INDEX crate_link_checks_checked_at ON crate_link_checks (checked_at);
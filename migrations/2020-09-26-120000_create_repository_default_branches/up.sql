@@ -0,0 +1,7 @@
+-- The default branches of the repositories of crates, fetched from the API of
+-- their host, which relative links in READMEs point to.
+CREATE TABLE repository_default_branches (
+    repository VARCHAR NOT NULL PRIMARY KEY,
+    branch VARCHAR NOT NULL,
+    fetched_at TIMESTAMP NOT NULL DEFAULT now()
+);
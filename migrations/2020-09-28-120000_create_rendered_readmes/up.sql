@@ -0,0 +1,10 @@
+-- The HTML of the READMEs rendered by the background jobs, keyed by the SHA-256 of the
+-- text and the settings they were rendered with, including the version of the renderer.
+CREATE TABLE rendered_readmes (
+    sha256 BYTEA NOT NULL PRIMARY KEY,
+    renderer_version INTEGER NOT NULL,
+    html TEXT NOT NULL,
+    rendered_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX rendered_readmes_renderer_version_idx ON rendered_readmes (renderer_version);
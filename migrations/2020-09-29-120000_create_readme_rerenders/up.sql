@@ -0,0 +1,12 @@
+-- The re-renderings of all the READMEs requested by admins, e.g. after a change of the
+-- sanitizer, which the `rerender_readmes` background job goes through in batches.
+CREATE TABLE readme_rerenders (
+    id SERIAL PRIMARY KEY,
+    requested_by INTEGER NOT NULL REFERENCES users(id),
+    total INTEGER NOT NULL,
+    rendered INTEGER NOT NULL DEFAULT 0,
+    skipped INTEGER NOT NULL DEFAULT 0,
+    last_version_id INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    finished_at TIMESTAMP
+);
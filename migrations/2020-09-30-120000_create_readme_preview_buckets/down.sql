@@ -0,0 +1 @@
+DROP TABLE readme_preview_buckets;
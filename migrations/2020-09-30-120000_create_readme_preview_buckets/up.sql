@@ -0,0 +1,7 @@
+-- The token buckets rate limiting the README previews of each user, like
+-- `publish_limit_buckets` does for publishing.
+CREATE TABLE readme_preview_buckets (
+    user_id INTEGER PRIMARY KEY NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tokens INTEGER NOT NULL,
+    last_refill TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,25 @@
+DROP TRIGGER trigger_update_crates_has_unyanked_versions ON versions;
+DROP FUNCTION update_crates_has_unyanked_versions();
+
+CREATE OR REPLACE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_dependents_count integer;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_dependents_count := NEW.dependents_count;
+    OLD.downloads := NEW.downloads;
+    OLD.dependents_count := NEW.dependents_count;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.dependents_count := new_dependents_count;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE crates DROP COLUMN has_unyanked_versions;
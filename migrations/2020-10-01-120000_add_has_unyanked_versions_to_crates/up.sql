@@ -0,0 +1,57 @@
+-- Whether any version of the crate isn't yanked, so that the search can leave out the crates
+-- whose every version is yanked without looking at their versions
+ALTER TABLE crates ADD COLUMN has_unyanked_versions BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Like the downloads and the dependents count, the flag doesn't touch the timestamp, so that
+-- the backfill below and the yanks don't change the order of the recently updated crates
+CREATE OR REPLACE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_dependents_count integer;
+    new_has_unyanked_versions boolean;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_dependents_count := NEW.dependents_count;
+    new_has_unyanked_versions := NEW.has_unyanked_versions;
+    OLD.downloads := NEW.downloads;
+    OLD.dependents_count := NEW.dependents_count;
+    OLD.has_unyanked_versions := NEW.has_unyanked_versions;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.dependents_count := new_dependents_count;
+    NEW.has_unyanked_versions := new_has_unyanked_versions;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+UPDATE crates SET has_unyanked_versions = TRUE
+WHERE EXISTS (
+    SELECT 1 FROM versions
+    WHERE versions.crate_id = crates.id AND NOT versions.yanked
+);
+
+CREATE FUNCTION update_crates_has_unyanked_versions() RETURNS trigger AS $$
+DECLARE
+    modified_crate_id integer;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        modified_crate_id := OLD.crate_id;
+    ELSE
+        modified_crate_id := NEW.crate_id;
+    END IF;
+    UPDATE crates SET has_unyanked_versions = EXISTS (
+        SELECT 1 FROM versions
+        WHERE versions.crate_id = modified_crate_id AND NOT versions.yanked
+    ) WHERE crates.id = modified_crate_id;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_update_crates_has_unyanked_versions
+AFTER INSERT OR DELETE OR UPDATE OF yanked ON versions
+FOR EACH ROW EXECUTE PROCEDURE update_crates_has_unyanked_versions();
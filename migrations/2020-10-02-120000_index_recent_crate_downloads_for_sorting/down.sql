@@ -0,0 +1 @@
+DROP INDEX index_recent_crate_downloads_by_downloads_desc;
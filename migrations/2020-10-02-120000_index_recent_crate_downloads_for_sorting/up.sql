@@ -0,0 +1,4 @@
+-- `sort=recent-downloads` orders by `downloads DESC NULLS LAST`, which a backward scan of
+-- `index_recent_crate_downloads_by_downloads` can't give, since it puts the nulls first
+CREATE INDEX index_recent_crate_downloads_by_downloads_desc
+  ON recent_crate_downloads (downloads DESC NULLS LAST);
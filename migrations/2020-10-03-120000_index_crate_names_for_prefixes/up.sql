@@ -0,0 +1,4 @@
+-- Serves the prefix matches of `GET /crates/suggest`, which the trigram index can't serve for
+-- prefixes shorter than three characters, and the unique index can't serve at all unless the
+-- database uses the C collation
+CREATE INDEX index_crates_name_prefix ON crates (canon_crate_name(name) text_pattern_ops);
@@ -0,0 +1,27 @@
+CREATE OR REPLACE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_dependents_count integer;
+    new_has_unyanked_versions boolean;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_dependents_count := NEW.dependents_count;
+    new_has_unyanked_versions := NEW.has_unyanked_versions;
+    OLD.downloads := NEW.downloads;
+    OLD.dependents_count := NEW.dependents_count;
+    OLD.has_unyanked_versions := NEW.has_unyanked_versions;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.dependents_count := new_dependents_count;
+    NEW.has_unyanked_versions := new_has_unyanked_versions;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP INDEX index_crates_readme_search;
+ALTER TABLE crates DROP COLUMN readme_textsearchable_index_col;
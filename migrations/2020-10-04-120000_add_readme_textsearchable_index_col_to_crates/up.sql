@@ -0,0 +1,39 @@
+-- The text of the rendered README of the latest version, updated by the
+-- `render_and_upload_readme` job and searched with `?readme=`. It starts from the
+-- raw README until the READMEs are rendered again.
+ALTER TABLE crates ADD COLUMN readme_textsearchable_index_col tsvector NOT NULL DEFAULT ''::tsvector;
+
+-- Like the downloads, the dependents count and the unyanked flag, the README index doesn't
+-- touch the timestamp, including while it is filled below
+CREATE OR REPLACE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_dependents_count integer;
+    new_has_unyanked_versions boolean;
+    new_readme_textsearchable_index_col tsvector;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_dependents_count := NEW.dependents_count;
+    new_has_unyanked_versions := NEW.has_unyanked_versions;
+    new_readme_textsearchable_index_col := NEW.readme_textsearchable_index_col;
+    OLD.downloads := NEW.downloads;
+    OLD.dependents_count := NEW.dependents_count;
+    OLD.has_unyanked_versions := NEW.has_unyanked_versions;
+    OLD.readme_textsearchable_index_col := NEW.readme_textsearchable_index_col;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.dependents_count := new_dependents_count;
+    NEW.has_unyanked_versions := new_has_unyanked_versions;
+    NEW.readme_textsearchable_index_col := new_readme_textsearchable_index_col;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+UPDATE crates SET readme_textsearchable_index_col = to_tsvector('pg_catalog.english', readme)
+WHERE readme IS NOT NULL;
+CREATE INDEX index_crates_readme_search ON crates USING gin (readme_textsearchable_index_col);
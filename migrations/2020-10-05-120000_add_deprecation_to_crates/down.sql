@@ -0,0 +1,3 @@
+ALTER TABLE crates
+    DROP COLUMN deprecated,
+    DROP COLUMN superseded_by;
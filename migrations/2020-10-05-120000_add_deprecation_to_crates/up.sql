@@ -0,0 +1,3 @@
+ALTER TABLE crates
+    ADD COLUMN deprecated BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN superseded_by VARCHAR;
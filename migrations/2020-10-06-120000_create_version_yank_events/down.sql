@@ -0,0 +1 @@
+DROP TABLE version_yank_events;
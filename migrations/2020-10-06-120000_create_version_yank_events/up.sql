@@ -0,0 +1,11 @@
+CREATE TABLE version_yank_events (
+    id SERIAL PRIMARY KEY,
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id),
+    api_token_id INTEGER REFERENCES api_tokens (id),
+    yanked BOOLEAN NOT NULL,
+    reason VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX index_version_yank_events_version_id ON version_yank_events (version_id);
@@ -0,0 +1 @@
+DROP TABLE crate_ownership_transfers;
@@ -0,0 +1,9 @@
+CREATE TABLE crate_ownership_transfers (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+    requested_by_user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    target_user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token TEXT NOT NULL DEFAULT random_string(26),
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX index_crate_ownership_transfers_token ON crate_ownership_transfers (token);
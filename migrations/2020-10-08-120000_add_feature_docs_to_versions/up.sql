@@ -0,0 +1,2 @@
+-- The documentation of the features, written as `##` comments in the manifest
+ALTER TABLE versions ADD COLUMN feature_docs JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,8 @@
+CREATE TABLE version_diffs (
+    from_version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    to_version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    diff JSONB,
+    requested_at TIMESTAMP NOT NULL DEFAULT now(),
+    computed_at TIMESTAMP,
+    PRIMARY KEY (from_version_id, to_version_id)
+);
@@ -0,0 +1,4 @@
+ALTER TABLE reserved_crate_names
+    DROP COLUMN reason,
+    DROP COLUMN reserved_by,
+    DROP COLUMN created_at;
@@ -0,0 +1,6 @@
+-- Shown to the users trying to publish a crate with a reserved name.
+ALTER TABLE reserved_crate_names
+    ADD COLUMN reason TEXT,
+    -- The admin who reserved the name, unknown for the names reserved by migrations
+    ADD COLUMN reserved_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT now();
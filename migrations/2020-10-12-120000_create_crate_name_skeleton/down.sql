@@ -0,0 +1,2 @@
+DROP INDEX crates_name_skeleton;
+DROP FUNCTION crate_name_skeleton(text);
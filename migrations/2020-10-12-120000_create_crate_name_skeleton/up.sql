@@ -0,0 +1,11 @@
+-- The form of a crate name used to find the names that can be confused with it: lowercase,
+-- without separators, and with the characters that look alike replaced by one of them.
+CREATE FUNCTION crate_name_skeleton(text) RETURNS text AS $$
+    SELECT translate(
+        replace(replace(translate(lower($1), '-_', ''), 'rn', 'm'), 'vv', 'w'),
+        '01i',
+        'oll'
+    )
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX crates_name_skeleton ON crates (crate_name_skeleton(name));
@@ -0,0 +1 @@
+DROP TABLE replayed_registry_events;
@@ -0,0 +1,9 @@
+-- The events of another instance replayed recently by this instance. The ids of the events are
+-- allocated before their transaction commits, so an event can appear after events with higher
+-- ids. The recent events are read again, and the ones listed here are skipped.
+CREATE TABLE replayed_registry_events (
+    upstream_url VARCHAR NOT NULL,
+    event_id BIGINT NOT NULL,
+    replayed_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (upstream_url, event_id)
+);
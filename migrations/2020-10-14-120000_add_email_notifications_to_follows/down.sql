@@ -0,0 +1 @@
+ALTER TABLE follows DROP COLUMN email_notifications;
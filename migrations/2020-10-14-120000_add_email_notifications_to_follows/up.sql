@@ -0,0 +1,2 @@
+-- Whether the follower is emailed when a new version of the crate changes its license
+ALTER TABLE follows ADD COLUMN email_notifications BOOLEAN NOT NULL DEFAULT TRUE;
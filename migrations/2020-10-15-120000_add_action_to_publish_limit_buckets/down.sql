@@ -0,0 +1,9 @@
+DELETE FROM publish_limit_buckets WHERE action <> 0;
+ALTER TABLE publish_limit_buckets DROP CONSTRAINT publish_limit_buckets_pkey;
+ALTER TABLE publish_limit_buckets ADD PRIMARY KEY (user_id);
+ALTER TABLE publish_limit_buckets DROP COLUMN action;
+CREATE TABLE readme_preview_buckets (
+    user_id INTEGER PRIMARY KEY NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tokens INTEGER NOT NULL,
+    last_refill TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+-- The buckets of the other actions limited per user are kept along with those of publishing,
+-- `0`, see `LimitedAction`.
+DROP TABLE readme_preview_buckets;
+ALTER TABLE publish_limit_buckets ADD COLUMN action INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE publish_limit_buckets DROP CONSTRAINT publish_limit_buckets_pkey;
+ALTER TABLE publish_limit_buckets ADD PRIMARY KEY (user_id, action);
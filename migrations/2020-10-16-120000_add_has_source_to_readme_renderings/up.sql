@@ -0,0 +1,3 @@
+-- Whether the markdown source of the README was uploaded along with its rendering, which isn't
+-- the case of the versions rendered before `?format=md` was served.
+ALTER TABLE readme_renderings ADD COLUMN has_source BOOLEAN NOT NULL DEFAULT FALSE;
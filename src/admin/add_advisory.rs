@@ -0,0 +1,54 @@
+use crate::{
+    db,
+    models::{Crate, NewCrateAdvisory},
+};
+
+use clap::Clap;
+use diesel::prelude::*;
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "add-advisory",
+    about = "Record a security advisory affecting some versions of a crate.",
+    after_help = "An existing advisory with the same identifier for the crate is replaced."
+)]
+pub struct Opts {
+    /// Name of the affected crate
+    crate_name: String,
+    /// Identifier of the advisory, e.g. `RUSTSEC-2020-0001`
+    identifier: String,
+    /// Short description of the advisory
+    title: String,
+    /// Semver requirement matching affected versions, can be repeated
+    #[clap(long = "affected", required = true)]
+    affected_versions: Vec<String>,
+    /// URL with the details of the advisory
+    #[clap(long)]
+    url: Option<String>,
+}
+
+pub fn run(opts: Opts) {
+    let conn = db::connect_now().unwrap();
+
+    for req in &opts.affected_versions {
+        if let Err(e) = semver::VersionReq::parse(req) {
+            panic!("invalid version requirement `{}`: {}", req, e);
+        }
+    }
+
+    let krate: Crate = Crate::by_name(&opts.crate_name).first(&conn).unwrap();
+    let advisory = NewCrateAdvisory {
+        crate_id: krate.id,
+        identifier: &opts.identifier,
+        title: &opts.title,
+        url: opts.url.as_deref(),
+        affected_versions: &opts.affected_versions,
+    }
+    .save(&conn)
+    .unwrap();
+
+    println!(
+        "recorded advisory {} for crate {} ({})",
+        advisory.identifier, krate.name, advisory.id
+    );
+}
@@ -0,0 +1,64 @@
+use crate::{db, render::audit, schema::crates, Config};
+
+use clap::Clap;
+use diesel::prelude::*;
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "audit-readmes",
+    about = "Checks that the README sanitizer stops a corpus of known XSS payloads, and the \
+        READMEs of the most downloaded crates.",
+    long_about = "Checks that the README sanitizer stops a corpus of known XSS payloads, and \
+        the READMEs of the most downloaded crates. The READMEs are rendered again with the \
+        current renderer, and the elements, attributes and URLs running scripts that their \
+        HTML still contains are listed. Exits with an error if anything is found."
+)]
+pub struct Opts {
+    /// How many of the most downloaded crates to audit the README of.
+    #[clap(long, default_value = "1000")]
+    top: i64,
+}
+
+pub fn run(opts: Opts) {
+    let config = Config::default();
+    let settings = Some(&config.render);
+    let mut found = false;
+
+    for (payload, findings) in audit::audit_corpus(settings) {
+        found = true;
+        println!("Payload {:?}:", payload);
+        for finding in findings {
+            println!("  - {}", finding);
+        }
+    }
+
+    let conn = db::connect_now().unwrap();
+    let readmes: Vec<(String, Option<String>)> = crates::table
+        .filter(crates::readme.is_not_null())
+        .order(crates::downloads.desc())
+        .limit(opts.top)
+        .select((crates::name, crates::readme))
+        .load(&conn)
+        .expect("error loading the READMEs");
+
+    println!("Auditing the READMEs of {} crates", readmes.len());
+    for (name, readme) in readmes {
+        // The file name of the README isn't stored, so only Markdown READMEs are audited
+        let readme = readme.unwrap_or_default();
+        let findings = audit::audit_markdown(&readme, settings);
+        if findings.is_empty() {
+            continue;
+        }
+        found = true;
+        println!("Crate {}:", name);
+        for finding in findings {
+            println!("  - {}", finding);
+        }
+    }
+
+    if found {
+        eprintln!("The sanitizer let some scripts through");
+        std::process::exit(1);
+    }
+    println!("Nothing was found");
+}
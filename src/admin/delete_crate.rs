@@ -1,7 +1,8 @@
-use crate::{admin::dialoguer, db, models::Crate, schema::crates};
+use crate::{admin::dialoguer, db, models::Crate, schema::crates, tasks};
 
 use clap::Clap;
 use diesel::prelude::*;
+use swirl::Job;
 
 #[derive(Clap, Debug)]
 #[clap(
@@ -40,6 +41,9 @@ fn delete(opts: Opts, conn: &PgConnection) {
         .unwrap();
     println!("  {} deleted", n);
 
+    // Removes the crate from the index of an external search engine, if there is one
+    tasks::index_crate(krate.id).enqueue(conn).unwrap();
+
     if !dialoguer::confirm("commit?") {
         panic!("aborting transaction");
     }
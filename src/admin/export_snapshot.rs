@@ -0,0 +1,140 @@
+use crate::{
+    admin::snapshot::{self, Snapshot, SnapshotCrate, SnapshotVersion},
+    db,
+    git::{Repository, RepositoryConfig},
+    models::{Crate, Version},
+    schema::crates,
+    Config, Uploader,
+};
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::Clap;
+use diesel::prelude::*;
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "export-snapshot",
+    about = "Export the index, crate files and metadata of crates to a directory, \
+        which can be imported into another instance with `import-snapshot`.",
+    after_help = "Warning: exporting all crates can take a lot of time and space."
+)]
+pub struct Opts {
+    /// Directory to write the snapshot to
+    dir: PathBuf,
+    /// Only export the specified crates, can be repeated
+    #[clap(long = "crate")]
+    crate_names: Vec<String>,
+}
+
+pub fn run(opts: Opts) {
+    let config = Config::default();
+    let conn = db::connect_now().unwrap();
+    let client = Client::new();
+
+    println!("Cloning index");
+    let repo =
+        Repository::open(&RepositoryConfig::from_environment()).expect("Failed to clone index");
+
+    let snapshot = export(
+        &conn,
+        &repo,
+        &config.uploader,
+        &client,
+        &opts.dir,
+        &opts.crate_names,
+    );
+    println!(
+        "Exported {} crates to {}",
+        snapshot.crates.len(),
+        opts.dir.display()
+    );
+}
+
+/// Writes the snapshot of the crates with the given names, or of all crates if there are none,
+/// to the directory.
+pub fn export(
+    conn: &PgConnection,
+    repo: &Repository,
+    uploader: &Uploader,
+    client: &Client,
+    dir: &Path,
+    crate_names: &[String],
+) -> Snapshot {
+    let crates: Vec<Crate> = if crate_names.is_empty() {
+        Crate::all().order(crates::name).load(conn).unwrap()
+    } else {
+        crate_names
+            .iter()
+            .map(|name| {
+                Crate::by_name(name)
+                    .first(conn)
+                    .unwrap_or_else(|_| panic!("crate `{}` not found", name))
+            })
+            .collect()
+    };
+
+    let mut snapshot = Snapshot::default();
+    for krate in crates {
+        println!("Exporting {}", krate.name);
+
+        let entries = repo.crate_entries(&krate.name).unwrap();
+        let index_path = snapshot::index_path(dir, &krate.name);
+        fs::create_dir_all(index_path.parent().unwrap()).unwrap();
+        let mut index_file = fs::File::create(&index_path).unwrap();
+        for entry in &entries {
+            serde_json::to_writer(&mut index_file, entry).unwrap();
+            index_file.write_all(b"\n").unwrap();
+        }
+
+        let versions: Vec<Version> = Version::belonging_to(&krate).load(conn).unwrap();
+        let mut snapshot_versions = Vec::with_capacity(versions.len());
+        for version in versions {
+            let num = version.num.to_string();
+            let entry = match entries.iter().find(|entry| entry.vers == num) {
+                Some(entry) => entry,
+                None => {
+                    println!("[{}-{}] Not in the index, skipping", krate.name, num);
+                    continue;
+                }
+            };
+
+            let body = uploader
+                .download_crate_file(client, &krate.name, &num)
+                .unwrap_or_else(|e| panic!("[{}-{}] Unable to fetch crate: {}", krate.name, num, e))
+                .unwrap_or_else(|| panic!("[{}-{}] Crate file not found", krate.name, num));
+            if hex::encode(Sha256::digest(&body)) != entry.cksum {
+                panic!("[{}-{}] Checksum mismatch", krate.name, num);
+            }
+
+            let path = snapshot::crate_file_path(dir, &krate.name, &num);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &body).unwrap();
+
+            snapshot_versions.push(SnapshotVersion {
+                num,
+                license: version.license,
+                created_at: version.created_at,
+            });
+        }
+
+        snapshot.crates.push(SnapshotCrate {
+            name: krate.name,
+            description: krate.description,
+            homepage: krate.homepage,
+            documentation: krate.documentation,
+            repository: krate.repository,
+            versions: snapshot_versions,
+        });
+    }
+
+    let metadata = serde_json::to_vec_pretty(&snapshot).unwrap();
+    fs::write(snapshot::metadata_path(dir), metadata).unwrap();
+    snapshot
+}
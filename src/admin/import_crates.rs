@@ -0,0 +1,637 @@
+use crate::{
+    db,
+    models::{
+        ApiToken, Crate, CrateOwner, DependencyKind, NewUser, Owner, OwnerKind, RegistryEvent,
+        RegistryEventKind, User,
+    },
+    schema::{api_tokens, crate_owners, emails, users},
+    views::krate_publish as u,
+};
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+use clap::Clap;
+use diesel::prelude::*;
+use flate2::read::GzDecoder;
+use reqwest::{blocking::Client, header};
+use tar::Archive;
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "import-crates",
+    about = "Publish a directory of `.crate` files through the publish endpoint of a \
+        running instance, creating the users and owners listed in its `manifest.json`.",
+    after_help = "The crates are published in the order of the manifest, so dependencies \
+        must be listed before the crates depending on them."
+)]
+pub struct Opts {
+    /// Directory containing the `.crate` files and `manifest.json`
+    dir: PathBuf,
+    /// URL of the instance to publish to
+    #[clap(long, default_value = "http://localhost:8888")]
+    registry_url: String,
+    /// Index URL of the registry the crates are imported from. Dependencies on this registry
+    /// are published as dependencies on the crates of this instance.
+    #[clap(long)]
+    index_url: Option<String>,
+}
+
+/// The contents of `manifest.json`.
+#[derive(Deserialize, Debug)]
+struct ImportManifest {
+    #[serde(default)]
+    users: Vec<ImportUser>,
+    crates: Vec<ImportCrate>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImportUser {
+    login: String,
+    github_id: i32,
+    name: Option<String>,
+    email: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImportCrate {
+    /// The path of the `.crate` file, relative to the directory of the manifest
+    file: String,
+    /// The logins of the owners, the first one being the publisher
+    owners: Vec<String>,
+}
+
+pub fn run(opts: Opts) {
+    let conn = db::connect_now().unwrap();
+    let client = Client::new();
+
+    let manifest = fs::read(opts.dir.join("manifest.json")).expect("Missing manifest.json");
+    let manifest: ImportManifest =
+        serde_json::from_slice(&manifest).expect("Invalid manifest.json");
+
+    for user in &manifest.users {
+        create_user(&conn, user).unwrap();
+        println!("Created user {}", user.login);
+    }
+
+    let mut failures = 0;
+    for import in &manifest.crates {
+        match import_crate(&conn, &client, &opts, import) {
+            Ok(published) => println!("[{}] Published {}", import.file, published),
+            Err(e) => {
+                failures += 1;
+                println!("[{}] Failed: {}", import.file, e);
+            }
+        }
+    }
+
+    println!(
+        "Published {} of {} crates",
+        manifest.crates.len() - failures,
+        manifest.crates.len()
+    );
+    if failures > 0 {
+        process::exit(1);
+    }
+}
+
+/// Creates or updates a user, with a verified email address since it is required to publish.
+fn create_user(conn: &PgConnection, user: &ImportUser) -> QueryResult<()> {
+    conn.transaction(|| {
+        let new_user = NewUser::new(user.github_id, &user.login, user.name.as_deref(), None, "");
+        let created = new_user.create_or_update(None, conn)?;
+
+        diesel::insert_into(emails::table)
+            .values((
+                emails::user_id.eq(created.id),
+                emails::email.eq(&user.email),
+                emails::verified.eq(true),
+            ))
+            .on_conflict(emails::user_id)
+            .do_update()
+            .set((emails::email.eq(&user.email), emails::verified.eq(true)))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+fn import_crate(
+    conn: &PgConnection,
+    client: &Client,
+    opts: &Opts,
+    import: &ImportCrate,
+) -> Result<String, String> {
+    let tarball = fs::read(opts.dir.join(&import.file)).map_err(|e| e.to_string())?;
+    let new_crate = crate_upload(&tarball, opts.index_url.as_deref())?;
+    let published = format!("{}#{}", new_crate.name.0, new_crate.vers.0);
+
+    let mut owners = Vec::with_capacity(import.owners.len());
+    for login in &import.owners {
+        let user: User = users::table
+            .filter(users::gh_login.eq(login))
+            .filter(users::gh_id.ne(-1))
+            .first(conn)
+            .map_err(|_| format!("unknown owner `{}`", login))?;
+        owners.push(user);
+    }
+    let publisher = owners.first().ok_or("no owners")?;
+
+    // Publishing with a short-lived token goes through the same checks as `cargo publish`
+    let token = ApiToken::insert(conn, publisher.id, "import-crates").map_err(|e| e.to_string())?;
+    let response = publish(
+        client,
+        &opts.registry_url,
+        &token.plaintext,
+        &new_crate,
+        tarball,
+    );
+    diesel::update(api_tokens::table.find(token.model.id))
+        .set(api_tokens::revoked.eq(true))
+        .execute(conn)
+        .map_err(|e| e.to_string())?;
+    response?;
+
+    let krate: Crate = Crate::by_exact_name(&new_crate.name.0)
+        .first(conn)
+        .map_err(|e| e.to_string())?;
+    for owner in owners.iter().skip(1) {
+        add_owner(conn, &krate, publisher, owner).map_err(|e| e.to_string())?;
+    }
+
+    Ok(published)
+}
+
+/// Adds an owner directly, without the invitation which is needed when added by another owner.
+fn add_owner(
+    conn: &PgConnection,
+    krate: &Crate,
+    publisher: &User,
+    owner: &User,
+) -> QueryResult<()> {
+    diesel::insert_into(crate_owners::table)
+        .values(&CrateOwner {
+            crate_id: krate.id,
+            owner_id: owner.id,
+            created_by: publisher.id,
+            owner_kind: OwnerKind::User as i32,
+            email_notifications: true,
+        })
+        .on_conflict(crate_owners::table.primary_key())
+        .do_update()
+        .set(crate_owners::deleted.eq(false))
+        .execute(conn)?;
+    RegistryEvent::record_owner(
+        conn,
+        RegistryEventKind::OwnerAdd,
+        &krate.name,
+        &Owner::User(owner.clone()),
+    )
+}
+
+fn publish(
+    client: &Client,
+    registry_url: &str,
+    token: &str,
+    new_crate: &u::EncodableCrateUpload,
+    tarball: Vec<u8>,
+) -> Result<(), String> {
+    let json = serde_json::to_vec(new_crate).map_err(|e| e.to_string())?;
+    let mut body = Vec::with_capacity(json.len() + tarball.len() + 8);
+    body.extend(&(json.len() as u32).to_le_bytes());
+    body.extend(json);
+    body.extend(&(tarball.len() as u32).to_le_bytes());
+    body.extend(tarball);
+
+    let url = format!("{}/api/v1/crates/new", registry_url.trim_end_matches('/'));
+    let response: serde_json::Value = client
+        .put(&url)
+        .header(header::AUTHORIZATION, token)
+        .body(body)
+        .send()
+        .and_then(|response| response.json())
+        .map_err(|e| e.to_string())?;
+
+    match response.get("errors").and_then(|errors| errors.as_array()) {
+        Some(errors) => Err(errors
+            .iter()
+            .filter_map(|error| error["detail"].as_str())
+            .collect::<Vec<_>>()
+            .join(", ")),
+        None => Ok(()),
+    }
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    package: Package,
+    #[serde(default)]
+    dependencies: BTreeMap<String, TomlDependency>,
+    #[serde(default, rename = "dev-dependencies")]
+    dev_dependencies: BTreeMap<String, TomlDependency>,
+    #[serde(default, rename = "build-dependencies")]
+    build_dependencies: BTreeMap<String, TomlDependency>,
+    #[serde(default)]
+    target: BTreeMap<String, TargetDependencies>,
+    #[serde(default)]
+    features: HashMap<String, Vec<String>>,
+    badges: Option<HashMap<String, HashMap<String, String>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Package {
+    name: String,
+    version: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    description: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    readme: Option<toml::Value>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+    license: Option<String>,
+    license_file: Option<String>,
+    repository: Option<String>,
+    links: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TargetDependencies {
+    #[serde(default)]
+    dependencies: BTreeMap<String, TomlDependency>,
+    #[serde(default, rename = "dev-dependencies")]
+    dev_dependencies: BTreeMap<String, TomlDependency>,
+    #[serde(default, rename = "build-dependencies")]
+    build_dependencies: BTreeMap<String, TomlDependency>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TomlDependency {
+    Simple(String),
+    Detailed(DetailedDependency),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DetailedDependency {
+    version: Option<String>,
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default = "default_true", alias = "default_features")]
+    default_features: bool,
+    package: Option<String>,
+    registry: Option<String>,
+    registry_index: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Builds the metadata sent by `cargo publish` from the `Cargo.toml` file of a tarball.
+///
+/// `cargo package` replaces the registry names of the dependencies with the URLs of their
+/// index, which are sent as is, except for `index_url`, the registry being imported.
+fn crate_upload(
+    tarball: &[u8],
+    index_url: Option<&str>,
+) -> Result<u::EncodableCrateUpload, String> {
+    let (root, manifest) = find_file(tarball, |root, path| {
+        path == Path::new(root).join("Cargo.toml")
+    })?
+    .ok_or("the tarball doesn't contain a `Cargo.toml` file")?;
+    let manifest: Manifest =
+        toml::from_str(&manifest).map_err(|e| format!("invalid `Cargo.toml`: {}", e))?;
+    let package = manifest.package;
+
+    let readme_file = match &package.readme {
+        Some(toml::Value::String(readme)) => Some(readme.clone()),
+        Some(toml::Value::Boolean(false)) => None,
+        _ => Some(String::from("README.md")),
+    };
+    let readme = match &readme_file {
+        Some(readme_file) => find_file(tarball, |_, path| {
+            path == Path::new(&root).join(readme_file)
+        })?
+        .map(|(_, readme)| readme),
+        None => None,
+    };
+
+    let mut deps = Vec::new();
+    let mut add_deps = |table: &BTreeMap<String, TomlDependency>,
+                        kind: DependencyKind,
+                        target: Option<&String>|
+     -> Result<(), String> {
+        for (name, dep) in table {
+            deps.push(crate_dependency(name, dep, kind, target, index_url)?);
+        }
+        Ok(())
+    };
+    add_deps(&manifest.dependencies, DependencyKind::Normal, None)?;
+    add_deps(&manifest.dev_dependencies, DependencyKind::Dev, None)?;
+    add_deps(&manifest.build_dependencies, DependencyKind::Build, None)?;
+    for (target, table) in &manifest.target {
+        add_deps(&table.dependencies, DependencyKind::Normal, Some(target))?;
+        add_deps(&table.dev_dependencies, DependencyKind::Dev, Some(target))?;
+        add_deps(
+            &table.build_dependencies,
+            DependencyKind::Build,
+            Some(target),
+        )?;
+    }
+
+    let vers = semver::Version::parse(&package.version)
+        .map_err(|e| format!("invalid version `{}`: {}", package.version, e))?;
+    Ok(u::EncodableCrateUpload {
+        name: u::EncodableCrateName(package.name),
+        vers: u::EncodableCrateVersion(vers),
+        deps,
+        features: manifest
+            .features
+            .into_iter()
+            .map(|(name, values)| {
+                let values = values.into_iter().map(u::EncodableFeature).collect();
+                (u::EncodableFeatureName(name), values)
+            })
+            .collect(),
+        authors: package.authors,
+        description: package.description,
+        homepage: package.homepage,
+        documentation: package.documentation,
+        readme,
+        readme_file,
+        keywords: u::EncodableKeywordList(
+            package
+                .keywords
+                .into_iter()
+                .map(u::EncodableKeyword)
+                .collect(),
+        ),
+        categories: u::EncodableCategoryList(
+            package
+                .categories
+                .into_iter()
+                .map(u::EncodableCategory)
+                .collect(),
+        ),
+        license: package.license,
+        license_file: package.license_file,
+        repository: package.repository,
+        badges: manifest.badges,
+        links: package.links,
+    })
+}
+
+fn crate_dependency(
+    name: &str,
+    dep: &TomlDependency,
+    kind: DependencyKind,
+    target: Option<&String>,
+    index_url: Option<&str>,
+) -> Result<u::EncodableCrateDependency, String> {
+    let simple;
+    let dep = match dep {
+        TomlDependency::Simple(version) => {
+            simple = DetailedDependency {
+                version: Some(version.clone()),
+                features: Vec::new(),
+                optional: false,
+                default_features: true,
+                package: None,
+                registry: None,
+                registry_index: None,
+            };
+            &simple
+        }
+        TomlDependency::Detailed(dep) => dep,
+    };
+
+    let version = dep
+        .version
+        .as_ref()
+        .ok_or_else(|| format!("dependency `{}` has no version requirement", name))?;
+    let version_req = semver::VersionReq::parse(version)
+        .map_err(|e| format!("invalid version requirement of `{}`: {}", name, e))?;
+    let (name, explicit_name_in_toml) = match &dep.package {
+        Some(package) => (
+            package.clone(),
+            Some(u::EncodableCrateName(name.to_string())),
+        ),
+        None => (name.to_string(), None),
+    };
+    let registry = match (&dep.registry_index, &dep.registry) {
+        (Some(registry_index), _) => {
+            let imported = index_url.map_or(false, |index_url| {
+                index_url.trim_end_matches('/') == registry_index.trim_end_matches('/')
+            });
+            if imported {
+                None
+            } else {
+                Some(registry_index.clone())
+            }
+        }
+        (None, Some(registry)) => {
+            return Err(format!(
+                "dependency `{}` is on the registry `{}`, whose index URL is unknown",
+                name, registry
+            ))
+        }
+        (None, None) => None,
+    };
+
+    Ok(u::EncodableCrateDependency {
+        optional: dep.optional,
+        default_features: dep.default_features,
+        name: u::EncodableCrateName(name),
+        features: dep
+            .features
+            .iter()
+            .cloned()
+            .map(u::EncodableFeature)
+            .collect(),
+        version_req: u::EncodableCrateVersionReq(version_req),
+        target: target.cloned(),
+        kind: Some(kind),
+        explicit_name_in_toml,
+        registry,
+        artifact: None,
+        bindep_target: None,
+        lib: false,
+    })
+}
+
+/// Returns the name of the root directory of a tarball and the contents of the first
+/// file for which `matches` returns `true`.
+fn find_file(
+    tarball: &[u8],
+    matches: impl Fn(&str, &Path) -> bool,
+) -> Result<Option<(String, String)>, String> {
+    let mut archive = Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        let root = match path.components().next() {
+            Some(root) => root.as_os_str().to_string_lossy().into_owned(),
+            None => continue,
+        };
+        if matches(&root, &path) {
+            let mut contents = String::new();
+            entry
+                .read_to_string(&mut contents)
+                .map_err(|e| e.to_string())?;
+            return Ok(Some((root, contents)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+
+    const INDEX_URL: &str = "https://git.example.com/index";
+
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut tarball = Vec::new();
+        {
+            let mut ar = tar::Builder::new(GzEncoder::new(&mut tarball, Compression::default()));
+            for &(name, data) in files {
+                let mut header = tar::Header::new_gnu();
+                assert_ok!(header.set_path(name));
+                header.set_size(data.len() as u64);
+                header.set_cksum();
+                assert_ok!(ar.append(&header, data.as_bytes()));
+            }
+            assert_ok!(ar.finish());
+        }
+        tarball
+    }
+
+    fn upload(manifest: &str) -> Result<u::EncodableCrateUpload, String> {
+        let tarball = tarball(&[
+            ("foo-1.0.0/Cargo.toml", manifest),
+            ("foo-1.0.0/README.md", "# foo"),
+        ]);
+        crate_upload(&tarball, Some(INDEX_URL))
+    }
+
+    #[test]
+    fn packages_are_read_from_the_manifest() {
+        let new_crate = assert_ok!(upload(
+            r#"
+            [package]
+            name = "foo"
+            version = "1.0.0"
+            authors = ["foo <foo@example.com>"]
+            description = "A crate"
+            keywords = ["a", "b"]
+            license = "MIT"
+
+            [features]
+            default = ["std"]
+            std = []
+            "#
+        ));
+        assert_eq!(new_crate.name.0, "foo");
+        assert_eq!(new_crate.vers.0.to_string(), "1.0.0");
+        assert_eq!(new_crate.authors, vec!["foo <foo@example.com>"]);
+        assert_eq!(new_crate.description.as_deref(), Some("A crate"));
+        assert_eq!(new_crate.keywords.0.len(), 2);
+        assert_eq!(new_crate.license.as_deref(), Some("MIT"));
+        assert_eq!(new_crate.features.len(), 2);
+        assert_eq!(new_crate.readme.as_deref(), Some("# foo"));
+        assert_eq!(new_crate.readme_file.as_deref(), Some("README.md"));
+    }
+
+    #[test]
+    fn dependencies_are_read_from_the_manifest() {
+        let new_crate = assert_ok!(upload(&format!(
+            r#"
+            [package]
+            name = "foo"
+            version = "1.0.0"
+            readme = false
+
+            [dependencies]
+            serde = "1.0"
+            bar = {{ version = "0.1", registry-index = "{}/" }}
+            baz = {{ version = "0.2", registry-index = "https://other.example.com/index" }}
+
+            [dependencies.renamed]
+            version = "=2.0.0"
+            package = "qux"
+            optional = true
+            default-features = false
+            features = ["a"]
+
+            [target."cfg(unix)".dev-dependencies]
+            libc = "0.2"
+            "#,
+            INDEX_URL
+        )));
+        assert_eq!(new_crate.readme, None);
+        assert_eq!(new_crate.readme_file, None);
+
+        let deps = new_crate
+            .deps
+            .iter()
+            .map(|dep| (dep.name.0.as_str(), dep))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(deps.len(), 5);
+        assert_eq!(deps["serde"].registry, None);
+        // Dependencies on the imported registry become dependencies on this instance
+        assert_eq!(deps["bar"].registry, None);
+        assert_eq!(
+            deps["baz"].registry.as_deref(),
+            Some("https://other.example.com/index")
+        );
+
+        let renamed = deps["qux"];
+        assert_eq!(
+            renamed.explicit_name_in_toml.as_ref().map(|name| &*name.0),
+            Some("renamed")
+        );
+        assert_eq!(
+            renamed.version_req.0,
+            semver::VersionReq::parse("=2.0.0").unwrap()
+        );
+        assert!(renamed.optional);
+        assert!(!renamed.default_features);
+        assert_eq!(renamed.features.len(), 1);
+
+        assert!(matches!(deps["libc"].kind, Some(DependencyKind::Dev)));
+        assert_eq!(deps["libc"].target.as_deref(), Some("cfg(unix)"));
+    }
+
+    #[test]
+    fn registry_names_are_rejected() {
+        let error = upload(
+            r#"
+            [package]
+            name = "foo"
+            version = "1.0.0"
+
+            [dependencies]
+            bar = { version = "0.1", registry = "private" }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.contains("whose index URL is unknown"), "{}", error);
+    }
+
+    #[test]
+    fn tarballs_without_a_manifest_are_rejected() {
+        let tarball = tarball(&[("foo-1.0.0/src/lib.rs", "")]);
+        let error = crate_upload(&tarball, None).unwrap_err();
+        assert!(error.contains("`Cargo.toml`"), "{}", error);
+    }
+}
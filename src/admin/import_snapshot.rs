@@ -0,0 +1,163 @@
+use crate::{
+    admin::snapshot::{self, Snapshot},
+    db, git,
+    models::{dependency, Crate, NewCrate, Version},
+    schema::versions,
+    Config, Uploader,
+};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Clap;
+use diesel::prelude::*;
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+use swirl::Job;
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "import-snapshot",
+    about = "Import the crates of a snapshot written by `export-snapshot`.",
+    after_help = "Versions which already exist on this instance are skipped. Imported crates \
+        have no owners, use `transfer-crates` to assign them."
+)]
+pub struct Opts {
+    /// Directory to read the snapshot from
+    dir: PathBuf,
+}
+
+pub fn run(opts: Opts) {
+    let config = Config::default();
+    let conn = db::connect_now().unwrap();
+    let client = Client::new();
+
+    let (versions, crates) = import(&conn, &config.uploader, &client, &opts.dir);
+    println!("Imported {} versions of {} crates", versions, crates);
+}
+
+/// Imports the snapshot in the directory, and returns the numbers of imported versions and of
+/// crates in the snapshot.
+///
+/// The names and versions of the snapshot are part of the paths of the files it reads, so they
+/// are all validated before anything is imported.
+pub fn import(
+    conn: &PgConnection,
+    uploader: &Uploader,
+    client: &Client,
+    dir: &Path,
+) -> (usize, usize) {
+    let metadata = fs::read(snapshot::metadata_path(dir)).expect("Missing snapshot.json");
+    let snapshot: Snapshot = serde_json::from_slice(&metadata).expect("Invalid snapshot.json");
+
+    for snapshot_crate in &snapshot.crates {
+        let name = &snapshot_crate.name;
+        if !Crate::valid_name(name) {
+            panic!("[{}] Invalid crate name", name);
+        }
+        for snapshot_version in &snapshot_crate.versions {
+            if semver::Version::parse(&snapshot_version.num).is_err() {
+                panic!("[{}-{}] Invalid version", name, snapshot_version.num);
+            }
+        }
+    }
+
+    let mut imported = Vec::new();
+    for snapshot_crate in &snapshot.crates {
+        let name = &snapshot_crate.name;
+        let index = fs::read_to_string(snapshot::index_path(dir, name))
+            .unwrap_or_else(|_| panic!("[{}] Missing index file", name));
+        let entries = index
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<git::Crate>, _>>()
+            .unwrap_or_else(|e| panic!("[{}] Invalid index file: {}", name, e));
+        if let Some(entry) = entries.iter().find(|entry| entry.name != *name) {
+            panic!("[{}] Index entry of another crate: {}", name, entry.name);
+        }
+
+        let existing: Option<Crate> = Crate::by_name(name).first(conn).optional().unwrap();
+        let is_new = existing.is_none();
+        let krate = match existing {
+            Some(krate) => krate,
+            None => {
+                let new_crate = NewCrate {
+                    name,
+                    description: snapshot_crate.description.as_deref(),
+                    homepage: snapshot_crate.homepage.as_deref(),
+                    documentation: snapshot_crate.documentation.as_deref(),
+                    repository: snapshot_crate.repository.as_deref(),
+                    ..NewCrate::default()
+                };
+                diesel::insert_into(crate::schema::crates::table)
+                    .values(&new_crate)
+                    .execute(conn)
+                    .unwrap();
+                Crate::by_name(name).first(conn).unwrap()
+            }
+        };
+        let existing_versions: Vec<String> = Version::belonging_to(&krate)
+            .select(versions::num)
+            .load(conn)
+            .unwrap();
+
+        let mut new_entries = Vec::new();
+        for snapshot_version in &snapshot_crate.versions {
+            let num = &snapshot_version.num;
+            if existing_versions.contains(num) {
+                println!("[{}-{}] Already exists, skipping", name, num);
+                continue;
+            }
+            let entry = entries
+                .iter()
+                .find(|entry| entry.vers == *num)
+                .unwrap_or_else(|| panic!("[{}-{}] Missing index entry", name, num));
+
+            let body = fs::read(snapshot::crate_file_path(dir, name, num))
+                .unwrap_or_else(|_| panic!("[{}-{}] Missing crate file", name, num));
+            if hex::encode(Sha256::digest(&body)) != entry.cksum {
+                panic!("[{}-{}] Checksum mismatch", name, num);
+            }
+            let crate_size = body.len() as i32;
+            uploader
+                .upload_crate_file(client, &krate.name, num, body)
+                .unwrap_or_else(|e| panic!("[{}-{}] Couldn't upload crate file: {}", name, num, e));
+
+            conn.transaction::<_, diesel::result::Error, _>(|| {
+                let version = Version::insert_from_index_entry(conn, krate.id, entry)?;
+                diesel::update(&version)
+                    .set((
+                        versions::license.eq(&snapshot_version.license),
+                        versions::crate_size.eq(crate_size),
+                        versions::created_at.eq(snapshot_version.created_at),
+                    ))
+                    .execute(conn)?;
+                imported.push((version.id, entry.deps.clone()));
+                Ok(())
+            })
+            .unwrap();
+            new_entries.push(entry.clone());
+            println!("[{}-{}] Imported", name, num);
+        }
+
+        if is_new {
+            git::replace_index_entries(krate.name, new_entries)
+                .enqueue(conn)
+                .unwrap();
+        } else {
+            for entry in new_entries {
+                git::add_crate(entry).enqueue(conn).unwrap();
+            }
+        }
+    }
+
+    // Dependencies are recorded once all crates exist, since they can depend on each other
+    for (version_id, deps) in &imported {
+        dependency::add_index_dependencies(conn, deps, *version_id).unwrap();
+    }
+
+    (imported.len(), snapshot.crates.len())
+}
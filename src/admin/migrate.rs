@@ -0,0 +1,45 @@
+use crate::db;
+use crate::migrations::Phase;
+
+use clap::Clap;
+use diesel::migration::Migration;
+use diesel_migrations::{find_migrations_directory, mark_migrations_in_directory, run_migrations};
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "migrate",
+    about = "Run the pending database migrations of a deploy phase, \
+             see the `migrations` module."
+)]
+pub struct Opts {
+    /// `pre-deploy` in the release phase, `post-deploy` once the previous release stopped
+    #[clap(long, possible_values = &["pre-deploy", "post-deploy"])]
+    phase: Phase,
+}
+
+pub fn run(opts: Opts) {
+    let conn = db::connect_now().unwrap();
+    let migrations_dir = find_migrations_directory().expect("Could not find the migrations");
+    let pending = mark_migrations_in_directory(&conn, &migrations_dir)
+        .expect("Could not list the migrations")
+        .into_iter()
+        .filter(|(_, run)| !run)
+        .map(|(migration, _)| migration)
+        .collect::<Vec<_>>();
+
+    let (to_run, skipped): (Vec<_>, Vec<_>) = pending
+        .into_iter()
+        .partition(|migration| Phase::of(migration.version()) == opts.phase);
+    for migration in &skipped {
+        println!(
+            "Skipping migration {} of the other phase",
+            migration.version()
+        );
+    }
+    if to_run.is_empty() {
+        println!("No pending migrations");
+        return;
+    }
+
+    run_migrations(&conn, to_run, &mut std::io::stdout()).expect("Error running migrations");
+}
@@ -1,9 +1,18 @@
+pub mod add_advisory;
+pub mod audit_readmes;
 pub mod delete_crate;
 pub mod delete_version;
 pub mod dialoguer;
+pub mod export_snapshot;
+pub mod import_crates;
+pub mod import_snapshot;
+pub mod migrate;
 pub mod on_call;
 pub mod populate;
 pub mod render_readmes;
+pub mod repair_downloads;
+pub mod seed;
+pub mod snapshot;
 pub mod test_pagerduty;
 pub mod transfer_crates;
 pub mod verify_token;
@@ -1,11 +1,17 @@
 use crate::{
     db,
-    models::Version,
-    render::readme_to_html,
+    models::{RenderedReadme, Version},
+    render::{path_in_repository, readme_to_html_cached, RENDERER_VERSION},
     schema::{crates, readme_renderings, versions},
+    uploaders::TarballVcsInfo,
+    util::LimitErrorReader,
     Config,
 };
-use std::{io::Read, path::Path, thread};
+use std::{
+    io::{self, Read},
+    path::Path,
+    thread,
+};
 
 use chrono::{TimeZone, Utc};
 use clap::Clap;
@@ -112,7 +118,13 @@ pub fn run(opts: Opts) {
             let client = client.clone();
             let handle = thread::spawn(move || {
                 println!("[{}-{}] Rendering README...", krate_name, version.num);
-                let readme = get_readme(&config, &client, &version, &krate_name);
+                let conn = db::connect_now().unwrap_or_else(|_| {
+                    panic!(
+                        "[{}-{}] Couldn't connect to the database",
+                        krate_name, version.num
+                    )
+                });
+                let readme = get_readme(&config, &conn, &client, &version, &krate_name);
                 if readme.is_none() {
                     return;
                 }
@@ -147,11 +159,16 @@ pub fn run(opts: Opts) {
             }
         }
     }
+
+    let deleted = RenderedReadme::delete_outdated(&conn, RENDERER_VERSION)
+        .expect("error deleting the outdated rendered readmes");
+    println!("Deleted {} readmes cached by older renderers", deleted);
 }
 
 /// Renders the readme of an uploaded crate version.
 fn get_readme(
     config: &Config,
+    conn: &PgConnection,
     client: &Client,
     version: &Version,
     krate_name: &str,
@@ -181,96 +198,127 @@ fn get_readme(
         return None;
     }
 
-    let reader = GzDecoder::new(response);
-    let mut archive = Archive::new(reader);
-    let mut entries = archive.entries().unwrap_or_else(|_| {
-        panic!(
-            "[{}-{}] Invalid tar archive entries",
-            krate_name, version.num
-        )
-    });
-    let manifest: Manifest = {
-        let path = format!("{}-{}/Cargo.toml", krate_name, version.num);
-        let contents = find_file_by_path(&mut entries, Path::new(&path), version, krate_name);
-        toml::from_str(&contents).unwrap_or_else(|_| {
-            panic!(
-                "[{}-{}] Syntax error in manifest file",
-                krate_name, version.num
-            )
-        })
-    };
-
-    let rendered = {
-        let path = format!(
-            "{}-{}/{}",
-            krate_name, version.num, manifest.package.readme?
+    let mut tarball = Vec::new();
+    if let Err(err) =
+        LimitErrorReader::new(response, config.max_upload_size).read_to_end(&mut tarball)
+    {
+        println!(
+            "[{}-{}] Unable to read crate: {}",
+            krate_name, version.num, err
         );
-        let contents = find_file_by_path(&mut entries, Path::new(&path), version, krate_name);
-        readme_to_html(
-            &contents,
-            manifest
-                .package
-                .readme_file
-                .as_ref()
-                .map_or("README.md", |e| &**e),
-            manifest.package.repository.as_deref(),
-        )
+        return None;
+    }
+
+    let prefix = format!("{}-{}", krate_name, version.num);
+    let max_size = config.render.limits.max_size as u64;
+    let readme = match read_readme(&tarball, &prefix, max_size) {
+        Ok(Some(readme)) => readme,
+        Ok(None) => return None,
+        Err(err) => {
+            println!(
+                "[{}-{}] Invalid crate file: {}",
+                krate_name, version.num, err
+            );
+            return None;
+        }
     };
-    return Some(rendered);
 
-    #[derive(Deserialize)]
-    struct Package {
-        readme: Option<String>,
-        readme_file: Option<String>,
-        repository: Option<String>,
+    let rendered = readme_to_html_cached(
+        conn,
+        &readme.text,
+        &readme.file_name,
+        readme.repository.as_deref(),
+        None,
+        path_in_repository(readme.path_in_vcs.as_deref(), &readme.file_name).as_deref(),
+        Some(&config.render),
+    );
+    match rendered {
+        Ok(rendered) => Some(rendered),
+        Err(e) => {
+            println!("[{}-{}] {}", krate_name, version.num, e);
+            None
+        }
     }
+}
+
+/// The README declared by the manifest of a `.crate` file, as cargo reads it when publishing
+pub(crate) struct CrateReadme {
+    pub(crate) file_name: String,
+    pub(crate) text: String,
+    pub(crate) repository: Option<String>,
+    /// The directory of the package in the repository, see `TarballVcsInfo`
+    pub(crate) path_in_vcs: Option<String>,
+}
 
+/// Reads the README of a `.crate` file whose files are in the `prefix` directory, reading at
+/// most `max_size` bytes of each file.
+///
+/// Like cargo, `README.md` is used if the manifest doesn't declare a README. The files can come
+/// in any order, so the archive is read again for each of them.
+pub(crate) fn read_readme(
+    tarball: &[u8],
+    prefix: &str,
+    max_size: u64,
+) -> io::Result<Option<CrateReadme>> {
     #[derive(Deserialize)]
     struct Manifest {
         package: Package,
     }
+
+    #[derive(Deserialize)]
+    struct Package {
+        readme: Option<toml::Value>,
+        repository: Option<String>,
+    }
+
+    let read_file = |path: &str| {
+        let mut archive = Archive::new(GzDecoder::new(tarball));
+        let path = Path::new(prefix).join(path);
+        find_file_by_path(&mut archive.entries()?, &path, max_size)
+    };
+
+    let manifest = match read_file("Cargo.toml")? {
+        Some(manifest) => manifest,
+        None => return Ok(None),
+    };
+    let package = toml::from_str::<Manifest>(&manifest)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .package;
+    let file_name = match package.readme {
+        Some(toml::Value::String(file_name)) => file_name,
+        Some(toml::Value::Boolean(false)) => return Ok(None),
+        _ => "README.md".to_string(),
+    };
+    let file_name = file_name.trim_start_matches("./").to_string();
+    let text = match read_file(&file_name)? {
+        Some(text) => text,
+        None => return Ok(None),
+    };
+    let path_in_vcs = read_file(".cargo_vcs_info.json")?
+        .and_then(|contents| TarballVcsInfo::parse(contents.as_bytes()))
+        .and_then(|vcs_info| vcs_info.path_in_vcs);
+
+    Ok(Some(CrateReadme {
+        file_name,
+        text,
+        repository: package.repository,
+        path_in_vcs,
+    }))
 }
 
-/// Search an entry by its path in a Tar archive.
+/// Search an entry by its path in a Tar archive, reading at most `max_size` bytes of it.
 fn find_file_by_path<R: Read>(
     entries: &mut tar::Entries<'_, R>,
     path: &Path,
-    version: &Version,
-    krate_name: &str,
-) -> String {
-    let mut file = entries
-        .find(|entry| match *entry {
-            Err(_) => false,
-            Ok(ref file) => {
-                let filepath = match file.path() {
-                    Ok(p) => p,
-                    Err(_) => return false,
-                };
-                filepath == path
-            }
-        })
-        .unwrap_or_else(|| {
-            panic!(
-                "[{}-{}] couldn't open file: {}",
-                krate_name,
-                version.num,
-                path.display()
-            )
-        })
-        .unwrap_or_else(|_| {
-            panic!(
-                "[{}-{}] file is not present: {}",
-                krate_name,
-                version.num,
-                path.display()
-            )
-        });
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap_or_else(|_| {
-        panic!(
-            "[{}-{}] Couldn't read file contents",
-            krate_name, version.num
-        )
-    });
-    contents
+    max_size: u64,
+) -> io::Result<Option<String>> {
+    for entry in entries {
+        let entry = entry?;
+        if *entry.path()? == *path {
+            let mut contents = String::new();
+            LimitErrorReader::new(entry, max_size).read_to_string(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+    Ok(None)
 }
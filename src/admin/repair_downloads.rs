@@ -0,0 +1,172 @@
+use crate::{
+    db,
+    models::Crate,
+    schema::{crates, metadata, versions},
+};
+
+use clap::Clap;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{Array, Bool, Integer, Text};
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "repair-downloads",
+    about = "Recompute the download counts of versions and crates from their daily downloads.",
+    after_help = "The drift is only reported, unless `--fix` is passed. Versions without any \
+        daily download history are left alone, since their count can't be recomputed."
+)]
+pub struct Opts {
+    /// Names of the crates to check, all crates if none is given
+    crate_names: Vec<String>,
+    /// Store the recomputed download counts
+    #[clap(long)]
+    fix: bool,
+}
+
+/// Lists the versions whose download count differs from the sum of their counted daily
+/// downloads.
+const VERSION_DRIFT_QUERY: &str = r#"
+SELECT versions.id, crates.name AS crate_name, versions.num,
+    versions.downloads AS stored, history.downloads AS computed
+FROM versions
+INNER JOIN crates
+  ON crates.id = versions.crate_id
+INNER JOIN (
+    SELECT version_id, SUM(counted)::integer AS downloads
+    FROM version_downloads
+    GROUP BY version_id
+) history
+  ON history.version_id = versions.id
+WHERE versions.downloads <> history.downloads
+  AND ($1 OR crates.id = ANY($2))
+ORDER BY crates.name, versions.id
+"#;
+
+/// Lists the crates whose download count differs from the sum of the counts of their versions.
+const CRATE_DRIFT_QUERY: &str = r#"
+SELECT crates.id, crates.name, crates.downloads AS stored,
+    SUM(versions.downloads)::integer AS computed
+FROM crates
+INNER JOIN versions
+  ON versions.crate_id = crates.id
+WHERE $1 OR crates.id = ANY($2)
+GROUP BY crates.id
+HAVING crates.downloads <> SUM(versions.downloads)
+ORDER BY crates.name
+"#;
+
+#[derive(QueryableByName, Debug)]
+struct VersionDrift {
+    #[sql_type = "Integer"]
+    id: i32,
+    #[sql_type = "Text"]
+    crate_name: String,
+    #[sql_type = "Text"]
+    num: String,
+    #[sql_type = "Integer"]
+    stored: i32,
+    #[sql_type = "Integer"]
+    computed: i32,
+}
+
+#[derive(QueryableByName, Debug)]
+struct CrateDrift {
+    #[sql_type = "Integer"]
+    id: i32,
+    #[sql_type = "Text"]
+    name: String,
+    #[sql_type = "Integer"]
+    stored: i32,
+    #[sql_type = "Integer"]
+    computed: i32,
+}
+
+pub fn run(opts: Opts) {
+    let conn = db::connect_now().unwrap();
+
+    let crate_ids = opts
+        .crate_names
+        .iter()
+        .map(|name| {
+            Crate::by_name(name)
+                .select(crates::id)
+                .first(&conn)
+                .unwrap_or_else(|_| panic!("crate `{}` not found", name))
+        })
+        .collect::<Vec<i32>>();
+
+    // The counts are always repaired, so that the drift of the crates is reported as if the
+    // counts of their versions were fixed. The changes are rolled back without `--fix`.
+    let result = conn.transaction::<_, Error, _>(|| {
+        repair(&conn, &crate_ids)?;
+        if opts.fix {
+            Ok(())
+        } else {
+            Err(Error::RollbackTransaction)
+        }
+    });
+    match result {
+        Ok(()) => println!("stored the recomputed download counts"),
+        Err(Error::RollbackTransaction) => println!("nothing was changed, pass `--fix` to do so"),
+        Err(e) => panic!("failed to repair the download counts: {}", e),
+    }
+}
+
+/// Recomputes the download counts of the given crates, or of all crates if `crate_ids` is
+/// empty, and prints the ones that were wrong.
+///
+/// The count of a version is the sum of its counted daily downloads, the uncounted ones are
+/// added by the `update_downloads` job.
+fn repair(conn: &PgConnection, crate_ids: &[i32]) -> QueryResult<()> {
+    let all_crates = crate_ids.is_empty();
+
+    let versions = diesel::sql_query(VERSION_DRIFT_QUERY)
+        .bind::<Bool, _>(all_crates)
+        .bind::<Array<Integer>, _>(crate_ids)
+        .load::<VersionDrift>(conn)?;
+    for version in &versions {
+        println!(
+            "{}@{} ({}): {} downloads instead of {}",
+            version.crate_name, version.num, version.id, version.stored, version.computed
+        );
+        diesel::update(versions::table.find(version.id))
+            .set(versions::downloads.eq(version.computed))
+            .execute(conn)?;
+    }
+    println!("{} versions had a wrong download count", versions.len());
+
+    let crates = diesel::sql_query(CRATE_DRIFT_QUERY)
+        .bind::<Bool, _>(all_crates)
+        .bind::<Array<Integer>, _>(crate_ids)
+        .load::<CrateDrift>(conn)?;
+    for krate in &crates {
+        println!(
+            "{} ({}): {} downloads instead of {}",
+            krate.name, krate.id, krate.stored, krate.computed
+        );
+        diesel::update(crates::table.find(krate.id))
+            .set(crates::downloads.eq(krate.computed))
+            .execute(conn)?;
+    }
+    println!("{} crates had a wrong download count", crates.len());
+
+    let total = crates::table
+        .select(diesel::dsl::sum(crates::downloads))
+        .first::<Option<i64>>(conn)?
+        .unwrap_or(0);
+    let stored_total = metadata::table
+        .select(metadata::total_downloads)
+        .first::<i64>(conn)?;
+    if stored_total != total {
+        println!(
+            "the total is {} downloads instead of {}",
+            stored_total, total
+        );
+        diesel::update(metadata::table)
+            .set(metadata::total_downloads.eq(total))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use crate::{
+    db,
+    models::{Crate, Keyword, NewCrate, NewUser, NewVersion, User},
+    schema::{crates, dependencies, users, version_downloads, versions},
+    tasks,
+    util::errors::AppResult,
+};
+
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Clap;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use swirl::Job;
+
+const SEED_USER: &str = "crates-io-seed";
+
+const KEYWORDS: &[&str] = &[
+    "async", "cli", "database", "encoding", "embedded", "ffi", "game", "gui", "http", "json",
+    "logging", "macro", "math", "network", "parser", "serde", "testing", "text", "wasm", "web",
+];
+
+#[derive(Clap, Debug)]
+#[clap(
+    name = "seed",
+    about = "Generate crates with versions, dependencies, keywords and download histories, \
+             for staging environments and benchmarks.",
+    after_help = "The crates are only added to the database, not to the index. Their downloads \
+                  are counted by the update_downloads job enqueued at the end."
+)]
+pub struct Opts {
+    /// Number of crates to generate
+    #[clap(long, default_value = "100")]
+    crates: usize,
+
+    /// Prefix of the names of the generated crates
+    #[clap(long, default_value = "seed")]
+    prefix: String,
+
+    /// Seed of the random generator, to generate the same data again
+    #[clap(long)]
+    seed: Option<u64>,
+}
+
+pub fn run(opts: Opts) {
+    let conn = db::connect_now().unwrap();
+    let mut rng = match opts.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let owner = seed_user(&conn).unwrap();
+    let mut created: Vec<i32> = Vec::new();
+    for index in 0..opts.crates {
+        let name = format!("{}-{}", opts.prefix, index);
+        let krate = conn
+            .transaction(|| seed_crate(&conn, &mut rng, &name, owner.id, index, &created))
+            .unwrap_or_else(|e| panic!("Unable to create crate {}: {}", name, e));
+        match krate {
+            Some(krate) => created.push(krate.id),
+            None => println!("Skipping {}, which already exists", name),
+        }
+
+        if (index + 1) % 100 == 0 {
+            println!("Created {} of {} crates", index + 1, opts.crates);
+        }
+    }
+    println!("Created {} crates", created.len());
+
+    tasks::update_downloads().enqueue(&conn).unwrap();
+    tasks::reconcile_dependents_counts().enqueue(&conn).unwrap();
+}
+
+/// Returns the user owning the generated crates, creating it the first time.
+fn seed_user(conn: &PgConnection) -> QueryResult<User> {
+    let user = users::table
+        .filter(users::gh_login.eq(SEED_USER))
+        .first(conn)
+        .optional()?;
+    match user {
+        Some(user) => Ok(user),
+        None => NewUser {
+            gh_login: SEED_USER,
+            ..NewUser::default()
+        }
+        .create_or_update(None, conn),
+    }
+}
+
+/// Creates a crate and its versions, returning `None` if a crate with the same name exists.
+///
+/// The popularity of the crates decreases with their index, like on crates.io where a few crates
+/// get most of the downloads and are the dependencies of most of the other crates.
+fn seed_crate(
+    conn: &PgConnection,
+    rng: &mut StdRng,
+    name: &str,
+    owner_id: i32,
+    index: usize,
+    created: &[i32],
+) -> AppResult<Option<Crate>> {
+    let already_exists =
+        diesel::select(exists(crates::table.filter(crates::name.eq(name)))).get_result(conn)?;
+    if already_exists {
+        return Ok(None);
+    }
+
+    let description = format!("A generated crate for load testing, number {}", index);
+    let repository = format!("https://github.com/example/{}", name);
+    let krate = NewCrate {
+        name,
+        description: Some(&description),
+        repository: Some(&repository),
+        ..NewCrate::default()
+    }
+    .create_or_update(conn, owner_id, None)?;
+
+    let keyword_count = rng.gen_range(0, 6);
+    let keywords = KEYWORDS
+        .choose_multiple(rng, keyword_count)
+        .copied()
+        .collect::<Vec<_>>();
+    Keyword::update_crate(conn, &krate, &keywords)?;
+
+    // Most crates have a few versions, and some have a lot
+    let version_count = 1 + (rng.gen::<f64>().powi(3) * 60.0) as usize;
+    let popularity = 100_000.0 / (index as f64 + 1.0);
+    let today = Utc::today().naive_utc();
+    let mut num = semver::Version::new(0, 1, 0);
+    for position in 0..version_count {
+        if position > 0 {
+            match rng.gen_range(0, 20) {
+                0 => num.increment_major(),
+                1..=5 => num.increment_minor(),
+                _ => num.increment_patch(),
+            }
+        }
+
+        let version = NewVersion::new(
+            krate.id,
+            &num,
+            &HashMap::new(),
+            Some("MIT OR Apache-2.0".to_string()),
+            None,
+            rng.gen_range(1_000, 500_000),
+            owner_id,
+        )?
+        .save(conn, &[], "seed@example.com")?;
+
+        // Versions are published a few days apart, the last one today
+        let age = (version_count - position - 1) as i64 * rng.gen_range(1, 30);
+        diesel::update(&version)
+            .set(versions::created_at.eq((today - Duration::days(age)).and_hms(12, 0, 0)))
+            .execute(conn)?;
+
+        seed_dependencies(conn, rng, version.id, created)?;
+
+        // The latest versions get most of the downloads
+        let share = 0.5f64.powi((version_count - position - 1) as i32);
+        seed_downloads(conn, rng, version.id, today, age, popularity * share)?;
+    }
+
+    Ok(Some(krate))
+}
+
+/// Adds dependencies on crates created before, favoring the most popular ones.
+fn seed_dependencies(
+    conn: &PgConnection,
+    rng: &mut StdRng,
+    version_id: i32,
+    created: &[i32],
+) -> QueryResult<()> {
+    let count = rng.gen_range(0, 8).min(created.len());
+    let mut crate_ids = (0..count)
+        .map(|_| created[(rng.gen::<f64>().powi(2) * created.len() as f64) as usize])
+        .collect::<Vec<_>>();
+    crate_ids.sort_unstable();
+    crate_ids.dedup();
+
+    let new_deps = crate_ids
+        .into_iter()
+        .map(|crate_id| {
+            (
+                dependencies::version_id.eq(version_id),
+                dependencies::crate_id.eq(crate_id),
+                dependencies::req.eq("^0.1"),
+                dependencies::optional.eq(false),
+                dependencies::default_features.eq(true),
+                dependencies::features.eq(Vec::<String>::new()),
+            )
+        })
+        .collect::<Vec<_>>();
+    diesel::insert_into(dependencies::table)
+        .values(&new_deps)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Adds the downloads of the last 90 days since the version was published, around an average
+/// number of downloads per day.
+fn seed_downloads(
+    conn: &PgConnection,
+    rng: &mut StdRng,
+    version_id: i32,
+    today: NaiveDate,
+    age: i64,
+    average: f64,
+) -> QueryResult<()> {
+    let downloads = (0..=age.min(89))
+        .map(|day| {
+            let count = (average * rng.gen_range(0.5, 1.5)) as i32;
+            (
+                version_downloads::version_id.eq(version_id),
+                version_downloads::downloads.eq(count),
+                version_downloads::date.eq(today - Duration::days(day)),
+            )
+        })
+        .collect::<Vec<_>>();
+    diesel::insert_into(version_downloads::table)
+        .values(&downloads)
+        .execute(conn)?;
+    Ok(())
+}
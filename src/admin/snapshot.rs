@@ -0,0 +1,50 @@
+//! The format of the snapshots written by `export-snapshot` and read by `import-snapshot`
+//!
+//! A snapshot is a directory with the following layout:
+//!
+//! - `snapshot.json`: the metadata of the crates, which isn't part of the index
+//! - `index/`: the index files of the crates, using the same paths as the index
+//! - `crates/<name>/<name>-<version>.crate`: the `.crate` files of all versions
+
+use chrono::NaiveDateTime;
+use std::path::{Path, PathBuf};
+
+use crate::git;
+use crate::util::rfc3339;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Snapshot {
+    pub crates: Vec<SnapshotCrate>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SnapshotCrate {
+    pub name: String,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub documentation: Option<String>,
+    pub repository: Option<String>,
+    pub versions: Vec<SnapshotVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SnapshotVersion {
+    pub num: String,
+    pub license: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+pub fn metadata_path(dir: &Path) -> PathBuf {
+    dir.join("snapshot.json")
+}
+
+pub fn index_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join("index").join(git::relative_index_file(name))
+}
+
+pub fn crate_file_path(dir: &Path, name: &str, version: &str) -> PathBuf {
+    dir.join("crates")
+        .join(name)
+        .join(format!("{}-{}.crate", name, version))
+}
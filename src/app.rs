@@ -1,10 +1,17 @@
 //! Application-wide components in a struct accessible from each request
 
-use crate::{db, Config, Env};
+use crate::auth_provider::{AuthProvider, GitHubProvider, OidcProvider};
+use crate::cache::{self, Cache};
+use crate::cpu_pool::CpuPool;
+use crate::feature_flags::{FeatureFlag, FeatureFlags};
+use crate::http_client::HttpClient;
+use crate::license_expressions::LicenseExpressions;
+use crate::search_backend::{self, SearchBackend};
+use crate::upstream::UpstreamLookups;
+use crate::{db, Config};
 use std::{sync::Arc, time::Duration};
 
 use diesel::r2d2;
-use oauth2::basic::BasicClient;
 use reqwest::blocking::Client;
 use scheduled_thread_pool::ScheduledThreadPool;
 
@@ -19,8 +26,8 @@ pub struct App {
     /// The read-only replica database connection pool
     pub read_only_replica_database: Option<db::DieselPool>,
 
-    /// The GitHub OAuth2 configuration
-    pub github: BasicClient,
+    /// The provider users log in with, GitHub OAuth unless configured otherwise
+    pub auth: Box<dyn AuthProvider>,
 
     /// A unique key used with conduit_cookie to generate cookies
     pub session_key: String,
@@ -28,12 +35,33 @@ pub struct App {
     /// The server configuration
     pub config: Config,
 
+    /// The cached runtime feature flags
+    pub feature_flags: FeatureFlags,
+
+    /// The cached license expressions of the versions, for the searches filtered by license
+    pub license_expressions: LicenseExpressions,
+
+    /// The lookups in the upstream registry made while serving requests, if one is configured
+    pub upstream_lookups: UpstreamLookups,
+
+    /// The threads verifying uploaded tarballs
+    pub cpu_pool: CpuPool,
+
+    /// The cache of the hot read endpoints, if enabled
+    pub cache: Option<Arc<dyn Cache>>,
+
+    /// The backend the text of searches is matched with, Postgres unless configured otherwise
+    pub search_backend: Arc<dyn SearchBackend>,
+
     /// A configured client for outgoing HTTP requests
     ///
     /// In production this shares a single connection pool across requests.  In tests
     /// this is either None (in which case any attempt to create an outgoing connection
     /// will panic) or a `Client` configured with a per-test replay proxy.
     pub(crate) http_client: Option<Client>,
+
+    /// The client for requests to external APIs, `http_client` unless replaced in tests
+    api_client: Option<Box<dyn HttpClient>>,
 }
 
 impl App {
@@ -41,60 +69,30 @@ impl App {
     ///
     /// Configures and sets up:
     ///
-    /// - GitHub OAuth
+    /// - The authentication provider, GitHub OAuth by default
     /// - Database connection pools
+    /// - The pool of threads for CPU-heavy work
+    /// - The cache of the hot read endpoints
+    /// - The backend of searches
     /// - A `git2::Repository` instance from the index repo checkout (that server.rs ensures exists)
     pub fn new(config: Config, http_client: Option<Client>) -> App {
-        use oauth2::{AuthUrl, ClientId, ClientSecret, TokenUrl};
-
-        let github = BasicClient::new(
-            ClientId::new(config.gh_client_id.clone()),
-            Some(ClientSecret::new(config.gh_client_secret.clone())),
-            AuthUrl::new(String::from("https://github.com/login/oauth/authorize")).unwrap(),
-            Some(
-                TokenUrl::new(String::from("https://github.com/login/oauth/access_token")).unwrap(),
-            ),
-        );
-
-        let db_pool_size = match (dotenv::var("DB_POOL_SIZE"), config.env) {
-            (Ok(num), _) => num.parse().expect("couldn't parse DB_POOL_SIZE"),
-            (_, Env::Production) => 10,
-            _ => 3,
-        };
-
-        let db_min_idle = match (dotenv::var("DB_MIN_IDLE"), config.env) {
-            (Ok(num), _) => Some(num.parse().expect("couldn't parse DB_MIN_IDLE")),
-            (_, Env::Production) => Some(5),
-            _ => None,
-        };
-
-        let db_helper_threads = match (dotenv::var("DB_HELPER_THREADS"), config.env) {
-            (Ok(num), _) => num.parse().expect("couldn't parse DB_HELPER_THREADS"),
-            (_, Env::Production) => 3,
-            _ => 1,
-        };
-
-        // Used as the connection and statement timeout value for the database pool(s)
-        let db_connection_timeout = match (dotenv::var("DB_TIMEOUT"), config.env) {
-            (Ok(num), _) => num.parse().expect("couldn't parse DB_TIMEOUT"),
-            (_, Env::Production) => 10,
-            (_, Env::Test) => 1,
-            _ => 30,
+        let auth: Box<dyn AuthProvider> = match &config.oidc {
+            Some(oidc) => Box::new(OidcProvider::new(oidc.clone())),
+            None => Box::new(GitHubProvider::new(&config)),
         };
 
-        // Determine if the primary pool is also read-only
-        let read_only_mode = dotenv::var("READ_ONLY_MODE").is_ok();
+        let db_pool = config.db_pool;
         let primary_db_connection_config = db::ConnectionConfig {
-            statement_timeout: db_connection_timeout,
-            read_only: read_only_mode,
+            statement_timeout: db_pool.connection_timeout,
+            read_only: db_pool.read_only_mode,
         };
 
-        let thread_pool = Arc::new(ScheduledThreadPool::new(db_helper_threads));
+        let thread_pool = Arc::new(ScheduledThreadPool::new(db_pool.helper_threads));
 
         let primary_db_config = r2d2::Pool::builder()
-            .max_size(db_pool_size)
-            .min_idle(db_min_idle)
-            .connection_timeout(Duration::from_secs(db_connection_timeout))
+            .max_size(db_pool.pool_size)
+            .min_idle(db_pool.min_idle)
+            .connection_timeout(Duration::from_secs(db_pool.connection_timeout))
             .connection_customizer(Box::new(primary_db_connection_config))
             .thread_pool(thread_pool.clone());
 
@@ -102,14 +100,14 @@ impl App {
 
         let read_only_replica_database = if let Some(url) = &config.replica_db_url {
             let replica_db_connection_config = db::ConnectionConfig {
-                statement_timeout: db_connection_timeout,
+                statement_timeout: db_pool.connection_timeout,
                 read_only: true,
             };
 
             let replica_db_config = r2d2::Pool::builder()
-                .max_size(db_pool_size)
-                .min_idle(db_min_idle)
-                .connection_timeout(Duration::from_secs(db_connection_timeout))
+                .max_size(db_pool.pool_size)
+                .min_idle(db_pool.min_idle)
+                .connection_timeout(Duration::from_secs(db_pool.connection_timeout))
                 .connection_customizer(Box::new(replica_db_connection_config))
                 .thread_pool(thread_pool);
 
@@ -118,16 +116,38 @@ impl App {
             None
         };
 
+        let cpu_pool = CpuPool::new(config.cpu_pool);
+        let cache = cache::from_config(&config.cache.backend);
+        let search_backend = search_backend::from_config(&config.search_backend);
+
         App {
             primary_database,
             read_only_replica_database,
-            github,
+            auth,
             session_key: config.session_key.clone(),
             config,
+            feature_flags: FeatureFlags::default(),
+            license_expressions: LicenseExpressions::default(),
+            upstream_lookups: UpstreamLookups::default(),
+            cpu_pool,
+            cache,
+            search_backend,
             http_client,
+            api_client: None,
         }
     }
 
+    /// Replaces the client used for requests to external APIs, e.g. with a `Replayer` in tests.
+    pub fn with_api_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.api_client = Some(Box::new(client));
+        self
+    }
+
+    /// Returns whether a runtime feature flag is enabled, see the `feature_flags` module.
+    pub fn is_feature_enabled(&self, flag: FeatureFlag) -> bool {
+        self.feature_flags.is_enabled(&self.primary_database, flag)
+    }
+
     /// Returns a client for making HTTP requests to upload crate files.
     ///
     /// The client will go through a proxy if the application was configured via
@@ -142,4 +162,17 @@ impl App {
             .as_ref()
             .expect("No HTTP client is configured.  In tests, use `TestApp::with_proxy()`.")
     }
+
+    /// Returns the client for requests to external APIs like GitHub.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the application was neither initialized with an HTTP client nor given an API
+    /// client.
+    pub fn api_client(&self) -> &dyn HttpClient {
+        match &self.api_client {
+            Some(client) => client.as_ref(),
+            None => self.http_client(),
+        }
+    }
 }
@@ -0,0 +1,100 @@
+//! Pluggable authentication of users
+//!
+//! By default users log in with GitHub OAuth and teams are GitHub teams. Private instances can
+//! instead authenticate users with an OpenID Connect provider, by setting the `OIDC_*`
+//! environment variables read by [`OidcConfig::from_settings`](struct.OidcConfig.html).
+//! LDAP and SAML directories can be used through an OpenID Connect bridge such as Dex or
+//! Keycloak.
+
+use std::error::Error;
+use std::fmt;
+
+use diesel::PgConnection;
+use oauth2::{AuthorizationCode, CsrfToken};
+use url::Url;
+
+use crate::app::App;
+use crate::http_client::HttpRequest;
+use crate::models::{Team, User};
+use crate::util::errors::AppResult;
+
+mod github;
+mod oidc;
+
+pub use self::github::GitHubProvider;
+pub use self::oidc::{OidcConfig, OidcProvider};
+
+/// A service users log in with, which also decides who belongs to the teams that can own crates.
+pub trait AuthProvider: Send + Sync {
+    /// The name of the provider, which is also the first part of the logins of its teams.
+    fn name(&self) -> &'static str;
+
+    /// The format of the logins of teams, shown when an invalid team is added as an owner.
+    fn team_login_format(&self) -> &'static str;
+
+    /// Returns the URL users are sent to for logging in, along with the state parameter which
+    /// is passed back to `authenticate`.
+    fn authorize_url(&self) -> (Url, CsrfToken);
+
+    /// Exchanges the code received once the user logged in for their identity, and creates or
+    /// updates the matching user.
+    fn authenticate(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        code: AuthorizationCode,
+    ) -> AppResult<User>;
+
+    /// Creates or updates the team with the given login, which starts with the name of the
+    /// provider and is stored in lowercase. Only members of a team can add it as an owner.
+    fn create_or_update_team(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        login: &str,
+        req_user: &User,
+    ) -> AppResult<Team>;
+
+    /// Checks whether the user is a member of a team created by this provider.
+    fn team_contains_user(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        team: &Team,
+        user: &User,
+    ) -> AppResult<bool>;
+}
+
+/// Sends the requests of the OAuth client through the API client of the app, so that they can
+/// be replayed in tests like the other requests to the provider.
+fn oauth_request(
+    app: &App,
+    request: oauth2::HttpRequest,
+) -> Result<oauth2::HttpResponse, OAuthRequestError> {
+    let response = app
+        .api_client()
+        .send(HttpRequest {
+            method: request.method,
+            url: request.url.into_string(),
+            headers: request.headers,
+            body: request.body,
+        })
+        .map_err(|e| OAuthRequestError(e.to_string()))?;
+
+    Ok(oauth2::HttpResponse {
+        status_code: response.status,
+        headers: response.headers,
+        body: response.body,
+    })
+}
+
+#[derive(Debug)]
+struct OAuthRequestError(String);
+
+impl Error for OAuthRequestError {}
+
+impl fmt::Display for OAuthRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
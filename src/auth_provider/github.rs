@@ -0,0 +1,271 @@
+use diesel::prelude::*;
+use failure::Fail;
+use oauth2::basic::BasicClient;
+use oauth2::{
+    AccessToken, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, Scope,
+    TokenResponse, TokenUrl,
+};
+use url::Url;
+
+use super::{oauth_request, AuthProvider};
+use crate::app::App;
+use crate::github::github_api;
+use crate::models::{NewTeam, NewUser, Team, User};
+use crate::schema::users;
+use crate::util::errors::{
+    cargo_err, server_error, AppError, AppResult, ChainError, NotFound, ReadOnlyMode,
+};
+use crate::Config;
+
+/// Authentication with GitHub OAuth, where teams are GitHub teams named `github:org:team`.
+pub struct GitHubProvider {
+    client: BasicClient,
+}
+
+impl GitHubProvider {
+    pub fn new(config: &Config) -> Self {
+        let client = BasicClient::new(
+            ClientId::new(config.gh_client_id.clone()),
+            Some(ClientSecret::new(config.gh_client_secret.clone())),
+            AuthUrl::new(String::from("https://github.com/login/oauth/authorize")).unwrap(),
+            Some(
+                TokenUrl::new(String::from("https://github.com/login/oauth/access_token")).unwrap(),
+            ),
+        );
+        GitHubProvider { client }
+    }
+}
+
+impl AuthProvider for GitHubProvider {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn team_login_format(&self) -> &'static str {
+        "github:org:team"
+    }
+
+    fn authorize_url(&self) -> (Url, CsrfToken) {
+        self.client
+            .authorize_url(CsrfToken::new_random)
+            .add_scope(Scope::new("read:org".to_string()))
+            .url()
+    }
+
+    fn authenticate(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        code: AuthorizationCode,
+    ) -> AppResult<User> {
+        // Fetch the access token from GitHub using the code we just got
+        let token = self
+            .client
+            .exchange_code(code)
+            .request(|request| oauth_request(app, request))
+            .map_err(|e| e.compat())
+            .chain_error(|| server_error("Error obtaining token"))?;
+        let token = token.access_token();
+
+        // Fetch the user info from GitHub using the access token we just got and create a user record
+        let ghuser = github_api::<GithubUser>(app, "/user", token)?;
+        ghuser.save_to_database(&token.secret(), conn)
+    }
+
+    fn create_or_update_team(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        login: &str,
+        req_user: &User,
+    ) -> AppResult<Team> {
+        // github:rust-lang:owners
+        let mut chunks = login.split(':').skip(1);
+        // unwrap is okay, the caller checked that the login contains a `:`
+        let org_name = chunks.next().unwrap();
+        let team_name = chunks.next().ok_or_else(|| {
+            cargo_err(
+                "missing github team argument; \
+                 format is github:org:team",
+            )
+        })?;
+
+        // GET orgs/:org/teams
+        // check that `team` is the `slug` in results, and grab its data
+
+        // "sanitization"
+        fn is_allowed_char(c: char) -> bool {
+            matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_')
+        }
+
+        if let Some(c) = org_name.chars().find(|c| !is_allowed_char(*c)) {
+            return Err(cargo_err(&format_args!(
+                "organization cannot contain special \
+                 characters like {}",
+                c
+            )));
+        }
+
+        #[derive(Deserialize)]
+        struct GithubOrganization {
+            id: i32, // unique GH id (needed for membership queries)
+        }
+
+        #[derive(Deserialize)]
+        struct GithubTeam {
+            id: i32,              // unique GH id (needed for membership queries)
+            name: Option<String>, // Pretty name
+            organization: GithubOrganization,
+        }
+
+        let url = format!("/orgs/{}/teams/{}", org_name, team_name);
+        let token = AccessToken::new(req_user.gh_access_token.clone());
+        let team = github_api::<GithubTeam>(app, &url, &token).map_err(|_| {
+            cargo_err(&format_args!(
+                "could not find the github team {}/{}",
+                org_name, team_name
+            ))
+        })?;
+
+        let org_id = team.organization.id;
+
+        if !team_with_gh_id_contains_user(app, org_id, team.id, req_user)? {
+            return Err(cargo_err("only members of a team can add it as an owner"));
+        }
+
+        #[derive(Deserialize)]
+        struct Org {
+            avatar_url: Option<String>,
+        }
+
+        let url = format!("/orgs/{}", org_name);
+        let org = github_api::<Org>(app, &url, &token)?;
+
+        NewTeam::new(
+            &login.to_lowercase(),
+            org_id,
+            team.id,
+            team.name,
+            org.avatar_url,
+        )
+        .create_or_update(conn)
+        .map_err(Into::into)
+    }
+
+    /// Phones home to Github to ask if this User is a member of the given team.
+    /// Note that we're assuming that the given user is the one interested in
+    /// the answer. If this is not the case, then we could accidentally leak
+    /// private membership information here.
+    fn team_contains_user(
+        &self,
+        app: &App,
+        _conn: &PgConnection,
+        team: &Team,
+        user: &User,
+    ) -> AppResult<bool> {
+        match team.org_id {
+            Some(org_id) => team_with_gh_id_contains_user(app, org_id, team.github_id, user),
+            // This means we don't have an org_id on file for the `self` team. It much
+            // probably was deleted from github by the time we backfilled the database.
+            // Short-circuiting to false since a non-existent team cannot contain any
+            // user
+            None => Ok(false),
+        }
+    }
+}
+
+fn team_with_gh_id_contains_user(
+    app: &App,
+    github_org_id: i32,
+    github_team_id: i32,
+    user: &User,
+) -> AppResult<bool> {
+    // GET /organizations/:org_id/team/:team_id/memberships/:username
+    // check that "state": "active"
+
+    #[derive(Deserialize)]
+    struct Membership {
+        state: String,
+    }
+
+    let url = format!(
+        "/organizations/{}/team/{}/memberships/{}",
+        &github_org_id, &github_team_id, &user.gh_login
+    );
+    let token = AccessToken::new(user.gh_access_token.clone());
+    let membership = match github_api::<Membership>(app, &url, &token) {
+        // Officially how `false` is returned
+        Err(ref e) if e.is::<NotFound>() => return Ok(false),
+        x => x?,
+    };
+
+    // There is also `state: pending` for which we could possibly give
+    // some feedback, but it's not obvious how that should work.
+    Ok(membership.state == "active")
+}
+
+#[derive(Deserialize)]
+struct GithubUser {
+    email: Option<String>,
+    name: Option<String>,
+    login: String,
+    id: i32,
+    avatar_url: Option<String>,
+}
+
+impl GithubUser {
+    fn save_to_database(&self, access_token: &str, conn: &PgConnection) -> AppResult<User> {
+        NewUser::new(
+            self.id,
+            &self.login,
+            self.name.as_deref(),
+            self.avatar_url.as_deref(),
+            access_token,
+        )
+        .create_or_update(self.email.as_deref(), conn)
+        .map_err(Into::into)
+        .or_else(|e: Box<dyn AppError>| {
+            // If we're in read only mode, we can't update their details
+            // just look for an existing user
+            if e.is::<ReadOnlyMode>() {
+                users::table
+                    .filter(users::gh_id.eq(self.id))
+                    .first(conn)
+                    .optional()?
+                    .ok_or(e)
+            } else {
+                Err(e)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pg_connection() -> PgConnection {
+        let database_url =
+            dotenv::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set to run tests");
+        PgConnection::establish(&database_url).unwrap()
+    }
+
+    #[test]
+    fn gh_user_with_invalid_email_doesnt_fail() {
+        let conn = pg_connection();
+        let gh_user = GithubUser {
+            email: Some("String.Format(\"{0}.{1}@live.com\", FirstName, LastName)".into()),
+            name: Some("My Name".into()),
+            login: "github_user".into(),
+            id: -1,
+            avatar_url: None,
+        };
+        let result = gh_user.save_to_database("arbitrary_token", &conn);
+
+        assert!(
+            result.is_ok(),
+            "Creating a User from a GitHub user failed when it shouldn't have, {:?}",
+            result
+        );
+    }
+}
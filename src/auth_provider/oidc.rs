@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+
+use diesel::dsl::{exists, now};
+use diesel::prelude::*;
+use failure::Fail;
+use http::header;
+use oauth2::basic::BasicClient;
+use oauth2::{
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
+    TokenResponse, TokenUrl,
+};
+use url::Url;
+
+use super::{oauth_request, AuthProvider};
+use crate::app::App;
+use crate::config::{self, Settings};
+use crate::http_client::HttpRequest;
+use crate::models::{NewUser, Team, User};
+use crate::schema::{teams, user_identities, users};
+use crate::util::errors::{cargo_err, server_error, AppError, AppResult, ChainError, ReadOnlyMode};
+
+/// The settings of an OpenID Connect provider.
+#[derive(Clone, Debug)]
+pub struct OidcConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    /// The URL of the frontend page users are sent back to after logging in
+    pub redirect_url: String,
+    pub scopes: Vec<String>,
+    /// The claim of the user info listing the groups of the user
+    pub groups_claim: String,
+}
+
+impl OidcConfig {
+    /// Reads the settings, if `OIDC_CLIENT_ID` is set.
+    ///
+    /// - `OIDC_CLIENT_ID` and `OIDC_CLIENT_SECRET`: The credentials of the registered client.
+    /// - `OIDC_AUTHORIZE_URL`, `OIDC_TOKEN_URL` and `OIDC_USERINFO_URL`: The endpoints of the
+    ///    provider, as listed in its `.well-known/openid-configuration` document.
+    /// - `OIDC_REDIRECT_URL`: Defaults to `https://<DOMAIN_NAME>/authorize/github`, the
+    ///    frontend route which completes the login.
+    /// - `OIDC_SCOPES`: Defaults to `openid profile email groups`.
+    /// - `OIDC_GROUPS_CLAIM`: Defaults to `groups`.
+    pub fn from_settings(settings: &mut Settings) -> Option<Self> {
+        let client_id = settings.var("OIDC_CLIENT_ID")?;
+        let redirect_url = settings
+            .var("OIDC_REDIRECT_URL")
+            .unwrap_or_else(|| format!("https://{}/authorize/github", config::domain_name()));
+        let scopes = settings
+            .var("OIDC_SCOPES")
+            .unwrap_or_else(|| String::from("openid profile email groups"))
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+
+        Some(OidcConfig {
+            client_id,
+            client_secret: settings.required("OIDC_CLIENT_SECRET"),
+            authorize_url: settings.required_url("OIDC_AUTHORIZE_URL"),
+            token_url: settings.required_url("OIDC_TOKEN_URL"),
+            userinfo_url: settings.required_url("OIDC_USERINFO_URL"),
+            redirect_url,
+            scopes,
+            groups_claim: settings
+                .var("OIDC_GROUPS_CLAIM")
+                .unwrap_or_else(|| "groups".into()),
+        })
+    }
+}
+
+/// Authentication with an OpenID Connect provider, where teams are groups of the provider
+/// named `oidc:group`.
+///
+/// Users are matched to their identity with the `sub` claim, and the groups they belong to are
+/// refreshed every time they log in.
+pub struct OidcProvider {
+    config: OidcConfig,
+    client: BasicClient,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcConfig) -> Self {
+        let client = BasicClient::new(
+            ClientId::new(config.client_id.clone()),
+            Some(ClientSecret::new(config.client_secret.clone())),
+            AuthUrl::new(config.authorize_url.clone()).expect("Invalid OIDC_AUTHORIZE_URL"),
+            Some(TokenUrl::new(config.token_url.clone()).expect("Invalid OIDC_TOKEN_URL")),
+        )
+        .set_redirect_url(
+            RedirectUrl::new(config.redirect_url.clone()).expect("Invalid OIDC_REDIRECT_URL"),
+        );
+        OidcProvider { config, client }
+    }
+
+    fn save_to_database(&self, info: &UserInfo, conn: &PgConnection) -> AppResult<User> {
+        let login = info.preferred_username.as_deref().ok_or_else(|| {
+            server_error("The identity provider didn't return a `preferred_username`")
+        })?;
+        let groups = info.groups(&self.config.groups_claim);
+
+        conn.transaction::<_, Box<dyn AppError>, _>(|| {
+            let user_id = user_identities::table
+                .find((self.name(), &info.sub))
+                .select(user_identities::user_id)
+                .first::<i32>(conn)
+                .optional()?;
+            let user: User = match user_id {
+                Some(user_id) => diesel::update(users::table.find(user_id))
+                    .set((
+                        users::name.eq(&info.name),
+                        users::gh_avatar.eq(&info.picture),
+                    ))
+                    .get_result(conn)?,
+                // Users of other providers are identified by their `user_identities` row. They
+                // have neither a GitHub ID nor a GitHub token, and since `users.gh_id` is only
+                // unique above `0`, each of them gets their own user.
+                None => NewUser::new(0, login, info.name.as_deref(), info.picture.as_deref(), "")
+                    .create_or_update(info.email.as_deref(), conn)?,
+            };
+            let login = unique_login(conn, login, user.id)?;
+            let user = if user.gh_login == login {
+                user
+            } else {
+                diesel::update(users::table.find(user.id))
+                    .set(users::gh_login.eq(&login))
+                    .get_result(conn)?
+            };
+
+            diesel::insert_into(user_identities::table)
+                .values((
+                    user_identities::provider.eq(self.name()),
+                    user_identities::subject.eq(&info.sub),
+                    user_identities::user_id.eq(user.id),
+                    user_identities::groups.eq(&groups),
+                ))
+                .on_conflict((user_identities::provider, user_identities::subject))
+                .do_update()
+                .set((
+                    user_identities::groups.eq(&groups),
+                    user_identities::updated_at.eq(now),
+                ))
+                .execute(conn)?;
+            Ok(user)
+        })
+        .or_else(|e: Box<dyn AppError>| {
+            // If we're in read only mode, we can't update their details
+            // just look for an existing user
+            if e.is::<ReadOnlyMode>() {
+                user_identities::table
+                    .find((self.name(), &info.sub))
+                    .inner_join(users::table)
+                    .select(users::all_columns)
+                    .first(conn)
+                    .optional()?
+                    .ok_or(e)
+            } else {
+                Err(e)
+            }
+        })
+    }
+
+    fn group_contains_user(
+        &self,
+        conn: &PgConnection,
+        group: &str,
+        user: &User,
+    ) -> AppResult<bool> {
+        let query = user_identities::table
+            .filter(user_identities::provider.eq(self.name()))
+            .filter(user_identities::user_id.eq(user.id))
+            .filter(user_identities::groups.contains(vec![group]));
+        Ok(diesel::select(exists(query)).get_result(conn)?)
+    }
+}
+
+impl AuthProvider for OidcProvider {
+    fn name(&self) -> &'static str {
+        "oidc"
+    }
+
+    fn team_login_format(&self) -> &'static str {
+        "oidc:group"
+    }
+
+    fn authorize_url(&self) -> (Url, CsrfToken) {
+        let mut request = self.client.authorize_url(CsrfToken::new_random);
+        for scope in &self.config.scopes {
+            request = request.add_scope(Scope::new(scope.clone()));
+        }
+        request.url()
+    }
+
+    fn authenticate(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        code: AuthorizationCode,
+    ) -> AppResult<User> {
+        let token = self
+            .client
+            .exchange_code(code)
+            .request(|request| oauth_request(app, request))
+            .map_err(|e| e.compat())
+            .chain_error(|| server_error("Error obtaining token"))?;
+
+        let request = HttpRequest::get(&self.config.userinfo_url).header(
+            header::AUTHORIZATION,
+            &format!("Bearer {}", token.access_token().secret()),
+        )?;
+        let response = app.api_client().send(request)?;
+        if !response.status.is_success() {
+            return Err(server_error("Error obtaining the user info"));
+        }
+        let info: UserInfo = response.json()?;
+        self.save_to_database(&info, conn)
+    }
+
+    fn create_or_update_team(
+        &self,
+        _app: &App,
+        conn: &PgConnection,
+        login: &str,
+        req_user: &User,
+    ) -> AppResult<Team> {
+        let login = login.to_lowercase();
+        let group = team_group(&login);
+        if group.is_empty() {
+            return Err(cargo_err("missing group argument; format is oidc:group"));
+        }
+        if !self.group_contains_user(conn, group, req_user)? {
+            return Err(cargo_err("only members of a team can add it as an owner"));
+        }
+
+        diesel::insert_into(teams::table)
+            .values((
+                teams::login.eq(&login),
+                teams::github_id.eq(0),
+                teams::name.eq(group),
+            ))
+            .on_conflict(teams::login)
+            .do_update()
+            .set(teams::name.eq(group))
+            .get_result(conn)
+            .map_err(Into::into)
+    }
+
+    fn team_contains_user(
+        &self,
+        _app: &App,
+        conn: &PgConnection,
+        team: &Team,
+        user: &User,
+    ) -> AppResult<bool> {
+        self.group_contains_user(conn, team_group(&team.login), user)
+    }
+}
+
+/// Returns `login`, or `login-<user id>` if another user already has it, since owners are
+/// added by login and the usernames of the provider can collide with those of other users.
+fn unique_login(conn: &PgConnection, login: &str, user_id: i32) -> QueryResult<String> {
+    let others = users::table
+        .filter(crate::lower(users::gh_login).eq(login.to_lowercase()))
+        .filter(users::id.ne(user_id));
+    if diesel::select(exists(others)).get_result(conn)? {
+        Ok(format!("{}-{}", login, user_id))
+    } else {
+        Ok(login.to_string())
+    }
+}
+
+/// Returns the group of a team named `oidc:group`.
+fn team_group(login: &str) -> &str {
+    login.splitn(2, ':').nth(1).unwrap_or_default()
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    sub: String,
+    preferred_username: Option<String>,
+    name: Option<String>,
+    email: Option<String>,
+    picture: Option<String>,
+    #[serde(flatten)]
+    claims: HashMap<String, serde_json::Value>,
+}
+
+impl UserInfo {
+    /// Returns the lowercase names of the groups listed in the given claim, since team logins
+    /// are lowercase.
+    fn groups(&self, claim: &str) -> Vec<String> {
+        self.claims
+            .get(claim)
+            .and_then(|groups| groups.as_array())
+            .map(|groups| {
+                groups
+                    .iter()
+                    .filter_map(|group| group.as_str())
+                    .map(str::to_lowercase)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_are_read_from_the_configured_claim() {
+        let info: UserInfo = serde_json::from_value(json!({
+            "sub": "1234",
+            "preferred_username": "foo",
+            "groups": ["Admins", 1],
+            "roles": ["publishers"],
+        }))
+        .unwrap();
+
+        assert_eq!(info.groups("groups"), vec!["admins"]);
+        assert_eq!(info.groups("roles"), vec!["publishers"]);
+        assert!(info.groups("teams").is_empty());
+    }
+
+    fn pg_connection() -> PgConnection {
+        let database_url =
+            dotenv::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set to run tests");
+        let conn = PgConnection::establish(&database_url).unwrap();
+        conn.begin_test_transaction().unwrap();
+        conn
+    }
+
+    fn provider() -> OidcProvider {
+        OidcProvider::new(OidcConfig {
+            client_id: "client".into(),
+            client_secret: "secret".into(),
+            authorize_url: "https://sso.example.com/authorize".into(),
+            token_url: "https://sso.example.com/token".into(),
+            userinfo_url: "https://sso.example.com/userinfo".into(),
+            redirect_url: "https://crates.example.com/authorize/github".into(),
+            scopes: vec!["openid".into()],
+            groups_claim: "groups".into(),
+        })
+    }
+
+    fn user_info(sub: &str, username: &str) -> UserInfo {
+        serde_json::from_value(json!({ "sub": sub, "preferred_username": username })).unwrap()
+    }
+
+    #[test]
+    fn users_are_identified_by_their_subject() {
+        let conn = pg_connection();
+        let provider = provider();
+
+        let foo = provider
+            .save_to_database(&user_info("1", "foo"), &conn)
+            .unwrap();
+        let other_foo = provider
+            .save_to_database(&user_info("2", "Foo"), &conn)
+            .unwrap();
+        assert_ne!(foo.id, other_foo.id);
+        assert_eq!(foo.gh_login, "foo");
+        assert_eq!(other_foo.gh_login, format!("Foo-{}", other_foo.id));
+
+        // Logging in again finds the same users, with the same logins
+        let user = provider
+            .save_to_database(&user_info("2", "Foo"), &conn)
+            .unwrap();
+        assert_eq!(user.id, other_foo.id);
+        assert_eq!(user.gh_login, other_foo.gh_login);
+        let user = provider
+            .save_to_database(&user_info("1", "foo"), &conn)
+            .unwrap();
+        assert_eq!(user.id, foo.id);
+        assert_eq!(user.gh_login, "foo");
+    }
+
+    #[test]
+    fn team_group_strips_the_provider() {
+        assert_eq!(team_group("oidc:release-team"), "release-team");
+        assert_eq!(team_group("oidc:"), "");
+    }
+}
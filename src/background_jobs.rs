@@ -5,8 +5,11 @@ use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use diesel::r2d2::PoolError;
 use swirl::PerformError;
 
+use crate::cache::Cache;
 use crate::db::{DieselPool, DieselPooledConn};
 use crate::git::Repository;
+use crate::render::RenderSettings;
+use crate::search_backend::{PostgresBackend, SearchBackend};
 use crate::uploaders::Uploader;
 
 impl<'a> swirl::db::BorrowedConnection<'a> for DieselPool {
@@ -26,6 +29,9 @@ pub struct Environment {
     index: Arc<Mutex<Repository>>,
     pub uploader: Uploader,
     http_client: AssertUnwindSafe<Client>,
+    cache: Option<AssertUnwindSafe<Arc<dyn Cache>>>,
+    search_backend: AssertUnwindSafe<Arc<dyn SearchBackend>>,
+    render_settings: RenderSettings,
 }
 
 // FIXME: AssertUnwindSafe should be `Clone`, this can be replaced with
@@ -36,6 +42,12 @@ impl Clone for Environment {
             index: self.index.clone(),
             uploader: self.uploader.clone(),
             http_client: AssertUnwindSafe(self.http_client.0.clone()),
+            cache: self
+                .cache
+                .as_ref()
+                .map(|cache| AssertUnwindSafe(cache.0.clone())),
+            search_backend: AssertUnwindSafe(self.search_backend.0.clone()),
+            render_settings: self.render_settings.clone(),
         }
     }
 }
@@ -54,9 +66,30 @@ impl Environment {
             index,
             uploader,
             http_client: AssertUnwindSafe(http_client),
+            cache: None,
+            search_backend: AssertUnwindSafe(Arc::new(PostgresBackend)),
+            render_settings: RenderSettings::default(),
         }
     }
 
+    /// Sets the cache whose responses are invalidated by the jobs, see the `cache` module.
+    pub fn with_cache(mut self, cache: Option<Arc<dyn Cache>>) -> Self {
+        self.cache = cache.map(AssertUnwindSafe);
+        self
+    }
+
+    /// Sets the backend whose index the jobs update, see the `search_backend` module.
+    pub fn with_search_backend(mut self, search_backend: Arc<dyn SearchBackend>) -> Self {
+        self.search_backend = AssertUnwindSafe(search_backend);
+        self
+    }
+
+    /// Sets the settings the READMEs are rendered with.
+    pub fn with_render_settings(mut self, render_settings: RenderSettings) -> Self {
+        self.render_settings = render_settings;
+        self
+    }
+
     pub fn lock_index(&self) -> Result<MutexGuard<'_, Repository>, PerformError> {
         let repo = self.index.lock().unwrap_or_else(PoisonError::into_inner);
         repo.reset_head()?;
@@ -67,4 +100,16 @@ impl Environment {
     pub(crate) fn http_client(&self) -> &Client {
         &self.http_client
     }
+
+    pub(crate) fn cache(&self) -> Option<&dyn Cache> {
+        self.cache.as_ref().map(|cache| &*cache.0)
+    }
+
+    pub(crate) fn search_backend(&self) -> &dyn SearchBackend {
+        &**self.search_backend
+    }
+
+    pub(crate) fn render_settings(&self) -> &RenderSettings {
+        &self.render_settings
+    }
 }
@@ -1,6 +1,7 @@
 //! Runs enqueued background jobs
 //!
-//! This binary will loop until interrupted. It will run all jobs in the
+//! This binary will loop until it receives `SIGINT` or `SIGTERM`, after which
+//! it finishes the jobs it started and exits. It will run all jobs in the
 //! background queue, sleeping for 1 second whenever the queue is empty. If we
 //! are unable to spawn workers to run jobs (either because we couldn't connect
 //! to the DB, an error occurred while loading, or we just never heard back from
@@ -13,20 +14,23 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::git::{Repository, RepositoryConfig};
-use cargo_registry::{background_jobs::*, db};
+use cargo_registry::shutdown::Shutdown;
+use cargo_registry::{background_jobs::*, config, db};
 use diesel::r2d2;
 use reqwest::blocking::Client;
 use std::sync::{Arc, Mutex};
-use std::thread::sleep;
 use std::time::Duration;
 
 fn main() {
     println!("Booting runner");
 
-    let config = cargo_registry::Config::default();
+    let config = cargo_registry::Config::from_environment().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
     let db_url = db::connection_url(&config.db_url);
 
-    let job_start_timeout = dotenv::var("BACKGROUND_JOB_TIMEOUT")
+    let job_start_timeout = config::var("BACKGROUND_JOB_TIMEOUT")
         .unwrap_or_else(|_| "30".into())
         .parse()
         .expect("Invalid value for `BACKGROUND_JOB_TIMEOUT`");
@@ -39,9 +43,15 @@ fn main() {
     ));
     println!("Index cloned");
 
+    let cache = cargo_registry::cache::from_config(&config.cache.backend);
+    let search_backend = cargo_registry::search_backend::from_config(&config.search_backend);
+
     let build_runner = || {
         let environment =
-            Environment::new_shared(repository.clone(), config.uploader.clone(), Client::new());
+            Environment::new_shared(repository.clone(), config.uploader.clone(), Client::new())
+                .with_cache(cache.clone())
+                .with_search_backend(search_backend.clone())
+                .with_render_settings(config.render.clone());
         let db_config = r2d2::Pool::builder().min_idle(Some(0));
         swirl::Runner::builder(environment)
             .connection_pool_builder(&db_url, db_config)
@@ -50,11 +60,15 @@ fn main() {
     };
     let mut runner = build_runner();
 
+    let shutdown = Shutdown::new();
+    shutdown.trigger_on_signals();
+    shutdown.exit_after(config.server.shutdown_timeout);
+
     println!("Runner booted, running jobs");
 
     let mut failure_count = 0;
 
-    loop {
+    while !shutdown.is_triggered() {
         if let Err(e) = runner.run_all_pending_jobs() {
             failure_count += 1;
             if failure_count < 5 {
@@ -67,6 +81,9 @@ fn main() {
                 panic!("Failed to begin running jobs 5 times. Restarting the process");
             }
         }
-        sleep(Duration::from_secs(1));
+        shutdown.sleep(Duration::from_secs(1));
     }
+
+    shutdown.run_hooks();
+    println!("Runner has gracefully shutdown!");
 }
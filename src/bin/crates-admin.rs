@@ -1,9 +1,11 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::admin::{
-    delete_crate, delete_version, populate, render_readmes, test_pagerduty, transfer_crates,
-    verify_token,
+    add_advisory, audit_readmes, delete_crate, delete_version, export_snapshot, import_crates,
+    import_snapshot, migrate, populate, render_readmes, repair_downloads, seed, test_pagerduty,
+    transfer_crates, verify_token,
 };
+use cargo_registry::config;
 
 use clap::Clap;
 
@@ -16,10 +18,18 @@ struct Opts {
 
 #[derive(Clap, Debug)]
 enum SubCommand {
+    AddAdvisory(add_advisory::Opts),
+    AuditReadmes(audit_readmes::Opts),
     DeleteCrate(delete_crate::Opts),
     DeleteVersion(delete_version::Opts),
+    ExportSnapshot(export_snapshot::Opts),
+    ImportCrates(import_crates::Opts),
+    ImportSnapshot(import_snapshot::Opts),
+    Migrate(migrate::Opts),
     Populate(populate::Opts),
     RenderReadmes(render_readmes::Opts),
+    RepairDownloads(repair_downloads::Opts),
+    Seed(seed::Opts),
     TestPagerduty(test_pagerduty::Opts),
     TransferCrates(transfer_crates::Opts),
     VerifyToken(verify_token::Opts),
@@ -28,11 +38,24 @@ enum SubCommand {
 fn main() {
     let opts: Opts = Opts::parse();
 
+    if let Err(e) = config::check_config_file() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
     match opts.command {
+        SubCommand::AddAdvisory(opts) => add_advisory::run(opts),
+        SubCommand::AuditReadmes(opts) => audit_readmes::run(opts),
         SubCommand::DeleteCrate(opts) => delete_crate::run(opts),
         SubCommand::DeleteVersion(opts) => delete_version::run(opts),
+        SubCommand::ExportSnapshot(opts) => export_snapshot::run(opts),
+        SubCommand::ImportCrates(opts) => import_crates::run(opts),
+        SubCommand::ImportSnapshot(opts) => import_snapshot::run(opts),
+        SubCommand::Migrate(opts) => migrate::run(opts),
         SubCommand::Populate(opts) => populate::run(opts),
         SubCommand::RenderReadmes(opts) => render_readmes::run(opts),
+        SubCommand::RepairDownloads(opts) => repair_downloads::run(opts),
+        SubCommand::Seed(opts) => seed::run(opts),
         SubCommand::TestPagerduty(opts) => test_pagerduty::run(opts).unwrap(),
         SubCommand::TransferCrates(opts) => transfer_crates::run(opts),
         SubCommand::VerifyToken(opts) => verify_token::run(opts).unwrap(),
@@ -1,12 +1,14 @@
 #![deny(clippy::all)]
 
 use anyhow::{anyhow, Result};
-use cargo_registry::{db, env, tasks};
+use cargo_registry::upstream::UpstreamRegistry;
+use cargo_registry::{config, db, env, tasks};
 use diesel::prelude::*;
 use swirl::schema::background_jobs::dsl::*;
 use swirl::Job;
 
 fn main() -> Result<()> {
+    config::check_config_file()?;
     let conn = db::connect_now()?;
     let mut args = std::env::args().skip(1);
 
@@ -35,6 +37,26 @@ fn main() -> Result<()> {
                 .unwrap_or_else(|| String::from("db-dump.tar.gz"));
             Ok(tasks::dump_db(database_url, target_name).enqueue(&conn)?)
         }
+        "reconcile_dependents_counts" => Ok(tasks::reconcile_dependents_counts().enqueue(&conn)?),
+        "update_quality_scores" => Ok(tasks::update_quality_scores().enqueue(&conn)?),
+        "update_trending_scores" => Ok(tasks::update_trending_scores().enqueue(&conn)?),
+        "update_notable_dependents" => Ok(tasks::update_notable_dependents().enqueue(&conn)?),
+        "update_usage_stats" => Ok(tasks::update_usage_stats().enqueue(&conn)?),
+        "check_crate_links" => Ok(tasks::check_crate_links().enqueue(&conn)?),
+        "clean_pageview_visitors" => Ok(tasks::clean_pageview_visitors().enqueue(&conn)?),
+        "follow_upstream_events" => {
+            let upstream = UpstreamRegistry::from_environment()
+                .ok_or_else(|| anyhow!("`UPSTREAM_REGISTRY_URL` must be set to follow events"))?;
+            Ok(tasks::follow_upstream_events(upstream).enqueue(&conn)?)
+        }
+        "mirror_upstream_crate" => {
+            let upstream = UpstreamRegistry::from_environment()
+                .ok_or_else(|| anyhow!("`UPSTREAM_REGISTRY_URL` must be set to mirror crates"))?;
+            let name = args
+                .next()
+                .ok_or_else(|| anyhow!("The name of the crate to mirror is missing"))?;
+            Ok(tasks::mirror_upstream_crate(upstream, name).enqueue(&conn)?)
+        }
         other => Err(anyhow!("Unrecognized job type `{}`", other)),
     }
 }
@@ -7,10 +7,11 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use anyhow::Result;
-use cargo_registry::{admin::on_call, db, schema::*};
+use cargo_registry::{admin::on_call, config, db, schema::*};
 use diesel::prelude::*;
 
 fn main() -> Result<()> {
+    config::check_config_file()?;
     let conn = db::connect_now()?;
 
     check_failing_background_jobs(&conn)?;
@@ -37,7 +38,7 @@ fn check_failing_background_jobs(conn: &PgConnection) -> Result<()> {
     println!("Checking for failed background jobs");
 
     // Max job execution time in minutes
-    let max_job_time = dotenv::var("MAX_JOB_TIME")
+    let max_job_time = config::var("MAX_JOB_TIME")
         .map(|s| s.parse::<i32>().unwrap())
         .unwrap_or(15);
 
@@ -79,7 +80,7 @@ fn check_stalled_update_downloads(conn: &PgConnection) -> Result<()> {
     println!("Checking for stalled background jobs");
 
     // Max job execution time in minutes
-    let max_job_time = dotenv::var("MONITOR_MAX_UPDATE_DOWNLOADS_TIME")
+    let max_job_time = config::var("MONITOR_MAX_UPDATE_DOWNLOADS_TIME")
         .map(|s| s.parse::<u32>().unwrap() as i64)
         .unwrap_or(120);
 
@@ -116,12 +117,12 @@ fn check_spam_attack(conn: &PgConnection) -> Result<()> {
 
     println!("Checking for crates indicating someone is spamming us");
 
-    let bad_crate_names = dotenv::var("SPAM_CRATE_NAMES");
+    let bad_crate_names = config::var("SPAM_CRATE_NAMES");
     let bad_crate_names: Vec<_> = bad_crate_names
         .as_ref()
         .map(|s| s.split(',').collect())
         .unwrap_or_default();
-    let bad_author_patterns = dotenv::var("SPAM_AUTHOR_PATTERNS");
+    let bad_author_patterns = config::var("SPAM_AUTHOR_PATTERNS");
     let bad_author_patterns: Vec<_> = bad_author_patterns
         .as_ref()
         .map(|s| s.split(',').collect())
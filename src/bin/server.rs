@@ -1,17 +1,12 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![allow(clippy::unknown_clippy_lints)]
 
-use cargo_registry::{boot, App, Env};
-use std::{
-    borrow::Cow,
-    fs::File,
-    sync::{mpsc::channel, Arc, Mutex},
-    thread,
-    time::Duration,
-};
+use cargo_registry::config::FastBoot;
+use cargo_registry::shutdown::Shutdown;
+use cargo_registry::{boot, config, db, migrations, server, App, Config, Env};
+use std::{borrow::Cow, fs::File, sync::Arc, thread, time::Duration};
 
 use civet::Server as CivetServer;
-use conduit_hyper::Service;
 use futures_util::future::FutureExt;
 use reqwest::blocking::Client;
 use sentry::{ClientOptions, IntoDsn};
@@ -27,19 +22,25 @@ enum Server {
 use Server::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _sentry = dotenv::var("SENTRY_DSN_API")
+    // Read the configuration first, so that all its errors are reported before booting
+    let config = Config::from_environment().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let _sentry = config::var("SENTRY_DSN_API")
         .ok()
         .into_dsn()
         .expect("SENTRY_DSN_API is not a valid Sentry DSN value")
         .map(|dsn| {
             let mut opts = ClientOptions::from(dsn);
             opts.environment = Some(
-                dotenv::var("SENTRY_ENV_API")
+                config::var("SENTRY_ENV_API")
                     .map(Cow::Owned)
                     .expect("SENTRY_ENV_API must be set when using SENTRY_DSN_API"),
             );
 
-            opts.release = dotenv::var("HEROKU_SLUG_COMMIT").ok().map(Into::into);
+            opts.release = config::var("HEROKU_SLUG_COMMIT").ok().map(Into::into);
 
             sentry::init(opts)
         });
@@ -47,7 +48,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
     env_logger::init();
 
-    let config = cargo_registry::Config::default();
+    // Refuse to boot with a schema older than this code, so that the previous release keeps
+    // serving the traffic until the pre-deploy migrations ran
+    let missing_migrations = migrations::missing_migrations(&db::connect_now()?)?;
+    if !missing_migrations.is_empty() {
+        eprintln!(
+            "The migrations {} haven't run, run `crates-admin migrate --phase pre-deploy`",
+            missing_migrations.join(", ")
+        );
+        std::process::exit(1);
+    }
+
     let client = Client::new();
 
     let app = App::new(config.clone(), Some(client));
@@ -58,18 +69,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let categories_toml = include_str!("../boot/categories.toml");
     boot::categories::sync(categories_toml).unwrap();
 
-    let heroku = dotenv::var("HEROKU").is_ok();
-    let fastboot = dotenv::var("USE_FASTBOOT").is_ok();
+    let heroku = config.env == Env::Production;
+    let fastboot = config.fastboot != FastBoot::Disabled;
 
     let port = if heroku {
         8888
     } else {
-        dotenv::var("PORT")
+        config::var("PORT")
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(8888)
     };
-    let threads = dotenv::var("SERVER_THREADS")
+    let threads = config::var("SERVER_THREADS")
         .map(|s| s.parse().expect("SERVER_THREADS was not a valid number"))
         .unwrap_or_else(|_| {
             if config.env == Env::Development {
@@ -81,7 +92,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         });
 
-    let server = if dotenv::var("WEB_USE_CIVET").is_err() {
+    let shutdown = Shutdown::new();
+    shutdown.exit_after(config.server.shutdown_timeout);
+
+    let server = if config::var("WEB_USE_CIVET").is_err() {
         use tokio::io::AsyncWriteExt;
         use tokio::signal::unix::{signal, SignalKind};
 
@@ -95,22 +109,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .build()
             .unwrap();
 
-        let handler = Arc::new(conduit_hyper::BlockingHandler::new(app));
-        let make_service =
-            hyper::service::make_service_fn(move |socket: &hyper::server::conn::AddrStream| {
-                let addr = socket.remote_addr();
-                let handler = handler.clone();
-                async move { Service::from_blocking(handler, addr) }
-            });
-
-        let addr = ([127, 0, 0, 1], port).into();
-        #[allow(clippy::async_yields_async)]
-        let server = rt.block_on(async { hyper::Server::bind(&addr).serve(make_service) });
-
         let mut sig_int = rt.block_on(async { signal(SignalKind::interrupt()) })?;
         let mut sig_term = rt.block_on(async { signal(SignalKind::terminate()) })?;
 
-        let server = server.with_graceful_shutdown(async move {
+        let signaled_shutdown = shutdown.clone();
+        let graceful_shutdown = async move {
             // Wait for either signal
             futures_util::select! {
                 _ = sig_int.recv().fuse() => {},
@@ -118,7 +121,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
             let mut stdout = tokio::io::stdout();
             stdout.write_all(b"Starting graceful shutdown\n").await.ok();
-        });
+            // hyper stops accepting connections and waits for the requests in flight
+            signaled_shutdown.trigger();
+        };
+
+        let addr = ([127, 0, 0, 1], port).into();
+        let server_config = config.server;
+        let server = rt
+            .block_on(async move { server::serve(app, &addr, server_config, graceful_shutdown) })?;
 
         let server = rt.spawn(async { server.await.unwrap() });
         Hyper(rt, server)
@@ -152,30 +162,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             rt.block_on(async { server.await.unwrap() });
         }
         Civet(server) => {
-            let (tx, rx) = channel::<()>();
-            ctrlc_handler(move || tx.send(()).unwrap_or(()));
-            rx.recv().unwrap();
+            shutdown.trigger_on_signals();
+            shutdown.wait();
             drop(server);
         }
     }
 
+    shutdown.run_hooks();
     println!("Server has gracefully shutdown!");
     Ok(())
 }
-
-fn ctrlc_handler<F>(f: F)
-where
-    F: FnOnce() + Send + 'static,
-{
-    let call_once = Mutex::new(Some(f));
-
-    ctrlc::set_handler(move || {
-        if let Some(f) = call_once.lock().unwrap().take() {
-            println!("Starting graceful shutdown");
-            f();
-        } else {
-            println!("Already sent signal to start graceful shutdown");
-        }
-    })
-    .unwrap();
-}
@@ -0,0 +1,281 @@
+//! A cache for the responses of the hot read endpoints
+//!
+//! Popular crates can get a lot of traffic at once, for example when they are linked from a news
+//! site. The responses of the crate, summary, keyword and category endpoints are cached for a
+//! short time, see `CacheConfig`, so that these spikes don't all reach the database.
+//!
+//! The cache is disabled unless `CACHE_REDIS_URL` or `CACHE_IN_MEMORY` is set. The in-memory
+//! cache is local to each process, so it is only suitable for a single server and for tests.
+//!
+//! The cached responses including a crate are removed when it is published or yanked, so that
+//! `cargo` and the frontend see these changes right away. The keys of the keyword and category
+//! responses include a generation, which publishing increments instead of looking for all
+//! these keys. Other changes, like new downloads, show up once the cached responses expire.
+//!
+//! The in-memory cache keeps at most `MAX_MEMORY_ENTRIES` responses, and Redis is expected to
+//! be configured with an eviction policy.
+//!
+//! Errors of the cache are logged and otherwise ignored, the responses are then read from the
+//! database.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use diesel::r2d2::Pool;
+use redis::Commands;
+
+use crate::config::CacheBackend;
+
+/// The key of the `GET /summary` response
+pub const SUMMARY_KEY: &str = "summary";
+
+/// The prefix of the keys of the keyword responses
+pub const KEYWORDS_PREFIX: &str = "keywords:";
+
+/// The prefix of the keys of the category responses
+pub const CATEGORIES_PREFIX: &str = "categories:";
+
+/// The key of the generation of the keyword and category responses, see `listing_key`
+const LISTINGS_GENERATION_KEY: &str = "listings:generation";
+
+/// How many responses the in-memory cache keeps at most
+const MAX_MEMORY_ENTRIES: usize = 10_000;
+
+pub trait Cache: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Stores a value, which expires after the TTL.
+    fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<()>;
+
+    fn delete(&self, key: &str) -> Result<()>;
+
+    /// Increments a counter which doesn't expire, starting from 0, and returns its new value.
+    fn increment(&self, key: &str) -> Result<u64>;
+}
+
+/// Creates the cache of the configured backend, or `None` if the cache is disabled.
+///
+/// # Panics
+///
+/// Panics if the Redis URL is invalid.
+pub fn from_config(backend: &CacheBackend) -> Option<Arc<dyn Cache>> {
+    match backend {
+        CacheBackend::Disabled => None,
+        CacheBackend::Memory => Some(Arc::new(MemoryCache::new())),
+        CacheBackend::Redis(url) => Some(Arc::new(RedisCache::new(url))),
+    }
+}
+
+/// Returns the key of the `GET /crates/:crate_id` response.
+///
+/// Crate names are compared without case and with `-` and `_` being the same, so the key uses
+/// the canonical form of the name.
+pub fn crate_key(name: &str) -> String {
+    format!("crates:{}", name.to_lowercase().replace('-', "_"))
+}
+
+/// Removes the cached responses which include the crate, after a version was published or
+/// yanked.
+pub fn invalidate_crate(cache: Option<&dyn Cache>, name: &str) {
+    if let Some(cache) = cache {
+        log_error(cache.delete(&crate_key(name)));
+        log_error(cache.delete(SUMMARY_KEY));
+    }
+}
+
+/// Returns the key of a keyword or category response, such as `keywords:3:show/cli`, whose
+/// prefix is `KEYWORDS_PREFIX` or `CATEGORIES_PREFIX`.
+///
+/// The key includes the generation of these responses, so that `invalidate_listings` replaces
+/// all of them at once. The responses of the previous generations expire on their own.
+pub fn listing_key(cache: &dyn Cache, prefix: &str, path: &str) -> Result<String> {
+    let generation = match cache.get(LISTINGS_GENERATION_KEY)? {
+        Some(value) => String::from_utf8(value)?.parse::<u64>()?,
+        None => 0,
+    };
+    Ok(format!("{}{}:{}", prefix, generation, path))
+}
+
+/// Replaces the cached keyword and category responses, whose numbers of crates change when
+/// versions are published, see `listing_key`.
+pub fn invalidate_listings(cache: Option<&dyn Cache>) {
+    if let Some(cache) = cache {
+        log_error(cache.increment(LISTINGS_GENERATION_KEY));
+    }
+}
+
+pub(crate) fn log_error<T>(result: Result<T>) {
+    if let Err(e) = result {
+        eprintln!("Cache error: {}", e);
+    }
+}
+
+/// A cache in the memory of the process
+///
+/// Once it holds `MAX_MEMORY_ENTRIES` values, the expired ones are removed, and then the ones
+/// expiring first.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    /// The values and when they expire, `None` for the counters
+    values: Mutex<HashMap<String, (Vec<u8>, Option<Instant>)>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut values = self.values.lock().unwrap();
+        if let Some((value, expires_at)) = values.get(key) {
+            if expires_at.map_or(true, |expires_at| expires_at > Instant::now()) {
+                return Ok(Some(value.clone()));
+            }
+        }
+        values.remove(key);
+        Ok(None)
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<()> {
+        let now = Instant::now();
+        let mut values = self.values.lock().unwrap();
+        if values.len() >= MAX_MEMORY_ENTRIES && !values.contains_key(key) {
+            values.retain(|_, (_, expires_at)| expires_at.map_or(true, |at| at > now));
+        }
+        if values.len() >= MAX_MEMORY_ENTRIES && !values.contains_key(key) {
+            let first_expiring = values
+                .iter()
+                .filter_map(|(key, (_, expires_at))| expires_at.map(|at| (at, key)))
+                .min()
+                .map(|(_, key)| key.clone());
+            if let Some(first_expiring) = first_expiring {
+                values.remove(&first_expiring);
+            }
+        }
+        values.insert(key.to_string(), (value.to_vec(), Some(now + ttl)));
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.values.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn increment(&self, key: &str) -> Result<u64> {
+        let mut values = self.values.lock().unwrap();
+        let counter = match values.get(key) {
+            Some((value, None)) => std::str::from_utf8(value)?.parse::<u64>()? + 1,
+            _ => 1,
+        };
+        values.insert(key.to_string(), (counter.to_string().into_bytes(), None));
+        Ok(counter)
+    }
+}
+
+/// A cache shared by all the processes through Redis
+///
+/// The keys are prefixed with `crates-io:`, so that the Redis database can be shared with other
+/// applications.
+// Can't derive Debug because of the connection pool.
+#[allow(missing_debug_implementations)]
+pub struct RedisCache {
+    pool: Pool<redis::Client>,
+}
+
+const REDIS_NAMESPACE: &str = "crates-io:";
+
+impl RedisCache {
+    /// Creates a cache connecting to Redis lazily, so that the server starts even when Redis is
+    /// unavailable.
+    pub fn new(url: &str) -> Self {
+        let client = redis::Client::open(url).expect("Invalid Redis URL");
+        let pool = Pool::builder()
+            .max_size(10)
+            .min_idle(Some(0))
+            // The database is used instead when Redis doesn't answer quickly
+            .connection_timeout(Duration::from_secs(1))
+            .build_unchecked(client);
+        RedisCache { pool }
+    }
+}
+
+impl Cache for RedisCache {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut conn = self.pool.get()?;
+        Ok(conn.get::<_, Option<Vec<u8>>>(format!("{}{}", REDIS_NAMESPACE, key))?)
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let millis = ttl.as_millis() as usize;
+        conn.pset_ex::<_, _, ()>(format!("{}{}", REDIS_NAMESPACE, key), value, millis)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        conn.del::<_, ()>(format!("{}{}", REDIS_NAMESPACE, key))?;
+        Ok(())
+    }
+
+    fn increment(&self, key: &str) -> Result<u64> {
+        let mut conn = self.pool.get()?;
+        Ok(conn.incr::<_, _, u64>(format!("{}{}", REDIS_NAMESPACE, key), 1)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn memory_cache_expires_values() {
+        let cache = MemoryCache::new();
+        cache.set("a", b"1", Duration::from_millis(20)).unwrap();
+        cache.set("b", b"2", Duration::from_secs(60)).unwrap();
+        assert_eq!(cache.get("a").unwrap().unwrap(), b"1");
+
+        thread::sleep(Duration::from_millis(30));
+        assert!(cache.get("a").unwrap().is_none());
+        assert_eq!(cache.get("b").unwrap().unwrap(), b"2");
+    }
+
+    #[test]
+    fn memory_cache_deletes_values() {
+        let cache = MemoryCache::new();
+        let ttl = Duration::from_secs(60);
+        cache.set(&crate_key("Foo-Bar"), b"foo", ttl).unwrap();
+        let keywords_key = listing_key(&cache, KEYWORDS_PREFIX, "show/cli").unwrap();
+        cache.set(&keywords_key, b"kw", ttl).unwrap();
+        assert_eq!(keywords_key, "keywords:0:show/cli");
+
+        invalidate_crate(Some(&cache), "foo_bar");
+        assert!(cache.get(&crate_key("foo-bar")).unwrap().is_none());
+
+        invalidate_listings(Some(&cache));
+        let keywords_key = listing_key(&cache, KEYWORDS_PREFIX, "show/cli").unwrap();
+        assert_eq!(keywords_key, "keywords:1:show/cli");
+        assert!(cache.get(&keywords_key).unwrap().is_none());
+    }
+
+    #[test]
+    fn memory_cache_is_bounded() {
+        let cache = MemoryCache::new();
+        cache.increment("counter").unwrap();
+        cache.set("first", b"1", Duration::from_secs(1)).unwrap();
+        for i in 0..MAX_MEMORY_ENTRIES {
+            cache
+                .set(&i.to_string(), b"2", Duration::from_secs(60))
+                .unwrap();
+        }
+
+        assert_eq!(cache.values.lock().unwrap().len(), MAX_MEMORY_ENTRIES);
+        assert!(cache.get("first").unwrap().is_none());
+        assert_eq!(cache.increment("counter").unwrap(), 2);
+    }
+}
@@ -1,5 +1,14 @@
-use crate::publish_rate_limit::PublishRateLimit;
-use crate::{env, uploaders::Uploader, Env, Replica};
+use std::time::Duration;
+
+use crate::auth_provider::OidcConfig;
+use crate::rate_limit::{LimitedAction, RateLimit};
+use crate::render::RenderSettings;
+use crate::upstream::UpstreamRegistry;
+use crate::{uploaders::Uploader, Env, Replica};
+
+mod settings;
+
+pub use self::settings::{check_config_file, var, ConfigError, Settings};
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -9,23 +18,379 @@ pub struct Config {
     pub gh_client_secret: String,
     pub db_url: String,
     pub replica_db_url: Option<String>,
+    pub db_pool: DbPoolConfig,
+    pub cpu_pool: CpuPoolConfig,
+    pub cache: CacheConfig,
+    pub search: SearchConfig,
+    /// Where the text of searches is matched, see the `search_backend` module
+    pub search_backend: SearchBackendConfig,
+    pub server: ServerConfig,
     pub env: Env,
     pub max_upload_size: u64,
     pub max_unpack_size: u64,
     pub mirror: Replica,
     pub api_protocol: String,
-    pub publish_rate_limit: PublishRateLimit,
+    pub publish_rate_limit: RateLimit,
+    /// The rate limit of the previews of READMEs
+    pub readme_preview_rate_limit: RateLimit,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
     pub domain_name: String,
     pub allowed_origins: Vec<String>,
+    pub upstream: Option<UpstreamRegistry>,
+    pub oidc: Option<OidcConfig>,
+    /// The settings READMEs are rendered with
+    pub render: RenderSettings,
+    pub fastboot: FastBoot,
+    pub maintenance_mode: bool,
+    pub spam_score_threshold: u32,
+}
+
+/// The settings of the database connection pools
+#[derive(Clone, Copy, Debug)]
+pub struct DbPoolConfig {
+    pub pool_size: u32,
+    pub min_idle: Option<u32>,
+    pub helper_threads: usize,
+    /// Used as the connection and statement timeout value for the database pool(s), in seconds
+    pub connection_timeout: u64,
+    /// Whether the primary database is also read-only
+    pub read_only_mode: bool,
+    /// Whether the `BalanceCapacity` middleware is enabled, which it is when `DB_POOL_SIZE` is
+    /// set to at least 10
+    pub balance_capacity: bool,
+}
+
+impl DbPoolConfig {
+    /// Returns the default settings for an environment.
+    pub fn for_env(env: Env) -> Self {
+        let production = env == Env::Production;
+        DbPoolConfig {
+            pool_size: if production { 10 } else { 3 },
+            min_idle: if production { Some(5) } else { None },
+            helper_threads: if production { 3 } else { 1 },
+            connection_timeout: match env {
+                Env::Production => 10,
+                Env::Test => 1,
+                Env::Development => 30,
+            },
+            read_only_mode: false,
+            balance_capacity: false,
+        }
+    }
+
+    /// Reads the settings, falling back to the defaults of the environment.
+    ///
+    /// - `DB_POOL_SIZE`, `DB_MIN_IDLE`, `DB_HELPER_THREADS` and `DB_TIMEOUT` (in seconds)
+    /// - `READ_ONLY_MODE`: Only allow reads from the primary database.
+    fn from_settings(settings: &mut Settings, env: Env) -> Self {
+        let defaults = Self::for_env(env);
+        let pool_size = settings.parse("DB_POOL_SIZE");
+        DbPoolConfig {
+            pool_size: pool_size.unwrap_or(defaults.pool_size),
+            min_idle: settings.parse("DB_MIN_IDLE").or(defaults.min_idle),
+            helper_threads: settings
+                .parse("DB_HELPER_THREADS")
+                .unwrap_or(defaults.helper_threads),
+            connection_timeout: settings
+                .parse("DB_TIMEOUT")
+                .unwrap_or(defaults.connection_timeout),
+            read_only_mode: settings.flag("READ_ONLY_MODE"),
+            balance_capacity: pool_size.map_or(false, |pool_size| pool_size >= 10),
+        }
+    }
+}
+
+/// The settings of the pool of threads for CPU-heavy work, see the `cpu_pool` module
+#[derive(Clone, Copy, Debug)]
+pub struct CpuPoolConfig {
+    pub workers: usize,
+    /// The number of tasks which can wait for a worker before new ones are rejected
+    pub queue_depth: usize,
+}
+
+impl Default for CpuPoolConfig {
+    fn default() -> Self {
+        CpuPoolConfig {
+            workers: 2,
+            queue_depth: 8,
+        }
+    }
+}
+
+impl CpuPoolConfig {
+    /// Reads `CPU_POOL_WORKERS`, at least 1, and `CPU_POOL_QUEUE_DEPTH`, falling back to the
+    /// defaults.
+    fn from_settings(settings: &mut Settings) -> Self {
+        let defaults = Self::default();
+        let workers = match settings.parse("CPU_POOL_WORKERS") {
+            // Without workers, the tasks would wait forever
+            Some(0) => {
+                settings.error("CPU_POOL_WORKERS", &"must be at least 1");
+                defaults.workers
+            }
+            Some(workers) => workers,
+            None => defaults.workers,
+        };
+        CpuPoolConfig {
+            workers,
+            queue_depth: settings
+                .parse("CPU_POOL_QUEUE_DEPTH")
+                .unwrap_or(defaults.queue_depth),
+        }
+    }
+}
+
+/// The settings of the HTTP server, see the `server` module
+#[derive(Clone, Copy, Debug)]
+pub struct ServerConfig {
+    /// Whether HTTP/1.1 connections are kept open between requests
+    pub keep_alive: bool,
+    /// Whether HTTP/2 connections are accepted, along with HTTP/1.1 ones
+    pub http2: bool,
+    /// How long to wait for the next bytes of a request body
+    pub read_timeout: Duration,
+    /// How long to wait for the client to receive the next bytes of a response
+    pub write_timeout: Duration,
+    /// How long connections are kept open without requests
+    pub idle_timeout: Duration,
+    /// How long the server and the background worker have to finish their work once asked to
+    /// stop, see the `shutdown` module
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            keep_alive: true,
+            http2: true,
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(75),
+            // Heroku kills the process 30 seconds after asking it to stop
+            shutdown_timeout: Duration::from_secs(25),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Reads the settings, falling back to the defaults.
+    ///
+    /// - `SERVER_DISABLE_KEEP_ALIVE`: Close HTTP/1.1 connections after each request.
+    /// - `SERVER_DISABLE_HTTP2`: Only accept HTTP/1.1 connections.
+    /// - `SERVER_READ_TIMEOUT`, `SERVER_WRITE_TIMEOUT` and `SERVER_IDLE_TIMEOUT`: The timeouts,
+    ///   in seconds. Default to 30, 30 and 75.
+    /// - `SHUTDOWN_TIMEOUT`: The number of seconds to finish the work in progress once asked to
+    ///   stop. Defaults to 25.
+    fn from_settings(settings: &mut Settings) -> Self {
+        let defaults = Self::default();
+        let keep_alive = !settings.flag("SERVER_DISABLE_KEEP_ALIVE");
+        let http2 = !settings.flag("SERVER_DISABLE_HTTP2");
+        let mut timeout = |name, default| {
+            settings
+                .parse(name)
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+        ServerConfig {
+            keep_alive,
+            http2,
+            read_timeout: timeout("SERVER_READ_TIMEOUT", defaults.read_timeout),
+            write_timeout: timeout("SERVER_WRITE_TIMEOUT", defaults.write_timeout),
+            idle_timeout: timeout("SERVER_IDLE_TIMEOUT", defaults.idle_timeout),
+            shutdown_timeout: timeout("SHUTDOWN_TIMEOUT", defaults.shutdown_timeout),
+        }
+    }
+}
+
+/// The settings of the cache of the hot read endpoints, see the `cache` module
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+    pub backend: CacheBackend,
+    /// How long the `GET /crates/:crate_id` responses are cached
+    pub crate_ttl: Duration,
+    /// How long the `GET /summary` response is cached
+    pub summary_ttl: Duration,
+    /// How long the keyword and category responses are cached
+    pub listing_ttl: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheBackend {
+    Disabled,
+    /// A cache in the memory of each process
+    Memory,
+    /// A cache shared through the Redis server with this URL
+    Redis(String),
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            backend: CacheBackend::Disabled,
+            crate_ttl: Duration::from_secs(60),
+            summary_ttl: Duration::from_secs(60),
+            listing_ttl: Duration::from_secs(300),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Reads the settings, falling back to the defaults.
+    ///
+    /// - `CACHE_REDIS_URL`: Cache the responses in Redis.
+    /// - `CACHE_IN_MEMORY`: Cache the responses in the memory of each process, when
+    ///   `CACHE_REDIS_URL` isn't set.
+    /// - `CACHE_CRATE_TTL`, `CACHE_SUMMARY_TTL` and `CACHE_LISTING_TTL`: How long the responses
+    ///   are cached, in seconds. Default to 60, 60 and 300.
+    fn from_settings(settings: &mut Settings) -> Self {
+        let defaults = Self::default();
+        let backend = match settings.var("CACHE_REDIS_URL") {
+            Some(url) => CacheBackend::Redis(url),
+            None if settings.flag("CACHE_IN_MEMORY") => CacheBackend::Memory,
+            None => CacheBackend::Disabled,
+        };
+        let mut ttl = |name, default| {
+            settings
+                .parse(name)
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+        CacheConfig {
+            backend,
+            crate_ttl: ttl("CACHE_CRATE_TTL", defaults.crate_ttl),
+            summary_ttl: ttl("CACHE_SUMMARY_TTL", defaults.summary_ttl),
+            listing_ttl: ttl("CACHE_LISTING_TTL", defaults.listing_ttl),
+        }
+    }
+}
+
+/// The weights of the signals blended by the `relevance` sort of searches, see
+/// `controllers::krate::search`
+#[derive(Clone, Copy, Debug)]
+pub struct SearchConfig {
+    /// The weight of the full-text rank of the name, keywords, description and README
+    pub text_weight: f64,
+    /// The weight of the logarithm of the downloads of the last 90 days
+    pub downloads_weight: f64,
+    /// The weight of the recency of the last update, which halves every `update_half_life`
+    pub updates_weight: f64,
+    pub update_half_life: Duration,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            text_weight: 1.0,
+            downloads_weight: 0.02,
+            updates_weight: 0.1,
+            update_half_life: Duration::from_secs(180 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Reads the settings, falling back to the defaults.
+    ///
+    /// - `SEARCH_TEXT_WEIGHT`, `SEARCH_DOWNLOADS_WEIGHT` and `SEARCH_UPDATES_WEIGHT`: The
+    ///   weights of the signals. Default to 1, 0.02 and 0.1.
+    /// - `SEARCH_UPDATE_HALF_LIFE_DAYS`: How many days the weight of the last update takes to
+    ///   halve, at least 1. Defaults to 180.
+    fn from_settings(settings: &mut Settings) -> Self {
+        let defaults = Self::default();
+        // The weights are part of the SQL of searches, see `search::popularity`
+        let mut weight = |name, default| match settings.parse::<f64>(name) {
+            Some(weight) if weight.is_finite() => weight,
+            Some(_) => {
+                settings.error(name, &"must be a finite number");
+                default
+            }
+            None => default,
+        };
+        let text_weight = weight("SEARCH_TEXT_WEIGHT", defaults.text_weight);
+        let downloads_weight = weight("SEARCH_DOWNLOADS_WEIGHT", defaults.downloads_weight);
+        let updates_weight = weight("SEARCH_UPDATES_WEIGHT", defaults.updates_weight);
+        SearchConfig {
+            text_weight,
+            downloads_weight,
+            updates_weight,
+            update_half_life: settings
+                .parse::<u64>("SEARCH_UPDATE_HALF_LIFE_DAYS")
+                .filter(|days| *days > 0)
+                .map_or(defaults.update_half_life, |days| {
+                    Duration::from_secs(days * 24 * 60 * 60)
+                }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchBackendConfig {
+    /// The full-text index of the database
+    Postgres,
+    /// The index of a Meilisearch server
+    Meilisearch {
+        url: String,
+        api_key: Option<String>,
+        index: String,
+    },
+}
+
+impl Default for SearchBackendConfig {
+    fn default() -> Self {
+        SearchBackendConfig::Postgres
+    }
+}
+
+impl SearchBackendConfig {
+    /// Reads the settings, falling back to Postgres.
+    ///
+    /// - `MEILISEARCH_URL`: Search the crates with the Meilisearch server at this URL.
+    /// - `MEILISEARCH_API_KEY`: The key of the Meilisearch API, if it requires one.
+    /// - `MEILISEARCH_INDEX`: The index of the crates. Defaults to `crates`.
+    fn from_settings(settings: &mut Settings) -> Self {
+        match settings.var("MEILISEARCH_URL") {
+            Some(url) => SearchBackendConfig::Meilisearch {
+                url,
+                api_key: settings.var("MEILISEARCH_API_KEY"),
+                index: settings
+                    .var("MEILISEARCH_INDEX")
+                    .unwrap_or_else(|| "crates".into()),
+            },
+            None => SearchBackendConfig::Postgres,
+        }
+    }
+}
+
+/// How the HTML of the frontend is served
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FastBoot {
+    /// The static `index.html` is served
+    Disabled,
+    /// Requests are proxied to FastBoot by nginx
+    Enabled,
+    /// Requests are proxied to a local FastBoot server by the backend
+    Experimental,
 }
 
 impl Default for Config {
-    /// Returns a default value for the application's config
+    /// Returns the application's config, read with `Config::from_environment`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration is invalid, listing all the errors.
+    fn default() -> Config {
+        Config::from_environment().unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl Config {
+    /// Reads the application's config from the environment and the optional configuration file,
+    /// see the `settings` module.
     ///
     /// Sets the following default values:
     ///
     /// - `Config::max_upload_size`: 10MiB
+    /// - `Config::max_unpack_size`: 512MiB
     /// - `Config::api_protocol`: `https`
     ///
     /// Pulls values from the following environment variables:
@@ -37,21 +402,62 @@ impl Default for Config {
     /// - `S3_REGION`: The region in which the bucket was created. Optional if US standard.
     /// - `S3_ACCESS_KEY`: The access key to interact with S3. Optional if running a mirror.
     /// - `S3_SECRET_KEY`: The secret key to interact with S3. Optional if running a mirror.
+    /// - `S3_CDN`: The domain of a CDN serving the files of the bucket.
     /// - `SESSION_KEY`: The key used to sign and encrypt session cookies.
     /// - `GH_CLIENT_ID`: The client ID of the associated GitHub application.
     /// - `GH_CLIENT_SECRET`: The client secret of the associated GitHub application.
     /// - `DATABASE_URL`: The URL of the postgres database to use.
     /// - `READ_ONLY_REPLICA_URL`: The URL of an optional postgres read-only replica database.
+    /// - `DB_*` and `READ_ONLY_MODE`: The settings of the database pools, see `DbPoolConfig`.
+    /// - `CPU_POOL_*`: The settings of the pool of threads for CPU-heavy work, see
+    ///    `CpuPoolConfig`.
+    /// - `CACHE_*`: The settings of the cache of the hot read endpoints, see `CacheConfig`.
+    /// - `SEARCH_*`: The weights of the relevance of search results, see `SearchConfig`.
+    /// - `MEILISEARCH_*`: Search an external engine instead of the database, see
+    ///    `SearchBackendConfig`.
+    /// - `SERVER_*`: The protocols and timeouts of the HTTP server, see `ServerConfig`.
+    /// - `MAX_UPLOAD_SIZE` and `MAX_UNPACK_SIZE`: The maximum size of crate files, compressed and
+    ///    decompressed, in bytes.
+    /// - `PUBLISH_RATE_LIMIT_SECONDS` and `PUBLISH_RATE_LIMIT_BURST`: The number of seconds
+    ///    between the new versions a user can publish, and the number of new versions they can
+    ///    publish in a burst. Default to 600 and 30.
+    /// - `README_PREVIEW_RATE_LIMIT_SECONDS` and `README_PREVIEW_RATE_LIMIT_BURST`: The same
+    ///    limits for the previews of READMEs. Default to 6 and 20.
     /// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use for blocking
     ///.  traffic. See the `block_traffic` module for more documentation.
-    fn default() -> Config {
+    /// - `DOMAIN_NAME`: The domain name of the instance. Defaults to `crates.io`.
+    /// - `WEB_ALLOWED_ORIGINS`: A comma separated list of the origins allowed to use the API.
+    /// - `UPSTREAM_REGISTRY_URL`: The URL of a registry to mirror unknown crates from, such as
+    ///    `https://crates.io`. See the `upstream` module for more documentation.
+    /// - `UPSTREAM_INDEX_URL`: The URL of the sparse index of the upstream registry. Defaults to
+    ///    `https://index.crates.io`.
+    /// - `OIDC_CLIENT_ID`: Authenticate users with an OpenID Connect provider instead of GitHub.
+    ///    See `OidcConfig::from_settings` for the other variables it requires.
+    /// - `CAMO_URL` and `CAMO_KEY`: Load the images of READMEs through a camo proxy, see
+    ///    `Camo::from_settings`.
+    /// - `FORGE_HOSTS`: The self-hosted forges whose repositories get relative links in
+    ///    READMEs, see `Forge::from_settings`.
+    /// - `README_MAX_SIZE` and `README_RENDER_TIMEOUT`: The limits of the size and of the
+    ///    rendering of READMEs, see `RenderLimits::from_settings`.
+    /// - `MAINTENANCE_MODE`: Reject all the requests except reads and downloads, see the
+    ///    `maintenance_mode` middleware.
+    /// - `SPAM_SCORE_THRESHOLD`: The spam score from which new versions are held for review, see
+    ///    the `spam` module. `0` disables the check. Defaults to 10.
+    /// - `USE_FASTBOOT`: Serve the frontend with FastBoot. `staging-experimental` proxies the
+    ///    requests to a local FastBoot server.
+    ///
+    /// The settings of the index and of the email server are read by
+    /// `RepositoryConfig::from_environment` and `email::init_config_vars`.
+    pub fn from_environment() -> Result<Config, ConfigError> {
+        let mut settings = Settings::new();
+
         let api_protocol = String::from("https");
-        let mirror = if dotenv::var("MIRROR").is_ok() {
+        let mirror = if settings.flag("MIRROR") {
             Replica::ReadOnlyMirror
         } else {
             Replica::Primary
         };
-        let heroku = dotenv::var("HEROKU").is_ok();
+        let heroku = settings.flag("HEROKU");
         let cargo_env = if heroku {
             Env::Production
         } else {
@@ -59,18 +465,18 @@ impl Default for Config {
         };
         let uploader = match (cargo_env, mirror) {
             (Env::Production, Replica::Primary) => {
-                // `env` panics if these vars are not set, and in production for a primary instance,
-                // that's what we want since we don't want to be able to start the server if the
-                // server doesn't know where to upload crates.
+                // `required` fails if these vars are not set, and in production for a primary
+                // instance, that's what we want since we don't want to be able to start the
+                // server if the server doesn't know where to upload crates.
                 Uploader::S3 {
                     bucket: s3::Bucket::new(
-                        env("S3_BUCKET"),
-                        dotenv::var("S3_REGION").ok(),
-                        env("S3_ACCESS_KEY"),
-                        env("S3_SECRET_KEY"),
+                        settings.required("S3_BUCKET"),
+                        settings.var("S3_REGION"),
+                        settings.required("S3_ACCESS_KEY"),
+                        settings.required("S3_SECRET_KEY"),
                         &api_protocol,
                     ),
-                    cdn: dotenv::var("S3_CDN").ok(),
+                    cdn: settings.var("S3_CDN"),
                 }
             }
             (Env::Production, Replica::ReadOnlyMirror) => {
@@ -84,18 +490,18 @@ impl Default for Config {
                 // to serve crate files from.
                 Uploader::S3 {
                     bucket: s3::Bucket::new(
-                        env("S3_BUCKET"),
-                        dotenv::var("S3_REGION").ok(),
-                        dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
-                        dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
+                        settings.required("S3_BUCKET"),
+                        settings.var("S3_REGION"),
+                        settings.var("S3_ACCESS_KEY").unwrap_or_default(),
+                        settings.var("S3_SECRET_KEY").unwrap_or_default(),
                         &api_protocol,
                     ),
-                    cdn: dotenv::var("S3_CDN").ok(),
+                    cdn: settings.var("S3_CDN"),
                 }
             }
             // In Development mode, either running as a primary instance or a read-only mirror
             _ => {
-                if dotenv::var("S3_BUCKET").is_ok() {
+                if let Some(bucket) = settings.var("S3_BUCKET") {
                     // If we've set the `S3_BUCKET` variable to any value, use all of the values
                     // for the related S3 environment variables and configure the app to upload to
                     // and read from S3 like production does. All values except for bucket are
@@ -103,13 +509,13 @@ impl Default for Config {
                     println!("Using S3 uploader");
                     Uploader::S3 {
                         bucket: s3::Bucket::new(
-                            env("S3_BUCKET"),
-                            dotenv::var("S3_REGION").ok(),
-                            dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
-                            dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
+                            bucket,
+                            settings.var("S3_REGION"),
+                            settings.var("S3_ACCESS_KEY").unwrap_or_default(),
+                            settings.var("S3_SECRET_KEY").unwrap_or_default(),
                             &api_protocol,
                         ),
-                        cdn: dotenv::var("S3_CDN").ok(),
+                        cdn: settings.var("S3_CDN"),
                     }
                 } else {
                     // If we don't set the `S3_BUCKET` variable, we'll use a development-only
@@ -122,39 +528,110 @@ impl Default for Config {
                 }
             }
         };
-        let allowed_origins = env("WEB_ALLOWED_ORIGINS")
+        let allowed_origins = settings
+            .required("WEB_ALLOWED_ORIGINS")
             .split(',')
             .map(ToString::to_string)
             .collect();
-        Config {
+
+        // The GitHub application is only needed when users log in with GitHub
+        let oidc = OidcConfig::from_settings(&mut settings);
+        let (gh_client_id, gh_client_secret) = if oidc.is_some() {
+            (
+                settings.var("GH_CLIENT_ID").unwrap_or_default(),
+                settings.var("GH_CLIENT_SECRET").unwrap_or_default(),
+            )
+        } else {
+            (
+                settings.required("GH_CLIENT_ID"),
+                settings.required("GH_CLIENT_SECRET"),
+            )
+        };
+
+        let defaults = LimitedAction::PublishNew.default_rate_limit();
+        let publish_rate_limit = RateLimit {
+            rate: settings
+                .parse("PUBLISH_RATE_LIMIT_SECONDS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.rate),
+            burst: settings
+                .parse("PUBLISH_RATE_LIMIT_BURST")
+                .unwrap_or(defaults.burst),
+        };
+
+        let defaults = LimitedAction::ReadmePreview.default_rate_limit();
+        let readme_preview_rate_limit = RateLimit {
+            rate: settings
+                .parse("README_PREVIEW_RATE_LIMIT_SECONDS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.rate),
+            burst: settings
+                .parse("README_PREVIEW_RATE_LIMIT_BURST")
+                .unwrap_or(defaults.burst),
+        };
+
+        let fastboot = match settings.var("USE_FASTBOOT").as_deref() {
+            None => FastBoot::Disabled,
+            Some("staging-experimental") => FastBoot::Experimental,
+            Some(_) => FastBoot::Enabled,
+        };
+
+        let config = Config {
             uploader,
-            session_key: env("SESSION_KEY"),
-            gh_client_id: env("GH_CLIENT_ID"),
-            gh_client_secret: env("GH_CLIENT_SECRET"),
-            db_url: env("DATABASE_URL"),
-            replica_db_url: dotenv::var("READ_ONLY_REPLICA_URL").ok(),
+            session_key: settings.required("SESSION_KEY"),
+            gh_client_id,
+            gh_client_secret,
+            db_url: settings.required_url("DATABASE_URL"),
+            replica_db_url: settings.var("READ_ONLY_REPLICA_URL"),
+            db_pool: DbPoolConfig::from_settings(&mut settings, cargo_env),
+            cpu_pool: CpuPoolConfig::from_settings(&mut settings),
+            cache: CacheConfig::from_settings(&mut settings),
+            search: SearchConfig::from_settings(&mut settings),
+            search_backend: SearchBackendConfig::from_settings(&mut settings),
+            server: ServerConfig::from_settings(&mut settings),
             env: cargo_env,
-            max_upload_size: 10 * 1024 * 1024, // 10 MB default file upload size limit
-            max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
+            // 10 MB default file upload size limit
+            max_upload_size: settings
+                .parse("MAX_UPLOAD_SIZE")
+                .unwrap_or(10 * 1024 * 1024),
+            // 512 MB max when decompressed
+            max_unpack_size: settings
+                .parse("MAX_UNPACK_SIZE")
+                .unwrap_or(512 * 1024 * 1024),
             mirror,
             api_protocol,
-            publish_rate_limit: Default::default(),
-            blocked_traffic: blocked_traffic(),
+            publish_rate_limit,
+            readme_preview_rate_limit,
+            blocked_traffic: blocked_traffic(&settings),
             domain_name: domain_name(),
             allowed_origins,
-        }
+            upstream: UpstreamRegistry::from_environment(),
+            oidc,
+            render: RenderSettings::from_settings(&mut settings),
+            fastboot,
+            maintenance_mode: settings.flag("MAINTENANCE_MODE"),
+            spam_score_threshold: settings
+                .parse("SPAM_SCORE_THRESHOLD")
+                .unwrap_or(crate::spam::DEFAULT_THRESHOLD),
+        };
+
+        // The email server is configured separately, but checked along with everything else
+        crate::email::MailgunConfigVars::from_settings(&mut settings);
+
+        settings.finish()?;
+        Ok(config)
     }
 }
 
 pub(crate) fn domain_name() -> String {
-    dotenv::var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into())
+    var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into())
 }
 
-fn blocked_traffic() -> Vec<(String, Vec<String>)> {
-    let pattern_list = dotenv::var("BLOCKED_TRAFFIC").unwrap_or_default();
+fn blocked_traffic(settings: &Settings) -> Vec<(String, Vec<String>)> {
+    let pattern_list = settings.var("BLOCKED_TRAFFIC").unwrap_or_default();
     parse_traffic_patterns(&pattern_list)
         .map(|(header, value_env_var)| {
-            let value_list = dotenv::var(value_env_var).unwrap_or_default();
+            let value_list = settings.var(value_env_var).unwrap_or_default();
             let values = value_list.split(',').map(String::from).collect();
             (header.into(), values)
         })
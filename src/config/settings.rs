@@ -0,0 +1,226 @@
+//! Reading the settings of the application
+//!
+//! Settings are read from environment variables (including the ones of the `.env` file). The
+//! variables which aren't set can also be read from a TOML file given by `CRATES_IO_CONFIG`,
+//! whose keys are the names of the variables:
+//!
+//! ```toml
+//! DATABASE_URL = "postgres://postgres@localhost/cargo_registry"
+//! DB_POOL_SIZE = 20
+//! MIRROR = true
+//! ```
+//!
+//! Settings which are neither in the environment nor in the file use their default value.
+
+use std::collections::HashMap;
+use std::env::VarError;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use url::Url;
+
+/// Errors in the settings, reported together when the application starts.
+#[derive(Debug)]
+pub struct ConfigError {
+    errors: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for error in &self.errors {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Returns a variable of the environment, or of the file given by `CRATES_IO_CONFIG` if it isn't
+/// set, like `std::env::var`.
+///
+/// The file is read on every call, so settings read together should go through `Settings`.
+pub fn var(name: &str) -> Result<String, VarError> {
+    dotenv::var(name).or_else(|_| {
+        read_config_file()
+            .ok()
+            .and_then(|mut file| file.remove(name))
+            .ok_or(VarError::NotPresent)
+    })
+}
+
+/// Checks that the file given by `CRATES_IO_CONFIG` is valid, for the commands which only read
+/// their settings with `var`, whose errors are ignored.
+pub fn check_config_file() -> Result<(), ConfigError> {
+    read_config_file().map(|_| ())
+}
+
+/// Reads the variables of the file given by `CRATES_IO_CONFIG`, if it is set.
+fn read_config_file() -> Result<HashMap<String, String>, ConfigError> {
+    let path = match dotenv::var("CRATES_IO_CONFIG") {
+        Ok(path) => path,
+        Err(_) => return Ok(HashMap::new()),
+    };
+    let error = |message: String| ConfigError {
+        errors: vec![message],
+    };
+
+    let contents = fs::read_to_string(&path).map_err(|e| {
+        error(format!(
+            "`CRATES_IO_CONFIG`: couldn't read `{}`: {}",
+            path, e
+        ))
+    })?;
+    let table = contents.parse::<toml::Value>().map_err(|e| {
+        error(format!(
+            "`CRATES_IO_CONFIG`: invalid TOML in `{}`: {}",
+            path, e
+        ))
+    })?;
+    let table = table
+        .as_table()
+        .ok_or_else(|| error(format!("`CRATES_IO_CONFIG`: `{}` isn't a table", path)))?;
+
+    let mut variables = HashMap::new();
+    let mut errors = Vec::new();
+    for (name, value) in table {
+        let value = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            // Flags are enabled by being set
+            toml::Value::Boolean(true) => String::from("1"),
+            toml::Value::Boolean(false) => continue,
+            _ => {
+                errors.push(format!(
+                    "`{}` in `{}` must be a string, a number or a boolean",
+                    name, path
+                ));
+                continue;
+            }
+        };
+        variables.insert(name.clone(), value);
+    }
+
+    if errors.is_empty() {
+        Ok(variables)
+    } else {
+        Err(ConfigError { errors })
+    }
+}
+
+/// Reads settings, collecting all the errors instead of stopping at the first one.
+#[derive(Debug)]
+pub struct Settings {
+    /// The variables of the file given by `CRATES_IO_CONFIG`
+    file: HashMap<String, String>,
+    errors: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Settings {
+    /// Reads the file given by `CRATES_IO_CONFIG`, whose errors are reported by `finish`.
+    pub fn new() -> Self {
+        let (file, errors) = match read_config_file() {
+            Ok(file) => (file, Vec::new()),
+            Err(error) => (HashMap::new(), error.errors),
+        };
+        Settings { file, errors }
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        dotenv::var(name)
+            .ok()
+            .or_else(|| self.file.get(name).cloned())
+    }
+
+    /// Returns whether a flag is set, whatever its value.
+    pub fn flag(&self, name: &str) -> bool {
+        self.var(name).is_some()
+    }
+
+    /// Returns a setting which has no default value.
+    pub fn required(&mut self, name: &str) -> String {
+        self.var(name).unwrap_or_else(|| {
+            self.errors.push(format!("`{}` must be set", name));
+            String::new()
+        })
+    }
+
+    /// Returns a URL which has no default value.
+    pub fn required_url(&mut self, name: &str) -> String {
+        let value = self.required(name);
+        if let Err(e) = Url::parse(&value) {
+            if !value.is_empty() {
+                self.error(
+                    name,
+                    &format_args!("is not a valid URL ({}): `{}`", e, value),
+                );
+            }
+        }
+        value
+    }
+
+    /// Parses a setting, if it is set.
+    pub fn parse<T>(&mut self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.var(name)?;
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                self.error(name, &format_args!("is invalid ({}): `{}`", e, value));
+                None
+            }
+        }
+    }
+
+    /// Records an error about a setting.
+    pub fn error(&mut self, name: &str, message: &dyn fmt::Display) {
+        self.errors.push(format!("`{}` {}", name, message));
+    }
+
+    pub fn finish(self) -> Result<(), ConfigError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError {
+                errors: self.errors,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_collected() {
+        std::env::set_var("SETTINGS_TEST_NUMBER", "12");
+        std::env::set_var("SETTINGS_TEST_INVALID_NUMBER", "twelve");
+
+        let mut settings = Settings::new();
+        assert_eq!(settings.parse::<u32>("SETTINGS_TEST_NUMBER"), Some(12));
+        assert_eq!(settings.parse::<u32>("SETTINGS_TEST_INVALID_NUMBER"), None);
+        assert_eq!(settings.parse::<u32>("SETTINGS_TEST_MISSING_NUMBER"), None);
+        assert_eq!(settings.required("SETTINGS_TEST_MISSING"), "");
+
+        let error = settings.finish().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "invalid configuration:\n  \
+             - `SETTINGS_TEST_INVALID_NUMBER` is invalid (invalid digit found in string): `twelve`\n  \
+             - `SETTINGS_TEST_MISSING` must be set"
+        );
+    }
+}
@@ -86,8 +86,15 @@ mod util;
 
 pub mod category;
 pub mod crate_owner_invitation;
+pub mod email_domain_rule;
+pub mod feature_flag;
 pub mod keyword;
 pub mod krate;
+pub mod quarantine;
+pub mod readme;
+pub mod readme_rerender;
+pub mod registry_event;
+pub mod reserved_crate_name;
 pub mod site_metadata;
 pub mod team;
 pub mod token;
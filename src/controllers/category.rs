@@ -1,12 +1,20 @@
 use super::helpers::pagination::*;
+use super::helpers::{cached_listing, listing_index_path};
 use super::prelude::*;
 
-use crate::models::Category;
+use crate::cache::CATEGORIES_PREFIX;
+
+use crate::models::{Category, CategoryStat};
 use crate::schema::categories;
-use crate::views::{EncodableCategory, EncodableCategoryWithSubcategories};
+use crate::views::{EncodableCategory, EncodableCategoryWithSubcategories, EncodableUsageStat};
 
 /// Handles the `GET /categories` route.
 pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    let path = listing_index_path(&req.query());
+    cached_listing(req, CATEGORIES_PREFIX, path, index_uncached)
+}
+
+fn index_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_conn()?;
     let query = req.query();
     // FIXME: There are 69 categories, 47 top level. This isn't going to
@@ -41,6 +49,11 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
 
 /// Handles the `GET /categories/:category_id` route.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let path = format!("show/{}", req.params()["category_id"].to_lowercase());
+    cached_listing(req, CATEGORIES_PREFIX, Some(path), show_uncached)
+}
+
+fn show_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     let slug = &req.params()["category_id"];
     let conn = req.db_conn()?;
     let cat: Category = Category::by_slug(slug).first(&*conn)?;
@@ -76,6 +89,31 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     }))
 }
 
+/// Handles the `GET /categories/:category_id/stats` route.
+///
+/// Returns the number of crates and the downloads of the category per day, over the last 90
+/// days. The crates of its subcategories aren't included.
+pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
+    let path = format!("stats/{}", req.params()["category_id"].to_lowercase());
+    cached_listing(req, CATEGORIES_PREFIX, Some(path), stats_uncached)
+}
+
+fn stats_uncached(req: &mut dyn RequestExt) -> EndpointResult {
+    let slug = &req.params()["category_id"];
+    let conn = req.db_conn()?;
+    let cat: Category = Category::by_slug(slug).first(&*conn)?;
+    let stats = CategoryStat::trend(&conn, &cat)?
+        .into_iter()
+        .map(CategoryStat::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        stats: Vec<EncodableUsageStat>,
+    }
+    Ok(req.json(&R { stats }))
+}
+
 /// Handles the `GET /category_slugs` route.
 pub fn slugs(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_conn()?;
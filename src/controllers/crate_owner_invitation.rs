@@ -1,7 +1,9 @@
 use super::frontend_prelude::*;
 
-use crate::models::{CrateOwner, CrateOwnerInvitation, OwnerKind};
-use crate::schema::{crate_owner_invitations, crate_owners};
+use crate::models::{
+    CrateOwner, CrateOwnerInvitation, Owner, OwnerKind, RegistryEvent, RegistryEventKind, User,
+};
+use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::views::{EncodableCrateOwnerInvitation, InvitationResponse};
 
 /// Handles the `GET /me/crate_owner_invitations` route.
@@ -99,6 +101,18 @@ fn accept_invite(
         delete(crate_owner_invitations::table.find((user_id, crate_invite.crate_id)))
             .execute(conn)?;
 
+        let crate_name: String = crates::table
+            .find(crate_invite.crate_id)
+            .select(crates::name)
+            .first(conn)?;
+        let user = User::find(conn, user_id)?;
+        RegistryEvent::record_owner(
+            conn,
+            RegistryEventKind::OwnerAdd,
+            &crate_name,
+            &Owner::User(user),
+        )?;
+
         #[derive(Serialize)]
         struct R {
             crate_owner_invitation: InvitationResponse,
@@ -0,0 +1,114 @@
+//! Endpoints for the admins of the instance to block or allow email domains
+//!
+//! See the `email_blocklist` module for how the rules are applied.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use super::frontend_prelude::*;
+use super::helpers::require_admin;
+
+use crate::email_blocklist::{normalize_domain, EmailDomainRule};
+use crate::schema::users;
+use crate::views::EncodableEmailDomainRule;
+
+/// Handles the `GET /admin/email_domain_rules` route.
+///
+/// Only lists the rules of the admins, not the bundled list of disposable email providers.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    let rules = EmailDomainRule::all(&conn)?;
+    let rules = encodable_rules(&conn, rules)?;
+
+    #[derive(Serialize)]
+    struct R {
+        email_domain_rules: Vec<EncodableEmailDomainRule>,
+    }
+    Ok(req.json(&R {
+        email_domain_rules: rules,
+    }))
+}
+
+/// Handles the `PUT /admin/email_domain_rules/:domain` route.
+///
+/// Expects a body of the form `{"blocked": true}`.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct Request {
+        blocked: bool,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let domain = domain_param(req)?;
+    let conn = req.db_conn()?;
+    let rule = EmailDomainRule::set(&conn, &domain, request.blocked, user.id)?;
+    let rule = encodable_rules(&conn, vec![rule])?.remove(0);
+
+    #[derive(Serialize)]
+    struct R {
+        email_domain_rule: EncodableEmailDomainRule,
+    }
+    Ok(req.json(&R {
+        email_domain_rule: rule,
+    }))
+}
+
+/// Handles the `DELETE /admin/email_domain_rules/:domain` route.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let domain = domain_param(req)?;
+    let conn = req.db_conn()?;
+    if EmailDomainRule::delete(&conn, &domain)? == 0 {
+        return Err(bad_request(&format_args!(
+            "there is no rule for the domain `{}`",
+            domain
+        )));
+    }
+    ok_true()
+}
+
+fn domain_param(req: &dyn RequestExt) -> AppResult<String> {
+    let domain = &req.params()["domain"];
+    normalize_domain(domain)
+        .ok_or_else(|| bad_request(&format_args!("invalid domain `{}`", domain)))
+}
+
+fn encodable_rules(
+    conn: &PgConnection,
+    rules: Vec<EmailDomainRule>,
+) -> AppResult<Vec<EncodableEmailDomainRule>> {
+    let admin_ids = rules
+        .iter()
+        .filter_map(|rule| rule.updated_by)
+        .collect::<Vec<_>>();
+    let admin_logins = users::table
+        .filter(users::id.eq_any(admin_ids))
+        .select((users::id, users::gh_login))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    Ok(rules
+        .into_iter()
+        .map(|rule| EncodableEmailDomainRule {
+            updated_by: rule
+                .updated_by
+                .and_then(|id| admin_logins.get(&id).cloned()),
+            domain: rule.domain,
+            blocked: rule.blocked,
+            updated_at: rule.updated_at,
+        })
+        .collect())
+}
@@ -0,0 +1,99 @@
+//! Endpoints for the admins of the instance to toggle the runtime feature flags
+//!
+//! See the `feature_flags` module for how the flags are read.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use super::frontend_prelude::*;
+use super::helpers::require_admin;
+
+use crate::feature_flags::{FeatureFlag, FeatureFlagState};
+use crate::schema::users;
+use crate::views::EncodableFeatureFlag;
+
+/// Handles the `GET /admin/feature_flags` route.
+///
+/// Lists all the known flags, including the ones which were never toggled.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    let flags = encodable_flags(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        feature_flags: Vec<EncodableFeatureFlag>,
+    }
+    Ok(req.json(&R {
+        feature_flags: flags,
+    }))
+}
+
+/// Handles the `PUT /admin/feature_flags/:name` route.
+///
+/// Expects a body of the form `{"enabled": true}`.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct Request {
+        enabled: bool,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let name = &req.params()["name"];
+    let flag = FeatureFlag::from_name(name)
+        .ok_or_else(|| bad_request(&format_args!("unknown feature flag `{}`", name)))?;
+
+    let conn = req.db_conn()?;
+    FeatureFlagState::set(&conn, flag, request.enabled, user.id)?;
+    req.app().feature_flags.invalidate();
+
+    let flag = encodable_flags(&conn)?
+        .into_iter()
+        .find(|encodable| encodable.name == flag.name())
+        .expect("the flag was just toggled");
+
+    #[derive(Serialize)]
+    struct R {
+        feature_flag: EncodableFeatureFlag,
+    }
+    Ok(req.json(&R { feature_flag: flag }))
+}
+
+fn encodable_flags(conn: &PgConnection) -> AppResult<Vec<EncodableFeatureFlag>> {
+    let states = FeatureFlagState::all(conn)?;
+    let admin_ids = states
+        .iter()
+        .filter_map(|state| state.updated_by)
+        .collect::<Vec<_>>();
+    let admin_logins = users::table
+        .filter(users::id.eq_any(admin_ids))
+        .select((users::id, users::gh_login))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    Ok(FeatureFlag::ALL
+        .iter()
+        .map(|&flag| {
+            let state = states.iter().find(|state| state.name == flag.name());
+            EncodableFeatureFlag {
+                name: flag.name().into(),
+                description: flag.description().into(),
+                enabled: state.map_or(false, |state| state.enabled),
+                updated_by: state
+                    .and_then(|state| state.updated_by)
+                    .and_then(|id| admin_logins.get(&id).cloned()),
+                updated_at: state.map(|state| state.updated_at),
+            }
+        })
+        .collect())
+}
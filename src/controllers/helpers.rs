@@ -1,7 +1,13 @@
+use crate::models::User;
+use crate::util::errors::{forbidden, AppResult};
 use crate::util::{json_response, EndpointResult};
 
+pub(crate) mod cache;
+pub(crate) mod etag;
 pub(crate) mod pagination;
 
+pub(crate) use self::cache::{cached, cached_listing, listing_index_path};
+pub(crate) use self::etag::{etag, with_etag};
 pub(crate) use self::pagination::Paginate;
 
 pub fn ok_true() -> EndpointResult {
@@ -12,3 +18,12 @@ pub fn ok_true() -> EndpointResult {
 
     Ok(json_response(&R { ok: true }))
 }
+
+/// Returns an error unless the user is an admin of the instance.
+pub(crate) fn require_admin(user: &User) -> AppResult<()> {
+    if user.is_admin {
+        Ok(())
+    } else {
+        Err(forbidden())
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use conduit::{Body, RequestExt, StatusCode};
+use indexmap::IndexMap;
+
+use super::pagination::{Page, PaginationOptions};
+use crate::cache::{listing_key, log_error};
+use crate::middleware::app::RequestApp;
+use crate::util::{json_bytes_response, EndpointResult};
+
+/// The last page of the keyword and category indexes whose responses are cached
+const MAX_CACHED_PAGE: u32 = 10;
+
+/// Returns the cached response of an endpoint, or calls the endpoint and caches its response
+/// for the TTL if it succeeded.
+///
+/// The endpoint is always called when the cache is disabled or fails, see the `cache` module.
+pub(crate) fn cached(
+    req: &mut dyn RequestExt,
+    key: &str,
+    ttl: Duration,
+    endpoint: fn(&mut dyn RequestExt) -> EndpointResult,
+) -> EndpointResult {
+    let app = Arc::clone(req.app());
+    let cache = match app.cache.as_deref() {
+        Some(cache) => cache,
+        None => return endpoint(req),
+    };
+
+    match cache.get(key) {
+        Ok(Some(json)) => return Ok(json_bytes_response(json)),
+        result => log_error(result),
+    }
+
+    let response = endpoint(req)?;
+    if response.status() == StatusCode::OK {
+        if let Body::Owned(json) = response.body() {
+            log_error(cache.set(key, json, ttl));
+        }
+    }
+    Ok(response)
+}
+
+/// Like `cached`, for the keyword and category responses whose keys start with the prefix and
+/// end with the path, see `cache::listing_key`. The endpoint isn't cached without a path.
+pub(crate) fn cached_listing(
+    req: &mut dyn RequestExt,
+    prefix: &str,
+    path: Option<String>,
+    endpoint: fn(&mut dyn RequestExt) -> EndpointResult,
+) -> EndpointResult {
+    let app = Arc::clone(req.app());
+    let (cache, path) = match (app.cache.as_deref(), path) {
+        (Some(cache), Some(path)) => (cache, path),
+        _ => return endpoint(req),
+    };
+
+    match listing_key(cache, prefix, &path) {
+        Ok(key) => cached(req, &key, app.config.cache.listing_ttl, endpoint),
+        Err(error) => {
+            log_error::<()>(Err(error));
+            endpoint(req)
+        }
+    }
+}
+
+/// Returns the path of a cached keyword or category index, such as
+/// `index?sort=alpha&page=1&per_page=10`.
+///
+/// The parameters the endpoints ignore are left out, so that they don't add keys to the cache,
+/// and pages after `MAX_CACHED_PAGE` or invalid parameters aren't cached.
+pub(crate) fn listing_index_path(query: &IndexMap<String, String>) -> Option<String> {
+    let sort = match query.get("sort").map(String::as_str) {
+        Some("crates") => "crates",
+        _ => "alpha",
+    };
+    let options = PaginationOptions::new(query).ok()?;
+    let page = match options.page {
+        Page::Numeric(page) if page <= MAX_CACHED_PAGE => page,
+        Page::Unspecified => 1,
+        _ => return None,
+    };
+    Some(format!(
+        "index?sort={}&page={}&per_page={}",
+        sort, page, options.per_page
+    ))
+}
@@ -0,0 +1,48 @@
+use conduit::{header, Body, RequestExt, Response, StatusCode};
+use sha2::{Digest, Sha256};
+
+use crate::util::errors::internal;
+use crate::util::EndpointResult;
+
+/// Returns a strong `ETag` derived from a key, which has to change whenever the response does.
+pub(crate) fn etag(key: &str) -> String {
+    format!("\"{}\"", hex::encode(Sha256::digest(key.as_bytes())))
+}
+
+/// Answers `304 Not Modified` without calling the endpoint if the `If-None-Match` header of the
+/// request matches the `ETag`, or calls the endpoint and adds the `ETag` to its response.
+///
+/// Unlike the `ConditionalGet` middleware, which compares the `ETag` once the response is built,
+/// this spares the queries of the endpoint when the `ETag` can be derived cheaply.
+pub(crate) fn with_etag(
+    req: &mut dyn RequestExt,
+    etag: &str,
+    endpoint: fn(&mut dyn RequestExt) -> EndpointResult,
+) -> EndpointResult {
+    let value = header::HeaderValue::from_str(etag).map_err(|e| internal(&e))?;
+    if if_none_match(req, etag) {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, value)
+            .body(Body::empty())
+            .unwrap()); // The `ETag` was checked above, so should not panic
+    }
+
+    let mut response = endpoint(req)?;
+    if response.status() == StatusCode::OK {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    Ok(response)
+}
+
+/// Returns whether one of the entity tags of the `If-None-Match` header of the request matches
+/// the `ETag`, using the weak comparison of RFC 7232.
+fn if_none_match(req: &dyn RequestExt, etag: &str) -> bool {
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
@@ -6,16 +6,25 @@ use diesel::query_builder::*;
 use diesel::query_dsl::LoadQuery;
 use diesel::sql_types::BigInt;
 use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub(crate) enum Page {
     Numeric(u32),
+    /// The opaque cursor of the `seek` parameter, after which the next page starts
+    Seek(String),
     Unspecified,
 }
 
 impl Page {
-    fn new(params: &IndexMap<String, String>) -> AppResult<Self> {
-        if let Some(s) = params.get("page") {
+    fn new(params: &IndexMap<String, String>, seek_enabled: bool) -> AppResult<Self> {
+        if let Some(cursor) = params.get("seek").filter(|_| seek_enabled) {
+            if params.contains_key("page") {
+                return Err(bad_request("cannot combine the page and seek parameters"));
+            }
+            Ok(Page::Seek(cursor.clone()))
+        } else if let Some(s) = params.get("page") {
             let numeric_page = s.parse().map_err(|e| bad_request(&e))?;
             if numeric_page < 1 {
                 return Err(bad_request(&format_args!(
@@ -31,14 +40,24 @@ impl Page {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub(crate) struct PaginationOptions {
-    page: Page,
+    pub(crate) page: Page,
     pub(crate) per_page: u32,
 }
 
 impl PaginationOptions {
     pub(crate) fn new(params: &IndexMap<String, String>) -> AppResult<Self> {
+        Self::gather(params, false)
+    }
+
+    /// Reads the options of an endpoint which also supports the cursors of the `seek`
+    /// parameter, see `encode_seek`.
+    pub(crate) fn with_seek(params: &IndexMap<String, String>) -> AppResult<Self> {
+        Self::gather(params, true)
+    }
+
+    fn gather(params: &IndexMap<String, String>, seek_enabled: bool) -> AppResult<Self> {
         const DEFAULT_PER_PAGE: u32 = 10;
         const MAX_PER_PAGE: u32 = 100;
 
@@ -55,7 +74,7 @@ impl PaginationOptions {
         }
 
         Ok(Self {
-            page: Page::new(params)?,
+            page: Page::new(params, seek_enabled)?,
             per_page,
         })
     }
@@ -69,6 +88,21 @@ impl PaginationOptions {
     }
 }
 
+/// Encodes the key of the last record of a page into the opaque cursor of the `seek` parameter
+/// of the next one.
+pub(crate) fn encode_seek<S: Serialize>(key: &S) -> String {
+    let json = serde_json::to_vec(key).expect("seek keys are serializable");
+    base64::encode_config(&json, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes the key of a cursor encoded by `encode_seek`.
+pub(crate) fn decode_seek<D: DeserializeOwned>(cursor: &str) -> AppResult<D> {
+    base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| bad_request("invalid seek parameter"))
+}
+
 pub(crate) trait Paginate: Sized {
     fn paginate(self, params: &IndexMap<String, String>) -> AppResult<PaginatedQuery<Self>> {
         Ok(PaginatedQuery {
@@ -103,20 +137,20 @@ impl<T> Paginated<T> {
         let mut opts = IndexMap::new();
         match self.options.page {
             Page::Numeric(n) => opts.insert("page".into(), (n + 1).to_string()),
-            Page::Unspecified => opts.insert("page".into(), 2.to_string()),
+            Page::Unspecified | Page::Seek(_) => opts.insert("page".into(), 2.to_string()),
         };
         Some(opts)
     }
 
     pub(crate) fn prev_page_params(&self) -> Option<IndexMap<String, String>> {
-        if let Page::Numeric(1) | Page::Unspecified = self.options.page {
+        if let Page::Numeric(1) | Page::Unspecified | Page::Seek(_) = self.options.page {
             return None;
         }
 
         let mut opts = IndexMap::new();
         match self.options.page {
             Page::Numeric(n) => opts.insert("page".into(), (n - 1).to_string()),
-            Page::Unspecified => unreachable!(),
+            Page::Unspecified | Page::Seek(_) => unreachable!(),
         };
         Some(opts)
     }
@@ -146,7 +180,7 @@ impl<T> PaginatedQuery<T> {
     where
         Self: LoadQuery<PgConnection, WithCount<U>>,
     {
-        let options = self.options;
+        let options = self.options.clone();
         let records_and_total = self.internal_load(conn)?;
         Ok(Paginated {
             records_and_total,
@@ -185,7 +219,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Page, PaginationOptions};
+    use super::{decode_seek, encode_seek, Page, PaginationOptions};
 
     use conduit::StatusCode;
     use indexmap::IndexMap;
@@ -199,6 +233,33 @@ mod tests {
         assert_eq!(page_error.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn seek_is_only_read_when_enabled() {
+        let mut params = IndexMap::new();
+        params.insert(String::from("seek"), encode_seek(&"serde"));
+        match PaginationOptions::with_seek(&params).unwrap().page {
+            Page::Seek(cursor) => assert_eq!(decode_seek::<String>(&cursor).unwrap(), "serde"),
+            page => panic!("unexpected page {:?}", page),
+        }
+        assert!(matches!(
+            PaginationOptions::new(&params).unwrap().page,
+            Page::Unspecified
+        ));
+
+        params.insert(String::from("page"), String::from("2"));
+        let error = PaginationOptions::with_seek(&params)
+            .unwrap_err()
+            .response()
+            .unwrap();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn seek_cursors_must_be_valid() {
+        assert!(decode_seek::<String>("not a cursor").is_err());
+        assert!(decode_seek::<i32>(&encode_seek(&"serde")).is_err());
+    }
+
     #[test]
     fn per_page_must_be_a_number() {
         let mut params = IndexMap::new();
@@ -1,11 +1,19 @@
 use super::prelude::*;
 
-use crate::controllers::helpers::{pagination::Paginated, Paginate};
-use crate::models::Keyword;
-use crate::views::EncodableKeyword;
+use crate::cache::KEYWORDS_PREFIX;
+use crate::controllers::helpers::{
+    cached_listing, listing_index_path, pagination::Paginated, Paginate,
+};
+use crate::models::{Keyword, KeywordStat};
+use crate::views::{EncodableKeyword, EncodableUsageStat};
 
 /// Handles the `GET /keywords` route.
 pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    let path = listing_index_path(&req.query());
+    cached_listing(req, KEYWORDS_PREFIX, path, index_uncached)
+}
+
+fn index_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     use crate::schema::keywords;
 
     let conn = req.db_conn()?;
@@ -42,6 +50,11 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
 
 /// Handles the `GET /keywords/:keyword_id` route.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let path = format!("show/{}", req.params()["keyword_id"].to_lowercase());
+    cached_listing(req, KEYWORDS_PREFIX, Some(path), show_uncached)
+}
+
+fn show_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     let name = &req.params()["keyword_id"];
     let conn = req.db_conn()?;
 
@@ -55,3 +68,28 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
         keyword: kw.encodable(),
     }))
 }
+
+/// Handles the `GET /keywords/:keyword_id/stats` route.
+///
+/// Returns the number of crates and the downloads of the keyword per day, over the last 90 days.
+pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
+    let path = format!("stats/{}", req.params()["keyword_id"].to_lowercase());
+    cached_listing(req, KEYWORDS_PREFIX, Some(path), stats_uncached)
+}
+
+fn stats_uncached(req: &mut dyn RequestExt) -> EndpointResult {
+    let name = &req.params()["keyword_id"];
+    let conn = req.db_conn()?;
+
+    let kw = Keyword::find_by_keyword(&conn, name)?;
+    let stats = KeywordStat::trend(&conn, &kw)?
+        .into_iter()
+        .map(KeywordStat::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        stats: Vec<EncodableUsageStat>,
+    }
+    Ok(req.json(&R { stats }))
+}
@@ -1,6 +1,14 @@
+pub mod audit;
+pub mod batch;
+pub mod compare;
+pub mod deprecation;
 pub mod downloads;
 pub mod follow;
 pub mod metadata;
 pub mod owners;
+pub mod pageviews;
 pub mod publish;
+pub mod publish_policy;
 pub mod search;
+pub mod similar;
+pub mod transfer;
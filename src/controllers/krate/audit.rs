@@ -0,0 +1,154 @@
+//! Endpoint for auditing the crates of a lockfile in bulk
+//!
+//! Tools like `cargo audit` need to check hundreds of dependencies at once. The
+//! metadata of all of them is loaded with a handful of queries, regardless of
+//! the number of entries.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::krate::canon_crate_name;
+use crate::models::{Badge, Crate, CrateAdvisory, CrateBadge, MaintenanceStatus, Version};
+use crate::schema::*;
+use crate::util::LimitErrorReader;
+use crate::views::EncodableAuditEntry;
+
+/// The maximum number of entries that can be audited in one request.
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// The maximum size of the body of a request, which leaves about 256 bytes for the name and
+/// the version of each of the `MAX_AUDIT_ENTRIES` entries.
+const MAX_AUDIT_BODY_SIZE: u64 = 256 * 1024;
+
+#[derive(Deserialize)]
+struct AuditRequest {
+    crates: Vec<AuditRequestEntry>,
+}
+
+#[derive(Deserialize)]
+struct AuditRequestEntry {
+    name: String,
+    version: String,
+}
+
+/// Handles the `POST /crates/audit` route.
+///
+/// Returns, for each requested crate version, whether it is yanked, the security
+/// advisories affecting it, the latest version of the crate and whether the crate
+/// is deprecated. Entries are returned in the order they were requested.
+///
+/// The latest version is the highest one which is neither yanked nor a prerelease, like the
+/// `LATEST_VERSION` of searches.
+pub fn audit(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let mut body = String::new();
+    LimitErrorReader::new(req.body(), MAX_AUDIT_BODY_SIZE)
+        .read_to_string(&mut body)
+        .map_err(|_| bad_request("the request is too large to be audited"))?;
+    let request: AuditRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if request.crates.len() > MAX_AUDIT_ENTRIES {
+        return Err(bad_request(&format!(
+            "at most {} crates can be audited at once",
+            MAX_AUDIT_ENTRIES
+        )));
+    }
+
+    let canon_name = |name: &str| name.to_lowercase().replace('-', "_");
+    let canon_names = request
+        .crates
+        .iter()
+        .map(|entry| canon_name(&entry.name))
+        .collect::<Vec<_>>();
+
+    let conn = req.db_read_only()?;
+    let crates: Vec<Crate> = Crate::all()
+        .filter(canon_crate_name(crates::name).eq(any(&canon_names)))
+        .load(&*conn)?;
+
+    let versions: Vec<Version> = Version::belonging_to(&crates).load(&*conn)?;
+    let versions = versions.grouped_by(&crates);
+
+    let badges: Vec<CrateBadge> = CrateBadge::belonging_to(&crates)
+        .select((badges::crate_id, badges::all_columns))
+        .load(&*conn)?;
+    let badges = badges.grouped_by(&crates);
+
+    let advisories: Vec<CrateAdvisory> = CrateAdvisory::belonging_to(&crates).load(&*conn)?;
+    let advisories = advisories.grouped_by(&crates);
+
+    let crates = crates
+        .into_iter()
+        .zip(versions)
+        .zip(badges)
+        .zip(advisories)
+        .map(|(((krate, versions), badges), advisories)| {
+            (canon_name(&krate.name), (versions, badges, advisories))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let crates = request
+        .crates
+        .into_iter()
+        .zip(canon_names)
+        .map(|(entry, canon_name)| audit_entry(entry, crates.get(&canon_name)))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableAuditEntry>,
+    }
+    Ok(req.json(&R { crates }))
+}
+
+/// Audits one requested crate version, given the metadata of the crate if it exists.
+fn audit_entry(
+    entry: AuditRequestEntry,
+    krate: Option<&(Vec<Version>, Vec<CrateBadge>, Vec<CrateAdvisory>)>,
+) -> EncodableAuditEntry {
+    let (versions, badges, advisories) = match krate {
+        Some((versions, badges, advisories)) => (&versions[..], &badges[..], &advisories[..]),
+        None => (&[][..], &[][..], &[][..]),
+    };
+
+    let semver = semver::Version::parse(&entry.version).ok();
+    let yanked = semver
+        .as_ref()
+        .and_then(|semver| versions.iter().find(|v| v.num == *semver))
+        .map(|v| v.yanked);
+    let latest_version = versions
+        .iter()
+        .filter(|v| !v.yanked && !v.num.is_prerelease())
+        .map(|v| &v.num)
+        .max()
+        .map(|num| num.to_string());
+    let deprecated = badges.iter().any(|cb| {
+        matches!(
+            cb.badge,
+            Badge::Maintenance {
+                status: MaintenanceStatus::Deprecated
+            }
+        )
+    });
+    let advisories = match &semver {
+        Some(semver) => advisories
+            .iter()
+            .filter(|advisory| advisory.affects(semver))
+            .cloned()
+            .map(CrateAdvisory::encodable)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    EncodableAuditEntry {
+        name: entry.name,
+        version: entry.version,
+        yanked,
+        latest_version,
+        deprecated,
+        advisories,
+    }
+}
@@ -0,0 +1,82 @@
+//! Endpoint for fetching the metadata of many crates at once
+//!
+//! Tools resolving lockfiles, like license checkers or dashboards, need the
+//! summary of every dependency. They are loaded with a handful of queries,
+//! regardless of the number of crates.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::controllers::krate::metadata::encode_crates;
+use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
+use crate::models::Crate;
+use crate::schema::*;
+use crate::views::EncodableCrate;
+
+/// The maximum number of crates that can be fetched in one request.
+const MAX_BATCH_CRATES: usize = 100;
+
+#[derive(Deserialize)]
+struct BatchRequest {
+    crates: Vec<String>,
+}
+
+/// Handles the `POST /crates/batch` route.
+///
+/// Returns the summary of each requested crate, as shown in crate listings, in the order they
+/// were first requested. The names of the crates which don't exist are listed in `missing`.
+pub fn batch(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let mut request: BatchRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if request.crates.len() > MAX_BATCH_CRATES {
+        return Err(bad_request(&format!(
+            "at most {} crates can be fetched at once",
+            MAX_BATCH_CRATES
+        )));
+    }
+
+    let canon_name = |name: &str| name.to_lowercase().replace('-', "_");
+    let mut seen = HashSet::new();
+    request.crates.retain(|name| seen.insert(canon_name(name)));
+    let canon_names = request
+        .crates
+        .iter()
+        .map(|name| canon_name(name))
+        .collect::<Vec<_>>();
+
+    let conn = req.db_read_only()?;
+    let data: Vec<(Crate, Option<i64>)> = crates::table
+        .left_join(recent_crate_downloads::table)
+        .filter(canon_crate_name(crates::name).eq(any(&canon_names)))
+        .select((ALL_COLUMNS, recent_crate_downloads::downloads.nullable()))
+        .load(&*conn)?;
+    let mut crates = encode_crates(&conn, data)?
+        .into_iter()
+        .map(|krate| (canon_name(&krate.name), krate))
+        .collect::<HashMap<_, _>>();
+
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for (name, canon_name) in request.crates.into_iter().zip(canon_names) {
+        match crates.remove(&canon_name) {
+            Some(krate) => found.push(krate),
+            None => missing.push(name),
+        }
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+        missing: Vec<String>,
+    }
+    Ok(req.json(&R {
+        crates: found,
+        missing,
+    }))
+}
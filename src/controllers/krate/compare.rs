@@ -0,0 +1,167 @@
+//! Endpoint for comparing crates side by side
+//!
+//! Tools evaluating alternatives for a dependency need the same handful of
+//! metrics for each candidate, which are loaded here with one query per metric
+//! rather than one request per crate.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
+use crate::models::{Crate, Dependency, DependencyKind, OwnerKind, Version};
+use crate::schema::*;
+use crate::views::{EncodableComparedCrate, EncodableDependencyCounts};
+
+/// The maximum number of crates that can be compared in one request.
+const MAX_COMPARED_CRATES: usize = 10;
+
+/// Handles the `GET /crate_comparison` route.
+///
+/// The crates are given as a comma-separated `?crates=` list and are returned in that
+/// order. The license, MSRV and dependencies compared are the ones of the latest version of
+/// each crate, see `latest_version`.
+pub fn compare(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::{any, sql};
+    use diesel::sql_types::{Array, Text};
+
+    let names = req
+        .query()
+        .get("crates")
+        .map(|names| {
+            names
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if names.is_empty() {
+        return Err(bad_request("the `crates` to compare are missing"));
+    }
+
+    // The requested names are normalized by `canon_crate_name` as well, in their order
+    let conn = req.db_read_only()?;
+    let canon_names = diesel::select(
+        sql::<Array<Text>>("ARRAY(SELECT canon_crate_name(name) FROM unnest(")
+            .bind::<Array<Text>, _>(&names)
+            .sql(") WITH ORDINALITY AS names(name, position) ORDER BY position)"),
+    )
+    .get_result::<Vec<String>>(&*conn)?;
+    let mut seen = HashSet::new();
+    let (names, canon_names): (Vec<_>, Vec<_>) = names
+        .into_iter()
+        .zip(canon_names)
+        .filter(|(_, canon_name)| seen.insert(canon_name.clone()))
+        .unzip();
+    if names.len() > MAX_COMPARED_CRATES {
+        return Err(bad_request(&format!(
+            "at most {} crates can be compared at once",
+            MAX_COMPARED_CRATES
+        )));
+    }
+
+    let (crates, crate_canon_names): (Vec<Crate>, Vec<String>) = crates::table
+        .filter(canon_crate_name(crates::name).eq(any(&canon_names)))
+        .select((ALL_COLUMNS, canon_crate_name(crates::name)))
+        .load::<(Crate, String)>(&*conn)?
+        .into_iter()
+        .unzip();
+    let crate_ids = crates.iter().map(|krate| krate.id).collect::<Vec<_>>();
+
+    let versions: Vec<Version> = Version::belonging_to(&crates).load(&*conn)?;
+    let versions = versions.grouped_by(&crates);
+
+    let latest_versions = versions
+        .iter()
+        .filter_map(|versions| latest_version(versions))
+        .collect::<Vec<_>>();
+    let latest_version_ids = latest_versions.iter().map(|v| v.id).collect::<Vec<_>>();
+    let dependencies: Vec<Dependency> = dependencies::table
+        .filter(dependencies::version_id.eq(any(&latest_version_ids)))
+        .load(&*conn)?;
+    let mut dependency_counts = HashMap::<i32, EncodableDependencyCounts>::new();
+    for dependency in dependencies {
+        let counts = dependency_counts.entry(dependency.version_id).or_default();
+        match dependency.kind {
+            DependencyKind::Normal => counts.normal += 1,
+            DependencyKind::Build => counts.build += 1,
+            DependencyKind::Dev => counts.dev += 1,
+        }
+    }
+
+    let owners: Vec<(i32, i32)> = crate_owners::table
+        .filter(crate_owners::crate_id.eq(any(&crate_ids)))
+        .filter(crate_owners::deleted.eq(false))
+        .select((crate_owners::crate_id, crate_owners::owner_kind))
+        .load(&*conn)?;
+
+    let recent_downloads: HashMap<i32, i64> = recent_crate_downloads::table
+        .filter(recent_crate_downloads::crate_id.eq(any(&crate_ids)))
+        .select((
+            recent_crate_downloads::crate_id,
+            recent_crate_downloads::downloads,
+        ))
+        .load::<(i32, i64)>(&*conn)?
+        .into_iter()
+        .collect();
+
+    let mut compared = crates
+        .into_iter()
+        .zip(crate_canon_names)
+        .zip(versions)
+        .map(|((krate, canon_name), versions)| {
+            let last_release_at = versions.iter().map(|v| v.created_at).max();
+            let latest_version = latest_version(&versions);
+            let owners_of = |kind: OwnerKind| {
+                owners
+                    .iter()
+                    .filter(|&&(crate_id, owner_kind)| {
+                        crate_id == krate.id && owner_kind == kind as i32
+                    })
+                    .count()
+            };
+            let entry = EncodableComparedCrate {
+                name: krate.name.clone(),
+                downloads: krate.downloads,
+                recent_downloads: recent_downloads.get(&krate.id).copied(),
+                last_release_at,
+                latest_version: latest_version.map(|v| v.num.to_string()),
+                license: latest_version.and_then(|v| v.license.clone()),
+                rust_version: latest_version.and_then(|v| v.rust_version.clone()),
+                dependencies: latest_version
+                    .and_then(|v| dependency_counts.remove(&v.id))
+                    .unwrap_or_default(),
+                user_owners: owners_of(OwnerKind::User),
+                team_owners: owners_of(OwnerKind::Team),
+            };
+            (canon_name, entry)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let crates = names
+        .iter()
+        .zip(canon_names)
+        .map(|(name, canon_name)| {
+            compared
+                .remove(&canon_name)
+                .ok_or_else(|| bad_request(&format!("crate `{}` does not exist", name)))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableComparedCrate>,
+    }
+    Ok(req.json(&R { crates }))
+}
+
+/// Returns the latest version of a crate, the highest one which is neither yanked nor a
+/// prerelease, like the `LATEST_VERSION` of searches.
+fn latest_version(versions: &[Version]) -> Option<&Version> {
+    versions
+        .iter()
+        .filter(|v| !v.yanked && !v.num.is_prerelease())
+        .max_by(|a, b| a.num.cmp(&b.num))
+}
@@ -0,0 +1,84 @@
+//! Endpoint for deprecating a crate
+//!
+//! A deprecated crate warns its users off it, optionally pointing them to the crate superseding
+//! it. The deprecation is shown with the crate and recorded in its index entries.
+
+use std::io::Read;
+
+use swirl::Job;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::cache;
+use crate::git;
+use crate::models::{Crate, Rights};
+use crate::schema::crates;
+
+/// Handles the `PUT /crates/:crate_id/deprecate` route.
+pub fn deprecate(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct Request {
+        deprecated: bool,
+        superseded_by: Option<String>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user = req.authenticate()?.user();
+    let crate_name = &req.params()["crate_id"];
+    let app = req.app();
+    let conn = req.db_conn()?;
+
+    let krate = conn.transaction(|| {
+        let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+        if user.rights(app, &conn, &krate.owners(&conn)?)? < Rights::Publish {
+            return Err(cargo_err(
+                "only owners have permission to deprecate a crate",
+            ));
+        }
+
+        let superseded_by = match &request.superseded_by {
+            Some(_) if !request.deprecated => {
+                return Err(cargo_err(
+                    "only deprecated crates can be superseded by another crate",
+                ));
+            }
+            Some(name) => {
+                let successor: Crate = Crate::by_name(name)
+                    .first(&*conn)
+                    .optional()?
+                    .ok_or_else(|| cargo_err(&format_args!("no crate named `{}`", name)))?;
+                if successor.id == krate.id {
+                    return Err(cargo_err("a crate can't supersede itself"));
+                }
+                Some(successor.name)
+            }
+            None => None,
+        };
+
+        let krate: Crate = diesel::update(&krate)
+            .set((
+                crates::deprecated.eq(request.deprecated),
+                crates::superseded_by.eq(&superseded_by),
+            ))
+            .returning(crate::models::krate::ALL_COLUMNS)
+            .get_result(&*conn)?;
+        git::deprecate(krate.name.clone(), krate.deprecated, superseded_by).enqueue(&conn)?;
+        Ok(krate)
+    })?;
+
+    cache::invalidate_crate(app.cache.as_deref(), &krate.name);
+
+    #[derive(Serialize)]
+    struct R {
+        deprecated: bool,
+        superseded_by: Option<String>,
+    }
+    Ok(req.json(&R {
+        deprecated: krate.deprecated,
+        superseded_by: krate.superseded_by,
+    }))
+}
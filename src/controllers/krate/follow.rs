@@ -44,16 +44,50 @@ pub fn unfollow(req: &mut dyn RequestExt) -> EndpointResult {
 
 /// Handles the `GET /crates/:crate_id/following` route.
 pub fn following(req: &mut dyn RequestExt) -> EndpointResult {
-    use diesel::dsl::exists;
-
     let user_id = req.authenticate()?.user_id();
     let conn = req.db_conn()?;
     let follow = follow_target(req, &conn, user_id)?;
-    let following = diesel::select(exists(follows::table.find(follow.id()))).get_result(&*conn)?;
+    let email_notifications = follows::table
+        .find(follow.id())
+        .select(follows::email_notifications)
+        .first::<bool>(&*conn)
+        .optional()?;
 
     #[derive(Serialize)]
     struct R {
         following: bool,
+        email_notifications: bool,
+    }
+    Ok(req.json(&R {
+        following: email_notifications.is_some(),
+        email_notifications: email_notifications.unwrap_or(false),
+    }))
+}
+
+/// Handles the `PUT /crates/:crate_id/follow/email_notifications` route.
+///
+/// Followers are emailed when a new version of the crate changes its license, unless they
+/// disabled the notifications here.
+pub fn update_email_notifications(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct Request {
+        email_notifications: bool,
     }
-    Ok(req.json(&R { following }))
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let follow = follow_target(req, &conn, user_id)?;
+    let updated = diesel::update(&follow)
+        .set(follows::email_notifications.eq(request.email_notifications))
+        .execute(&*conn)?;
+    if updated == 0 {
+        return Err(bad_request("the crate isn't followed"));
+    }
+
+    ok_true()
 }
@@ -4,21 +4,58 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use crate::cache::{self, SUMMARY_KEY};
 use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::{cached, etag, with_etag};
+use chrono::{NaiveDateTime, Utc};
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    User, Version, VersionOwnerAction,
+    negotiate_language, Category, Crate, CrateCategory, CrateKeyword, CrateLinkCheck,
+    CrateQualityScore, CrateVersions, Dependency, Keyword, LocalizedReadme, RecentCrateDownloads,
+    User, Version, VersionOwnerAction, VersionYankEvent,
 };
+use crate::render::ReadmeHeading;
 use crate::schema::*;
+use crate::tasks::QUALITY_FORMULA_VERSION;
+use crate::util::errors::internal;
 use crate::views::{
-    EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
+    EncodableCategory, EncodableCrate, EncodableCrateLinkCheck, EncodableCrateQualityScore,
+    EncodableDependedUponCrate, EncodableDependency, EncodableFullVersion, EncodableKeyword,
+    EncodableNotableDependent, EncodableReadmeHeading, EncodableTrendingCrate,
+    EncodableTrendingScore, EncodableVersion,
 };
 
 use crate::models::krate::ALL_COLUMNS;
 
+/// Encodes a list of crates along with their recent downloads, as shown in crate listings.
+pub(crate) fn encode_crates(
+    conn: &PgConnection,
+    data: Vec<(Crate, Option<i64>)>,
+) -> AppResult<Vec<EncodableCrate>> {
+    let recent_downloads = data.iter().map(|&(_, s)| s).collect::<Vec<_>>();
+
+    let krates = data.into_iter().map(|(c, _)| c).collect::<Vec<_>>();
+
+    let versions: Vec<Version> = krates.versions().load(conn)?;
+    versions
+        .grouped_by(&krates)
+        .into_iter()
+        .map(|versions| Version::top(versions.into_iter().map(|v| (v.created_at, v.num))))
+        .zip(krates)
+        .zip(recent_downloads)
+        .map(|((top_versions, krate), recent_downloads)| {
+            Ok(krate.minimal_encodable(&top_versions, None, false, recent_downloads))
+        })
+        .collect()
+}
+
 /// Handles the `GET /summary` route.
 pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
+    let ttl = req.app().config.cache.summary_ttl;
+    cached(req, SUMMARY_KEY, ttl, summary_uncached)
+}
+
+fn summary_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     use crate::schema::crates::dsl::*;
 
     let conn = req.db_read_only()?;
@@ -27,24 +64,6 @@ pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
         .select(metadata::total_downloads)
         .get_result(&*conn)?;
 
-    let encode_crates = |data: Vec<(Crate, Option<i64>)>| -> AppResult<Vec<_>> {
-        let recent_downloads = data.iter().map(|&(_, s)| s).collect::<Vec<_>>();
-
-        let krates = data.into_iter().map(|(c, _)| c).collect::<Vec<_>>();
-
-        let versions: Vec<Version> = krates.versions().load(&*conn)?;
-        versions
-            .grouped_by(&krates)
-            .into_iter()
-            .map(|versions| Version::top(versions.into_iter().map(|v| (v.created_at, v.num))))
-            .zip(krates)
-            .zip(recent_downloads)
-            .map(|((top_versions, krate), recent_downloads)| {
-                Ok(krate.minimal_encodable(&top_versions, None, false, recent_downloads))
-            })
-            .collect()
-    };
-
     let selection = (ALL_COLUMNS, recent_crate_downloads::downloads.nullable());
 
     let new_crates = crates
@@ -101,18 +120,264 @@ pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
     Ok(req.json(&R {
         num_downloads,
         num_crates,
-        new_crates: encode_crates(new_crates)?,
-        most_downloaded: encode_crates(most_downloaded)?,
-        most_recently_downloaded: encode_crates(most_recently_downloaded)?,
-        just_updated: encode_crates(just_updated)?,
+        new_crates: encode_crates(&conn, new_crates)?,
+        most_downloaded: encode_crates(&conn, most_downloaded)?,
+        most_recently_downloaded: encode_crates(&conn, most_recently_downloaded)?,
+        just_updated: encode_crates(&conn, just_updated)?,
         popular_keywords,
         popular_categories,
     }))
 }
 
+/// Handles the `GET /most_depended_upon` route.
+///
+/// Lists crates by the number of crates whose latest version depends on them. The counts
+/// are cached on the `crates` table so this doesn't need to scan the dependencies.
+pub fn most_depended_upon(req: &mut dyn RequestExt) -> EndpointResult {
+    use crate::controllers::helpers::pagination::Paginated;
+    use crate::controllers::helpers::Paginate;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<(Crate, i32, Option<i64>)> = crates::table
+        .left_join(recent_crate_downloads::table)
+        .filter(crates::dependents_count.gt(0))
+        .select((
+            ALL_COLUMNS,
+            crates::dependents_count,
+            recent_crate_downloads::downloads.nullable(),
+        ))
+        .order((crates::dependents_count.desc(), crates::name.asc()))
+        .paginate(&req.query())?
+        .load(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+    let (data, dependents_counts): (Vec<_>, Vec<_>) = data
+        .into_iter()
+        .map(|(krate, dependents_count, recent_downloads)| {
+            ((krate, recent_downloads), dependents_count)
+        })
+        .unzip();
+    let crates = encode_crates(&conn, data)?
+        .into_iter()
+        .zip(dependents_counts)
+        .map(|(krate, dependents_count)| EncodableDependedUponCrate {
+            krate,
+            dependents_count,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableDependedUponCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+        prev_page: Option<String>,
+    }
+    Ok(req.json(&R {
+        crates,
+        meta: Meta {
+            total,
+            next_page,
+            prev_page,
+        },
+    }))
+}
+
+/// Handles the `GET /trending_crates` route.
+///
+/// Lists crates by their trending score, which is periodically computed by the
+/// `update_trending_scores` background job.
+pub fn trending(req: &mut dyn RequestExt) -> EndpointResult {
+    use crate::controllers::helpers::pagination::Paginated;
+    use crate::controllers::helpers::Paginate;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<(Crate, (f64, i64, i64, i32), Option<i64>)> = crates::table
+        .inner_join(crate_trending_scores::table)
+        .left_join(recent_crate_downloads::table)
+        .select((
+            ALL_COLUMNS,
+            (
+                crate_trending_scores::score,
+                crate_trending_scores::recent_downloads,
+                crate_trending_scores::baseline_downloads,
+                crate_trending_scores::new_dependents,
+            ),
+            recent_crate_downloads::downloads.nullable(),
+        ))
+        .order((crate_trending_scores::score.desc(), crates::name.asc()))
+        .paginate(&req.query())?
+        .load(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+    let (data, scores): (Vec<_>, Vec<_>) = data
+        .into_iter()
+        .map(|(krate, score, recent_downloads)| ((krate, recent_downloads), score))
+        .unzip();
+    let crates = encode_crates(&conn, data)?
+        .into_iter()
+        .zip(scores)
+        .map(
+            |(krate, (score, recent_downloads, baseline_downloads, new_dependents))| {
+                EncodableTrendingCrate {
+                    krate,
+                    trending: EncodableTrendingScore {
+                        score,
+                        recent_downloads,
+                        baseline_downloads,
+                        new_dependents,
+                    },
+                }
+            },
+        )
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableTrendingCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+        prev_page: Option<String>,
+    }
+    Ok(req.json(&R {
+        crates,
+        meta: Meta {
+            total,
+            next_page,
+            prev_page,
+        },
+    }))
+}
+
+/// Returns the `ETag` of a crate and its versions, which changes whenever one of them is
+/// published, changed or downloaded, or `None` if the crate doesn't exist.
+///
+/// The versions list the profiles of their publishers and of the users who yanked or acted on
+/// them, so these profiles are part of it too.
+fn versions_etag(conn: &PgConnection, crate_name: &str) -> QueryResult<Option<String>> {
+    use diesel::dsl::{count_star, max};
+
+    let krate = crates::table
+        .filter(Crate::with_name(crate_name))
+        .select((crates::id, crates::updated_at, crates::downloads))
+        .first::<(i32, NaiveDateTime, i32)>(conn)
+        .optional()?;
+    let (crate_id, updated_at, downloads) = match krate {
+        Some(krate) => krate,
+        None => return Ok(None),
+    };
+    let (count, versions_updated_at) = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .select((count_star(), max(versions::updated_at)))
+        .first::<(i64, Option<NaiveDateTime>)>(conn)?;
+
+    let version_ids = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .select(versions::id)
+        .load::<i32>(conn)?;
+    let last_action = version_owner_actions::table
+        .filter(version_owner_actions::version_id.eq_any(&version_ids))
+        .select(max(version_owner_actions::id))
+        .first::<Option<i32>>(conn)?;
+    let last_yank_event = version_yank_events::table
+        .filter(version_yank_events::version_id.eq_any(&version_ids))
+        .select(max(version_yank_events::id))
+        .first::<Option<i32>>(conn)?;
+    let profiles = users::table
+        .filter(
+            users::id
+                .nullable()
+                .eq_any(
+                    versions::table
+                        .filter(versions::crate_id.eq(crate_id))
+                        .select(versions::published_by),
+                )
+                .or(users::id.eq_any(
+                    version_owner_actions::table
+                        .filter(version_owner_actions::version_id.eq_any(&version_ids))
+                        .select(version_owner_actions::user_id),
+                ))
+                .or(users::id.eq_any(
+                    version_yank_events::table
+                        .filter(version_yank_events::version_id.eq_any(&version_ids))
+                        .select(version_yank_events::user_id),
+                )),
+        )
+        .select((users::id, users::gh_login, users::name, users::gh_avatar))
+        .order(users::id)
+        .load::<(i32, String, Option<String>, Option<String>)>(conn)?;
+
+    Ok(Some(etag(&format!(
+        "{}:{}:{}:{}:{:?}:{:?}:{:?}:{:?}",
+        crate_id,
+        updated_at,
+        downloads,
+        count,
+        versions_updated_at,
+        last_action,
+        last_yank_event,
+        profiles
+    ))))
+}
+
 /// Handles the `GET /crates/:crate_id` route.
+///
+/// The response has an `ETag` derived from the crate and its versions, see `with_etag`. The
+/// date is part of it too, so that the data computed daily by background jobs, like the recent
+/// downloads or the notable dependents, is refreshed.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    // Crates mirrored from an upstream registry are refreshed by `show_uncached`
+    if req.app().config.upstream.is_some() {
+        return show_cached(req);
+    }
+
+    let crate_name = req.params()["crate_id"].to_string();
+    let base_etag = {
+        let conn = req.db_read_only()?;
+        versions_etag(&conn, &crate_name)?
+    };
+    match base_etag {
+        Some(base_etag) => {
+            let today = Utc::today().naive_utc();
+            let etag = etag(&format!("{}:{}", base_etag, today));
+            with_etag(req, &etag, show_cached)
+        }
+        None => show_cached(req),
+    }
+}
+
+fn show_cached(req: &mut dyn RequestExt) -> EndpointResult {
+    let key = cache::crate_key(&req.params()["crate_id"]);
+    let ttl = req.app().config.cache.crate_ttl;
+    cached(req, &key, ttl, show_uncached)
+}
+
+fn show_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     let name = &req.params()["crate_id"];
+    if let Some(upstream) = &req.app().config.upstream {
+        let conn = req.db_conn()?;
+        let app = req.app();
+        upstream
+            .ensure_crate(&conn, app.http_client(), Some(&app.upstream_lookups), name)
+            .map_err(|e| {
+                internal(&format_args!(
+                    "failed to mirror `{}` from the upstream registry: {}",
+                    name, e
+                ))
+            })?;
+    }
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(name).first(&*conn)?;
 
@@ -130,7 +395,8 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let versions_publishers_and_audit_actions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| (v, pb, aas))
+        .zip(VersionYankEvent::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, pb), aas), yes)| (v, pb, aas, yes))
         .collect::<Vec<_>>();
     let ids = versions_publishers_and_audit_actions
         .iter()
@@ -155,6 +421,17 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
         .load(&*conn)?;
     let top_versions = krate.top_versions(&conn)?;
 
+    // Computed by the `update_notable_dependents` background job
+    let used_by = crate_notable_dependents::table
+        .inner_join(crates::table.on(crates::id.eq(crate_notable_dependents::dependent_id)))
+        .filter(crate_notable_dependents::crate_id.eq(krate.id))
+        .order((crate_notable_dependents::downloads.desc(), crates::name))
+        .select((crates::name, crate_notable_dependents::downloads))
+        .load::<(String, i64)>(&*conn)?;
+
+    // Computed by the `check_crate_links` background job
+    let link_checks = CrateLinkCheck::current(&conn, &krate)?;
+
     #[derive(Serialize)]
     struct R {
         #[serde(rename = "crate")]
@@ -162,6 +439,8 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
         versions: Vec<EncodableVersion>,
         keywords: Vec<EncodableKeyword>,
         categories: Vec<EncodableCategory>,
+        used_by: Vec<EncodableNotableDependent>,
+        link_checks: Vec<EncodableCrateLinkCheck>,
     }
     Ok(req.json(&R {
         krate: krate.clone().encodable(
@@ -175,39 +454,160 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
         ),
         versions: versions_publishers_and_audit_actions
             .into_iter()
-            .map(|(v, pb, aas)| v.encodable(&krate.name, pb, aas))
+            .map(|(v, pb, aas, yes)| v.encodable(&krate.name, pb, aas, yes))
             .collect(),
         keywords: kws.into_iter().map(Keyword::encodable).collect(),
         categories: cats.into_iter().map(Category::encodable).collect(),
+        used_by: used_by
+            .into_iter()
+            .map(|(name, downloads)| EncodableNotableDependent { name, downloads })
+            .collect(),
+        link_checks: link_checks
+            .into_iter()
+            .map(CrateLinkCheck::encodable)
+            .collect(),
     }))
 }
 
+/// How long clients may cache the location of a README.
+const CACHE_CONTROL_README_LOCATION: &str = "public,max-age=3600";
+
 /// Handles the `GET /crates/:crate_id/:version/readme` route.
+///
+/// A localized README can be asked for with `?lang=`, and the `Accept-Language` header is
+/// used if there is none in that language. The main README is served if no localized one
+/// matches.
+///
+/// The README is rendered as HTML, and `?format=md` serves its markdown source instead. Only
+/// the source of the main README is stored, so `?lang=` is ignored with that format. The
+/// versions rendered before the sources were stored respond with a 404.
 pub fn readme(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
     let version = &req.params()["version"];
+    let raw = match req.query().get("format").map(String::as_str) {
+        None | Some("html") => false,
+        Some("md") => true,
+        Some(_) => return Err(bad_request("the `format` must be either `html` or `md`")),
+    };
+    let lang = req.query().get("lang").cloned().filter(|_| !raw);
+    let accept_language = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| !raw);
+
+    // Most versions only have a main README, which can be served without a query
+    let languages = if lang.is_some() || accept_language.is_some() {
+        let conn = req.db_read_only()?;
+        let version_id = versions::table
+            .inner_join(crates::table)
+            .filter(Crate::with_name(crate_name))
+            .filter(versions::num.eq(version))
+            .select(versions::id)
+            .first::<i32>(&*conn)
+            .optional()?;
+        match version_id {
+            Some(version_id) => LocalizedReadme::languages(&conn, version_id)?,
+            None => Vec::new(),
+        }
+    } else {
+        Vec::new()
+    };
+    let language = negotiate_language(&languages, lang.as_deref(), accept_language);
+
+    if raw {
+        let conn = req.db_read_only()?;
+        versions::table
+            .inner_join(crates::table)
+            .inner_join(readme_renderings::table)
+            .filter(Crate::with_name(crate_name))
+            .filter(versions::num.eq(version))
+            .filter(readme_renderings::has_source)
+            .select(versions::id)
+            .first::<i32>(&*conn)?;
+    }
 
-    let redirect_url = req
-        .app()
-        .config
-        .uploader
-        .readme_location(crate_name, version);
+    let uploader = &req.app().config.uploader;
+    let redirect_url = match language {
+        Some(language) => uploader.localized_readme_location(crate_name, version, language),
+        None if raw => uploader.raw_readme_location(crate_name, version),
+        None => uploader.readme_location(crate_name, version),
+    };
 
-    if req.wants_json() {
+    let mut response = if req.wants_json() {
         #[derive(Serialize)]
-        struct R {
+        struct R<'a> {
             url: String,
+            language: Option<&'a str>,
         }
-        Ok(req.json(&R { url: redirect_url }))
+        req.json(&R {
+            url: redirect_url,
+            language,
+        })
     } else {
-        Ok(req.redirect(redirect_url))
+        req.redirect(redirect_url)
+    };
+    response.headers_mut().insert(
+        header::VARY,
+        header::HeaderValue::from_static("Accept-Language"),
+    );
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static(CACHE_CONTROL_README_LOCATION),
+    );
+    Ok(response)
+}
+
+/// Handles the `GET /crates/:crate_id/:version/readme/headings` route.
+///
+/// The headings are extracted when the README is rendered, so versions whose README wasn't
+/// rendered yet, or was rendered before headings were extracted, have none.
+pub fn readme_headings(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let version = &req.params()["version"];
+    let conn = req.db_read_only()?;
+
+    let headings = versions::table
+        .inner_join(crates::table)
+        .inner_join(readme_renderings::table)
+        .filter(Crate::with_name(crate_name))
+        .filter(versions::num.eq(version))
+        .select(readme_renderings::headings)
+        .first::<Option<serde_json::Value>>(&*conn)
+        .optional()?
+        .flatten();
+    let headings: Vec<ReadmeHeading> = match headings {
+        Some(headings) => serde_json::from_value(headings)?,
+        None => Vec::new(),
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        headings: Vec<EncodableReadmeHeading>,
     }
+    Ok(req.json(&R {
+        headings: ReadmeHeading::tree(headings),
+    }))
 }
 
 /// Handles the `GET /crates/:crate_id/versions` route.
+///
+/// The response has an `ETag` derived from the crate and its versions, see `with_etag`.
 // FIXME: Not sure why this is necessary since /crates/:crate_id returns
 // this information already, but ember is definitely requesting it
 pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = req.params()["crate_id"].to_string();
+    let etag = {
+        let conn = req.db_read_only()?;
+        versions_etag(&conn, &crate_name)?
+    };
+    match etag {
+        Some(etag) => with_etag(req, &etag, versions_uncached),
+        None => versions_uncached(req),
+    }
+}
+
+fn versions_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
@@ -225,7 +625,8 @@ pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| v.encodable(crate_name, pb, aas))
+        .zip(VersionYankEvent::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, pb), aas), yes)| v.encodable(crate_name, pb, aas, yes))
         .collect();
 
     #[derive(Serialize)]
@@ -235,6 +636,82 @@ pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
     Ok(req.json(&R { versions }))
 }
 
+/// Handles the `GET /crates/:crate_id/full` route.
+///
+/// Lists all the versions of the crate along with their dependencies, so that tools don't need
+/// a request per version. The response has an `ETag` derived from the crate and its versions,
+/// see `with_etag`.
+pub fn full(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = req.params()["crate_id"].to_string();
+    let etag = {
+        let conn = req.db_read_only()?;
+        versions_etag(&conn, &crate_name)?
+    };
+    match etag {
+        Some(etag) => with_etag(req, &etag, full_uncached),
+        None => full_uncached(req),
+    }
+}
+
+fn full_uncached(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    let mut versions: Vec<Version> = krate.all_versions().load(&*conn)?;
+    versions.sort_by(|a, b| b.num.cmp(&a.num));
+    let dependencies = Dependency::for_versions(&conn, &versions)?;
+    let versions = versions
+        .into_iter()
+        .zip(dependencies)
+        .map(|(version, dependencies)| EncodableFullVersion {
+            id: version.id,
+            num: version.num.to_string(),
+            yanked: version.yanked,
+            features: version.features,
+            rust_version: version.rust_version,
+            created_at: version.created_at,
+            dependencies: dependencies
+                .into_iter()
+                .map(|(dep, crate_name)| dep.encodable(&crate_name, None))
+                .collect(),
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        versions: Vec<EncodableFullVersion>,
+    }
+    Ok(req.json(&R { versions }))
+}
+
+/// Handles the `GET /crates/:crate_id/quality` route.
+///
+/// Returns the quality score of the crate as last computed by the
+/// `update_quality_scores` background job, along with the current version of the
+/// formula so that outdated scores can be recognized.
+pub fn quality(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    let score: CrateQualityScore = CrateQualityScore::belonging_to(&krate).first(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        quality: EncodableCrateQualityScore,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        formula_version: i32,
+    }
+    Ok(req.json(&R {
+        quality: score.encodable(),
+        meta: Meta {
+            formula_version: QUALITY_FORMULA_VERSION,
+        },
+    }))
+}
+
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
 pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::dsl::any;
@@ -268,9 +745,12 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, krate_name, published_by), actions)| {
-            version.encodable(&krate_name, published_by, actions)
-        })
+        .zip(VersionYankEvent::for_versions(&conn, &versions)?.into_iter())
+        .map(
+            |(((version, krate_name, published_by), actions), yank_events)| {
+                version.encodable(&krate_name, published_by, actions, yank_events)
+            },
+        )
         .collect();
 
     #[derive(Serialize)]
@@ -1,11 +1,66 @@
 //! All routes related to managing owners of a crate
 
+use chrono::NaiveDateTime;
+
+use crate::controllers::helpers::{etag, with_etag};
 use crate::controllers::prelude::*;
-use crate::models::{Crate, Owner, Rights, Team, User};
-use crate::views::EncodableOwner;
+use crate::models::{Crate, Owner, OwnerKind, Rights, Team, User};
+use crate::schema::{crate_owners, crates, teams, users};
+use crate::util::errors::{forbidden, not_found};
+use crate::views::{EncodableOwner, EncodableTeamMember};
 
 /// Handles the `GET /crates/:crate_id/owners` route.
+///
+/// The response has an `ETag` derived from the ownerships of the crate and from the profiles of
+/// the owners, which changes whenever an owner is added or removed, or renames their account or
+/// changes their avatar, see `with_etag`.
 pub fn owners(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::{count_star, max};
+
+    let crate_name = req.params()["crate_id"].to_string();
+    let (ownerships, users, teams) = {
+        let conn = req.db_conn()?;
+        let ownerships = crate_owners::table
+            .inner_join(crates::table)
+            .filter(Crate::with_name(&crate_name))
+            .select((count_star(), max(crate_owners::updated_at)))
+            .first::<(i64, Option<NaiveDateTime>)>(&*conn)?;
+        let users = crate_owners::table
+            .inner_join(crates::table)
+            .inner_join(users::table)
+            .filter(Crate::with_name(&crate_name))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .select((users::gh_login, users::gh_avatar, users::name))
+            .order(users::id)
+            .load::<(String, Option<String>, Option<String>)>(&*conn)?;
+        let teams = crate_owners::table
+            .inner_join(crates::table)
+            .inner_join(teams::table)
+            .filter(Crate::with_name(&crate_name))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32))
+            .select((teams::login, teams::avatar, teams::name))
+            .order(teams::id)
+            .load::<(String, Option<String>, Option<String>)>(&*conn)?;
+        (ownerships, users, teams)
+    };
+    match ownerships {
+        (count, Some(updated_at)) => {
+            let profiles = users
+                .iter()
+                .chain(&teams)
+                .map(|(login, avatar, name)| format!("{:?}:{:?}:{:?}", login, avatar, name))
+                .collect::<Vec<_>>()
+                .join(",");
+            let key = format!("{}:{}:{}:{}", crate_name, count, updated_at, profiles);
+            with_etag(req, &etag(&key), owners_uncached)
+        }
+        (_, None) => owners_uncached(req),
+    }
+}
+
+fn owners_uncached(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_conn()?;
     let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
@@ -39,6 +94,51 @@ pub fn owner_team(req: &mut dyn RequestExt) -> EndpointResult {
     Ok(req.json(&R { teams: owners }))
 }
 
+/// Handles the `GET /crates/:crate_id/owner_team/:team_id/members` route.
+///
+/// Lists the members of the team which were cached when their membership was last checked,
+/// since the authentication provider only tells whether a given user belongs to a team.
+///
+/// Only the users owning the crate can see who belongs to its teams, the members of its teams
+/// can't.
+pub fn owner_team_members(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let crate_name = &req.params()["crate_id"];
+    let team_login = req.params()["team_id"].to_lowercase();
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+
+    let user = authenticated_user.user();
+    let is_owner = User::owning(&krate, &conn)?
+        .iter()
+        .any(|owner| matches!(owner, Owner::User(owner) if owner.id == user.id));
+    if !is_owner {
+        return Err(forbidden());
+    }
+
+    let team = Team::owning(&krate, &conn)?
+        .into_iter()
+        .find_map(|owner| match owner {
+            Owner::Team(team) if team.login == team_login => Some(team),
+            _ => None,
+        })
+        .ok_or_else(not_found)?;
+    let members = team
+        .members(&conn)?
+        .into_iter()
+        .map(|(membership, user)| EncodableTeamMember {
+            user: user.encodable_public(),
+            checked_at: membership.checked_at,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        members: Vec<EncodableTeamMember>,
+    }
+    Ok(req.json(&R { members }))
+}
+
 /// Handles the `GET /crates/:crate_id/owner_user` route.
 pub fn owner_user(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
@@ -100,7 +200,7 @@ fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
         let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
         let owners = krate.owners(&conn)?;
 
-        match user.rights(app, &owners)? {
+        match user.rights(app, &conn, &owners)? {
             Rights::Full => {}
             // Yes!
             Rights::Publish => {
@@ -0,0 +1,68 @@
+//! Endpoints for counting views of crate pages
+//!
+//! Views are counted at most once per visitor and per day, without cookies.
+//! Visitors are only identified by a hash of their IP address and user agent,
+//! salted with a random value that is discarded at the end of the day.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, CratePageview, Rights};
+use crate::schema::{crate_pageviews, crates};
+use crate::util::errors::forbidden;
+use crate::views::EncodableCratePageview;
+
+/// Handles the `POST /crates/:crate_id/pageview` route.
+pub fn record(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let crate_id = Crate::by_name(crate_name)
+        .select(crates::id)
+        .first(&*conn)?;
+
+    let ip = match req.headers().get("x-real-ip") {
+        Some(ip) => ip.as_bytes().to_vec(),
+        None => req.remote_addr().ip().to_string().into_bytes(),
+    };
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .map(|ua| ua.as_bytes())
+        .unwrap_or_default();
+    let visitor = [&ip[..], b"\0", user_agent].concat();
+
+    CratePageview::record(&conn, crate_id, &visitor)?;
+
+    ok_true()
+}
+
+/// Handles the `GET /crates/:crate_id/pageviews` route.
+///
+/// Only the owners of the crate can see the daily views of the last 90 days.
+pub fn pageviews(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::*;
+
+    let authenticated_user = req.authenticate()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+
+    let user = authenticated_user.user();
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &conn, &owners)? < Rights::Publish {
+        return Err(forbidden());
+    }
+
+    let pageviews = CratePageview::belonging_to(&krate)
+        .filter(crate_pageviews::date.gt(date(now - 90.days())))
+        .order(crate_pageviews::date.asc())
+        .load(&*conn)?
+        .into_iter()
+        .map(CratePageview::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        pageviews: Vec<EncodableCratePageview>,
+    }
+    Ok(req.json(&R { pageviews }))
+}
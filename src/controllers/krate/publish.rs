@@ -1,18 +1,25 @@
 //! Functionality related to publishing a new crate or version of a crate.
 
 use hex::ToHex;
+use std::collections::HashMap;
 use std::sync::Arc;
 use swirl::Job;
 
+use crate::cache;
 use crate::controllers::cargo_prelude::*;
+use crate::feature_flags::FeatureFlag;
 use crate::git;
 use crate::models::dependency;
+use crate::models::krate::canonical_url;
 use crate::models::{
-    insert_version_owner_action, Badge, Category, Keyword, NewCrate, NewVersion, Rights,
-    VersionAction,
+    insert_version_owner_action, Badge, Category, CratePublishPolicy, Keyword, NewCrate,
+    NewVersion, QuarantinedVersion, RegistryEvent, RegistryEventKind, Rights, VersionAction,
+    VersionFile,
 };
 
-use crate::render;
+use crate::render::{PendingRendering, RenderingKind};
+use crate::spam::SpamScore;
+use crate::tasks;
 use crate::util::{read_fill, read_le_u32, Maximums};
 use crate::views::{EncodableCrateUpload, GoodCrate, PublishWarnings};
 
@@ -44,6 +51,23 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
     req.log_metadata("crate_name", new_crate.name.to_string());
     req.log_metadata("crate_version", new_crate.vers.to_string());
 
+    if app.is_feature_enabled(FeatureFlag::StrictPublishChecks)
+        && new_crate.repository.as_deref().map_or(true, str::is_empty)
+    {
+        return Err(cargo_err(
+            "missing or empty metadata fields: repository. The `repository` field \
+             is required to publish crates to this registry.",
+        ));
+    }
+
+    if let Some(readme) = &new_crate.readme {
+        app.config
+            .render
+            .limits
+            .check_size(readme.len())
+            .map_err(|e| cargo_err(&e))?;
+    }
+
     let conn = app.primary_database.get()?;
     let ids = req.authenticate()?;
     let api_token_id = ids.api_token_id();
@@ -58,13 +82,26 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         ))
     })?;
 
+    let crate_name = new_crate.name.to_string();
+    let spam = SpamScore::new(
+        new_crate.description.as_deref(),
+        new_crate.readme.as_deref(),
+    );
+
     // Create a transaction on the database, if there are no errors,
     // commit the transactions to record a new or updated crate.
-    conn.transaction(|| {
+    let response = conn.transaction::<_, Box<dyn AppError>, _>(|| {
         let name = new_crate.name;
         let vers = &*new_crate.vers;
         let links = new_crate.links;
-        let repo = new_crate.repository;
+        let canonical = |url: &Option<String>, field| {
+            url.as_deref()
+                .map(|url| canonical_url(url, field))
+                .transpose()
+        };
+        let homepage = canonical(&new_crate.homepage, "homepage")?;
+        let documentation = canonical(&new_crate.documentation, "documentation")?;
+        let repo = canonical(&new_crate.repository, "repository")?;
         let features = new_crate
             .features
             .into_iter()
@@ -85,8 +122,8 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         let persist = NewCrate {
             name: &name,
             description: new_crate.description.as_deref(),
-            homepage: new_crate.homepage.as_deref(),
-            documentation: new_crate.documentation.as_deref(),
+            homepage: homepage.as_deref(),
+            documentation: documentation.as_deref(),
             readme: new_crate.readme.as_deref(),
             repository: repo.as_deref(),
             max_upload_size: None,
@@ -97,7 +134,18 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             persist.create_or_update(&conn, user.id, Some(&app.config.publish_rate_limit))?;
 
         let owners = krate.owners(&conn)?;
-        if user.rights(req.app(), &owners)? < Rights::Publish {
+        let rights = user.rights(req.app(), &conn, &owners)?;
+        if rights < Rights::Publish && krate.name != *name {
+            // Names are unique once normalized by `canon_crate_name`, so `foo_bar`
+            // can't be registered while `foo-bar` exists (and vice versa).
+            return Err(cargo_err(&format_args!(
+                "crate name `{}` is too similar to the existing crate `{}`. \
+                 Crate names must be unique when ignoring case and treating \
+                 `-` and `_` as equal.",
+                *name, krate.name
+            )));
+        }
+        if rights < Rights::Publish {
             return Err(cargo_err(
                 "this crate exists but you don't seem to be an owner. \
                  If you believe this is a mistake, perhaps you need \
@@ -106,6 +154,10 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             ));
         }
 
+        if let Some(policy) = CratePublishPolicy::for_crate(&conn, &krate)? {
+            policy.check(rights, api_token_id)?;
+        }
+
         if krate.name != *name {
             return Err(cargo_err(&format_args!(
                 "crate was previously named `{}`",
@@ -160,6 +212,21 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             api_token_id,
             VersionAction::Publish,
         )?;
+        RegistryEvent::record_version(
+            &conn,
+            RegistryEventKind::Publish,
+            &krate.name,
+            &version.num.to_string(),
+        )?;
+        if version.record_license_change(&conn)? {
+            RegistryEvent::record_version(
+                &conn,
+                RegistryEventKind::LicenseChange,
+                &krate.name,
+                &version.num.to_string(),
+            )?;
+            tasks::notify_license_change(version.id).enqueue(&conn)?;
+        }
 
         // Link this new version to all dependencies
         let git_deps = dependency::add_dependencies(&conn, &new_crate.deps, version.id)?;
@@ -176,27 +243,74 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         let ignored_invalid_badges = Badge::update_crate(&conn, &krate, new_crate.badges.as_ref())?;
         let top_versions = krate.top_versions(&conn)?;
 
-        if let Some(readme) = new_crate.readme {
-            render::render_and_upload_readme(
-                version.id,
-                readme,
-                new_crate
-                    .readme_file
-                    .unwrap_or_else(|| String::from("README.md")),
-                repo,
-            )
-            .enqueue(&conn)?;
-        }
-
-        let cksum = app
+        let (cksum, tarball_info) = app
             .config
             .uploader
             .upload_crate(req, &krate, maximums, vers)?;
 
+        // Relative links of the READMEs are resolved against their directory in the repository
+        let path_in_vcs = tarball_info
+            .vcs_info
+            .as_ref()
+            .and_then(|vcs_info| vcs_info.path_in_vcs.clone());
+
+        let rendering = |kind, text, file_name| PendingRendering {
+            kind,
+            text,
+            file_name,
+            base_url: repo.clone(),
+            path_in_vcs: path_in_vcs.clone(),
+        };
+        let mut renderings = Vec::new();
+        if let Some(readme) = new_crate.readme {
+            let file_name = new_crate
+                .readme_file
+                .unwrap_or_else(|| String::from("README.md"));
+            renderings.push(rendering(RenderingKind::Readme, readme, file_name));
+        }
+
+        VersionFile::insert_all(&conn, version.id, &tarball_info.files)?;
+        let uncompressed_size = tarball_info.uncompressed_size();
+        version.record_uncompressed_size(&conn, uncompressed_size as i64)?;
+        if let Some(vcs_info) = &tarball_info.vcs_info {
+            version.record_vcs_info(&conn, vcs_info)?;
+        }
+        if let Some(rust_version) = &tarball_info.rust_version {
+            version.record_rust_version(&conn, rust_version)?;
+        }
+        if !tarball_info.metadata_targets.is_empty() {
+            version.record_metadata_targets(&conn, &tarball_info.metadata_targets)?;
+        }
+        if !tarball_info.feature_docs.is_empty() {
+            version.record_feature_docs(&conn, &tarball_info.feature_docs)?;
+        }
+        for readme in tarball_info.localized_readmes {
+            let kind = RenderingKind::LocalizedReadme {
+                language: readme.language,
+            };
+            renderings.push(rendering(kind, readme.text, readme.file_name));
+        }
+        if let Some(changelog) = tarball_info.changelog {
+            let kind = RenderingKind::Changelog;
+            renderings.push(rendering(kind, changelog.text, changelog.file_name));
+        }
+
         let hex_cksum = cksum.encode_hex::<String>();
+        version.record_checksum(&conn, &hex_cksum)?;
+
+        // The features enabling `dep:` dependencies or `?` features are listed in `features2`,
+        // which versions of Cargo that don't understand this syntax skip
+        let (features2, features): (HashMap<_, _>, HashMap<_, _>) =
+            features
+                .into_iter()
+                .partition(|(_, enables): &(_, Vec<String>)| {
+                    enables
+                        .iter()
+                        .any(|enable| enable.starts_with("dep:") || enable.contains("?/"))
+                });
 
         // Register this crate in our local git repo.
-        let git_crate = git::Crate {
+        let mut git_crate = git::Crate {
             name: name.0,
             vers: vers.to_string(),
             cksum: hex_cksum,
@@ -204,23 +318,48 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             deps: git_deps,
             yanked: Some(false),
             links,
+            features2: Some(features2).filter(|features2| !features2.is_empty()),
+            v: None,
+            deprecated: Some(true).filter(|_| krate.deprecated),
+            superseded_by: krate.superseded_by.clone().filter(|_| krate.deprecated),
+            size: Some(u64::from(file_length)),
+            uncompressed_size: Some(uncompressed_size),
         };
-        git::add_crate(git_crate).enqueue(&conn)?;
+        git_crate.v = git_crate.required_index_version();
+        let mut other_warnings = vec![];
+        if spam.is_spam(app.config.spam_score_threshold) {
+            // The version is added to the index and rendered once an admin approves it
+            QuarantinedVersion::insert(&conn, version.id, &spam, &git_crate, &renderings)?;
+            other_warnings.push(String::from(
+                "this version looks like spam, so it is held for review by the admins \
+                 of the registry, and can't be used by cargo until they approve it",
+            ));
+        } else {
+            for pending in renderings {
+                pending.enqueue(&conn, version.id)?;
+            }
+            git::add_crate(git_crate).enqueue(&conn)?;
+        }
+        tasks::update_dependents_counts(krate.id).enqueue(&conn)?;
+        if app.search_backend.needs_indexing() {
+            tasks::index_crate(krate.id).enqueue(&conn)?;
+        }
 
-        // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
-        // that is no longer needed. As such, crates.io currently does not return any `other`
-        // warnings at this time, but if we need to, the field is available.
         let warnings = PublishWarnings {
             invalid_categories: ignored_invalid_categories,
             invalid_badges: ignored_invalid_badges,
-            other: vec![],
+            other: other_warnings,
         };
 
         Ok(req.json(&GoodCrate {
             krate: krate.minimal_encodable(&top_versions, None, false, None),
             warnings,
         }))
-    })
+    })?;
+
+    cache::invalidate_crate(app.cache.as_deref(), &crate_name);
+    cache::invalidate_listings(app.cache.as_deref());
+    Ok(response)
 }
 
 /// Used by the `krate::new` function.
@@ -0,0 +1,159 @@
+//! Endpoints for managing the publish policy of a crate
+//!
+//! A publish policy restricts how new versions of a crate may be published,
+//! e.g. only with the API tokens used by its CI. It is enforced when a new
+//! version is uploaded.
+//!
+//! Policies requiring trusted publishing or a session authenticated with 2FA
+//! are rejected, since this registry supports neither of them yet. They can
+//! be added once it does.
+
+use std::io::Read;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, CratePublishPolicy, NewCratePublishPolicy, Owner, Rights};
+use crate::schema::api_tokens;
+use crate::views::EncodablePublishPolicy;
+
+/// Handles the `GET /crates/:crate_id/publish_policy` route.
+///
+/// Only the owners of the crate can see its publish policy. Crates without a
+/// configured policy return the default one, which allows any owner to publish
+/// with any API token.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+
+    if user.rights(req.app(), &conn, &krate.owners(&conn)?)? < Rights::Publish {
+        return Err(cargo_err(
+            "only owners have permission to see the publish policy",
+        ));
+    }
+
+    let publish_policy = match CratePublishPolicy::for_crate(&conn, &krate)? {
+        Some(policy) => policy.encodable(),
+        None => EncodablePublishPolicy {
+            allowed_token_ids: None,
+            require_user_owner: false,
+            updated_at: krate.created_at,
+        },
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        publish_policy: EncodablePublishPolicy,
+    }
+    Ok(req.json(&R { publish_policy }))
+}
+
+/// Handles the `PUT /crates/:crate_id/publish_policy` route.
+///
+/// Only user owners of the crate can change its publish policy, and only their
+/// own API tokens can be allowed to publish.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    #[derive(Deserialize)]
+    struct Request {
+        publish_policy: PublishPolicy,
+    }
+
+    #[derive(Deserialize)]
+    struct PublishPolicy {
+        allowed_token_ids: Option<Vec<i32>>,
+        #[serde(default)]
+        require_user_owner: bool,
+        #[serde(default)]
+        trusted_publishing_only: bool,
+        #[serde(default)]
+        require_two_factor: bool,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    let mut policy = request.publish_policy;
+    if policy.trusted_publishing_only {
+        return Err(bad_request(
+            "this registry doesn't support trusted publishing, so publishing can't be \
+             restricted to it",
+        ));
+    }
+    if policy.require_two_factor {
+        return Err(bad_request(
+            "this registry doesn't support two-factor authentication, so publishing can't \
+             require it",
+        ));
+    }
+
+    let user = req.authenticate()?.user();
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+
+    conn.transaction(|| {
+        let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+        let owners = krate.owners(&conn)?;
+
+        match user.rights(req.app(), &conn, &owners)? {
+            Rights::Full => {}
+            Rights::Publish => {
+                return Err(cargo_err(
+                    "team members don't have permission to change the publish policy",
+                ));
+            }
+            Rights::None => {
+                return Err(cargo_err(
+                    "only owners have permission to change the publish policy",
+                ));
+            }
+        }
+
+        if let Some(token_ids) = &mut policy.allowed_token_ids {
+            token_ids.sort_unstable();
+            token_ids.dedup();
+            if token_ids.is_empty() {
+                return Err(cargo_err(
+                    "at least one API token must be allowed to publish new versions",
+                ));
+            }
+
+            let owner_ids = owners
+                .iter()
+                .filter_map(|owner| match owner {
+                    Owner::User(user) => Some(user.id),
+                    Owner::Team(_) => None,
+                })
+                .collect::<Vec<_>>();
+            let valid_tokens: i64 = api_tokens::table
+                .filter(api_tokens::id.eq(any(&*token_ids)))
+                .filter(api_tokens::user_id.eq(any(owner_ids)))
+                .filter(api_tokens::revoked.eq(false))
+                .count()
+                .get_result(&*conn)?;
+            if valid_tokens != token_ids.len() as i64 {
+                return Err(cargo_err(
+                    "only API tokens of the user owners of this crate can be allowed \
+                     to publish new versions",
+                ));
+            }
+        }
+
+        let publish_policy = NewCratePublishPolicy {
+            crate_id: krate.id,
+            allowed_token_ids: policy.allowed_token_ids,
+            require_user_owner: policy.require_user_owner,
+        }
+        .save(&conn)?
+        .encodable();
+
+        #[derive(Serialize)]
+        struct R {
+            publish_policy: EncodablePublishPolicy,
+        }
+        Ok(req.json(&R { publish_policy }))
+    })
+}
@@ -1,18 +1,29 @@
 //! Endpoint for searching and discovery functionality
 
 use diesel::dsl::*;
+use diesel::expression::SqlLiteral;
+use diesel::pg::Pg;
+use diesel::sql_types::{BigInt, Bool, Text};
 use diesel_full_text_search::*;
+use htmlescape::encode_minimal;
+use indexmap::IndexMap;
+use spdx::LicenseItem;
+use std::collections::HashSet;
 
+use crate::config::SearchConfig;
 use crate::controllers::cargo_prelude::*;
 use crate::controllers::helpers::Paginate;
 use crate::controllers::util::AuthenticatedUser;
 use crate::models::{Crate, CrateBadge, CrateOwner, CrateVersions, OwnerKind, Version};
 use crate::schema::*;
-use crate::util::errors::{bad_request, ChainError};
-use crate::views::EncodableCrate;
+use crate::search_backend::{self, TextMatches, MAX_MATCHES};
+use crate::util::errors::{bad_request, forbidden, ChainError};
+use crate::views::{EncodableCrate, EncodableCrateHighlights};
 
-use crate::controllers::helpers::pagination::Paginated;
-use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
+use crate::controllers::helpers::pagination::{
+    decode_seek, encode_seek, Page, Paginated, PaginationOptions,
+};
+use crate::models::krate::{canon_crate_name, ALL_COLUMNS, MAX_NAME_LENGTH};
 
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
@@ -36,7 +47,7 @@ use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
 /// function out to cover the different use cases, and create unit tests
 /// for them.
 pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
-    use diesel::sql_types::{Bool, Text};
+    use diesel::sql_types::{Double, Integer};
 
     // Don't require that authentication succeed, because it's only necessary
     // if the "following" param is set.
@@ -44,137 +55,285 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_read_only()?;
     let params = req.query();
     let sort = params.get("sort").map(|s| &**s);
+    let ranking = req.app().config.search;
     let include_yanked = params
         .get("include_yanked")
         .map(|s| s == "yes")
-        .unwrap_or(true);
+        .unwrap_or(false);
 
-    let selection = (
-        ALL_COLUMNS,
-        false.into_sql::<Bool>(),
-        recent_crate_downloads::downloads.nullable(),
-    );
-    let mut query = crates::table
-        .left_join(recent_crate_downloads::table)
-        .select(selection)
-        .into_boxed();
+    let q_string = params.get("q").filter(|q| !q.is_empty());
+    let readme_string = params.get("readme").filter(|q| !q.is_empty());
+    let categories = query_values(req, "category");
+    let keywords = query_values(req, "keyword");
+    let msrv = params
+        .get("msrv")
+        .map(|msrv| {
+            rust_version_parts(msrv)
+                .ok_or_else(|| bad_request("msrv must be a Rust version such as `1.46`"))
+        })
+        .transpose()?;
+    let licenses = query_values(req, "license");
+    // The license expressions of the versions offering one of the requested licenses, which
+    // are few enough to be matched here rather than in SQL
+    let matching_licenses = if licenses.is_empty() {
+        None
+    } else {
+        let accepted = accepted_licenses(&licenses)?;
+        let expressions = req.app().license_expressions.all(&*conn)?;
+        Some(
+            expressions
+                .into_iter()
+                .filter(|expression| offers_license(expression, &accepted))
+                .collect::<Vec<_>>(),
+        )
+    };
+    let text_matches = match q_string {
+        Some(q_string) => search_backend::search(&*req.app().search_backend, q_string),
+        None => TextMatches::Database,
+    };
+    // The crates with a name similar to the query are only searched when nothing matches it, so
+    // that misspellings such as `tokoi` still find `tokio` and suggest it as `did_you_mean`
+    let nothing_matches = match (&text_matches, q_string) {
+        (TextMatches::Ranked(ids), Some(_)) => ids.is_empty(),
+        (TextMatches::Database, Some(q_string)) => {
+            !diesel::select(exists(crates::table.filter(matches_query(q_string))))
+                .get_result::<bool>(&*conn)?
+        }
+        (_, None) => false,
+    };
+
+    // The crates matching the filters, built once for the results and once for each facet
+    let filtered = || -> AppResult<_> {
+        let mut query = crates::table
+            .left_join(recent_crate_downloads::table)
+            .into_boxed();
 
-    if let Some(q_string) = params.get("q") {
-        if !q_string.is_empty() {
-            let sort = params.get("sort").map(|s| &**s).unwrap_or("relevance");
+        if let TextMatches::Ranked(ids) = &text_matches {
+            query = query.filter(crates::id.eq(any(ids.clone())));
+        } else if let Some(q_string) = q_string {
+            if nothing_matches {
+                query = query.filter(Crate::fuzzily_matches_name(q_string));
+            } else {
+                query = query.filter(matches_query(q_string));
+            }
+        }
 
+        if let Some(readme_string) = readme_string {
             let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                .bind::<Text, _>(q_string)
+                .bind::<Text, _>(readme_string)
                 .sql(")");
+            query = query.filter(q.matches(crates::readme_textsearchable_index_col));
+        }
+
+        if !categories.is_empty() {
+            let in_categories = |slugs: &[String]| {
+                let patterns = slugs
+                    .iter()
+                    .map(|slug| format!("{}::%", slug))
+                    .collect::<Vec<_>>();
+                crates::id.eq_any(
+                    crates_categories::table
+                        .select(crates_categories::crate_id)
+                        .inner_join(categories::table)
+                        .filter(
+                            categories::slug
+                                .eq(any(slugs.to_vec()))
+                                .or(categories::slug.like(any(patterns))),
+                        ),
+                )
+            };
+            match match_mode(&params, "category_mode")? {
+                MatchMode::All => {
+                    for slug in &categories {
+                        query = query.filter(in_categories(std::slice::from_ref(slug)));
+                    }
+                }
+                MatchMode::Any => query = query.filter(in_categories(&categories)),
+            }
+        }
+
+        if let Some(kws) = params.get("all_keywords") {
+            use diesel::sql_types::Array;
+            sql_function!(#[aggregate] fn array_agg<T>(x: T) -> Array<T>);
+
+            let names: Vec<_> = kws
+                .split_whitespace()
+                .map(|name| name.to_lowercase())
+                .collect();
+
+            query = query.filter(
+                // FIXME: Just use `.contains` in Diesel 2.0
+                // https://github.com/diesel-rs/diesel/issues/2066
+                Contains::new(
+                    crates_keywords::table
+                        .inner_join(keywords::table)
+                        .filter(crates_keywords::crate_id.eq(crates::id))
+                        .select(array_agg(keywords::keyword))
+                        .single_value(),
+                    names.into_sql::<Array<Text>>(),
+                ),
+            );
+        } else if !keywords.is_empty() {
+            // Compared in lowercase, which `index_keywords_lower_keyword` indexes
+            let with_keywords = |names: &[String]| {
+                let names = names
+                    .iter()
+                    .map(|name| name.to_lowercase())
+                    .collect::<Vec<_>>();
+                crates::id.eq_any(
+                    crates_keywords::table
+                        .select(crates_keywords::crate_id)
+                        .inner_join(keywords::table)
+                        .filter(crate::lower(keywords::keyword).eq(any(names))),
+                )
+            };
+            match match_mode(&params, "keyword_mode")? {
+                MatchMode::All => {
+                    for name in &keywords {
+                        query = query.filter(with_keywords(std::slice::from_ref(name)));
+                    }
+                }
+                MatchMode::Any => query = query.filter(with_keywords(&keywords)),
+            }
+        } else if let Some(letter) = params.get("letter") {
+            let pattern = format!(
+                "{}%",
+                letter
+                    .chars()
+                    .next()
+                    .chain_error(|| bad_request("letter value must contain 1 character"))?
+                    .to_lowercase()
+                    .collect::<String>()
+            );
+            query = query.filter(canon_crate_name(crates::name).like(pattern));
+        } else if let Some(user_id) = params.get("user_id").and_then(|s| s.parse::<i32>().ok()) {
+            query = query.filter(
+                crates::id.eq_any(
+                    CrateOwner::by_owner_kind(OwnerKind::User)
+                        .select(crate_owners::crate_id)
+                        .filter(crate_owners::owner_id.eq(user_id)),
+                ),
+            );
+        } else if let Some(team_id) = params.get("team_id").and_then(|s| s.parse::<i32>().ok()) {
             query = query.filter(
-                q.clone()
-                    .matches(crates::textsearchable_index_col)
-                    .or(Crate::loosly_matches_name(&q_string)),
+                crates::id.eq_any(
+                    CrateOwner::by_owner_kind(OwnerKind::Team)
+                        .select(crate_owners::crate_id)
+                        .filter(crate_owners::owner_id.eq(team_id)),
+                ),
             );
+        } else if params.get("following").is_some() {
+            let user_id = match &authenticated_user {
+                Ok(user) => user.user_id(),
+                Err(_) => return Err(forbidden()),
+            };
+            query = query.filter(
+                crates::id.eq_any(
+                    follows::table
+                        .select(follows::crate_id)
+                        .filter(follows::user_id.eq(user_id)),
+                ),
+            );
+        }
 
-            query = query.select((
-                ALL_COLUMNS,
-                Crate::with_name(q_string),
-                recent_crate_downloads::downloads.nullable(),
-            ));
-            query = query.order(Crate::with_name(q_string).desc());
+        if let Some(dependency) = params.get("depends_on") {
+            // Like the reverse dependencies, only the latest version of each crate counts
+            query = query.filter(
+                sql::<Bool>(
+                    "crates.id IN (\
+                     SELECT versions.crate_id FROM versions \
+                     INNER JOIN dependencies ON dependencies.version_id = versions.id \
+                     WHERE dependencies.crate_id = (\
+                     SELECT id FROM crates WHERE canon_crate_name(name) = canon_crate_name(",
+                )
+                .bind::<Text, _>(dependency)
+                .sql(&format!(")) AND {})", LATEST_VERSION)),
+            );
+        }
 
-            if sort == "relevance" {
-                let rank = ts_rank_cd(crates::textsearchable_index_col, q);
-                query = query.then_order_by(rank.desc())
-            }
+        if let Some(matching_licenses) = &matching_licenses {
+            // Like the license facets, only the latest version of each crate counts
+            query = query.filter(
+                crates::id.eq_any(
+                    versions::table
+                        .select(versions::crate_id)
+                        .filter(versions::license.eq(any(matching_licenses.clone())))
+                        .filter(sql::<Bool>(LATEST_VERSION)),
+                ),
+            );
         }
-    }
 
-    if let Some(cat) = params.get("category") {
-        query = query.filter(
-            crates::id.eq_any(
-                crates_categories::table
-                    .select(crates_categories::crate_id)
-                    .inner_join(categories::table)
-                    .filter(
-                        categories::slug
-                            .eq(cat)
-                            .or(categories::slug.like(format!("{}::%", cat))),
-                    ),
-            ),
-        );
-    }
+        if let Some(msrv) = &msrv {
+            // The crates whose latest version declares a `rust-version` no newer than the
+            // requested one, those which don't declare any are left out
+            query = query.filter(
+                crates::id.eq_any(
+                    versions::table
+                        .select(versions::crate_id)
+                        .filter(sql::<Bool>(LATEST_VERSION))
+                        .filter(
+                            sql::<Bool>(
+                                "(string_to_array(versions.rust_version || '.0.0', '.')::int[])\
+                                 [1:3] <= ",
+                            )
+                            .bind::<diesel::sql_types::Array<Integer>, _>(msrv.clone()),
+                        ),
+                ),
+            );
+        }
 
-    if let Some(kws) = params.get("all_keywords") {
-        use diesel::sql_types::Array;
-        sql_function!(#[aggregate] fn array_agg<T>(x: T) -> Array<T>);
-
-        let names: Vec<_> = kws
-            .split_whitespace()
-            .map(|name| name.to_lowercase())
-            .collect();
-
-        query = query.filter(
-            // FIXME: Just use `.contains` in Diesel 2.0
-            // https://github.com/diesel-rs/diesel/issues/2066
-            Contains::new(
-                crates_keywords::table
-                    .inner_join(keywords::table)
-                    .filter(crates_keywords::crate_id.eq(crates::id))
-                    .select(array_agg(keywords::keyword))
-                    .single_value(),
-                names.into_sql::<Array<Text>>(),
-            ),
-        );
-    } else if let Some(kw) = params.get("keyword") {
-        query = query.filter(
-            crates::id.eq_any(
-                crates_keywords::table
-                    .select(crates_keywords::crate_id)
-                    .inner_join(keywords::table)
-                    .filter(crate::lower(keywords::keyword).eq(crate::lower(kw))),
-            ),
-        );
-    } else if let Some(letter) = params.get("letter") {
-        let pattern = format!(
-            "{}%",
-            letter
-                .chars()
-                .next()
-                .chain_error(|| bad_request("letter value must contain 1 character"))?
-                .to_lowercase()
-                .collect::<String>()
-        );
-        query = query.filter(canon_crate_name(crates::name).like(pattern));
-    } else if let Some(user_id) = params.get("user_id").and_then(|s| s.parse::<i32>().ok()) {
-        query = query.filter(
-            crates::id.eq_any(
-                CrateOwner::by_owner_kind(OwnerKind::User)
-                    .select(crate_owners::crate_id)
-                    .filter(crate_owners::owner_id.eq(user_id)),
-            ),
-        );
-    } else if let Some(team_id) = params.get("team_id").and_then(|s| s.parse::<i32>().ok()) {
-        query = query.filter(
-            crates::id.eq_any(
-                CrateOwner::by_owner_kind(OwnerKind::Team)
-                    .select(crate_owners::crate_id)
-                    .filter(crate_owners::owner_id.eq(team_id)),
-            ),
-        );
-    } else if params.get("following").is_some() {
-        let user_id = authenticated_user?.user_id();
-        query = query.filter(
-            crates::id.eq_any(
-                follows::table
-                    .select(follows::crate_id)
-                    .filter(follows::user_id.eq(user_id)),
-            ),
-        );
-    }
+        // The crates whose every version is yanked are left out, unless `include_yanked=yes`
+        if !include_yanked {
+            query = query.filter(crates::has_unyanked_versions);
+        }
+
+        Ok(query)
+    };
+
+    let selection = (
+        ALL_COLUMNS,
+        false.into_sql::<Bool>(),
+        recent_crate_downloads::downloads.nullable(),
+    );
+    let mut query = filtered()?.select(selection);
+
+    if let Some(q_string) = q_string {
+        let sort = sort.unwrap_or("relevance");
 
-    if !include_yanked {
-        query = query.filter(exists(
-            versions::table
-                .filter(versions::crate_id.eq(crates::id))
-                .filter(versions::yanked.eq(false)),
+        query = query.select((
+            ALL_COLUMNS,
+            Crate::with_name(q_string),
+            recent_crate_downloads::downloads.nullable(),
         ));
+        query = query.order(Crate::with_name(q_string).desc());
+
+        if let (TextMatches::Ranked(ids), "relevance") = (&text_matches, sort) {
+            // The external search engine ranks the crates by itself
+            let position = sql::<Integer>("array_position(")
+                .bind::<diesel::sql_types::Array<Integer>, _>(ids.clone())
+                .sql(", crates.id)");
+            query = query.then_order_by(position.asc())
+        } else if sort == "relevance" {
+            let rank = sql::<Double>(&format!(
+                "{} * ts_rank_cd(crates.textsearchable_index_col, plainto_tsquery('english', ",
+                ranking.text_weight
+            ))
+            .bind::<Text, _>(q_string)
+            .sql(&format!(")) + {}", popularity(&ranking)));
+            query = query.then_order_by(rank.desc())
+        }
+    } else if let (Some(readme_string), None) | (Some(readme_string), Some("relevance")) =
+        (readme_string, sort)
+    {
+        let rank = sql::<Double>(&format!(
+            "{} * ts_rank_cd(crates.readme_textsearchable_index_col, plainto_tsquery('english', ",
+            ranking.text_weight
+        ))
+        .bind::<Text, _>(readme_string)
+        .sql(&format!(")) + {}", popularity(&ranking)));
+        query = query.order(rank.desc());
+    } else if sort == Some("relevance") {
+        query = query.order(sql::<Double>(&popularity(&ranking)).desc());
     }
 
     if sort == Some("downloads") {
@@ -189,11 +348,123 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         query = query.then_order_by(crates::name.asc())
     }
 
-    let data: Paginated<(Crate, bool, Option<i64>)> = query.paginate(&req.query())?.load(&*conn)?;
-    let total = data.total();
+    let did_you_mean = match q_string {
+        Some(q_string) if nothing_matches => Crate::similar_name(q_string, &*conn)?,
+        _ => None,
+    };
+    // The facets are a few more aggregate queries over all the matching crates, so they are only
+    // computed when requested with `include=facets` for a search which is refined by something
+    let refined = q_string.is_some()
+        || readme_string.is_some()
+        || !categories.is_empty()
+        || !keywords.is_empty()
+        || !licenses.is_empty()
+        || msrv.is_some()
+        || [
+            "all_keywords",
+            "letter",
+            "user_id",
+            "team_id",
+            "following",
+            "depends_on",
+        ]
+        .iter()
+        .any(|name| params.contains_key(*name));
+    let include_facets = params
+        .get("include")
+        .map_or(false, |include| include.split(',').any(|i| i == "facets"));
+    let facets = if include_facets && refined {
+        Some(Facets {
+            categories: crates_categories::table
+                .inner_join(categories::table)
+                .filter(crates_categories::crate_id.eq_any(filtered()?.select(crates::id)))
+                .group_by(categories::id)
+                .select((categories::slug, categories::category, crate_count()))
+                .order((crate_count().desc(), categories::slug.asc()))
+                .limit(FACET_SIZE)
+                .load(&*conn)?,
+            keywords: crates_keywords::table
+                .inner_join(keywords::table)
+                .filter(crates_keywords::crate_id.eq_any(filtered()?.select(crates::id)))
+                .group_by(keywords::id)
+                .select((keywords::keyword, crate_count()))
+                .order((crate_count().desc(), keywords::keyword.asc()))
+                .limit(FACET_SIZE)
+                .load(&*conn)?,
+            licenses: versions::table
+                .filter(versions::crate_id.eq_any(filtered()?.select(crates::id)))
+                .filter(sql::<Bool>(LATEST_VERSION))
+                .group_by(versions::license)
+                .select((versions::license, crate_count()))
+                .order((crate_count().desc(), versions::license.asc()))
+                .limit(FACET_SIZE)
+                .load(&*conn)?,
+        })
+    } else {
+        None
+    };
 
-    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
-    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+    // The listings ordered by name alone are paginated with a cursor on the last name, unless a
+    // page number is requested, so that crawling all the crates doesn't scan the skipped ones
+    let ordered_by_name = q_string.is_none()
+        && readme_string.is_none()
+        && !matches!(
+            sort,
+            Some("relevance")
+                | Some("downloads")
+                | Some("recent-downloads")
+                | Some("recent-updates")
+                | Some("new")
+        );
+    let pagination = PaginationOptions::with_seek(&params)?;
+    let seek = match &pagination.page {
+        Page::Seek(cursor) if ordered_by_name => Some(Some(decode_seek::<String>(cursor)?)),
+        Page::Seek(_) => {
+            return Err(bad_request(
+                "the seek parameter is only supported when sorting by name",
+            ))
+        }
+        Page::Unspecified if ordered_by_name => Some(None),
+        _ => None,
+    };
+
+    let (data, total, next_page, prev_page) = if let Some(after) = seek {
+        // Counting scans all the matching crates, so only the first page has a total
+        let total = match after {
+            Some(after) => {
+                query = query.filter(crates::name.gt(after));
+                None
+            }
+            None => Some(filtered()?.count().get_result::<i64>(&*conn)?),
+        };
+        let data: Vec<(Crate, bool, Option<i64>)> =
+            query.limit(i64::from(pagination.per_page)).load(&*conn)?;
+        let next_page = match data.last() {
+            Some((krate, _, _)) if data.len() == pagination.per_page as usize => {
+                let mut params = IndexMap::new();
+                params.insert("seek".into(), encode_seek(&krate.name));
+                Some(req.query_with_params(params))
+            }
+            _ => None,
+        };
+        (data, total, next_page, None)
+    } else {
+        let data: Paginated<(Crate, bool, Option<i64>)> = query.paginate(&params)?.load(&*conn)?;
+        let total = data.total();
+        let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+        let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+        (
+            data.into_iter().collect::<Vec<_>>(),
+            total,
+            next_page,
+            prev_page,
+        )
+    };
+
+    let total_capped = match &text_matches {
+        TextMatches::Ranked(ids) => ids.len() >= MAX_MATCHES,
+        TextMatches::Database => false,
+    };
 
     let perfect_matches = data.iter().map(|&(_, b, _)| b).collect::<Vec<_>>();
     let recent_downloads = data
@@ -203,10 +474,11 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
     let crates = data.into_iter().map(|(c, _, _)| c).collect::<Vec<_>>();
 
     let versions: Vec<Version> = crates.versions().load(&*conn)?;
-    let versions = versions
-        .grouped_by(&crates)
-        .into_iter()
-        .map(|versions| Version::top(versions.into_iter().map(|v| (v.created_at, v.num))));
+    let versions = versions.grouped_by(&crates).into_iter().map(|versions| {
+        let rust_version = latest_rust_version(&versions);
+        let top_versions = Version::top(versions.into_iter().map(|v| (v.created_at, v.num)));
+        (top_versions, rust_version)
+    });
 
     let badges: Vec<CrateBadge> = CrateBadge::belonging_to(&crates)
         .select((badges::crate_id, badges::all_columns))
@@ -216,19 +488,33 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .into_iter()
         .map(|badges| badges.into_iter().map(|cb| cb.badge).collect());
 
+    let terms = q_string.map(|q| query_terms(q)).unwrap_or_default();
     let crates = versions
         .zip(crates)
         .zip(perfect_matches)
         .zip(recent_downloads)
         .zip(badges)
         .map(
-            |((((max_version, krate), perfect_match), recent_downloads), badges)| {
-                krate.minimal_encodable(
+            |(
+                ((((max_version, rust_version), krate), perfect_match), recent_downloads),
+                badges,
+            )| {
+                let mut krate = krate.minimal_encodable(
                     &max_version,
                     Some(badges),
                     perfect_match,
                     Some(recent_downloads),
-                )
+                );
+                krate.rust_version = rust_version;
+                if !terms.is_empty() {
+                    krate.highlights = Some(EncodableCrateHighlights {
+                        name: highlight(&krate.name, &terms, None),
+                        description: krate.description.as_deref().and_then(|description| {
+                            highlight(description, &terms, Some(FRAGMENT_LENGTH))
+                        }),
+                    });
+                }
+                krate
             },
         )
         .collect();
@@ -236,23 +522,454 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
     #[derive(Serialize)]
     struct R {
         crates: Vec<EncodableCrate>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        facets: Option<Facets>,
         meta: Meta,
     }
     #[derive(Serialize)]
     struct Meta {
         total: Option<i64>,
+        /// Whether the external search engine returned `MAX_MATCHES` crates, in which case
+        /// `total` counts only those
+        total_capped: bool,
         next_page: Option<String>,
         prev_page: Option<String>,
+        did_you_mean: Option<String>,
     }
 
     Ok(req.json(&R {
         crates,
+        facets,
         meta: Meta {
             total,
+            total_capped,
             next_page,
             prev_page,
+            did_you_mean,
         },
     }))
 }
 
+/// How many crates `GET /crate_suggestions` returns at most
+const MAX_SUGGESTIONS: i64 = 10;
+
+/// Handles the `GET /crate_suggestions` route.
+///
+/// Returns the names and descriptions of the crates whose name starts with the `q` parameter,
+/// the exact match then the most downloaded first, for editors and the search box to
+/// autocomplete. The query only reads the `crates` table, through `index_crates_name_prefix`.
+pub fn suggest(req: &mut dyn RequestExt) -> EndpointResult {
+    let q = req.query().get("q").cloned().unwrap_or_default();
+
+    #[derive(Serialize, Queryable)]
+    struct Suggestion {
+        name: String,
+        description: Option<String>,
+    }
+
+    let crates: Vec<Suggestion> = if q.is_empty() || q.len() > MAX_NAME_LENGTH {
+        Vec::new()
+    } else {
+        let conn = req.db_read_only()?;
+        crates::table
+            .select((crates::name, crates::description))
+            .filter(canon_crate_name(crates::name).like(prefix_pattern(&q)))
+            .filter(crates::has_unyanked_versions)
+            .order((
+                Crate::with_name(&q).desc(),
+                crates::downloads.desc(),
+                crates::name.asc(),
+            ))
+            .limit(MAX_SUGGESTIONS)
+            .load(&*conn)?
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<Suggestion>,
+    }
+    Ok(req.json(&R { crates }))
+}
+
+/// Returns the `LIKE` pattern of the canonical names starting with a prefix, whose `_`
+/// mustn't match any character.
+fn prefix_pattern(prefix: &str) -> String {
+    let mut pattern = String::new();
+    for c in prefix.to_lowercase().chars() {
+        match c {
+            '-' | '_' => pattern.push_str("\\_"),
+            '%' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            c => pattern.push(c),
+        }
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// How long the fragment of a description around its first match is at most, in bytes
+const FRAGMENT_LENGTH: usize = 200;
+
+/// How much of the description is kept before its first match, in bytes
+const FRAGMENT_CONTEXT: usize = 40;
+
+/// Returns the terms of a search query to highlight in its results, in lowercase.
+fn query_terms(q: &str) -> Vec<String> {
+    q.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+/// Escapes a text for HTML and wraps the occurrences of the terms in `<mark>` elements, or
+/// returns `None` when no term occurs in the text.
+///
+/// The terms are matched anywhere in the words, and regardless of their ASCII case. The text
+/// is shortened to a fragment of `max_length` bytes around its first match, if given.
+fn highlight(text: &str, terms: &[String], max_length: Option<usize>) -> Option<String> {
+    let lowercase = text.to_ascii_lowercase();
+    let mut matches = terms
+        .iter()
+        .flat_map(|term| {
+            lowercase
+                .match_indices(&**term)
+                .map(move |(start, _)| start..start + term.len())
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|range| range.start);
+    // Overlapping matches are merged, such as the ones of `pars` and `parser`
+    let matches = matches.into_iter().fold(Vec::new(), |mut merged, range| {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+        merged
+    });
+
+    let first = matches.first()?.start;
+    let (mut start, mut end) = (0, text.len());
+    if let Some(max_length) = max_length.filter(|max_length| text.len() > *max_length) {
+        start = floor_char_boundary(text, first.saturating_sub(FRAGMENT_CONTEXT));
+        // Start the fragment at a word, unless that would skip the match
+        if start > 0 {
+            if let Some(space) = text[start..first].find(' ') {
+                start += space + 1;
+            }
+        }
+        end = floor_char_boundary(text, (start + max_length).min(text.len()));
+    }
+
+    let mut html = String::new();
+    if start > 0 {
+        html.push('…');
+    }
+    let mut position = start;
+    // The matches the fragment cuts aren't marked
+    for range in matches {
+        if range.start < start || range.end > end {
+            continue;
+        }
+        html.push_str(&encode_minimal(&text[position..range.start]));
+        html.push_str("<mark>");
+        html.push_str(&encode_minimal(&text[range.start..range.end]));
+        html.push_str("</mark>");
+        position = range.end;
+    }
+    html.push_str(&encode_minimal(&text[position..end]));
+    if end < text.len() {
+        html.push('…');
+    }
+    Some(html)
+}
+
+/// Returns the largest character boundary of a text at or before an index.
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// SQL filter selecting the crates whose text or name matches the `q` parameter of a search.
+fn matches_query<QS>(q_string: &str) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Bool> + '_>
+where
+    crates::name: SelectableExpression<QS>,
+    crates::textsearchable_index_col: SelectableExpression<QS>,
+{
+    Box::new(
+        sql::<TsQuery>("plainto_tsquery('english', ")
+            .bind::<Text, _>(q_string)
+            .sql(")")
+            .matches(crates::textsearchable_index_col)
+            .or(Crate::loosly_matches_name(q_string)),
+    )
+}
+
+/// The SQL condition selecting the latest version of each crate, ignoring the yanked versions
+/// and the prereleases
+const LATEST_VERSION: &str = "versions.id = (\
+     SELECT latest.id FROM versions latest \
+     WHERE latest.crate_id = versions.crate_id AND NOT latest.yanked \
+     ORDER BY to_semver_no_prerelease(latest.num) DESC NULLS LAST \
+     LIMIT 1)";
+
+/// How many buckets each facet has at most
+const FACET_SIZE: i64 = 10;
+
+/// The longest `license` parameter of searches
+const MAX_LICENSE_QUERY_LENGTH: usize = 256;
+
+/// How many licenses the `license` parameters of a search can name in total
+const MAX_LICENSE_QUERY_TERMS: usize = 16;
+
+/// The most common categories, keywords and licenses of the crates matching a search, for
+/// the frontend to refine it with
+#[derive(Serialize)]
+struct Facets {
+    categories: Vec<CategoryFacet>,
+    keywords: Vec<KeywordFacet>,
+    /// The licenses of the latest version of the crates
+    licenses: Vec<LicenseFacet>,
+}
+
+#[derive(Serialize, Queryable)]
+struct CategoryFacet {
+    slug: String,
+    category: String,
+    crates_cnt: i64,
+}
+
+#[derive(Serialize, Queryable)]
+struct KeywordFacet {
+    keyword: String,
+    crates_cnt: i64,
+}
+
+#[derive(Serialize, Queryable)]
+struct LicenseFacet {
+    license: Option<String>,
+    crates_cnt: i64,
+}
+
+/// The number of crates in a bucket of a facet
+fn crate_count() -> SqlLiteral<BigInt> {
+    sql("COUNT(*)")
+}
+
+/// How the crates are matched against the values of a repeated parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MatchMode {
+    /// The crates matching all the values, the default
+    All,
+    /// The crates matching any of the values
+    Any,
+}
+
+/// Reads the `all` or `any` mode of a repeated parameter, such as `category_mode`.
+fn match_mode(params: &IndexMap<String, String>, name: &str) -> AppResult<MatchMode> {
+    match params.get(name).map(|mode| &**mode) {
+        None | Some("all") => Ok(MatchMode::All),
+        Some("any") => Ok(MatchMode::Any),
+        Some(_) => Err(bad_request(&format!("{} must be `all` or `any`", name))),
+    }
+}
+
+/// Returns the non-empty values of a parameter which can be repeated, such as
+/// `category=database&category=asynchronous`.
+fn query_values(req: &dyn RequestExt, name: &str) -> Vec<String> {
+    url::form_urlencoded::parse(req.query_string().unwrap_or("").as_bytes())
+        .filter(|(key, value)| key == name && !value.is_empty())
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+/// Returns the major, minor and patch numbers of a Rust version such as `1.46`, whose missing
+/// numbers are zeros.
+fn rust_version_parts(version: &str) -> Option<Vec<i32>> {
+    let mut parts = version
+        .split('.')
+        .map(|part| {
+            part.parse()
+                .ok()
+                .filter(|_| part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect::<Option<Vec<i32>>>()?;
+    if parts.len() > 3 {
+        return None;
+    }
+    parts.resize(3, 0);
+    Some(parts)
+}
+
+/// Returns the minimum supported Rust version of the latest version of a crate, the highest
+/// one which is neither yanked nor a prerelease, like `LATEST_VERSION`.
+fn latest_rust_version(versions: &[Version]) -> Option<String> {
+    versions
+        .iter()
+        .filter(|version| !version.yanked && !version.num.is_prerelease())
+        .max_by(|a, b| a.num.cmp(&b.num))
+        .and_then(|version| version.rust_version.clone())
+}
+
+/// Returns the SPDX identifiers of the licenses of the `license` parameters, such as `MIT`
+/// and `Apache-2.0` for `license=MIT&license=Apache-2.0`.
+///
+/// The parameters are limited to `MAX_LICENSE_QUERY_LENGTH` bytes and `MAX_LICENSE_QUERY_TERMS`
+/// licenses in total, since they come from anonymous requests.
+fn accepted_licenses(licenses: &[String]) -> AppResult<HashSet<&'static str>> {
+    let mut accepted = HashSet::new();
+    let mut terms = 0;
+    for license in licenses {
+        let invalid = || bad_request(&format!("invalid license expression `{}`", license));
+        if license.len() > MAX_LICENSE_QUERY_LENGTH {
+            return Err(invalid());
+        }
+        let expression = parse_license_expression(license).ok_or_else(invalid)?;
+        for requirement in expression.requirements() {
+            terms += 1;
+            if terms > MAX_LICENSE_QUERY_TERMS {
+                return Err(bad_request(&format!(
+                    "searches can't filter by more than {} licenses",
+                    MAX_LICENSE_QUERY_TERMS
+                )));
+            }
+            match &requirement.req.license {
+                LicenseItem::SPDX { id, .. } => {
+                    accepted.insert(id.name);
+                }
+                LicenseItem::Other { .. } => return Err(invalid()),
+            }
+        }
+    }
+    Ok(accepted)
+}
+
+/// Whether a license expression offers an alternative whose licenses are all accepted, so that
+/// `MIT OR Apache-2.0` offers `MIT` but `MIT AND Apache-2.0` doesn't. The legacy `/` separator
+/// is an `OR`, and the exceptions of `WITH` are ignored.
+fn offers_license(expression: &str, accepted: &HashSet<&str>) -> bool {
+    parse_license_expression(expression).map_or(false, |expression| {
+        expression.evaluate(|requirement| match &requirement.license {
+            LicenseItem::SPDX { id, .. } => accepted.contains(id.name),
+            LicenseItem::Other { .. } => false,
+        })
+    })
+}
+
+/// Parses an SPDX license expression, accepting the legacy `/` separator of older crates.
+fn parse_license_expression(expression: &str) -> Option<spdx::Expression> {
+    spdx::Expression::parse_mode(expression, spdx::ParseMode::Lax).ok()
+}
+
+/// Returns the SQL of the signals of popularity blended in the relevance of search results:
+/// the downloads of the last 90 days, whose logarithm keeps the most downloaded crates from
+/// burying the others, and the recency of the last update.
+///
+/// The weights come from the configuration and are finite, so they are part of the SQL.
+fn popularity(ranking: &SearchConfig) -> String {
+    format!(
+        "{} * ln(1 + coalesce(recent_crate_downloads.downloads, 0)::float8) + \
+         {} * power(0.5, extract(epoch from now() - crates.updated_at) / {})",
+        ranking.downloads_weight,
+        ranking.updates_weight,
+        ranking.update_half_life.as_secs_f64(),
+    )
+}
+
 diesel_infix_operator!(Contains, "@>");
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        accepted_licenses, highlight, offers_license, prefix_pattern, query_terms,
+        rust_version_parts,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn rust_versions_are_padded() {
+        assert_eq!(rust_version_parts("1.46"), Some(vec![1, 46, 0]));
+        assert_eq!(rust_version_parts("1.46.1"), Some(vec![1, 46, 1]));
+        assert_eq!(rust_version_parts("1"), Some(vec![1, 0, 0]));
+        assert_eq!(rust_version_parts("1.46.0.0"), None);
+        assert_eq!(rust_version_parts("1.46-beta"), None);
+        assert_eq!(rust_version_parts("+1.46"), None);
+        assert_eq!(rust_version_parts(""), None);
+    }
+
+    #[test]
+    fn licenses_are_offered_by_alternatives() {
+        let accepted: HashSet<_> = vec!["MIT", "Zlib"].into_iter().collect();
+        assert!(offers_license("MIT", &accepted));
+        assert!(offers_license("Apache-2.0 OR MIT", &accepted));
+        assert!(offers_license("MIT/Apache-2.0", &accepted));
+        assert!(offers_license("(MIT OR Apache-2.0) AND Zlib", &accepted));
+        assert!(offers_license(
+            "GPL-2.0 WITH Classpath-exception-2.0 OR MIT",
+            &accepted
+        ));
+        assert!(!offers_license("MIT AND Apache-2.0", &accepted));
+        assert!(!offers_license("GPL-3.0", &accepted));
+        assert!(!offers_license("MIT OR", &accepted));
+        assert!(!offers_license("(MIT", &accepted));
+    }
+
+    #[test]
+    fn license_parameters_are_limited() {
+        let licenses = vec!["MIT".to_string(), "Apache-2.0 OR Zlib".to_string()];
+        let accepted = accepted_licenses(&licenses).unwrap();
+        assert_eq!(accepted.len(), 3);
+        assert!(accepted.contains("Apache-2.0"));
+
+        assert!(accepted_licenses(&["MIT OR".to_string()]).is_err());
+        assert!(accepted_licenses(&["MIT ".repeat(100)]).is_err());
+
+        // The alternatives of groups are evaluated, not listed
+        let groups = vec!["(MIT OR Zlib)"; 25].join(" AND ");
+        assert!(offers_license(&groups, &["MIT"].iter().copied().collect()));
+        assert!(accepted_licenses(&[groups]).is_err());
+    }
+
+    #[test]
+    fn prefixes_are_escaped() {
+        assert_eq!(prefix_pattern("Serde-J"), "serde\\_j%");
+        assert_eq!(prefix_pattern("a%b\\"), "a\\%b\\\\%");
+    }
+
+    #[test]
+    fn matches_are_marked() {
+        let terms = query_terms("Serde JSON");
+        assert_eq!(terms, ["serde", "json"]);
+        assert_eq!(
+            highlight("serde_json", &terms, None).unwrap(),
+            "<mark>serde</mark>_<mark>json</mark>"
+        );
+        assert_eq!(
+            highlight("A <JSON> parser for Serde", &terms, None).unwrap(),
+            "A &lt;<mark>JSON</mark>&gt; parser for <mark>Serde</mark>"
+        );
+        assert_eq!(highlight("toml", &terms, None), None);
+
+        let terms = query_terms("pars parser");
+        assert_eq!(
+            highlight("parsers", &terms, None).unwrap(),
+            "<mark>parser</mark>s"
+        );
+    }
+
+    #[test]
+    fn long_texts_are_shortened_around_the_first_match() {
+        let text = format!(
+            "{}the serde crate {}",
+            "word ".repeat(20),
+            "word ".repeat(60)
+        );
+        let fragment = highlight(&text, &query_terms("serde"), Some(60)).unwrap();
+        assert_eq!(
+            fragment,
+            "…word word word word word word word the <mark>serde</mark> crate word word…"
+        );
+    }
+}
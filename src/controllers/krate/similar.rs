@@ -0,0 +1,56 @@
+//! Endpoint for finding the crates whose names can be confused with a name
+//!
+//! Names are compared by their skeleton, computed by the `crate_name_skeleton` SQL function:
+//! lowercase, without `-` and `_`, and with the characters that look alike, like `0` and `o` or
+//! `rn` and `m`, replaced by one of them. Crates are indexed by the skeleton of their name, so
+//! that admins and users reviewing typosquatting can look up any name.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::krate::{canon_crate_name, crate_name_skeleton};
+use crate::models::Crate;
+use crate::schema::crates;
+use crate::views::EncodableSimilarCrate;
+
+/// Handles the `GET /crates/:crate_id/similar` route.
+///
+/// The crate doesn't need to exist, so that a name can be checked before publishing it. The
+/// crate with this name, compared without case and with `-` and `_` being the same, isn't
+/// listed, and the others are sorted by downloads.
+pub fn similar(req: &mut dyn RequestExt) -> EndpointResult {
+    let name = &req.params()["crate_id"];
+    if !Crate::valid_name(name) {
+        return Err(bad_request(&format_args!("invalid crate name `{}`", name)));
+    }
+
+    let conn = req.db_read_only()?;
+    let similar: Vec<Crate> = Crate::all()
+        .filter(crate_name_skeleton(crates::name).eq(crate_name_skeleton(name)))
+        .filter(canon_crate_name(crates::name).ne(canon_crate_name(name)))
+        .order((crates::downloads.desc(), crates::name))
+        .load(&*conn)?;
+
+    let without_separators = |name: &str| name.to_lowercase().replace(&['-', '_'][..], "");
+    let crates = similar
+        .into_iter()
+        .map(|krate| {
+            let collision = if without_separators(&krate.name) == without_separators(name) {
+                "separators"
+            } else {
+                "homoglyphs"
+            };
+            EncodableSimilarCrate {
+                name: krate.name,
+                downloads: krate.downloads,
+                created_at: krate.created_at,
+                collision: collision.into(),
+            }
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableSimilarCrate>,
+    }
+    Ok(req.json(&R { crates }))
+}
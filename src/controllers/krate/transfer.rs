@@ -0,0 +1,243 @@
+//! Endpoints for transferring the ownership of a crate
+//!
+//! An owner requests the transfer of a crate to a user, who must accept it through the token
+//! emailed to them within `TRANSFER_EXPIRATION_DAYS` days. Accepting it makes the target the
+//! only owner of the crate. All the owners are told about the transfers, so that an accidental
+//! or hostile request can be cancelled before it is accepted.
+
+use std::io::Read;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::email;
+use crate::models::{
+    Crate, CrateOwner, CrateOwnershipTransfer, NewCrateOwnershipTransfer, Owner, OwnerKind,
+    RegistryEvent, RegistryEventKind, Rights, User, TRANSFER_EXPIRATION_DAYS,
+};
+use crate::schema::{
+    crate_owner_invitations, crate_owners, crate_ownership_transfers, crates, users,
+};
+use crate::views::EncodableCrateOwnershipTransfer;
+
+/// Handles the `PUT /crates/:crate_id/transfer` route.
+///
+/// The body names the target of the transfer: `{"user": "username"}`. The pending transfer of
+/// the crate, if there is one, is replaced.
+pub fn request(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct Request {
+        user: String,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user = req.authenticate()?.user();
+    let crate_name = &req.params()["crate_id"];
+    let app = req.app();
+    let conn = req.db_conn()?;
+
+    conn.transaction(|| {
+        let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+        let owners = krate.owners(&conn)?;
+        if user.rights(app, &conn, &owners)? < Rights::Full {
+            return Err(cargo_err("only owners have permission to transfer a crate"));
+        }
+
+        let target: User = users::table
+            .filter(users::gh_login.eq(&request.user))
+            .filter(users::gh_id.ne(-1))
+            .order(users::gh_id.desc())
+            .first(&*conn)
+            .optional()?
+            .ok_or_else(|| {
+                cargo_err(&format_args!(
+                    "could not find user with login `{}`",
+                    request.user
+                ))
+            })?;
+        if target.id == user.id {
+            return Err(cargo_err("a crate can't be transferred to its requester"));
+        }
+        let target_email = target.verified_email(&conn)?.ok_or_else(|| {
+            cargo_err(&format_args!(
+                "`{}` has no verified email address to accept the transfer with",
+                target.gh_login
+            ))
+        })?;
+
+        let transfer = NewCrateOwnershipTransfer {
+            crate_id: krate.id,
+            requested_by_user_id: user.id,
+            target_user_id: target.id,
+        }
+        .save(&conn)?;
+
+        email::send_ownership_transfer_email(
+            &target_email,
+            &user.gh_login,
+            &krate.name,
+            &transfer.token,
+            TRANSFER_EXPIRATION_DAYS,
+        );
+        for owner in &owners {
+            if let Owner::User(owner) = owner {
+                if let Ok(Some(email)) = owner.verified_email(&conn) {
+                    email::send_ownership_transfer_requested_email(
+                        &email,
+                        &user.gh_login,
+                        &krate.name,
+                        &target.gh_login,
+                    );
+                }
+            }
+        }
+
+        #[derive(Serialize)]
+        struct R {
+            crate_ownership_transfer: EncodableCrateOwnershipTransfer,
+        }
+        Ok(req.json(&R {
+            crate_ownership_transfer: transfer.encodable(&conn),
+        }))
+    })
+}
+
+/// Handles the `DELETE /crates/:crate_id/transfer` route.
+pub fn cancel(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let crate_name = &req.params()["crate_id"];
+    let app = req.app();
+    let conn = req.db_conn()?;
+
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    if user.rights(app, &conn, &krate.owners(&conn)?)? < Rights::Full {
+        return Err(cargo_err(
+            "only owners have permission to cancel the transfer of a crate",
+        ));
+    }
+
+    let deleted =
+        diesel::delete(crate_ownership_transfers::table.find(krate.id)).execute(&*conn)?;
+    if deleted == 0 {
+        return Err(bad_request("no transfer of this crate is pending"));
+    }
+
+    ok_true()
+}
+
+/// Handles the `PUT /me/crate_ownership_transfers/accept/:token` route.
+///
+/// Replaces all the owners of the crate, users and teams, by the target of the transfer. The
+/// pending owner invitations of the crate are dropped as well, so that the previous owners
+/// can't come back through them.
+///
+/// The transfer is locked while it is accepted, so that it can't be cancelled or replaced in
+/// the meantime, and it is dropped if its requester isn't an owner of the crate anymore.
+pub fn accept(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_conn()?;
+    let req_token = &req.params()["token"];
+
+    enum Outcome {
+        Expired,
+        RequesterRemoved,
+        Accepted(Crate, Vec<Owner>, Owner),
+    }
+
+    let outcome = conn.transaction(|| {
+        let transfer: CrateOwnershipTransfer = crate_ownership_transfers::table
+            .filter(crate_ownership_transfers::token.eq(req_token))
+            .for_update()
+            .first(&*conn)?;
+        if transfer.is_expired() {
+            diesel::delete(&transfer).execute(&*conn)?;
+            return Ok(Outcome::Expired);
+        }
+
+        let krate: Crate = crates::table.find(transfer.crate_id).first(&*conn)?;
+        let target = User::find(&conn, transfer.target_user_id)?;
+        let owners = krate.owners(&conn)?;
+
+        let requester_is_owner = owners.iter().any(
+            |owner| matches!(owner, Owner::User(user) if user.id == transfer.requested_by_user_id),
+        );
+        if !requester_is_owner {
+            diesel::delete(&transfer).execute(&*conn)?;
+            return Ok(Outcome::RequesterRemoved);
+        }
+
+        let mut previous_owners = Vec::new();
+        let mut already_owner = false;
+        for owner in owners {
+            if let Owner::User(ref user) = owner {
+                if user.id == target.id {
+                    already_owner = true;
+                    continue;
+                }
+            }
+            let row = crate_owners::table.find((krate.id, owner.id(), owner.kind()));
+            diesel::update(row)
+                .set(crate_owners::deleted.eq(true))
+                .execute(&*conn)?;
+            RegistryEvent::record_owner(
+                &conn,
+                RegistryEventKind::OwnerRemove,
+                &krate.name,
+                &owner,
+            )?;
+            previous_owners.push(owner);
+        }
+
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: target.id,
+                created_by: transfer.requested_by_user_id,
+                owner_kind: OwnerKind::User as i32,
+                email_notifications: true,
+            })
+            .on_conflict(crate_owners::table.primary_key())
+            .do_update()
+            .set(crate_owners::deleted.eq(false))
+            .execute(&*conn)?;
+        let target = Owner::User(target);
+        if !already_owner {
+            RegistryEvent::record_owner(&conn, RegistryEventKind::OwnerAdd, &krate.name, &target)?;
+        }
+
+        diesel::delete(
+            crate_owner_invitations::table.filter(crate_owner_invitations::crate_id.eq(krate.id)),
+        )
+        .execute(&*conn)?;
+        diesel::delete(&transfer).execute(&*conn)?;
+
+        Ok(Outcome::Accepted(krate, previous_owners, target))
+    })?;
+
+    let (krate, previous_owners, target) = match outcome {
+        Outcome::Expired => {
+            return Err(bad_request(&format_args!(
+                "the transfer expired, transfers must be accepted within {} days",
+                TRANSFER_EXPIRATION_DAYS
+            )));
+        }
+        Outcome::RequesterRemoved => {
+            return Err(bad_request(
+                "the transfer was requested by a user who isn't an owner of the crate anymore",
+            ));
+        }
+        Outcome::Accepted(krate, previous_owners, target) => (krate, previous_owners, target),
+    };
+
+    for owner in &previous_owners {
+        if let Owner::User(owner) = owner {
+            if let Ok(Some(email)) = owner.verified_email(&conn) {
+                email::send_ownership_transferred_email(&email, &krate.name, target.login());
+            }
+        }
+    }
+
+    ok_true()
+}
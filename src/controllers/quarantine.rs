@@ -0,0 +1,143 @@
+//! Endpoints for the admins of the instance to review the versions held as spam
+//!
+//! See the `spam` module for how versions end up in quarantine.
+
+use swirl::Job;
+
+use super::frontend_prelude::*;
+use super::helpers::require_admin;
+
+use crate::cache;
+use crate::git;
+use crate::models::QuarantinedVersion;
+use crate::render::RenderingKind;
+use crate::schema::{crates, quarantined_versions, users, versions};
+use crate::views::EncodableQuarantinedVersion;
+
+/// Handles the `GET /admin/quarantined_versions` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    let held = quarantined_versions::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .left_join(users::table.on(versions::published_by.eq(users::id.nullable())))
+        .select((
+            quarantined_versions::all_columns,
+            crates::name,
+            versions::num,
+            users::gh_login.nullable(),
+        ))
+        .order(quarantined_versions::created_at)
+        .load::<(QuarantinedVersion, String, String, Option<String>)>(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        quarantined_versions: Vec<EncodableQuarantinedVersion>,
+    }
+    Ok(req.json(&R {
+        quarantined_versions: held
+            .into_iter()
+            .map(
+                |(quarantined, crate_name, num, published_by)| EncodableQuarantinedVersion {
+                    version_id: quarantined.version_id,
+                    crate_name,
+                    num,
+                    published_by,
+                    spam_score: quarantined.spam_score,
+                    reasons: quarantined.reasons,
+                    created_at: quarantined.created_at,
+                },
+            )
+            .collect(),
+    }))
+}
+
+/// Handles the `PUT /admin/quarantined_versions/:version_id/approve` route.
+///
+/// Adds the version to the index, and renders its READMEs and changelog.
+pub fn approve(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    let quarantined = find(req, &conn)?;
+    let index_entry = quarantined
+        .index_entry()
+        .map_err(|e| server_error(&format_args!("invalid index entry: {}", e)))?;
+    let renderings = quarantined
+        .renderings()
+        .map_err(|e| server_error(&format_args!("invalid renderings: {}", e)))?;
+    let crate_name = index_entry.name.clone();
+    conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        diesel::delete(&quarantined).execute(&*conn)?;
+        for rendering in renderings {
+            rendering.enqueue(&conn, quarantined.version_id)?;
+        }
+        git::add_crate(index_entry).enqueue(&conn)?;
+        Ok(())
+    })?;
+
+    let app = req.app();
+    cache::invalidate_crate(app.cache.as_deref(), &crate_name);
+    ok_true()
+}
+
+/// Handles the `DELETE /admin/quarantined_versions/:version_id` route.
+///
+/// Deletes the version and its uploaded files, and the crate if it has no other version.
+pub fn reject(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let app = req.app();
+    let conn = req.db_conn()?;
+    let quarantined = find(req, &conn)?;
+    let (crate_id, crate_name, num) = versions::table
+        .find(quarantined.version_id)
+        .inner_join(crates::table)
+        .select((crates::id, crates::name, versions::num))
+        .first::<(i32, String, String)>(&*conn)?;
+
+    // The files are deleted first, so that the version can be rejected again if this fails
+    let renderings = quarantined.renderings().unwrap_or_default();
+    let languages = renderings
+        .iter()
+        .filter_map(|rendering| match &rendering.kind {
+            RenderingKind::LocalizedReadme { language } => Some(language.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    app.config
+        .uploader
+        .delete_version_files(app.http_client(), &crate_name, &num, &languages)
+        .map_err(|e| server_error(&format_args!("failed to delete the files: {}", e)))?;
+
+    conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        diesel::delete(versions::table.find(quarantined.version_id)).execute(&*conn)?;
+        let remaining = versions::table
+            .filter(versions::crate_id.eq(crate_id))
+            .count()
+            .get_result::<i64>(&*conn)?;
+        if remaining == 0 {
+            diesel::delete(crates::table.find(crate_id)).execute(&*conn)?;
+        }
+        Ok(())
+    })?;
+
+    cache::invalidate_crate(app.cache.as_deref(), &crate_name);
+    cache::invalidate_listings(app.cache.as_deref());
+    ok_true()
+}
+
+fn find(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<QuarantinedVersion> {
+    let version_id = req.params()["version_id"]
+        .parse::<i32>()
+        .map_err(|_| bad_request("invalid version id"))?;
+    quarantined_versions::table
+        .find(version_id)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| bad_request("this version is not held for review"))
+}
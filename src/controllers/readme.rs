@@ -0,0 +1,67 @@
+//! Endpoint for authors to preview the rendering of a README before publishing it
+
+use super::frontend_prelude::*;
+
+use crate::rate_limit::LimitedAction;
+use crate::render::{readme_to_html_limited, stored_default_branch};
+use crate::util::LimitErrorReader;
+
+use std::io::Read;
+
+/// The README to preview, with the fields of the metadata sent by `cargo publish`
+#[derive(Deserialize)]
+struct PreviewRequest {
+    readme: String,
+    readme_file: Option<String>,
+    repository: Option<String>,
+}
+
+/// Handles the `PUT /readme/preview` route.
+///
+/// Renders a README to the sanitized HTML a version published with it would get, including
+/// the relative links resolved against its repository. Previews are rate limited per user.
+///
+/// The default branch of the repository is only used if it was already fetched while rendering
+/// a published version, so that previews don't make the server send requests to other hosts.
+pub fn preview(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+
+    // The JSON escapes can make the body up to 6 times larger than the README itself
+    let max_size = req.app().config.render.limits.max_size as u64 * 6 + 1024;
+    let mut body = String::new();
+    LimitErrorReader::new(req.body(), max_size)
+        .read_to_string(&mut body)
+        .map_err(|_| bad_request("the README is too large to be previewed"))?;
+    let request: PreviewRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let app = req.app();
+    let conn = req.db_conn()?;
+    app.config.readme_preview_rate_limit.check_rate_limit(
+        LimitedAction::ReadmePreview,
+        user.id,
+        &conn,
+    )?;
+
+    let settings = &app.config.render;
+    let repository = request.repository.as_deref();
+    let default_branch = match repository {
+        Some(repository) => stored_default_branch(&conn, repository)?,
+        None => None,
+    };
+    let html = readme_to_html_limited(
+        &request.readme,
+        request.readme_file.as_deref().unwrap_or("README.md"),
+        repository,
+        default_branch.as_deref(),
+        None,
+        Some(settings),
+    )
+    .map_err(|e| cargo_err(&e.to_string()))?;
+
+    #[derive(Serialize)]
+    struct R {
+        html: String,
+    }
+    Ok(req.json(&R { html }))
+}
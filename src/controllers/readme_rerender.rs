@@ -0,0 +1,59 @@
+//! Endpoints for the admins of the instance to render all the READMEs again
+//!
+//! The HTML of READMEs is rendered once when a version is published, so changes of the
+//! renderer or of the sanitizer only apply to the existing READMEs once they are rendered
+//! again by the `rerender_readmes` background job.
+
+use super::frontend_prelude::*;
+use super::helpers::require_admin;
+
+use crate::models::ReadmeRerender;
+use crate::tasks::enqueue_readme_rerender;
+use crate::views::EncodableReadmeRerender;
+
+/// The number of re-renderings listed, most recent first
+const LISTED_RERENDERS: i64 = 10;
+
+/// Handles the `GET /admin/readme_rerenders` route.
+///
+/// Lists the latest re-renderings along with their progress.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    let rerenders = ReadmeRerender::recent(&conn, LISTED_RERENDERS)?;
+
+    #[derive(Serialize)]
+    struct R {
+        readme_rerenders: Vec<EncodableReadmeRerender>,
+    }
+    Ok(req.json(&R {
+        readme_rerenders: rerenders
+            .into_iter()
+            .map(ReadmeRerender::encodable)
+            .collect(),
+    }))
+}
+
+/// Handles the `POST /admin/readme_rerenders` route.
+///
+/// Starts rendering all the READMEs again, unless a re-rendering is already in progress.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    if ReadmeRerender::running(&conn)?.is_some() {
+        return Err(cargo_err("the READMEs are already being rendered again"));
+    }
+    let rerender = enqueue_readme_rerender(&conn, user.id)?;
+
+    #[derive(Serialize)]
+    struct R {
+        readme_rerender: EncodableReadmeRerender,
+    }
+    Ok(req.json(&R {
+        readme_rerender: rerender.encodable(),
+    }))
+}
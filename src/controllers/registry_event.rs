@@ -0,0 +1,67 @@
+//! Endpoint for following the changes to the crates of this instance
+//!
+//! Every publish, yank, unyank and change of ownership is recorded as an event
+//! with an increasing `id`. Other instances can replay the events which happened
+//! after the last one they have seen to follow this instance.
+//!
+//! The `id` of an event is allocated before its transaction commits, so an event
+//! can show up after events with higher ids. Followers should read the recent
+//! events again and skip the ones they have already replayed, like
+//! `UpstreamRegistry::follow_events` does.
+
+use super::frontend_prelude::*;
+
+use crate::models::RegistryEvent;
+use crate::schema::registry_events;
+use crate::views::EncodableRegistryEvent;
+
+const MAX_PER_PAGE: i64 = 100;
+
+/// Handles the `GET /events` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::max;
+
+    let query = req.query();
+    let since = match query.get("since") {
+        Some(since) => since
+            .parse()
+            .map_err(|_| bad_request("`since` must be the id of an event"))?,
+        None => 0,
+    };
+    let per_page = match query.get("per_page") {
+        Some(per_page) => per_page
+            .parse()
+            .ok()
+            .filter(|per_page| (1..=MAX_PER_PAGE).contains(per_page))
+            .ok_or_else(|| {
+                bad_request(&format_args!(
+                    "`per_page` must be between 1 and {}",
+                    MAX_PER_PAGE
+                ))
+            })?,
+        None => MAX_PER_PAGE,
+    };
+
+    let conn = req.db_read_only()?;
+    let events = RegistryEvent::since(&conn, since, per_page)?
+        .into_iter()
+        .map(RegistryEvent::encodable)
+        .collect();
+    let latest_id = registry_events::table
+        .select(max(registry_events::id))
+        .get_result(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        events: Vec<EncodableRegistryEvent>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        latest_id: Option<i64>,
+    }
+    Ok(req.json(&R {
+        events,
+        meta: Meta { latest_id },
+    }))
+}
@@ -0,0 +1,128 @@
+//! Endpoints for the admins of the instance to reserve crate names
+//!
+//! Reserved names, like the ones of the crates of the standard library or common typos of
+//! popular crates, are refused at publish time with the reason given by the admin.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use super::frontend_prelude::*;
+use super::helpers::require_admin;
+
+use crate::models::{Crate, ReservedCrateName};
+use crate::schema::users;
+use crate::views::EncodableReservedCrateName;
+
+/// Handles the `GET /admin/reserved_crate_names` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let conn = req.db_conn()?;
+    let names = ReservedCrateName::all(&conn)?;
+    let names = encodable_names(&conn, names)?;
+
+    #[derive(Serialize)]
+    struct R {
+        reserved_crate_names: Vec<EncodableReservedCrateName>,
+    }
+    Ok(req.json(&R {
+        reserved_crate_names: names,
+    }))
+}
+
+/// Handles the `PUT /admin/reserved_crate_names/:name` route.
+///
+/// Expects a body of the form `{"reason": "part of the standard library"}`, the reason being
+/// optional.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct Request {
+        #[serde(default)]
+        reason: Option<String>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    let reason = request
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let name = req.params()["name"].clone();
+    if !Crate::valid_name(&name) {
+        return Err(bad_request(&format_args!("invalid crate name `{}`", name)));
+    }
+
+    let conn = req.db_conn()?;
+    if Crate::by_name(&name)
+        .first::<Crate>(&*conn)
+        .optional()?
+        .is_some()
+    {
+        return Err(bad_request(&format_args!(
+            "the crate `{}` already exists",
+            name
+        )));
+    }
+    let reserved = ReservedCrateName::reserve(&conn, &name, reason, user.id)?;
+    let reserved = encodable_names(&conn, vec![reserved])?.remove(0);
+
+    #[derive(Serialize)]
+    struct R {
+        reserved_crate_name: EncodableReservedCrateName,
+    }
+    Ok(req.json(&R {
+        reserved_crate_name: reserved,
+    }))
+}
+
+/// Handles the `DELETE /admin/reserved_crate_names/:name` route.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    require_admin(&user)?;
+
+    let name = req.params()["name"].clone();
+    let conn = req.db_conn()?;
+    if ReservedCrateName::delete(&conn, &name)? == 0 {
+        return Err(bad_request(&format_args!(
+            "the name `{}` is not reserved",
+            name
+        )));
+    }
+    ok_true()
+}
+
+fn encodable_names(
+    conn: &PgConnection,
+    names: Vec<ReservedCrateName>,
+) -> AppResult<Vec<EncodableReservedCrateName>> {
+    let admin_ids = names
+        .iter()
+        .filter_map(|reserved| reserved.reserved_by)
+        .collect::<Vec<_>>();
+    let admin_logins = users::table
+        .filter(users::id.eq_any(admin_ids))
+        .select((users::id, users::gh_login))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    Ok(names
+        .into_iter()
+        .map(|reserved| EncodableReservedCrateName {
+            reserved_by: reserved
+                .reserved_by
+                .and_then(|id| admin_logins.get(&id).cloned()),
+            name: reserved.name,
+            reason: reserved.reason,
+            created_at: reserved.created_at,
+        })
+        .collect())
+}
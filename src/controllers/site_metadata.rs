@@ -1,12 +1,14 @@
 use super::prelude::*;
 
+use crate::migrations;
+
 /// Returns the JSON representation of the current deployed commit sha.
 ///
 /// The sha is contained within the `HEROKU_SLUG_COMMIT` environment variable.
 /// If `HEROKU_SLUG_COMMIT` is not set, returns `"unknown"`.
 pub fn show_deployed_sha(req: &mut dyn RequestExt) -> EndpointResult {
     let deployed_sha =
-        dotenv::var("HEROKU_SLUG_COMMIT").unwrap_or_else(|_| String::from("unknown"));
+        crate::config::var("HEROKU_SLUG_COMMIT").unwrap_or_else(|_| String::from("unknown"));
 
     #[derive(Serialize)]
     struct R<'a> {
@@ -18,3 +20,26 @@ pub fn show_deployed_sha(req: &mut dyn RequestExt) -> EndpointResult {
         commit: &deployed_sha[..],
     }))
 }
+
+/// Handles the `GET /api/private/ready` route.
+///
+/// Returns a 503 listing the missing migrations if the schema of the database is older than
+/// this code, see the `migrations` module.
+pub fn readiness(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_conn()?;
+    let missing_migrations = migrations::missing_migrations(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ready: bool,
+        missing_migrations: Vec<&'static str>,
+    }
+    let mut response = req.json(&R {
+        ready: missing_migrations.is_empty(),
+        missing_migrations: missing_migrations.clone(),
+    });
+    if !missing_migrations.is_empty() {
+        *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    }
+    Ok(response)
+}
@@ -4,14 +4,24 @@ use crate::controllers::frontend_prelude::*;
 
 use crate::controllers::helpers::*;
 use crate::email;
+use crate::email_blocklist;
 
 use crate::controllers::helpers::pagination::Paginated;
 use crate::models::{
     CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    VersionYankEvent,
 };
 use crate::schema::{crate_owners, crates, emails, follows, users, versions};
 use crate::views::{EncodableMe, EncodableVersion, OwnedCrate};
 
+const DISPOSABLE_EMAIL_REJECTED: &str =
+    "email addresses of disposable email providers are not accepted";
+
+/// Matches the first release of each semver-compatible series, like `2.0.0`, `0.3.0` or `0.0.4`.
+/// Pre-releases are left out, and build metadata is ignored.
+const SEMVER_MAJOR_RELEASE: &str =
+    r"versions.num ~ '^([1-9][0-9]*\.0\.0|0\.[1-9][0-9]*\.0|0\.0\.[0-9]+)(\+.*)?$'";
+
 /// Handles the `GET /me` route.
 pub fn me(req: &mut dyn RequestExt) -> EndpointResult {
     let user_id = req.authenticate()?.user_id();
@@ -52,24 +62,42 @@ pub fn me(req: &mut dyn RequestExt) -> EndpointResult {
 }
 
 /// Handles the `GET /me/updates` route.
+///
+/// The versions of the followed crates can be narrowed down with `?filter=major_only`, to the
+/// releases breaking semver compatibility, or with `?filter=security_only`, to the yanked
+/// versions.
 pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
-    use diesel::dsl::any;
+    use diesel::dsl::{any, sql};
+    use diesel::sql_types::Bool;
 
     let authenticated_user = req.authenticate()?;
     let conn = req.db_conn()?;
     let user = authenticated_user.user();
 
     let followed_crates = Follow::belonging_to(&user).select(follows::crate_id);
-    let data: Paginated<(Version, String, Option<User>)> = versions::table
+    let mut query = versions::table
         .inner_join(crates::table)
         .left_outer_join(users::table)
         .filter(crates::id.eq(any(followed_crates)))
-        .order(versions::created_at.desc())
         .select((
             versions::all_columns,
             crates::name,
             users::all_columns.nullable(),
         ))
+        .into_boxed();
+    query = match req.query().get("filter").map(String::as_str) {
+        None => query,
+        Some("major_only") => query.filter(sql::<Bool>(SEMVER_MAJOR_RELEASE)),
+        Some("security_only") => query.filter(versions::yanked.eq(true)),
+        Some(filter) => {
+            return Err(bad_request(&format_args!(
+                "unknown filter `{}`, expected `major_only` or `security_only`",
+                filter
+            )))
+        }
+    };
+    let data: Paginated<(Version, String, Option<User>)> = query
+        .order(versions::created_at.desc())
         .paginate(&req.query())?
         .load(&*conn)?;
     let more = data.next_page_params().is_some();
@@ -77,14 +105,17 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     let data = data
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, cn, pb), voas)| (v, cn, pb, voas))
+        .zip(VersionYankEvent::for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, cn, pb), voas), yes)| (v, cn, pb, voas, yes))
         .collect::<Vec<_>>();
 
     let versions = data
         .into_iter()
-        .map(|(version, crate_name, published_by, actions)| {
-            version.encodable(&crate_name, published_by, actions)
-        })
+        .map(
+            |(version, crate_name, published_by, actions, yank_events)| {
+                version.encodable(&crate_name, published_by, actions, yank_events)
+            },
+        )
         .collect();
 
     #[derive(Serialize)]
@@ -143,6 +174,10 @@ pub fn update_user(req: &mut dyn RequestExt) -> EndpointResult {
         return Err(bad_request("empty email rejected"));
     }
 
+    if email_blocklist::is_blocked(&conn, user_email)? {
+        return Err(bad_request(DISPOSABLE_EMAIL_REJECTED));
+    }
+
     conn.transaction::<_, Box<dyn AppError>, _>(|| {
         let new_email = NewEmail {
             user_id: user.id,
@@ -173,6 +208,18 @@ pub fn confirm_user_email(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_conn()?;
     let req_token = &req.params()["email_token"];
 
+    // The domain may have been blocked since the email was set
+    let email = emails::table
+        .filter(emails::token.eq(req_token))
+        .select(emails::email)
+        .first::<String>(&*conn)
+        .optional()?;
+    if let Some(email) = email {
+        if email_blocklist::is_blocked(&conn, &email)? {
+            return Err(bad_request(DISPOSABLE_EMAIL_REJECTED));
+        }
+    }
+
     let updated_rows = update(emails::table.filter(emails::token.eq(req_token)))
         .set(emails::verified.eq(true))
         .execute(&*conn)?;
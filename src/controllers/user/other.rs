@@ -1,9 +1,18 @@
+use std::collections::HashSet;
+
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{CrateOwner, OwnerKind, User};
-use crate::schema::{crate_owners, crates, users};
+use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::Paginate;
+use crate::controllers::krate::metadata::encode_crates;
+use crate::controllers::util::AuthenticatedUser;
+use crate::models::krate::ALL_COLUMNS;
+use crate::models::{Crate, CrateOwner, OwnerKind, User};
+use crate::schema::{
+    crate_owners, crates, recent_crate_downloads, team_memberships, users, versions,
+};
 use crate::util::errors::ChainError;
-use crate::views::EncodablePublicUser;
+use crate::views::{EncodableCrateRole, EncodablePublicUser, EncodableUserCrate};
 
 /// Handles the `GET /users/:user_id` route.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
@@ -49,3 +58,145 @@ pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
         total_downloads: data,
     }))
 }
+
+/// Handles the `GET /users/:user_id/crates` route.
+///
+/// Lists the crates the user owns, the crates owned by the teams the user is a member of, and
+/// the crates the user published a version of. With `?include=role`, each crate says which of
+/// these the user is, owners first. The crates are sorted like searches, by `name` by default.
+///
+/// The teams the user is a member of aren't public, so the crates owned by these teams are only
+/// listed to the user themself.
+pub fn crates(req: &mut dyn RequestExt) -> EndpointResult {
+    // Don't require that authentication succeed, because it's only necessary to list the crates
+    // of the teams of the user
+    let authenticated_user: AppResult<AuthenticatedUser> = req.authenticate();
+    let name = &req.params()["user_id"].to_lowercase();
+    let conn = req.db_read_only()?;
+    let user: User = users::table
+        .filter(crate::lower(users::gh_login).eq(name))
+        .order(users::id.desc())
+        .first(&*conn)?;
+    let include_teams = authenticated_user.map_or(false, |auth| auth.user_id() == user.id);
+
+    let params = req.query();
+    let include_role = params
+        .get("include")
+        .map_or(false, |include| include.split(',').any(|i| i == "role"));
+
+    let owned = || {
+        CrateOwner::by_owner_kind(OwnerKind::User)
+            .select(crate_owners::crate_id)
+            .filter(crate_owners::owner_id.eq(user.id))
+    };
+    let owned_by_teams = || {
+        CrateOwner::by_owner_kind(OwnerKind::Team)
+            .select(crate_owners::crate_id)
+            .filter(
+                crate_owners::owner_id.eq_any(
+                    team_memberships::table
+                        .select(team_memberships::team_id)
+                        .filter(team_memberships::user_id.eq(user.id))
+                        .filter(team_memberships::active.eq(true)),
+                ),
+            )
+    };
+    let published = || {
+        versions::table
+            .select(versions::crate_id)
+            .filter(versions::published_by.eq(user.id))
+    };
+
+    let mut query = crates::table
+        .left_join(recent_crate_downloads::table)
+        .select((ALL_COLUMNS, recent_crate_downloads::downloads.nullable()))
+        .into_boxed();
+    query = if include_teams {
+        query.filter(
+            crates::id
+                .eq_any(owned())
+                .or(crates::id.eq_any(owned_by_teams()))
+                .or(crates::id.eq_any(published())),
+        )
+    } else {
+        query.filter(
+            crates::id
+                .eq_any(owned())
+                .or(crates::id.eq_any(published())),
+        )
+    };
+    query = match params.get("sort").map(String::as_str) {
+        Some("downloads") => query.order(crates::downloads.desc()),
+        Some("recent-downloads") => {
+            query.order(recent_crate_downloads::downloads.desc().nulls_last())
+        }
+        Some("recent-updates") => query.order(crates::updated_at.desc()),
+        Some("new") => query.order(crates::created_at.desc()),
+        _ => query.order(crates::name.asc()),
+    };
+    let data: Paginated<(Crate, Option<i64>)> = query
+        .then_order_by(crates::name.asc())
+        .paginate(&params)?
+        .load(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+    let data = data.into_iter().collect::<Vec<_>>();
+    let roles = if include_role {
+        let ids = data.iter().map(|(krate, _)| krate.id).collect::<Vec<_>>();
+        let to_set = |ids: Vec<i32>| ids.into_iter().collect::<HashSet<_>>();
+        let owned = to_set(
+            owned()
+                .filter(crate_owners::crate_id.eq_any(&ids))
+                .load(&*conn)?,
+        );
+        let owned_by_teams = if include_teams {
+            to_set(
+                owned_by_teams()
+                    .filter(crate_owners::crate_id.eq_any(&ids))
+                    .load(&*conn)?,
+            )
+        } else {
+            HashSet::new()
+        };
+        ids.iter()
+            .map(|id| {
+                Some(if owned.contains(id) {
+                    EncodableCrateRole::Owner
+                } else if owned_by_teams.contains(id) {
+                    EncodableCrateRole::TeamMember
+                } else {
+                    EncodableCrateRole::Publisher
+                })
+            })
+            .collect()
+    } else {
+        vec![None; data.len()]
+    };
+    let crates = encode_crates(&conn, data)?
+        .into_iter()
+        .zip(roles)
+        .map(|(krate, role)| EncodableUserCrate { krate, role })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableUserCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+        prev_page: Option<String>,
+    }
+    Ok(req.json(&R {
+        crates,
+        meta: Meta {
+            total,
+            next_page,
+            prev_page,
+        },
+    }))
+}
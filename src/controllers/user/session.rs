@@ -1,20 +1,14 @@
 use crate::controllers::frontend_prelude::*;
 
-use crate::github;
 use conduit_cookie::RequestSession;
-use failure::Fail;
-use oauth2::reqwest::http_client;
-use oauth2::{AuthorizationCode, Scope, TokenResponse};
+use oauth2::AuthorizationCode;
 
 use crate::middleware::current_user::TrustedUserId;
-use crate::models::{NewUser, User};
-use crate::schema::users;
-use crate::util::errors::ReadOnlyMode;
 
 /// Handles the `GET /api/private/session/begin` route.
 ///
-/// This route will return an authorization URL for the GitHub OAuth flow including the crates.io
-/// `client_id` and a randomly generated `state` secret.
+/// This route will return an authorization URL for the OAuth flow of the authentication provider,
+/// GitHub by default, including the crates.io `client_id` and a randomly generated `state` secret.
 ///
 /// see <https://developer.github.com/v3/oauth/#redirect-users-to-request-github-access>
 ///
@@ -27,15 +21,10 @@ use crate::util::errors::ReadOnlyMode;
 /// }
 /// ```
 pub fn begin(req: &mut dyn RequestExt) -> EndpointResult {
-    let (url, state) = req
-        .app()
-        .github
-        .authorize_url(oauth2::CsrfToken::new_random)
-        .add_scope(Scope::new("read:org".to_string()))
-        .url();
+    let (url, state) = req.app().auth.authorize_url();
     let state = state.secret().to_string();
     req.session_mut()
-        .insert("github_oauth_state".to_string(), state.clone());
+        .insert("oauth_state".to_string(), state.clone());
 
     #[derive(Serialize)]
     struct R {
@@ -50,10 +39,10 @@ pub fn begin(req: &mut dyn RequestExt) -> EndpointResult {
 
 /// Handles the `GET /api/private/session/authorize` route.
 ///
-/// This route is called from the GitHub API OAuth flow after the user accepted or rejected
-/// the data access permissions. It will check the `state` parameter and then call the GitHub API
-/// to exchange the temporary `code` for an API token. The API token is returned together with
-/// the corresponding user information.
+/// This route is called from the OAuth flow of the authentication provider after the user
+/// accepted or rejected the data access permissions. It will check the `state` parameter and then
+/// call the provider to exchange the temporary `code` for an API token. The API token is returned
+/// together with the corresponding user information.
 ///
 /// see <https://developer.github.com/v3/oauth/#github-redirects-back-to-your-site>
 ///
@@ -85,27 +74,19 @@ pub fn authorize(req: &mut dyn RequestExt) -> EndpointResult {
     // Make sure that the state we just got matches the session state that we
     // should have issued earlier.
     {
-        let session_state = req.session_mut().remove(&"github_oauth_state".to_string());
+        let session_state = req.session_mut().remove(&"oauth_state".to_string());
         let session_state = session_state.as_deref();
         if Some(&state[..]) != session_state {
             return Err(bad_request("invalid state parameter"));
         }
     }
 
-    // Fetch the access token from GitHub using the code we just got
+    // Exchange the code we just got for the user's identity and create a user record
     let code = AuthorizationCode::new(code);
-    let token = req
+    let user = req
         .app()
-        .github
-        .exchange_code(code)
-        .request(http_client)
-        .map_err(|e| e.compat())
-        .chain_error(|| server_error("Error obtaining token"))?;
-    let token = token.access_token();
-
-    // Fetch the user info from GitHub using the access token we just got and create a user record
-    let ghuser = github::github_api::<GithubUser>(req.app(), "/user", token)?;
-    let user = ghuser.save_to_database(&token.secret(), &*req.db_conn()?)?;
+        .auth
+        .authenticate(req.app(), &*req.db_conn()?, code)?;
 
     // Log in by setting a cookie and the middleware authentication
     req.session_mut()
@@ -115,74 +96,8 @@ pub fn authorize(req: &mut dyn RequestExt) -> EndpointResult {
     super::me::me(req)
 }
 
-#[derive(Deserialize)]
-struct GithubUser {
-    email: Option<String>,
-    name: Option<String>,
-    login: String,
-    id: i32,
-    avatar_url: Option<String>,
-}
-
-impl GithubUser {
-    fn save_to_database(&self, access_token: &str, conn: &PgConnection) -> AppResult<User> {
-        NewUser::new(
-            self.id,
-            &self.login,
-            self.name.as_deref(),
-            self.avatar_url.as_deref(),
-            access_token,
-        )
-        .create_or_update(self.email.as_deref(), conn)
-        .map_err(Into::into)
-        .or_else(|e: Box<dyn AppError>| {
-            // If we're in read only mode, we can't update their details
-            // just look for an existing user
-            if e.is::<ReadOnlyMode>() {
-                users::table
-                    .filter(users::gh_id.eq(self.id))
-                    .first(conn)
-                    .optional()?
-                    .ok_or(e)
-            } else {
-                Err(e)
-            }
-        })
-    }
-}
-
 /// Handles the `DELETE /api/private/session` route.
 pub fn logout(req: &mut dyn RequestExt) -> EndpointResult {
     req.session_mut().remove(&"user_id".to_string());
     Ok(req.json(&true))
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn pg_connection() -> PgConnection {
-        let database_url =
-            dotenv::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set to run tests");
-        PgConnection::establish(&database_url).unwrap()
-    }
-
-    #[test]
-    fn gh_user_with_invalid_email_doesnt_fail() {
-        let conn = pg_connection();
-        let gh_user = GithubUser {
-            email: Some("String.Format(\"{0}.{1}@live.com\", FirstName, LastName)".into()),
-            name: Some("My Name".into()),
-            login: "github_user".into(),
-            id: -1,
-            avatar_url: None,
-        };
-        let result = gh_user.save_to_database("arbitrary_token", &conn);
-
-        assert!(
-            result.is_ok(),
-            "Creating a User from a GitHub user failed when it shouldn't have, {:?}",
-            result
-        );
-    }
-}
@@ -1,6 +1,8 @@
 pub mod deprecated;
+pub mod diff;
 pub mod downloads;
 pub mod metadata;
+pub mod rebuilds;
 pub mod yank;
 
 use super::prelude::*;
@@ -7,7 +7,7 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, User, Version, VersionOwnerAction};
+use crate::models::{Crate, User, Version, VersionOwnerAction, VersionYankEvent};
 use crate::schema::*;
 use crate::views::EncodableVersion;
 
@@ -31,6 +31,11 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
             users::all_columns.nullable(),
         ))
         .filter(versions::id.eq(any(ids)))
+        // The versions held for review are hidden
+        .filter(
+            versions::id
+                .ne_all(quarantined_versions::table.select(quarantined_versions::version_id)),
+        )
         .load(&*conn)?;
     let versions = versions_and_publishers
         .iter()
@@ -40,9 +45,12 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, crate_name, published_by), actions)| {
-            version.encodable(&crate_name, published_by, actions)
-        })
+        .zip(VersionYankEvent::for_versions(&conn, &versions)?.into_iter())
+        .map(
+            |(((version, crate_name, published_by), actions), yank_events)| {
+                version.encodable(&crate_name, published_by, actions, yank_events)
+            },
+        )
         .collect();
 
     #[derive(Serialize)]
@@ -61,6 +69,10 @@ pub fn show_by_id(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_conn()?;
     let (version, krate, published_by): (Version, Crate, Option<User>) = versions::table
         .find(id)
+        .filter(
+            versions::id
+                .ne_all(quarantined_versions::table.select(quarantined_versions::version_id)),
+        )
         .inner_join(crates::table)
         .left_outer_join(users::table)
         .select((
@@ -70,12 +82,13 @@ pub fn show_by_id(req: &mut dyn RequestExt) -> EndpointResult {
         ))
         .first(&*conn)?;
     let audit_actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let yank_events = VersionYankEvent::by_version(&conn, &version)?;
 
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
     Ok(req.json(&R {
-        version: version.encodable(&krate.name, published_by, audit_actions),
+        version: version.encodable(&krate.name, published_by, audit_actions, yank_events),
     }))
 }
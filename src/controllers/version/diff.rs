@@ -0,0 +1,64 @@
+//! Endpoint comparing two versions of a crate
+//!
+//! Supply-chain review tools check what changed between the version of a
+//! dependency they already audited and the one they upgrade to. Comparing the
+//! file listings of large crates is too slow for a request, so the diffs are
+//! computed by a background job and stored once they were first requested.
+
+use swirl::Job;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, VersionDiff};
+use crate::tasks;
+
+/// Handles the `GET /crates/:crate_id/diff` route.
+///
+/// The versions are given with `?from=` and `?to=`. Until the diff is computed the response
+/// has a `202 Accepted` status and a `null` diff, and should be requested again later.
+///
+/// Anyone can read the diffs which were already requested, but only authenticated users can
+/// request new ones, since they are computed by downloading both versions.
+pub fn diff(req: &mut dyn RequestExt) -> EndpointResult {
+    let query = req.query();
+    let (from, to) = match (query.get("from"), query.get("to")) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            return Err(bad_request(
+                "the `from` and `to` versions to compare are missing",
+            ))
+        }
+    };
+
+    let (from, to, existing) = {
+        let conn = req.db_read_only()?;
+        let krate: Crate = Crate::by_name(&req.params()["crate_id"]).first(&*conn)?;
+        let from = krate.find_version(&conn, from)?;
+        let to = krate.find_version(&conn, to)?;
+        let existing = VersionDiff::find(&conn, from.id, to.id)?;
+        (from, to, existing)
+    };
+
+    let diff = match existing {
+        Some(existing) => existing.diff,
+        None => {
+            req.authenticate()?;
+            let conn = req.db_conn()?;
+            if VersionDiff::request(&conn, from.id, to.id)? {
+                tasks::compute_version_diff(from.id, to.id).enqueue(&conn)?;
+            }
+            None
+        }
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        diff: Option<serde_json::Value>,
+    }
+    let pending = diff.is_none();
+    let mut response = req.json(&R { diff });
+    if pending {
+        *response.status_mut() = StatusCode::ACCEPTED;
+    }
+    Ok(response)
+}
@@ -6,8 +6,10 @@ use crate::controllers::prelude::*;
 
 use chrono::{Duration, NaiveDate, Utc};
 
+use crate::feature_flags::FeatureFlag;
 use crate::models::{Crate, VersionDownload};
 use crate::schema::*;
+use crate::util::errors::{bad_request, internal};
 use crate::views::EncodableVersionDownload;
 
 use super::{extract_crate_name, extract_semver};
@@ -18,7 +20,33 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
     let version = &req.params()["version"];
 
-    let (crate_name, was_counted) = increment_download_counts(req, crate_name, version)?;
+    if let Some(upstream) = &req.app().config.upstream {
+        let conn = req.db_conn()?;
+        let app = req.app();
+        upstream
+            .ensure_crate_file(
+                &conn,
+                app.http_client(),
+                &app.config.uploader,
+                Some(&app.upstream_lookups),
+                crate_name,
+                version,
+            )
+            .map_err(|e| {
+                internal(&format_args!(
+                    "failed to mirror `{}#{}` from the upstream registry: {}",
+                    crate_name, version, e
+                ))
+            })?;
+    }
+
+    // In degraded mode the crate name isn't normalized, since that needs the database
+    let degraded = req.app().is_feature_enabled(FeatureFlag::DegradedDownloads);
+    let (crate_name, was_counted) = if degraded {
+        (crate_name.to_string(), false)
+    } else {
+        increment_download_counts(req, crate_name, version)?
+    };
 
     let redirect_url = req
         .app()
@@ -73,28 +101,57 @@ fn increment_download_counts(
     Ok((crate_name, res.is_ok()))
 }
 
+/// How many days of downloads are returned by default
+const DEFAULT_DAYS: i64 = 90;
+/// How many days of downloads can be requested at most
+const MAX_DAYS: i64 = 365;
+
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
+///
+/// Returns the downloads of each of the `days` days (90 by default) up to `before_date`
+/// (today by default), including the days without any download.
 pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = extract_crate_name(req);
     let semver = extract_semver(req)?;
+    let query = req.query();
+    let days = match query.get("days") {
+        Some(days) => days.parse().map_err(|e| bad_request(&e))?,
+        None => DEFAULT_DAYS,
+    };
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(bad_request(&format_args!(
+            "days must be between 1 and {}",
+            MAX_DAYS
+        )));
+    }
 
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
     let version = krate.find_version(&conn, semver)?;
 
-    let cutoff_end_date = req
-        .query()
+    let cutoff_end_date = query
         .get("before_date")
         .and_then(|d| NaiveDate::parse_from_str(d, "%F").ok())
         .unwrap_or_else(|| Utc::today().naive_utc());
-    let cutoff_start_date = cutoff_end_date - Duration::days(89);
+    let cutoff_start_date = cutoff_end_date - Duration::days(days - 1);
 
-    let downloads = VersionDownload::belonging_to(&version)
+    let recorded: Vec<VersionDownload> = VersionDownload::belonging_to(&version)
         .filter(version_downloads::date.between(cutoff_start_date, cutoff_end_date))
         .order(version_downloads::date)
-        .load(&*conn)?
-        .into_iter()
-        .map(VersionDownload::encodable)
+        .load(&*conn)?;
+    let mut recorded = recorded.into_iter().peekable();
+    let downloads = (0..days)
+        .map(|day| {
+            let date = cutoff_start_date + Duration::days(day);
+            match recorded.peek() {
+                Some(download) if download.date == date => recorded.next().unwrap().encodable(),
+                _ => EncodableVersionDownload {
+                    version: version.id,
+                    downloads: 0,
+                    date: date.to_string(),
+                },
+            }
+        })
         .collect();
 
     #[derive(Serialize)]
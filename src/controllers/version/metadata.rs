@@ -4,11 +4,17 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use std::collections::BTreeMap;
+
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::VersionOwnerAction;
+use crate::models::{VersionChangelog, VersionFile, VersionOwnerAction, VersionYankEvent};
 use crate::schema::*;
-use crate::views::{EncodableDependency, EncodablePublicUser, EncodableVersion};
+use crate::util::target::Target;
+use crate::views::{
+    EncodableDependency, EncodablePublicUser, EncodableVersion, EncodableVersionChangelog,
+    EncodableVersionFeature, EncodableVersionFile,
+};
 
 use super::version_and_crate;
 
@@ -19,11 +25,27 @@ use super::version_and_crate;
 /// In addition to returning cached data from the index, this returns
 /// fields for `id`, `version_id`, and `downloads` (which appears to always
 /// be 0)
+///
+/// With a `?target=<triple>` query parameter, only the dependencies which
+/// apply to that target are returned. Dependencies whose `cfg` expression
+/// can't be evaluated are kept.
 pub fn dependencies(req: &mut dyn RequestExt) -> EndpointResult {
+    let target = match req.query().get("target") {
+        Some(triple) => Some(
+            Target::from_triple(triple)
+                .ok_or_else(|| bad_request(&format!("invalid target `{}`", triple)))?,
+        ),
+        None => None,
+    };
+
     let (conn, version, _) = version_and_crate(req)?;
     let deps = version.dependencies(&*conn)?;
     let deps = deps
         .into_iter()
+        .filter(|(dep, _)| match (&target, &dep.target) {
+            (Some(target), Some(platform)) => target.matches(platform).unwrap_or(true),
+            _ => true,
+        })
         .map(|(dep, crate_name)| dep.encodable(&crate_name, None))
         .collect();
 
@@ -61,6 +83,71 @@ pub fn authors(req: &mut dyn RequestExt) -> EndpointResult {
     }))
 }
 
+/// Handles the `GET /crates/:crate_id/:version/files` route.
+///
+/// Lists every regular file contained in the `.crate` tarball of the version,
+/// as recorded when it was published.
+pub fn files(req: &mut dyn RequestExt) -> EndpointResult {
+    let (conn, version, _) = version_and_crate(req)?;
+    let files = VersionFile::belonging_to(&version)
+        .order(version_files::path)
+        .load::<VersionFile>(&*conn)?
+        .into_iter()
+        .map(VersionFile::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        files: Vec<EncodableVersionFile>,
+    }
+    Ok(req.json(&R { files }))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/features` route.
+///
+/// Lists the features of the version by name, along with their documentation if the
+/// manifest has some, see `TarballInfo`.
+pub fn features(req: &mut dyn RequestExt) -> EndpointResult {
+    let (_, version, _) = version_and_crate(req)?;
+    let features: BTreeMap<String, Vec<String>> =
+        serde_json::from_value(version.features).unwrap_or_default();
+    let mut feature_docs: BTreeMap<String, String> =
+        serde_json::from_value(version.feature_docs).unwrap_or_default();
+    let defaults = features.get("default").cloned().unwrap_or_default();
+    let features = features
+        .into_iter()
+        .map(|(name, enables)| EncodableVersionFeature {
+            default: defaults.contains(&name),
+            description: feature_docs.remove(&name),
+            name,
+            enables,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        features: Vec<EncodableVersionFeature>,
+    }
+    Ok(req.json(&R { features }))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/changelog` route.
+///
+/// Returns the rendered changelog shipped with the version, along with the
+/// section describing the version itself if its heading could be found.
+pub fn changelog(req: &mut dyn RequestExt) -> EndpointResult {
+    let (conn, version, _) = version_and_crate(req)?;
+    let changelog = VersionChangelog::belonging_to(&version).first::<VersionChangelog>(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        changelog: EncodableVersionChangelog,
+    }
+    Ok(req.json(&R {
+        changelog: changelog.encodable(),
+    }))
+}
+
 /// Handles the `GET /crates/:crate/:version` route.
 ///
 /// The frontend doesn't appear to hit this endpoint, but our tests do, and it seems to be a useful
@@ -69,12 +156,13 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let (conn, version, krate) = version_and_crate(req)?;
     let published_by = version.published_by(&conn);
     let actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let yank_events = VersionYankEvent::by_version(&conn, &version)?;
 
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
     Ok(req.json(&R {
-        version: version.encodable(&krate.name, published_by, actions),
+        version: version.encodable(&krate.name, published_by, actions, yank_events),
     }))
 }
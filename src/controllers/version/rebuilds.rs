@@ -0,0 +1,76 @@
+//! Endpoints for reporting and summarizing independent rebuilds of crate versions
+//!
+//! Third parties can rebuild a version from the VCS revision it was packaged
+//! from and report the checksum of the `.crate` file they obtained. Comparing
+//! it with the published checksum tells whether the release is reproducible.
+
+use std::io::Read;
+
+use super::version_and_crate;
+use crate::controllers::frontend_prelude::*;
+use crate::models::VersionRebuild;
+use crate::views::{EncodableVersionRebuild, EncodableVersionRebuildSummary};
+
+/// Handles the `PUT /crates/:crate_id/:version/rebuilds` route.
+pub fn report(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct RebuildReport {
+        checksum: String,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let report: RebuildReport =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let checksum = report.checksum.trim().to_lowercase();
+    if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad_request(
+            "checksum must be a hex encoded SHA-256 digest of the `.crate` file",
+        ));
+    }
+
+    let user_id = req.authenticate()?.user_id();
+    let (conn, version, _) = version_and_crate(req)?;
+    let published_checksum = version
+        .checksum
+        .as_ref()
+        .ok_or_else(|| bad_request("the published checksum of this version is not known"))?;
+
+    let matches = *published_checksum == checksum;
+    VersionRebuild::record(&conn, &version, user_id, &checksum, matches)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        matches: bool,
+    }
+    Ok(req.json(&R { ok: true, matches }))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/rebuilds` route.
+pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
+    let (conn, version, _) = version_and_crate(req)?;
+    let rebuilds = VersionRebuild::by_version(&conn, &version)?;
+
+    let matching = rebuilds.iter().filter(|(r, _)| r.matches).count();
+    let summary = EncodableVersionRebuildSummary {
+        published_checksum: version.checksum,
+        matching,
+        mismatching: rebuilds.len() - matching,
+    };
+    let rebuilds = rebuilds
+        .into_iter()
+        .map(|(rebuild, user)| rebuild.encodable(user))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        rebuilds: Vec<EncodableVersionRebuild>,
+        meta: EncodableVersionRebuildSummary,
+    }
+    Ok(req.json(&R {
+        rebuilds,
+        meta: summary,
+    }))
+}
@@ -1,12 +1,21 @@
 //! Endpoints for yanking and unyanking specific versions of crates
 
+use std::io::Read;
+
 use swirl::Job;
 
 use super::version_and_crate;
 use crate::controllers::cargo_prelude::*;
 use crate::git;
 use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::models::{
+    insert_version_owner_action, NewVersionYankEvent, RegistryEvent, RegistryEventKind,
+    VersionAction,
+};
+use crate::tasks;
+
+/// The maximum length of the reason given for a yank or an unyank, in characters
+const MAX_REASON_LENGTH: usize = 512;
 
 /// Handles the `DELETE /crates/:crate_id/:version/yank` route.
 /// This does not delete a crate version, it makes the crate
@@ -27,25 +36,68 @@ pub fn unyank(req: &mut dyn RequestExt) -> EndpointResult {
 }
 
 /// Changes `yanked` flag on a crate version record
+///
+/// The request can have a JSON body with the `reason` of the change, which is recorded with
+/// who made it in the yank history of the version.
 fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
+    let reason = reason(req)?;
     let authenticated_user = req.authenticate()?;
     let (conn, version, krate) = version_and_crate(req)?;
     let api_token_id = authenticated_user.api_token_id();
     let user = authenticated_user.user();
     let owners = krate.owners(&conn)?;
 
-    if user.rights(req.app(), &owners)? < Rights::Publish {
+    if user.rights(req.app(), &conn, &owners)? < Rights::Publish {
         return Err(cargo_err("must already be an owner to yank or unyank"));
     }
-    let action = if yanked {
-        VersionAction::Yank
+    let (action, event_kind) = if yanked {
+        (VersionAction::Yank, RegistryEventKind::Yank)
     } else {
-        VersionAction::Unyank
+        (VersionAction::Unyank, RegistryEventKind::Unyank)
     };
 
     insert_version_owner_action(&conn, version.id, user.id, api_token_id, action)?;
+    NewVersionYankEvent {
+        version_id: version.id,
+        user_id: user.id,
+        api_token_id,
+        yanked,
+        reason: reason.as_deref(),
+    }
+    .save(&conn)?;
+    RegistryEvent::record_version(&conn, event_kind, &krate.name, &version.num.to_string())?;
 
     git::yank(krate.name, version, yanked).enqueue(&conn)?;
+    tasks::update_dependents_counts(krate.id).enqueue(&conn)?;
 
     ok_true()
 }
+
+/// Reads the optional reason of a yank or an unyank from the body of the request.
+fn reason(req: &mut dyn RequestExt) -> AppResult<Option<String>> {
+    #[derive(Deserialize)]
+    struct Request {
+        reason: Option<String>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    if body.trim().is_empty() {
+        return Ok(None);
+    }
+    let request: Request =
+        serde_json::from_str(&body).map_err(|_| cargo_err("invalid json request"))?;
+    let reason = request
+        .reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if let Some(reason) = &reason {
+        if reason.chars().count() > MAX_REASON_LENGTH {
+            return Err(cargo_err(&format_args!(
+                "the reason can't be longer than {} characters",
+                MAX_REASON_LENGTH
+            )));
+        }
+    }
+    Ok(reason)
+}
@@ -0,0 +1,139 @@
+//! A pool of threads for CPU-heavy work
+//!
+//! Verifying and hashing uploaded tarballs takes a lot of CPU time. Running it on the threads
+//! serving requests would slow down every other request during a burst of publishes, so it is
+//! dispatched to a fixed number of worker threads instead.
+//!
+//! The tasks waiting for a worker are bounded by the queue depth. Once the queue is full, new
+//! tasks are rejected with a `503 Service Unavailable` response instead of piling up.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::config::CpuPoolConfig;
+use crate::util::errors::{internal, AppResult, ServerBusy};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+// Can't derive Debug because of the tasks.
+#[allow(missing_debug_implementations)]
+pub struct CpuPool {
+    sender: Mutex<SyncSender<Task>>,
+}
+
+impl CpuPool {
+    pub fn new(config: CpuPoolConfig) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Task>(config.queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..config.workers {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("cpu-worker-{}", index))
+                .spawn(move || work(&receiver))
+                .expect("Unable to spawn a CPU worker");
+        }
+
+        CpuPool {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Runs a task on a worker and waits for its result.
+    ///
+    /// Returns a `503 Service Unavailable` error if too many tasks are already waiting, and an
+    /// internal error if the task panicked.
+    pub fn run<T, F>(&self, task: F) -> AppResult<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (result_sender, result_receiver) = mpsc::channel();
+        let task: Task = Box::new(move || {
+            // The caller may have stopped waiting, in which case the result is dropped
+            let _ = result_sender.send(task());
+        });
+
+        match self.sender.lock().unwrap().try_send(task) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return Err(Box::new(ServerBusy)),
+            Err(TrySendError::Disconnected(_)) => return Err(internal("The CPU pool stopped")),
+        }
+
+        result_receiver
+            .recv()
+            .map_err(|_| internal("A task of the CPU pool panicked"))
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Task>>) {
+    loop {
+        // The lock is released before running the task, so that the other workers can wait
+        // for the next one
+        let task = receiver.lock().unwrap().recv();
+        match task {
+            // Tasks which panic drop their result sender, which their caller reports, and the
+            // worker keeps running the next tasks
+            Ok(task) => {
+                let _ = panic::catch_unwind(AssertUnwindSafe(task));
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+
+    #[test]
+    fn runs_tasks_on_workers() {
+        let pool = CpuPool::new(CpuPoolConfig {
+            workers: 2,
+            queue_depth: 1,
+        });
+        let name = pool
+            .run(|| thread::current().name().map(String::from))
+            .unwrap();
+        assert!(name.unwrap().starts_with("cpu-worker-"));
+        let panicked: AppResult<()> = pool.run(|| panic!("oops"));
+        assert!(panicked.is_err());
+        assert_eq!(pool.run(|| 42).unwrap(), 42);
+    }
+
+    #[test]
+    fn rejects_tasks_when_the_queue_is_full() {
+        let pool = Arc::new(CpuPool::new(CpuPoolConfig {
+            workers: 1,
+            queue_depth: 1,
+        }));
+
+        // Block the worker, then fill the queue
+        let started = Arc::new(Barrier::new(2));
+        let release = Arc::new(Barrier::new(2));
+        let blocked = {
+            let (pool, started, release) = (pool.clone(), started.clone(), release.clone());
+            thread::spawn(move || {
+                pool.run(move || {
+                    started.wait();
+                    release.wait();
+                })
+            })
+        };
+        started.wait();
+        pool.sender
+            .lock()
+            .unwrap()
+            .try_send(Box::new(|| ()))
+            .unwrap();
+
+        let error = pool.run(|| 2).unwrap_err();
+        assert!(error.is::<ServerBusy>());
+
+        release.wait();
+        blocked.join().unwrap().unwrap();
+        assert_eq!(pool.run(|| 3).unwrap(), 3);
+    }
+}
@@ -57,7 +57,7 @@ impl Deref for DieselPooledConn<'_> {
 
 pub fn connect_now() -> ConnectionResult<PgConnection> {
     let mut url = Url::parse(&crate::env("DATABASE_URL")).expect("Invalid database URL");
-    if dotenv::var("HEROKU").is_ok() && !url.query_pairs().any(|(k, _)| k == "sslmode") {
+    if crate::config::var("HEROKU").is_ok() && !url.query_pairs().any(|(k, _)| k == "sslmode") {
         url.query_pairs_mut().append_pair("sslmode", "require");
     }
     PgConnection::establish(&url.to_string())
@@ -65,7 +65,7 @@ pub fn connect_now() -> ConnectionResult<PgConnection> {
 
 pub fn connection_url(url: &str) -> String {
     let mut url = Url::parse(url).expect("Invalid database URL");
-    if dotenv::var("HEROKU").is_ok() && !url.query_pairs().any(|(k, _)| k == "sslmode") {
+    if crate::config::var("HEROKU").is_ok() && !url.query_pairs().any(|(k, _)| k == "sslmode") {
         url.query_pairs_mut().append_pair("sslmode", "require");
     }
     url.into_string()
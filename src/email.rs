@@ -1,5 +1,6 @@
 use std::path::Path;
 
+use crate::config::Settings;
 use crate::util::errors::{server_error, AppResult};
 
 use lettre::transport::file::FileTransport;
@@ -14,21 +15,47 @@ pub struct MailgunConfigVars {
     pub smtp_server: String,
 }
 
-pub fn init_config_vars() -> Option<MailgunConfigVars> {
-    match (
-        dotenv::var("MAILGUN_SMTP_LOGIN"),
-        dotenv::var("MAILGUN_SMTP_PASSWORD"),
-        dotenv::var("MAILGUN_SMTP_SERVER"),
-    ) {
-        (Ok(login), Ok(password), Ok(server)) => Some(MailgunConfigVars {
-            smtp_login: login,
-            smtp_password: password,
-            smtp_server: server,
-        }),
-        _ => None,
+impl MailgunConfigVars {
+    /// Reads `MAILGUN_SMTP_LOGIN`, `MAILGUN_SMTP_PASSWORD` and `MAILGUN_SMTP_SERVER`, which must
+    /// be set together. Emails are written to files when none of them are set.
+    pub fn from_settings(settings: &mut Settings) -> Option<Self> {
+        let names = [
+            "MAILGUN_SMTP_LOGIN",
+            "MAILGUN_SMTP_PASSWORD",
+            "MAILGUN_SMTP_SERVER",
+        ];
+        match (
+            settings.var(names[0]),
+            settings.var(names[1]),
+            settings.var(names[2]),
+        ) {
+            (Some(login), Some(password), Some(server)) => Some(MailgunConfigVars {
+                smtp_login: login,
+                smtp_password: password,
+                smtp_server: server,
+            }),
+            (None, None, None) => None,
+            _ => {
+                let missing = names
+                    .iter()
+                    .filter(|name| settings.var(name).is_none())
+                    .collect::<Vec<_>>();
+                for name in missing {
+                    settings.error(
+                        name,
+                        &"must be set along with the other `MAILGUN_SMTP_*` variables",
+                    );
+                }
+                None
+            }
+        }
     }
 }
 
+pub fn init_config_vars() -> Option<MailgunConfigVars> {
+    MailgunConfigVars::from_settings(&mut Settings::new())
+}
+
 fn build_email(
     recipient: &str,
     subject: &str,
@@ -102,6 +129,140 @@ or go to https://{domain}/me/pending-invites to manage all of your crate ownersh
     let _ = send_email(email, subject, &body);
 }
 
+/// Attempts to ask a user to accept the ownership of a crate. Swallows all errors.
+///
+/// The transfer can only be accepted through the link of this email, before it expires.
+pub fn send_ownership_transfer_email(
+    email: &str,
+    user_name: &str,
+    crate_name: &str,
+    token: &str,
+    expiration_days: i64,
+) {
+    let subject = format!("Ownership transfer of {}", crate_name);
+    let body = format!(
+        "{} has requested to transfer the ownership of the crate {} to you. Once you accept \
+it, you will be its only owner.\n
+Visit https://{domain}/accept-transfer/{} within {} days to accept this transfer.",
+        user_name,
+        crate_name,
+        token,
+        expiration_days,
+        domain = crate::config::domain_name()
+    );
+
+    let _ = send_email(email, &subject, &body);
+}
+
+/// Attempts to tell an owner of a crate that its ownership is being transferred. Swallows all
+/// errors.
+///
+/// The owners are always told about the transfers, whatever their notification settings.
+pub fn send_ownership_transfer_requested_email(
+    email: &str,
+    user_name: &str,
+    crate_name: &str,
+    target_name: &str,
+) {
+    let subject = format!("Ownership transfer of {} requested", crate_name);
+    let body = format!(
+        "{} has requested to transfer the ownership of the crate {} to {}. If they accept it, \
+{} will replace all the current owners of the crate.\n
+If this transfer wasn't intended, any owner can cancel it before it is accepted: \
+https://{domain}/crates/{}/settings",
+        user_name,
+        crate_name,
+        target_name,
+        target_name,
+        crate_name,
+        domain = crate::config::domain_name()
+    );
+
+    let _ = send_email(email, &subject, &body);
+}
+
+/// Attempts to tell a previous owner of a crate that its ownership was transferred. Swallows
+/// all errors.
+pub fn send_ownership_transferred_email(email: &str, crate_name: &str, target_name: &str) {
+    let subject = format!("Ownership of {} transferred", crate_name);
+    let body = format!(
+        "{} accepted the ownership of the crate {}, and is now its only owner.",
+        target_name, crate_name
+    );
+
+    let _ = send_email(email, &subject, &body);
+}
+
+/// Attempts to tell an owner of a crate that a version of one of its dependencies was yanked.
+/// Swallows all errors.
+///
+/// The owners can turn these emails off for each of their crates, like the other
+/// notifications about them.
+pub fn send_dependency_yanked_email(
+    email: &str,
+    crate_name: &str,
+    dependency_name: &str,
+    version: &str,
+) {
+    let subject = format!("{} {} was yanked", dependency_name, version);
+    let body = format!(
+        "The version {version} of {dependency}, which the latest version of your crate {krate} \
+depends on, was yanked.\n
+Yanked versions can't be used by new builds, you may want to update your dependency to \
+another version: https://{domain}/crates/{dependency}/versions\n
+You can turn off these notifications at https://{domain}/me",
+        version = version,
+        dependency = dependency_name,
+        krate = crate_name,
+        domain = crate::config::domain_name()
+    );
+
+    let _ = send_email(email, &subject, &body);
+}
+
+/// Attempts to tell a user that a new version of a crate changed its license. Swallows all
+/// errors.
+///
+/// `dependent_name` is the crate of the user depending on the crate, or `None` if the user
+/// follows it.
+pub fn send_license_changed_email(
+    email: &str,
+    crate_name: &str,
+    version: &str,
+    previous_license: &str,
+    license: &str,
+    dependent_name: Option<&str>,
+) {
+    let subject = format!("{} {} changed its license", crate_name, version);
+    let reason = match dependent_name {
+        Some(dependent) => format!(
+            "which the latest version of your crate {} depends on",
+            dependent
+        ),
+        None => String::from("which you follow"),
+    };
+    let mut body = format!(
+        "The version {version} of {krate}, {reason}, changed its license from `{previous}` \
+to `{license}`.\n
+You may want to check that the new license is compatible with your use of the crate: \
+https://{domain}/crates/{krate}/{version}",
+        version = version,
+        krate = crate_name,
+        reason = reason,
+        previous = previous_license,
+        license = license,
+        domain = crate::config::domain_name()
+    );
+    if dependent_name.is_some() {
+        body.push_str(&format!(
+            "\n\nYou can turn off these notifications at https://{}/me",
+            crate::config::domain_name()
+        ));
+    }
+
+    let _ = send_email(email, &subject, &body);
+}
+
 fn send_email(recipient: &str, subject: &str, body: &str) -> AppResult<()> {
     let mailgun_config = init_config_vars();
     let email = build_email(recipient, subject, body, &mailgun_config)?;
@@ -0,0 +1,147 @@
+//! Blocking the email addresses of disposable email providers
+//!
+//! Throwaway accounts used to publish spam tend to have disposable email addresses, so the
+//! addresses of these providers can't be set or verified. The blocked domains are the ones
+//! bundled in `disposable_email_domains.txt`, and the ones blocked by the admins of the instance
+//! with `PUT /api/v1/admin/email_domain_rules/:domain`. The admins can also allow a bundled
+//! domain, in case a provider was listed by mistake.
+//!
+//! A rule applies to the subdomains of its domain too, and the rule of the most specific domain
+//! wins.
+
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::email_domain_rules;
+
+const BUNDLED_DOMAINS: &str = include_str!("disposable_email_domains.txt");
+
+/// A domain blocked or allowed by an admin
+#[derive(Clone, Debug, Queryable)]
+pub struct EmailDomainRule {
+    pub domain: String,
+    pub blocked: bool,
+    pub updated_by: Option<i32>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl EmailDomainRule {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        email_domain_rules::table
+            .order(email_domain_rules::domain)
+            .load(conn)
+    }
+
+    /// Blocks or allows a domain, recording the admin who changed the rule.
+    pub fn set(
+        conn: &PgConnection,
+        domain: &str,
+        blocked: bool,
+        user_id: i32,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(email_domain_rules::table)
+            .values((
+                email_domain_rules::domain.eq(domain),
+                email_domain_rules::blocked.eq(blocked),
+                email_domain_rules::updated_by.eq(user_id),
+            ))
+            .on_conflict(email_domain_rules::domain)
+            .do_update()
+            .set((
+                email_domain_rules::blocked.eq(blocked),
+                email_domain_rules::updated_by.eq(user_id),
+                email_domain_rules::updated_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+
+    /// Removes the rule of a domain, so that only the bundled list applies to it.
+    pub fn delete(conn: &PgConnection, domain: &str) -> QueryResult<usize> {
+        diesel::delete(email_domain_rules::table.find(domain)).execute(conn)
+    }
+}
+
+/// Returns a domain in its canonical form, or `None` if it is not one.
+pub fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    if domain.is_empty() || domain.contains('@') {
+        None
+    } else {
+        Some(domain)
+    }
+}
+
+/// Returns whether the email address belongs to a blocked domain.
+pub fn is_blocked(conn: &PgConnection, email: &str) -> QueryResult<bool> {
+    let domain = match email
+        .rfind('@')
+        .and_then(|i| normalize_domain(&email[i + 1..]))
+    {
+        Some(domain) => domain,
+        None => return Ok(false),
+    };
+    let domains = domain_and_parents(&domain);
+    let rules = email_domain_rules::table
+        .filter(email_domain_rules::domain.eq_any(&domains))
+        .select((email_domain_rules::domain, email_domain_rules::blocked))
+        .load::<(String, bool)>(conn)?;
+
+    for domain in domains {
+        if let Some((_, blocked)) = rules.iter().find(|(rule, _)| rule == domain) {
+            return Ok(*blocked);
+        }
+        if is_bundled(domain) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn is_bundled(domain: &str) -> bool {
+    BUNDLED_DOMAINS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .any(|bundled| bundled == domain)
+}
+
+/// Returns the domain followed by its parents, from the most to the least specific.
+fn domain_and_parents(domain: &str) -> Vec<&str> {
+    let mut domains = vec![domain];
+    let mut rest = domain;
+    while let Some(i) = rest.find('.') {
+        rest = &rest[i + 1..];
+        domains.push(rest);
+    }
+    domains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domains_are_normalized() {
+        assert_eq!(
+            normalize_domain(" MailInator.com. ").unwrap(),
+            "mailinator.com"
+        );
+        assert_none!(normalize_domain(""));
+        assert_none!(normalize_domain("foo@example.com"));
+    }
+
+    #[test]
+    fn parents_of_a_domain() {
+        assert_eq!(
+            domain_and_parents("a.mailinator.com"),
+            vec!["a.mailinator.com", "mailinator.com", "com"]
+        );
+    }
+
+    #[test]
+    fn bundled_domains() {
+        assert!(is_bundled("mailinator.com"));
+        assert!(!is_bundled("example.com"));
+    }
+}
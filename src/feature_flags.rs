@@ -0,0 +1,181 @@
+//! Runtime feature flags
+//!
+//! Feature flags toggle behaviors of the running application without redeploying it. They are
+//! stored in the `feature_flags` table and toggled by admins with
+//! `PUT /api/v1/admin/feature_flags/:name`. Flags which were never toggled are disabled.
+//!
+//! Every server process caches the enabled flags, and reloads them at most every
+//! `REFRESH_INTERVAL`, so toggling a flag takes effect everywhere within that delay.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::db::DieselPool;
+use crate::schema::feature_flags;
+
+/// How long the cached flags are used before being reloaded from the database
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureFlag {
+    /// Require new versions to have a `repository` in their metadata
+    StrictPublishChecks,
+    /// Redirect downloads to the crate files without using the database, so that downloads keep
+    /// working when the database is struggling. Downloads aren't counted in this mode.
+    DegradedDownloads,
+    /// Reject all the requests which could write to the database, see the `maintenance_mode`
+    /// middleware
+    MaintenanceMode,
+}
+
+impl FeatureFlag {
+    pub const ALL: &'static [FeatureFlag] = &[
+        FeatureFlag::StrictPublishChecks,
+        FeatureFlag::DegradedDownloads,
+        FeatureFlag::MaintenanceMode,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FeatureFlag::StrictPublishChecks => "strict_publish_checks",
+            FeatureFlag::DegradedDownloads => "degraded_downloads",
+            FeatureFlag::MaintenanceMode => "maintenance_mode",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            FeatureFlag::StrictPublishChecks => {
+                "Require new versions to have a `repository` in their metadata"
+            }
+            FeatureFlag::DegradedDownloads => {
+                "Redirect downloads without using the database, and without counting them"
+            }
+            FeatureFlag::MaintenanceMode => {
+                "Reject all the requests except reads and downloads with a maintenance message"
+            }
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|flag| flag.name() == name)
+    }
+}
+
+/// A feature flag which was toggled at least once
+#[derive(Clone, Debug, Queryable)]
+pub struct FeatureFlagState {
+    pub name: String,
+    pub enabled: bool,
+    pub updated_by: Option<i32>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl FeatureFlagState {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        feature_flags::table.load(conn)
+    }
+
+    /// Enables or disables a flag, recording the admin who toggled it.
+    pub fn set(
+        conn: &PgConnection,
+        flag: FeatureFlag,
+        enabled: bool,
+        user_id: i32,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(feature_flags::table)
+            .values((
+                feature_flags::name.eq(flag.name()),
+                feature_flags::enabled.eq(enabled),
+                feature_flags::updated_by.eq(user_id),
+            ))
+            .on_conflict(feature_flags::name)
+            .do_update()
+            .set((
+                feature_flags::enabled.eq(enabled),
+                feature_flags::updated_by.eq(user_id),
+                feature_flags::updated_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+}
+
+/// The cached flags of this process.
+#[derive(Debug, Default)]
+pub struct FeatureFlags {
+    cache: Mutex<Cache>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    enabled: Vec<String>,
+    loaded_at: Option<Instant>,
+    loading: bool,
+}
+
+impl FeatureFlags {
+    /// Returns whether a flag is enabled, reloading the flags if they are outdated.
+    ///
+    /// Only one request reloads the flags at a time, while the others keep using the previous
+    /// values. If the flags can't be loaded, the previous values are kept until the next reload.
+    pub fn is_enabled(&self, database: &DieselPool, flag: FeatureFlag) -> bool {
+        let needs_reload = {
+            let mut cache = self.cache.lock().unwrap();
+            let outdated = cache
+                .loaded_at
+                .map_or(true, |loaded_at| loaded_at.elapsed() >= REFRESH_INTERVAL);
+            let needs_reload = outdated && !cache.loading;
+            if needs_reload {
+                cache.loading = true;
+            }
+            needs_reload
+        };
+
+        if needs_reload {
+            let enabled = database
+                .get()
+                .map_err(|e| e.to_string())
+                .and_then(|conn| load_enabled(&conn).map_err(|e| e.to_string()));
+
+            let mut cache = self.cache.lock().unwrap();
+            match enabled {
+                Ok(enabled) => cache.enabled = enabled,
+                Err(e) => eprintln!("Failed to load the feature flags: {}", e),
+            }
+            cache.loaded_at = Some(Instant::now());
+            cache.loading = false;
+        }
+
+        let cache = self.cache.lock().unwrap();
+        cache.enabled.iter().any(|name| name == flag.name())
+    }
+
+    /// Reloads the flags the next time they are read, after they were toggled by this process.
+    pub fn invalidate(&self) {
+        self.cache.lock().unwrap().loaded_at = None;
+    }
+}
+
+fn load_enabled(conn: &PgConnection) -> QueryResult<Vec<String>> {
+    feature_flags::table
+        .filter(feature_flags::enabled)
+        .select(feature_flags::name)
+        .load(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for &flag in FeatureFlag::ALL {
+            assert_eq!(FeatureFlag::from_name(flag.name()), Some(flag));
+        }
+        assert_eq!(FeatureFlag::from_name("unknown"), None);
+    }
+}
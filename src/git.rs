@@ -8,6 +8,7 @@ use tempfile::{Builder, TempDir};
 use url::Url;
 
 use crate::background_jobs::Environment;
+use crate::config::Settings;
 use crate::models::{DependencyKind, Version};
 use crate::schema::versions;
 
@@ -54,7 +55,7 @@ impl Credentials {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Crate {
     pub name: String,
     pub vers: String,
@@ -64,9 +65,48 @@ pub struct Crate {
     pub yanked: Option<bool>,
     #[serde(default)]
     pub links: Option<String>,
+    /// Features using syntax that older versions of Cargo don't understand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features2: Option<HashMap<String, Vec<String>>>,
+    /// The version of the index format, which is implicitly 1 when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub v: Option<u32>,
+    /// Whether the owners deprecated the crate, absent when they didn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<bool>,
+    /// The crate recommended instead of this deprecated one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+    /// The size of the `.crate` file in bytes, so that cargo can show download sizes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The total size of the files of the `.crate` file in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncompressed_size: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Crate {
+    /// Returns the version of the index format needed to understand this entry.
+    ///
+    /// Entries using `features2` or artifact dependencies need `v: 2`, so that
+    /// versions of Cargo which don't support them skip the entry instead of
+    /// misinterpreting it. Other entries keep the original format without a `v`
+    /// field.
+    pub fn required_index_version(&self) -> Option<u32> {
+        let uses_features2 = self
+            .features2
+            .as_ref()
+            .map_or(false, |features2| !features2.is_empty());
+        let uses_artifact_deps = self.deps.iter().any(|dep| dep.artifact.is_some());
+        if uses_features2 || uses_artifact_deps {
+            Some(2)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Dependency {
     pub name: String,
     pub req: String,
@@ -77,6 +117,12 @@ pub struct Dependency {
     pub kind: Option<DependencyKind>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bindep_target: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lib: bool,
 }
 
 pub struct RepositoryConfig {
@@ -85,57 +131,85 @@ pub struct RepositoryConfig {
 }
 
 impl RepositoryConfig {
+    /// Reads the location of the index and the credentials to push to it, see `from_settings`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the settings are invalid, listing all the errors.
     pub fn from_environment() -> Self {
-        let username = dotenv::var("GIT_HTTP_USER");
-        let password = dotenv::var("GIT_HTTP_PWD");
-        let http_url = dotenv::var("GIT_REPO_URL");
-
-        let ssh_key = dotenv::var("GIT_SSH_KEY");
-        let ssh_url = dotenv::var("GIT_SSH_REPO_URL");
+        let mut settings = Settings::new();
+        let config = Self::from_settings(&mut settings);
+        settings
+            .finish()
+            .map(|_| config)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        match (username, password, http_url, ssh_key, ssh_url) {
-            (extra_user, extra_pass, extra_http_url, Ok(encoded_key), Ok(ssh_url)) => {
-                if let (Ok(_), Ok(_), Ok(_)) = (extra_user, extra_pass, extra_http_url) {
+    /// Reads the following settings:
+    ///
+    /// - `GIT_SSH_REPO_URL` and `GIT_SSH_KEY`: The SSH URL of the index and the base64 encoded
+    ///    private key to push to it.
+    /// - `GIT_REPO_URL`, `GIT_HTTP_USER` and `GIT_HTTP_PWD`: The HTTP URL of the index and the
+    ///    credentials to push to it, used if the SSH settings aren't set. The credentials are
+    ///    optional.
+    pub fn from_settings(settings: &mut Settings) -> Self {
+        let username = settings.var("GIT_HTTP_USER");
+        let password = settings.var("GIT_HTTP_PWD");
+        let http_url = settings.var("GIT_REPO_URL");
+
+        let ssh_key = settings.var("GIT_SSH_KEY");
+        let ssh_url = settings.var("GIT_SSH_REPO_URL");
+
+        let (url_name, credentials) = match (username, password, &http_url, ssh_key, &ssh_url) {
+            (extra_user, extra_pass, extra_http_url, Some(encoded_key), Some(_)) => {
+                if let (Some(_), Some(_), Some(_)) = (extra_user, extra_pass, extra_http_url) {
                     println!(
                         "warning: both http and ssh credentials to authenticate with git are set"
                     );
                     println!("note: ssh credentials will take precedence over the http ones");
                 }
 
-                let index_location =
-                    Url::parse(&ssh_url).expect("failed to parse GIT_SSH_REPO_URL");
-
-                let credentials = Credentials::Ssh {
-                    key: String::from_utf8(
-                        base64::decode(&encoded_key).expect("failed to base64 decode the ssh key"),
-                    )
-                    .expect("failed to convert the ssh key to a string"),
+                let key = base64::decode(&encoded_key)
+                    .map_err(|e| e.to_string())
+                    .and_then(|key| String::from_utf8(key).map_err(|e| e.to_string()));
+                let credentials = match key {
+                    Ok(key) => Credentials::Ssh { key },
+                    Err(e) => {
+                        settings.error(
+                            "GIT_SSH_KEY",
+                            &format_args!("isn't a base64 encoded ssh key ({})", e),
+                        );
+                        Credentials::Missing
+                    }
                 };
-
-                Self {
-                    index_location,
-                    credentials,
-                }
+                ("GIT_SSH_REPO_URL", credentials)
             }
-            (Ok(username), Ok(password), Ok(http_url), Err(_), Err(_)) => {
-                let index_location = Url::parse(&http_url).expect("failed to parse GIT_REPO_URL");
-                let credentials = Credentials::Http { username, password };
-
-                Self {
-                    index_location,
-                    credentials,
-                }
+            (Some(username), Some(password), Some(_), None, None) => {
+                ("GIT_REPO_URL", Credentials::Http { username, password })
             }
-            (_, _, Ok(http_url), _, _) => {
-                let index_location = Url::parse(&http_url).expect("failed to parse GIT_REPO_URL");
-                let credentials = Credentials::Missing;
-
-                Self {
-                    index_location,
-                    credentials,
-                }
+            _ => ("GIT_REPO_URL", Credentials::Missing),
+        };
+
+        let url = if url_name == "GIT_SSH_REPO_URL" {
+            ssh_url
+        } else {
+            http_url
+        };
+        let index_location = match url.as_deref().map(Url::parse) {
+            Some(Ok(url)) => url,
+            Some(Err(e)) => {
+                settings.error(url_name, &format_args!("is not a valid URL ({})", e));
+                Url::parse("file:///").unwrap()
             }
-            _ => panic!("must have `GIT_REPO_URL` defined"),
+            None => {
+                settings.error(url_name, &"must be set");
+                Url::parse("file:///").unwrap()
+            }
+        };
+
+        Self {
+            index_location,
+            credentials,
         }
     }
 }
@@ -177,13 +251,22 @@ impl Repository {
     }
 
     fn relative_index_file(&self, name: &str) -> PathBuf {
-        let name = name.to_lowercase();
-        match name.len() {
-            1 => Path::new("1").join(&name),
-            2 => Path::new("2").join(&name),
-            3 => Path::new("3").join(&name[..1]).join(&name),
-            _ => Path::new(&name[0..2]).join(&name[2..4]).join(&name),
+        relative_index_file(name)
+    }
+
+    /// Reads the index entries of a crate, which are empty if the crate isn't in the index.
+    pub fn crate_entries(&self, name: &str) -> Result<Vec<Crate>, PerformError> {
+        let path = self.index_file(name);
+        if !path.exists() {
+            return Ok(Vec::new());
         }
+
+        let entries = fs::read_to_string(&path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
     }
 
     fn perform_commit_and_push(&self, msg: &str, modified_file: &Path) -> Result<(), PerformError> {
@@ -265,6 +348,17 @@ impl Repository {
     }
 }
 
+/// Returns the path of the index file of a crate, relative to the root of the index.
+pub fn relative_index_file(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[0..2]).join(&name[2..4]).join(&name),
+    }
+}
+
 #[swirl::background_job]
 pub fn add_crate(env: &Environment, krate: Crate) -> Result<(), PerformError> {
     use std::io::prelude::*;
@@ -283,6 +377,30 @@ pub fn add_crate(env: &Environment, krate: Crate) -> Result<(), PerformError> {
     repo.commit_and_push(&message, &repo.relative_index_file(&krate.name))
 }
 
+/// Replaces the index entries of a crate mirrored from an upstream registry with the
+/// entries it has upstream.
+#[swirl::background_job]
+pub fn replace_index_entries(
+    env: &Environment,
+    krate: String,
+    entries: Vec<Crate>,
+) -> Result<(), PerformError> {
+    let repo = env.lock_index()?;
+    let dst = repo.index_file(&krate);
+
+    let mut contents = String::new();
+    for entry in &entries {
+        contents.push_str(&serde_json::to_string(entry)?);
+        contents.push('\n');
+    }
+    fs::create_dir_all(dst.parent().unwrap())?;
+    fs::write(&dst, contents.as_bytes())?;
+
+    let message: String = format!("Mirroring crate `{}` from the upstream registry", krate);
+
+    repo.commit_and_push(&message, &repo.relative_index_file(&krate))
+}
+
 /// Yanks or unyanks a crate version. This requires finding the index
 /// file, deserlialise the crate from JSON, change the yank boolean to
 /// `true` or `false`, write all the lines back out, and commit and
@@ -296,11 +414,12 @@ pub fn yank(
     yanked: bool,
 ) -> Result<(), PerformError> {
     use diesel::prelude::*;
+    use swirl::Job;
 
     let repo = env.lock_index()?;
     let dst = repo.index_file(&krate);
 
-    conn.transaction(|| {
+    conn.transaction::<_, PerformError, _>(|| {
         let yanked_in_db: bool = versions::table
             .find(version.id)
             .select(versions::yanked)
@@ -316,15 +435,7 @@ pub fn yank(
         let version_num = version.num.to_string();
         let new = prev
             .lines()
-            .map(|line| {
-                let mut git_crate = serde_json::from_str::<Crate>(line)
-                    .map_err(|_| format!("couldn't decode: `{}`", line))?;
-                if git_crate.name != krate || git_crate.vers != version_num {
-                    return Ok(line.to_string());
-                }
-                git_crate.yanked = Some(yanked);
-                Ok(serde_json::to_string(&git_crate)?)
-            })
+            .map(|line| update_yanked(line, &krate, &version_num, yanked))
             .collect::<Result<Vec<_>, PerformError>>();
         let new = new?.join("\n") + "\n";
         fs::write(&dst, new.as_bytes())?;
@@ -342,6 +453,153 @@ pub fn yank(
             .set(versions::yanked.eq(yanked))
             .execute(&*conn)?;
 
+        if yanked {
+            crate::tasks::notify_yanked_dependents(version.id).enqueue(conn)?;
+        }
+        if env.search_backend().needs_indexing() {
+            crate::tasks::index_crate(version.crate_id).enqueue(conn)?;
+        }
+
         Ok(())
-    })
+    })?;
+
+    crate::cache::invalidate_crate(env.cache(), &krate);
+    Ok(())
+}
+
+/// Marks all the index entries of a crate as deprecated, with the crate superseding it, or
+/// removes these marks.
+#[swirl::background_job]
+pub fn deprecate(
+    env: &Environment,
+    krate: String,
+    deprecated: bool,
+    superseded_by: Option<String>,
+) -> Result<(), PerformError> {
+    let repo = env.lock_index()?;
+    let dst = repo.index_file(&krate);
+
+    let prev = fs::read_to_string(&dst)?;
+    let new = prev
+        .lines()
+        .map(|line| update_deprecation(line, deprecated, superseded_by.as_deref()))
+        .collect::<Result<Vec<_>, PerformError>>();
+    let new = new?.join("\n") + "\n";
+    fs::write(&dst, new.as_bytes())?;
+
+    let message: String = format!(
+        "{} crate `{}`",
+        if deprecated {
+            "Deprecating"
+        } else {
+            "Undeprecating"
+        },
+        krate
+    );
+
+    repo.commit_and_push(&message, &repo.relative_index_file(&krate))
+}
+
+/// Sets the `deprecated` and `superseded_by` fields of an index entry, which are left out of
+/// the entries of crates which aren't deprecated.
+fn update_deprecation(
+    line: &str,
+    deprecated: bool,
+    superseded_by: Option<&str>,
+) -> Result<String, PerformError> {
+    let mut git_crate =
+        serde_json::from_str::<Crate>(line).map_err(|_| format!("couldn't decode: `{}`", line))?;
+    git_crate.deprecated = Some(true).filter(|_| deprecated);
+    git_crate.superseded_by = superseded_by.filter(|_| deprecated).map(String::from);
+    Ok(serde_json::to_string(&git_crate)?)
+}
+
+/// Sets the `yanked` field of an index entry if it is the one of `krate#version_num`,
+/// leaving all other entries untouched.
+fn update_yanked(
+    line: &str,
+    krate: &str,
+    version_num: &str,
+    yanked: bool,
+) -> Result<String, PerformError> {
+    let mut git_crate =
+        serde_json::from_str::<Crate>(line).map_err(|_| format!("couldn't decode: `{}`", line))?;
+    if git_crate.name != krate || git_crate.vers != version_num {
+        return Ok(line.to_string());
+    }
+    git_crate.yanked = Some(yanked);
+    Ok(serde_json::to_string(&git_crate)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_ENTRY: &str = r#"{"name":"foo","vers":"1.0.0","deps":[{"name":"bar","req":"^1.0","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}],"cksum":"abc","features":{},"yanked":false,"links":null}"#;
+    const V2_ENTRY: &str = r#"{"name":"foo","vers":"2.0.0","deps":[{"name":"bar","req":"^1.0","features":[],"optional":false,"default_features":true,"target":null,"kind":"build","artifact":["bin"],"lib":true}],"cksum":"abc","features":{},"yanked":false,"links":null,"features2":{"serde":["dep:serde"]},"v":2}"#;
+
+    #[test]
+    fn relative_index_files() {
+        assert_eq!(relative_index_file("a"), Path::new("1/a"));
+        assert_eq!(relative_index_file("ab"), Path::new("2/ab"));
+        assert_eq!(relative_index_file("abc"), Path::new("3/a/abc"));
+        assert_eq!(relative_index_file("Serde"), Path::new("se/rd/serde"));
+    }
+
+    #[test]
+    fn v1_entries_round_trip_through_yank_updates() {
+        let yanked = assert_ok!(update_yanked(V1_ENTRY, "foo", "1.0.0", true));
+        assert_eq!(
+            yanked,
+            V1_ENTRY.replace(r#""yanked":false"#, r#""yanked":true"#)
+        );
+
+        let unyanked = assert_ok!(update_yanked(&yanked, "foo", "1.0.0", false));
+        assert_eq!(unyanked, V1_ENTRY);
+    }
+
+    #[test]
+    fn v2_entries_round_trip_through_yank_updates() {
+        let yanked = assert_ok!(update_yanked(V2_ENTRY, "foo", "2.0.0", true));
+        assert_eq!(
+            yanked,
+            V2_ENTRY.replace(r#""yanked":false"#, r#""yanked":true"#)
+        );
+    }
+
+    #[test]
+    fn other_entries_are_left_untouched() {
+        let line = V1_ENTRY.replace(r#""cksum":"abc""#, r#""cksum":"abc","unknown":1"#);
+        assert_eq!(assert_ok!(update_yanked(&line, "foo", "2.0.0", true)), line);
+        assert_err!(update_yanked("not json", "foo", "1.0.0", true));
+    }
+
+    #[test]
+    fn entries_round_trip_through_deprecation_updates() {
+        let deprecated = assert_ok!(update_deprecation(V1_ENTRY, true, Some("bar")));
+        assert_eq!(
+            deprecated,
+            V1_ENTRY.replace(
+                r#""links":null"#,
+                r#""links":null,"deprecated":true,"superseded_by":"bar""#
+            )
+        );
+
+        let undeprecated = assert_ok!(update_deprecation(&deprecated, false, Some("bar")));
+        assert_eq!(undeprecated, V1_ENTRY);
+    }
+
+    #[test]
+    fn required_index_version() {
+        let v1: Crate = serde_json::from_str(V1_ENTRY).unwrap();
+        assert_none!(v1.required_index_version());
+
+        let mut v2: Crate = serde_json::from_str(V2_ENTRY).unwrap();
+        assert_eq!(v2.required_index_version(), Some(2));
+
+        v2.deps[0].artifact = None;
+        assert_eq!(v2.required_index_version(), Some(2));
+        v2.features2 = None;
+        assert_none!(v2.required_index_version());
+    }
 }
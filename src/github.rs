@@ -1,18 +1,18 @@
 //! This module implements functionality for interacting with GitHub.
 
 use oauth2::AccessToken;
-use reqwest::{self, header};
+use reqwest::{header, StatusCode};
 
 use serde::de::DeserializeOwned;
 
 use std::str;
 
 use crate::app::App;
+use crate::http_client::HttpRequest;
 use crate::util::errors::{cargo_err, internal, not_found, AppError, AppResult};
 
-/// Does all the nonsense for sending a GET to Github. Doesn't handle parsing
-/// because custom error-code handling may be desirable. Use
-/// `parse_github_response` to handle the "common" processing of responses.
+/// Does all the nonsense for sending a GET to Github, through the API client of the app, and
+/// parses the response. Error statuses are turned into errors shown to the user.
 pub fn github_api<T>(app: &App, url: &str, auth: &AccessToken) -> AppResult<T>
 where
     T: DeserializeOwned,
@@ -20,23 +20,21 @@ where
     let url = format!("{}://api.github.com{}", app.config.api_protocol, url);
     info!("GITHUB HTTP: {}", url);
 
-    app.http_client()
-        .get(&url)
-        .header(header::ACCEPT, "application/vnd.github.v3+json")
-        .header(header::AUTHORIZATION, format!("token {}", auth.secret()))
-        .header(header::USER_AGENT, "crates.io (https://crates.io)")
-        .send()?
-        .error_for_status()
-        .map_err(|e| handle_error_response(app, &e))?
-        .json()
-        .map_err(Into::into)
-}
+    let request = HttpRequest::get(url)
+        .header(header::ACCEPT, "application/vnd.github.v3+json")?
+        .header(header::AUTHORIZATION, &format!("token {}", auth.secret()))?
+        .header(header::USER_AGENT, "crates.io (https://crates.io)")?;
 
-fn handle_error_response(app: &App, error: &reqwest::Error) -> Box<dyn AppError> {
-    use reqwest::StatusCode as Status;
+    let response = app.api_client().send(request)?;
+    if !response.status.is_success() {
+        return Err(handle_error_response(app, response.status));
+    }
+    response.json()
+}
 
-    match error.status() {
-        Some(Status::UNAUTHORIZED) | Some(Status::FORBIDDEN) => cargo_err(&format!(
+fn handle_error_response(app: &App, status: StatusCode) -> Box<dyn AppError> {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => cargo_err(&format!(
             "It looks like you don't have permission \
              to query a necessary property from Github \
              to complete this request. \
@@ -46,19 +44,23 @@ fn handle_error_response(app: &App, error: &reqwest::Error) -> Box<dyn AppError>
              https://{}/login",
             app.config.domain_name,
         )),
-        Some(Status::NOT_FOUND) => not_found(),
+        StatusCode::NOT_FOUND => not_found(),
         _ => internal(&format_args!(
             "didn't get a 200 result from github: {}",
-            error
+            status
         )),
     }
 }
 
-pub fn team_url(login: &str) -> String {
+/// Returns the URL of the organization of a GitHub team, or `None` for the teams of other
+/// authentication providers.
+pub fn team_url(login: &str) -> Option<String> {
     let mut login_pieces = login.split(':');
-    login_pieces.next();
-    format!(
+    if login_pieces.next() != Some("github") {
+        return None;
+    }
+    Some(format!(
         "https://github.com/{}",
         login_pieces.next().expect("org failed"),
-    )
+    ))
 }
@@ -0,0 +1,287 @@
+//! Outgoing HTTP requests to external APIs
+//!
+//! Requests to the APIs of external services, like GitHub, go through an `HttpClient`, which is
+//! a `reqwest` client in production. Tests can instead use a `Replayer`, which answers the
+//! requests with the responses previously captured by a `Recorder` wrapping a real client, so
+//! that they run deterministically and without network access.
+//!
+//! Captured exchanges are saved as JSON fixtures, in the same format as the ones of the
+//! recording proxy used by the integration tests.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::{Method, StatusCode};
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+
+use crate::util::errors::{internal, AppResult};
+
+/// A client sending requests to external APIs.
+pub trait HttpClient: Send + Sync {
+    fn send(&self, request: HttpRequest) -> AppResult<HttpResponse>;
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        HttpRequest {
+            method: Method::GET,
+            url: url.into(),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: HeaderName, value: &str) -> AppResult<Self> {
+        self.headers.insert(name, HeaderValue::from_str(value)?);
+        Ok(self)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn json<T: DeserializeOwned>(&self) -> AppResult<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+impl HttpClient for Client {
+    fn send(&self, request: HttpRequest) -> AppResult<HttpResponse> {
+        let response = self
+            .request(request.method, &request.url)
+            .headers(request.headers)
+            .body(request.body)
+            .send()?;
+
+        Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes()?.to_vec(),
+        })
+    }
+}
+
+/// A request and the response it got, as saved in the fixtures
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub uri: String,
+    pub method: String,
+    pub headers: HashSet<(String, String)>,
+    /// The body, encoded in base64
+    pub body: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: HashSet<(String, String)>,
+    /// The body, encoded in base64
+    pub body: String,
+}
+
+fn record_headers(headers: &HeaderMap) -> HashSet<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+/// Loads the exchanges saved in a fixture.
+pub fn load_fixture(path: &Path) -> io::Result<Vec<Exchange>> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// A client capturing the exchanges of another client, to save them as a fixture.
+#[derive(Debug)]
+pub struct Recorder<C> {
+    client: C,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<C: HttpClient> Recorder<C> {
+    pub fn new(client: C) -> Self {
+        Recorder {
+            client,
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the exchanges captured so far.
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+
+    /// Saves the exchanges captured so far as a fixture.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let exchanges = serde_json::to_vec_pretty(&*self.exchanges.lock().unwrap())?;
+        fs::write(path, exchanges)
+    }
+}
+
+impl<C: HttpClient> HttpClient for Recorder<C> {
+    fn send(&self, request: HttpRequest) -> AppResult<HttpResponse> {
+        let recorded_request = RecordedRequest {
+            uri: request.url.clone(),
+            method: request.method.to_string(),
+            headers: record_headers(&request.headers),
+            body: base64::encode(&request.body),
+        };
+
+        let response = self.client.send(request)?;
+        let recorded_response = RecordedResponse {
+            status: response.status.as_u16(),
+            headers: record_headers(&response.headers),
+            body: base64::encode(&response.body),
+        };
+
+        self.exchanges.lock().unwrap().push(Exchange {
+            request: recorded_request,
+            response: recorded_response,
+        });
+        Ok(response)
+    }
+}
+
+/// A client answering requests with captured responses, in the order they were captured.
+///
+/// Requests must be sent in the same order, with the same method, URL and body as when they
+/// were captured. Headers aren't compared since they contain tokens and dates.
+#[derive(Debug)]
+pub struct Replayer {
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl Replayer {
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        Replayer {
+            exchanges: Mutex::new(exchanges.into()),
+        }
+    }
+
+    pub fn from_fixture(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(load_fixture(path)?))
+    }
+
+    /// Returns the number of captured exchanges which weren't replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().len()
+    }
+}
+
+impl HttpClient for Replayer {
+    fn send(&self, request: HttpRequest) -> AppResult<HttpResponse> {
+        let exchange = self.exchanges.lock().unwrap().pop_front();
+        let exchange = exchange.ok_or_else(|| {
+            internal(&format_args!(
+                "no response was recorded for {} {}",
+                request.method, request.url
+            ))
+        })?;
+
+        let expected = &exchange.request;
+        if expected.method != request.method.as_str()
+            || expected.uri != request.url
+            || base64::decode(&expected.body)? != request.body
+        {
+            return Err(internal(&format_args!(
+                "expected {} {} but got {} {}",
+                expected.method, expected.uri, request.method, request.url
+            )));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &exchange.response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+
+        Ok(HttpResponse {
+            status: StatusCode::from_u16(exchange.response.status)?,
+            headers,
+            body: base64::decode(&exchange.response.body)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl HttpClient for Echo {
+        fn send(&self, request: HttpRequest) -> AppResult<HttpResponse> {
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: request.url.into_bytes(),
+            })
+        }
+    }
+
+    #[test]
+    fn recorded_exchanges_are_replayed_in_order() {
+        let recorder = Recorder::new(Echo);
+        recorder
+            .send(HttpRequest::get("https://example.com/a"))
+            .unwrap();
+        recorder
+            .send(HttpRequest::get("https://example.com/b"))
+            .unwrap();
+
+        let replayer = Replayer::new(recorder.exchanges());
+        let response = replayer
+            .send(HttpRequest::get("https://example.com/a"))
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, b"https://example.com/a");
+        assert_eq!(replayer.remaining(), 1);
+    }
+
+    #[test]
+    fn unexpected_requests_are_errors() {
+        let recorder = Recorder::new(Echo);
+        recorder
+            .send(HttpRequest::get("https://example.com/a"))
+            .unwrap();
+
+        let replayer = Replayer::new(recorder.exchanges());
+        assert!(replayer
+            .send(HttpRequest::get("https://example.com/b"))
+            .is_err());
+        assert!(replayer
+            .send(HttpRequest::get("https://example.com/a"))
+            .is_err());
+    }
+}
@@ -32,20 +32,34 @@ static ALLOC: Jemalloc = Jemalloc;
 
 pub mod admin;
 mod app;
+pub mod auth_provider;
 pub mod background_jobs;
 pub mod boot;
-mod config;
+pub mod cache;
+pub mod config;
+pub mod cpu_pool;
 pub mod db;
 pub mod email;
+pub mod email_blocklist;
+pub mod feature_flags;
 pub mod git;
 pub mod github;
+pub mod http_client;
+pub mod license_expressions;
 pub mod middleware;
-mod publish_rate_limit;
+pub mod migrations;
+pub mod rate_limit;
 pub mod render;
 pub mod schema;
+pub mod search_backend;
+pub mod server;
+pub mod shutdown;
+pub mod spam;
 pub mod tasks;
-mod test_util;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod uploaders;
+pub mod upstream;
 pub mod util;
 
 pub mod controllers;
@@ -102,7 +116,7 @@ pub fn build_handler(app: Arc<App>) -> MiddlewareBuilder {
 /// in the current environment.
 #[track_caller]
 pub fn env(s: &str) -> String {
-    dotenv::var(s).unwrap_or_else(|_| panic!("must have `{}` defined", s))
+    config::var(s).unwrap_or_else(|_| panic!("must have `{}` defined", s))
 }
 
 sql_function!(fn lower(x: ::diesel::sql_types::Text) -> ::diesel::sql_types::Text);
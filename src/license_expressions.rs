@@ -0,0 +1,77 @@
+//! The license expressions of the published versions
+//!
+//! Searches filtered by license match the expressions of the versions with the SPDX parser of
+//! `controllers::krate::search`, instead of scanning all the versions for their distinct
+//! expressions on each search. Every server process keeps the expressions it loaded, and only
+//! loads those of the versions published since then.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use diesel::dsl::max;
+use diesel::prelude::*;
+
+use crate::schema::versions;
+
+/// How many of the last loaded versions are loaded again, since versions published concurrently
+/// can be committed out of the order of their ids
+const RELOADED_VERSIONS: i32 = 1000;
+
+/// The cached license expressions of this process.
+#[derive(Debug, Default)]
+pub struct LicenseExpressions {
+    cache: Mutex<Cache>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    expressions: HashSet<String>,
+    /// The id of the last version when the expressions were loaded, `None` until they are
+    max_version_id: Option<i32>,
+}
+
+impl LicenseExpressions {
+    /// Returns the distinct license expressions of the versions.
+    ///
+    /// The first call loads all of them, and the next ones only those of the versions with an id
+    /// above the last one seen minus `RELOADED_VERSIONS`. The expressions of deleted versions are
+    /// kept, which only makes searches look for licenses no crate has anymore.
+    ///
+    /// The lock isn't held while querying, so concurrent searches each load the new versions
+    /// rather than waiting for one another.
+    pub fn all(&self, conn: &PgConnection) -> QueryResult<Vec<String>> {
+        let max_version_id = self.cache.lock().unwrap().max_version_id;
+        let (loaded_max_version_id, expressions) = match max_version_id {
+            None => {
+                let max_version_id = versions::table
+                    .select(max(versions::id))
+                    .first::<Option<i32>>(conn)?
+                    .unwrap_or(0);
+                let expressions = versions::table
+                    .select(versions::license)
+                    .filter(versions::license.is_not_null())
+                    .distinct()
+                    .load::<Option<String>>(conn)?;
+                (max_version_id, expressions)
+            }
+            Some(max_version_id) => {
+                let published = versions::table
+                    .select((versions::id, versions::license))
+                    .filter(versions::id.gt(max_version_id - RELOADED_VERSIONS))
+                    .load::<(i32, Option<String>)>(conn)?;
+                let loaded_max_version_id = published
+                    .iter()
+                    .map(|(id, _)| *id)
+                    .max()
+                    .unwrap_or(max_version_id);
+                let expressions = published.into_iter().map(|(_, license)| license).collect();
+                (loaded_max_version_id, expressions)
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.expressions.extend(expressions.into_iter().flatten());
+        cache.max_version_id = cache.max_version_id.max(Some(loaded_max_version_id));
+        Ok(cache.expressions.iter().cloned().collect())
+    }
+}
@@ -21,6 +21,7 @@ mod ensure_well_formed_500;
 mod head;
 mod log_connection_pool_status;
 pub mod log_request;
+mod maintenance_mode;
 mod normalize_path;
 mod require_user_agent;
 mod static_or_continue;
@@ -82,24 +83,21 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
     // In production we currently have 2 equally sized pools (primary and a read-only replica).
     // Because such a large portion of production traffic is for download requests (which update
     // download counts), we consider only the primary pool here.
-    if let Ok(capacity) = env::var("DB_POOL_SIZE") {
-        if let Ok(capacity) = capacity.parse() {
-            if capacity >= 10 {
-                println!(
-                    "Enabling BalanceCapacity middleware with {} pool capacity",
-                    capacity
-                );
-                m.around(balance_capacity::BalanceCapacity::new(capacity))
-            } else {
-                println!("BalanceCapacity middleware not enabled. DB_POOL_SIZE is too low.");
-            }
-        }
+    if config.db_pool.balance_capacity {
+        let capacity = config.db_pool.pool_size as usize;
+        println!(
+            "Enabling BalanceCapacity middleware with {} pool capacity",
+            capacity
+        );
+        m.around(balance_capacity::BalanceCapacity::new(capacity))
+    } else if env != Env::Test {
+        println!("BalanceCapacity middleware not enabled. DB_POOL_SIZE is too low.");
     }
 
     // Serve the static files in the *dist* directory, which are the frontend assets.
     // Not needed for the backend tests.
     if env != Env::Test {
-        m.around(EmberHtml::new("dist"));
+        m.around(EmberHtml::new("dist", config.fastboot));
         m.around(StaticOrContinue::new("dist"));
     }
 
@@ -109,6 +107,7 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
     }
 
     m.around(Head::default());
+    m.around(maintenance_mode::MaintenanceMode::default());
 
     for (header, blocked_values) in config.blocked_traffic {
         m.around(block_traffic::BlockTraffic::new(header, blocked_values));
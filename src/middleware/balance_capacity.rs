@@ -8,7 +8,6 @@
 //! we should avoid dropping download requests even if that means rejecting some legitimate
 //! requests to other endpoints.
 
-use std::env;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::prelude::*;
@@ -35,7 +34,9 @@ impl BalanceCapacity {
             db_capacity,
             in_flight_non_dl_requests: AtomicUsize::new(0),
 
-            report_only: env::var("WEB_CAPACITY_REPORT_ONLY").ok().is_some(),
+            report_only: crate::config::var("WEB_CAPACITY_REPORT_ONLY")
+                .ok()
+                .is_some(),
             log_total_at_count: read_env_percentage("WEB_CAPACITY_LOG_TOTAL_AT_COUNT", 50),
             // The following are a percentage of `db_capacity`
             log_at_percentage: read_env_percentage("WEB_CAPACITY_LOG_PCT", 50),
@@ -117,7 +118,7 @@ impl Handler for BalanceCapacity {
 }
 
 fn read_env_percentage(name: &str, default: usize) -> usize {
-    if let Ok(value) = env::var(name) {
+    if let Ok(value) = crate::config::var(name) {
         value.parse().unwrap_or(default)
     } else {
         default
@@ -11,6 +11,7 @@
 use super::prelude::*;
 use std::fmt::Write;
 
+use crate::config::FastBoot;
 use crate::util::{errors::NotFound, AppResponse};
 
 use anyhow::{ensure, Result};
@@ -25,10 +26,10 @@ pub(super) struct EmberHtml {
 }
 
 impl EmberHtml {
-    pub fn new(path: &str) -> Self {
-        let fastboot_client = match dotenv::var("USE_FASTBOOT") {
-            Ok(val) if val == "staging-experimental" => Some(Client::new()),
-            _ => None,
+    pub fn new(path: &str, fastboot: FastBoot) -> Self {
+        let fastboot_client = match fastboot {
+            FastBoot::Experimental => Some(Client::new()),
+            FastBoot::Enabled | FastBoot::Disabled => None,
         };
 
         Self {
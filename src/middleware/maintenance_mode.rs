@@ -0,0 +1,48 @@
+//! Middleware that rejects the requests which could write to the database during maintenance
+//!
+//! Maintenance mode is enabled by setting the `MAINTENANCE_MODE` environment variable, or at
+//! runtime with the `maintenance_mode` feature flag. All the requests except `GET`, `HEAD` and
+//! `OPTIONS` ones are answered with a 503, so that browsing and downloading crates keep working
+//! while the database is migrated or an incident is handled.
+//!
+//! Requests to the admin endpoints are always allowed, so that the feature flag can be disabled.
+
+use super::prelude::*;
+use conduit::Method;
+
+use crate::feature_flags::FeatureFlag;
+use crate::middleware::app::RequestApp;
+use crate::util::errors::{AppError, ReadOnlyMode};
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+#[derive(Default)]
+pub struct MaintenanceMode {
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl AroundMiddleware for MaintenanceMode {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for MaintenanceMode {
+    fn call(&self, req: &mut dyn RequestExt) -> AfterResult {
+        let method = req.method();
+        let is_read = method == Method::GET || method == Method::HEAD || method == Method::OPTIONS;
+        let is_admin = req.path().starts_with("/api/v1/admin/");
+
+        if !is_read && !is_admin && is_enabled(req) {
+            super::log_request::add_custom_metadata(req, "cause", "maintenance mode");
+            Ok(ReadOnlyMode.response().unwrap())
+        } else {
+            self.handler.as_ref().unwrap().call(req)
+        }
+    }
+}
+
+fn is_enabled(req: &dyn RequestExt) -> bool {
+    let app = req.app();
+    app.config.maintenance_mode || app.is_feature_enabled(FeatureFlag::MaintenanceMode)
+}
@@ -8,7 +8,6 @@
 //! 0.17 (released alongside rustc 1.17).
 
 use super::prelude::*;
-use std::env;
 
 use crate::util::request_header;
 
@@ -23,7 +22,7 @@ pub struct RequireUserAgent {
 impl AroundMiddleware for RequireUserAgent {
     fn with_handler(&mut self, handler: Box<dyn Handler>) {
         self.cdn_user_agent =
-            env::var("WEB_CDN_USER_AGENT").unwrap_or_else(|_| "Amazon CloudFront".into());
+            crate::config::var("WEB_CDN_USER_AGENT").unwrap_or_else(|_| "Amazon CloudFront".into());
         self.handler = Some(handler);
     }
 }
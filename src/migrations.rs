@@ -0,0 +1,97 @@
+//! Phases of the database migrations
+//!
+//! During a rolling deploy the old and the new versions of the code run at the same time, so
+//! migrations are split in two phases:
+//!
+//! - Pre-deploy migrations run before the new code starts, in the release phase. They may only
+//!   add to the schema (tables, nullable columns, columns with defaults, indexes) so that the
+//!   old code keeps working.
+//! - Post-deploy migrations run once the old code stopped, with
+//!   `crates-admin migrate --phase post-deploy`. They remove or constrain what only the old code
+//!   used, e.g. drop a column or add a `NOT NULL` constraint.
+//!
+//! Migrations are pre-deploy unless their version is listed in `POST_DEPLOY_MIGRATIONS`. The
+//! code requires all the pre-deploy migrations it knows about, and refuses to boot if one of
+//! them is missing, while it must keep working whether its post-deploy migrations ran or not.
+
+use std::str::FromStr;
+
+use diesel::prelude::*;
+
+include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+// The table in which Diesel records the migrations which ran
+table! {
+    __diesel_schema_migrations (version) {
+        version -> VarChar,
+        run_on -> Timestamp,
+    }
+}
+
+/// The versions of the migrations which run after the deploy, e.g. `20200916120000`
+pub const POST_DEPLOY_MIGRATIONS: &[&str] = &[];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    PreDeploy,
+    PostDeploy,
+}
+
+impl Phase {
+    /// Returns the phase of the migration with the given version.
+    pub fn of(version: &str) -> Self {
+        if POST_DEPLOY_MIGRATIONS.contains(&version) {
+            Phase::PostDeploy
+        } else {
+            Phase::PreDeploy
+        }
+    }
+}
+
+impl FromStr for Phase {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pre-deploy" => Ok(Phase::PreDeploy),
+            "post-deploy" => Ok(Phase::PostDeploy),
+            _ => Err("Phase must be pre-deploy or post-deploy"),
+        }
+    }
+}
+
+/// Returns the versions of the pre-deploy migrations which haven't run yet, which the code
+/// requires.
+pub fn missing_migrations(conn: &PgConnection) -> QueryResult<Vec<&'static str>> {
+    let run = __diesel_schema_migrations::table
+        .select(__diesel_schema_migrations::version)
+        .load::<String>(conn)?;
+    Ok(MIGRATIONS
+        .iter()
+        .copied()
+        .filter(|version| Phase::of(version) == Phase::PreDeploy)
+        .filter(|version| !run.iter().any(|run| run == version))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_deploy_migrations_exist() {
+        for version in POST_DEPLOY_MIGRATIONS {
+            assert!(
+                MIGRATIONS.contains(version),
+                "unknown migration {}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn versions_are_sorted() {
+        assert!(MIGRATIONS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(MIGRATIONS.contains(&"20200916120000"));
+    }
+}
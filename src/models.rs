@@ -1,35 +1,74 @@
-pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
+pub use self::action::{
+    insert_version_owner_action, NewVersionYankEvent, VersionAction, VersionOwnerAction,
+    VersionYankEvent,
+};
+pub use self::advisory::{CrateAdvisory, NewCrateAdvisory};
 pub use self::badge::{Badge, CrateBadge, MaintenanceStatus};
 pub use self::category::{Category, CrateCategory, NewCategory};
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitation};
+pub use self::crate_ownership_transfer::{
+    CrateOwnershipTransfer, NewCrateOwnershipTransfer, TRANSFER_EXPIRATION_DAYS,
+};
 pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
 pub use self::download::VersionDownload;
 pub use self::email::{Email, NewEmail};
 pub use self::follow::Follow;
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
+pub use self::link_check::{CrateLinkCheck, LinkStatus};
+pub use self::localized_readme::{negotiate_language, LocalizedReadme};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::pageview::CratePageview;
+pub use self::publish_policy::{CratePublishPolicy, NewCratePublishPolicy};
+pub use self::quality_score::CrateQualityScore;
+pub use self::quarantined_version::QuarantinedVersion;
+pub use self::readme_rerender::ReadmeRerender;
+pub use self::rebuild::VersionRebuild;
+pub use self::registry_event::{RegistryEvent, RegistryEventKind};
+pub use self::rendered_readme::RenderedReadme;
+pub use self::reserved_crate_name::ReservedCrateName;
 pub use self::rights::Rights;
-pub use self::team::{NewTeam, Team};
+pub use self::team::{NewTeam, Team, TeamMembership};
 pub use self::token::{ApiToken, CreatedApiToken};
+pub use self::usage_stats::{CategoryStat, KeywordStat};
 pub use self::user::{NewUser, User};
 pub use self::version::{NewVersion, Version};
+pub use self::version_changelog::{NewVersionChangelog, VersionChangelog};
+pub use self::version_diff::VersionDiff;
+pub use self::version_file::VersionFile;
 
 pub mod helpers;
 
 mod action;
+mod advisory;
 mod badge;
 pub mod category;
 mod crate_owner_invitation;
+mod crate_ownership_transfer;
 pub mod dependency;
 mod download;
 mod email;
 mod follow;
 mod keyword;
 pub mod krate;
+mod link_check;
+mod localized_readme;
 mod owner;
+mod pageview;
+mod publish_policy;
+mod quality_score;
+mod quarantined_version;
+mod readme_rerender;
+mod rebuild;
+mod registry_event;
+mod rendered_readme;
+mod reserved_crate_name;
 mod rights;
 mod team;
 mod token;
+mod usage_stats;
 pub mod user;
 mod version;
+mod version_changelog;
+mod version_diff;
+mod version_file;
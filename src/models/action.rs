@@ -96,6 +96,61 @@ impl VersionOwnerAction {
     }
 }
 
+/// A yank or an unyank of a version, with the reason the owner gave for it
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[belongs_to(ApiToken, foreign_key = "api_token_id")]
+#[table_name = "version_yank_events"]
+pub struct VersionYankEvent {
+    pub id: i32,
+    pub version_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    /// Whether the version was yanked, or unyanked
+    pub yanked: bool,
+    pub reason: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionYankEvent {
+    pub fn by_version(conn: &PgConnection, version: &Version) -> QueryResult<Vec<(Self, User)>> {
+        Self::belonging_to(version)
+            .inner_join(users::table)
+            .order(version_yank_events::id)
+            .load(conn)
+    }
+
+    pub fn for_versions(
+        conn: &PgConnection,
+        versions: &[Version],
+    ) -> QueryResult<Vec<Vec<(Self, User)>>> {
+        Ok(Self::belonging_to(versions)
+            .inner_join(users::table)
+            .order(version_yank_events::id)
+            .load(conn)?
+            .grouped_by(versions))
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "version_yank_events"]
+pub struct NewVersionYankEvent<'a> {
+    pub version_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub yanked: bool,
+    pub reason: Option<&'a str>,
+}
+
+impl NewVersionYankEvent<'_> {
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<VersionYankEvent> {
+        diesel::insert_into(version_yank_events::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
 pub fn insert_version_owner_action(
     conn: &PgConnection,
     version_id_: i32,
@@ -0,0 +1,102 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use semver::VersionReq;
+
+use crate::models::Crate;
+use crate::schema::crate_advisories;
+use crate::views::EncodableCrateAdvisory;
+
+/// A security advisory affecting some versions of a crate, such as one from the
+/// RustSec advisory database.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Crate)]
+#[table_name = "crate_advisories"]
+pub struct CrateAdvisory {
+    pub id: i32,
+    pub crate_id: i32,
+    pub identifier: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Semver requirements matching the affected versions
+    pub affected_versions: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[table_name = "crate_advisories"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewCrateAdvisory<'a> {
+    pub crate_id: i32,
+    pub identifier: &'a str,
+    pub title: &'a str,
+    pub url: Option<&'a str>,
+    pub affected_versions: &'a [String],
+}
+
+impl<'a> NewCrateAdvisory<'a> {
+    /// Stores the advisory, replacing any previous advisory with the same identifier
+    /// for this crate.
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<CrateAdvisory> {
+        diesel::insert_into(crate_advisories::table)
+            .values(self)
+            .on_conflict((crate_advisories::crate_id, crate_advisories::identifier))
+            .do_update()
+            .set(self)
+            .get_result(conn)
+    }
+}
+
+impl CrateAdvisory {
+    /// Returns whether `version` matches any of the affected version requirements.
+    ///
+    /// Requirements which can't be parsed never match.
+    pub fn affects(&self, version: &semver::Version) -> bool {
+        self.affected_versions.iter().any(|req| {
+            VersionReq::parse(req)
+                .map(|req| req.matches(version))
+                .unwrap_or(false)
+        })
+    }
+
+    pub fn encodable(self) -> EncodableCrateAdvisory {
+        EncodableCrateAdvisory {
+            id: self.identifier,
+            title: self.title,
+            url: self.url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(affected_versions: &[&str]) -> CrateAdvisory {
+        CrateAdvisory {
+            id: 1,
+            crate_id: 1,
+            identifier: "RUSTSEC-2020-0001".into(),
+            title: "Memory corruption".into(),
+            url: None,
+            affected_versions: affected_versions.iter().map(|&req| req.into()).collect(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+        }
+    }
+
+    #[test]
+    fn affects_matching_versions() {
+        let advisory = advisory(&["< 0.3.5", ">= 1.0.0, < 1.2.1"]);
+        let affects = |version| advisory.affects(&semver::Version::parse(version).unwrap());
+
+        assert!(affects("0.3.4"));
+        assert!(!affects("0.3.5"));
+        assert!(affects("1.1.0"));
+        assert!(!affects("1.2.1"));
+    }
+
+    #[test]
+    fn invalid_requirements_never_match() {
+        let advisory = advisory(&["not a requirement"]);
+        assert!(!advisory.affects(&semver::Version::parse("1.0.0").unwrap()));
+    }
+}
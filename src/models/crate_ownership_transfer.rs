@@ -0,0 +1,89 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::schema::{crate_ownership_transfers, crates, users};
+use crate::views::EncodableCrateOwnershipTransfer;
+
+/// How many days the target of an ownership transfer has to accept it
+pub const TRANSFER_EXPIRATION_DAYS: i64 = 7;
+
+/// The model representing a row in the `crate_ownership_transfers` database table.
+///
+/// A crate has at most one pending transfer, which replaces all of its owners by the target
+/// user once they accept it.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+#[primary_key(crate_id)]
+pub struct CrateOwnershipTransfer {
+    pub crate_id: i32,
+    pub requested_by_user_id: i32,
+    pub target_user_id: i32,
+    pub token: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Clone, Copy, Debug)]
+#[table_name = "crate_ownership_transfers"]
+pub struct NewCrateOwnershipTransfer {
+    pub crate_id: i32,
+    pub requested_by_user_id: i32,
+    pub target_user_id: i32,
+}
+
+impl NewCrateOwnershipTransfer {
+    /// Inserts the transfer, replacing the pending transfer of the crate if there is one.
+    ///
+    /// A new token is generated, so that the target of a replaced transfer can't accept it.
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<CrateOwnershipTransfer> {
+        use diesel::dsl::{now, sql};
+        use diesel::insert_into;
+        use diesel::sql_types::Text;
+
+        insert_into(crate_ownership_transfers::table)
+            .values(self)
+            .on_conflict(crate_ownership_transfers::crate_id)
+            .do_update()
+            .set((
+                crate_ownership_transfers::requested_by_user_id.eq(self.requested_by_user_id),
+                crate_ownership_transfers::target_user_id.eq(self.target_user_id),
+                crate_ownership_transfers::token.eq(sql::<Text>("random_string(26)")),
+                crate_ownership_transfers::created_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+}
+
+impl CrateOwnershipTransfer {
+    pub fn expires_at(&self) -> NaiveDateTime {
+        self.created_at + Duration::days(TRANSFER_EXPIRATION_DAYS)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at() < Utc::now().naive_utc()
+    }
+
+    fn username(conn: &PgConnection, user_id: i32) -> String {
+        users::table
+            .find(user_id)
+            .select(users::gh_login)
+            .first(&*conn)
+            .unwrap_or_else(|_| String::from("(unknown username)"))
+    }
+
+    pub fn crate_name(&self, conn: &PgConnection) -> String {
+        crates::table
+            .find(self.crate_id)
+            .select(crates::name)
+            .first(&*conn)
+            .unwrap_or_else(|_| String::from("(unknown crate name)"))
+    }
+
+    pub fn encodable(self, conn: &PgConnection) -> EncodableCrateOwnershipTransfer {
+        EncodableCrateOwnershipTransfer {
+            crate_name: self.crate_name(conn),
+            requested_by_username: Self::username(conn, self.requested_by_user_id),
+            target_username: Self::username(conn, self.target_user_id),
+            created_at: self.created_at,
+            expires_at: self.expires_at(),
+        }
+    }
+}
@@ -21,12 +21,18 @@ pub struct Dependency {
     pub features: Vec<String>,
     pub target: Option<String>,
     pub kind: DependencyKind,
+    /// The artifact kinds requested by an artifact dependency, e.g. `bin` or `cdylib`
+    pub artifact: Option<Vec<String>>,
+    pub bindep_target: Option<String>,
+    pub lib: bool,
 }
 
 #[derive(Debug, QueryableByName)]
 pub struct ReverseDependency {
     #[diesel(embed)]
     dependency: Dependency,
+    #[sql_type = "::diesel::sql_types::Text"]
+    published_req: String,
     #[sql_type = "::diesel::sql_types::Integer"]
     crate_downloads: i32,
     #[sql_type = "::diesel::sql_types::Text"]
@@ -45,6 +51,20 @@ pub enum DependencyKind {
 }
 
 impl Dependency {
+    /// Returns the dependencies of each of the versions, in the same order as the versions,
+    /// along with the names of the crates they depend on.
+    pub fn for_versions(
+        conn: &PgConnection,
+        versions: &[Version],
+    ) -> QueryResult<Vec<Vec<(Self, String)>>> {
+        Ok(Self::belonging_to(versions)
+            .inner_join(crates::table)
+            .select((dependencies::all_columns, crates::name))
+            .order((dependencies::optional, crates::name))
+            .load(conn)?
+            .grouped_by(versions))
+    }
+
     // `downloads` need only be specified when generating a reverse dependency
     pub fn encodable(self, crate_name: &str, downloads: Option<i32>) -> EncodableDependency {
         EncodableDependency {
@@ -57,6 +77,9 @@ impl Dependency {
             features: self.features,
             target: self.target,
             kind: self.kind,
+            artifact: self.artifact,
+            bindep_target: self.bindep_target,
+            lib: self.lib,
             downloads: downloads.unwrap_or(0),
         }
     }
@@ -64,11 +87,54 @@ impl Dependency {
 
 impl ReverseDependency {
     pub fn encodable(self, crate_name: &str) -> EncodableDependency {
-        self.dependency
-            .encodable(crate_name, Some(self.crate_downloads))
+        EncodableDependency {
+            req: self.published_req,
+            ..self
+                .dependency
+                .encodable(crate_name, Some(self.crate_downloads))
+        }
     }
 }
 
+/// Records the dependencies of a version from its index entry, for versions which were
+/// published to another registry. Dependencies on crates unknown to this instance are
+/// skipped.
+pub fn add_index_dependencies(
+    conn: &PgConnection,
+    deps: &[git::Dependency],
+    target_version_id: i32,
+) -> QueryResult<()> {
+    use self::dependencies::dsl::*;
+    use diesel::insert_into;
+
+    let mut new_dependencies = Vec::with_capacity(deps.len());
+    for dep in deps {
+        let name = dep.package.as_ref().unwrap_or(&dep.name);
+        let krate = match Crate::by_exact_name(name).first::<Crate>(conn).optional()? {
+            Some(krate) => krate,
+            None => continue,
+        };
+        new_dependencies.push((
+            version_id.eq(target_version_id),
+            crate_id.eq(krate.id),
+            req.eq(&dep.req),
+            kind.eq(dep.kind.unwrap_or(DependencyKind::Normal) as i32),
+            optional.eq(dep.optional),
+            default_features.eq(dep.default_features),
+            features.eq(&dep.features),
+            target.eq(dep.target.as_deref()),
+            artifact.eq(dep.artifact.as_ref()),
+            bindep_target.eq(dep.bindep_target.as_deref()),
+            lib.eq(dep.lib),
+        ));
+    }
+
+    insert_into(dependencies)
+        .values(&new_dependencies)
+        .execute(conn)?;
+    Ok(())
+}
+
 pub fn add_dependencies(
     conn: &PgConnection,
     deps: &[EncodableCrateDependency],
@@ -99,6 +165,8 @@ pub fn add_dependencies(
                 ));
             }
 
+            validate_artifact(dep)?;
+
             // If this dependency has an explicit name in `Cargo.toml` that
             // means that the `name` we have listed is actually the package name
             // that we're depending on. The `name` listed in the index is the
@@ -119,6 +187,9 @@ pub fn add_dependencies(
                     target: dep.target.clone(),
                     kind: dep.kind.or(Some(DependencyKind::Normal)),
                     package,
+                    artifact: dep.artifact.clone(),
+                    bindep_target: dep.bindep_target.clone(),
+                    lib: dep.lib,
                 },
                 (
                     version_id.eq(target_version_id),
@@ -129,6 +200,9 @@ pub fn add_dependencies(
                     default_features.eq(dep.default_features),
                     features.eq(&dep.features),
                     target.eq(dep.target.as_deref()),
+                    artifact.eq(dep.artifact.as_ref()),
+                    bindep_target.eq(dep.bindep_target.as_deref()),
+                    lib.eq(dep.lib),
                 ),
             ))
         })
@@ -144,6 +218,47 @@ pub fn add_dependencies(
     Ok(git_deps)
 }
 
+/// Validates the fields of an artifact dependency, which requests the binaries or
+/// libraries built by a crate rather than linking to it.
+fn validate_artifact(dep: &EncodableCrateDependency) -> AppResult<()> {
+    let artifact = match &dep.artifact {
+        Some(artifact) => artifact,
+        None if dep.bindep_target.is_some() || dep.lib => {
+            return Err(cargo_err(&format_args!(
+                "dependency `{}` sets `bindep_target` or `lib` without `artifact`. \
+                 These fields are only allowed for artifact dependencies.",
+                &*dep.name
+            )));
+        }
+        None => return Ok(()),
+    };
+
+    if artifact.is_empty() {
+        return Err(cargo_err(&format_args!(
+            "artifact dependency `{}` must request at least one artifact kind",
+            &*dep.name
+        )));
+    }
+
+    for kind in artifact {
+        let valid = match kind.as_str() {
+            "bin" | "cdylib" | "staticlib" => true,
+            kind => kind
+                .strip_prefix("bin:")
+                .map_or(false, |name| !name.is_empty()),
+        };
+        if !valid {
+            return Err(cargo_err(&format_args!(
+                "artifact dependency `{}` requests the unknown artifact kind `{}`. \
+                 Expected `bin`, `cdylib`, `staticlib` or `bin:<name>`.",
+                &*dep.name, kind
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
 use diesel::sql_types::Integer;
@@ -0,0 +1,21 @@
+-- Recompute the cached dependents count of every crate, in case an
+-- incremental update was missed
+WITH latest_versions AS (
+    SELECT DISTINCT ON (crate_id) id, crate_id
+    FROM versions
+    WHERE NOT yanked
+    ORDER BY crate_id, to_semver_no_prerelease(num) DESC NULLS LAST
+), counts AS (
+    SELECT crates.id, COUNT(DISTINCT latest_versions.crate_id) AS dependents_count
+    FROM crates
+    LEFT JOIN dependencies
+      ON dependencies.crate_id = crates.id
+    LEFT JOIN latest_versions
+      ON latest_versions.id = dependencies.version_id
+    GROUP BY crates.id
+)
+UPDATE crates
+SET dependents_count = counts.dependents_count
+FROM counts
+WHERE crates.id = counts.id
+  AND crates.dependents_count <> counts.dependents_count
@@ -1,9 +1,12 @@
 -- Apply pagination to the whole thing
 SELECT *, COUNT(*) OVER () as total FROM (
     -- Multple dependencies can exist, make it distinct
-    SELECT DISTINCT ON (crate_downloads, crate_name)
+    SELECT DISTINCT ON (crate_name)
     dependencies.*,
+    -- The requirement as it was published, `req` is normalized when it's parsed
+    dependencies.req AS published_req,
     crates.downloads AS crate_downloads,
+    COALESCE(recent_crate_downloads.downloads, 0) AS crate_recent_downloads,
     crates.name AS crate_name
     FROM dependencies
     -- We only want the crates whose *max* version is dependent, so we join on a
@@ -29,9 +32,17 @@ SELECT *, COUNT(*) OVER () as total FROM (
       ON versions.id = dependencies.version_id
     INNER JOIN crates
       ON crates.id = versions.crate_id
+    LEFT JOIN recent_crate_downloads
+      ON recent_crate_downloads.crate_id = crates.id
     WHERE dependencies.crate_id = $1
       AND rn = 1
-    ORDER BY crate_downloads DESC
+    ORDER BY crate_name, dependencies.id
 ) t
+-- Sort by `$4`, which is `downloads`, `name` or `recent`
+ORDER BY
+    CASE WHEN $4 = 'name' THEN crate_name END ASC,
+    CASE WHEN $4 = 'recent' THEN crate_recent_downloads END DESC,
+    crate_downloads DESC,
+    crate_name ASC
 OFFSET $2
 LIMIT $3
@@ -0,0 +1,25 @@
+-- Refresh the cached number of crates whose latest non-yanked version depends
+-- on each of the given crates. This is the same set of crates which is listed
+-- by `krate_reverse_dependencies.sql`.
+UPDATE crates
+SET dependents_count = (
+    SELECT COUNT(DISTINCT versions.crate_id)
+    FROM dependencies
+    INNER JOIN (
+        SELECT DISTINCT ON (crate_id) id, crate_id
+        FROM versions
+        WHERE NOT yanked
+        -- Only look at the crates which have ever depended on this crate
+        AND crate_id = ANY(
+            SELECT versions.crate_id
+            FROM versions
+            INNER JOIN dependencies
+            ON dependencies.version_id = versions.id
+            WHERE dependencies.crate_id = crates.id
+        )
+        ORDER BY crate_id, to_semver_no_prerelease(num) DESC NULLS LAST
+    ) versions
+      ON versions.id = dependencies.version_id
+    WHERE dependencies.crate_id = crates.id
+)
+WHERE crates.id = ANY($1)
@@ -0,0 +1,195 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::Crate;
+use crate::schema::crate_link_checks;
+use crate::views::EncodableCrateLinkCheck;
+
+/// Hosts which serve the pages of parked domains, and which the links of a crate are
+/// redirected to once its domain expired.
+const PARKING_HOSTS: &[&str] = &[
+    "sedoparking.com",
+    "parkingcrew.net",
+    "bodis.com",
+    "dan.com",
+    "afternic.com",
+    "hugedomains.com",
+    "parklogic.com",
+];
+
+/// Text found in the pages of parked domains
+const PARKING_MARKERS: &[&str] = &[
+    "this domain is for sale",
+    "this domain may be for sale",
+    "buy this domain",
+    "domain is parked",
+    "parked free, courtesy of",
+];
+
+/// The outcome of the check of a link of a crate, see `CrateLinkCheck`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    Ok,
+    /// The server answered with `404 Not Found` or `410 Gone`, which is what
+    /// code hosts answer for deleted repositories
+    NotFound,
+    /// The domain of the link expired and now serves ads or offers to buy it
+    Parked,
+    /// The server could not be reached or failed to answer
+    Unreachable,
+}
+
+impl LinkStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkStatus::Ok => "ok",
+            LinkStatus::NotFound => "not_found",
+            LinkStatus::Parked => "parked",
+            LinkStatus::Unreachable => "unreachable",
+        }
+    }
+
+    /// Classifies the response to a request for a link, given its status code, the host
+    /// it was answered by after following redirects, and the start of its body.
+    ///
+    /// Other client errors, such as `403 Forbidden` or `429 Too Many Requests`, are
+    /// usually answered to bots by working sites and aren't reported.
+    pub fn from_response(status: u16, host: Option<&str>, body: &str) -> Self {
+        if status == 404 || status == 410 {
+            return LinkStatus::NotFound;
+        }
+        if status >= 500 {
+            return LinkStatus::Unreachable;
+        }
+
+        let parking_host = host.map_or(false, |host| {
+            let host = host.to_lowercase();
+            PARKING_HOSTS
+                .iter()
+                .any(|parking| host == *parking || host.ends_with(&format!(".{}", parking)))
+        });
+        let body = body.to_lowercase();
+        if parking_host || PARKING_MARKERS.iter().any(|marker| body.contains(marker)) {
+            return LinkStatus::Parked;
+        }
+
+        LinkStatus::Ok
+    }
+}
+
+/// The result of the last check of the `documentation`, `homepage` or `repository` link of a
+/// crate by the `check_crate_links` background job.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id, field)]
+pub struct CrateLinkCheck {
+    pub crate_id: i32,
+    /// The name of the column of the link in `crates`
+    pub field: String,
+    /// The link at the time of the check
+    pub url: String,
+    /// See `LinkStatus::as_str`
+    pub status: String,
+    pub checked_at: NaiveDateTime,
+}
+
+impl CrateLinkCheck {
+    /// Records the status of a link of a crate, replacing the previous check of the field.
+    pub fn record(
+        conn: &PgConnection,
+        crate_id: i32,
+        field: &str,
+        url: &str,
+        status: LinkStatus,
+    ) -> QueryResult<()> {
+        use diesel::dsl::now;
+        use diesel::pg::upsert::excluded;
+
+        diesel::insert_into(crate_link_checks::table)
+            .values((
+                crate_link_checks::crate_id.eq(crate_id),
+                crate_link_checks::field.eq(field),
+                crate_link_checks::url.eq(url),
+                crate_link_checks::status.eq(status.as_str()),
+            ))
+            .on_conflict((crate_link_checks::crate_id, crate_link_checks::field))
+            .do_update()
+            .set((
+                crate_link_checks::url.eq(excluded(crate_link_checks::url)),
+                crate_link_checks::status.eq(excluded(crate_link_checks::status)),
+                crate_link_checks::checked_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the checks of the current links of a crate, leaving out the links which were
+    /// changed since they were checked.
+    pub fn current(conn: &PgConnection, krate: &Crate) -> QueryResult<Vec<Self>> {
+        let checks = CrateLinkCheck::belonging_to(krate)
+            .order(crate_link_checks::field)
+            .load::<Self>(conn)?;
+        Ok(checks
+            .into_iter()
+            .filter(|check| {
+                let current = match &*check.field {
+                    "documentation" => &krate.documentation,
+                    "homepage" => &krate.homepage,
+                    "repository" => &krate.repository,
+                    _ => return false,
+                };
+                current.as_deref() == Some(&*check.url)
+            })
+            .collect())
+    }
+
+    pub fn encodable(self) -> EncodableCrateLinkCheck {
+        EncodableCrateLinkCheck {
+            field: self.field,
+            url: self.url,
+            status: self.status,
+            checked_at: self.checked_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinkStatus;
+
+    #[test]
+    fn missing_pages_are_not_found() {
+        assert_eq!(
+            LinkStatus::from_response(404, Some("github.com"), ""),
+            LinkStatus::NotFound
+        );
+        assert_eq!(
+            LinkStatus::from_response(410, Some("example.com"), ""),
+            LinkStatus::NotFound
+        );
+        assert_eq!(
+            LinkStatus::from_response(503, Some("example.com"), ""),
+            LinkStatus::Unreachable
+        );
+        assert_eq!(
+            LinkStatus::from_response(403, Some("example.com"), ""),
+            LinkStatus::Ok
+        );
+    }
+
+    #[test]
+    fn parked_domains_are_detected() {
+        assert_eq!(
+            LinkStatus::from_response(200, Some("www.sedoparking.com"), ""),
+            LinkStatus::Parked
+        );
+        assert_eq!(
+            LinkStatus::from_response(200, Some("foo.rs"), "<h1>This domain is for sale!</h1>"),
+            LinkStatus::Parked
+        );
+        assert_eq!(
+            LinkStatus::from_response(200, Some("notdan.com"), "<h1>Foo</h1>"),
+            LinkStatus::Ok
+        );
+    }
+}
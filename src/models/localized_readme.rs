@@ -0,0 +1,132 @@
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::localized_readmes;
+
+/// The localized READMEs of versions, see `TarballReadme`
+pub struct LocalizedReadme;
+
+impl LocalizedReadme {
+    /// Records that the README of a version in the given language was rendered.
+    pub fn record(
+        conn: &PgConnection,
+        version_id: i32,
+        language: &str,
+        file_name: &str,
+    ) -> QueryResult<()> {
+        diesel::insert_into(localized_readmes::table)
+            .values((
+                localized_readmes::version_id.eq(version_id),
+                localized_readmes::language.eq(language),
+                localized_readmes::file_name.eq(file_name),
+            ))
+            .on_conflict((localized_readmes::version_id, localized_readmes::language))
+            .do_update()
+            .set((
+                localized_readmes::file_name.eq(file_name),
+                localized_readmes::rendered_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the languages the README of a version is available in, besides the main one.
+    pub fn languages(conn: &PgConnection, version_id: i32) -> QueryResult<Vec<String>> {
+        localized_readmes::table
+            .filter(localized_readmes::version_id.eq(version_id))
+            .select(localized_readmes::language)
+            .order(localized_readmes::language)
+            .load(conn)
+    }
+}
+
+/// Picks the language of the README to serve among the `available` ones, or `None` for the
+/// main README.
+///
+/// The language asked for with `?lang=` wins, then the ones of the `Accept-Language` header
+/// by order of preference. A language matches a README in the same language whatever its
+/// region, e.g. `zh` or `zh-TW` match `zh-CN` if no better README exists.
+pub fn negotiate_language<'a>(
+    available: &'a [String],
+    lang: Option<&str>,
+    accept_language: Option<&str>,
+) -> Option<&'a str> {
+    let wanted = lang
+        .into_iter()
+        .chain(accept_language.map_or_else(Vec::new, parse_accept_language));
+
+    for tag in wanted {
+        let tag = tag.to_lowercase();
+        let primary = tag.split('-').next().unwrap_or_default();
+        let exact = available.iter().find(|language| **language == tag);
+        let same_primary = || {
+            available
+                .iter()
+                .find(|language| language.split('-').next() == Some(primary))
+        };
+        if let Some(language) = exact.or_else(same_primary) {
+            return Some(language);
+        }
+    }
+    None
+}
+
+/// Returns the language ranges of an `Accept-Language` header, most preferred first.
+fn parse_accept_language(header: &str) -> Vec<&str> {
+    let mut ranges = header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            if tag.is_empty() || tag == "*" || q <= 0.0 {
+                None
+            } else {
+                Some((tag, q))
+            }
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, so ranges of the same weight keep their order
+    ranges.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    ranges.into_iter().map(|(tag, _)| tag).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available() -> Vec<String> {
+        vec!["fr".into(), "zh-cn".into()]
+    }
+
+    #[test]
+    fn lang_parameter_wins() {
+        let available = available();
+        assert_eq!(
+            negotiate_language(&available, Some("zh-CN"), Some("fr")),
+            Some("zh-cn")
+        );
+        assert_eq!(
+            negotiate_language(&available, Some("de"), Some("fr;q=0.5")),
+            Some("fr")
+        );
+        assert_none!(negotiate_language(&available, Some("de"), None));
+    }
+
+    #[test]
+    fn accept_language_fallback() {
+        let available = available();
+        assert_eq!(
+            negotiate_language(&available, None, Some("de, zh-TW;q=0.8, fr;q=0.5")),
+            Some("zh-cn")
+        );
+        assert_eq!(
+            negotiate_language(&available, None, Some("fr;q=0.1, zh;q=0.9")),
+            Some("zh-cn")
+        );
+        assert_none!(negotiate_language(&available, None, Some("en-US, *;q=0.5")));
+        assert_none!(negotiate_language(&available, None, None));
+    }
+}
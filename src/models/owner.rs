@@ -94,6 +94,13 @@ impl Owner {
         }
     }
 
+    pub fn github_id(&self) -> i32 {
+        match *self {
+            Owner::User(ref user) => user.gh_id,
+            Owner::Team(ref team) => team.github_id,
+        }
+    }
+
     pub fn id(&self) -> i32 {
         match *self {
             Owner::User(ref user) => user.id,
@@ -103,19 +110,20 @@ impl Owner {
 
     pub fn encodable(self) -> EncodableOwner {
         match self {
-            Owner::User(User {
-                id,
-                name,
-                gh_login,
-                gh_avatar,
-                ..
-            }) => {
-                let url = format!("https://github.com/{}", gh_login);
+            Owner::User(user) => {
+                let url = user.github_url();
+                let User {
+                    id,
+                    name,
+                    gh_login,
+                    gh_avatar,
+                    ..
+                } = user;
                 EncodableOwner {
                     id,
                     login: gh_login,
                     avatar: gh_avatar,
-                    url: Some(url),
+                    url,
                     name,
                     kind: String::from("user"),
                 }
@@ -131,7 +139,7 @@ impl Owner {
                 EncodableOwner {
                     id,
                     login,
-                    url: Some(url),
+                    url,
                     avatar,
                     name,
                     kind: String::from("team"),
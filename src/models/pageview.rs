@@ -0,0 +1,87 @@
+use chrono::NaiveDate;
+use diesel::dsl::{date, now};
+use diesel::prelude::*;
+use rand::{rngs::OsRng, Rng};
+use sha2::{Digest, Sha256};
+
+use crate::models::Crate;
+use crate::schema::{crate_pageview_visitors, crate_pageviews, pageview_salts};
+use crate::views::EncodableCratePageview;
+
+#[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id, date)]
+pub struct CratePageview {
+    pub crate_id: i32,
+    pub date: NaiveDate,
+    pub views: i32,
+}
+
+impl CratePageview {
+    /// Counts a view of the page of a crate, at most once per visitor and per day.
+    ///
+    /// `visitor` identifies who is viewing the page (e.g. their IP address and user
+    /// agent). It is never stored as is, only hashed with a random salt which changes
+    /// every day and is deleted by `delete_expired_visitors` afterwards.
+    pub fn record(conn: &PgConnection, crate_id: i32, visitor: &[u8]) -> QueryResult<()> {
+        conn.transaction(|| {
+            let salt = todays_salt(conn)?;
+            let mut hasher = Sha256::new();
+            hasher.update(&salt);
+            hasher.update(visitor);
+            let visitor = hasher.finalize();
+
+            let first_visit = diesel::insert_into(crate_pageview_visitors::table)
+                .values((
+                    crate_pageview_visitors::crate_id.eq(crate_id),
+                    crate_pageview_visitors::visitor.eq(visitor.as_slice()),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?
+                > 0;
+
+            if first_visit {
+                diesel::insert_into(crate_pageviews::table)
+                    .values(crate_pageviews::crate_id.eq(crate_id))
+                    .on_conflict((crate_pageviews::crate_id, crate_pageviews::date))
+                    .do_update()
+                    .set(crate_pageviews::views.eq(crate_pageviews::views + 1))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Deletes the salts and hashed visitors of the previous days, which are only
+    /// needed to count visitors once per day.
+    pub fn delete_expired_visitors(conn: &PgConnection) -> QueryResult<()> {
+        diesel::delete(
+            crate_pageview_visitors::table.filter(crate_pageview_visitors::date.lt(date(now))),
+        )
+        .execute(conn)?;
+        diesel::delete(pageview_salts::table.filter(pageview_salts::date.lt(date(now))))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn encodable(self) -> EncodableCratePageview {
+        EncodableCratePageview {
+            date: self.date.to_string(),
+            views: self.views,
+        }
+    }
+}
+
+/// Returns the salt of the current day, generating it if this is the first view today.
+fn todays_salt(conn: &PgConnection) -> QueryResult<Vec<u8>> {
+    let salt: [u8; 32] = OsRng.gen();
+    diesel::insert_into(pageview_salts::table)
+        .values(pageview_salts::salt.eq(&salt[..]))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    pageview_salts::table
+        .filter(pageview_salts::date.eq(date(now)))
+        .select(pageview_salts::salt)
+        .first(conn)
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{Crate, Rights};
+use crate::schema::crate_publish_policies;
+use crate::util::errors::{cargo_err, AppResult};
+use crate::views::EncodablePublishPolicy;
+
+/// Restrictions the owners of a crate put on how new versions may be published.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id)]
+#[table_name = "crate_publish_policies"]
+pub struct CratePublishPolicy {
+    pub crate_id: i32,
+    /// The API tokens new versions may be published with, or `None` to allow any
+    pub allowed_token_ids: Option<Vec<i32>>,
+    /// Whether members of owning teams are prevented from publishing
+    pub require_user_owner: bool,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[table_name = "crate_publish_policies"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewCratePublishPolicy {
+    pub crate_id: i32,
+    pub allowed_token_ids: Option<Vec<i32>>,
+    pub require_user_owner: bool,
+}
+
+impl NewCratePublishPolicy {
+    /// Stores the policy, replacing any previous policy of the crate.
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<CratePublishPolicy> {
+        use diesel::dsl::now;
+
+        diesel::insert_into(crate_publish_policies::table)
+            .values(self)
+            .on_conflict(crate_publish_policies::crate_id)
+            .do_update()
+            .set((self, crate_publish_policies::updated_at.eq(now)))
+            .get_result(conn)
+    }
+}
+
+impl CratePublishPolicy {
+    /// Returns the publish policy of a crate, if its owners configured one.
+    pub fn for_crate(conn: &PgConnection, krate: &Crate) -> QueryResult<Option<Self>> {
+        Self::belonging_to(krate).first(conn).optional()
+    }
+
+    /// Checks that a user with the given `rights` on the crate may publish a new
+    /// version, authenticated with the API token `api_token_id` (or a cookie
+    /// session if `None`).
+    pub fn check(&self, rights: Rights, api_token_id: Option<i32>) -> AppResult<()> {
+        if self.require_user_owner && rights < Rights::Full {
+            return Err(cargo_err(
+                "the publish policy of this crate only allows its user owners to publish \
+                 new versions, members of owning teams can't",
+            ));
+        }
+
+        if let Some(allowed_token_ids) = &self.allowed_token_ids {
+            let allowed = api_token_id.map_or(false, |id| allowed_token_ids.contains(&id));
+            if !allowed {
+                return Err(cargo_err(
+                    "the publish policy of this crate only allows publishing new versions \
+                     with specific API tokens, and this request wasn't authenticated with \
+                     one of them",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn encodable(self) -> EncodablePublishPolicy {
+        EncodablePublishPolicy {
+            allowed_token_ids: self.allowed_token_ids,
+            require_user_owner: self.require_user_owner,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allowed_token_ids: Option<Vec<i32>>, require_user_owner: bool) -> CratePublishPolicy {
+        CratePublishPolicy {
+            crate_id: 1,
+            allowed_token_ids,
+            require_user_owner,
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+        }
+    }
+
+    #[test]
+    fn default_policy_allows_everything() {
+        let policy = policy(None, false);
+        assert_ok!(policy.check(Rights::Publish, None));
+        assert_ok!(policy.check(Rights::Full, Some(1)));
+    }
+
+    #[test]
+    fn require_user_owner() {
+        let policy = policy(None, true);
+        assert_ok!(policy.check(Rights::Full, Some(1)));
+        assert_err!(policy.check(Rights::Publish, Some(1)));
+    }
+
+    #[test]
+    fn allowed_token_ids() {
+        let policy = policy(Some(vec![1, 2]), false);
+        assert_ok!(policy.check(Rights::Full, Some(2)));
+        assert_err!(policy.check(Rights::Full, Some(3)));
+        assert_err!(policy.check(Rights::Full, None));
+    }
+}
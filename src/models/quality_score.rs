@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+
+use crate::models::Crate;
+use crate::schema::crate_quality_scores;
+use crate::views::EncodableCrateQualityScore;
+
+/// The quality score of a crate, as last computed by the `update_quality_scores`
+/// background job.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id)]
+pub struct CrateQualityScore {
+    pub crate_id: i32,
+    /// The version of the formula this score was computed with
+    pub formula_version: i32,
+    pub docs: f64,
+    pub recent_release: f64,
+    pub tests: f64,
+    pub msrv: f64,
+    pub not_yanked: f64,
+    pub total: f64,
+    pub computed_at: NaiveDateTime,
+}
+
+impl CrateQualityScore {
+    pub fn encodable(self) -> EncodableCrateQualityScore {
+        EncodableCrateQualityScore {
+            formula_version: self.formula_version,
+            docs: self.docs,
+            recent_release: self.recent_release,
+            tests: self.tests,
+            msrv: self.msrv,
+            not_yanked: self.not_yanked,
+            total: self.total,
+            computed_at: self.computed_at,
+        }
+    }
+}
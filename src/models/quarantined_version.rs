@@ -0,0 +1,62 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::git;
+use crate::models::Version;
+use crate::render::PendingRendering;
+use crate::schema::quarantined_versions;
+use crate::spam::SpamScore;
+
+/// A new version which looks like spam, held for review by the admins of the instance
+///
+/// The version is stored like any other one, but it is hidden from the API, and it is only
+/// added to the index and its READMEs rendered once an admin approves it, see the `spam`
+/// module.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[primary_key(version_id)]
+pub struct QuarantinedVersion {
+    pub version_id: i32,
+    pub spam_score: i32,
+    pub reasons: Vec<String>,
+    pub index_entry: serde_json::Value,
+    pub renderings: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl QuarantinedVersion {
+    /// Holds a new version, with the index entry to add and the files to render once it is
+    /// approved.
+    pub fn insert(
+        conn: &PgConnection,
+        version_id: i32,
+        spam: &SpamScore,
+        index_entry: &git::Crate,
+        renderings: &[PendingRendering],
+    ) -> QueryResult<()> {
+        let index_entry = serde_json::to_value(index_entry)
+            .expect("index entries can always be serialized to JSON");
+        let renderings =
+            serde_json::to_value(renderings).expect("renderings can always be serialized to JSON");
+        diesel::insert_into(quarantined_versions::table)
+            .values((
+                quarantined_versions::version_id.eq(version_id),
+                quarantined_versions::spam_score.eq(spam.score as i32),
+                quarantined_versions::reasons.eq(&spam.reasons),
+                quarantined_versions::index_entry.eq(index_entry),
+                quarantined_versions::renderings.eq(renderings),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the index entry of the version.
+    pub fn index_entry(&self) -> serde_json::Result<git::Crate> {
+        serde_json::from_value(self.index_entry.clone())
+    }
+
+    /// Returns the READMEs and changelog of the version to render.
+    pub fn renderings(&self) -> serde_json::Result<Vec<PendingRendering>> {
+        serde_json::from_value(self.renderings.clone())
+    }
+}
@@ -0,0 +1,52 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::readme_rerenders;
+use crate::views::EncodableReadmeRerender;
+
+/// A re-rendering of all the READMEs requested by an admin, see `enqueue_readme_rerender`.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(User, foreign_key = "requested_by")]
+pub struct ReadmeRerender {
+    pub id: i32,
+    pub requested_by: i32,
+    /// The number of versions with a README when the re-rendering was requested
+    pub total: i32,
+    pub rendered: i32,
+    /// The versions whose README couldn't be read from their `.crate` file
+    pub skipped: i32,
+    /// The versions are re-rendered by order of id, up to this one so far
+    pub last_version_id: i32,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+impl ReadmeRerender {
+    /// Returns the re-rendering in progress, if there is one.
+    pub fn running(conn: &PgConnection) -> QueryResult<Option<Self>> {
+        readme_rerenders::table
+            .filter(readme_rerenders::finished_at.is_null())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the latest re-renderings, most recent first.
+    pub fn recent(conn: &PgConnection, limit: i64) -> QueryResult<Vec<Self>> {
+        readme_rerenders::table
+            .order(readme_rerenders::id.desc())
+            .limit(limit)
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableReadmeRerender {
+        EncodableReadmeRerender {
+            id: self.id,
+            total: self.total,
+            rendered: self.rendered,
+            skipped: self.skipped,
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+        }
+    }
+}
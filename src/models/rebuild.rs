@@ -0,0 +1,67 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{User, Version};
+use crate::schema::*;
+use crate::views::EncodableVersionRebuild;
+
+/// A report by a third party that rebuilt a version from its VCS revision, recording
+/// whether the checksum they obtained matches the published one.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[table_name = "version_rebuilds"]
+pub struct VersionRebuild {
+    pub id: i32,
+    pub version_id: i32,
+    pub user_id: i32,
+    pub checksum: String,
+    pub matches: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionRebuild {
+    /// Records the checksum `user_id` obtained by rebuilding `version`, replacing any
+    /// previous report of that user for the same version.
+    pub fn record(
+        conn: &PgConnection,
+        version: &Version,
+        user_id: i32,
+        checksum: &str,
+        matches: bool,
+    ) -> QueryResult<Self> {
+        use diesel::dsl::now;
+
+        diesel::insert_into(version_rebuilds::table)
+            .values((
+                version_rebuilds::version_id.eq(version.id),
+                version_rebuilds::user_id.eq(user_id),
+                version_rebuilds::checksum.eq(checksum),
+                version_rebuilds::matches.eq(matches),
+            ))
+            .on_conflict((version_rebuilds::version_id, version_rebuilds::user_id))
+            .do_update()
+            .set((
+                version_rebuilds::checksum.eq(checksum),
+                version_rebuilds::matches.eq(matches),
+                version_rebuilds::created_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+
+    pub fn by_version(conn: &PgConnection, version: &Version) -> QueryResult<Vec<(Self, User)>> {
+        Self::belonging_to(version)
+            .inner_join(users::table)
+            .order(version_rebuilds::created_at.desc())
+            .load(conn)
+    }
+
+    pub fn encodable(self, user: User) -> EncodableVersionRebuild {
+        EncodableVersionRebuild {
+            user: User::encodable_public(user),
+            checksum: self.checksum,
+            matches: self.matches,
+            created_at: self.created_at,
+        }
+    }
+}
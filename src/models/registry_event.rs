@@ -0,0 +1,136 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::models::{Owner, OwnerKind};
+use crate::schema::registry_events;
+use crate::views::{EncodableRegistryEvent, EncodableRegistryEventOwner};
+
+#[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression, Serialize, Deserialize)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryEventKind {
+    Publish = 0,
+    Yank = 1,
+    Unyank = 2,
+    OwnerAdd = 3,
+    OwnerRemove = 4,
+    /// A new version changed the license of its crate, see `Version::record_license_change`
+    LicenseChange = 5,
+}
+
+impl FromSql<Integer, Pg> for RegistryEventKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(RegistryEventKind::Publish),
+            1 => Ok(RegistryEventKind::Yank),
+            2 => Ok(RegistryEventKind::Unyank),
+            3 => Ok(RegistryEventKind::OwnerAdd),
+            4 => Ok(RegistryEventKind::OwnerRemove),
+            5 => Ok(RegistryEventKind::LicenseChange),
+            n => Err(format!("unknown registry event kind: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for RegistryEventKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// A change to a crate, recorded so that other instances can follow this one by
+/// replaying the events in order of their `id`.
+///
+/// Ids are allocated before the transaction recording the event commits, so they don't
+/// necessarily become visible in order, see the `registry_event` controller.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+pub struct RegistryEvent {
+    pub id: i64,
+    pub kind: RegistryEventKind,
+    pub crate_name: String,
+    pub version: Option<String>,
+    pub owner_kind: Option<i32>,
+    pub owner_login: Option<String>,
+    pub owner_github_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl RegistryEvent {
+    /// Records the publication, yanking or unyanking of a version, or the change of license
+    /// of a new version.
+    pub fn record_version(
+        conn: &PgConnection,
+        kind: RegistryEventKind,
+        crate_name: &str,
+        version: &str,
+    ) -> QueryResult<()> {
+        diesel::insert_into(registry_events::table)
+            .values((
+                registry_events::kind.eq(kind),
+                registry_events::crate_name.eq(crate_name),
+                registry_events::version.eq(version),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the addition or removal of an owner of a crate.
+    pub fn record_owner(
+        conn: &PgConnection,
+        kind: RegistryEventKind,
+        crate_name: &str,
+        owner: &Owner,
+    ) -> QueryResult<()> {
+        diesel::insert_into(registry_events::table)
+            .values((
+                registry_events::kind.eq(kind),
+                registry_events::crate_name.eq(crate_name),
+                registry_events::owner_kind.eq(owner.kind()),
+                registry_events::owner_login.eq(owner.login()),
+                registry_events::owner_github_id.eq(owner.github_id()),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the events recorded after the event `since`, oldest first.
+    pub fn since(conn: &PgConnection, since: i64, limit: i64) -> QueryResult<Vec<Self>> {
+        registry_events::table
+            .filter(registry_events::id.gt(since))
+            .order(registry_events::id.asc())
+            .limit(limit)
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableRegistryEvent {
+        let owner = match (self.owner_kind, self.owner_login, self.owner_github_id) {
+            (Some(kind), Some(login), Some(github_id)) => Some(EncodableRegistryEventOwner {
+                kind: if kind == OwnerKind::Team as i32 {
+                    String::from("team")
+                } else {
+                    String::from("user")
+                },
+                login,
+                github_id,
+            }),
+            _ => None,
+        };
+
+        EncodableRegistryEvent {
+            id: self.id,
+            kind: self.kind,
+            crate_name: self.crate_name,
+            version: self.version,
+            owner,
+            created_at: self.created_at,
+        }
+    }
+}
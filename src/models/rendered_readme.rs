@@ -0,0 +1,56 @@
+use diesel::dsl::now;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+
+use crate::schema::rendered_readmes;
+
+/// The cache of the HTML of rendered READMEs, see `render::readme_to_html_cached`
+pub struct RenderedReadme;
+
+impl RenderedReadme {
+    /// Returns the HTML rendered by the given version of the renderer for a key.
+    pub fn find(
+        conn: &PgConnection,
+        sha256: &[u8],
+        renderer_version: i32,
+    ) -> QueryResult<Option<String>> {
+        rendered_readmes::table
+            .find(sha256)
+            .filter(rendered_readmes::renderer_version.eq(renderer_version))
+            .select(rendered_readmes::html)
+            .first(conn)
+            .optional()
+    }
+
+    /// Stores the HTML rendered for a key, replacing the HTML of an older renderer.
+    pub fn store(
+        conn: &PgConnection,
+        sha256: &[u8],
+        renderer_version: i32,
+        html: &str,
+    ) -> QueryResult<()> {
+        diesel::insert_into(rendered_readmes::table)
+            .values((
+                rendered_readmes::sha256.eq(sha256),
+                rendered_readmes::renderer_version.eq(renderer_version),
+                rendered_readmes::html.eq(html),
+            ))
+            .on_conflict(rendered_readmes::sha256)
+            .do_update()
+            .set((
+                rendered_readmes::renderer_version.eq(excluded(rendered_readmes::renderer_version)),
+                rendered_readmes::html.eq(excluded(rendered_readmes::html)),
+                rendered_readmes::rendered_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Deletes the HTML rendered by older versions of the renderer, which is never served.
+    pub fn delete_outdated(conn: &PgConnection, renderer_version: i32) -> QueryResult<usize> {
+        diesel::delete(
+            rendered_readmes::table.filter(rendered_readmes::renderer_version.lt(renderer_version)),
+        )
+        .execute(conn)
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::krate::canon_crate_name;
+use crate::schema::reserved_crate_names;
+
+/// A name that can't be used by new crates
+///
+/// Names are matched in their canonical form, so reserving `compiler-rt` also reserves
+/// `Compiler_RT`. The admins of the instance reserve names with
+/// `PUT /api/v1/admin/reserved_crate_names/:name`.
+#[derive(Clone, Debug, Queryable)]
+pub struct ReservedCrateName {
+    pub name: String,
+    /// Shown to the users trying to publish a crate with this name
+    pub reason: Option<String>,
+    pub reserved_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ReservedCrateName {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        reserved_crate_names::table
+            .order(reserved_crate_names::name)
+            .load(conn)
+    }
+
+    /// Returns the reservation matching the canonical form of `name`, if any.
+    pub fn find(conn: &PgConnection, name: &str) -> QueryResult<Option<Self>> {
+        reserved_crate_names::table
+            .filter(canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)))
+            .first(conn)
+            .optional()
+    }
+
+    /// Reserves a name, or updates the reason of an existing reservation of the name.
+    ///
+    /// The database refuses to reserve the name of an existing crate.
+    pub fn reserve(
+        conn: &PgConnection,
+        name: &str,
+        reason: Option<&str>,
+        user_id: i32,
+    ) -> QueryResult<Self> {
+        conn.transaction(|| {
+            let updated = diesel::update(reserved_crate_names::table)
+                .filter(canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)))
+                .set((
+                    reserved_crate_names::reason.eq(reason),
+                    reserved_crate_names::reserved_by.eq(user_id),
+                ))
+                .get_result(conn)
+                .optional()?;
+            if let Some(reserved) = updated {
+                return Ok(reserved);
+            }
+
+            diesel::insert_into(reserved_crate_names::table)
+                .values((
+                    reserved_crate_names::name.eq(name),
+                    reserved_crate_names::reason.eq(reason),
+                    reserved_crate_names::reserved_by.eq(user_id),
+                ))
+                .get_result(conn)
+        })
+    }
+
+    /// Releases a name, so that a crate can be published with it.
+    pub fn delete(conn: &PgConnection, name: &str) -> QueryResult<usize> {
+        diesel::delete(
+            reserved_crate_names::table
+                .filter(canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name))),
+        )
+        .execute(conn)
+    }
+}
@@ -1,17 +1,16 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
 use diesel::prelude::*;
 
 use crate::app::App;
-use crate::github::{github_api, team_url};
-use crate::util::errors::{cargo_err, AppResult, NotFound};
-
-use oauth2::AccessToken;
+use crate::github::team_url;
+use crate::util::errors::{cargo_err, AppResult};
 
 use crate::models::{Crate, CrateOwner, Owner, OwnerKind, User};
-use crate::schema::{crate_owners, teams};
+use crate::schema::{crate_owners, team_memberships, teams, users};
 use crate::views::EncodableTeam;
 
-/// For now, just a Github Team. Can be upgraded to other teams
-/// later if desirable.
+/// A Github Team, or a group of another authentication provider.
 #[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
 pub struct Team {
     /// Unique table id
@@ -23,6 +22,7 @@ pub struct Team {
     pub login: String,
     /// The GitHub API works on team ID numbers. This can change, if a team
     /// is deleted and then recreated with the same name!!!
+    /// `0` for teams of other authentication providers.
     pub github_id: i32,
     /// Sugary goodness
     pub name: Option<String>,
@@ -31,6 +31,20 @@ pub struct Team {
     pub org_id: Option<i32>,
 }
 
+/// The result of the last check of whether a user belongs to a team
+///
+/// The checks are made by the authentication provider whenever a user acts on a crate owned by
+/// the team, so a membership which was `active` may have ended since `checked_at`.
+#[derive(Queryable, Associations, Debug, Clone)]
+#[belongs_to(Team)]
+#[belongs_to(User)]
+pub struct TeamMembership {
+    pub team_id: i32,
+    pub user_id: i32,
+    pub active: bool,
+    pub checked_at: NaiveDateTime,
+}
+
 #[derive(Insertable, AsChangeset, Debug)]
 #[table_name = "teams"]
 pub struct NewTeam<'a> {
@@ -74,9 +88,8 @@ impl<'a> NewTeam<'a> {
 impl Team {
     /// Tries to create the Team in the DB (assumes a `:` has already been found).
     ///
-    /// # Panics
-    ///
-    /// This function will panic if login contains less than 2 `:` characters.
+    /// Teams are managed by the authentication provider, and their login starts with its name,
+    /// e.g. `github:org:team`.
     pub fn create_or_update(
         app: &App,
         conn: &PgConnection,
@@ -84,121 +97,73 @@ impl Team {
         req_user: &User,
     ) -> AppResult<Self> {
         // must look like system:xxxxxxx
-        let mut chunks = login.split(':');
         // unwrap is okay, split on an empty string still has 1 chunk
-        match chunks.next().unwrap() {
-            // github:rust-lang:owners
-            "github" => {
-                // unwrap is documented above as part of the calling contract
-                let org = chunks.next().unwrap();
-                let team = chunks.next().ok_or_else(|| {
-                    cargo_err(
-                        "missing github team argument; \
-                         format is github:org:team",
-                    )
-                })?;
-                Team::create_or_update_github_team(
-                    app,
-                    conn,
-                    &login.to_lowercase(),
-                    org,
-                    team,
-                    req_user,
-                )
-            }
-            _ => Err(cargo_err(
-                "unknown organization handler, \
-                 only 'github:org:team' is supported",
-            )),
-        }
-    }
-
-    /// Tries to create or update a Github Team. Assumes `org` and `team` are
-    /// correctly parsed out of the full `name`. `name` is passed as a
-    /// convenience to avoid rebuilding it.
-    fn create_or_update_github_team(
-        app: &App,
-        conn: &PgConnection,
-        login: &str,
-        org_name: &str,
-        team_name: &str,
-        req_user: &User,
-    ) -> AppResult<Self> {
-        // GET orgs/:org/teams
-        // check that `team` is the `slug` in results, and grab its data
-
-        // "sanitization"
-        fn is_allowed_char(c: char) -> bool {
-            matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_')
-        }
-
-        if let Some(c) = org_name.chars().find(|c| !is_allowed_char(*c)) {
+        let system = login.split(':').next().unwrap();
+        if system != app.auth.name() {
             return Err(cargo_err(&format_args!(
-                "organization cannot contain special \
-                 characters like {}",
-                c
+                "unknown organization handler, \
+                 only '{}' is supported",
+                app.auth.team_login_format()
             )));
         }
-
-        #[derive(Deserialize)]
-        struct GithubOrganization {
-            id: i32, // unique GH id (needed for membership queries)
-        }
-
-        #[derive(Deserialize)]
-        struct GithubTeam {
-            id: i32,              // unique GH id (needed for membership queries)
-            name: Option<String>, // Pretty name
-            organization: GithubOrganization,
-        }
-
-        let url = format!("/orgs/{}/teams/{}", org_name, team_name);
-        let token = AccessToken::new(req_user.gh_access_token.clone());
-        let team = github_api::<GithubTeam>(app, &url, &token).map_err(|_| {
-            cargo_err(&format_args!(
-                "could not find the github team {}/{}",
-                org_name, team_name
-            ))
-        })?;
-
-        let org_id = team.organization.id;
-
-        if !team_with_gh_id_contains_user(app, org_id, team.id, req_user)? {
-            return Err(cargo_err("only members of a team can add it as an owner"));
-        }
-
-        #[derive(Deserialize)]
-        struct Org {
-            avatar_url: Option<String>,
-        }
-
-        let url = format!("/orgs/{}", org_name);
-        let org = github_api::<Org>(app, &url, &token)?;
-
-        NewTeam::new(
-            &login.to_lowercase(),
-            org_id,
-            team.id,
-            team.name,
-            org.avatar_url,
-        )
-        .create_or_update(conn)
-        .map_err(Into::into)
+        // Only members of a team can add it, which the provider checked
+        let team = app.auth.create_or_update_team(app, conn, login, req_user)?;
+        team.record_membership(conn, req_user, true);
+        Ok(team)
     }
 
-    /// Phones home to Github to ask if this User is a member of the given team.
+    /// Asks the authentication provider if this User is a member of the team.
     /// Note that we're assuming that the given user is the one interested in
     /// the answer. If this is not the case, then we could accidentally leak
     /// private membership information here.
-    pub fn contains_user(&self, app: &App, user: &User) -> AppResult<bool> {
-        match self.org_id {
-            Some(org_id) => team_with_gh_id_contains_user(app, org_id, self.github_id, user),
-            // This means we don't have an org_id on file for the `self` team. It much
-            // probably was deleted from github by the time we backfilled the database.
-            // Short-circuiting to false since a non-existent team cannot contain any
-            // user
-            None => Ok(false),
+    ///
+    /// The answer is cached in `team_memberships`, see `members`.
+    pub fn contains_user(&self, app: &App, conn: &PgConnection, user: &User) -> AppResult<bool> {
+        // Teams of another provider, which was used before this one, can't be checked
+        if !self.login.starts_with(&format!("{}:", app.auth.name())) {
+            return Ok(false);
         }
+        let active = app.auth.team_contains_user(app, conn, self, user)?;
+        self.record_membership(conn, user, active);
+        Ok(active)
+    }
+
+    /// Caches whether the user belongs to the team.
+    ///
+    /// Failing to do so only leaves the cache stale, so errors are logged instead of being
+    /// returned. The savepoint keeps the transaction of the caller usable, which matters when
+    /// the database is in read-only mode.
+    fn record_membership(&self, conn: &PgConnection, user: &User, active: bool) {
+        let result = conn.transaction(|| {
+            diesel::insert_into(team_memberships::table)
+                .values((
+                    team_memberships::team_id.eq(self.id),
+                    team_memberships::user_id.eq(user.id),
+                    team_memberships::active.eq(active),
+                ))
+                .on_conflict((team_memberships::team_id, team_memberships::user_id))
+                .do_update()
+                .set((
+                    team_memberships::active.eq(active),
+                    team_memberships::checked_at.eq(now),
+                ))
+                .execute(conn)
+        });
+        if let Err(e) = result {
+            warn!(
+                "could not record the membership of {} in {}: {}",
+                user.gh_login, self.login, e
+            );
+        }
+    }
+
+    /// Returns the users who belonged to the team when their membership was last checked.
+    pub fn members(&self, conn: &PgConnection) -> QueryResult<Vec<(TeamMembership, User)>> {
+        TeamMembership::belonging_to(self)
+            .inner_join(users::table)
+            .filter(team_memberships::active.eq(true))
+            .order(users::gh_login)
+            .load(conn)
     }
 
     pub fn owning(krate: &Crate, conn: &PgConnection) -> QueryResult<Vec<Owner>> {
@@ -229,37 +194,7 @@ impl Team {
             login,
             name,
             avatar,
-            url: Some(url),
+            url,
         }
     }
 }
-
-fn team_with_gh_id_contains_user(
-    app: &App,
-    github_org_id: i32,
-    github_team_id: i32,
-    user: &User,
-) -> AppResult<bool> {
-    // GET /organizations/:org_id/team/:team_id/memberships/:username
-    // check that "state": "active"
-
-    #[derive(Deserialize)]
-    struct Membership {
-        state: String,
-    }
-
-    let url = format!(
-        "/organizations/{}/team/{}/memberships/{}",
-        &github_org_id, &github_team_id, &user.gh_login
-    );
-    let token = AccessToken::new(user.gh_access_token.clone());
-    let membership = match github_api::<Membership>(app, &url, &token) {
-        // Officially how `false` is returned
-        Err(ref e) if e.is::<NotFound>() => return Ok(false),
-        x => x?,
-    };
-
-    // There is also `state: pending` for which we could possibly give
-    // some feedback, but it's not obvious how that should work.
-    Ok(membership.state == "active")
-}
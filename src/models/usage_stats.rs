@@ -0,0 +1,67 @@
+use chrono::NaiveDate;
+use diesel::dsl::{date, now, IntervalDsl};
+use diesel::prelude::*;
+
+use crate::models::{Category, Keyword};
+use crate::schema::{category_stats, keyword_stats};
+use crate::views::EncodableUsageStat;
+
+/// The usage of a keyword on a day, recorded by the `update_usage_stats` job
+#[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
+#[belongs_to(Keyword)]
+#[primary_key(keyword_id, date)]
+pub struct KeywordStat {
+    pub keyword_id: i32,
+    pub date: NaiveDate,
+    pub crates_cnt: i32,
+    pub downloads: i64,
+}
+
+impl KeywordStat {
+    /// Returns the stats of the last 90 days, oldest first.
+    pub fn trend(conn: &PgConnection, keyword: &Keyword) -> QueryResult<Vec<Self>> {
+        KeywordStat::belonging_to(keyword)
+            .filter(keyword_stats::date.gt(date(now - 90.days())))
+            .order(keyword_stats::date.asc())
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableUsageStat {
+        EncodableUsageStat {
+            date: self.date.to_string(),
+            crates_cnt: self.crates_cnt,
+            downloads: self.downloads,
+        }
+    }
+}
+
+/// The usage of a category on a day, recorded by the `update_usage_stats` job
+///
+/// Only the crates directly in the category are counted, not the ones of its subcategories.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
+#[belongs_to(Category)]
+#[primary_key(category_id, date)]
+pub struct CategoryStat {
+    pub category_id: i32,
+    pub date: NaiveDate,
+    pub crates_cnt: i32,
+    pub downloads: i64,
+}
+
+impl CategoryStat {
+    /// Returns the stats of the last 90 days, oldest first.
+    pub fn trend(conn: &PgConnection, category: &Category) -> QueryResult<Vec<Self>> {
+        CategoryStat::belonging_to(category)
+            .filter(category_stats::date.gt(date(now - 90.days())))
+            .order(category_stats::date.asc())
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableUsageStat {
+        EncodableUsageStat {
+            date: self.date.to_string(),
+            crates_cnt: self.crates_cnt,
+            downloads: self.downloads,
+        }
+    }
+}
@@ -20,6 +20,7 @@ pub struct User {
     pub gh_id: i32,
     pub account_lock_reason: Option<String>,
     pub account_lock_until: Option<NaiveDateTime>,
+    pub is_admin: bool,
 }
 
 /// Represents a new user record insertable to the `users` table
@@ -139,7 +140,7 @@ impl User {
     /// `Publish` as well, but this is a non-obvious invariant so we don't bother.
     /// Sweet free optimization if teams are proving burdensome to check.
     /// More than one team isn't really expected, though.
-    pub fn rights(&self, app: &App, owners: &[Owner]) -> AppResult<Rights> {
+    pub fn rights(&self, app: &App, conn: &PgConnection, owners: &[Owner]) -> AppResult<Rights> {
         let mut best = Rights::None;
         for owner in owners {
             match *owner {
@@ -149,7 +150,7 @@ impl User {
                     }
                 }
                 Owner::Team(ref team) => {
-                    if team.contains_user(app, self)? {
+                    if team.contains_user(app, conn, self)? {
                         best = Rights::Publish;
                     }
                 }
@@ -168,6 +169,17 @@ impl User {
             .optional()?)
     }
 
+    /// Returns the URL of the GitHub profile of the user, or `None` for the users who logged in
+    /// with another provider, or whose GitHub ID couldn't be found, since their login may not be
+    /// a GitHub one.
+    pub fn github_url(&self) -> Option<String> {
+        if self.gh_id > 0 {
+            Some(format!("https://github.com/{}", self.gh_login))
+        } else {
+            None
+        }
+    }
+
     /// Converts this `User` model into an `EncodablePrivateUser` for JSON serialization.
     pub fn encodable_private(
         self,
@@ -175,6 +187,7 @@ impl User {
         email_verified: bool,
         email_verification_sent: bool,
     ) -> EncodablePrivateUser {
+        let url = self.github_url();
         let User {
             id,
             name,
@@ -182,7 +195,6 @@ impl User {
             gh_avatar,
             ..
         } = self;
-        let url = format!("https://github.com/{}", gh_login);
 
         EncodablePrivateUser {
             id,
@@ -192,7 +204,7 @@ impl User {
             avatar: gh_avatar,
             login: gh_login,
             name,
-            url: Some(url),
+            url,
         }
     }
 
@@ -206,6 +218,7 @@ impl User {
 
     /// Converts this`User` model into an `EncodablePublicUser` for JSON serialization.
     pub fn encodable_public(self) -> EncodablePublicUser {
+        let url = self.github_url();
         let User {
             id,
             name,
@@ -213,13 +226,12 @@ impl User {
             gh_avatar,
             ..
         } = self;
-        let url = format!("https://github.com/{}", gh_login);
         EncodablePublicUser {
             id,
             avatar: gh_avatar,
             login: gh_login,
             name,
-            url: Some(url),
+            url,
         }
     }
 }
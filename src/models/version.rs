@@ -1,13 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 
+use crate::git;
 use crate::util::errors::{cargo_err, AppResult};
 
-use crate::models::{Crate, Dependency, User, VersionOwnerAction};
+use crate::models::{Crate, Dependency, User, VersionOwnerAction, VersionYankEvent};
 use crate::schema::*;
-use crate::views::{EncodableAuditAction, EncodableVersion, EncodableVersionLinks};
+use crate::uploaders::TarballVcsInfo;
+use crate::views::{
+    EncodableAuditAction, EncodableVersion, EncodableVersionLinks, EncodableVersionVcsInfo,
+    EncodableYankEvent,
+};
 
 // Queryable has a custom implementation below
 #[derive(Clone, Identifiable, Associations, Debug, Queryable, Deserialize, Serialize)]
@@ -24,6 +29,19 @@ pub struct Version {
     pub license: Option<String>,
     pub crate_size: Option<i32>,
     pub published_by: Option<i32>,
+    pub vcs_sha1: Option<String>,
+    pub vcs_dirty: Option<bool>,
+    pub checksum: Option<String>,
+    pub rust_version: Option<String>,
+    pub metadata_targets: Option<Vec<String>>,
+    /// Whether the license differs from the one of the version published before this one
+    pub license_changed: bool,
+    /// The license of the version published before this one, if it changed
+    pub previous_license: Option<String>,
+    /// The documentation of the features, by feature name
+    pub feature_docs: serde_json::Value,
+    /// The total size of the files of the `.crate` tarball, unknown for older versions
+    pub uncompressed_size: Option<i64>,
 }
 
 #[derive(Insertable, Debug)]
@@ -62,6 +80,7 @@ impl Version {
         crate_name: &str,
         published_by: Option<User>,
         audit_actions: Vec<(VersionOwnerAction, User)>,
+        yank_events: Vec<(VersionYankEvent, User)>,
     ) -> EncodableVersion {
         let Version {
             id,
@@ -73,6 +92,13 @@ impl Version {
             yanked,
             license,
             crate_size,
+            uncompressed_size,
+            vcs_sha1,
+            vcs_dirty,
+            metadata_targets,
+            license_changed,
+            previous_license,
+            feature_docs,
             ..
         } = self;
         let num = num.to_string();
@@ -94,6 +120,15 @@ impl Version {
                 authors: format!("/api/v1/crates/{}/{}/authors", crate_name, num),
             },
             crate_size,
+            uncompressed_size,
+            vcs_info: vcs_sha1.map(|sha1| EncodableVersionVcsInfo {
+                sha1,
+                dirty: vcs_dirty.unwrap_or(false),
+            }),
+            metadata_targets,
+            license_changed,
+            previous_license,
+            feature_docs,
             published_by: published_by.map(User::encodable_public),
             audit_actions: audit_actions
                 .into_iter()
@@ -103,9 +138,40 @@ impl Version {
                     time: audit_action.time,
                 })
                 .collect(),
+            yank_history: yank_events
+                .into_iter()
+                .map(|(event, user)| EncodableYankEvent {
+                    yanked: event.yanked,
+                    reason: event.reason,
+                    user: User::encodable_public(user),
+                    time: event.created_at,
+                })
+                .collect(),
         }
     }
 
+    /// Records a version which was published to another registry, from its index entry.
+    pub fn insert_from_index_entry(
+        conn: &PgConnection,
+        crate_id: i32,
+        entry: &git::Crate,
+    ) -> QueryResult<Self> {
+        let mut features = entry.features.clone();
+        features.extend(entry.features2.clone().unwrap_or_default());
+        let features = serde_json::to_value(&features)
+            .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+
+        diesel::insert_into(versions::table)
+            .values((
+                versions::crate_id.eq(crate_id),
+                versions::num.eq(&entry.vers),
+                versions::features.eq(features),
+                versions::yanked.eq(entry.yanked.unwrap_or(false)),
+                versions::checksum.eq(&entry.cksum),
+            ))
+            .get_result(conn)
+    }
+
     /// Returns (dependency, crate dependency name)
     pub fn dependencies(&self, conn: &PgConnection) -> QueryResult<Vec<(Dependency, String)>> {
         Dependency::belonging_to(self)
@@ -151,6 +217,107 @@ impl Version {
             .execute(conn)
     }
 
+    /// Records the total size of the files of the uploaded `.crate` file.
+    pub fn record_uncompressed_size(
+        &self,
+        conn: &PgConnection,
+        uncompressed_size: i64,
+    ) -> QueryResult<()> {
+        diesel::update(self)
+            .set(versions::uncompressed_size.eq(uncompressed_size))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the hex encoded SHA-256 checksum of the uploaded `.crate` file.
+    pub fn record_checksum(&self, conn: &PgConnection, checksum: &str) -> QueryResult<()> {
+        diesel::update(self)
+            .set(versions::checksum.eq(checksum))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the repository commit this version was packaged from, as found in
+    /// the `.cargo_vcs_info.json` file of its tarball.
+    pub fn record_vcs_info(
+        &self,
+        conn: &PgConnection,
+        vcs_info: &TarballVcsInfo,
+    ) -> QueryResult<()> {
+        diesel::update(self)
+            .set((
+                versions::vcs_sha1.eq(&vcs_info.sha1),
+                versions::vcs_dirty.eq(vcs_info.dirty),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the minimum supported Rust version declared in the manifest of this
+    /// version.
+    pub fn record_rust_version(&self, conn: &PgConnection, rust_version: &str) -> QueryResult<()> {
+        diesel::update(self)
+            .set(versions::rust_version.eq(rust_version))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the targets listed as platform hints in the manifest of this version.
+    pub fn record_metadata_targets(
+        &self,
+        conn: &PgConnection,
+        metadata_targets: &[String],
+    ) -> QueryResult<()> {
+        diesel::update(self)
+            .set(versions::metadata_targets.eq(metadata_targets))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the documentation of the features of this version, see `TarballInfo`. Only
+    /// the documentation of the features the version has is kept.
+    pub fn record_feature_docs(
+        &self,
+        conn: &PgConnection,
+        feature_docs: &BTreeMap<String, String>,
+    ) -> QueryResult<()> {
+        let feature_docs = feature_docs
+            .iter()
+            .filter(|(name, _)| self.features.get(name).is_some())
+            .map(|(name, doc)| (name.clone(), serde_json::Value::from(doc.as_str())))
+            .collect::<serde_json::Map<_, _>>();
+        diesel::update(self)
+            .set(versions::feature_docs.eq(serde_json::Value::Object(feature_docs)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Compares the license of this new version with the one of the version published before
+    /// it, and records the previous license if it changed. Returns whether it did.
+    ///
+    /// The first version of a crate has no license to compare with.
+    pub fn record_license_change(&self, conn: &PgConnection) -> QueryResult<bool> {
+        let previous_license = versions::table
+            .filter(versions::crate_id.eq(self.crate_id))
+            .filter(versions::id.ne(self.id))
+            .order((versions::created_at.desc(), versions::id.desc()))
+            .select(versions::license)
+            .first::<Option<String>>(conn)
+            .optional()?;
+        match previous_license {
+            Some(previous_license) if previous_license != self.license => {
+                diesel::update(self)
+                    .set((
+                        versions::license_changed.eq(true),
+                        versions::previous_license.eq(previous_license),
+                    ))
+                    .execute(conn)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     /// Gets the User who ran `cargo publish` for this version, if recorded.
     /// Not for use when you have a group of versions you need the publishers for.
     pub fn published_by(&self, conn: &PgConnection) -> Option<User> {
@@ -0,0 +1,54 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::Version;
+use crate::schema::version_changelogs;
+use crate::views::EncodableVersionChangelog;
+
+/// The rendered changelog that was shipped in the `.crate` tarball of a version.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[primary_key(version_id)]
+pub struct VersionChangelog {
+    pub version_id: i32,
+    pub file_name: String,
+    pub html: String,
+    /// The rendered section of the changelog whose heading mentions this
+    /// version, if one was found.
+    pub section_html: Option<String>,
+    pub rendered_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[table_name = "version_changelogs"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewVersionChangelog<'a> {
+    pub version_id: i32,
+    pub file_name: &'a str,
+    pub html: &'a str,
+    pub section_html: Option<&'a str>,
+}
+
+impl<'a> NewVersionChangelog<'a> {
+    /// Stores the rendered changelog, replacing any previous rendering.
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<VersionChangelog> {
+        use diesel::dsl::now;
+
+        diesel::insert_into(version_changelogs::table)
+            .values(self)
+            .on_conflict(version_changelogs::version_id)
+            .do_update()
+            .set((self, version_changelogs::rendered_at.eq(now)))
+            .get_result(conn)
+    }
+}
+
+impl VersionChangelog {
+    pub fn encodable(self) -> EncodableVersionChangelog {
+        EncodableVersionChangelog {
+            file_name: self.file_name,
+            html: self.html,
+            section_html: self.section_html,
+        }
+    }
+}
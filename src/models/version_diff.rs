@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::version_diffs;
+
+/// The differences between two versions of a crate, which are computed by the
+/// `compute_version_diff` background job the first time they are requested.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[primary_key(from_version_id, to_version_id)]
+pub struct VersionDiff {
+    pub from_version_id: i32,
+    pub to_version_id: i32,
+    /// The serialized `EncodableVersionDiff`, or `None` until it is computed
+    pub diff: Option<serde_json::Value>,
+    pub requested_at: NaiveDateTime,
+    pub computed_at: Option<NaiveDateTime>,
+}
+
+impl VersionDiff {
+    /// Returns the diff between two versions, if it was requested.
+    pub fn find(
+        conn: &PgConnection,
+        from_version_id: i32,
+        to_version_id: i32,
+    ) -> QueryResult<Option<VersionDiff>> {
+        version_diffs::table
+            .find((from_version_id, to_version_id))
+            .first(conn)
+            .optional()
+    }
+
+    /// Records that the diff between two versions was requested.
+    ///
+    /// Returns `true` if it was requested for the first time, in which case the job computing
+    /// it has to be enqueued by the caller.
+    pub fn request(
+        conn: &PgConnection,
+        from_version_id: i32,
+        to_version_id: i32,
+    ) -> QueryResult<bool> {
+        let inserted = diesel::insert_into(version_diffs::table)
+            .values((
+                version_diffs::from_version_id.eq(from_version_id),
+                version_diffs::to_version_id.eq(to_version_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// Stores the computed diff between two versions.
+    pub fn record(
+        conn: &PgConnection,
+        from_version_id: i32,
+        to_version_id: i32,
+        diff: serde_json::Value,
+    ) -> QueryResult<()> {
+        use diesel::dsl::now;
+
+        diesel::update(version_diffs::table.find((from_version_id, to_version_id)))
+            .set((
+                version_diffs::diff.eq(diff),
+                version_diffs::computed_at.eq(now.nullable()),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+}
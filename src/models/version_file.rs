@@ -0,0 +1,66 @@
+use diesel::prelude::*;
+use hex::ToHex;
+
+use crate::models::Version;
+use crate::schema::version_files;
+use crate::uploaders::TarballFile;
+use crate::views::EncodableVersionFile;
+
+/// Postgres only supports 65535 bind parameters per statement, so large
+/// crates have their file listing inserted in batches of this many rows.
+const INSERT_BATCH_SIZE: usize = 1000;
+
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[primary_key(version_id, path)]
+pub struct VersionFile {
+    pub version_id: i32,
+    pub path: String,
+    pub size: i64,
+    pub sha256: Vec<u8>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "version_files"]
+struct NewVersionFile<'a> {
+    version_id: i32,
+    path: &'a str,
+    size: i64,
+    sha256: &'a [u8],
+}
+
+impl VersionFile {
+    /// Records the listing of all regular files contained in the `.crate`
+    /// tarball of the given version.
+    pub fn insert_all(
+        conn: &PgConnection,
+        version_id: i32,
+        files: &[TarballFile],
+    ) -> QueryResult<()> {
+        for chunk in files.chunks(INSERT_BATCH_SIZE) {
+            let new_files = chunk
+                .iter()
+                .map(|file| NewVersionFile {
+                    version_id,
+                    path: &file.path,
+                    size: file.size as i64,
+                    sha256: &file.sha256,
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(version_files::table)
+                .values(&new_files)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    pub fn encodable(self) -> EncodableVersionFile {
+        EncodableVersionFile {
+            path: self.path,
+            size: self.size,
+            sha256: self.sha256.encode_hex(),
+        }
+    }
+}
@@ -6,21 +6,37 @@ use std::time::Duration;
 use crate::schema::{publish_limit_buckets, publish_rate_overrides};
 use crate::util::errors::{AppResult, TooManyRequests};
 
-#[derive(Debug, Clone, Copy)]
-pub struct PublishRateLimit {
-    pub rate: Duration,
-    pub burst: i32,
+/// The actions rate limited per user, each with its own bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitedAction {
+    /// Publishing a new crate
+    PublishNew = 0,
+    /// Previewing the rendering of a README
+    ReadmePreview = 1,
 }
 
-impl Default for PublishRateLimit {
-    fn default() -> Self {
-        Self {
-            rate: Duration::from_secs(60) * 10,
-            burst: 30,
+impl LimitedAction {
+    /// The rate limit of the action, unless configured otherwise
+    pub fn default_rate_limit(self) -> RateLimit {
+        match self {
+            LimitedAction::PublishNew => RateLimit {
+                rate: Duration::from_secs(60) * 10,
+                burst: 30,
+            },
+            LimitedAction::ReadmePreview => RateLimit {
+                rate: Duration::from_secs(6),
+                burst: 20,
+            },
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub rate: Duration,
+    pub burst: i32,
+}
+
 #[derive(Queryable, Insertable, Debug, PartialEq, Clone, Copy)]
 #[table_name = "publish_limit_buckets"]
 #[allow(dead_code)] // Most fields only read in tests
@@ -28,11 +44,17 @@ struct Bucket {
     user_id: i32,
     tokens: i32,
     last_refill: NaiveDateTime,
+    action: i32,
 }
 
-impl PublishRateLimit {
-    pub fn check_rate_limit(&self, uploader: i32, conn: &PgConnection) -> AppResult<()> {
-        let bucket = self.take_token(uploader, Utc::now().naive_utc(), conn)?;
+impl RateLimit {
+    pub fn check_rate_limit(
+        &self,
+        action: LimitedAction,
+        user: i32,
+        conn: &PgConnection,
+    ) -> AppResult<()> {
+        let bucket = self.take_token(action, user, Utc::now().naive_utc(), conn)?;
         if bucket.tokens >= 1 {
             Ok(())
         } else {
@@ -50,9 +72,12 @@ impl PublishRateLimit {
     /// have a token to take. Technically a "full" bucket would have
     /// `self.burst + 1` tokens in it, but that value would never be returned
     /// since we only refill buckets when trying to take a token from it.
+    ///
+    /// The overrides of the burst only apply to publishing new crates.
     fn take_token(
         &self,
-        uploader: i32,
+        limited_action: LimitedAction,
+        user: i32,
         now: NaiveDateTime,
         conn: &PgConnection,
     ) -> QueryResult<Bucket> {
@@ -68,12 +93,16 @@ impl PublishRateLimit {
         sql_function!(fn greatest<T>(x: T, y: T) -> T);
         sql_function!(fn least<T>(x: T, y: T) -> T);
 
-        let burst: i32 = publish_rate_overrides::table
-            .find(uploader)
-            .select(publish_rate_overrides::burst)
-            .first(conn)
-            .optional()?
-            .unwrap_or(self.burst);
+        let burst: i32 = if limited_action == LimitedAction::PublishNew {
+            publish_rate_overrides::table
+                .find(user)
+                .select(publish_rate_overrides::burst)
+                .first(conn)
+                .optional()?
+                .unwrap_or(self.burst)
+        } else {
+            self.burst
+        };
 
         // Interval division is poorly defined in general (what is 1 month / 30 days?)
         // However, for the intervals we're dealing with, it is always well
@@ -84,8 +113,13 @@ impl PublishRateLimit {
         );
 
         diesel::insert_into(publish_limit_buckets)
-            .values((user_id.eq(uploader), tokens.eq(burst), last_refill.eq(now)))
-            .on_conflict(user_id)
+            .values((
+                user_id.eq(user),
+                action.eq(limited_action as i32),
+                tokens.eq(burst),
+                last_refill.eq(now),
+            ))
+            .on_conflict((user_id, action))
             .do_update()
             .set((
                 tokens.eq(least(burst, greatest(0, tokens - 1) + tokens_to_add)),
@@ -111,27 +145,39 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
-        let bucket = rate.take_token(new_user(&conn, "user1")?, now, &conn)?;
+        let bucket = rate.take_token(
+            LimitedAction::PublishNew,
+            new_user(&conn, "user1")?,
+            now,
+            &conn,
+        )?;
         let expected = Bucket {
             user_id: bucket.user_id,
             tokens: 10,
             last_refill: now,
+            action: 0,
         };
         assert_eq!(expected, bucket);
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_millis(50),
             burst: 20,
         };
-        let bucket = rate.take_token(new_user(&conn, "user2")?, now, &conn)?;
+        let bucket = rate.take_token(
+            LimitedAction::PublishNew,
+            new_user(&conn, "user2")?,
+            now,
+            &conn,
+        )?;
         let expected = Bucket {
             user_id: bucket.user_id,
             tokens: 20,
             last_refill: now,
+            action: 0,
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -142,16 +188,17 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 5, now)?.user_id;
-        let bucket = rate.take_token(user_id, now, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, now, &conn)?;
         let expected = Bucket {
             user_id,
             tokens: 4,
             last_refill: now,
+            action: 0,
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -162,17 +209,18 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 5, now)?.user_id;
         let refill_time = now + chrono::Duration::seconds(2);
-        let bucket = rate.take_token(user_id, refill_time, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, refill_time, &conn)?;
         let expected = Bucket {
             user_id,
             tokens: 6,
             last_refill: refill_time,
+            action: 0,
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -187,17 +235,18 @@ mod tests {
             NaiveDateTime::parse_from_str("2019-03-19T21:11:24.620401", "%Y-%m-%dT%H:%M:%S%.f")
                 .unwrap();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_millis(100),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 5, now)?.user_id;
         let refill_time = now + chrono::Duration::milliseconds(300);
-        let bucket = rate.take_token(user_id, refill_time, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, refill_time, &conn)?;
         let expected = Bucket {
             user_id,
             tokens: 7,
             last_refill: refill_time,
+            action: 0,
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -208,17 +257,23 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_millis(100),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 5, now)?.user_id;
-        let bucket = rate.take_token(user_id, now + chrono::Duration::milliseconds(250), &conn)?;
+        let bucket = rate.take_token(
+            LimitedAction::PublishNew,
+            user_id,
+            now + chrono::Duration::milliseconds(250),
+            &conn,
+        )?;
         let expected_refill_time = now + chrono::Duration::milliseconds(200);
         let expected = Bucket {
             user_id,
             tokens: 6,
             last_refill: expected_refill_time,
+            action: 0,
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -229,20 +284,21 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 1, now)?.user_id;
-        let bucket = rate.take_token(user_id, now, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, now, &conn)?;
         let expected = Bucket {
             user_id,
             tokens: 0,
             last_refill: now,
+            action: 0,
         };
         assert_eq!(expected, bucket);
 
-        let bucket = rate.take_token(user_id, now, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, now, &conn)?;
         assert_eq!(expected, bucket);
         Ok(())
     }
@@ -252,17 +308,18 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 0, now)?.user_id;
         let refill_time = now + chrono::Duration::seconds(1);
-        let bucket = rate.take_token(user_id, refill_time, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, refill_time, &conn)?;
         let expected = Bucket {
             user_id,
             tokens: 1,
             last_refill: refill_time,
+            action: 0,
         };
         assert_eq!(expected, bucket);
 
@@ -274,17 +331,18 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
         let user_id = new_user_bucket(&conn, 8, now)?.user_id;
         let refill_time = now + chrono::Duration::seconds(4);
-        let bucket = rate.take_token(user_id, refill_time, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, refill_time, &conn)?;
         let expected = Bucket {
             user_id,
             tokens: 10,
             last_refill: refill_time,
+            action: 0,
         };
         assert_eq!(expected, bucket);
 
@@ -296,7 +354,7 @@ mod tests {
         let conn = pg_connection();
         let now = now();
 
-        let rate = PublishRateLimit {
+        let rate = RateLimit {
             rate: Duration::from_secs(1),
             burst: 10,
         };
@@ -310,14 +368,44 @@ mod tests {
             ))
             .execute(&conn)?;
 
-        let bucket = rate.take_token(user_id, now, &conn)?;
-        let other_bucket = rate.take_token(other_user_id, now, &conn)?;
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, now, &conn)?;
+        let other_bucket = rate.take_token(LimitedAction::PublishNew, other_user_id, now, &conn)?;
 
         assert_eq!(20, bucket.tokens);
         assert_eq!(10, other_bucket.tokens);
         Ok(())
     }
 
+    #[test]
+    fn actions_have_their_own_bucket() -> QueryResult<()> {
+        let conn = pg_connection();
+        let now = now();
+
+        let rate = RateLimit {
+            rate: Duration::from_secs(1),
+            burst: 10,
+        };
+        let user_id = new_user_bucket(&conn, 0, now)?.user_id;
+        diesel::insert_into(publish_rate_overrides::table)
+            .values((
+                publish_rate_overrides::user_id.eq(user_id),
+                publish_rate_overrides::burst.eq(20),
+            ))
+            .execute(&conn)?;
+
+        let bucket = rate.take_token(LimitedAction::ReadmePreview, user_id, now, &conn)?;
+        let expected = Bucket {
+            user_id,
+            tokens: 10,
+            last_refill: now,
+            action: LimitedAction::ReadmePreview as i32,
+        };
+        assert_eq!(expected, bucket);
+        let bucket = rate.take_token(LimitedAction::PublishNew, user_id, now, &conn)?;
+        assert_eq!(0, bucket.tokens);
+        Ok(())
+    }
+
     fn new_user(conn: &PgConnection, gh_login: &str) -> QueryResult<i32> {
         use crate::models::NewUser;
 
@@ -339,6 +427,7 @@ mod tests {
                 user_id: new_user(conn, "new_user")?,
                 tokens,
                 last_refill: now,
+                action: 0,
             })
             .get_result(conn)
     }
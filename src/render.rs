@@ -1,86 +1,217 @@
 //! Render README files to HTML.
 
-use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
-use comrak::nodes::{AstNode, NodeValue};
-use htmlescape::encode_minimal;
+use ammonia::{AttributeFilter, Builder, UrlRelative, UrlRelativeEvaluate};
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, NodeHtmlBlock, NodeValue};
+use comrak::{ComrakExtensionOptions, ComrakOptions, ComrakRenderOptions};
+use diesel::{PgConnection, QueryResult};
+use htmlescape::{decode_html, encode_attribute};
+use reqwest::{blocking::Client, header};
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::ops::Range;
 use std::path::Path;
-use swirl::PerformError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::Duration;
+use swirl::{EnqueueError, Job, PerformError};
 use url::Url;
 
 use crate::background_jobs::Environment;
 use crate::models::Version;
+use crate::views::EncodableReadmeHeading;
+
+pub mod audit;
+mod camo;
+mod emoji;
+mod forge;
+mod markup;
+mod math;
+mod rst;
+
+pub use self::camo::Camo;
+pub use self::forge::{Forge, ForgeKind};
+pub use self::markup::{renderer_for, Renderer};
+
+/// The maximum number of READMEs rendered at the same time by `readme_to_html_limited`,
+/// including those which timed out and are still being rendered
+const MAX_CONCURRENT_RENDERS: usize = 8;
+
+/// The number of READMEs being rendered by `readme_to_html_limited`
+static CONCURRENT_RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+/// The size of the chunks of HTML passed from comrak to ammonia
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of chunks buffered between comrak and ammonia
+const BUFFERED_CHUNKS: usize = 4;
+
+/// The number of images from which a run of badges is collapsed, see `collapse_badges`
+const MIN_COLLAPSED_BADGES: usize = 3;
+
+/// The settings of the renderer which come from the configuration
+#[derive(Clone, Debug, Default)]
+pub struct RenderSettings {
+    /// The proxy the images are loaded through, if any
+    pub camo: Option<Camo>,
+    /// The self-hosted forges whose repositories get relative links
+    pub forges: Vec<Forge>,
+    /// Whether the runs of badges are collapsed into a container, see `collapse_badges`
+    pub collapse_badges: bool,
+    /// Whether the LaTeX math is wrapped for the frontend to typeset, see the `math` module
+    pub math: bool,
+    /// The limits pathological READMEs are stopped by
+    pub limits: RenderLimits,
+}
+
+impl RenderSettings {
+    /// Reads the settings, see `Camo::from_settings`, `Forge::from_settings` and
+    /// `RenderLimits::from_settings`.
+    ///
+    /// - `COLLAPSE_README_BADGES`: Collapse the runs of badges of Markdown READMEs.
+    /// - `RENDER_README_MATH`: Typeset the `$...$` and `$$...$$` math of Markdown READMEs.
+    pub fn from_settings(settings: &mut crate::config::Settings) -> Self {
+        RenderSettings {
+            camo: Camo::from_settings(settings),
+            forges: Forge::from_settings(settings),
+            collapse_badges: settings.flag("COLLAPSE_README_BADGES"),
+            math: settings.flag("RENDER_README_MATH"),
+            limits: RenderLimits::from_settings(settings),
+        }
+    }
+}
+
+/// The limits of rendering, so that pathological READMEs can't hog a worker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderLimits {
+    /// The maximum size of a README, in bytes
+    pub max_size: usize,
+    /// The maximum wall-clock duration of the rendering of a README
+    pub timeout: Duration,
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        RenderLimits {
+            max_size: 1024 * 1024,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RenderLimits {
+    /// Reads the settings, falling back to the defaults.
+    ///
+    /// - `README_MAX_SIZE`: The maximum size of a README, in bytes. Defaults to 1 MiB.
+    /// - `README_RENDER_TIMEOUT`: The maximum duration of rendering, in seconds. Defaults to 10.
+    pub fn from_settings(settings: &mut crate::config::Settings) -> Self {
+        let default = Self::default();
+        RenderLimits {
+            max_size: settings
+                .parse("README_MAX_SIZE")
+                .unwrap_or(default.max_size),
+            timeout: settings
+                .parse("README_RENDER_TIMEOUT")
+                .map_or(default.timeout, Duration::from_secs),
+        }
+    }
+
+    /// Checks the size of a README, before publishing or rendering it.
+    pub fn check_size(&self, size: usize) -> Result<(), RenderError> {
+        if size > self.max_size {
+            return Err(RenderError::TooLarge {
+                size,
+                max_size: self.max_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A README which exceeds the `RenderLimits`
+#[derive(Debug, PartialEq, Eq)]
+pub enum RenderError {
+    /// The README is larger than `RenderLimits::max_size`
+    TooLarge { size: usize, max_size: usize },
+    /// Rendering took longer than `RenderLimits::timeout`
+    Timeout(Duration),
+    /// `MAX_CONCURRENT_RENDERS` READMEs are already being rendered
+    Busy,
+    /// The renderer panicked
+    Failed,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::TooLarge { size, max_size } => write!(
+                f,
+                "the README is {} bytes long, more than the maximum of {} bytes",
+                size, max_size
+            ),
+            RenderError::Timeout(timeout) => write!(
+                f,
+                "rendering the README took more than {} seconds",
+                timeout.as_secs()
+            ),
+            RenderError::Busy => write!(f, "too many READMEs are being rendered, try again later"),
+            RenderError::Failed => write!(f, "the README could not be rendered"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
 
 /// Context for markdown to HTML rendering.
-#[allow(missing_debug_implementations)]
+#[derive(Debug)]
 struct MarkdownRenderer<'a> {
-    html_sanitizer: Builder<'a>,
+    base_url: Option<&'a str>,
+    default_branch: Option<&'a str>,
+    readme_path: Option<&'a str>,
+    settings: Option<&'a RenderSettings>,
 }
 
 impl<'a> MarkdownRenderer<'a> {
     /// Creates a new renderer instance.
     ///
     /// Per `readme_to_html`, `base_url` is the base URL prepended to any
-    /// relative links in the input document.  See that function for more detail.
-    fn new(base_url: Option<&'a str>) -> MarkdownRenderer<'a> {
-        let allowed_classes = hashmap(&[(
-            "code",
-            hashset(&[
-                "language-bash",
-                "language-clike",
-                "language-glsl",
-                "language-go",
-                "language-ini",
-                "language-javascript",
-                "language-json",
-                "language-markup",
-                "language-protobuf",
-                "language-ruby",
-                "language-rust",
-                "language-scss",
-                "language-sql",
-                "language-toml",
-                "language-yaml",
-            ]),
-        )]);
-        let sanitize_url = UrlRelative::Custom(Box::new(SanitizeUrl::new(base_url)));
-
-        let mut html_sanitizer = Builder::default();
-        html_sanitizer
-            .add_tags(&["input"])
-            .link_rel(Some("nofollow noopener noreferrer"))
-            .add_tag_attributes("a", &["id", "target"])
-            .add_tag_attributes("input", &["checked", "disabled", "type"])
-            .allowed_classes(allowed_classes)
-            .url_relative(sanitize_url)
-            .id_prefix(Some("user-content-"));
-        MarkdownRenderer { html_sanitizer }
+    /// relative links in the input document, which point to `default_branch`
+    /// and are resolved against the directory of `readme_path`.
+    /// See that function for more detail.
+    fn new(
+        base_url: Option<&'a str>,
+        default_branch: Option<&'a str>,
+        readme_path: Option<&'a str>,
+    ) -> MarkdownRenderer<'a> {
+        MarkdownRenderer {
+            base_url,
+            default_branch,
+            readme_path,
+            settings: None,
+        }
+    }
+
+    /// Renders with the configured settings, see `readme_to_html`.
+    fn with_settings(mut self, settings: Option<&'a RenderSettings>) -> Self {
+        self.settings = settings;
+        self
     }
 
     /// Renders the given markdown to HTML using the current settings.
+    ///
+    /// The HTML written by comrak is sanitized by ammonia while it is written, on another
+    /// thread, instead of being buffered entirely first. Only `BUFFERED_CHUNKS` chunks of
+    /// `CHUNK_SIZE` bytes are kept between the two, which halves the peak memory usage for
+    /// large READMEs.
     fn to_html(&self, text: &str) -> String {
-        use comrak::{
-            format_html, parse_document, Arena, ComrakExtensionOptions, ComrakOptions,
-            ComrakRenderOptions,
-        };
-
-        let options = ComrakOptions {
-            render: ComrakRenderOptions {
-                unsafe_: true, // The output will be sanitized with `ammonia`
-                ..ComrakRenderOptions::default()
-            },
-            extension: ComrakExtensionOptions {
-                autolink: true,
-                strikethrough: true,
-                table: true,
-                tagfilter: true,
-                tasklist: true,
-                header_ids: Some("user-content-".to_string()),
-                ..ComrakExtensionOptions::default()
-            },
-            ..ComrakOptions::default()
-        };
+        use comrak::{format_html, parse_document, Arena};
 
+        let options = comrak_options();
         let arena = Arena::new();
         let root = parse_document(&arena, text, &options);
 
@@ -98,10 +229,370 @@ impl<'a> MarkdownRenderer<'a> {
             }
         });
 
-        let mut html = Vec::new();
-        format_html(root, &options, &mut html).unwrap();
-        let rendered = String::from_utf8(html).unwrap();
-        self.html_sanitizer.clean(&rendered).to_string()
+        if self.settings.map_or(false, |settings| settings.math) {
+            wrap_math(&arena, root);
+        }
+
+        // Convert the emoji shortcodes of the text, but not of the code, like GitHub.
+        iter_nodes(root, &|node| {
+            if let NodeValue::Text(ref mut text) = node.data.borrow_mut().value {
+                if let Cow::Owned(replaced) =
+                    emoji::replace_shortcodes(&String::from_utf8_lossy(text))
+                {
+                    *text = replaced.into_bytes();
+                }
+            }
+        });
+
+        // Remove the HTML comments, such as the ones left by README templates, instead of
+        // keeping the empty lines of their blocks.
+        iter_nodes(root, &|node| {
+            let is_comment = match &node.data.borrow().value {
+                NodeValue::HtmlBlock(block) => is_only_comments(&block.literal),
+                NodeValue::HtmlInline(html) => is_only_comments(html),
+                _ => false,
+            };
+            if is_comment {
+                node.detach();
+            }
+        });
+
+        if self
+            .settings
+            .map_or(false, |settings| settings.collapse_badges)
+        {
+            collapse_badges(&arena, root);
+        }
+
+        fill_markdown_image_alts(&arena, root);
+
+        let (sender, receiver) = mpsc::sync_channel(BUFFERED_CHUNKS);
+        let base_url = self.base_url.map(String::from);
+        let default_branch = self.default_branch.map(String::from);
+        let readme_path = self.readme_path.map(String::from);
+        let settings = self.settings.cloned();
+        let sanitizer = thread::spawn(move || {
+            html_sanitizer(
+                base_url.as_deref(),
+                default_branch.as_deref(),
+                readme_path.as_deref(),
+                settings.as_ref(),
+            )
+            .clean_from_reader(ChunkReader::new(receiver))
+            .map(|document| document.to_string())
+        });
+
+        // Writing only fails if the sanitizer stopped reading, whose error is reported below
+        let mut writer = ChunkWriter::new(sender);
+        if format_html(root, &options, &mut writer).is_ok() {
+            writer.finish();
+        }
+
+        sanitizer
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            .expect("Unable to read the rendered HTML")
+    }
+}
+
+/// Whether some HTML is only made of comments and whitespace.
+fn is_only_comments(html: &[u8]) -> bool {
+    let html = String::from_utf8_lossy(html);
+    let mut rest = html.trim_start();
+    if rest.is_empty() {
+        return false;
+    }
+    while !rest.is_empty() {
+        let comment = match rest.strip_prefix("<!--") {
+            Some(comment) => comment,
+            None => return false,
+        };
+        match comment.find("-->") {
+            Some(end) => rest = comment[end + 3..].trim_start(),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Returns the number of images of a paragraph only made of images, which may be links,
+/// such as the badges of the CI, the documentation or the license of a crate.
+fn badge_count<'a>(node: &'a AstNode<'a>) -> Option<usize> {
+    fn is_image<'a>(node: &'a AstNode<'a>) -> bool {
+        matches!(node.data.borrow().value, NodeValue::Image(_))
+    }
+
+    if !matches!(node.data.borrow().value, NodeValue::Paragraph) {
+        return None;
+    }
+    let mut count = 0;
+    for child in node.children() {
+        match &child.data.borrow().value {
+            NodeValue::Image(_) => count += 1,
+            NodeValue::Link(_)
+                if child.first_child().is_some() && child.children().all(is_image) =>
+            {
+                count += child.children().count()
+            }
+            NodeValue::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {}
+            NodeValue::SoftBreak | NodeValue::LineBreak => {}
+            _ => return None,
+        }
+    }
+    if count > 0 {
+        Some(count)
+    } else {
+        None
+    }
+}
+
+/// Merges the consecutive paragraphs of badges into one paragraph, wrapped in a
+/// `readme-badges` container which the frontend lays out in rows, as long as they have at
+/// least `MIN_COLLAPSED_BADGES` images.
+fn collapse_badges<'a>(arena: &'a comrak::Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let html_block = |html: &str| {
+        let block = NodeValue::HtmlBlock(NodeHtmlBlock {
+            block_type: 6,
+            literal: html.as_bytes().to_vec(),
+        });
+        &*arena.alloc(Node::new(RefCell::new(Ast::new(block))))
+    };
+
+    // The tree is changed once the runs are found, which would stop the iteration
+    let firsts = root
+        .descendants()
+        .filter(|node| {
+            badge_count(node).is_some()
+                && node
+                    .previous_sibling()
+                    .map_or(true, |previous| badge_count(previous).is_none())
+        })
+        .collect::<Vec<_>>();
+
+    for first in firsts {
+        let mut run = vec![first];
+        let mut count = badge_count(first).unwrap_or_default();
+        while let Some(next) = run[run.len() - 1].next_sibling() {
+            match badge_count(next) {
+                Some(next_count) => {
+                    count += next_count;
+                    run.push(next);
+                }
+                None => break,
+            }
+        }
+        if count < MIN_COLLAPSED_BADGES {
+            continue;
+        }
+
+        for paragraph in &run[1..] {
+            first.append(arena.alloc(Node::new(RefCell::new(Ast::new(NodeValue::SoftBreak)))));
+            for child in paragraph.children().collect::<Vec<_>>() {
+                first.append(child);
+            }
+            paragraph.detach();
+        }
+        first.insert_before(html_block("<div class=\"readme-badges\">\n"));
+        first.insert_after(html_block("</div>\n"));
+    }
+}
+
+/// Replaces the math of the text and of the `math` code blocks by the HTML the frontend
+/// typesets, see the `math` module.
+fn wrap_math<'a>(arena: &'a comrak::Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let html_inline = |html: String| {
+        let inline = NodeValue::HtmlInline(html.into_bytes());
+        &*arena.alloc(Node::new(RefCell::new(Ast::new(inline))))
+    };
+    let text = |text: &str| {
+        let text = NodeValue::Text(text.as_bytes().to_vec());
+        &*arena.alloc(Node::new(RefCell::new(Ast::new(text))))
+    };
+
+    // The tree is changed once the math is found, which would stop the iteration
+    for node in root.descendants().collect::<Vec<_>>() {
+        let block = match &node.data.borrow().value {
+            NodeValue::CodeBlock(ncb) if ncb.fenced && is_math_info(&ncb.info) => {
+                Some(math::block_html(&String::from_utf8_lossy(&ncb.literal)))
+            }
+            _ => None,
+        };
+        if let Some(html) = block {
+            node.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 0,
+                literal: html.into_bytes(),
+            });
+            continue;
+        }
+
+        let literal = match &node.data.borrow().value {
+            NodeValue::Text(literal) => String::from_utf8_lossy(literal).into_owned(),
+            _ => continue,
+        };
+        let segments = math::split(&literal);
+        if segments.is_empty() {
+            continue;
+        }
+        for segment in segments {
+            node.insert_before(match segment {
+                math::Segment::Text(literal) => text(literal),
+                math => html_inline(math.html()),
+            });
+        }
+        node.detach();
+    }
+}
+
+/// Whether the info string of a code block is the `math` of GitHub.
+fn is_math_info(info: &[u8]) -> bool {
+    String::from_utf8_lossy(info).trim() == "math"
+}
+
+/// Returns the comrak settings used to parse and render Markdown.
+fn comrak_options() -> ComrakOptions {
+    ComrakOptions {
+        render: ComrakRenderOptions {
+            unsafe_: true, // The output will be sanitized with `ammonia`
+            ..ComrakRenderOptions::default()
+        },
+        extension: ComrakExtensionOptions {
+            autolink: true,
+            footnotes: true,
+            strikethrough: true,
+            table: true,
+            tagfilter: true,
+            tasklist: true,
+            header_ids: Some("user-content-".to_string()),
+            ..ComrakExtensionOptions::default()
+        },
+        ..ComrakOptions::default()
+    }
+}
+
+/// Returns the ammonia settings used to sanitize the rendered HTML.
+fn html_sanitizer(
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> Builder<'static> {
+    // The classes of code blocks are filtered by `SanitizeAttributes`
+    let math_classes = hashset(&["math", "math-inline", "math-display"]);
+    let allowed_classes = hashmap(&[
+        ("a", hashset(&["footnote-backref"])),
+        ("div", hashset(&["math", "math-display", "readme-badges"])),
+        ("section", hashset(&["footnotes"])),
+        ("span", math_classes),
+        ("sup", hashset(&["footnote-ref"])),
+    ]);
+    let forges = settings.map_or(&[][..], |settings| &settings.forges);
+    let sanitize_url = || SanitizeUrl::new(base_url, default_branch, readme_path, forges);
+    let sanitize_attributes = SanitizeAttributes {
+        sanitize_url: sanitize_url(),
+        camo: settings.and_then(|settings| settings.camo.clone()),
+    };
+
+    let mut html_sanitizer = Builder::default();
+    html_sanitizer
+        .add_tags(&["details", "input", "section", "summary"])
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .add_tag_attributes("a", &["id", "target", "title"])
+        .add_tag_attributes("code", &["class"])
+        .add_tag_attributes("details", &["open"])
+        .add_tag_attributes("div", &["align"])
+        .add_tag_attributes("img", &["alt", "title"])
+        .add_tag_attributes("input", &["checked", "disabled", "type"])
+        // The footnotes, whose ids are prefixed like the ones of links
+        .add_tag_attributes("li", &["id"])
+        .add_tag_attributes("p", &["align"])
+        .add_tag_attributes("td", &["align", "width"])
+        .add_tag_attributes("th", &["align", "width"])
+        .allowed_classes(allowed_classes)
+        .url_relative(UrlRelative::Custom(Box::new(sanitize_url())))
+        .attribute_filter(sanitize_attributes)
+        .id_prefix(Some("user-content-"));
+    html_sanitizer
+}
+
+/// Sends what is written to a channel, in chunks of `CHUNK_SIZE` bytes.
+struct ChunkWriter {
+    sender: SyncSender<Vec<u8>>,
+    chunk: Vec<u8>,
+}
+
+impl ChunkWriter {
+    fn new(sender: SyncSender<Vec<u8>>) -> Self {
+        ChunkWriter {
+            sender,
+            chunk: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    /// Sends the last chunk. Dropping the writer without calling this discards it.
+    fn finish(mut self) {
+        let _ = self.send_chunk();
+    }
+
+    fn send_chunk(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        let chunk = mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_SIZE));
+        self.sender
+            .send(chunk)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the reader stopped"))
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(CHUNK_SIZE - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..len]);
+        if self.chunk.len() == CHUNK_SIZE {
+            self.send_chunk()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the chunks sent by a `ChunkWriter`, until it is dropped.
+struct ChunkReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChunkReader {
+    fn new(receiver: Receiver<Vec<u8>>) -> Self {
+        ChunkReader {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                // The writer was dropped, so everything was read
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
     }
 }
 
@@ -130,20 +621,39 @@ fn canon_base_url(mut base_url: String) -> String {
 
 /// Sanitize relative URLs in README files.
 struct SanitizeUrl {
-    base_url: Option<String>,
+    /// The URL of the repository and the kind of its forge, if it is known
+    base_url: Option<(String, ForgeKind)>,
+    /// The branch relative links point to, `HEAD` resolving to the default branch of the
+    /// repository on GitHub
+    branch: String,
+    /// The directory of the README in the repository, relative links being resolved against
+    /// it, which ends with a slash unless it is the root
+    directory: String,
 }
 
 impl SanitizeUrl {
-    fn new(base_url: Option<&str>) -> Self {
+    fn new(
+        base_url: Option<&str>,
+        default_branch: Option<&str>,
+        readme_path: Option<&str>,
+        forges: &[Forge],
+    ) -> Self {
         let base_url = base_url
             .and_then(|base_url| Url::parse(base_url).ok())
-            .and_then(|url| match url.host_str() {
-                Some("github.com") | Some("gitlab.com") | Some("bitbucket.org") => {
-                    Some(canon_base_url(url.into_string()))
-                }
-                _ => None,
+            .and_then(|url| {
+                let kind = Forge::kind_of(forges, url.host_str()?)?;
+                Some((canon_base_url(url.into_string()), kind))
             });
-        Self { base_url }
+        let branch = default_branch.unwrap_or("HEAD").to_string();
+        let directory = match readme_path.and_then(|path| path.rfind('/')) {
+            Some(end) => readme_path.unwrap_or_default()[..=end].to_string(),
+            None => String::new(),
+        };
+        Self {
+            base_url,
+            branch,
+            directory,
+        }
     }
 }
 
@@ -188,17 +698,36 @@ impl UrlRelativeEvaluate for SanitizeUrl {
             // Always allow fragment URLs.
             return Some(Cow::Borrowed(url));
         }
-        self.base_url.as_ref().map(|base_url| {
+        let MediaUrl {
+            is_media,
+            add_sanitize_query,
+        } = is_media_url(url);
+        self.rewrite(url, is_media, add_sanitize_query)
+    }
+}
+
+impl SanitizeUrl {
+    /// Points a relative URL to the repository, in the "raw" view if `raw` is set.
+    fn rewrite<'a>(
+        &self,
+        url: &'a str,
+        raw: bool,
+        add_sanitize_query: bool,
+    ) -> Option<Cow<'a, str>> {
+        self.base_url.as_ref().map(|(base_url, kind)| {
             let mut new_url = base_url.clone();
-            // Assumes GitHub’s URL scheme. GitHub renders text and markdown
-            // better in the "blob" view, but images need to be served raw.
-            let MediaUrl {
-                is_media,
-                add_sanitize_query,
-            } = is_media_url(url);
-            new_url += if is_media { "raw/HEAD" } else { "blob/HEAD" };
+            // Forges render text and markdown better in the "blob" view,
+            // but images need to be served raw.
+            new_url += if raw {
+                kind.raw_path()
+            } else {
+                kind.blob_path()
+            };
+            new_url += &self.branch;
+            // Absolute paths are relative to the root of the repository, like on GitHub
             if !url.starts_with('/') {
                 new_url.push('/');
+                new_url += &self.directory;
             }
             new_url += url;
             if add_sanitize_query {
@@ -212,79 +741,1132 @@ impl UrlRelativeEvaluate for SanitizeUrl {
     }
 }
 
-/// Renders Markdown text to sanitized HTML with a given `base_url`.
-/// See `readme_to_html` for the interpretation of `base_url`.
-fn markdown_to_html(text: &str, base_url: Option<&str>) -> String {
-    let renderer = MarkdownRenderer::new(base_url);
+/// Rewrites the attributes which ammonia can't sanitize on its own:
+///
+/// - The relative sources of images point to the "raw" view of the repository, whatever
+///   their extension, since badges and screenshots are often served without one, and the
+///   images are loaded through `camo` if it is set. Ammonia filters attributes before calling
+///   `SanitizeUrl::evaluate`, so the rewritten sources are absolute and left alone by it.
+/// - Only the `language-*` classes of code blocks are kept, see `is_language_class`, so that
+///   the frontend can highlight them.
+/// - The `align` and `width` attributes of blocks and cells are only kept with a valid value,
+///   see `is_alignment` and `is_length`.
+struct SanitizeAttributes {
+    sanitize_url: SanitizeUrl,
+    camo: Option<Camo>,
+}
+
+impl AttributeFilter for SanitizeAttributes {
+    fn filter<'a>(&self, element: &str, attribute: &str, value: &'a str) -> Option<Cow<'a, str>> {
+        match (element, attribute) {
+            ("img", "src") => Some(self.image_source(value)),
+            ("code", "class") => {
+                let classes = value
+                    .split_whitespace()
+                    .filter(|class| is_language_class(class))
+                    .collect::<Vec<_>>();
+                if classes.is_empty() {
+                    None
+                } else {
+                    Some(Cow::Owned(classes.join(" ")))
+                }
+            }
+            ("div", "align") | ("p", "align") | ("td", "align") | ("th", "align") => {
+                let value = value.trim().to_ascii_lowercase();
+                if is_alignment(&value) {
+                    Some(Cow::Owned(value))
+                } else {
+                    None
+                }
+            }
+            ("td", "width") | ("th", "width") => {
+                let value = value.trim();
+                if is_length(value) {
+                    Some(Cow::Borrowed(value))
+                } else {
+                    None
+                }
+            }
+            _ => Some(Cow::Borrowed(value)),
+        }
+    }
+}
+
+impl SanitizeAttributes {
+    fn image_source<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let is_relative = Url::parse(value) == Err(url::ParseError::RelativeUrlWithoutBase);
+        let source = if is_relative && !value.starts_with("//") && !value.starts_with('#') {
+            let add_sanitize_query = is_media_url(value).add_sanitize_query;
+            self.sanitize_url
+                .rewrite(value, true, add_sanitize_query)
+                .unwrap_or(Cow::Borrowed(value))
+        } else {
+            Cow::Borrowed(value)
+        };
+        match &self.camo {
+            Some(camo) => Cow::Owned(camo.proxy_url(&source)),
+            None => source,
+        }
+    }
+}
+
+/// Gives the images without an alternative text the name of their file, such as `logo.png`,
+/// so that screen readers have something to announce, see `fill_image_alts` for the images of
+/// the HTML of the README.
+fn fill_markdown_image_alts<'a>(arena: &'a comrak::Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    for node in root.descendants().collect::<Vec<_>>() {
+        let mut value = node.data.borrow_mut();
+        let url = match &mut value.value {
+            NodeValue::Image(link) => String::from_utf8_lossy(&link.url).into_owned(),
+            NodeValue::HtmlBlock(block) => {
+                block.literal = fill_image_alts(&String::from_utf8_lossy(&block.literal)).into();
+                continue;
+            }
+            NodeValue::HtmlInline(html) => {
+                *html = fill_image_alts(&String::from_utf8_lossy(html)).into();
+                continue;
+            }
+            _ => continue,
+        };
+        drop(value);
+
+        let has_alt = node
+            .descendants()
+            .skip(1)
+            .any(|child| match &child.data.borrow().value {
+                NodeValue::Text(text) | NodeValue::Code(text) => {
+                    !text.iter().all(u8::is_ascii_whitespace)
+                }
+                _ => false,
+            });
+        if has_alt {
+            continue;
+        }
+        for child in node.children().collect::<Vec<_>>() {
+            child.detach();
+        }
+        let name = image_file_name(&url).unwrap_or_else(|| "image".to_string());
+        let text = NodeValue::Text(name.into_bytes());
+        node.append(arena.alloc(Node::new(RefCell::new(Ast::new(text)))));
+    }
+}
+
+/// Gives the `img` tags of some HTML without an alternative text the name of their file, see
+/// `fill_markdown_image_alts`.
+///
+/// The HTML is written by the author of the README and sanitized afterwards, so the tags which
+/// can't be parsed here are left alone.
+fn fill_image_alts(html: &str) -> String {
+    if !html.contains("<img") {
+        return html.to_string();
+    }
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let end = tag_end(tag);
+        if tag.starts_with("<img ") || tag.starts_with("<img>") {
+            output.push_str(&fill_image_alt(&tag[..end]));
+        } else {
+            output.push_str(&tag[..end]);
+        }
+        rest = &tag[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Returns the length of the tag `html` starts with, up to its unquoted `>`.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('>', None) => return i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// Fills the `alt` attribute of an `img` tag, if it's missing or empty, see `fill_image_alts`.
+fn fill_image_alt(tag: &str) -> Cow<'_, str> {
+    let attributes = tag_attributes(tag);
+    let value = |name: &str| {
+        attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, range)| range.clone())
+    };
+    let alt = value("alt");
+    if alt
+        .as_ref()
+        .map_or(false, |alt| !tag[alt.clone()].trim().is_empty())
+    {
+        return Cow::Borrowed(tag);
+    }
+
+    let source = value("src").map(|src| decode_html(&tag[src]).unwrap_or_default());
+    let name = source
+        .as_deref()
+        .and_then(image_file_name)
+        .unwrap_or_else(|| "image".to_string());
+    let name = encode_attribute(&name);
+    let tag = match alt {
+        // The value of `alt` is quoted
+        Some(alt) if tag[..alt.start].ends_with(|c| c == '"' || c == '\'') => {
+            format!("{}{}{}", &tag[..alt.start], name, &tag[alt.end..])
+        }
+        Some(alt) => format!("{}=\"{}\"{}", &tag[..alt.start], name, &tag[alt.end..]),
+        None => {
+            let end = tag.rfind('>').unwrap_or_else(|| tag.len());
+            let end = tag[..end].trim_end_matches('/').trim_end().len();
+            format!("{} alt=\"{}\"{}", &tag[..end], name, &tag[end..])
+        }
+    };
+    Cow::Owned(tag)
+}
+
+/// Returns the names of the attributes of a tag, with the range of their value in the tag.
+fn tag_attributes(tag: &str) -> Vec<(&str, Range<usize>)> {
+    let is_end = |c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/';
+    let mut attributes = Vec::new();
+    // Skip the name of the tag
+    let mut position = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    loop {
+        let rest = &tag[position..];
+        let start = position + (rest.len() - rest.trim_start().len());
+        let rest = &tag[start..];
+        if rest.is_empty() || rest.starts_with('>') || rest.starts_with('/') {
+            return attributes;
+        }
+        let name_end = start + rest.find(is_end).unwrap_or(rest.len());
+        if name_end == start {
+            return attributes;
+        }
+        let name = &tag[start..name_end];
+        let quote = tag[name_end..]
+            .strip_prefix('=')
+            .and_then(|value| value.chars().next());
+        if let Some(quote @ '"') | Some(quote @ '\'') = quote {
+            let value_start = name_end + 2;
+            let value_end = tag[value_start..]
+                .find(quote)
+                .map_or(tag.len(), |end| value_start + end);
+            attributes.push((name, value_start..value_end));
+            position = (value_end + 1).min(tag.len());
+        } else if tag[name_end..].starts_with('=') {
+            let value_start = name_end + 1;
+            let value_end = tag[value_start..]
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .map_or(tag.len(), |end| value_start + end);
+            attributes.push((name, value_start..value_end));
+            position = value_end;
+        } else {
+            attributes.push((name, name_end..name_end));
+            position = name_end;
+        }
+    }
+}
+
+/// Returns the name of the file of an image, such as `logo.png`.
+fn image_file_name(source: &str) -> Option<String> {
+    let path = source.split(|c| c == '?' || c == '#').next()?;
+    path.rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .filter(|segment| !segment.ends_with(':'))
+        .map(String::from)
+}
+
+/// Whether an `align` attribute has one of the values of HTML, in lowercase.
+fn is_alignment(value: &str) -> bool {
+    matches!(value, "left" | "center" | "right" | "justify")
+}
+
+/// Whether a `width` attribute is a number of pixels or a percentage, such as `200` or `50%`.
+fn is_length(value: &str) -> bool {
+    let digits = value.strip_suffix('%').unwrap_or(value);
+    !digits.is_empty() && digits.len() <= 5 && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether a class names the language of a code block, such as `language-rust` or
+/// `language-c++`, which the frontend uses to highlight it.
+fn is_language_class(class: &str) -> bool {
+    class.strip_prefix("language-").map_or(false, |language| {
+        !language.is_empty()
+            && language.len() <= 32
+            && language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+#-_".contains(c))
+    })
+}
+
+/// Renders Markdown text to sanitized HTML with a given `base_url`, `default_branch`,
+/// `readme_path` and `settings`. See `readme_to_html` for their interpretation.
+fn markdown_to_html(
+    text: &str,
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> String {
+    let renderer =
+        MarkdownRenderer::new(base_url, default_branch, readme_path).with_settings(settings);
     renderer.to_html(text)
 }
 
-/// Any readme with a filename ending in one of these extensions will be rendered as Markdown.
-/// Note we also render a readme as Markdown if _no_ extension is on the filename.
-static MARKDOWN_EXTENSIONS: [&str; 7] = [
-    ".md",
-    ".markdown",
-    ".mdown",
-    ".mdwn",
-    ".mkd",
-    ".mkdn",
-    ".mkdown",
-];
-
-/// Renders a readme to sanitized HTML.  An appropriate rendering method is chosen depending
-/// on the extension of the supplied `filename`.
+/// Renders reStructuredText to sanitized HTML with a given `base_url`, `default_branch`,
+/// `readme_path` and `settings`. See `readme_to_html` for their interpretation.
+fn rst_to_html(
+    text: &str,
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> String {
+    html_sanitizer(base_url, default_branch, readme_path, settings)
+        .clean(&fill_image_alts(&rst::render(text).0))
+        .to_string()
+}
+
+/// Renders a readme to sanitized HTML.  An appropriate `Renderer` is chosen depending
+/// on the extension of the supplied `filename`, see `renderer_for`.
 ///
 /// The returned text will not contain any harmful HTML tag or attribute (such as iframe,
 /// onclick, onmouseover, etc.).
 ///
 /// The `base_url` parameter will be used as the base for any relative links found in the
-/// Markdown, as long as its host part is github.com, gitlab.com, or bitbucket.org.  The
+/// Markdown, as long as its host part is github.com, gitlab.com, bitbucket.org, or one of the
+/// forges of `settings`, whose URL scheme the relative links follow.  The
 /// supplied URL will be used as a directory base whether or not the relative link is
 /// prefixed with '/'.  If `None` is passed, relative links will be omitted.
 ///
+/// Relative links point to the files of `default_branch` in the repository, or to `HEAD`
+/// if the branch is unknown, which only GitHub resolves to the default branch.
+///
+/// Relative links are resolved against the directory of `readme_path`, the path of the readme
+/// in the repository, such as `crates/foo/README.md` for a member of a workspace, while the
+/// links prefixed with '/' are resolved against the root of the repository.
+///
+/// If the `camo` of `settings` is set, all the images are loaded through that proxy instead of
+/// their host.
+///
 /// # Examples
 ///
-/// ```
-/// use render::render_to_html;
+/// ```
+/// use render::render_to_html;
+///
+/// let text = "[Rust](https://rust-lang.org/) is an awesome *systems programming* language!";
+/// let rendered = readme_to_html(text, "README.md", None, None, None, None)?;
+/// ```
+pub fn readme_to_html(
+    text: &str,
+    filename: &str,
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> String {
+    renderer_for(filename).to_html(text, base_url, default_branch, readme_path, settings)
+}
+
+/// Renders a readme like `readme_to_html`, unless it exceeds the `limits` of `settings`.
+///
+/// The readme is rendered on another thread, which is left to finish on its own if it takes
+/// longer than the timeout, so that the caller is free to go on. At most
+/// `MAX_CONCURRENT_RENDERS` of these threads run at the same time, readmes rendered beyond
+/// that are rejected rather than piling up threads.
+pub fn readme_to_html_limited(
+    text: &str,
+    filename: &str,
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> Result<String, RenderError> {
+    let limits = settings.map(|settings| settings.limits).unwrap_or_default();
+    limits.check_size(text.len())?;
+
+    let text = text.to_string();
+    let filename = filename.to_string();
+    let base_url = base_url.map(String::from);
+    let default_branch = default_branch.map(String::from);
+    let readme_path = readme_path.map(String::from);
+    let settings = settings.cloned();
+
+    /// Releases the slot of a rendering thread, even if the renderer panics
+    struct RenderSlot;
+
+    impl Drop for RenderSlot {
+        fn drop(&mut self) {
+            CONCURRENT_RENDERS.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    if CONCURRENT_RENDERS.fetch_add(1, Ordering::SeqCst) >= MAX_CONCURRENT_RENDERS {
+        CONCURRENT_RENDERS.fetch_sub(1, Ordering::SeqCst);
+        return Err(RenderError::Busy);
+    }
+    let slot = RenderSlot;
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _slot = slot;
+        let html = readme_to_html(
+            &text,
+            &filename,
+            base_url.as_deref(),
+            default_branch.as_deref(),
+            readme_path.as_deref(),
+            settings.as_ref(),
+        );
+        // The receiver is gone if rendering timed out
+        let _ = sender.send(html);
+    });
+
+    match receiver.recv_timeout(limits.timeout) {
+        Ok(html) => Ok(html),
+        Err(RecvTimeoutError::Timeout) => Err(RenderError::Timeout(limits.timeout)),
+        Err(RecvTimeoutError::Disconnected) => Err(RenderError::Failed),
+    }
+}
+
+/// The version of the renderer, which is part of the keys of the cached HTML of READMEs.
+///
+/// Bump it whenever the HTML rendered for a README changes, so that the cached HTML isn't
+/// reused by the jobs and the `render-readmes` backfill.
+pub const RENDERER_VERSION: i32 = 7;
+
+/// Renders a readme like `readme_to_html`, reusing the HTML of an identical readme rendered
+/// with the same settings by the same version of the renderer, see `RenderedReadme`.
+///
+/// The cache is only an optimization, so its errors are logged and the readme is rendered.
+/// The savepoint keeps the transaction of the job usable.
+///
+/// Readmes exceeding the `limits` of `settings` aren't rendered, see `readme_to_html_limited`.
+pub fn readme_to_html_cached(
+    conn: &PgConnection,
+    text: &str,
+    filename: &str,
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> Result<String, RenderError> {
+    use crate::models::RenderedReadme;
+    use diesel::Connection;
+
+    let limits = settings.map(|settings| settings.limits).unwrap_or_default();
+    limits.check_size(text.len())?;
+
+    let key = render_cache_key(
+        text,
+        filename,
+        base_url,
+        default_branch,
+        readme_path,
+        settings,
+    );
+    match RenderedReadme::find(conn, &key, RENDERER_VERSION) {
+        Ok(Some(html)) => return Ok(html),
+        Ok(None) => {}
+        Err(e) => warn!("could not read a cached readme: {}", e),
+    }
+
+    let html = readme_to_html_limited(
+        text,
+        filename,
+        base_url,
+        default_branch,
+        readme_path,
+        settings,
+    )?;
+    let stored = conn.transaction(|| RenderedReadme::store(conn, &key, RENDERER_VERSION, &html));
+    if let Err(e) = stored {
+        warn!("could not cache a rendered readme: {}", e);
+    }
+    Ok(html)
+}
+
+/// Returns the SHA-256 of the version of the renderer and of everything the HTML of a readme
+/// depends on.
+fn render_cache_key(
+    text: &str,
+    filename: &str,
+    base_url: Option<&str>,
+    default_branch: Option<&str>,
+    readme_path: Option<&str>,
+    settings: Option<&RenderSettings>,
+) -> Vec<u8> {
+    use sha2::{Digest, Sha256};
+
+    let camo = settings.and_then(|settings| settings.camo.as_ref());
+    // Only the forge of the repository changes the relative links
+    let forges = settings.map_or(&[][..], |settings| &settings.forges);
+    let forge = base_url
+        .and_then(|base_url| Url::parse(base_url).ok())
+        .and_then(|url| Forge::kind_of(forges, url.host_str()?))
+        .map(|kind| format!("{:?}", kind));
+    let inputs = [
+        Some(filename),
+        base_url,
+        default_branch,
+        readme_path,
+        camo.map(|camo| &*camo.url),
+        camo.map(|camo| &*camo.key),
+        forge.as_deref(),
+        settings
+            .filter(|settings| settings.collapse_badges)
+            .map(|_| "collapse_badges"),
+        settings.filter(|settings| settings.math).map(|_| "math"),
+        Some(text),
+    ];
+    let mut hasher = Sha256::new();
+    hasher.update(&RENDERER_VERSION.to_be_bytes());
+    for input in &inputs {
+        // The lengths keep the boundaries of the inputs unambiguous
+        match input {
+            Some(input) => {
+                hasher.update(&(input.len() as u64 + 1).to_be_bytes());
+                hasher.update(input.as_bytes());
+            }
+            None => hasher.update(&0u64.to_be_bytes()),
+        }
+    }
+    hasher.finalize().to_vec()
+}
+
+/// A heading of a README, see `readme_headings`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadmeHeading {
+    pub level: u8,
+    pub text: String,
+    /// The fragment linking to the heading in the rendered README, its `id` being prefixed
+    /// with `user-content-`
+    pub anchor: String,
+}
+
+impl ReadmeHeading {
+    /// Nests the headings of a README under the previous heading of a lower level.
+    pub fn tree(headings: Vec<ReadmeHeading>) -> Vec<EncodableReadmeHeading> {
+        fn children(
+            headings: &mut std::iter::Peekable<std::vec::IntoIter<ReadmeHeading>>,
+            level: u8,
+        ) -> Vec<EncodableReadmeHeading> {
+            let mut nodes = Vec::new();
+            while headings
+                .peek()
+                .map_or(false, |heading| heading.level > level)
+            {
+                let heading = headings.next().unwrap();
+                nodes.push(EncodableReadmeHeading {
+                    children: children(headings, heading.level),
+                    level: heading.level,
+                    text: heading.text,
+                    anchor: heading.anchor,
+                });
+            }
+            nodes
+        }
+
+        children(&mut headings.into_iter().peekable(), 0)
+    }
+}
+
+/// Generates the anchors of headings like GitHub and comrak: the text is lowercased, spaces
+/// are replaced by `-`, punctuation is dropped, and duplicates are suffixed with a number.
+#[derive(Default)]
+struct Anchorizer(HashSet<String>);
+
+impl Anchorizer {
+    fn anchorize(&mut self, text: &str) -> String {
+        let anchor = text
+            .to_lowercase()
+            .chars()
+            .filter(|&c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ')
+            .map(|c| if c == ' ' { '-' } else { c })
+            .collect::<String>();
+
+        let mut unique = anchor.clone();
+        let mut suffix = 0;
+        while self.0.contains(&unique) {
+            suffix += 1;
+            unique = format!("{}-{}", anchor, suffix);
+        }
+        self.0.insert(unique.clone());
+        unique
+    }
+}
+
+/// Returns the headings of a readme in order, with the anchors of the rendered headings.
+/// Only Markdown and reStructuredText readmes have headings.
+pub fn readme_headings(text: &str, filename: &str) -> Vec<ReadmeHeading> {
+    renderer_for(filename).headings(text)
+}
+
+fn markdown_headings(text: &str) -> Vec<ReadmeHeading> {
+    use comrak::{parse_document, Arena};
+
+    fn collect_text<'a>(node: &'a AstNode<'a>, text: &mut String) {
+        match node.data.borrow().value {
+            NodeValue::Text(ref literal) | NodeValue::Code(ref literal) => {
+                text.push_str(&String::from_utf8_lossy(literal))
+            }
+            NodeValue::LineBreak | NodeValue::SoftBreak => text.push(' '),
+            _ => {
+                for child in node.children() {
+                    collect_text(child, text);
+                }
+            }
+        }
+    }
+
+    let arena = Arena::new();
+    let root = parse_document(&arena, text, &comrak_options());
+    let mut anchorizer = Anchorizer::default();
+    root.descendants()
+        .filter_map(|node| match node.data.borrow().value {
+            NodeValue::Heading(ref heading) => Some((node, heading.level)),
+            _ => None,
+        })
+        .map(|(node, level)| {
+            let mut text = String::new();
+            collect_text(node, &mut text);
+            ReadmeHeading {
+                level: level as u8,
+                anchor: anchorizer.anchorize(&text),
+                text,
+            }
+        })
+        .collect()
+}
+
+/// The number of days after which the default branch of a repository is fetched again, as
+/// repositories are sometimes moved from `master` to `main`
+const DEFAULT_BRANCH_TTL_DAYS: i32 = 30;
+
+/// Returns the default branch of a repository hosted on GitHub, GitLab or one of the
+/// self-hosted `forges`, which is fetched from their API unless it was fetched recently.
+///
+/// Rendering doesn't depend on it, so errors are logged and `None` is returned, as for the
+/// repositories hosted elsewhere. The savepoint keeps the transaction of the job usable.
+pub(crate) fn repository_default_branch(
+    conn: &PgConnection,
+    client: &Client,
+    forges: &[Forge],
+    repository: &str,
+) -> Option<String> {
+    use crate::schema::repository_default_branches;
+    use diesel::dsl::now;
+    use diesel::pg::upsert::excluded;
+    use diesel::prelude::*;
+
+    let repository = canon_base_url(repository.to_string());
+    match stored_default_branch(conn, &repository) {
+        Ok(Some(branch)) => return Some(branch),
+        Ok(None) => {}
+        Err(e) => {
+            warn!("could not read the default branch of {}: {}", repository, e);
+            return None;
+        }
+    }
+
+    let branch = match fetch_default_branch(client, forges, &repository) {
+        Ok(branch) => branch?,
+        Err(e) => {
+            warn!(
+                "could not fetch the default branch of {}: {}",
+                repository, e
+            );
+            return None;
+        }
+    };
+    let result = conn.transaction(|| {
+        diesel::insert_into(repository_default_branches::table)
+            .values((
+                repository_default_branches::repository.eq(&repository),
+                repository_default_branches::branch.eq(&branch),
+            ))
+            .on_conflict(repository_default_branches::repository)
+            .do_update()
+            .set((
+                repository_default_branches::branch
+                    .eq(excluded(repository_default_branches::branch)),
+                repository_default_branches::fetched_at.eq(now),
+            ))
+            .execute(conn)
+    });
+    if let Err(e) = result {
+        warn!(
+            "could not record the default branch of {}: {}",
+            repository, e
+        );
+    }
+    Some(branch)
+}
+
+/// Returns the default branch of a repository if it was fetched recently by
+/// `repository_default_branch`, without fetching it otherwise.
+pub(crate) fn stored_default_branch(
+    conn: &PgConnection,
+    repository: &str,
+) -> QueryResult<Option<String>> {
+    use crate::schema::repository_default_branches;
+    use diesel::dsl::{now, IntervalDsl};
+    use diesel::prelude::*;
+
+    repository_default_branches::table
+        .find(canon_base_url(repository.to_string()))
+        .filter(repository_default_branches::fetched_at.gt(now - DEFAULT_BRANCH_TTL_DAYS.days()))
+        .select(repository_default_branches::branch)
+        .first(conn)
+        .optional()
+}
+
+/// Asks the API of GitHub, GitLab or one of the self-hosted `forges` for the default branch
+/// of a repository, returning `None` for the other hosts.
+fn fetch_default_branch(
+    client: &Client,
+    forges: &[Forge],
+    repository: &str,
+) -> anyhow::Result<Option<String>> {
+    #[derive(Deserialize)]
+    struct Repository {
+        default_branch: Option<String>,
+    }
+
+    let url = Url::parse(repository)?;
+    let mut segments = url.path_segments().into_iter().flatten();
+    let (owner, name) = match (segments.next(), segments.next()) {
+        (Some(owner), Some(name)) if !owner.is_empty() && !name.is_empty() => (owner, name),
+        _ => return Ok(None),
+    };
+    let host = url.host_str().unwrap_or_default();
+    let forge = forges
+        .iter()
+        .find(|forge| forge.host.eq_ignore_ascii_case(host));
+    let api_url = match (forge, host) {
+        (Some(forge), _) => forge.kind.api_url(host, owner, name),
+        (None, "github.com") => ForgeKind::GitHub.api_url(host, owner, name),
+        (None, "gitlab.com") => ForgeKind::GitLab.api_url(host, owner, name),
+        _ => None,
+    };
+    let api_url = match api_url {
+        Some(api_url) => api_url,
+        None => return Ok(None),
+    };
+
+    let repository: Repository = client
+        .get(&api_url)
+        .header(header::USER_AGENT, "crates.io (https://crates.io)")
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(repository.default_branch)
+}
+
+/// Returns the path in the repository of a file of a package, given the directory of the
+/// package in the repository, such as `crates/foo`, and the path of the file in the package,
+/// which may be in the parent directories of a workspace, such as `../../README.md`.
+///
+/// Returns `None` if the path leaves the repository, whose relative links are then resolved
+/// against its root.
+pub(crate) fn path_in_repository(path_in_vcs: Option<&str>, path: &str) -> Option<String> {
+    let mut segments = Vec::new();
+    let path_in_vcs = path_in_vcs.unwrap_or_default();
+    for segment in path_in_vcs.split('/').chain(path.split(&['/', '\\'][..])) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// A README or a changelog of a new version, to be rendered by one of the jobs below
+///
+/// Versions held for review are only rendered once approved, see `QuarantinedVersion`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingRendering {
+    pub kind: RenderingKind,
+    pub text: String,
+    pub file_name: String,
+    /// The repository against which the relative links are resolved
+    pub base_url: Option<String>,
+    /// The directory of the package in the repository, see `TarballVcsInfo`
+    pub path_in_vcs: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RenderingKind {
+    /// The main README, see `render_and_upload_readme`
+    Readme,
+    /// A localized README, see `render_and_upload_localized_readme`
+    LocalizedReadme { language: String },
+    /// The changelog, see `render_and_store_changelog`
+    Changelog,
+}
+
+impl PendingRendering {
+    /// Enqueues the job rendering the file for the version.
+    pub fn enqueue(self, conn: &PgConnection, version_id: i32) -> Result<(), EnqueueError> {
+        let PendingRendering {
+            kind,
+            text,
+            file_name,
+            base_url,
+            path_in_vcs,
+        } = self;
+        match kind {
+            RenderingKind::Readme => {
+                render_and_upload_readme(version_id, text, file_name, base_url, path_in_vcs)
+                    .enqueue(conn)
+            }
+            RenderingKind::LocalizedReadme { language } => render_and_upload_localized_readme(
+                version_id,
+                language,
+                text,
+                file_name,
+                base_url,
+                path_in_vcs,
+            )
+            .enqueue(conn),
+            RenderingKind::Changelog => {
+                render_and_store_changelog(version_id, text, file_name, base_url, path_in_vcs)
+                    .enqueue(conn)
+            }
+        }
+    }
+}
+
+#[swirl::background_job]
+pub fn render_and_upload_readme(
+    conn: &PgConnection,
+    env: &Environment,
+    version_id: i32,
+    text: String,
+    file_name: String,
+    base_url: Option<String>,
+    path_in_vcs: Option<String>,
+) -> Result<(), PerformError> {
+    use crate::schema::*;
+    use diesel::prelude::*;
+
+    let default_branch = base_url.as_deref().and_then(|base_url| {
+        repository_default_branch(
+            conn,
+            env.http_client(),
+            &env.render_settings().forges,
+            base_url,
+        )
+    });
+    let readme_path = path_in_repository(path_in_vcs.as_deref(), &file_name);
+    let rendered = match readme_to_html_cached(
+        conn,
+        &text,
+        &file_name,
+        base_url.as_deref(),
+        default_branch.as_deref(),
+        readme_path.as_deref(),
+        Some(env.render_settings()),
+    ) {
+        Ok(rendered) => rendered,
+        // Retrying wouldn't help, the README is left unrendered
+        Err(e) => {
+            warn!(
+                "could not render the readme of version {}: {}",
+                version_id, e
+            );
+            return Ok(());
+        }
+    };
+    let headings = serde_json::to_value(readme_headings(&text, &file_name))?;
+    let plain_text = html_to_text(&rendered);
+
+    conn.transaction(|| {
+        Version::record_readme_rendering(version_id, &conn)?;
+        diesel::update(readme_renderings::table.find(version_id))
+            .set((
+                readme_renderings::headings.eq(headings),
+                readme_renderings::has_source.eq(true),
+            ))
+            .execute(&*conn)?;
+        let (crate_id, crate_name, vers): (i32, String, String) = versions::table
+            .find(version_id)
+            .inner_join(crates::table)
+            .select((crates::id, crates::name, versions::num))
+            .first(&*conn)?;
+        update_readme_search_index(&*conn, crate_id, version_id, &plain_text)?;
+        env.uploader
+            .upload_readme(env.http_client(), &crate_name, &vers, None, rendered)?;
+        env.uploader
+            .upload_raw_readme(env.http_client(), &crate_name, &vers, text)?;
+        Ok(())
+    })
+}
+
+/// Indexes the text of the README of a version for `?readme=` searches, unless a newer version
+/// of the crate was published since.
+fn update_readme_search_index(
+    conn: &PgConnection,
+    crate_id: i32,
+    version_id: i32,
+    plain_text: &str,
+) -> QueryResult<()> {
+    use crate::schema::*;
+    use diesel::dsl::sql;
+    use diesel::prelude::*;
+    use diesel::sql_types::Text;
+    use diesel_full_text_search::TsVector;
+
+    let latest_version_id: i32 = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .order(versions::created_at.desc())
+        .select(versions::id)
+        .first(conn)?;
+    if latest_version_id != version_id {
+        return Ok(());
+    }
+    let index = sql::<TsVector>("to_tsvector('pg_catalog.english', ")
+        .bind::<Text, _>(plain_text)
+        .sql(")");
+    diesel::update(crates::table.find(crate_id))
+        .set(crates::readme_textsearchable_index_col.eq(index))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Returns the text of some rendered HTML, without its elements and with its whitespace
+/// collapsed.
+pub fn html_to_text(html: &str) -> String {
+    // Keeps the words of adjacent elements, like the cells of tables, apart
+    let html = html.replace('<', " <");
+    let text = Builder::empty().clean(&html).to_string();
+    let text = decode_html(&text).unwrap_or(text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Renders a localized README of a version, see `TarballReadme`, and uploads it next to the
+/// main one.
+#[swirl::background_job]
+pub fn render_and_upload_localized_readme(
+    conn: &PgConnection,
+    env: &Environment,
+    version_id: i32,
+    language: String,
+    text: String,
+    file_name: String,
+    base_url: Option<String>,
+    path_in_vcs: Option<String>,
+) -> Result<(), PerformError> {
+    use crate::models::LocalizedReadme;
+    use crate::schema::*;
+    use diesel::prelude::*;
+
+    let default_branch = base_url.as_deref().and_then(|base_url| {
+        repository_default_branch(
+            conn,
+            env.http_client(),
+            &env.render_settings().forges,
+            base_url,
+        )
+    });
+    let readme_path = path_in_repository(path_in_vcs.as_deref(), &file_name);
+    let rendered = match readme_to_html_cached(
+        conn,
+        &text,
+        &file_name,
+        base_url.as_deref(),
+        default_branch.as_deref(),
+        readme_path.as_deref(),
+        Some(env.render_settings()),
+    ) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            warn!(
+                "could not render the {} readme of version {}: {}",
+                language, version_id, e
+            );
+            return Ok(());
+        }
+    };
+
+    conn.transaction(|| {
+        LocalizedReadme::record(&conn, version_id, &language, &file_name)?;
+        let (crate_name, vers): (String, String) = versions::table
+            .find(version_id)
+            .inner_join(crates::table)
+            .select((crates::name, versions::num))
+            .first(&*conn)?;
+        env.uploader.upload_readme(
+            env.http_client(),
+            &crate_name,
+            &vers,
+            Some(&language),
+            rendered,
+        )?;
+        Ok(())
+    })
+}
+
+/// Returns the level of `line` if it is an ATX heading (`# Title`), along with its text.
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level, rest.trim()))
+}
+
+/// Returns whether `heading` mentions `version`, optionally prefixed with a `v`.
+///
+/// The version has to appear on its own, so `1.0.0` doesn't match `11.0.0` or
+/// `1.0.0-beta`.
+fn heading_mentions_version(heading: &str, version: &str) -> bool {
+    fn is_version_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+'
+    }
+
+    heading.match_indices(version).any(|(start, _)| {
+        let before = heading[..start]
+            .trim_end_matches(|c| c == 'v' || c == 'V')
+            .chars()
+            .last();
+        let after = heading[start + version.len()..].chars().next();
+        !before.map_or(false, is_version_char) && !after.map_or(false, is_version_char)
+    })
+}
+
+/// Extracts the section of a Markdown changelog which describes `version`.
 ///
-/// let text = "[Rust](https://rust-lang.org/) is an awesome *systems programming* language!";
-/// let rendered = readme_to_html(text, "README.md", None)?;
-/// ```
-pub fn readme_to_html(text: &str, filename: &str, base_url: Option<&str>) -> String {
-    let filename = filename.to_lowercase();
+/// The section starts at the first heading mentioning the version and ends right before
+/// the next heading of the same or a higher level. Headings inside fenced code blocks are
+/// ignored.
+pub fn changelog_section<'a>(text: &'a str, version: &str) -> Option<&'a str> {
+    let mut start = None;
+    let mut offset = 0;
+    let mut fence: Option<&str> = None;
+
+    for line in text.split('\n') {
+        let line_start = offset;
+        offset += line.len() + 1;
+
+        let trimmed = line.trim();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            fence = Some("```");
+            continue;
+        }
+        if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+            continue;
+        }
 
-    if !filename.contains('.') || MARKDOWN_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
-        return markdown_to_html(text, base_url);
+        if let Some((level, heading)) = atx_heading(line.trim_end()) {
+            match start {
+                Some((section_start, section_level)) if level <= section_level => {
+                    return Some(text[section_start..line_start].trim_end());
+                }
+                None if heading_mentions_version(heading, version) => {
+                    start = Some((line_start, level));
+                }
+                _ => {}
+            }
+        }
     }
 
-    encode_minimal(text).replace("\n", "<br>\n")
+    start.map(|(section_start, _)| text[section_start..].trim_end())
 }
 
+/// Renders the changelog of a version and stores it, along with the section describing
+/// the version itself if one can be found.
 #[swirl::background_job]
-pub fn render_and_upload_readme(
+pub fn render_and_store_changelog(
     conn: &PgConnection,
     env: &Environment,
     version_id: i32,
     text: String,
     file_name: String,
     base_url: Option<String>,
+    path_in_vcs: Option<String>,
 ) -> Result<(), PerformError> {
+    use crate::models::NewVersionChangelog;
     use crate::schema::*;
     use diesel::prelude::*;
 
-    let rendered = readme_to_html(&text, &file_name, base_url.as_deref());
+    let num: String = versions::table
+        .find(version_id)
+        .select(versions::num)
+        .first(&*conn)?;
 
-    conn.transaction(|| {
-        Version::record_readme_rendering(version_id, &conn)?;
-        let (crate_name, vers): (String, String) = versions::table
-            .find(version_id)
-            .inner_join(crates::table)
-            .select((crates::name, versions::num))
-            .first(&*conn)?;
-        env.uploader
-            .upload_readme(env.http_client(), &crate_name, &vers, rendered)?;
-        Ok(())
-    })
+    let base_url = base_url.as_deref();
+    let default_branch = base_url.and_then(|base_url| {
+        repository_default_branch(
+            conn,
+            env.http_client(),
+            &env.render_settings().forges,
+            base_url,
+        )
+    });
+    let default_branch = default_branch.as_deref();
+    let readme_path = path_in_repository(path_in_vcs.as_deref(), &file_name);
+    let readme_path = readme_path.as_deref();
+    let settings = Some(env.render_settings());
+    let html = match readme_to_html_cached(
+        conn,
+        &text,
+        &file_name,
+        base_url,
+        default_branch,
+        readme_path,
+        settings,
+    ) {
+        Ok(html) => html,
+        Err(e) => {
+            warn!(
+                "could not render the changelog of version {}: {}",
+                version_id, e
+            );
+            return Ok(());
+        }
+    };
+    let section_html = changelog_section(&text, &num).and_then(|section| {
+        readme_to_html_limited(
+            section,
+            &file_name,
+            base_url,
+            default_branch,
+            readme_path,
+            settings,
+        )
+        .ok()
+    });
+
+    NewVersionChangelog {
+        version_id,
+        file_name: &file_name,
+        html: &html,
+        section_html: section_html.as_deref(),
+    }
+    .save(&conn)?;
+    Ok(())
 }
 
 /// Helper function to build a new `HashSet` from the items slice.
@@ -311,24 +1893,42 @@ mod tests {
     #[test]
     fn empty_text() {
         let text = "";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(result, "");
     }
 
     #[test]
     fn text_with_script_tag() {
         let text = "foo_readme\n\n<script>alert('Hello World')</script>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(
             result,
             "<p>foo_readme</p>\n&lt;script&gt;alert(\'Hello World\')&lt;/script&gt;\n"
         );
     }
 
+    #[test]
+    fn text_larger_than_the_chunks() {
+        let paragraph = "foo_readme *with* [a link](https://example.com)\n\n";
+        let count = 2 * CHUNK_SIZE / paragraph.len();
+        let result = markdown_to_html(&paragraph.repeat(count), None, None, None, None);
+        let expected = "<p>foo_readme <em>with</em> <a href=\"https://example.com\" rel=\"nofollow noopener noreferrer\">a link</a></p>\n";
+        assert_eq!(result, expected.repeat(count));
+    }
+
+    #[test]
+    fn multibyte_characters_split_across_chunks() {
+        // The pattern is 10 bytes long, so some of its characters are split across the
+        // boundaries of the chunks
+        let text = "aé€😀".repeat(2 * CHUNK_SIZE / 10);
+        let result = markdown_to_html(&text, None, None, None, None);
+        assert_eq!(result, format!("<p>{}</p>\n", text));
+    }
+
     #[test]
     fn text_with_iframe_tag() {
         let text = "foo_readme\n\n<iframe>alert('Hello World')</iframe>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(
             result,
             "<p>foo_readme</p>\n&lt;iframe&gt;alert(\'Hello World\')&lt;/iframe&gt;\n"
@@ -338,14 +1938,14 @@ mod tests {
     #[test]
     fn text_with_unknown_tag() {
         let text = "foo_readme\n\n<unknown>alert('Hello World')</unknown>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(result, "<p>foo_readme</p>\n<p>alert(\'Hello World\')</p>\n");
     }
 
     #[test]
     fn text_with_inline_javascript() {
         let text = r#"foo_readme\n\n<a href="https://crates.io/crates/cargo-registry" onclick="window.alert('Got you')">Crate page</a>"#;
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(
             result,
             "<p>foo_readme\\n\\n<a href=\"https://crates.io/crates/cargo-registry\" rel=\"nofollow noopener noreferrer\">Crate page</a></p>\n"
@@ -357,7 +1957,7 @@ mod tests {
     #[test]
     fn text_with_fancy_single_quotes() {
         let text = r#"wb’"#;
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(result, "<p>wb’</p>\n");
     }
 
@@ -366,7 +1966,7 @@ mod tests {
         let code_block = r#"```rust \
                             println!("Hello World"); \
                            ```"#;
-        let result = markdown_to_html(code_block, None);
+        let result = markdown_to_html(code_block, None, None, None, None);
         assert!(result.contains("<code class=\"language-rust\">"));
     }
 
@@ -375,14 +1975,172 @@ mod tests {
         let code_block = r#"```rust  ,  no_run \
                             println!("Hello World"); \
                            ```"#;
-        let result = markdown_to_html(code_block, None);
+        let result = markdown_to_html(code_block, None, None, None, None);
         assert!(result.contains("<code class=\"language-rust\">"));
     }
 
+    #[test]
+    fn code_block_keeps_the_class_of_any_language() {
+        let code_block = "```haskell\nmain = pure ()\n```\n";
+        let result = markdown_to_html(code_block, None, None, None, None);
+        assert_eq!(
+            result,
+            "<pre><code class=\"language-haskell\">main = pure ()\n</code></pre>\n"
+        );
+
+        let text = "<code class=\"language-c++ bad-class language-a&lt;b\">x</code>";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(result, "<p><code class=\"language-c++\">x</code></p>\n");
+
+        let text = "<code class=\"bad-class\">x</code>";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(result, "<p><code>x</code></p>\n");
+    }
+
+    #[test]
+    fn emoji_shortcodes_are_converted() {
+        let text = "Written in Rust :crab: :tada:\n\n`:crab:`\n\n```\n:crab:\n```\n";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result,
+            "<p>Written in Rust 🦀 🎉</p>\n\
+             <p><code>:crab:</code></p>\n\
+             <pre><code>:crab:\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn footnotes_are_kept() {
+        let text = "See the benchmarks[^bench].\n\n[^bench]: Run on a laptop.\n";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert!(result.contains(
+            "<sup class=\"footnote-ref\"><a href=\"#fn1\" id=\"user-content-fnref1\" \
+             rel=\"nofollow noopener noreferrer\">1</a></sup>"
+        ));
+        assert!(result.contains("<section class=\"footnotes\">"));
+        assert!(result.contains("<li id=\"user-content-fn1\">"));
+        assert!(result.contains("Run on a laptop."));
+        assert!(result.contains(
+            "<a href=\"#fnref1\" class=\"footnote-backref\" rel=\"nofollow noopener noreferrer\">"
+        ));
+    }
+
+    #[test]
+    fn html_comments_are_removed() {
+        let text = "<!-- cargo-rdme start -->\n\nSome text <!-- inline -->here.\n\n<!-- a -->\n<!-- b -->\n";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(result, "<p>Some text here.</p>\n");
+
+        assert!(is_only_comments(b" <!-- a --> <!--\nb\n-->\n"));
+        assert!(!is_only_comments(b"<!-- a --> <br>"));
+        assert!(!is_only_comments(b"<!-- a"));
+        assert!(!is_only_comments(b""));
+    }
+
+    #[test]
+    fn badges_are_collapsed() {
+        let settings = RenderSettings {
+            collapse_badges: true,
+            ..RenderSettings::default()
+        };
+        let text = "# Foo\n\n\
+                    [![CI](https://example.com/ci.svg)](https://example.com/ci)\n\
+                    ![License](https://example.com/license.svg)\n\n\
+                    [![Docs](https://example.com/docs.svg)](https://docs.rs/foo)\n\n\
+                    Some text.\n";
+        let result = markdown_to_html(text, None, None, None, Some(&settings));
+        assert_eq!(
+            result,
+            "<h1><a href=\"#foo\" id=\"user-content-foo\" rel=\"nofollow noopener noreferrer\"></a>Foo</h1>\n\
+             <div class=\"readme-badges\">\n\
+             <p><a href=\"https://example.com/ci\" rel=\"nofollow noopener noreferrer\"><img src=\"https://example.com/ci.svg\" alt=\"CI\"></a>\n\
+             <img src=\"https://example.com/license.svg\" alt=\"License\">\n\
+             <a href=\"https://docs.rs/foo\" rel=\"nofollow noopener noreferrer\"><img src=\"https://example.com/docs.svg\" alt=\"Docs\"></a></p>\n\
+             </div>\n\
+             <p>Some text.</p>\n"
+        );
+
+        // Short runs and badges mixed with text are kept as is
+        let text =
+            "![CI](ci.svg) ![Docs](docs.svg)\n\n![a](a.svg) ![b](b.svg) ![c](c.svg) and text\n";
+        let result = markdown_to_html(text, None, None, None, Some(&settings));
+        assert!(!result.contains("readme-badges"));
+
+        // Unless they are enabled
+        let text = "![a](a.svg) ![b](b.svg) ![c](c.svg)\n";
+        assert!(!markdown_to_html(text, None, None, None, None).contains("readme-badges"));
+        assert!(markdown_to_html(text, None, None, None, Some(&settings)).contains("readme-badges"));
+    }
+
+    #[test]
+    fn math_is_wrapped_when_enabled() {
+        let text = "Euler: $e^{i\\pi} + 1 < 2$ for $5\n\n```math\n\\sum_k k\n```\n";
+        let settings = RenderSettings {
+            math: true,
+            ..RenderSettings::default()
+        };
+        let result = markdown_to_html(text, None, None, None, Some(&settings));
+        assert_eq!(
+            result,
+            "<p>Euler: <span class=\"math math-inline\">e^{i\\pi} + 1 &lt; 2</span> for $5</p>\n\
+             <div class=\"math math-display\">\\sum_k k</div>\n"
+        );
+
+        let result = markdown_to_html(text, None, None, None, None);
+        assert!(result.starts_with("<p>Euler: $e^{i\\pi} + 1 &lt; 2$ for $5</p>\n"));
+        assert!(!result.contains("math-display"));
+    }
+
+    #[test]
+    fn details_are_kept() {
+        let text = "<details open>\n<summary>Example</summary>\n\n```\nfoo\n```\n\n</details>\n";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result,
+            "<details open=\"\">\n<summary>Example</summary>\n<pre><code>foo\n</code></pre>\n</details>\n"
+        );
+
+        let text = "<details ontoggle=\"alert(1)\" style=\"display: none\" class=\"x\">\
+                    <summary onclick=\"alert(2)\" id=\"s\">Example</summary></details>";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result.trim_end(),
+            "<details><summary>Example</summary></details>"
+        );
+    }
+
+    #[test]
+    fn alignment_is_kept() {
+        let text = "<p align=\"Center\" onclick=\"alert(1)\">\n<img src=\"https://example.com/logo.png\" width=\"200\">\n</p>\n\n\
+                    <div align=\"right\" onmouseover=\"alert(2)\" style=\"color: red\">a</div>\n\n\
+                    | a | b |\n|:-:|--:|\n| c | d |\n";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result,
+            "<p align=\"center\">\n<img src=\"https://example.com/logo.png\" width=\"200\" alt=\"logo.png\">\n</p>\n\
+             <div align=\"right\">a</div>\n\
+             <table>\n<thead>\n<tr>\n<th align=\"center\">a</th>\n<th align=\"right\">b</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td align=\"center\">c</td>\n<td align=\"right\">d</td>\n</tr>\n</tbody>\n</table>\n"
+        );
+
+        let text = "<table><tr>\
+                    <td align=\"javascript:alert(1)\" width=\"50%\" onload=\"alert(2)\">a</td>\
+                    <th width=\"expression(alert(3))\" align=\"left\">b</th>\
+                    </tr></table>";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result.trim_end(),
+            "<table><tbody><tr><td width=\"50%\">a</td><th align=\"left\">b</th></tr></tbody></table>"
+        );
+
+        assert!(is_length("200") && is_length("50%"));
+        assert!(!is_length("%") && !is_length("1e9") && !is_length("100px"));
+    }
+
     #[test]
     fn text_with_forbidden_class_attribute() {
         let text = "<p class='bad-class'>Hello World!</p>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(result, "<p>Hello World!</p>\n");
     }
 
@@ -402,7 +2160,7 @@ mod tests {
                     if extra_slash { "/" } else { "" },
                 );
 
-                let result = markdown_to_html(absolute, Some(&url));
+                let result = markdown_to_html(absolute, Some(&url), None, None, None);
                 assert_eq!(
                     result,
                     format!(
@@ -411,7 +2169,7 @@ mod tests {
                     )
                 );
 
-                let result = markdown_to_html(relative, Some(&url));
+                let result = markdown_to_html(relative, Some(&url), None, None, None);
                 assert_eq!(
                     result,
                     format!(
@@ -420,7 +2178,7 @@ mod tests {
                     )
                 );
 
-                let result = markdown_to_html(image, Some(&url));
+                let result = markdown_to_html(image, Some(&url), None, None, None);
                 assert_eq!(
                     result,
                     format!(
@@ -429,7 +2187,7 @@ mod tests {
                     )
                 );
 
-                let result = markdown_to_html(svg, Some(&url));
+                let result = markdown_to_html(svg, Some(&url), None, None, None);
                 assert_eq!(
                     result,
                     format!(
@@ -440,19 +2198,188 @@ mod tests {
             }
         }
 
-        let result = markdown_to_html(absolute, Some("https://google.com/"));
+        let result = markdown_to_html(absolute, Some("https://google.com/"), None, None, None);
         assert_eq!(
             result,
             "<p><a rel=\"nofollow noopener noreferrer\">hi</a></p>\n"
         );
     }
 
+    #[test]
+    fn relative_links_on_self_hosted_forges() {
+        let settings = RenderSettings {
+            camo: None,
+            forges: vec![
+                Forge {
+                    host: "git.example.com".into(),
+                    kind: ForgeKind::GitLab,
+                },
+                Forge {
+                    host: "code.example.org".into(),
+                    kind: ForgeKind::Gitea,
+                },
+            ],
+            ..RenderSettings::default()
+        };
+        let text = "[there](there) ![alt](img.png)";
+
+        let result = markdown_to_html(
+            text,
+            Some("https://git.example.com/rust-lang/test.git"),
+            Some("main"),
+            None,
+            Some(&settings),
+        );
+        assert_eq!(
+            result,
+            "<p><a href=\"https://git.example.com/rust-lang/test/-/blob/main/there\" rel=\"nofollow noopener noreferrer\">there</a> \
+             <img src=\"https://git.example.com/rust-lang/test/-/raw/main/img.png\" alt=\"alt\"></p>\n"
+        );
+
+        let result = markdown_to_html(
+            text,
+            Some("https://code.example.org/rust-lang/test"),
+            Some("main"),
+            None,
+            Some(&settings),
+        );
+        assert_eq!(
+            result,
+            "<p><a href=\"https://code.example.org/rust-lang/test/src/branch/main/there\" rel=\"nofollow noopener noreferrer\">there</a> \
+             <img src=\"https://code.example.org/rust-lang/test/raw/branch/main/img.png\" alt=\"alt\"></p>\n"
+        );
+
+        // Unknown hosts still get no relative links
+        let result = markdown_to_html(
+            text,
+            Some("https://git.example.net/rust-lang/test"),
+            None,
+            None,
+            Some(&settings),
+        );
+        assert_eq!(
+            result,
+            "<p><a rel=\"nofollow noopener noreferrer\">there</a> <img alt=\"alt\"></p>\n"
+        );
+    }
+
+    #[test]
+    fn images_without_alt_get_their_file_name() {
+        let text = "![](https://example.com/img/logo.svg?v=2 \"The logo\") [a](https://example.com \"A\")\n\n\
+                    <img src=\"https://example.com/a%20b.png\" title=\"x > y\"> <img alt=\" \"> <img alt=\"kept\">";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result,
+            "<p><img src=\"https://example.com/img/logo.svg?v=2\" alt=\"logo.svg\" title=\"The logo\"> \
+             <a href=\"https://example.com\" title=\"A\" rel=\"nofollow noopener noreferrer\">a</a></p>\n\
+             <p><img src=\"https://example.com/a%20b.png\" title=\"x > y\" alt=\"a%20b.png\"> \
+             <img alt=\"image\"> <img alt=\"kept\"></p>\n"
+        );
+
+        let camo = Camo {
+            url: "https://camo.example.com".into(),
+            key: "secret".into(),
+        };
+        let settings = RenderSettings {
+            camo: Some(camo.clone()),
+            ..RenderSettings::default()
+        };
+        let result = markdown_to_html(
+            "![](https://example.com/a.png)",
+            None,
+            None,
+            None,
+            Some(&settings),
+        );
+        assert!(result.contains("alt=\"a.png\""));
+
+        let text = "<img src='img/a.png' alt /> <img src=b.gif>";
+        let result = markdown_to_html(text, None, None, None, None);
+        assert_eq!(
+            result,
+            "<p><img src=\"img/a.png\" alt=\"a.png\"> <img src=\"b.gif\" alt=\"b.gif\"></p>\n"
+        );
+    }
+
+    #[test]
+    fn images_are_loaded_through_camo() {
+        let camo = Camo {
+            url: "https://camo.example.com".into(),
+            key: "secret".into(),
+        };
+        let settings = RenderSettings {
+            camo: Some(camo.clone()),
+            ..RenderSettings::default()
+        };
+        let text = "![a](https://example.com/a.png) ![b](b.png) [c](https://example.com/c.png)";
+        let result = markdown_to_html(
+            text,
+            Some("https://github.com/rust-lang/test"),
+            None,
+            None,
+            Some(&settings),
+        );
+        assert!(result.contains(&format!(
+            "<img src=\"{}\" alt=\"a\">",
+            camo.proxy_url("https://example.com/a.png")
+        )));
+        assert!(result.contains(&format!(
+            "<img src=\"{}\" alt=\"b\">",
+            camo.proxy_url("https://github.com/rust-lang/test/raw/HEAD/b.png")
+        )));
+        // Only images are proxied
+        assert!(result.contains("<a href=\"https://example.com/c.png\""));
+    }
+
+    #[test]
+    fn relative_image_sources_point_to_raw_files() {
+        let url = Some("https://github.com/rust-lang/test");
+        let result = markdown_to_html("![badge](badges/build)", url, Some("main"), None, None);
+        assert_eq!(
+            result,
+            "<p><img src=\"https://github.com/rust-lang/test/raw/main/badges/build\" alt=\"badge\"></p>\n"
+        );
+
+        let text = "<img src=\"docs/screenshot.webp\" width=\"200\">";
+        let result = markdown_to_html(text, url, Some("main"), None, None);
+        assert!(result.contains(
+            "<img src=\"https://github.com/rust-lang/test/raw/main/docs/screenshot.webp\" width=\"200\" alt=\"screenshot.webp\">"
+        ));
+
+        // Links to the same files are still shown in the "blob" view
+        let result = markdown_to_html("[build](badges/build)", url, Some("main"), None, None);
+        assert!(result.contains("https://github.com/rust-lang/test/blob/main/badges/build"));
+
+        let result = markdown_to_html("![alt](https://example.com/img)", url, None, None, None);
+        assert_eq!(
+            result,
+            "<p><img src=\"https://example.com/img\" alt=\"alt\"></p>\n"
+        );
+    }
+
+    #[test]
+    fn relative_links_point_to_the_default_branch() {
+        let url = "https://gitlab.com/rust-lang/test";
+
+        let result = markdown_to_html("[there](there)", Some(url), Some("main"), None, None);
+        assert_eq!(
+            result,
+            "<p><a href=\"https://gitlab.com/rust-lang/test/blob/main/there\" rel=\"nofollow noopener noreferrer\">there</a></p>\n"
+        );
+
+        let result = markdown_to_html("![alt](img.png)", Some(url), Some("main"), None, None);
+        assert_eq!(
+            result,
+            "<p><img src=\"https://gitlab.com/rust-lang/test/raw/main/img.png\" alt=\"alt\"></p>\n"
+        );
+    }
+
     #[test]
     fn absolute_links_dont_get_resolved() {
         let readme_text =
             "[![Crates.io](https://img.shields.io/crates/v/clap.svg)](https://crates.io/crates/clap)";
         let repository = "https://github.com/kbknapp/clap-rs/";
-        let result = markdown_to_html(readme_text, Some(repository));
+        let result = markdown_to_html(readme_text, Some(repository), None, None, None);
 
         assert_eq!(
             result,
@@ -464,7 +2391,7 @@ mod tests {
     fn readme_to_html_renders_markdown() {
         for f in &["README", "readme.md", "README.MARKDOWN", "whatever.mkd"] {
             assert_eq!(
-                readme_to_html("*lobster*", f, None),
+                readme_to_html("*lobster*", f, None, None, None, None),
                 "<p><em>lobster</em></p>\n"
             );
         }
@@ -474,27 +2401,244 @@ mod tests {
     fn readme_to_html_renders_other_things() {
         for f in &["readme.exe", "readem.org", "blah.adoc"] {
             assert_eq!(
-                readme_to_html("<script>lobster</script>\n\nis my friend\n", f, None),
+                readme_to_html(
+                    "<script>lobster</script>\n\nis my friend\n",
+                    f,
+                    None,
+                    None,
+                    None,
+                    None
+                ),
                 "&lt;script&gt;lobster&lt;/script&gt;<br>\n<br>\nis my friend<br>\n"
             );
         }
     }
 
+    #[test]
+    fn readme_to_html_renders_rst() {
+        let text = "\
+My crate
+========
+
+Some *emphasis*, **strong** and ``code``, see `the docs <https://docs.rs/foo>`_
+or Rust_.
+
+* one
+* two
+
+Example::
+
+    let x = 1;
+
+.. code-block:: rust
+
+    fn main() {}
+
+.. _Rust: https://www.rust-lang.org/
+";
+        assert_eq!(
+            readme_to_html(text, "README.rst", None, None, None, None),
+            "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>\
+             My crate</h1>\n\
+             <p>Some <em>emphasis</em>, <strong>strong</strong> and <code>code</code>, see \
+             <a href=\"https://docs.rs/foo\" rel=\"nofollow noopener noreferrer\">the docs</a> \
+             or <a href=\"https://www.rust-lang.org/\" rel=\"nofollow noopener noreferrer\">Rust</a>.</p>\n\
+             <ul>\n<li>one</li>\n<li>two</li>\n</ul>\n\
+             <p>Example:</p>\n\
+             <pre><code>let x = 1;\n</code></pre>\n\
+             <pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn rst_badges_and_relative_images() {
+        let text = "\
+|build|
+
+.. |build| image:: https://img.shields.io/badge/build-passing-green.svg
+   :target: https://ci.example.com/foo
+   :alt: Build Status
+
+.. image:: docs/logo.png
+";
+        assert_eq!(
+            readme_to_html(text, "README.rst", Some("https://github.com/foo/bar"), None, None, None),
+            "<p><a href=\"https://ci.example.com/foo\" rel=\"nofollow noopener noreferrer\">\
+             <img src=\"https://img.shields.io/badge/build-passing-green.svg\" alt=\"Build Status\"></a></p>\n\
+             <p><img src=\"https://github.com/foo/bar/raw/HEAD/docs/logo.png\" alt=\"logo.png\"></p>\n"
+        );
+    }
+
+    #[test]
+    fn rst_is_sanitized() {
+        let text =
+            "Hello\n\n.. raw:: html\n\n    <script>alert(1)</script>\n\n<script>x</script>\n";
+        assert_eq!(
+            readme_to_html(text, "README.rst", None, None, None, None),
+            "<p>Hello</p>\n<p>&lt;script&gt;x&lt;/script&gt;</p>\n"
+        );
+    }
+
     #[test]
     fn header_has_tags() {
         let text = "# My crate\n\nHello, world!\n";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(
             result,
             "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>My crate</h1>\n<p>Hello, world!</p>\n"
         );
     }
 
+    #[test]
+    fn readme_headings_match_the_rendered_anchors() {
+        let text = "# My `crate`\n\n## Usage\n\n### Install it\n\n## Usage\n";
+        let headings = readme_headings(text, "README.md");
+        assert_eq!(
+            headings
+                .iter()
+                .map(|heading| (heading.level, &*heading.text, &*heading.anchor))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, "My crate", "my-crate"),
+                (2, "Usage", "usage"),
+                (3, "Install it", "install-it"),
+                (2, "Usage", "usage-1"),
+            ]
+        );
+        for heading in &headings {
+            assert!(markdown_to_html(text, None, None, None, None)
+                .contains(&format!("id=\"user-content-{}\"", heading.anchor)));
+        }
+
+        let text = "Title\n=====\n\nSection\n-------\n\nSection\n-------\n";
+        let headings = readme_headings(text, "README.rst");
+        assert_eq!(
+            headings
+                .iter()
+                .map(|heading| (heading.level, &*heading.anchor))
+                .collect::<Vec<_>>(),
+            vec![(1, "title"), (2, "section"), (2, "section-1")]
+        );
+        assert!(readme_to_html(text, "README.rst", None, None, None, None)
+            .contains("id=\"user-content-section-1\""));
+
+        assert_eq!(readme_headings("# Not a heading", "README.txt"), vec![]);
+    }
+
+    #[test]
+    fn readmes_exceeding_the_limits_are_not_rendered() {
+        let settings = |max_size, timeout| RenderSettings {
+            limits: RenderLimits { max_size, timeout },
+            ..RenderSettings::default()
+        };
+        let render = |text: &str, settings: &RenderSettings| {
+            readme_to_html_limited(text, "README.md", None, None, None, Some(settings))
+        };
+
+        let text = "# Foo\n";
+        assert_eq!(
+            render(text, &settings(6, Duration::from_secs(10))),
+            Ok(readme_to_html(text, "README.md", None, None, None, None))
+        );
+        assert_eq!(
+            render(text, &settings(5, Duration::from_secs(10))),
+            Err(RenderError::TooLarge {
+                size: 6,
+                max_size: 5
+            })
+        );
+
+        let text = "*a* [b](c) `d`\n\n".repeat(20_000);
+        assert_eq!(
+            render(&text, &settings(usize::MAX, Duration::from_nanos(1))),
+            Err(RenderError::Timeout(Duration::from_nanos(1)))
+        );
+    }
+
+    #[test]
+    fn relative_links_are_resolved_against_the_readme_directory() {
+        let text = "[a](docs/a.md) [b](/b.md) ![c](../c.png)";
+        let result = markdown_to_html(
+            text,
+            Some("https://github.com/rust-lang/test"),
+            Some("main"),
+            Some("crates/foo/README.md"),
+            None,
+        );
+        assert!(result
+            .contains("href=\"https://github.com/rust-lang/test/blob/main/crates/foo/docs/a.md\""));
+        assert!(result.contains("href=\"https://github.com/rust-lang/test/blob/main/b.md\""));
+        assert!(result
+            .contains("src=\"https://github.com/rust-lang/test/raw/main/crates/foo/../c.png\""));
+    }
+
+    #[test]
+    fn path_in_repository_joins_the_package_directory() {
+        assert_eq!(
+            path_in_repository(None, "README.md"),
+            Some("README.md".into())
+        );
+        assert_eq!(
+            path_in_repository(Some("crates/foo"), "./README.md"),
+            Some("crates/foo/README.md".into())
+        );
+        assert_eq!(
+            path_in_repository(Some("crates/foo"), "../../README.md"),
+            Some("README.md".into())
+        );
+        assert_eq!(
+            path_in_repository(Some("crates/foo"), "docs\\README.md"),
+            Some("crates/foo/docs/README.md".into())
+        );
+        assert_eq!(path_in_repository(None, "../README.md"), None);
+    }
+
+    #[test]
+    fn render_cache_key_depends_on_all_the_settings() {
+        let key = |text, base_url| render_cache_key(text, "README.md", base_url, None, None, None);
+        let repository = Some("https://github.com/rust-lang/test");
+        assert_eq!(key("# Foo", None), key("# Foo", None));
+        assert_ne!(key("# Foo", None), key("# Bar", None));
+        assert_ne!(key("# Foo", None), key("# Foo", repository));
+        assert_ne!(
+            render_cache_key("a", "README.md", Some("b"), None, None, None),
+            render_cache_key("a", "README.md", None, Some("b"), None, None)
+        );
+        assert_ne!(
+            key("# Foo", None),
+            render_cache_key("# Foo", "README.rst", None, None, None, None)
+        );
+    }
+
+    #[test]
+    fn readme_headings_are_nested() {
+        let heading = |level, text: &str| ReadmeHeading {
+            level,
+            text: text.into(),
+            anchor: text.into(),
+        };
+        let tree = ReadmeHeading::tree(vec![
+            heading(2, "a"),
+            heading(3, "b"),
+            heading(1, "c"),
+            heading(3, "d"),
+        ]);
+        let names = |nodes: &[EncodableReadmeHeading]| {
+            nodes
+                .iter()
+                .map(|node| node.text.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&tree), vec!["a", "c"]);
+        assert_eq!(names(&tree[0].children), vec!["b"]);
+        assert_eq!(names(&tree[1].children), vec!["d"]);
+    }
+
     #[test]
     fn manual_anchor_is_sanitized() {
         let text =
             "<h1><a href=\"#my-crate\" id=\"my-crate\"></a>My crate</h1>\n<p>Hello, world!</p>\n";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(
             result,
             "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>My crate</h1>\n<p>Hello, world!</p>\n"
@@ -504,10 +2648,45 @@ mod tests {
     #[test]
     fn tables_with_rowspan_and_colspan() {
         let text = "<table><tr><th rowspan=\"1\" colspan=\"2\">Target</th></tr></table>\n";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None, None, None);
         assert_eq!(
             result,
             "<table><tbody><tr><th rowspan=\"1\" colspan=\"2\">Target</th></tr></tbody></table>\n"
         );
     }
+
+    #[test]
+    fn html_is_converted_to_text() {
+        let html = "<h1>Example</h1>\n<table><tr><td>foo</td><td>bar &amp; baz</td></tr></table>\n<pre><code>fn  main()</code></pre>\n";
+        assert_eq!(html_to_text(html), "Example foo bar & baz fn main()");
+    }
+
+    #[test]
+    fn changelog_section_is_extracted() {
+        let text = "# Changelog\n\n## [1.1.0] - 2020-09-01\n\n- New\n\n### Fixed\n\n- Bug\n\n## v1.0.0\n\n- Initial\n";
+        assert_eq!(
+            changelog_section(text, "1.1.0"),
+            Some("## [1.1.0] - 2020-09-01\n\n- New\n\n### Fixed\n\n- Bug")
+        );
+        assert_eq!(
+            changelog_section(text, "1.0.0"),
+            Some("## v1.0.0\n\n- Initial")
+        );
+        assert_none!(changelog_section(text, "0.1.0"));
+    }
+
+    #[test]
+    fn changelog_section_requires_exact_version() {
+        let text = "## 11.0.0\n\n## 1.0.0-beta\n\n## 1.0.0.1\n";
+        assert_none!(changelog_section(text, "1.0.0"));
+    }
+
+    #[test]
+    fn changelog_section_ignores_code_blocks() {
+        let text = "## 1.0.0\n\n```\n# 0.9.0\n```\n\n## 0.9.0\n";
+        assert_eq!(
+            changelog_section(text, "1.0.0"),
+            Some("## 1.0.0\n\n```\n# 0.9.0\n```")
+        );
+    }
 }
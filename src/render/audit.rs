@@ -0,0 +1,234 @@
+//! Checks that the sanitizer removes the known ways of running scripts from READMEs, so that
+//! upgrades of comrak and ammonia can be verified before they are deployed.
+//!
+//! The rendered HTML is searched for the elements, attributes and URLs which could run a
+//! script or change the page. `audit_corpus` renders a corpus of known payloads, and the
+//! `audit-readmes` admin task renders the READMEs of the most downloaded crates.
+
+use std::fmt;
+
+use super::{markdown_to_html, tag_attributes, tag_end, RenderSettings};
+
+/// Known payloads, most of them from the XSS filter evasion cheat sheet of OWASP
+pub static PAYLOADS: &[&str] = &[
+    "<script>alert(1)</script>",
+    "<SCRIPT SRC=https://example.com/xss.js></SCRIPT>",
+    "<scr<script>ipt>alert(1)</script>",
+    "<img src=x onerror=alert(1)>",
+    "<img src=\"javascript:alert(1)\">",
+    "<IMG SRC=JaVaScRiPt:alert(1)>",
+    "<img src=\"jav&#x09;ascript:alert(1)\">",
+    "<img src=`javascript:alert(1)`>",
+    "<svg onload=alert(1)>",
+    "<svg><script>alert(1)</script></svg>",
+    "<math><mtext><table><mglyph><style><img src=x onerror=alert(1)>",
+    "<body onload=alert(1)>",
+    "<iframe src=\"javascript:alert(1)\"></iframe>",
+    "<iframe srcdoc=\"<script>alert(1)</script>\"></iframe>",
+    "<object data=\"javascript:alert(1)\"></object>",
+    "<embed src=\"javascript:alert(1)\">",
+    "<form action=\"javascript:alert(1)\"><input type=submit></form>",
+    "<button formaction=\"javascript:alert(1)\">x</button>",
+    "<a href=\"javascript:alert(1)\">x</a>",
+    "<a href=\"  javascript:alert(1)\">x</a>",
+    "<a href=\"vbscript:msgbox(1)\">x</a>",
+    "<a href=\"data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==\">x</a>",
+    "<a href=\"x\" onmouseover=\"alert(1)\">x</a>",
+    "<details open ontoggle=alert(1)>",
+    "<div style=\"background-image: url(javascript:alert(1))\">x</div>",
+    "<style>body { background: url(javascript:alert(1)) }</style>",
+    "<link rel=stylesheet href=\"https://example.com/xss.css\">",
+    "<meta http-equiv=\"refresh\" content=\"0;url=javascript:alert(1)\">",
+    "<base href=\"javascript:alert(1)//\">",
+    "<table background=\"javascript:alert(1)\"><tr><td>x</td></tr></table>",
+    "<video poster=javascript:alert(1)//></video>",
+    "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\">",
+    "<template><script>alert(1)</script></template>",
+    "<!-- --!><script>alert(1)</script> -->",
+    "<![CDATA[<script>alert(1)</script>]]>",
+    "[x](javascript:alert(1))",
+    "[x](JAVASCRIPT:alert(1))",
+    "[x](javascript&colon;alert(1))",
+    "![x](javascript:alert(1))",
+    "[x]: javascript:alert(1)\n\n[x]",
+    "<javascript:alert(1)>",
+    "```\n</code></pre><script>alert(1)</script>\n```",
+    "`<script>alert(1)</script>`",
+    "<p align=\"center\" onclick=\"alert(1)\">x</p>",
+    "<td width=\"expression(alert(1))\">x</td>",
+    "<code class=\"language-rust\" onclick=\"alert(1)\">x</code>",
+];
+
+/// The elements which run scripts, load other documents or change the page
+static FORBIDDEN_ELEMENTS: &[&str] = &[
+    "applet", "base", "body", "embed", "form", "frame", "frameset", "head", "html", "iframe",
+    "link", "meta", "noscript", "object", "script", "style", "svg", "template",
+];
+
+/// The attributes which run scripts or change the page, besides the `on*` event handlers
+static FORBIDDEN_ATTRIBUTES: &[&str] = &["formaction", "srcdoc", "style"];
+
+/// The attributes whose value is a URL
+static URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "data",
+    "href",
+    "poster",
+    "src",
+    "xlink:href",
+];
+
+/// What the sanitizer let through
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Finding {
+    /// An element such as `script`
+    Element(String),
+    /// An event handler, such as `onerror` on an `img`
+    EventHandler { element: String, attribute: String },
+    /// Another attribute running scripts or changing the page, such as `style`
+    Attribute { element: String, attribute: String },
+    /// A URL running a script, such as `javascript:alert(1)`
+    ScriptUrl { element: String, url: String },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Element(element) => write!(f, "`{}` element", element),
+            Finding::EventHandler { element, attribute } => {
+                write!(f, "`{}` event handler on a `{}`", attribute, element)
+            }
+            Finding::Attribute { element, attribute } => {
+                write!(f, "`{}` attribute on a `{}`", attribute, element)
+            }
+            Finding::ScriptUrl { element, url } => {
+                write!(f, "script URL `{}` on a `{}`", url, element)
+            }
+        }
+    }
+}
+
+/// Renders the payloads of the corpus, and returns the ones the sanitizer let something
+/// through, with their findings.
+pub fn audit_corpus(settings: Option<&RenderSettings>) -> Vec<(&'static str, Vec<Finding>)> {
+    PAYLOADS
+        .iter()
+        .map(|payload| (*payload, audit_markdown(payload, settings)))
+        .filter(|(_, findings)| !findings.is_empty())
+        .collect()
+}
+
+/// Renders a Markdown README and returns what the sanitizer let through.
+pub fn audit_markdown(text: &str, settings: Option<&RenderSettings>) -> Vec<Finding> {
+    audit_html(&markdown_to_html(text, None, None, None, settings))
+}
+
+/// Returns what the sanitizer let through in some rendered HTML.
+///
+/// The HTML is expected to be the output of the sanitizer, which escapes the text and always
+/// quotes the values of attributes.
+pub fn audit_html(html: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let tag = &rest[start..];
+        let end = tag_end(tag);
+        audit_tag(&tag[..end], &mut findings);
+        rest = &tag[end..];
+    }
+    findings
+}
+
+fn audit_tag(tag: &str, findings: &mut Vec<Finding>) {
+    let name_end = tag[1..]
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .map_or(tag.len(), |end| end + 1);
+    let element = tag[1..name_end].to_ascii_lowercase();
+    // Closing tags have no attributes, and their opening tag is audited
+    if element.is_empty() || element.starts_with('/') || element.starts_with('!') {
+        return;
+    }
+    if FORBIDDEN_ELEMENTS.contains(&&*element) {
+        findings.push(Finding::Element(element.clone()));
+    }
+
+    for (attribute, value) in tag_attributes(tag) {
+        let attribute = attribute.to_ascii_lowercase();
+        let value = &tag[value];
+        if attribute.starts_with("on") {
+            findings.push(Finding::EventHandler {
+                element: element.clone(),
+                attribute,
+            });
+        } else if FORBIDDEN_ATTRIBUTES.contains(&&*attribute) {
+            findings.push(Finding::Attribute {
+                element: element.clone(),
+                attribute,
+            });
+        } else if URL_ATTRIBUTES.contains(&&*attribute) && is_script_url(value) {
+            findings.push(Finding::ScriptUrl {
+                element: element.clone(),
+                url: value.to_string(),
+            });
+        }
+    }
+}
+
+/// Whether a URL runs a script once followed, ignoring the whitespace and the control
+/// characters browsers ignore.
+fn is_script_url(url: &str) -> bool {
+    let url = htmlescape::decode_html(url).unwrap_or_else(|_| url.to_string());
+    let url = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    url.starts_with("javascript:")
+        || url.starts_with("vbscript:")
+        || url.starts_with("data:text/html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{audit_corpus, audit_html, Finding};
+    use crate::render::RenderSettings;
+
+    #[test]
+    fn the_sanitizer_stops_the_corpus() {
+        assert_eq!(audit_corpus(None), vec![]);
+
+        let settings = RenderSettings {
+            collapse_badges: true,
+            math: true,
+            ..RenderSettings::default()
+        };
+        assert_eq!(audit_corpus(Some(&settings)), vec![]);
+    }
+
+    #[test]
+    fn unsafe_html_is_found() {
+        let html = "<p>&lt;script&gt;</p><script>alert(1)</script>\
+                    <img src=\"x\" onerror=\"alert(1)\" title=\"<a href='javascript:'>\">\
+                    <a href=\" JAVA&#x09;SCRIPT:alert(1)\" style=\"color: red\">x</a>";
+        assert_eq!(
+            audit_html(html),
+            vec![
+                Finding::Element("script".into()),
+                Finding::EventHandler {
+                    element: "img".into(),
+                    attribute: "onerror".into(),
+                },
+                Finding::ScriptUrl {
+                    element: "a".into(),
+                    url: " JAVA&#x09;SCRIPT:alert(1)".into(),
+                },
+                Finding::Attribute {
+                    element: "a".into(),
+                    attribute: "style".into(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Proxies the images of READMEs through [camo](https://github.com/atmos/camo), so that pages
+//! never load images over plain HTTP, nor let third-party hosts track their visitors.
+
+use hmac::{Hmac, Mac, NewMac};
+use sha1::Sha1;
+use url::Url;
+
+use crate::config::Settings;
+
+/// The settings of the camo proxy images are loaded through
+#[derive(Clone, Debug)]
+pub struct Camo {
+    /// The URL of the proxy, such as `https://camo.crates.io/`
+    pub url: String,
+    /// The key shared with the proxy, which refuses URLs that aren't signed with it
+    pub key: String,
+}
+
+impl Camo {
+    /// Reads the settings, if `CAMO_URL` is set.
+    ///
+    /// - `CAMO_URL`: The URL of the proxy.
+    /// - `CAMO_KEY`: The key shared with the proxy.
+    pub fn from_settings(settings: &mut Settings) -> Option<Self> {
+        settings.var("CAMO_URL")?;
+        Some(Camo {
+            url: settings.required_url("CAMO_URL"),
+            key: settings.required("CAMO_KEY"),
+        })
+    }
+
+    /// Returns the URL loading an image through the proxy, in the
+    /// `<proxy>/<HMAC-SHA1 of the URL>/<URL>` format, both being hex-encoded.
+    ///
+    /// Only the `http` and `https` URLs of other hosts are proxied, others are returned as is.
+    pub fn proxy_url(&self, url: &str) -> String {
+        let parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(_) => return url.to_string(),
+        };
+        let proxy_host = Url::parse(&self.url).ok();
+        let proxy_host = proxy_host.as_ref().and_then(Url::host_str);
+        if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str() == proxy_host {
+            return url.to_string();
+        }
+
+        let mut mac =
+            Hmac::<Sha1>::new_varkey(self.key.as_bytes()).expect("HMAC can take key of any size");
+        mac.update(url.as_bytes());
+        let digest = hex::encode(mac.finalize().into_bytes());
+        format!(
+            "{}/{}/{}",
+            self.url.trim_end_matches('/'),
+            digest,
+            hex::encode(url)
+        )
+    }
+
+    /// Returns the URL of an image loaded through the proxy, from the URL returned by
+    /// `proxy_url`.
+    pub fn original_url(&self, url: &str) -> Option<String> {
+        let rest = url
+            .strip_prefix(self.url.trim_end_matches('/'))?
+            .strip_prefix('/')?;
+        let encoded = rest.splitn(2, '/').nth(1)?;
+        String::from_utf8(hex::decode(encoded).ok()?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camo;
+
+    fn camo() -> Camo {
+        Camo {
+            url: "https://camo.example.com/".into(),
+            key: "0x24FEEDFACEDEADBEEFCAFE".into(),
+        }
+    }
+
+    #[test]
+    fn external_images_are_signed() {
+        assert_eq!(
+            camo().proxy_url("http://images.example.com/logo.png"),
+            "https://camo.example.com/\
+             900d22602426563b50627bdd81b2fd4b956c991f/\
+             687474703a2f2f696d616765732e6578616d706c652e636f6d2f6c6f676f2e706e67"
+        );
+    }
+
+    #[test]
+    fn proxied_urls_are_unwrapped() {
+        let camo = camo();
+        let url = "http://images.example.com/logo.png";
+        assert_eq!(
+            camo.original_url(&camo.proxy_url(url)).as_deref(),
+            Some(url)
+        );
+        assert_eq!(camo.original_url(url), None);
+    }
+
+    #[test]
+    fn other_urls_are_kept() {
+        let camo = camo();
+        assert_eq!(camo.proxy_url("data:image/png,abc"), "data:image/png,abc");
+        assert_eq!(camo.proxy_url("img.png"), "img.png");
+        assert_eq!(
+            camo.proxy_url("https://camo.example.com/abc/def"),
+            "https://camo.example.com/abc/def"
+        );
+    }
+}
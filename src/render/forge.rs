@@ -0,0 +1,167 @@
+//! The forges hosting the repositories of crates, whose URL schemes relative links of READMEs
+//! are resolved with.
+
+use crate::config::Settings;
+
+/// The software run by a forge, which determines the URLs of the files of its repositories
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    Gitea,
+    Bitbucket,
+}
+
+impl ForgeKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "github" => Some(ForgeKind::GitHub),
+            "gitlab" => Some(ForgeKind::GitLab),
+            "gitea" => Some(ForgeKind::Gitea),
+            "bitbucket" => Some(ForgeKind::Bitbucket),
+            _ => None,
+        }
+    }
+
+    /// The path of the view rendering the files of a branch, relative to the repository.
+    pub fn blob_path(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "blob/",
+            ForgeKind::GitLab => "-/blob/",
+            ForgeKind::Gitea => "src/branch/",
+            ForgeKind::Bitbucket => "src/",
+        }
+    }
+
+    /// The path of the view serving the raw files of a branch, relative to the repository.
+    pub fn raw_path(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "raw/",
+            ForgeKind::GitLab => "-/raw/",
+            ForgeKind::Gitea => "raw/branch/",
+            ForgeKind::Bitbucket => "raw/",
+        }
+    }
+
+    /// The URL of the API describing a repository of `host`, including its `default_branch`.
+    pub fn api_url(self, host: &str, owner: &str, name: &str) -> Option<String> {
+        match self {
+            ForgeKind::GitHub if host == "github.com" => {
+                Some(format!("https://api.github.com/repos/{}/{}", owner, name))
+            }
+            ForgeKind::GitHub => Some(format!("https://{}/api/v3/repos/{}/{}", host, owner, name)),
+            ForgeKind::GitLab => Some(format!(
+                "https://{}/api/v4/projects/{}%2F{}",
+                host, owner, name
+            )),
+            ForgeKind::Gitea => Some(format!("https://{}/api/v1/repos/{}/{}", host, owner, name)),
+            ForgeKind::Bitbucket => None,
+        }
+    }
+}
+
+/// A self-hosted forge, such as a GitLab or Gitea instance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forge {
+    pub host: String,
+    pub kind: ForgeKind,
+}
+
+impl Forge {
+    /// Reads the forges listed in `FORGE_HOSTS`, see `Forge::parse_list`.
+    pub fn from_settings(settings: &mut Settings) -> Vec<Self> {
+        let value = match settings.var("FORGE_HOSTS") {
+            Some(value) => value,
+            None => return Vec::new(),
+        };
+        Self::parse_list(&value).unwrap_or_else(|entry| {
+            settings.error(
+                "FORGE_HOSTS",
+                &format_args!("has an invalid entry: `{}`", entry),
+            );
+            Vec::new()
+        })
+    }
+
+    /// Parses comma separated `<host>=<kind>` pairs, the kind being one of `github`,
+    /// `gitlab`, `gitea` or `bitbucket`, such as `git.example.com=gitlab,code.example.org=gitea`.
+    ///
+    /// Returns the first invalid entry if any.
+    fn parse_list(value: &str) -> Result<Vec<Self>, &str> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let mut parts = entry.splitn(2, '=');
+                let host = parts.next().unwrap_or_default().trim();
+                let kind = parts.next().map(str::trim).and_then(ForgeKind::from_name);
+                match kind {
+                    Some(kind) if !host.is_empty() => Ok(Forge {
+                        host: host.to_lowercase(),
+                        kind,
+                    }),
+                    _ => Err(entry),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the kind of the forge hosting `host`, among the configured `forges` and the
+    /// hosts known without configuration.
+    ///
+    /// The links to the files of github.com, gitlab.com and bitbucket.org repositories use the
+    /// URLs of GitHub, which the other two redirect.
+    pub fn kind_of(forges: &[Forge], host: &str) -> Option<ForgeKind> {
+        forges
+            .iter()
+            .find(|forge| forge.host.eq_ignore_ascii_case(host))
+            .map(|forge| forge.kind)
+            .or_else(|| match host {
+                "github.com" | "gitlab.com" | "bitbucket.org" => Some(ForgeKind::GitHub),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Forge, ForgeKind};
+
+    #[test]
+    fn forges_are_parsed() {
+        assert_eq!(
+            Forge::parse_list("git.example.com=gitlab, Code.example.org=gitea,"),
+            Ok(vec![
+                Forge {
+                    host: "git.example.com".into(),
+                    kind: ForgeKind::GitLab,
+                },
+                Forge {
+                    host: "code.example.org".into(),
+                    kind: ForgeKind::Gitea,
+                },
+            ])
+        );
+        assert_eq!(Forge::parse_list(""), Ok(vec![]));
+        assert_eq!(
+            Forge::parse_list("a.com=gitea,git.example.com=fossil"),
+            Err("git.example.com=fossil")
+        );
+        assert_eq!(Forge::parse_list("=gitlab"), Err("=gitlab"));
+    }
+
+    #[test]
+    fn configured_forges_win_over_known_hosts() {
+        let forges = [Forge {
+            host: "gitlab.com".into(),
+            kind: ForgeKind::GitLab,
+        }];
+        assert_eq!(
+            Forge::kind_of(&forges, "gitlab.com"),
+            Some(ForgeKind::GitLab)
+        );
+        assert_eq!(Forge::kind_of(&[], "gitlab.com"), Some(ForgeKind::GitHub));
+        assert_eq!(Forge::kind_of(&forges, "example.com"), None);
+    }
+}
@@ -0,0 +1,140 @@
+//! The markup formats READMEs are written in, each rendered by a `Renderer`.
+
+use htmlescape::encode_minimal;
+
+use super::{markdown_headings, markdown_to_html, rst, rst_to_html, ReadmeHeading, RenderSettings};
+
+/// Renders the READMEs written in a markup format.
+///
+/// Supporting another format only takes another implementation, listed in `RENDERERS`.
+pub trait Renderer: Sync {
+    /// Whether a README is written in this format, given its lowercased file name.
+    fn matches(&self, filename: &str) -> bool;
+
+    /// Renders a README to sanitized HTML, see `readme_to_html` for the parameters.
+    fn to_html(
+        &self,
+        text: &str,
+        base_url: Option<&str>,
+        default_branch: Option<&str>,
+        readme_path: Option<&str>,
+        settings: Option<&RenderSettings>,
+    ) -> String;
+
+    /// Returns the headings of a README, see `readme_headings`.
+    fn headings(&self, _text: &str) -> Vec<ReadmeHeading> {
+        Vec::new()
+    }
+}
+
+/// The renderers, by order of precedence
+static RENDERERS: [&dyn Renderer; 3] = [&Markdown, &ReStructuredText, &PlainText];
+
+/// Returns the renderer of a README, chosen by the extension of its file name.
+pub fn renderer_for(filename: &str) -> &'static dyn Renderer {
+    let filename = filename.to_lowercase();
+    RENDERERS
+        .iter()
+        .copied()
+        .find(|renderer| renderer.matches(&filename))
+        .unwrap_or(&PlainText)
+}
+
+/// Any readme with a filename ending in one of these extensions will be rendered as Markdown.
+/// Note we also render a readme as Markdown if _no_ extension is on the filename.
+static MARKDOWN_EXTENSIONS: [&str; 7] = [
+    ".md",
+    ".markdown",
+    ".mdown",
+    ".mdwn",
+    ".mkd",
+    ".mkdn",
+    ".mkdown",
+];
+
+/// Any readme with a filename ending in one of these extensions will be rendered as
+/// reStructuredText.
+static RST_EXTENSIONS: [&str; 2] = [".rst", ".rest"];
+
+/// Renders Markdown with comrak, see `MarkdownRenderer`.
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn matches(&self, filename: &str) -> bool {
+        !filename.contains('.') || MARKDOWN_EXTENSIONS.iter().any(|e| filename.ends_with(e))
+    }
+
+    fn to_html(
+        &self,
+        text: &str,
+        base_url: Option<&str>,
+        default_branch: Option<&str>,
+        readme_path: Option<&str>,
+        settings: Option<&RenderSettings>,
+    ) -> String {
+        markdown_to_html(text, base_url, default_branch, readme_path, settings)
+    }
+
+    fn headings(&self, text: &str) -> Vec<ReadmeHeading> {
+        markdown_headings(text)
+    }
+}
+
+/// Renders reStructuredText, see the `rst` module.
+pub struct ReStructuredText;
+
+impl Renderer for ReStructuredText {
+    fn matches(&self, filename: &str) -> bool {
+        RST_EXTENSIONS.iter().any(|e| filename.ends_with(e))
+    }
+
+    fn to_html(
+        &self,
+        text: &str,
+        base_url: Option<&str>,
+        default_branch: Option<&str>,
+        readme_path: Option<&str>,
+        settings: Option<&RenderSettings>,
+    ) -> String {
+        rst_to_html(text, base_url, default_branch, readme_path, settings)
+    }
+
+    fn headings(&self, text: &str) -> Vec<ReadmeHeading> {
+        rst::render(text).1
+    }
+}
+
+/// Renders the other READMEs as escaped text, keeping their line breaks.
+pub struct PlainText;
+
+impl Renderer for PlainText {
+    fn matches(&self, _filename: &str) -> bool {
+        true
+    }
+
+    fn to_html(
+        &self,
+        text: &str,
+        _base_url: Option<&str>,
+        _default_branch: Option<&str>,
+        _readme_path: Option<&str>,
+        _settings: Option<&RenderSettings>,
+    ) -> String {
+        encode_minimal(text).replace("\n", "<br>\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::renderer_for;
+
+    #[test]
+    fn renderers_are_chosen_by_extension() {
+        let render = |filename| renderer_for(filename).to_html("*a*", None, None, None, None);
+        assert_eq!(render("README"), "<p><em>a</em></p>\n");
+        assert_eq!(render("README.MD"), "<p><em>a</em></p>\n");
+        assert_eq!(render("readme.mkdown"), "<p><em>a</em></p>\n");
+        assert_eq!(render("README.rst"), "<p><em>a</em></p>\n");
+        assert_eq!(render("README.txt"), "*a*");
+    }
+}
@@ -0,0 +1,139 @@
+//! Finds the LaTeX math of READMEs, such as `$e^{i\pi} + 1 = 0$`, and wraps it in elements with
+//! the `math` class. The frontend shows their TeX source as is, and the clients of the API can
+//! typeset them, with KaTeX for instance.
+//!
+//! Like pandoc, an inline formula can't start after nor end before a space, and can't be
+//! followed by a digit, so that prices such as `$5 and $10` stay text. Only the formulas
+//! within a single run of text are found: the ones Markdown parsed as emphasis are kept as is.
+
+use htmlescape::encode_minimal;
+
+/// A part of a text, which is either text or math
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Segment<'a> {
+    Text(&'a str),
+    /// Math within a line, between single dollars
+    Inline(&'a str),
+    /// Math on its own line, between double dollars
+    Display(&'a str),
+}
+
+impl Segment<'_> {
+    /// Returns the HTML of the segment, the text being escaped.
+    pub(super) fn html(&self) -> String {
+        match self {
+            Segment::Text(text) => encode_minimal(text),
+            Segment::Inline(math) => wrap("span", "math math-inline", math),
+            Segment::Display(math) => wrap("span", "math math-display", math),
+        }
+    }
+}
+
+/// Returns the HTML of a `math` code block, typeset on its own line.
+pub(super) fn block_html(math: &str) -> String {
+    format!("{}\n", wrap("div", "math math-display", math.trim_end()))
+}
+
+fn wrap(tag: &str, class: &str, math: &str) -> String {
+    format!(
+        "<{} class=\"{}\">{}</{}>",
+        tag,
+        class,
+        encode_minimal(math),
+        tag
+    )
+}
+
+/// Splits a text into text and math, returning an empty list if there's no math.
+pub(super) fn split(text: &str) -> Vec<Segment<'_>> {
+    let bytes = text.as_bytes();
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'$' {
+            i += 1;
+            continue;
+        }
+        let found = if bytes.get(i + 1) == Some(&b'$') {
+            display_end(text, i + 2).map(|end| (Segment::Display(&text[i + 2..end]), end + 2))
+        } else {
+            inline_end(text, i + 1).map(|end| (Segment::Inline(&text[i + 1..end]), end + 1))
+        };
+        match found {
+            Some((math, next)) => {
+                if text_start < i {
+                    segments.push(Segment::Text(&text[text_start..i]));
+                }
+                segments.push(math);
+                i = next;
+                text_start = next;
+            }
+            // A `$$` which isn't closed is text, not two openings
+            None if bytes.get(i + 1) == Some(&b'$') => i += 2,
+            None => i += 1,
+        }
+    }
+    if segments.is_empty() {
+        return segments;
+    }
+    if text_start < text.len() {
+        segments.push(Segment::Text(&text[text_start..]));
+    }
+    segments
+}
+
+/// Returns the position of the `$$` closing the display math starting at `start`.
+fn display_end(text: &str, start: usize) -> Option<usize> {
+    let end = start + text[start..].find("$$")?;
+    if text[start..end].trim().is_empty() {
+        None
+    } else {
+        Some(end)
+    }
+}
+
+/// Returns the position of the `$` closing the inline math starting at `start`.
+fn inline_end(text: &str, start: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if bytes.get(start).map_or(true, u8::is_ascii_whitespace) {
+        return None;
+    }
+    let end = start + text[start..].find('$')?;
+    let closes = !bytes[end - 1].is_ascii_whitespace()
+        && !bytes.get(end + 1).map_or(false, u8::is_ascii_digit);
+    if closes {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split, Segment::*};
+
+    #[test]
+    fn math_is_split_from_text() {
+        assert_eq!(
+            split("Euler: $e^{i\\pi} + 1 = 0$, and $$\\sum_k k$$."),
+            vec![
+                Text("Euler: "),
+                Inline("e^{i\\pi} + 1 = 0"),
+                Text(", and "),
+                Display("\\sum_k k"),
+                Text("."),
+            ]
+        );
+        assert_eq!(split("$x$"), vec![Inline("x")]);
+    }
+
+    #[test]
+    fn dollars_of_text_are_kept() {
+        assert_eq!(split("It costs $5 and $10."), vec![]);
+        assert_eq!(split("$ 5 $"), vec![]);
+        assert_eq!(split("$x $"), vec![]);
+        assert_eq!(split("$$ $$ and"), vec![]);
+        assert_eq!(split("no math"), vec![]);
+    }
+}
@@ -0,0 +1,593 @@
+//! Converts the subset of reStructuredText commonly found in READMEs to HTML.
+//!
+//! Section titles, paragraphs, lists, definition lists, block quotes, literal and code blocks,
+//! images, admonitions, hyperlinks and substitutions are supported. Other directives and
+//! comments are dropped, and tables are kept as preformatted text. The HTML isn't sanitized,
+//! which is left to the caller.
+
+use htmlescape::{encode_attribute, encode_minimal};
+use std::collections::HashMap;
+use std::mem;
+
+use super::{Anchorizer, ReadmeHeading};
+
+/// The characters which can adorn section titles
+const ADORNMENTS: &str = "=-~^\"'`#*+_:.";
+
+/// Returns the HTML of a document along with its section titles.
+pub(super) fn render(text: &str) -> (String, Vec<ReadmeHeading>) {
+    let text = text.replace('\t', "        ");
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+
+    let mut renderer = Renderer {
+        references: References::collect(&lines),
+        title_styles: Vec::new(),
+        anchorizer: Anchorizer::default(),
+        headings: Vec::new(),
+        out: String::new(),
+    };
+    renderer.blocks(&lines);
+    (renderer.out, renderer.headings)
+}
+
+/// The hyperlink targets and substitutions defined anywhere in a document, which can be
+/// referenced before their definition.
+struct References {
+    targets: HashMap<String, String>,
+    substitutions: HashMap<String, String>,
+}
+
+impl References {
+    fn collect(lines: &[&str]) -> Self {
+        let mut targets = HashMap::new();
+        let mut substitutions = HashMap::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            let content = match line.trim_start().strip_prefix(".. ") {
+                Some(content) => content.trim(),
+                None => continue,
+            };
+
+            if let Some(target) = content.strip_prefix('_') {
+                // `.. _name: url` or ``.. _`name with colons`: url``
+                let (name, url) = match target.strip_prefix('`') {
+                    Some(quoted) => match quoted.find("`:") {
+                        Some(end) => (&quoted[..end], &quoted[end + 2..]),
+                        None => continue,
+                    },
+                    None => match target.find(": ") {
+                        Some(end) => (&target[..end], &target[end + 2..]),
+                        None => continue,
+                    },
+                };
+                let url = url.trim();
+                if !url.is_empty() {
+                    targets.insert(normalize_name(name), url.to_string());
+                }
+            } else if let Some(definition) = content.strip_prefix('|') {
+                // `.. |name| image:: url` or `.. |name| replace:: text`
+                let end = match definition.find('|') {
+                    Some(end) => end,
+                    None => continue,
+                };
+                let name = &definition[..end];
+                let definition = definition[end + 1..].trim();
+                if let Some(url) = definition.strip_prefix("image::") {
+                    let (options, _) = split_options(&indented_block(lines, i + 1, 0).0);
+                    substitutions.insert(name.to_string(), image_html(url.trim(), &options));
+                } else if let Some(text) = definition.strip_prefix("replace::") {
+                    substitutions.insert(name.to_string(), encode_minimal(text.trim()));
+                }
+            }
+        }
+
+        Self {
+            targets,
+            substitutions,
+        }
+    }
+
+    fn target(&self, name: &str) -> Option<&str> {
+        self.targets.get(&normalize_name(name)).map(String::as_str)
+    }
+}
+
+struct Renderer {
+    references: References,
+    /// The adornment styles of the section titles in order of appearance, which gives
+    /// their level
+    title_styles: Vec<(char, bool)>,
+    anchorizer: Anchorizer,
+    headings: Vec<ReadmeHeading>,
+    out: String,
+}
+
+impl Renderer {
+    fn blocks(&mut self, lines: &[&str]) {
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.is_empty() {
+                i += 1;
+                continue;
+            }
+
+            if indent(line) > 0 {
+                let (block, end) = indented_block(lines, i, 0);
+                self.out += "<blockquote>\n";
+                self.blocks(&block);
+                self.out += "</blockquote>\n";
+                i = end;
+                continue;
+            }
+
+            if let Some(c) = adornment(line) {
+                if i + 2 < lines.len()
+                    && !lines[i + 1].trim().is_empty()
+                    && adornment(lines[i + 2]) == Some(c)
+                {
+                    self.title(lines[i + 1].trim(), (c, true));
+                    i += 3;
+                    continue;
+                }
+                if line.len() >= 4 && lines.get(i + 1).map_or(true, |next| next.is_empty()) {
+                    self.out += "<hr>\n";
+                    i += 1;
+                    continue;
+                }
+            }
+
+            if let Some(underline) = lines.get(i + 1) {
+                if let Some(c) = adornment(underline) {
+                    if underline.chars().count() >= line.chars().count().min(3) {
+                        self.title(line, (c, false));
+                        i += 2;
+                        continue;
+                    }
+                }
+            }
+
+            if line == ".." || line.starts_with(".. ") {
+                i = self.directive(lines, i);
+            } else if bullet(line).is_some() {
+                i = self.list(lines, i, false);
+            } else if enumerator(line).is_some() {
+                i = self.list(lines, i, true);
+            } else if line.starts_with("+-") || line.starts_with("+=") {
+                let end = lines[i..]
+                    .iter()
+                    .position(|line| line.is_empty())
+                    .map_or(lines.len(), |len| i + len);
+                self.preformatted(&lines[i..end], None);
+                i = end;
+            } else {
+                i = self.paragraph(lines, i);
+            }
+        }
+    }
+
+    fn title(&mut self, text: &str, style: (char, bool)) {
+        let level = match self.title_styles.iter().position(|s| *s == style) {
+            Some(position) => position + 1,
+            None => {
+                self.title_styles.push(style);
+                self.title_styles.len()
+            }
+        };
+        let level = level.min(6);
+
+        // The anchors are generated from the text without markup, like in Markdown
+        let plain_text = text
+            .chars()
+            .filter(|&c| c != '`' && c != '*' && c != '|')
+            .collect::<String>();
+        let anchor = self.anchorizer.anchorize(&plain_text);
+        let html = self.inline(text);
+        self.out += &format!(
+            "<h{0}><a href=\"#{1}\" id=\"{1}\"></a>{2}</h{0}>\n",
+            level,
+            encode_attribute(&anchor),
+            html
+        );
+        self.headings.push(ReadmeHeading {
+            level: level as u8,
+            text: plain_text,
+            anchor,
+        });
+    }
+
+    /// Renders a paragraph, along with the literal block it introduces if it ends with `::`,
+    /// or a definition list item if it is a single line followed by an indented block.
+    fn paragraph(&mut self, lines: &[&str], start: usize) -> usize {
+        let mut end = start;
+        while end < lines.len() && !lines[end].is_empty() && indent(lines[end]) == 0 {
+            end += 1;
+        }
+
+        if end == start + 1 && lines.get(end).map_or(false, |line| indent(line) > 0) {
+            let (definition, after) = indented_block(lines, end, 0);
+            let term = self.inline(lines[start]);
+            let definition = self.nested(&definition);
+            self.out += &format!(
+                "<dl>\n<dt>{}</dt>\n<dd>\n{}</dd>\n</dl>\n",
+                term, definition
+            );
+            return after;
+        }
+
+        let text = lines[start..end].join(" ");
+        if !text.ends_with("::") {
+            let text = self.inline(&text);
+            self.out += &format!("<p>{}</p>\n", text);
+            return end;
+        }
+
+        // `Paragraph::` is rendered as `Paragraph:`, and `Paragraph ::` as `Paragraph`
+        let text = text[..text.len() - 1].to_string();
+        let text = match text.strip_suffix(" :") {
+            Some(text) => text,
+            None if text == ":" => "",
+            None => &text,
+        };
+        if !text.is_empty() {
+            let text = self.inline(text);
+            self.out += &format!("<p>{}</p>\n", text);
+        }
+
+        let mut next = end;
+        while next < lines.len() && lines[next].is_empty() {
+            next += 1;
+        }
+        if next < lines.len() && indent(lines[next]) > 0 {
+            let (block, after) = indented_block(lines, next, 0);
+            self.preformatted(&block, None);
+            return after;
+        }
+        end
+    }
+
+    fn list(&mut self, lines: &[&str], start: usize, ordered: bool) -> usize {
+        let marker: fn(&str) -> Option<usize> = if ordered { enumerator } else { bullet };
+        let tag = if ordered { "ol" } else { "ul" };
+
+        self.out += &format!("<{}>\n", tag);
+        let mut i = start;
+        while let Some(width) = lines.get(i).and_then(|line| marker(line)) {
+            let mut end = i + 1;
+            while end < lines.len() && (lines[end].is_empty() || indent(lines[end]) >= width) {
+                end += 1;
+            }
+            while end > i + 1 && lines[end - 1].is_empty() {
+                end -= 1;
+            }
+
+            let mut item = vec![&lines[i][width..]];
+            item.extend(lines[i + 1..end].iter().map(|line| {
+                if line.is_empty() {
+                    *line
+                } else {
+                    &line[width..]
+                }
+            }));
+            let html = self.nested(&item);
+            // Items made of a single paragraph are rendered without it
+            let html = match html
+                .strip_prefix("<p>")
+                .and_then(|html| html.strip_suffix("</p>\n"))
+            {
+                Some(text) if !text.contains("<p>") => text.to_string(),
+                _ => html,
+            };
+            self.out += &format!("<li>{}</li>\n", html);
+
+            i = end;
+            while lines.get(i).map_or(false, |line| line.is_empty()) {
+                i += 1;
+            }
+        }
+        self.out += &format!("</{}>\n", tag);
+        i
+    }
+
+    fn directive(&mut self, lines: &[&str], start: usize) -> usize {
+        let (body, end) = indented_block(lines, start + 1, 0);
+        let content = lines[start][2..].trim();
+        let (name, argument) = match content.find("::") {
+            // Substitution definitions are collected beforehand
+            Some(_) if content.starts_with('|') => return end,
+            Some(position) => (content[..position].trim(), content[position + 2..].trim()),
+            // Comments and hyperlink targets
+            None => return end,
+        };
+        let (options, body) = split_options(&body);
+
+        match name {
+            "code" | "code-block" | "sourcecode" => {
+                let language = argument.split_whitespace().next();
+                self.preformatted(&body, language);
+            }
+            "image" | "figure" => {
+                self.out += &format!("<p>{}</p>\n", image_html(argument, &options));
+                self.blocks(&body);
+            }
+            "note" | "tip" | "hint" | "important" | "attention" | "caution" | "warning"
+            | "danger" | "error" | "admonition" => {
+                let title = if name == "admonition" {
+                    self.inline(argument)
+                } else {
+                    let mut title = name.to_string();
+                    title[..1].make_ascii_uppercase();
+                    title
+                };
+                self.out += &format!("<blockquote>\n<p><strong>{}</strong></p>\n", title);
+                if name != "admonition" && !argument.is_empty() {
+                    let text = self.inline(argument);
+                    self.out += &format!("<p>{}</p>\n", text);
+                }
+                self.blocks(&body);
+                self.out += "</blockquote>\n";
+            }
+            _ => {}
+        }
+        end
+    }
+
+    fn preformatted(&mut self, lines: &[&str], language: Option<&str>) {
+        let class = language
+            .map(|language| format!(" class=\"language-{}\"", encode_attribute(language)))
+            .unwrap_or_default();
+        self.out += &format!(
+            "<pre><code{}>{}\n</code></pre>\n",
+            class,
+            encode_minimal(&lines.join("\n"))
+        );
+    }
+
+    /// Renders the blocks of a list item or a definition to a string.
+    fn nested(&mut self, lines: &[&str]) -> String {
+        let outer = mem::take(&mut self.out);
+        self.blocks(lines);
+        mem::replace(&mut self.out, outer)
+    }
+
+    fn inline(&self, text: &str) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        let mut previous: Option<char> = None;
+        while let Some(c) = rest.chars().next() {
+            // Inline markup can't start in the middle of a word
+            if previous.map_or(true, |previous| !previous.is_alphanumeric()) {
+                if let Some((html, len)) = self.markup(rest) {
+                    out += &html;
+                    previous = rest[..len].chars().last();
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            out += &encode_minimal(c.encode_utf8(&mut [0; 4]));
+            previous = Some(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        out
+    }
+
+    /// Renders the inline markup at the start of `text`, returning its HTML and length.
+    fn markup(&self, text: &str) -> Option<(String, usize)> {
+        if let Some(after) = text.strip_prefix("``") {
+            let end = find_end(after, "``")?;
+            let html = format!("<code>{}</code>", encode_minimal(&after[..end]));
+            return Some((html, end + 4));
+        }
+        if let Some(after) = text.strip_prefix("**") {
+            let end = find_end(after, "**")?;
+            let html = format!("<strong>{}</strong>", encode_minimal(&after[..end]));
+            return Some((html, end + 4));
+        }
+        if let Some(after) = text.strip_prefix('*') {
+            let end = find_end(after, "*")?;
+            let html = format!("<em>{}</em>", encode_minimal(&after[..end]));
+            return Some((html, end + 2));
+        }
+        if let Some(after) = text.strip_prefix('`') {
+            let end = find_end(after, "`")?;
+            let content = &after[..end];
+            let underscores = count_underscores(&after[end + 1..]);
+            if underscores == 0 {
+                let html = format!("<code>{}</code>", encode_minimal(content));
+                return Some((html, end + 2));
+            }
+            let (label, url) = match embedded_url(content) {
+                Some((label, url)) => (label, Some(url)),
+                None => (content, self.references.target(content)),
+            };
+            return Some((link_html(url, encode_minimal(label)), end + 2 + underscores));
+        }
+        if let Some(after) = text.strip_prefix('|') {
+            let end = find_end(after, "|")?;
+            let name = &after[..end];
+            let html = self.references.substitutions.get(name)?.clone();
+            let underscores = count_underscores(&after[end + 1..]);
+            let html = if underscores > 0 {
+                link_html(self.references.target(name), html)
+            } else {
+                html
+            };
+            return Some((html, end + 2 + underscores));
+        }
+        if text.starts_with("http://") || text.starts_with("https://") {
+            let end = text
+                .find(|c: char| c.is_whitespace() || c == '<' || c == '>' || c == '"')
+                .unwrap_or_else(|| text.len());
+            let url = text[..end].trim_end_matches(|c| ".,;:!?)'".contains(c));
+            return Some((link_html(Some(url), encode_minimal(url)), url.len()));
+        }
+
+        // `name_` references
+        let word_len = text
+            .find(|c: char| !(c.is_alphanumeric() || c == '-'))
+            .unwrap_or_else(|| text.len());
+        let after = text[word_len..].strip_prefix('_')?;
+        if word_len == 0 || after.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+        let url = self.references.target(&text[..word_len])?;
+        let html = link_html(Some(url), encode_minimal(&text[..word_len]));
+        Some((html, word_len + 1))
+    }
+}
+
+fn link_html(url: Option<&str>, label: String) -> String {
+    match url {
+        Some(url) => format!("<a href=\"{}\">{}</a>", encode_attribute(url), label),
+        None => label,
+    }
+}
+
+fn image_html(url: &str, options: &HashMap<&str, &str>) -> String {
+    let html = format!(
+        "<img src=\"{}\" alt=\"{}\">",
+        encode_attribute(url),
+        encode_attribute(options.get("alt").copied().unwrap_or_default())
+    );
+    link_html(options.get("target").copied(), html)
+}
+
+/// Splits the leading `:name: value` options of the body of a directive from its content.
+fn split_options<'a>(body: &[&'a str]) -> (HashMap<&'a str, &'a str>, Vec<&'a str>) {
+    let mut options = HashMap::new();
+    let mut lines = body.iter().copied().peekable();
+    while let Some(option) = lines
+        .peek()
+        .copied()
+        .and_then(|line| line.strip_prefix(':'))
+    {
+        if let Some(end) = option.find(':') {
+            options.insert(&option[..end], option[end + 1..].trim());
+        }
+        lines.next();
+    }
+    let content = lines.skip_while(|line| line.is_empty()).collect();
+    (options, content)
+}
+
+/// Returns the dedented lines indented by more than `min_indent` starting at `start`, along
+/// with the index of the line following them.
+fn indented_block<'a>(lines: &[&'a str], start: usize, min_indent: usize) -> (Vec<&'a str>, usize) {
+    let mut end = start;
+    while end < lines.len() && (lines[end].is_empty() || indent(lines[end]) > min_indent) {
+        end += 1;
+    }
+    while end > start && lines[end - 1].is_empty() {
+        end -= 1;
+    }
+
+    let block = &lines[start.min(end)..end];
+    let dedent = block
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| indent(line))
+        .min()
+        .unwrap_or(0);
+    let block = block
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                *line
+            } else {
+                &line[dedent..]
+            }
+        })
+        .collect();
+    (block, end)
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Returns the character of a line which can be the overline or underline of a section title.
+fn adornment(line: &str) -> Option<char> {
+    let c = line.chars().next()?;
+    if line.len() >= 2 && ADORNMENTS.contains(c) && line.chars().all(|other| other == c) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+/// Returns the width of the bullet of a list item starting a line, spaces included.
+fn bullet(line: &str) -> Option<usize> {
+    let c = line.chars().next()?;
+    if !"*-+•".contains(c) {
+        return None;
+    }
+    marker_width(line, c.len_utf8())
+}
+
+/// Returns the width of the enumerator of a list item starting a line, spaces included.
+fn enumerator(line: &str) -> Option<usize> {
+    let digits = line
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| line.len());
+    let len = if digits > 0 {
+        digits
+    } else if line.starts_with('#') {
+        1
+    } else {
+        return None;
+    };
+    if !line[len..].starts_with(|c: char| c == '.' || c == ')') {
+        return None;
+    }
+    marker_width(line, len + 1)
+}
+
+fn marker_width(line: &str, marker_len: usize) -> Option<usize> {
+    let spaces = indent(&line[marker_len..]);
+    if spaces == 0 || line.len() == marker_len + spaces {
+        return None;
+    }
+    Some(marker_len + spaces)
+}
+
+/// Finds the end-string of some inline markup, which must not follow whitespace nor be
+/// followed by a letter or a digit, and can't immediately follow the start-string.
+fn find_end(text: &str, end: &str) -> Option<usize> {
+    if text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(position) = text[from..].find(end) {
+        let position = from + position;
+        let before = text[..position].chars().last();
+        let after = text[position + end.len()..].chars().next();
+        if before.map_or(false, |c| !c.is_whitespace())
+            && !after.map_or(false, char::is_alphanumeric)
+        {
+            return Some(position);
+        }
+        from = position + 1;
+    }
+    None
+}
+
+fn count_underscores(text: &str) -> usize {
+    text.chars().take_while(|&c| c == '_').count().min(2)
+}
+
+/// Returns the label and the URL of a reference like `` `label <url>`_ ``
+fn embedded_url(content: &str) -> Option<(&str, &str)> {
+    let inner = content.strip_suffix('>')?;
+    let start = inner.rfind('<')?;
+    let label = inner[..start].trim();
+    let url = &inner[start + 1..];
+    Some((if label.is_empty() { url } else { label }, url))
+}
+
+/// Reference names are case and whitespace insensitive
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -34,12 +34,22 @@ pub fn build_router(app: &App) -> R404 {
     api_router.get("/versions/:version_id", C(version::deprecated::show_by_id));
 
     // Routes used by the frontend
+    // These aren't under `/crates/` where they would shadow the crates with the same name
+    api_router.get("/trending_crates", C(krate::metadata::trending));
+    api_router.get("/crate_suggestions", C(krate::search::suggest));
+    api_router.get("/crate_comparison", C(krate::compare::compare));
+    api_router.post("/crates/audit", C(krate::audit::audit));
+    api_router.post("/crates/batch", C(krate::batch::batch));
     api_router.get("/crates/:crate_id", C(krate::metadata::show));
     api_router.get("/crates/:crate_id/:version", C(version::metadata::show));
     api_router.get(
         "/crates/:crate_id/:version/readme",
         C(krate::metadata::readme),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/readme/headings",
+        C(krate::metadata::readme_headings),
+    );
     api_router.get(
         "/crates/:crate_id/:version/dependencies",
         C(version::metadata::dependencies),
@@ -52,15 +62,66 @@ pub fn build_router(app: &App) -> R404 {
         "/crates/:crate_id/:version/authors",
         C(version::metadata::authors),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/changelog",
+        C(version::metadata::changelog),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/files",
+        C(version::metadata::files),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/features",
+        C(version::metadata::features),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/rebuilds",
+        C(version::rebuilds::summary),
+    );
+    api_router.put(
+        "/crates/:crate_id/:version/rebuilds",
+        C(version::rebuilds::report),
+    );
     api_router.get(
         "/crates/:crate_id/downloads",
         C(krate::downloads::downloads),
     );
+    api_router.get("/crates/:crate_id/diff", C(version::diff::diff));
     api_router.get("/crates/:crate_id/versions", C(krate::metadata::versions));
+    api_router.get("/crates/:crate_id/full", C(krate::metadata::full));
+    api_router.get("/crates/:crate_id/quality", C(krate::metadata::quality));
+    api_router.get("/crates/:crate_id/similar", C(krate::similar::similar));
     api_router.put("/crates/:crate_id/follow", C(krate::follow::follow));
     api_router.delete("/crates/:crate_id/follow", C(krate::follow::unfollow));
     api_router.get("/crates/:crate_id/following", C(krate::follow::following));
+    api_router.put(
+        "/crates/:crate_id/follow/email_notifications",
+        C(krate::follow::update_email_notifications),
+    );
+    api_router.post("/crates/:crate_id/pageview", C(krate::pageviews::record));
+    api_router.get(
+        "/crates/:crate_id/pageviews",
+        C(krate::pageviews::pageviews),
+    );
+    api_router.put(
+        "/crates/:crate_id/deprecate",
+        C(krate::deprecation::deprecate),
+    );
+    api_router.put("/crates/:crate_id/transfer", C(krate::transfer::request));
+    api_router.delete("/crates/:crate_id/transfer", C(krate::transfer::cancel));
+    api_router.get(
+        "/crates/:crate_id/publish_policy",
+        C(krate::publish_policy::show),
+    );
+    api_router.put(
+        "/crates/:crate_id/publish_policy",
+        C(krate::publish_policy::update),
+    );
     api_router.get("/crates/:crate_id/owner_team", C(krate::owners::owner_team));
+    api_router.get(
+        "/crates/:crate_id/owner_team/:team_id/members",
+        C(krate::owners::owner_team_members),
+    );
     api_router.get("/crates/:crate_id/owner_user", C(krate::owners::owner_user));
     api_router.get(
         "/crates/:crate_id/reverse_dependencies",
@@ -68,18 +129,22 @@ pub fn build_router(app: &App) -> R404 {
     );
     api_router.get("/keywords", C(keyword::index));
     api_router.get("/keywords/:keyword_id", C(keyword::show));
+    api_router.get("/keywords/:keyword_id/stats", C(keyword::stats));
     api_router.get("/categories", C(category::index));
     api_router.get("/categories/:category_id", C(category::show));
+    api_router.get("/categories/:category_id/stats", C(category::stats));
     api_router.get("/category_slugs", C(category::slugs));
     api_router.get("/users/:user_id", C(user::other::show));
     api_router.put("/users/:user_id", C(user::me::update_user));
     api_router.get("/users/:user_id/stats", C(user::other::stats));
+    api_router.get("/users/:user_id/crates", C(user::other::crates));
     api_router.get("/teams/:team_id", C(team::show_team));
     api_router.get("/me", C(user::me::me));
     api_router.get("/me/updates", C(user::me::updates));
     api_router.get("/me/tokens", C(token::list));
     api_router.put("/me/tokens", C(token::new));
     api_router.delete("/me/tokens/:id", C(token::revoke));
+    api_router.put("/readme/preview", C(readme::preview));
     api_router.get(
         "/me/crate_owner_invitations",
         C(crate_owner_invitation::list),
@@ -92,17 +157,59 @@ pub fn build_router(app: &App) -> R404 {
         "/me/crate_owner_invitations/accept/:token",
         C(crate_owner_invitation::handle_invite_with_token),
     );
+    api_router.put(
+        "/me/crate_ownership_transfers/accept/:token",
+        C(krate::transfer::accept),
+    );
     api_router.put(
         "/me/email_notifications",
         C(user::me::update_email_notifications),
     );
     api_router.get("/summary", C(krate::metadata::summary));
+    api_router.get(
+        "/most_depended_upon",
+        C(krate::metadata::most_depended_upon),
+    );
     api_router.put("/confirm/:email_token", C(user::me::confirm_user_email));
     api_router.put(
         "/users/:user_id/resend",
         C(user::me::regenerate_token_and_send),
     );
     api_router.get("/site_metadata", C(site_metadata::show_deployed_sha));
+    api_router.get("/events", C(registry_event::list));
+
+    // Routes used by the admins of the instance
+    api_router.get("/admin/feature_flags", C(feature_flag::list));
+    api_router.put("/admin/feature_flags/:name", C(feature_flag::update));
+    api_router.get("/admin/email_domain_rules", C(email_domain_rule::list));
+    api_router.put(
+        "/admin/email_domain_rules/:domain",
+        C(email_domain_rule::update),
+    );
+    api_router.delete(
+        "/admin/email_domain_rules/:domain",
+        C(email_domain_rule::delete),
+    );
+    api_router.get("/admin/quarantined_versions", C(quarantine::list));
+    api_router.put(
+        "/admin/quarantined_versions/:version_id/approve",
+        C(quarantine::approve),
+    );
+    api_router.delete(
+        "/admin/quarantined_versions/:version_id",
+        C(quarantine::reject),
+    );
+    api_router.get("/admin/readme_rerenders", C(readme_rerender::list));
+    api_router.post("/admin/readme_rerenders", C(readme_rerender::create));
+    api_router.get("/admin/reserved_crate_names", C(reserved_crate_name::list));
+    api_router.put(
+        "/admin/reserved_crate_names/:name",
+        C(reserved_crate_name::update),
+    );
+    api_router.delete(
+        "/admin/reserved_crate_names/:name",
+        C(reserved_crate_name::delete),
+    );
     let api_router = Arc::new(R404(api_router));
 
     let mut router = RouteBuilder::new();
@@ -122,6 +229,7 @@ pub fn build_router(app: &App) -> R404 {
         C(user::session::authorize),
     );
     router.delete("/api/private/session", C(user::session::logout));
+    router.get("/api/private/ready", C(site_metadata::readiness));
 
     // Only serve the local checkout of the git index in development mode.
     // In production, for crates.io, cargo gets the index from
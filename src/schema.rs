@@ -176,6 +176,170 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `category_stats` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    category_stats (category_id, date) {
+        /// The `category_id` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category_id -> Int4,
+        /// The `date` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `crates_cnt` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `downloads` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_advisories` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_advisories (id) {
+        /// The `id` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `identifier` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        identifier -> Varchar,
+        /// The `title` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        title -> Varchar,
+        /// The `url` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Nullable<Varchar>,
+        /// The `affected_versions` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        affected_versions -> Array<Text>,
+        /// The `created_at` column of the `crate_advisories` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_link_checks` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_link_checks (crate_id, field) {
+        /// The `crate_id` column of the `crate_link_checks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `field` column of the `crate_link_checks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        field -> Varchar,
+        /// The `url` column of the `crate_link_checks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `status` column of the `crate_link_checks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        status -> Varchar,
+        /// The `checked_at` column of the `crate_link_checks` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checked_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_notable_dependents` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_notable_dependents (crate_id, dependent_id) {
+        /// The `crate_id` column of the `crate_notable_dependents` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `dependent_id` column of the `crate_notable_dependents` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        dependent_id -> Int4,
+        /// The `downloads` column of the `crate_notable_dependents` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+        /// The `computed_at` column of the `crate_notable_dependents` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -286,82 +450,69 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates` table.
+    /// Representation of the `crate_ownership_transfers` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates (id) {
-        /// The `id` column of the `crates` table.
+    crate_ownership_transfers (crate_id) {
+        /// The `crate_id` column of the `crate_ownership_transfers` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `name` column of the `crates` table.
-        ///
-        /// Its SQL type is `Varchar`.
-        ///
-        /// (Automatically generated by Diesel.)
-        name -> Varchar,
-        /// The `updated_at` column of the `crates` table.
-        ///
-        /// Its SQL type is `Timestamp`.
-        ///
-        /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `created_at` column of the `crates` table.
-        ///
-        /// Its SQL type is `Timestamp`.
-        ///
-        /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `downloads` column of the `crates` table.
+        crate_id -> Int4,
+        /// The `requested_by_user_id` column of the `crate_ownership_transfers` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        downloads -> Int4,
-        /// The `description` column of the `crates` table.
-        ///
-        /// Its SQL type is `Nullable<Varchar>`.
-        ///
-        /// (Automatically generated by Diesel.)
-        description -> Nullable<Varchar>,
-        /// The `homepage` column of the `crates` table.
+        requested_by_user_id -> Int4,
+        /// The `target_user_id` column of the `crate_ownership_transfers` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        homepage -> Nullable<Varchar>,
-        /// The `documentation` column of the `crates` table.
+        target_user_id -> Int4,
+        /// The `token` column of the `crate_ownership_transfers` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        documentation -> Nullable<Varchar>,
-        /// The `readme` column of the `crates` table.
+        token -> Text,
+        /// The `created_at` column of the `crate_ownership_transfers` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        readme -> Nullable<Varchar>,
-        /// The `textsearchable_index_col` column of the `crates` table.
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_pageview_visitors` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_pageview_visitors (crate_id, date, visitor) {
+        /// The `crate_id` column of the `crate_pageview_visitors` table.
         ///
-        /// Its SQL type is `Tsvector`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        textsearchable_index_col -> Tsvector,
-        /// The `repository` column of the `crates` table.
+        crate_id -> Int4,
+        /// The `date` column of the `crate_pageview_visitors` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Date`.
         ///
         /// (Automatically generated by Diesel.)
-        repository -> Nullable<Varchar>,
-        /// The `max_upload_size` column of the `crates` table.
+        date -> Date,
+        /// The `visitor` column of the `crate_pageview_visitors` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Bytea`.
         ///
         /// (Automatically generated by Diesel.)
-        max_upload_size -> Nullable<Int4>,
+        visitor -> Bytea,
     }
 }
 
@@ -369,22 +520,28 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_categories` table.
+    /// Representation of the `crate_pageviews` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_categories (crate_id, category_id) {
-        /// The `crate_id` column of the `crates_categories` table.
+    crate_pageviews (crate_id, date) {
+        /// The `crate_id` column of the `crate_pageviews` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `category_id` column of the `crates_categories` table.
+        /// The `date` column of the `crate_pageviews` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `views` column of the `crate_pageviews` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        category_id -> Int4,
+        views -> Int4,
     }
 }
 
@@ -392,22 +549,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_keywords` table.
+    /// Representation of the `crate_publish_policies` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_keywords (crate_id, keyword_id) {
-        /// The `crate_id` column of the `crates_keywords` table.
+    crate_publish_policies (crate_id) {
+        /// The `crate_id` column of the `crate_publish_policies` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `keyword_id` column of the `crates_keywords` table.
+        /// The `allowed_token_ids` column of the `crate_publish_policies` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Array<Int4>>`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword_id -> Int4,
+        allowed_token_ids -> Nullable<Array<Int4>>,
+        /// The `require_user_owner` column of the `crate_publish_policies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        require_user_owner -> Bool,
+        /// The `updated_at` column of the `crate_publish_policies` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
     }
 }
 
@@ -415,64 +584,64 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `dependencies` table.
+    /// Representation of the `crate_quality_scores` table.
     ///
     /// (Automatically generated by Diesel.)
-    dependencies (id) {
-        /// The `id` column of the `dependencies` table.
+    crate_quality_scores (crate_id) {
+        /// The `crate_id` column of the `crate_quality_scores` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `version_id` column of the `dependencies` table.
+        crate_id -> Int4,
+        /// The `formula_version` column of the `crate_quality_scores` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `crate_id` column of the `dependencies` table.
+        formula_version -> Int4,
+        /// The `docs` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `req` column of the `dependencies` table.
+        docs -> Float8,
+        /// The `recent_release` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        req -> Varchar,
-        /// The `optional` column of the `dependencies` table.
+        recent_release -> Float8,
+        /// The `tests` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        optional -> Bool,
-        /// The `default_features` column of the `dependencies` table.
+        tests -> Float8,
+        /// The `msrv` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        default_features -> Bool,
-        /// The `features` column of the `dependencies` table.
+        msrv -> Float8,
+        /// The `not_yanked` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Array<Text>`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        features -> Array<Text>,
-        /// The `target` column of the `dependencies` table.
+        not_yanked -> Float8,
+        /// The `total` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        target -> Nullable<Varchar>,
-        /// The `kind` column of the `dependencies` table.
+        total -> Float8,
+        /// The `computed_at` column of the `crate_quality_scores` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        kind -> Int4,
+        computed_at -> Timestamp,
     }
 }
 
@@ -480,46 +649,719 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `emails` table.
+    /// Representation of the `crate_trending_scores` table.
     ///
     /// (Automatically generated by Diesel.)
-    emails (id) {
-        /// The `id` column of the `emails` table.
+    crate_trending_scores (crate_id) {
+        /// The `crate_id` column of the `crate_trending_scores` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `user_id` column of the `emails` table.
+        crate_id -> Int4,
+        /// The `score` column of the `crate_trending_scores` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Float8`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `email` column of the `emails` table.
+        score -> Float8,
+        /// The `recent_downloads` column of the `crate_trending_scores` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        recent_downloads -> Int8,
+        /// The `baseline_downloads` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        baseline_downloads -> Int8,
+        /// The `new_dependents` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_dependents -> Int4,
+        /// The `computed_at` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates (id) {
+        /// The `id` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `updated_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `created_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `downloads` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int4,
+        /// The `description` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `homepage` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        homepage -> Nullable<Varchar>,
+        /// The `documentation` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        documentation -> Nullable<Varchar>,
+        /// The `readme` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme -> Nullable<Varchar>,
+        /// The `textsearchable_index_col` column of the `crates` table.
+        ///
+        /// Its SQL type is `Tsvector`.
+        ///
+        /// (Automatically generated by Diesel.)
+        textsearchable_index_col -> Tsvector,
+        /// The `repository` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository -> Nullable<Varchar>,
+        /// The `max_upload_size` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_upload_size -> Nullable<Int4>,
+        /// The `dependents_count` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        dependents_count -> Int4,
+        /// The `has_unyanked_versions` column of the `crates` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        has_unyanked_versions -> Bool,
+        /// The `readme_textsearchable_index_col` column of the `crates` table.
+        ///
+        /// Its SQL type is `Tsvector`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme_textsearchable_index_col -> Tsvector,
+        /// The `deprecated` column of the `crates` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deprecated -> Bool,
+        /// The `superseded_by` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        superseded_by -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_categories` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_categories (crate_id, category_id) {
+        /// The `crate_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `category_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_keywords (crate_id, keyword_id) {
+        /// The `crate_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `keyword_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependencies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependencies (id) {
+        /// The `id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crate_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `req` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        req -> Varchar,
+        /// The `optional` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        optional -> Bool,
+        /// The `default_features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        default_features -> Bool,
+        /// The `features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        features -> Array<Text>,
+        /// The `target` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target -> Nullable<Varchar>,
+        /// The `kind` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+        /// The `artifact` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Array<Text>>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        artifact -> Nullable<Array<Text>>,
+        /// The `bindep_target` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        bindep_target -> Nullable<Varchar>,
+        /// The `lib` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        lib -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependency_yank_notifications` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependency_yank_notifications (version_id, crate_id, user_id) {
+        /// The `version_id` column of the `dependency_yank_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crate_id` column of the `dependency_yank_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `user_id` column of the `dependency_yank_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `created_at` column of the `dependency_yank_notifications` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `email_domain_rules` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    email_domain_rules (domain) {
+        /// The `domain` column of the `email_domain_rules` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        domain -> Varchar,
+        /// The `blocked` column of the `email_domain_rules` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        blocked -> Bool,
+        /// The `updated_by` column of the `email_domain_rules` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_by -> Nullable<Int4>,
+        /// The `updated_at` column of the `email_domain_rules` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `emails` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    emails (id) {
+        /// The `id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `email` column of the `emails` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email -> Varchar,
+        /// The `verified` column of the `emails` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified -> Bool,
+        /// The `token` column of the `emails` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Text,
+        /// The `token_generated_at` column of the `emails` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token_generated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `feature_flags` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    feature_flags (name) {
+        /// The `name` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `enabled` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        enabled -> Bool,
+        /// The `updated_by` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_by -> Nullable<Int4>,
+        /// The `updated_at` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `follows` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    follows (user_id, crate_id) {
+        /// The `user_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `email_notifications` column of the `follows` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email_notifications -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keyword_stats` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keyword_stats (keyword_id, date) {
+        /// The `keyword_id` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+        /// The `date` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `crates_cnt` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `downloads` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keywords (id) {
+        /// The `id` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `keyword` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword -> Text,
+        /// The `crates_cnt` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `created_at` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `localized_readmes` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    localized_readmes (version_id, language) {
+        /// The `version_id` column of the `localized_readmes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `language` column of the `localized_readmes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        language -> Varchar,
+        /// The `file_name` column of the `localized_readmes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        file_name -> Varchar,
+        /// The `rendered_at` column of the `localized_readmes` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `metadata` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    metadata (total_downloads) {
+        /// The `total_downloads` column of the `metadata` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_downloads -> Int8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `pageview_salts` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    pageview_salts (date) {
+        /// The `date` column of the `pageview_salts` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `salt` column of the `pageview_salts` table.
+        ///
+        /// Its SQL type is `Bytea`.
+        ///
+        /// (Automatically generated by Diesel.)
+        salt -> Bytea,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_limit_buckets` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_limit_buckets (user_id, action) {
+        /// The `user_id` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `tokens` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        tokens -> Int4,
+        /// The `last_refill` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_refill -> Timestamp,
+        /// The `action` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        action -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_rate_overrides` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_rate_overrides (user_id) {
+        /// The `user_id` column of the `publish_rate_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        email -> Varchar,
-        /// The `verified` column of the `emails` table.
+        user_id -> Int4,
+        /// The `burst` column of the `publish_rate_overrides` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        verified -> Bool,
-        /// The `token` column of the `emails` table.
+        burst -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `quarantined_versions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    quarantined_versions (version_id) {
+        /// The `version_id` column of the `quarantined_versions` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        token -> Text,
-        /// The `token_generated_at` column of the `emails` table.
+        version_id -> Int4,
+        /// The `spam_score` column of the `quarantined_versions` table.
         ///
-        /// Its SQL type is `Nullable<Timestamp>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        token_generated_at -> Nullable<Timestamp>,
+        spam_score -> Int4,
+        /// The `reasons` column of the `quarantined_versions` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reasons -> Array<Text>,
+        /// The `index_entry` column of the `quarantined_versions` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        index_entry -> Jsonb,
+        /// The `renderings` column of the `quarantined_versions` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        renderings -> Jsonb,
+        /// The `created_at` column of the `quarantined_versions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -527,22 +1369,58 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `follows` table.
+    /// Representation of the `readme_rerenders` table.
     ///
     /// (Automatically generated by Diesel.)
-    follows (user_id, crate_id) {
-        /// The `user_id` column of the `follows` table.
+    readme_rerenders (id) {
+        /// The `id` column of the `readme_rerenders` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `crate_id` column of the `follows` table.
+        id -> Int4,
+        /// The `requested_by` column of the `readme_rerenders` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
+        requested_by -> Int4,
+        /// The `total` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total -> Int4,
+        /// The `rendered` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered -> Int4,
+        /// The `skipped` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        skipped -> Int4,
+        /// The `last_version_id` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_version_id -> Int4,
+        /// The `created_at` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `finished_at` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        finished_at -> Nullable<Timestamp>,
     }
 }
 
@@ -550,34 +1428,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `keywords` table.
+    /// Representation of the `readme_renderings` table.
     ///
     /// (Automatically generated by Diesel.)
-    keywords (id) {
-        /// The `id` column of the `keywords` table.
+    readme_renderings (version_id) {
+        /// The `version_id` column of the `readme_renderings` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `keyword` column of the `keywords` table.
+        version_id -> Int4,
+        /// The `rendered_at` column of the `readme_renderings` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword -> Text,
-        /// The `crates_cnt` column of the `keywords` table.
+        rendered_at -> Timestamp,
+        /// The `headings` column of the `readme_renderings` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Jsonb>`.
         ///
         /// (Automatically generated by Diesel.)
-        crates_cnt -> Int4,
-        /// The `created_at` column of the `keywords` table.
+        headings -> Nullable<Jsonb>,
+        /// The `has_source` column of the `readme_renderings` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Bool`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
+        has_source -> Bool,
+    }
+}
+
+table! {
+    /// Representation of the `recent_crate_downloads` view.
+    ///
+    /// This data represents the downloads in the last 90 days.
+    /// This view does not contain realtime data.
+    /// It is refreshed by the `update-downloads` script.
+    recent_crate_downloads (crate_id) {
+        /// The `crate_id` column of the `recent_crate_downloads` view.
+        ///
+        /// Its SQL type is `Integer`.
+        crate_id -> Integer,
+        /// The `downloads` column of the `recent_crate_downloads` table.
+        ///
+        /// Its SQL type is `BigInt`.
+        downloads -> BigInt,
     }
 }
 
@@ -585,16 +1481,58 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `metadata` table.
+    /// Representation of the `registry_events` table.
     ///
     /// (Automatically generated by Diesel.)
-    metadata (total_downloads) {
-        /// The `total_downloads` column of the `metadata` table.
+    registry_events (id) {
+        /// The `id` column of the `registry_events` table.
         ///
         /// Its SQL type is `Int8`.
         ///
         /// (Automatically generated by Diesel.)
-        total_downloads -> Int8,
+        id -> Int8,
+        /// The `kind` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+        /// The `crate_name` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Varchar,
+        /// The `version` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version -> Nullable<Varchar>,
+        /// The `owner_kind` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_kind -> Nullable<Int4>,
+        /// The `owner_login` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_login -> Nullable<Varchar>,
+        /// The `owner_github_id` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_github_id -> Nullable<Int4>,
+        /// The `created_at` column of the `registry_events` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -602,28 +1540,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `publish_limit_buckets` table.
+    /// Representation of the `rendered_readmes` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_limit_buckets (user_id) {
-        /// The `user_id` column of the `publish_limit_buckets` table.
+    rendered_readmes (sha256) {
+        /// The `sha256` column of the `rendered_readmes` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Bytea`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `tokens` column of the `publish_limit_buckets` table.
+        sha256 -> Bytea,
+        /// The `renderer_version` column of the `rendered_readmes` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        tokens -> Int4,
-        /// The `last_refill` column of the `publish_limit_buckets` table.
+        renderer_version -> Int4,
+        /// The `html` column of the `rendered_readmes` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        html -> Text,
+        /// The `rendered_at` column of the `rendered_readmes` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        last_refill -> Timestamp,
+        rendered_at -> Timestamp,
     }
 }
 
@@ -631,22 +1575,28 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `publish_rate_overrides` table.
+    /// Representation of the `replayed_registry_events` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_rate_overrides (user_id) {
-        /// The `user_id` column of the `publish_rate_overrides` table.
+    replayed_registry_events (upstream_url, event_id) {
+        /// The `upstream_url` column of the `replayed_registry_events` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `burst` column of the `publish_rate_overrides` table.
+        upstream_url -> Varchar,
+        /// The `event_id` column of the `replayed_registry_events` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Int8`.
         ///
         /// (Automatically generated by Diesel.)
-        burst -> Int4,
+        event_id -> Int8,
+        /// The `replayed_at` column of the `replayed_registry_events` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        replayed_at -> Timestamp,
     }
 }
 
@@ -654,40 +1604,57 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `readme_renderings` table.
+    /// Representation of the `replication_cursors` table.
     ///
     /// (Automatically generated by Diesel.)
-    readme_renderings (version_id) {
-        /// The `version_id` column of the `readme_renderings` table.
+    replication_cursors (upstream_url) {
+        /// The `upstream_url` column of the `replication_cursors` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `rendered_at` column of the `readme_renderings` table.
+        upstream_url -> Varchar,
+        /// The `last_event_id` column of the `replication_cursors` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_event_id -> Int8,
+        /// The `updated_at` column of the `replication_cursors` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        rendered_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
 table! {
-    /// Representation of the `recent_crate_downloads` view.
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `repository_default_branches` table.
     ///
-    /// This data represents the downloads in the last 90 days.
-    /// This view does not contain realtime data.
-    /// It is refreshed by the `update-downloads` script.
-    recent_crate_downloads (crate_id) {
-        /// The `crate_id` column of the `recent_crate_downloads` view.
+    /// (Automatically generated by Diesel.)
+    repository_default_branches (repository) {
+        /// The `repository` column of the `repository_default_branches` table.
         ///
-        /// Its SQL type is `Integer`.
-        crate_id -> Integer,
-        /// The `downloads` column of the `recent_crate_downloads` table.
+        /// Its SQL type is `Varchar`.
         ///
-        /// Its SQL type is `BigInt`.
-        downloads -> BigInt,
+        /// (Automatically generated by Diesel.)
+        repository -> Varchar,
+        /// The `branch` column of the `repository_default_branches` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        branch -> Varchar,
+        /// The `fetched_at` column of the `repository_default_branches` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fetched_at -> Timestamp,
     }
 }
 
@@ -701,10 +1668,63 @@ table! {
     reserved_crate_names (name) {
         /// The `name` column of the `reserved_crate_names` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Text,
+        /// The `reason` column of the `reserved_crate_names` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Nullable<Text>,
+        /// The `reserved_by` column of the `reserved_crate_names` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reserved_by -> Nullable<Int4>,
+        /// The `created_at` column of the `reserved_crate_names` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `team_memberships` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    team_memberships (team_id, user_id) {
+        /// The `team_id` column of the `team_memberships` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        team_id -> Int4,
+        /// The `user_id` column of the `team_memberships` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `active` column of the `team_memberships` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        active -> Bool,
+        /// The `checked_at` column of the `team_memberships` table.
+        ///
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Text,
+        checked_at -> Timestamp,
     }
 }
 
@@ -755,6 +1775,70 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `upstream_versions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    upstream_versions (version_id) {
+        /// The `version_id` column of the `upstream_versions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crate_file_cached_at` column of the `upstream_versions` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_file_cached_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `user_identities` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    user_identities (provider, subject) {
+        /// The `provider` column of the `user_identities` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        provider -> Varchar,
+        /// The `subject` column of the `user_identities` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        subject -> Varchar,
+        /// The `user_id` column of the `user_identities` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `groups` column of the `user_identities` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        groups -> Array<Text>,
+        /// The `updated_at` column of the `user_identities` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -811,6 +1895,12 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         account_lock_until -> Nullable<Timestamp>,
+        /// The `is_admin` column of the `users` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        is_admin -> Bool,
     }
 }
 
@@ -843,6 +1933,88 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_changelogs` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_changelogs (version_id) {
+        /// The `version_id` column of the `version_changelogs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `file_name` column of the `version_changelogs` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        file_name -> Varchar,
+        /// The `html` column of the `version_changelogs` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        html -> Text,
+        /// The `section_html` column of the `version_changelogs` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        section_html -> Nullable<Text>,
+        /// The `rendered_at` column of the `version_changelogs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_diffs` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_diffs (from_version_id, to_version_id) {
+        /// The `from_version_id` column of the `version_diffs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        from_version_id -> Int4,
+        /// The `to_version_id` column of the `version_diffs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        to_version_id -> Int4,
+        /// The `diff` column of the `version_diffs` table.
+        ///
+        /// Its SQL type is `Nullable<Jsonb>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        diff -> Nullable<Jsonb>,
+        /// The `requested_at` column of the `version_diffs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requested_at -> Timestamp,
+        /// The `computed_at` column of the `version_diffs` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -884,6 +2056,41 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_files` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_files (version_id, path) {
+        /// The `version_id` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `path` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        path -> Varchar,
+        /// The `size` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        size -> Int8,
+        /// The `sha256` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Bytea`.
+        ///
+        /// (Automatically generated by Diesel.)
+        sha256 -> Bytea,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -931,6 +2138,106 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_rebuilds` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_rebuilds (id) {
+        /// The `id` column of the `version_rebuilds` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `version_rebuilds` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `user_id` column of the `version_rebuilds` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `checksum` column of the `version_rebuilds` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Varchar,
+        /// The `matches` column of the `version_rebuilds` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        matches -> Bool,
+        /// The `created_at` column of the `version_rebuilds` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_yank_events` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_yank_events (id) {
+        /// The `id` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `user_id` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `api_token_id` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        api_token_id -> Nullable<Int4>,
+        /// The `yanked` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        yanked -> Bool,
+        /// The `reason` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Nullable<Varchar>,
+        /// The `created_at` column of the `version_yank_events` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -1005,6 +2312,60 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         published_by -> Nullable<Int4>,
+        /// The `vcs_sha1` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        vcs_sha1 -> Nullable<Varchar>,
+        /// The `vcs_dirty` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Bool>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        vcs_dirty -> Nullable<Bool>,
+        /// The `checksum` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Nullable<Varchar>,
+        /// The `rust_version` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rust_version -> Nullable<Varchar>,
+        /// The `metadata_targets` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Array<Text>>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        metadata_targets -> Nullable<Array<Text>>,
+        /// The `license_changed` column of the `versions` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        license_changed -> Bool,
+        /// The `previous_license` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        previous_license -> Nullable<Varchar>,
+        /// The `feature_docs` column of the `versions` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        feature_docs -> Jsonb,
+        /// The `uncompressed_size` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Int8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        uncompressed_size -> Nullable<Int8>,
     }
 }
 
@@ -1033,28 +2394,58 @@ table! {
 
 joinable!(api_tokens -> users (user_id));
 joinable!(badges -> crates (crate_id));
+joinable!(category_stats -> categories (category_id));
+joinable!(crate_advisories -> crates (crate_id));
+joinable!(crate_link_checks -> crates (crate_id));
 joinable!(crate_owner_invitations -> crates (crate_id));
 joinable!(crate_owners -> crates (crate_id));
 joinable!(crate_owners -> teams (owner_id));
 joinable!(crate_owners -> users (owner_id));
+joinable!(crate_ownership_transfers -> crates (crate_id));
+joinable!(crate_pageview_visitors -> crates (crate_id));
+joinable!(crate_pageviews -> crates (crate_id));
+joinable!(crate_publish_policies -> crates (crate_id));
+joinable!(crate_quality_scores -> crates (crate_id));
+joinable!(crate_trending_scores -> crates (crate_id));
 joinable!(crates_categories -> categories (category_id));
 joinable!(crates_categories -> crates (crate_id));
 joinable!(crates_keywords -> crates (crate_id));
 joinable!(crates_keywords -> keywords (keyword_id));
 joinable!(dependencies -> crates (crate_id));
 joinable!(dependencies -> versions (version_id));
+joinable!(dependency_yank_notifications -> crates (crate_id));
+joinable!(dependency_yank_notifications -> users (user_id));
+joinable!(dependency_yank_notifications -> versions (version_id));
+joinable!(email_domain_rules -> users (updated_by));
 joinable!(emails -> users (user_id));
+joinable!(feature_flags -> users (updated_by));
 joinable!(follows -> crates (crate_id));
 joinable!(follows -> users (user_id));
+joinable!(keyword_stats -> keywords (keyword_id));
+joinable!(localized_readmes -> versions (version_id));
 joinable!(publish_limit_buckets -> users (user_id));
 joinable!(publish_rate_overrides -> users (user_id));
+joinable!(quarantined_versions -> versions (version_id));
+joinable!(readme_rerenders -> users (requested_by));
 joinable!(readme_renderings -> versions (version_id));
 joinable!(recent_crate_downloads -> crates (crate_id));
+joinable!(reserved_crate_names -> users (reserved_by));
+joinable!(team_memberships -> teams (team_id));
+joinable!(team_memberships -> users (user_id));
+joinable!(upstream_versions -> versions (version_id));
+joinable!(user_identities -> users (user_id));
 joinable!(version_authors -> versions (version_id));
+joinable!(version_changelogs -> versions (version_id));
 joinable!(version_downloads -> versions (version_id));
+joinable!(version_files -> versions (version_id));
 joinable!(version_owner_actions -> api_tokens (api_token_id));
 joinable!(version_owner_actions -> users (user_id));
 joinable!(version_owner_actions -> versions (version_id));
+joinable!(version_rebuilds -> users (user_id));
+joinable!(version_rebuilds -> versions (version_id));
+joinable!(version_yank_events -> api_tokens (api_token_id));
+joinable!(version_yank_events -> users (user_id));
+joinable!(version_yank_events -> versions (version_id));
 joinable!(versions -> crates (crate_id));
 joinable!(versions -> users (published_by));
 joinable!(versions_published_by -> versions (version_id));
@@ -1064,26 +2455,57 @@ allow_tables_to_appear_in_same_query!(
     background_jobs,
     badges,
     categories,
+    category_stats,
+    crate_advisories,
+    crate_link_checks,
+    crate_notable_dependents,
     crate_owner_invitations,
     crate_owners,
+    crate_ownership_transfers,
+    crate_pageview_visitors,
+    crate_pageviews,
+    crate_publish_policies,
+    crate_quality_scores,
+    crate_trending_scores,
     crates,
     crates_categories,
     crates_keywords,
     dependencies,
+    dependency_yank_notifications,
+    email_domain_rules,
     emails,
+    feature_flags,
     follows,
+    keyword_stats,
     keywords,
+    localized_readmes,
     metadata,
+    pageview_salts,
     publish_limit_buckets,
     publish_rate_overrides,
+    quarantined_versions,
+    readme_rerenders,
     readme_renderings,
     recent_crate_downloads,
+    registry_events,
+    rendered_readmes,
+    replayed_registry_events,
+    replication_cursors,
+    repository_default_branches,
     reserved_crate_names,
+    team_memberships,
     teams,
+    upstream_versions,
+    user_identities,
     users,
     version_authors,
+    version_changelogs,
+    version_diffs,
     version_downloads,
+    version_files,
     version_owner_actions,
+    version_rebuilds,
+    version_yank_events,
     versions,
     versions_published_by,
 );
@@ -0,0 +1,250 @@
+//! The backends the text of searches is matched with
+//!
+//! By default, the crates are searched in Postgres, with the full-text index of their name,
+//! keywords, description and README, which triggers keep up to date. Large deployments can
+//! instead search an external engine, currently Meilisearch when `MEILISEARCH_URL` is set, so
+//! that searches scale independently of the primary database.
+//!
+//! The documents of an external engine are indexed by the `index_crate` background job, which
+//! is enqueued when a version is published or yanked, and when a crate is deleted. Only the text
+//! of queries is matched by the engine: the other filters and the facets of searches still run
+//! in the database, on the IDs of the crates it found.
+//!
+//! Errors of an external engine are logged, and the searches then fall back to Postgres.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use diesel::prelude::*;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::Method;
+
+use crate::config::SearchBackendConfig;
+use crate::models::{Crate, CrateKeyword};
+use crate::schema::{crates, keywords};
+
+/// How many crates an external engine returns at most for a query
+///
+/// The searches report that their `total` is capped when an engine returns this many crates.
+pub const MAX_MATCHES: usize = 1000;
+
+/// How long connecting to an external engine may take, so that searches quickly fall back to
+/// the database when it is down
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a search of an external engine may take
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the other requests to an external engine may take, from the background jobs
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub trait SearchBackend: Send + Sync {
+    /// Returns the crates matching the text of a query.
+    fn search(&self, query: &str) -> Result<TextMatches>;
+
+    /// Whether the documents of the crates must be indexed by the `index_crate` job.
+    fn needs_indexing(&self) -> bool;
+
+    /// Adds the document of a crate, or replaces it.
+    fn index_crate(&self, document: &CrateDocument) -> Result<()>;
+
+    fn remove_crate(&self, crate_id: i32) -> Result<()>;
+}
+
+/// The crates matching the text of a query
+#[derive(Debug, PartialEq, Eq)]
+pub enum TextMatches {
+    /// The crates are matched in the database, see `controllers::krate::search`
+    Database,
+    /// The IDs of the crates an external engine found, the most relevant first
+    Ranked(Vec<i32>),
+}
+
+/// Creates the backend of the configuration.
+pub fn from_config(config: &SearchBackendConfig) -> Arc<dyn SearchBackend> {
+    match config {
+        SearchBackendConfig::Postgres => Arc::new(PostgresBackend),
+        SearchBackendConfig::Meilisearch {
+            url,
+            api_key,
+            index,
+        } => Arc::new(MeilisearchBackend::new(url, api_key.clone(), index)),
+    }
+}
+
+/// What an external engine indexes of a crate
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CrateDocument {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub keywords: Vec<String>,
+    pub downloads: i32,
+}
+
+impl CrateDocument {
+    /// Loads the document of a crate, or `None` if the crate was deleted.
+    pub fn load(conn: &PgConnection, crate_id: i32) -> QueryResult<Option<Self>> {
+        let krate: Crate = match Crate::all()
+            .filter(crates::id.eq(crate_id))
+            .first(conn)
+            .optional()?
+        {
+            Some(krate) => krate,
+            None => return Ok(None),
+        };
+        let keywords = CrateKeyword::belonging_to(&krate)
+            .inner_join(keywords::table)
+            .select(keywords::keyword)
+            .load(conn)?;
+        Ok(Some(CrateDocument {
+            id: krate.id,
+            name: krate.name,
+            description: krate.description,
+            keywords,
+            downloads: krate.downloads,
+        }))
+    }
+}
+
+/// Matches the text of a query with a backend, falling back to the database on errors.
+pub fn search(backend: &dyn SearchBackend, query: &str) -> TextMatches {
+    backend.search(query).unwrap_or_else(|e| {
+        eprintln!("Search backend error: {}", e);
+        TextMatches::Database
+    })
+}
+
+/// The full-text search of Postgres, whose index is kept up to date by triggers
+#[derive(Debug, Default)]
+pub struct PostgresBackend;
+
+impl SearchBackend for PostgresBackend {
+    fn search(&self, _query: &str) -> Result<TextMatches> {
+        Ok(TextMatches::Database)
+    }
+
+    fn needs_indexing(&self) -> bool {
+        false
+    }
+
+    fn index_crate(&self, _document: &CrateDocument) -> Result<()> {
+        Ok(())
+    }
+
+    fn remove_crate(&self, _crate_id: i32) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A Meilisearch server, whose index is fed by the `index_crate` job
+///
+/// The index should rank the crates by their `downloads` after the relevance of the text, and
+/// is created with the `id` of the crates as its primary key by the first indexed document.
+#[derive(Debug)]
+pub struct MeilisearchBackend {
+    client: Client,
+    url: String,
+    api_key: Option<String>,
+    index: String,
+}
+
+impl MeilisearchBackend {
+    pub fn new(url: &str, api_key: Option<String>, index: &str) -> Self {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("the Meilisearch client can always be built");
+        MeilisearchBackend {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            api_key,
+            index: index.to_string(),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/indexes/{}/{}", self.url, self.index, path);
+        let request = self.client.request(method, &url);
+        match &self.api_key {
+            Some(api_key) => request.header("X-Meili-API-Key", api_key),
+            None => request,
+        }
+    }
+}
+
+impl SearchBackend for MeilisearchBackend {
+    fn search(&self, query: &str) -> Result<TextMatches> {
+        let response = self
+            .request(Method::POST, "search")
+            .json(&json!({
+                "q": query,
+                "limit": MAX_MATCHES,
+                "attributesToRetrieve": ["id"],
+            }))
+            .timeout(SEARCH_TIMEOUT)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(parse_hits(response)?)
+    }
+
+    fn needs_indexing(&self) -> bool {
+        true
+    }
+
+    fn index_crate(&self, document: &CrateDocument) -> Result<()> {
+        self.request(Method::POST, "documents")
+            .json(&[document])
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn remove_crate(&self, crate_id: i32) -> Result<()> {
+        self.request(Method::DELETE, &format!("documents/{}", crate_id))
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Reads the IDs of the crates found by a Meilisearch search.
+fn parse_hits(response: serde_json::Value) -> serde_json::Result<TextMatches> {
+    #[derive(Deserialize)]
+    struct Hit {
+        id: i32,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        hits: Vec<Hit>,
+    }
+
+    let response: Response = serde_json::from_value(response)?;
+    Ok(TextMatches::Ranked(
+        response.hits.into_iter().map(|hit| hit.id).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_hits, TextMatches};
+
+    #[test]
+    fn meilisearch_hits_are_ranked() {
+        let response = json!({
+            "hits": [{ "id": 3 }, { "id": 1 }, { "id": 2 }],
+            "offset": 0,
+            "limit": 1000,
+            "nbHits": 3,
+            "query": "serde",
+        });
+        assert_eq!(
+            parse_hits(response).unwrap(),
+            TextMatches::Ranked(vec![3, 1, 2])
+        );
+        assert!(parse_hits(json!({ "message": "Index crates not found" })).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! The HTTP server in front of the conduit handlers
+//!
+//! Requests are served by hyper, over HTTP/1.1 with keep-alive and over HTTP/2, and handed to
+//! the blocking conduit handlers by `conduit_hyper`. Connections are closed when:
+//!
+//! - no request is in flight and nothing was written for `ServerConfig::idle_timeout`,
+//! - the client stops sending the body of a request for `ServerConfig::read_timeout`,
+//! - the client stops receiving a response for `ServerConfig::write_timeout`.
+
+use std::error::Error;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use conduit::Handler;
+use conduit_hyper::{BlockingHandler, Service as ConduitService};
+use futures_util::stream::Stream;
+use hyper::body::{Body, Bytes};
+use hyper::server::accept::Accept;
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use hyper::service::{make_service_fn, service_fn, Service};
+use hyper::Request;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::{self, Delay, Instant};
+
+use crate::config::ServerConfig;
+
+/// Binds the address and returns a future serving the requests with the handler until the
+/// `shutdown` future completes, after which the requests in flight are finished.
+///
+/// Must be called from within a tokio runtime.
+pub fn serve<H, F>(
+    handler: H,
+    addr: &SocketAddr,
+    config: ServerConfig,
+    shutdown: F,
+) -> hyper::Result<impl Future<Output = hyper::Result<()>>>
+where
+    H: Handler,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let handler = Arc::new(BlockingHandler::new(handler));
+    let read_timeout = config.read_timeout;
+    let make_service = make_service_fn(move |connection: &Connection<AddrStream>| {
+        let state = Arc::clone(&connection.state);
+        let service = ConduitService::from_blocking(handler.clone(), connection.remote_addr());
+        async move {
+            service.map(|mut service| {
+                service_fn(move |request: Request<Body>| {
+                    let in_flight = InFlight::start(&state);
+                    let request =
+                        request.map(|body| Body::wrap_stream(ReadTimeout::new(body, read_timeout)));
+                    let response = service.call(request);
+                    async move {
+                        let response = response.await;
+                        drop(in_flight);
+                        response
+                    }
+                })
+            })
+        }
+    });
+
+    let incoming = Incoming {
+        inner: AddrIncoming::bind(addr)?,
+        config,
+    };
+    let server = hyper::Server::builder(incoming)
+        .http1_keepalive(config.keep_alive)
+        .http1_only(!config.http2)
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown);
+    Ok(server)
+}
+
+/// Accepts the connections, adding the timeouts to them
+struct Incoming {
+    inner: AddrIncoming,
+    config: ServerConfig,
+}
+
+impl Accept for Incoming {
+    type Conn = Connection<AddrStream>;
+    type Error = io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_accept(cx) {
+            Poll::Ready(Some(Ok(stream))) => {
+                Poll::Ready(Some(Ok(Connection::new(stream, this.config))))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The state of a connection shared with its requests
+#[derive(Debug)]
+struct ConnectionState {
+    in_flight: AtomicUsize,
+    /// The last time a request finished or a response was written to
+    idle_since: Mutex<Instant>,
+}
+
+impl ConnectionState {
+    fn touch(&self) {
+        *self.idle_since.lock().unwrap() = Instant::now();
+    }
+
+    /// Returns when the connection times out if no request starts until then.
+    fn idle_deadline(&self, idle_timeout: Duration) -> Option<Instant> {
+        if self.in_flight.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(*self.idle_since.lock().unwrap() + idle_timeout)
+    }
+}
+
+/// Marks a request as in flight until dropped
+struct InFlight(Arc<ConnectionState>);
+
+impl InFlight {
+    fn start(state: &Arc<ConnectionState>) -> Self {
+        state.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(Arc::clone(state))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.touch();
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A connection enforcing the idle and write timeouts
+///
+/// hyper keeps reading from connections while their requests are handled, to notice when
+/// clients go away, so the idle timeout only applies when no request is in flight.
+struct Connection<S> {
+    stream: S,
+    state: Arc<ConnectionState>,
+    config: ServerConfig,
+    idle: Option<Delay>,
+    write: Option<Delay>,
+}
+
+impl Connection<AddrStream> {
+    fn new(stream: AddrStream, config: ServerConfig) -> Self {
+        Connection {
+            stream,
+            state: Arc::new(ConnectionState {
+                in_flight: AtomicUsize::new(0),
+                idle_since: Mutex::new(Instant::now()),
+            }),
+            config,
+            idle: None,
+            write: None,
+        }
+    }
+
+    fn remote_addr(&self) -> SocketAddr {
+        self.stream.remote_addr()
+    }
+}
+
+impl<S> Connection<S> {
+    /// Returns whether the connection is idle for longer than the idle timeout, and otherwise
+    /// schedules a wake up for when it will be.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> bool {
+        let deadline = match self.state.idle_deadline(self.config.idle_timeout) {
+            Some(deadline) => deadline,
+            None => {
+                self.idle = None;
+                return false;
+            }
+        };
+        let idle = self.idle.get_or_insert_with(|| time::delay_until(deadline));
+        if idle.deadline() != deadline {
+            idle.reset(deadline);
+        }
+        Pin::new(idle).poll(cx).is_ready()
+    }
+
+    /// Fails once a write was pending for longer than the write timeout.
+    fn poll_write_timeout<T>(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<T>> {
+        let timeout = self.config.write_timeout;
+        let write = self.write.get_or_insert_with(|| time::delay_for(timeout));
+        match Pin::new(write).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Timed out writing the response",
+            ))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Connection<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if let Poll::Ready(result) = Pin::new(&mut this.stream).poll_read(cx, buf) {
+            return Poll::Ready(result);
+        }
+        if this.poll_idle(cx) {
+            // Closing an idle connection is like the client closing it
+            return Poll::Ready(Ok(0));
+        }
+        Poll::Pending
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Connection<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.stream).poll_write(cx, buf) {
+            Poll::Pending => this.poll_write_timeout(cx),
+            Poll::Ready(result) => {
+                this.write = None;
+                this.state.touch();
+                Poll::Ready(result)
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.stream).poll_flush(cx) {
+            Poll::Pending => this.poll_write_timeout(cx),
+            Poll::Ready(result) => {
+                this.write = None;
+                Poll::Ready(result)
+            }
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// A request body failing when the client stops sending it
+struct ReadTimeout {
+    body: Body,
+    timeout: Duration,
+    delay: Option<Delay>,
+}
+
+impl ReadTimeout {
+    fn new(body: Body, timeout: Duration) -> Self {
+        ReadTimeout {
+            body,
+            timeout,
+            delay: None,
+        }
+    }
+}
+
+impl Stream for ReadTimeout {
+    type Item = Result<Bytes, Box<dyn Error + Send + Sync>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.body).poll_next(cx) {
+            Poll::Ready(chunk) => {
+                this.delay = None;
+                Poll::Ready(chunk.map(|chunk| chunk.map_err(Into::into)))
+            }
+            Poll::Pending => {
+                let timeout = this.timeout;
+                let delay = this.delay.get_or_insert_with(|| time::delay_for(timeout));
+                match Pin::new(delay).poll(cx) {
+                    Poll::Ready(()) => {
+                        Poll::Ready(Some(Err("Timed out reading the request body".into())))
+                    }
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_are_not_idle_with_requests_in_flight() {
+        let state = Arc::new(ConnectionState {
+            in_flight: AtomicUsize::new(0),
+            idle_since: Mutex::new(Instant::now()),
+        });
+        let timeout = Duration::from_secs(10);
+        let initial_deadline = state.idle_deadline(timeout).unwrap();
+
+        let first = InFlight::start(&state);
+        let second = InFlight::start(&state);
+        assert_eq!(state.idle_deadline(timeout), None);
+        drop(first);
+        assert_eq!(state.idle_deadline(timeout), None);
+        drop(second);
+        assert!(state.idle_deadline(timeout).unwrap() >= initial_deadline);
+    }
+}
@@ -0,0 +1,145 @@
+//! Coordination of the graceful shutdown of a process
+//!
+//! A `Shutdown` is cloned into every subsystem of a process. Once it is triggered, e.g. by a
+//! `SIGTERM` sent by Heroku, the server stops accepting connections and finishes the requests in
+//! flight, and the job runner finishes the jobs it started. Buffered state is then flushed by the
+//! hooks registered with `on_shutdown`.
+//!
+//! Heroku kills processes 30 seconds after sending `SIGTERM`, so `exit_after` ends the process
+//! before that, after running the hooks, if some subsystem didn't finish in time.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+// Can't derive Debug because of the hooks.
+#[allow(missing_debug_implementations)]
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    triggered: Mutex<bool>,
+    condvar: Condvar,
+    hooks: Mutex<Vec<Hook>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Triggers the shutdown when the process receives `SIGINT` or `SIGTERM`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a signal handler was already installed in this process.
+    pub fn trigger_on_signals(&self) {
+        let shutdown = self.clone();
+        ctrlc::set_handler(move || {
+            if shutdown.is_triggered() {
+                println!("Already sent signal to start graceful shutdown");
+            } else {
+                println!("Starting graceful shutdown");
+                shutdown.trigger();
+            }
+        })
+        .expect("Failed to install the signal handler");
+    }
+
+    pub fn trigger(&self) {
+        *self.inner.triggered.lock().unwrap() = true;
+        self.inner.condvar.notify_all();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.triggered.lock().unwrap()
+    }
+
+    /// Blocks until the shutdown is triggered.
+    pub fn wait(&self) {
+        let triggered = self.inner.triggered.lock().unwrap();
+        let _triggered = self
+            .inner
+            .condvar
+            .wait_while(triggered, |triggered| !*triggered)
+            .unwrap();
+    }
+
+    /// Sleeps for the given duration, waking up early if the shutdown is triggered.
+    ///
+    /// Returns whether the shutdown was triggered.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let triggered = self.inner.triggered.lock().unwrap();
+        let (triggered, _) = self
+            .inner
+            .condvar
+            .wait_timeout_while(triggered, duration, |triggered| !*triggered)
+            .unwrap();
+        *triggered
+    }
+
+    /// Registers a hook flushing buffered state, run once the subsystems stopped.
+    pub fn on_shutdown(&self, hook: impl FnOnce() + Send + 'static) {
+        self.inner.hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    /// Runs the registered hooks, which are only run once even if this is called again.
+    pub fn run_hooks(&self) {
+        let hooks = std::mem::take(&mut *self.inner.hooks.lock().unwrap());
+        for hook in hooks {
+            hook();
+        }
+    }
+
+    /// Exits the process if it is still running `deadline` after the shutdown was triggered.
+    pub fn exit_after(&self, deadline: Duration) {
+        let shutdown = self.clone();
+        thread::spawn(move || {
+            shutdown.wait();
+            thread::sleep(deadline);
+            eprintln!(
+                "Graceful shutdown didn't finish within {} seconds, exiting",
+                deadline.as_secs()
+            );
+            shutdown.run_hooks();
+            std::process::exit(1);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn sleep_is_interrupted_by_trigger() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.sleep(Duration::from_millis(1)));
+
+        let trigger = shutdown.clone();
+        let handle = thread::spawn(move || trigger.trigger());
+        assert!(shutdown.sleep(Duration::from_secs(60)));
+        handle.join().unwrap();
+        assert!(shutdown.is_triggered());
+    }
+
+    #[test]
+    fn hooks_run_once() {
+        let shutdown = Shutdown::new();
+        let count = Arc::new(AtomicUsize::new(0));
+        let hook_count = count.clone();
+        shutdown.on_shutdown(move || {
+            hook_count.fetch_add(1, Ordering::SeqCst);
+        });
+
+        shutdown.run_hooks();
+        shutdown.run_hooks();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
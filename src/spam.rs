@@ -0,0 +1,155 @@
+//! Heuristics recognizing spam in the descriptions and READMEs of new versions
+//!
+//! Spammers publish crates whose pages advertise unrelated products, with many links and
+//! without any Rust in them. The versions whose score reaches `Config::spam_score_threshold`
+//! are held for review by the admins of the instance, see `QuarantinedVersion`, instead of
+//! being added to the index.
+//!
+//! The heuristics are deliberately simple, so that the reasons of a score are easy to explain.
+//! They only need to catch the obvious cases, since the admins review the held versions.
+
+/// The default value of `Config::spam_score_threshold`
+pub const DEFAULT_THRESHOLD: u32 = 10;
+
+/// Phrases which are common in spam, and uncommon in crates
+const SPAM_PHRASES: &[&str] = &[
+    "buy now",
+    "casino",
+    "cheap price",
+    "cialis",
+    "crypto signals",
+    "customer care number",
+    "customer support number",
+    "escort service",
+    "free download",
+    "free followers",
+    "increase followers",
+    "keygen",
+    "loan approval",
+    "online pharmacy",
+    "payday loan",
+    "sports betting",
+    "viagra",
+    "watch online free",
+    "weight loss",
+];
+
+/// Words showing that a text is about code, whose absence from a long text is suspicious
+const CODE_WORDS: &[&str] = &[
+    "cargo", "crate", "crates", "fn", "impl", "library", "rust", "use",
+];
+
+/// Texts with fewer words than this are too short to judge whether they are about code
+const MIN_WORDS_FOR_CONTENT_CHECK: usize = 150;
+
+/// The number of links per 100 words above which a text is mostly links
+const MAX_LINKS_PER_100_WORDS: usize = 5;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SpamScore {
+    pub score: u32,
+    /// Why the score was raised, for the admins reviewing the version
+    pub reasons: Vec<String>,
+}
+
+impl SpamScore {
+    /// Scores the description and the README of a new version.
+    pub fn new(description: Option<&str>, readme: Option<&str>) -> Self {
+        let mut score = SpamScore::default();
+        let description = description.unwrap_or_default();
+        let readme = readme.unwrap_or_default();
+        let text = format!("{}\n{}", description, readme).to_lowercase();
+
+        for phrase in SPAM_PHRASES {
+            if text.contains(phrase) {
+                score.add(4, format!("contains the spam phrase `{}`", phrase));
+            }
+        }
+
+        let description_links = count_links(description);
+        if description_links > 0 {
+            score.add(
+                3 * description_links as u32,
+                format!("the description contains {} link(s)", description_links),
+            );
+        }
+
+        let words = text.split_whitespace().count();
+        let links = count_links(&text);
+        if words > 0 && links * 100 / words > MAX_LINKS_PER_100_WORDS {
+            score.add(5, format!("{} links for {} words", links, words));
+        }
+
+        if words >= MIN_WORDS_FOR_CONTENT_CHECK && !contains_code(&text) {
+            score.add(5, format!("{} words without any code", words));
+        }
+
+        score
+    }
+
+    pub fn is_spam(&self, threshold: u32) -> bool {
+        threshold > 0 && self.score >= threshold
+    }
+
+    fn add(&mut self, points: u32, reason: String) {
+        self.score += points;
+        self.reasons.push(reason);
+    }
+}
+
+/// Whether a text has a code block or one of the `CODE_WORDS`, as a whole word so that
+/// e.g. `because` doesn't count as `use`
+fn contains_code(text: &str) -> bool {
+    text.contains("```")
+        || text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .any(|word| CODE_WORDS.contains(&word))
+}
+
+fn count_links(text: &str) -> usize {
+    text.matches("http://").count() + text.matches("https://").count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regular_crates_are_not_spam() {
+        let readme = "# foo\n\nA library to parse foo files, see https://docs.rs/foo.\n\n\
+            ```rust\nlet foo = foo::parse(input)?;\n```";
+        let score = SpamScore::new(Some("Parses foo files"), Some(readme));
+        assert_eq!(score.score, 0);
+        assert!(!score.is_spam(DEFAULT_THRESHOLD));
+    }
+
+    #[test]
+    fn spam_phrases_and_links_add_up() {
+        let readme = "Best casino bonus! Sports betting at https://a.example and \
+            https://b.example, buy now at https://c.example";
+        let score = SpamScore::new(Some("Casino https://a.example"), Some(readme));
+        assert!(score.is_spam(DEFAULT_THRESHOLD), "{:?}", score);
+        assert!(score
+            .reasons
+            .contains(&"contains the spam phrase `casino`".to_string()));
+        assert!(score
+            .reasons
+            .contains(&"the description contains 1 link(s)".to_string()));
+    }
+
+    #[test]
+    fn long_texts_without_code_are_suspicious() {
+        let readme = "lorem ipsum ".repeat(100);
+        let score = SpamScore::new(Some("Some text"), Some(&readme));
+        assert_eq!(score.score, 5);
+        assert!(!score.is_spam(0));
+
+        let readme = "because trusted cargoes ".repeat(100);
+        let score = SpamScore::new(Some("Some text"), Some(&readme));
+        assert_eq!(score.score, 5);
+
+        let readme = format!("{}\n\nuse foo::Bar;", "lorem ipsum ".repeat(100));
+        let score = SpamScore::new(Some("Some text"), Some(&readme));
+        assert_eq!(score.score, 0);
+    }
+}
@@ -1,5 +1,31 @@
+mod dependents_counts;
 pub mod dump_db;
+mod license_notifications;
+mod link_checks;
+mod notable_dependents;
+mod pageviews;
+mod quality;
+mod readme_rerenders;
+mod search_index;
+mod trending;
 mod update_downloads;
+mod upstream;
+mod usage_stats;
+mod version_diffs;
+mod yank_notifications;
 
+pub use dependents_counts::{reconcile_dependents_counts, update_dependents_counts};
 pub use dump_db::dump_db;
+pub use license_notifications::notify_license_change;
+pub use link_checks::check_crate_links;
+pub use notable_dependents::update_notable_dependents;
+pub use pageviews::clean_pageview_visitors;
+pub use quality::{update_quality_scores, QUALITY_FORMULA_VERSION};
+pub use readme_rerenders::{enqueue_readme_rerender, rerender_readmes};
+pub use search_index::index_crate;
+pub use trending::update_trending_scores;
 pub use update_downloads::update_downloads;
+pub use upstream::{follow_upstream_events, mirror_upstream_crate};
+pub use usage_stats::update_usage_stats;
+pub use version_diffs::compute_version_diff;
+pub use yank_notifications::notify_yanked_dependents;
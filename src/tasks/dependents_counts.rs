@@ -0,0 +1,29 @@
+use crate::models::Crate;
+use crate::schema::{dependencies, versions};
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Refreshes the dependents count of every crate that the given crate depends on, or
+/// used to depend on, after one of its versions was published, yanked or unyanked.
+#[swirl::background_job]
+pub fn update_dependents_counts(conn: &PgConnection, crate_id: i32) -> Result<(), PerformError> {
+    let dependency_ids: Vec<i32> = dependencies::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq(crate_id))
+        .select(dependencies::crate_id)
+        .distinct()
+        .load(conn)?;
+
+    Crate::update_dependents_counts(conn, &dependency_ids)?;
+    Ok(())
+}
+
+/// Recomputes the dependents count of all crates, in case an incremental update was
+/// missed.
+#[swirl::background_job]
+pub fn reconcile_dependents_counts(conn: &PgConnection) -> Result<(), PerformError> {
+    let updated = Crate::reconcile_dependents_counts(conn)?;
+    println!("Updated the dependents count of {} crates", updated);
+    Ok(())
+}
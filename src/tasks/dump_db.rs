@@ -74,7 +74,7 @@ impl DumpDirectory {
         }
         let metadata = Metadata {
             timestamp: &self.timestamp,
-            crates_io_commit: dotenv::var("HEROKU_SLUG_COMMIT")
+            crates_io_commit: crate::config::var("HEROKU_SLUG_COMMIT")
                 .unwrap_or_else(|_| "unknown".to_owned()),
         };
         let file = File::create(self.export_dir.join("metadata.json"))?;
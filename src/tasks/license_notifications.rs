@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use super::yank_notifications::latest_dependents;
+use crate::email;
+use crate::models::{CrateOwner, OwnerKind, User};
+use crate::schema::{crate_owners, crates, follows, users, versions};
+
+/// Emails the followers of a crate, and the owners of the crates whose latest version depends
+/// on it, that a new version changed its license.
+///
+/// The owners of the dependents are only told if the new version matches their requirement,
+/// and if they enabled the notifications of the dependent crate, and the followers only if they
+/// enabled the notifications of the crate. Users are told only once,
+/// even if they follow the crate and own several of its dependents.
+#[swirl::background_job]
+pub fn notify_license_change(conn: &PgConnection, version_id: i32) -> Result<(), PerformError> {
+    let (crate_id, crate_name, num, license, previous_license): (
+        i32,
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+    ) = versions::table
+        .find(version_id)
+        .inner_join(crates::table)
+        .select((
+            crates::id,
+            crates::name,
+            versions::num,
+            versions::license,
+            versions::previous_license,
+        ))
+        .first(conn)?;
+    let version = semver::Version::parse(&num)?;
+    let license = license.as_deref().unwrap_or("none");
+    let previous_license = previous_license.as_deref().unwrap_or("none");
+
+    let mut notified = HashSet::new();
+    let dependents = latest_dependents(conn, crate_id, &version)?;
+    for dependent in dependents {
+        let owners: Vec<User> = CrateOwner::by_owner_kind(OwnerKind::User)
+            .filter(crate_owners::crate_id.eq(dependent.crate_id))
+            .filter(crate_owners::email_notifications.eq(true))
+            .inner_join(users::table)
+            .select(users::all_columns)
+            .load(conn)?;
+        for owner in owners {
+            if notified.contains(&owner.id) {
+                continue;
+            }
+            if let Some(email) = owner.verified_email(conn)? {
+                email::send_license_changed_email(
+                    &email,
+                    &crate_name,
+                    &num,
+                    previous_license,
+                    license,
+                    Some(&dependent.crate_name),
+                );
+                notified.insert(owner.id);
+            }
+        }
+    }
+
+    let followers: Vec<User> = follows::table
+        .filter(follows::crate_id.eq(crate_id))
+        .filter(follows::email_notifications.eq(true))
+        .inner_join(users::table)
+        .select(users::all_columns)
+        .load(conn)?;
+    for follower in followers {
+        if notified.contains(&follower.id) {
+            continue;
+        }
+        if let Some(email) = follower.verified_email(conn)? {
+            email::send_license_changed_email(
+                &email,
+                &crate_name,
+                &num,
+                previous_license,
+                license,
+                None,
+            );
+            notified.insert(follower.id);
+        }
+    }
+
+    println!(
+        "Notified {} users that {}#{} changed its license",
+        notified.len(),
+        crate_name,
+        num
+    );
+    Ok(())
+}
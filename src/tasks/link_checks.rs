@@ -0,0 +1,267 @@
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text};
+use reqwest::{blocking::Client, redirect};
+use swirl::PerformError;
+use url::{Host, Url};
+
+use crate::models::{CrateLinkCheck, LinkStatus};
+
+const LINK_CHECK_USER_AGENT: &str =
+    "crates.io link checker (https://github.com/rust-lang/crates.io)";
+
+/// The number of links checked by each run of the job at most
+const LINKS_PER_RUN: i64 = 1000;
+
+/// How long a run checks links at most, so that it doesn't hold a background worker for hours.
+/// The links it didn't get to are checked by the next runs.
+const MAX_RUN_TIME: Duration = Duration::from_secs(5 * 60);
+
+/// The number of days after which a link is checked again
+const RECHECK_AFTER_DAYS: i64 = 7;
+
+/// How long a server has to answer before its link is reported as unreachable
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long connecting to a server can take before its link is reported as unreachable
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The number of redirects followed before a link is reported as unreachable
+const MAX_REDIRECTS: usize = 5;
+
+/// The number of bytes of a page searched for the signs of a parked domain
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// The links which were never checked, were changed since their last check, or were
+/// checked the longest time ago
+const LINKS_TO_CHECK_QUERY: &str = r#"
+SELECT crates.id AS crate_id, links.field, links.url
+FROM crates
+CROSS JOIN LATERAL (
+    VALUES
+        ('documentation', crates.documentation),
+        ('homepage', crates.homepage),
+        ('repository', crates.repository)
+) AS links (field, url)
+LEFT JOIN crate_link_checks checks
+  ON checks.crate_id = crates.id AND checks.field = links.field
+WHERE links.url IS NOT NULL
+  AND links.url <> ''
+  AND (
+    checks.url IS DISTINCT FROM links.url
+    OR checks.checked_at < now() - $1 * interval '1 day'
+  )
+ORDER BY checks.checked_at NULLS FIRST, crates.id
+LIMIT $2
+"#;
+
+#[derive(QueryableByName, Debug)]
+struct Link {
+    #[sql_type = "Integer"]
+    crate_id: i32,
+    #[sql_type = "Text"]
+    field: String,
+    #[sql_type = "Text"]
+    url: String,
+}
+
+/// Checks the `documentation`, `homepage` and `repository` links of the crates, so that
+/// dead or hijacked links can be flagged instead of being served forever.
+///
+/// Each run checks a batch of links, starting with the ones which were never checked, so
+/// the job is meant to be enqueued periodically.
+///
+/// The links come from anyone publishing a crate, so they are only requested, and their
+/// redirects followed, if their hosts resolve to public addresses. Otherwise the checker could
+/// be used to reach the internal network of the workers.
+#[swirl::background_job]
+pub fn check_crate_links(conn: &PgConnection) -> Result<(), PerformError> {
+    let links: Vec<Link> = diesel::sql_query(LINKS_TO_CHECK_QUERY)
+        .bind::<BigInt, _>(RECHECK_AFTER_DAYS)
+        .bind::<BigInt, _>(LINKS_PER_RUN)
+        .load(conn)?;
+
+    let client = link_check_client()?;
+    let started_at = Instant::now();
+    let mut checked = 0;
+    let mut broken = 0;
+    for link in &links {
+        if started_at.elapsed() > MAX_RUN_TIME {
+            break;
+        }
+        let status = check_link(&client, &link.url);
+        if status != LinkStatus::Ok {
+            broken += 1;
+        }
+        CrateLinkCheck::record(conn, link.crate_id, &link.field, &link.url, status)?;
+        checked += 1;
+    }
+
+    println!("Checked {} crate links, {} are broken", checked, broken);
+    Ok(())
+}
+
+/// Returns the client of the checks, which only follows redirects to public addresses.
+fn link_check_client() -> reqwest::Result<Client> {
+    let redirect_policy = redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if resolves_to_public_addresses(attempt.url()) {
+            attempt.follow()
+        } else {
+            attempt.error("redirected to a private address")
+        }
+    });
+
+    Client::builder()
+        .user_agent(LINK_CHECK_USER_AGENT)
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .redirect(redirect_policy)
+        .build()
+}
+
+fn check_link(client: &Client, url: &str) -> LinkStatus {
+    match Url::parse(url) {
+        Ok(url) if resolves_to_public_addresses(&url) => {}
+        _ => return LinkStatus::Unreachable,
+    }
+
+    let response = client.get(url).send();
+    let response = match response {
+        Ok(response) => response,
+        Err(_) => return LinkStatus::Unreachable,
+    };
+
+    let status = response.status().as_u16();
+    let host = response.url().host_str().map(String::from);
+    let mut body = Vec::new();
+    if response.status().is_success() {
+        // A page which can't be read entirely is classified with what was read
+        let _ = response.take(MAX_BODY_SIZE).read_to_end(&mut body);
+    }
+
+    LinkStatus::from_response(status, host.as_deref(), &String::from_utf8_lossy(&body))
+}
+
+/// Whether the URL is an `http` or `https` one whose host only resolves to public addresses,
+/// see `is_public_address`.
+fn resolves_to_public_addresses(url: &Url) -> bool {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return false;
+    }
+    let port = match url.port_or_known_default() {
+        Some(port) => port,
+        None => return false,
+    };
+
+    let addresses = match url.host() {
+        Some(Host::Domain(domain)) => match (domain, port).to_socket_addrs() {
+            Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+            Err(_) => return false,
+        },
+        Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        None => return false,
+    };
+    !addresses.is_empty() && addresses.into_iter().all(is_public_address)
+}
+
+/// Whether an address is reachable from the internet, as opposed to the loopback, private,
+/// link-local, shared or reserved ranges.
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // 0.0.0.0/8 "this network"
+        || a == 0
+        // 100.64.0.0/10 shared address space
+        || (a == 100 && (b & 0xc0) == 64)
+        // 240.0.0.0/4 reserved
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let embedded_ipv4 = || {
+        let [.., a, b, c, d] = ip.octets();
+        Ipv4Addr::new(a, b, c, d)
+    };
+    match segments {
+        // ::ffff:0:0/96 IPv4-mapped and 64:ff9b::/96 NAT64 addresses
+        [0, 0, 0, 0, 0, 0xffff, _, _] | [0x64, 0xff9b, 0, 0, 0, 0, _, _] => {
+            is_public_ipv4(embedded_ipv4())
+        }
+        _ => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // fc00::/7 unique local
+                || (segments[0] & 0xfe00) == 0xfc00
+                // fe80::/10 link-local
+                || (segments[0] & 0xffc0) == 0xfe80
+                // 2001:db8::/32 documentation
+                || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_public_address, resolves_to_public_addresses};
+    use url::Url;
+
+    #[test]
+    fn only_public_addresses_are_checked() {
+        for ip in &["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn private_urls_are_not_checked() {
+        for url in &[
+            "http://127.0.0.1:8888/",
+            "http://[::1]/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://localhost/",
+            "file:///etc/passwd",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(!resolves_to_public_addresses(&url), "{}", url);
+        }
+    }
+}
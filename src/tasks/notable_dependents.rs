@@ -0,0 +1,16 @@
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::schema::crate_notable_dependents;
+
+/// Recomputes the notable dependents of all crates, shown as "used by" on the
+/// crate pages.
+#[swirl::background_job]
+pub fn update_notable_dependents(conn: &PgConnection) -> Result<(), PerformError> {
+    let count = conn.transaction(|| {
+        diesel::delete(crate_notable_dependents::table).execute(conn)?;
+        diesel::sql_query(include_str!("update_notable_dependents.sql")).execute(conn)
+    })?;
+    println!("Stored {} notable dependents", count);
+    Ok(())
+}
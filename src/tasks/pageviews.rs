@@ -0,0 +1,12 @@
+use crate::models::CratePageview;
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Deletes the data used to count each visitor of a crate page only once per day,
+/// once that day is over.
+#[swirl::background_job]
+pub fn clean_pageview_visitors(conn: &PgConnection) -> Result<(), PerformError> {
+    CratePageview::delete_expired_visitors(conn)?;
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use swirl::PerformError;
+
+/// The version of the formula used by `update_quality_scores`, which is stored
+/// along with each score. Bump it whenever the formula changes so that scores
+/// computed with different formulas can be told apart until they are recomputed.
+pub const QUALITY_FORMULA_VERSION: i32 = 1;
+
+/// Recomputes the quality score of all crates from objective signals, such as
+/// having documentation or declaring a minimum supported Rust version.
+#[swirl::background_job]
+pub fn update_quality_scores(conn: &PgConnection) -> Result<(), PerformError> {
+    let count = diesel::sql_query(include_str!("update_quality_scores.sql"))
+        .bind::<Integer, _>(QUALITY_FORMULA_VERSION)
+        .execute(conn)?;
+    println!("Updated the quality score of {} crates", count);
+    Ok(())
+}
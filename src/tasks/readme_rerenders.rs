@@ -0,0 +1,151 @@
+use diesel::dsl::now;
+use diesel::prelude::*;
+use swirl::{EnqueueError, Job, PerformError};
+
+use crate::admin::render_readmes::read_readme;
+use crate::background_jobs::Environment;
+use crate::models::{ReadmeRerender, Version};
+use crate::render::{
+    path_in_repository, readme_headings, readme_to_html_cached, repository_default_branch,
+};
+use crate::schema::{crates, readme_renderings, readme_rerenders, versions};
+
+/// The number of versions whose README is rendered by each run of the job, which then
+/// enqueues the next run, so that a re-rendering doesn't hold a background worker for days
+const VERSIONS_PER_RUN: i64 = 100;
+
+/// Starts re-rendering the READMEs of all the versions which have one, e.g. after the tags
+/// and attributes allowed by the sanitizer changed, see `rerender_readmes`.
+pub fn enqueue_readme_rerender(
+    conn: &PgConnection,
+    requested_by: i32,
+) -> Result<ReadmeRerender, EnqueueError> {
+    conn.transaction(|| {
+        let total = readme_renderings::table.count().get_result::<i64>(conn)?;
+        let rerender = diesel::insert_into(readme_rerenders::table)
+            .values((
+                readme_rerenders::requested_by.eq(requested_by),
+                readme_rerenders::total.eq(total as i32),
+            ))
+            .get_result::<ReadmeRerender>(conn)?;
+        rerender_readmes(rerender.id).enqueue(conn)?;
+        Ok(rerender)
+    })
+}
+
+/// Renders the READMEs of the next batch of versions of a re-rendering again, reading them
+/// from the `.crate` files, and records the progress.
+///
+/// The job enqueues itself until all the versions were rendered.
+#[swirl::background_job]
+pub fn rerender_readmes(
+    conn: &PgConnection,
+    env: &Environment,
+    rerender_id: i32,
+) -> Result<(), PerformError> {
+    let rerender: ReadmeRerender = readme_rerenders::table.find(rerender_id).first(conn)?;
+    if rerender.finished_at.is_some() {
+        return Ok(());
+    }
+
+    let batch: Vec<(i32, String, String)> = versions::table
+        .inner_join(crates::table)
+        .inner_join(readme_renderings::table)
+        .filter(versions::id.gt(rerender.last_version_id))
+        .select((versions::id, crates::name, versions::num))
+        .order(versions::id)
+        .limit(VERSIONS_PER_RUN)
+        .load(conn)?;
+
+    let (mut rendered, mut skipped) = (0, 0);
+    for (version_id, crate_name, num) in &batch {
+        match rerender_readme(conn, env, *version_id, crate_name, num) {
+            Ok(true) => rendered += 1,
+            Ok(false) => skipped += 1,
+            Err(e) => {
+                warn!(
+                    "could not render the readme of {} {}: {}",
+                    crate_name, num, e
+                );
+                skipped += 1;
+            }
+        }
+    }
+
+    let last_version_id = batch.last().map_or(rerender.last_version_id, |v| v.0);
+    let finished = (batch.len() as i64) < VERSIONS_PER_RUN;
+    conn.transaction(|| {
+        diesel::update(readme_rerenders::table.find(rerender_id))
+            .set((
+                readme_rerenders::rendered.eq(readme_rerenders::rendered + rendered),
+                readme_rerenders::skipped.eq(readme_rerenders::skipped + skipped),
+                readme_rerenders::last_version_id.eq(last_version_id),
+            ))
+            .execute(conn)?;
+        if finished {
+            diesel::update(readme_rerenders::table.find(rerender_id))
+                .set(readme_rerenders::finished_at.eq(now.nullable()))
+                .execute(conn)?;
+        } else {
+            rerender_readmes(rerender_id).enqueue(conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Renders and uploads the README of a version again, returning whether it was found.
+fn rerender_readme(
+    conn: &PgConnection,
+    env: &Environment,
+    version_id: i32,
+    crate_name: &str,
+    num: &str,
+) -> Result<bool, PerformError> {
+    let tarball = match env
+        .uploader
+        .download_crate_file(env.http_client(), crate_name, num)?
+    {
+        Some(tarball) => tarball,
+        None => return Ok(false),
+    };
+    let prefix = format!("{}-{}", crate_name, num);
+    let max_size = env.render_settings().limits.max_size as u64;
+    let readme = match read_readme(&tarball, &prefix, max_size)? {
+        Some(readme) => readme,
+        None => return Ok(false),
+    };
+
+    let base_url = readme.repository.as_deref();
+    let forges = &env.render_settings().forges;
+    let default_branch = base_url
+        .and_then(|base_url| repository_default_branch(conn, env.http_client(), forges, base_url));
+    let html = match readme_to_html_cached(
+        conn,
+        &readme.text,
+        &readme.file_name,
+        base_url,
+        default_branch.as_deref(),
+        path_in_repository(readme.path_in_vcs.as_deref(), &readme.file_name).as_deref(),
+        Some(env.render_settings()),
+    ) {
+        Ok(html) => html,
+        Err(e) => {
+            warn!(
+                "could not render the readme of {}-{}: {}",
+                crate_name, num, e
+            );
+            return Ok(false);
+        }
+    };
+    let headings = serde_json::to_value(readme_headings(&readme.text, &readme.file_name))?;
+
+    conn.transaction(|| {
+        Version::record_readme_rendering(version_id, conn)?;
+        diesel::update(readme_renderings::table.find(version_id))
+            .set(readme_renderings::headings.eq(headings))
+            .execute(conn)?;
+        env.uploader
+            .upload_readme(env.http_client(), crate_name, num, None, html)?;
+        Ok(true)
+    })
+}
@@ -0,0 +1,22 @@
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::search_backend::CrateDocument;
+
+use diesel::prelude::*;
+
+/// Indexes the document of a crate in the external search engine after one of its versions was
+/// published or yanked, or removes it once the crate was deleted.
+#[swirl::background_job]
+pub fn index_crate(
+    conn: &PgConnection,
+    env: &Environment,
+    crate_id: i32,
+) -> Result<(), PerformError> {
+    let backend = env.search_backend();
+    match CrateDocument::load(conn, crate_id)? {
+        Some(document) => backend.index_crate(&document)?,
+        None => backend.remove_crate(crate_id)?,
+    }
+    Ok(())
+}
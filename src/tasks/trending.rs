@@ -0,0 +1,16 @@
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::schema::crate_trending_scores;
+
+/// Recomputes the trending score of all crates from their recent downloads and
+/// new dependents.
+#[swirl::background_job]
+pub fn update_trending_scores(conn: &PgConnection) -> Result<(), PerformError> {
+    let count = conn.transaction(|| {
+        diesel::delete(crate_trending_scores::table).execute(conn)?;
+        diesel::sql_query(include_str!("update_trending_scores.sql")).execute(conn)
+    })?;
+    println!("Updated the trending score of {} crates", count);
+    Ok(())
+}
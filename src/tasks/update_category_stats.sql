@@ -0,0 +1,25 @@
+-- Record the number of crates of each category at the end of yesterday, and the
+-- downloads of these crates yesterday. Crates created since then aren't counted.
+WITH daily AS (
+    SELECT versions.crate_id, SUM(version_downloads.downloads) AS downloads
+    FROM version_downloads
+    INNER JOIN versions
+      ON versions.id = version_downloads.version_id
+    WHERE version_downloads.date = CURRENT_DATE - 1
+    GROUP BY versions.crate_id
+)
+INSERT INTO category_stats (category_id, date, crates_cnt, downloads)
+SELECT crates_categories.category_id,
+    CURRENT_DATE - 1,
+    COUNT(*),
+    COALESCE(SUM(daily.downloads), 0)
+FROM crates_categories
+INNER JOIN crates
+  ON crates.id = crates_categories.crate_id
+LEFT JOIN daily
+  ON daily.crate_id = crates_categories.crate_id
+WHERE crates.created_at < CURRENT_DATE
+GROUP BY crates_categories.category_id
+ON CONFLICT (category_id, date) DO UPDATE
+SET crates_cnt = EXCLUDED.crates_cnt,
+    downloads = EXCLUDED.downloads;
@@ -85,7 +85,8 @@ mod test {
     use super::*;
     use crate::{
         env,
-        models::{Crate, NewCrate, NewUser, NewVersion, User, Version},
+        models::{Crate, NewCrate, NewVersion, User, Version},
+        test_util::UserBuilder,
     };
     use std::collections::HashMap;
 
@@ -96,9 +97,7 @@ mod test {
     }
 
     fn user(conn: &PgConnection) -> User {
-        NewUser::new(2, "login", None, None, "access_token")
-            .create_or_update(None, conn)
-            .unwrap()
+        UserBuilder::new("login").expect_build(conn)
     }
 
     fn crate_and_version(conn: &PgConnection, user_id: i32) -> (Crate, Version) {
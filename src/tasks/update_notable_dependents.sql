@@ -0,0 +1,27 @@
+-- Recompute the notable dependents of every crate: the 5 most downloaded crates
+-- with a non-yanked version depending on it. Dev dependencies are not counted,
+-- since they don't make the crate part of the dependent.
+WITH dependents AS (
+    SELECT DISTINCT dependencies.crate_id, versions.crate_id AS dependent_id
+    FROM dependencies
+    INNER JOIN versions
+      ON versions.id = dependencies.version_id
+    WHERE NOT versions.yanked
+      AND dependencies.kind <> 2
+      AND versions.crate_id <> dependencies.crate_id
+), ranked AS (
+    SELECT dependents.crate_id,
+        dependents.dependent_id,
+        crates.downloads,
+        ROW_NUMBER() OVER (
+            PARTITION BY dependents.crate_id
+            ORDER BY crates.downloads DESC, crates.name
+        ) AS rank
+    FROM dependents
+    INNER JOIN crates
+      ON crates.id = dependents.dependent_id
+)
+INSERT INTO crate_notable_dependents (crate_id, dependent_id, downloads)
+SELECT crate_id, dependent_id, downloads
+FROM ranked
+WHERE rank <= 5;
@@ -0,0 +1,62 @@
+-- Recompute the quality score of every crate with the formula version given
+-- as `$1`. Bump `QUALITY_FORMULA_VERSION` whenever the scoring below changes.
+--
+-- Each component is between 0 and 1 and the total is their average:
+--
+-- * docs: 1 if the crate links to its documentation, 0.5 if it only has a
+--   readme
+-- * recent_release: 1 if a version was published during the last 180 days,
+--   0.5 during the last 365 days
+-- * tests: 1 if the tarball of the latest version contains a `tests` directory
+-- * msrv: 1 if the latest version declares a `rust-version`
+-- * not_yanked: 1 if the latest version isn't yanked
+WITH latest_versions AS (
+    SELECT DISTINCT ON (crate_id) id, crate_id, yanked, rust_version
+    FROM versions
+    ORDER BY crate_id, to_semver_no_prerelease(num) DESC NULLS LAST, created_at DESC
+), last_releases AS (
+    SELECT crate_id, MAX(created_at) AS created_at
+    FROM versions
+    GROUP BY crate_id
+), components AS (
+    SELECT crates.id AS crate_id,
+        CASE
+            WHEN crates.documentation IS NOT NULL THEN 1.0
+            WHEN crates.readme IS NOT NULL THEN 0.5
+            ELSE 0.0
+        END::float8 AS docs,
+        CASE
+            WHEN last_releases.created_at > CURRENT_DATE - 180 THEN 1.0
+            WHEN last_releases.created_at > CURRENT_DATE - 365 THEN 0.5
+            ELSE 0.0
+        END::float8 AS recent_release,
+        CASE WHEN EXISTS (
+            SELECT 1
+            FROM version_files
+            WHERE version_files.version_id = latest_versions.id
+              AND version_files.path LIKE 'tests/%'
+        ) THEN 1.0 ELSE 0.0 END::float8 AS tests,
+        CASE WHEN latest_versions.rust_version IS NOT NULL
+            THEN 1.0 ELSE 0.0 END::float8 AS msrv,
+        CASE WHEN NOT latest_versions.yanked
+            THEN 1.0 ELSE 0.0 END::float8 AS not_yanked
+    FROM crates
+    INNER JOIN latest_versions
+      ON latest_versions.crate_id = crates.id
+    INNER JOIN last_releases
+      ON last_releases.crate_id = crates.id
+)
+INSERT INTO crate_quality_scores
+    (crate_id, formula_version, docs, recent_release, tests, msrv, not_yanked, total)
+SELECT crate_id, $1, docs, recent_release, tests, msrv, not_yanked,
+    (docs + recent_release + tests + msrv + not_yanked) / 5
+FROM components
+ON CONFLICT (crate_id) DO UPDATE
+SET formula_version = excluded.formula_version,
+    docs = excluded.docs,
+    recent_release = excluded.recent_release,
+    tests = excluded.tests,
+    msrv = excluded.msrv,
+    not_yanked = excluded.not_yanked,
+    total = excluded.total,
+    computed_at = now();
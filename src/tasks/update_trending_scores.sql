@@ -0,0 +1,59 @@
+-- Recompute the trending score of every crate which was downloaded recently or
+-- gained new dependents.
+--
+-- The score compares the downloads of the last 7 days with the weekly average
+-- of the 4 weeks before. The baseline is smoothed so that small absolute
+-- changes of rarely downloaded crates don't dominate the ranking. Each crate
+-- that started depending on the crate during the last 7 days adds to the score.
+WITH downloads AS (
+    SELECT versions.crate_id,
+        COALESCE(SUM(version_downloads.downloads)
+            FILTER (WHERE version_downloads.date > CURRENT_DATE - 7), 0) AS recent,
+        COALESCE(SUM(version_downloads.downloads)
+            FILTER (WHERE version_downloads.date <= CURRENT_DATE - 7), 0) / 4 AS baseline
+    FROM version_downloads
+    INNER JOIN versions
+      ON versions.id = version_downloads.version_id
+    WHERE version_downloads.date > CURRENT_DATE - 35
+    GROUP BY versions.crate_id
+), new_dependents AS (
+    SELECT dependencies.crate_id, COUNT(DISTINCT versions.crate_id) AS new_dependents
+    FROM dependencies
+    INNER JOIN versions
+      ON versions.id = dependencies.version_id
+    WHERE versions.created_at > CURRENT_DATE - 7
+      AND NOT EXISTS (
+        SELECT 1
+        FROM dependencies old_dependencies
+        INNER JOIN versions old_versions
+          ON old_versions.id = old_dependencies.version_id
+        WHERE old_dependencies.crate_id = dependencies.crate_id
+          AND old_versions.crate_id = versions.crate_id
+          AND old_versions.created_at <= CURRENT_DATE - 7
+      )
+    GROUP BY dependencies.crate_id
+), scores AS (
+    SELECT crates.id AS crate_id,
+        COALESCE(downloads.recent, 0) AS recent_downloads,
+        COALESCE(downloads.baseline, 0) AS baseline_downloads,
+        COALESCE(new_dependents.new_dependents, 0) AS new_dependents
+    FROM crates
+    LEFT JOIN downloads
+      ON downloads.crate_id = crates.id
+    LEFT JOIN new_dependents
+      ON new_dependents.crate_id = crates.id
+    WHERE downloads.crate_id IS NOT NULL
+       OR new_dependents.crate_id IS NOT NULL
+)
+INSERT INTO crate_trending_scores
+    (crate_id, score, recent_downloads, baseline_downloads, new_dependents)
+SELECT * FROM (
+    SELECT crate_id,
+        (recent_downloads - baseline_downloads)::float8 / (baseline_downloads + 1000)
+            + new_dependents * 0.5 AS score,
+        recent_downloads,
+        baseline_downloads,
+        new_dependents
+    FROM scores
+) trending
+WHERE score > 0;
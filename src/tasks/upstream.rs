@@ -0,0 +1,31 @@
+use diesel::PgConnection;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::upstream::UpstreamRegistry;
+
+/// Mirrors a crate from the upstream registry unless it is already known to this
+/// instance. This is enqueued for the dependencies of mirrored versions, which Cargo
+/// needs to find in the index to resolve them.
+#[swirl::background_job]
+pub fn mirror_upstream_crate(
+    conn: &PgConnection,
+    env: &Environment,
+    upstream: UpstreamRegistry,
+    name: String,
+) -> Result<(), PerformError> {
+    upstream.ensure_crate(conn, env.http_client(), None, &name)?;
+    Ok(())
+}
+
+/// Replays the events of the upstream registry which happened since this job last
+/// ran. Enqueuing this job periodically makes this instance follow the upstream one.
+#[swirl::background_job]
+pub fn follow_upstream_events(
+    conn: &PgConnection,
+    env: &Environment,
+    upstream: UpstreamRegistry,
+) -> Result<(), PerformError> {
+    upstream.follow_events(conn, env.http_client(), &env.uploader)?;
+    Ok(())
+}
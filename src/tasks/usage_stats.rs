@@ -0,0 +1,21 @@
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// Records the number of crates and the downloads of each keyword and category
+/// yesterday, shown as their trends.
+///
+/// The stats of a day are replaced if the job runs more than once.
+#[swirl::background_job]
+pub fn update_usage_stats(conn: &PgConnection) -> Result<(), PerformError> {
+    let (keywords, categories) = conn.transaction::<_, diesel::result::Error, _>(|| {
+        let keywords = diesel::sql_query(include_str!("update_keyword_stats.sql")).execute(conn)?;
+        let categories =
+            diesel::sql_query(include_str!("update_category_stats.sql")).execute(conn)?;
+        Ok((keywords, categories))
+    })?;
+    println!(
+        "Recorded the usage of {} keywords and {} categories",
+        keywords, categories
+    );
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::models::{Dependency, Version, VersionDiff, VersionFile};
+use crate::schema::versions;
+use crate::views::{
+    EncodableDependenciesDiff, EncodableDiffDependency, EncodableNamesDiff, EncodableVersionDiff,
+};
+
+/// Dependencies are matched across versions by crate name, kind and target.
+type DependencyKey = (String, u32, Option<String>);
+
+/// Computes the differences between two versions of a crate requested from the
+/// `GET /crates/:crate_id/diff` endpoint, and stores them for the following requests.
+#[swirl::background_job]
+pub fn compute_version_diff(
+    conn: &PgConnection,
+    from_version_id: i32,
+    to_version_id: i32,
+) -> Result<(), PerformError> {
+    let from: Version = versions::table.find(from_version_id).first(conn)?;
+    let to: Version = versions::table.find(to_version_id).first(conn)?;
+    let versions = vec![from, to];
+
+    let mut dependencies =
+        Dependency::for_versions(conn, &versions)?
+            .into_iter()
+            .map(|dependencies| {
+                dependencies
+                    .into_iter()
+                    .map(diff_dependency)
+                    .collect::<BTreeMap<_, _>>()
+            });
+    let mut from_dependencies = dependencies.next().unwrap_or_default();
+    let mut to_dependencies = dependencies.next().unwrap_or_default();
+
+    let mut files = VersionFile::belonging_to(&versions)
+        .load::<VersionFile>(conn)?
+        .grouped_by(&versions)
+        .into_iter()
+        .map(|files| {
+            files
+                .into_iter()
+                .map(|file| (file.path, file.sha256))
+                .collect::<BTreeMap<_, _>>()
+        });
+    let from_files = files.next().unwrap_or_default();
+    let to_files = files.next().unwrap_or_default();
+
+    let (from, to) = (&versions[0], &versions[1]);
+    let from_features: BTreeMap<String, Vec<String>> =
+        serde_json::from_value(from.features.clone()).unwrap_or_default();
+    let to_features: BTreeMap<String, Vec<String>> =
+        serde_json::from_value(to.features.clone()).unwrap_or_default();
+
+    let (added, removed, changed) = diff_keys(&from_dependencies, &to_dependencies, |a, b| {
+        a.req != b.req
+            || a.optional != b.optional
+            || a.default_features != b.default_features
+            || a.features != b.features
+    });
+    let dependencies = EncodableDependenciesDiff {
+        added: added
+            .iter()
+            .filter_map(|key| to_dependencies.remove(key))
+            .collect(),
+        removed: removed
+            .iter()
+            .filter_map(|key| from_dependencies.remove(key))
+            .collect(),
+        changed: changed
+            .iter()
+            .filter_map(|key| {
+                let mut dependency = to_dependencies.remove(key)?;
+                dependency.previous_req = from_dependencies.get(key).map(|d| d.req.clone());
+                Some(dependency)
+            })
+            .collect(),
+    };
+
+    let (added, removed, changed) = diff_keys(&from_features, &to_features, |a, b| a != b);
+    let features = EncodableNamesDiff {
+        added,
+        removed,
+        changed,
+    };
+
+    let (added, removed, changed) = diff_keys(&from_files, &to_files, |a, b| a != b);
+    let files = EncodableNamesDiff {
+        added,
+        removed,
+        changed,
+    };
+
+    let diff = EncodableVersionDiff {
+        from: from.num.to_string(),
+        to: to.num.to_string(),
+        dependencies,
+        features,
+        files,
+    };
+    VersionDiff::record(conn, from.id, to.id, serde_json::to_value(&diff)?)?;
+    Ok(())
+}
+
+fn diff_dependency(
+    (dependency, name): (Dependency, String),
+) -> (DependencyKey, EncodableDiffDependency) {
+    let key = (
+        name.clone(),
+        dependency.kind as u32,
+        dependency.target.clone(),
+    );
+    let dependency = EncodableDiffDependency {
+        name,
+        req: dependency.req.to_string(),
+        kind: dependency.kind,
+        target: dependency.target,
+        optional: dependency.optional,
+        default_features: dependency.default_features,
+        features: dependency.features,
+        previous_req: None,
+    };
+    (key, dependency)
+}
+
+/// Compares two maps by key, returning the keys only in `to`, the keys only in `from`, and the
+/// keys of both whose values differ according to `is_changed`.
+fn diff_keys<K: Ord + Clone, V>(
+    from: &BTreeMap<K, V>,
+    to: &BTreeMap<K, V>,
+    is_changed: impl Fn(&V, &V) -> bool,
+) -> (Vec<K>, Vec<K>, Vec<K>) {
+    let added = to
+        .keys()
+        .filter(|key| !from.contains_key(key))
+        .cloned()
+        .collect();
+    let removed = from
+        .keys()
+        .filter(|key| !to.contains_key(key))
+        .cloned()
+        .collect();
+    let changed = to
+        .iter()
+        .filter(|(key, value)| from.get(key).map_or(false, |from| is_changed(from, value)))
+        .map(|(key, _)| key.clone())
+        .collect();
+    (added, removed, changed)
+}
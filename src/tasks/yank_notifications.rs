@@ -0,0 +1,120 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use swirl::PerformError;
+
+use crate::email;
+use crate::models::{CrateOwner, OwnerKind, User};
+use crate::schema::{crate_owners, crates, dependency_yank_notifications, users, versions};
+
+/// The dependencies of the latest non-yanked version of each crate on a crate
+const LATEST_DEPENDENTS_QUERY: &str = r#"
+SELECT crates.id AS crate_id, crates.name AS crate_name, dependencies.req
+FROM (
+    SELECT DISTINCT ON (crate_id) id, crate_id
+    FROM versions
+    WHERE NOT yanked
+      AND crate_id IN (
+        SELECT versions.crate_id
+        FROM dependencies
+        INNER JOIN versions
+          ON versions.id = dependencies.version_id
+        WHERE dependencies.crate_id = $1
+      )
+    ORDER BY crate_id, created_at DESC
+) latest
+INNER JOIN dependencies
+  ON dependencies.version_id = latest.id
+INNER JOIN crates
+  ON crates.id = latest.crate_id
+WHERE dependencies.crate_id = $1
+"#;
+
+#[derive(QueryableByName, Debug)]
+pub(super) struct Dependent {
+    #[sql_type = "Integer"]
+    pub(super) crate_id: i32,
+    #[sql_type = "Text"]
+    pub(super) crate_name: String,
+    #[sql_type = "Text"]
+    pub(super) req: String,
+}
+
+/// Emails the owners of the crates whose latest version depends on a version which was
+/// yanked, if they enabled the notifications of the dependent crate.
+///
+/// The notifications are recorded, so that owners are told only once per yanked version and
+/// dependent crate, even if the job is retried or the version is yanked again.
+#[swirl::background_job]
+pub fn notify_yanked_dependents(conn: &PgConnection, version_id: i32) -> Result<(), PerformError> {
+    let (crate_id, crate_name, num, yanked): (i32, String, String, bool) = versions::table
+        .find(version_id)
+        .inner_join(crates::table)
+        .select((crates::id, crates::name, versions::num, versions::yanked))
+        .first(conn)?;
+    if !yanked {
+        // The version was unyanked before the job ran
+        return Ok(());
+    }
+    let yanked_version = semver::Version::parse(&num)?;
+
+    let dependents = latest_dependents(conn, crate_id, &yanked_version)?;
+
+    let mut notified = 0;
+    for dependent in dependents {
+        let owners: Vec<User> = CrateOwner::by_owner_kind(OwnerKind::User)
+            .filter(crate_owners::crate_id.eq(dependent.crate_id))
+            .filter(crate_owners::email_notifications.eq(true))
+            .inner_join(users::table)
+            .select(users::all_columns)
+            .load(conn)?;
+        for owner in owners {
+            let email = match owner.verified_email(conn)? {
+                Some(email) => email,
+                None => continue,
+            };
+            let inserted = diesel::insert_into(dependency_yank_notifications::table)
+                .values((
+                    dependency_yank_notifications::version_id.eq(version_id),
+                    dependency_yank_notifications::crate_id.eq(dependent.crate_id),
+                    dependency_yank_notifications::user_id.eq(owner.id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            if inserted > 0 {
+                email::send_dependency_yanked_email(
+                    &email,
+                    &dependent.crate_name,
+                    &crate_name,
+                    &num,
+                );
+                notified += 1;
+            }
+        }
+    }
+    println!(
+        "Notified {} owners that {}#{} was yanked",
+        notified, crate_name, num
+    );
+    Ok(())
+}
+
+/// Returns the crates whose latest non-yanked version depends on a crate, with a requirement
+/// matching the given version of it.
+pub(super) fn latest_dependents(
+    conn: &PgConnection,
+    crate_id: i32,
+    version: &semver::Version,
+) -> QueryResult<Vec<Dependent>> {
+    let dependents = diesel::sql_query(LATEST_DEPENDENTS_QUERY)
+        .bind::<Integer, _>(crate_id)
+        .load::<Dependent>(conn)?
+        .into_iter()
+        .filter(
+            |dependent| match semver::VersionReq::parse(&dependent.req) {
+                Ok(req) => req.matches(version),
+                Err(_) => false,
+            },
+        )
+        .collect();
+    Ok(dependents)
+}
@@ -1,7 +1,31 @@
-#![cfg(test)]
+//! Helpers shared by the unit tests and the integration tests
+//!
+//! The builders create records in the database with sensible defaults, so that a test only
+//! spells out the fields it cares about.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use diesel::prelude::*;
 
+mod dependency;
+mod krate;
+mod publish;
+mod user;
+mod version;
+
+pub use dependency::DependencyBuilder;
+pub use krate::CrateBuilder;
+pub use publish::PublishBuilder;
+pub use user::UserBuilder;
+pub use version::VersionBuilder;
+
+static NEXT_GH_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Returns a GitHub ID which wasn't used by another record created in this process.
+pub fn next_gh_id() -> i32 {
+    NEXT_GH_ID.fetch_add(1, Ordering::SeqCst) as i32
+}
+
 pub fn pg_connection_no_transaction() -> PgConnection {
     let database_url =
         dotenv::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set to run tests");
@@ -1,11 +1,14 @@
-use cargo_registry::views::krate_publish as u;
+use crate::views::krate_publish as u;
 
 /// A builder for constructing a dependency of another crate.
+#[derive(Debug)]
 pub struct DependencyBuilder {
     explicit_name_in_toml: Option<u::EncodableCrateName>,
     name: String,
     registry: Option<String>,
     version_req: u::EncodableCrateVersionReq,
+    artifact: Option<Vec<String>>,
+    bindep_target: Option<String>,
 }
 
 impl DependencyBuilder {
@@ -16,6 +19,8 @@ impl DependencyBuilder {
             name: name.to_string(),
             registry: None,
             version_req: u::EncodableCrateVersionReq(semver::VersionReq::parse("> 0").unwrap()),
+            artifact: None,
+            bindep_target: None,
         }
     }
 
@@ -31,6 +36,18 @@ impl DependencyBuilder {
         self
     }
 
+    /// Make this an artifact dependency requesting the given artifact kinds.
+    pub fn artifact(mut self, kinds: &[&str]) -> Self {
+        self.artifact = Some(kinds.iter().map(|kind| kind.to_string()).collect());
+        self
+    }
+
+    /// Set the target the artifacts of this dependency are built for.
+    pub fn bindep_target(mut self, target: &str) -> Self {
+        self.bindep_target = Some(target.to_string());
+        self
+    }
+
     /// Set the version requirement for this dependency.
     ///
     /// # Panics
@@ -58,6 +75,9 @@ impl DependencyBuilder {
             kind: None,
             explicit_name_in_toml: self.explicit_name_in_toml,
             registry: self.registry,
+            artifact: self.artifact,
+            bindep_target: self.bindep_target,
+            lib: false,
         }
     }
 }
@@ -1,4 +1,4 @@
-use cargo_registry::{
+use crate::{
     models::{Category, Crate, Keyword, NewCrate},
     schema::{crates, version_downloads},
     util::errors::AppResult,
@@ -12,6 +12,7 @@ use super::VersionBuilder;
 /// A builder to create crate records for the purpose of inserting directly into the database.
 /// If you want to test logic that happens as part of a publish request, use `PublishBuilder`
 /// instead.
+#[derive(Debug)]
 pub struct CrateBuilder<'a> {
     categories: Vec<&'a str>,
     downloads: Option<i32>,
@@ -124,7 +125,7 @@ impl<'a> CrateBuilder<'a> {
         if let Some(downloads) = self.downloads {
             krate = update(&krate)
                 .set(crates::downloads.eq(downloads))
-                .returning(cargo_registry::models::krate::ALL_COLUMNS)
+                .returning(crate::models::krate::ALL_COLUMNS)
                 .get_result(connection)?;
         }
 
@@ -162,7 +163,7 @@ impl<'a> CrateBuilder<'a> {
         if let Some(updated_at) = self.updated_at {
             krate = update(&krate)
                 .set(crates::updated_at.eq(updated_at))
-                .returning(cargo_registry::models::krate::ALL_COLUMNS)
+                .returning(crate::models::krate::ALL_COLUMNS)
                 .get_result(connection)?;
         }
 
@@ -1,28 +1,26 @@
-use cargo_registry::views::krate_publish as u;
+use crate::views::krate_publish as u;
 use std::{collections::HashMap, io::Read};
 
 use flate2::{write::GzEncoder, Compression};
 
 use super::DependencyBuilder;
 
-lazy_static! {
-    // The bytes of an empty tarball is not an empty vector of bytes because of tarball headers.
-    // Unless files are added to a PublishBuilder, the `.crate` tarball that gets uploaded
-    // will be empty, so precompute the empty tarball bytes to use as a default.
-    static ref EMPTY_TARBALL_BYTES: Vec<u8> = {
-        let mut empty_tarball = vec![];
-        {
-            let mut ar =
-                tar::Builder::new(GzEncoder::new(&mut empty_tarball, Compression::default()));
-            assert_ok!(ar.finish());
-        }
-        empty_tarball
-    };
+/// The bytes of an empty tarball, which is not an empty vector of bytes because of tarball
+/// headers. Unless files are added to a PublishBuilder, the `.crate` tarball that gets uploaded
+/// will be empty.
+fn empty_tarball() -> Vec<u8> {
+    let mut empty_tarball = vec![];
+    {
+        let mut ar = tar::Builder::new(GzEncoder::new(&mut empty_tarball, Compression::default()));
+        ar.finish().unwrap();
+    }
+    empty_tarball
 }
 
 /// A builder for constructing a crate for the purposes of testing publishing. If you only need
 /// a crate to exist and don't need to test behavior caused by the publish request, inserting
 /// a crate into the database directly by using CrateBuilder will be faster.
+#[derive(Debug)]
 pub struct PublishBuilder {
     authors: Vec<String>,
     badges: HashMap<String, HashMap<String, String>>,
@@ -30,11 +28,13 @@ pub struct PublishBuilder {
     deps: Vec<u::EncodableCrateDependency>,
     desc: Option<String>,
     doc_url: Option<String>,
+    features: HashMap<String, Vec<String>>,
     keywords: Vec<String>,
     pub krate_name: String,
     license: Option<String>,
     license_file: Option<String>,
     readme: Option<String>,
+    repository: Option<String>,
     tarball: Vec<u8>,
     version: semver::Version,
 }
@@ -50,12 +50,14 @@ impl PublishBuilder {
             deps: vec![],
             desc: Some("description".to_string()),
             doc_url: None,
+            features: HashMap::new(),
             keywords: vec![],
             krate_name: krate_name.into(),
             license: Some("MIT".to_string()),
             license_file: None,
             readme: None,
-            tarball: EMPTY_TARBALL_BYTES.to_vec(),
+            repository: None,
+            tarball: empty_tarball(),
             version: semver::Version::parse("1.0.0").unwrap(),
         }
     }
@@ -88,12 +90,12 @@ impl PublishBuilder {
             let mut ar = tar::Builder::new(GzEncoder::new(&mut tarball, Compression::default()));
             for &mut (name, ref mut data, size) in files {
                 let mut header = tar::Header::new_gnu();
-                assert_ok!(header.set_path(name));
+                header.set_path(name).unwrap();
                 header.set_size(size);
                 header.set_cksum();
-                assert_ok!(ar.append(&header, data));
+                ar.append(&header, data).unwrap();
             }
-            assert_ok!(ar.finish());
+            ar.finish().unwrap();
         }
 
         self.tarball = tarball;
@@ -113,6 +115,13 @@ impl PublishBuilder {
         self
     }
 
+    /// Add a feature enabling the given features and optional dependencies
+    pub fn feature(mut self, name: &str, enables: &[&str]) -> Self {
+        let enables = enables.iter().map(|feature| feature.to_string()).collect();
+        self.features.insert(name.into(), enables);
+        self
+    }
+
     /// Set the description of this crate
     pub fn description(mut self, description: &str) -> Self {
         self.desc = Some(description.to_string());
@@ -137,6 +146,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Set the repository URL of this crate
+    pub fn repository(mut self, repository: &str) -> Self {
+        self.repository = Some(repository.to_string());
+        self
+    }
+
     /// Add a keyword to this crate.
     pub fn keyword(mut self, keyword: &str) -> Self {
         self.keywords.push(keyword.into());
@@ -156,6 +171,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Set the license of this crate
+    pub fn license(mut self, license: &str) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
     /// Remove the license from this crate. Publish will fail unless license or license file is set.
     pub fn unset_license(mut self) -> Self {
         self.license = None;
@@ -185,7 +206,14 @@ impl PublishBuilder {
         let new_crate = u::EncodableCrateUpload {
             name: u::EncodableCrateName(self.krate_name.clone()),
             vers: u::EncodableCrateVersion(self.version),
-            features: HashMap::new(),
+            features: self
+                .features
+                .into_iter()
+                .map(|(name, enables)| {
+                    let enables = enables.into_iter().map(u::EncodableFeature).collect();
+                    (u::EncodableFeatureName(name), enables)
+                })
+                .collect(),
             deps: self.deps,
             authors: self.authors,
             description: self.desc,
@@ -204,7 +232,7 @@ impl PublishBuilder {
             ),
             license: self.license,
             license_file: self.license_file,
-            repository: None,
+            repository: self.repository,
             badges: Some(self.badges),
             links: None,
         };
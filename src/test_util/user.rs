@@ -0,0 +1,86 @@
+use crate::{
+    models::{NewUser, User},
+    schema::{emails, users},
+    util::errors::AppResult,
+};
+
+use diesel::prelude::*;
+
+use super::next_gh_id;
+
+/// A builder to create user records for the purpose of inserting directly into the database.
+#[derive(Debug)]
+pub struct UserBuilder<'a> {
+    admin: bool,
+    email: Option<&'a str>,
+    user: NewUser<'a>,
+}
+
+impl<'a> UserBuilder<'a> {
+    /// Create a new instance with the given login and a GitHub ID unused by the other records.
+    pub fn new(login: &'a str) -> Self {
+        UserBuilder {
+            admin: false,
+            email: None,
+            user: NewUser::new(next_gh_id(), login, None, None, "some random token"),
+        }
+    }
+
+    /// Sets the user's display name.
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.user.name = Some(name);
+        self
+    }
+
+    /// Gives the user a verified email address.
+    pub fn email(mut self, email: &'a str) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// Sets the user's GitHub ID. Users with the same GitHub ID are the same account.
+    pub fn gh_id(mut self, gh_id: i32) -> Self {
+        self.user.gh_id = gh_id;
+        self
+    }
+
+    /// Makes the user an admin of the instance.
+    pub fn admin(mut self) -> Self {
+        self.admin = true;
+        self
+    }
+
+    pub fn build(self, connection: &PgConnection) -> AppResult<User> {
+        let mut user = self.user.create_or_update(None, connection)?;
+
+        if let Some(email) = self.email {
+            diesel::insert_into(emails::table)
+                .values((
+                    emails::user_id.eq(user.id),
+                    emails::email.eq(email),
+                    emails::verified.eq(true),
+                ))
+                .execute(connection)?;
+        }
+
+        if self.admin {
+            user = diesel::update(&user)
+                .set(users::is_admin.eq(true))
+                .get_result(connection)?;
+        }
+
+        Ok(user)
+    }
+
+    /// Consumes the builder to make a user record in the database.
+    ///
+    /// # Panics
+    ///
+    /// Panics (and fails the test) if any part of inserting the user record fails.
+    #[track_caller]
+    pub fn expect_build(self, connection: &PgConnection) -> User {
+        self.build(connection).unwrap_or_else(|e| {
+            panic!("Unable to create user: {:?}", e);
+        })
+    }
+}
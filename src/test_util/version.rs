@@ -1,4 +1,4 @@
-use cargo_registry::{
+use crate::{
     models::{Crate, NewVersion, Version},
     schema::{dependencies, versions},
     util::errors::AppResult,
@@ -9,6 +9,7 @@ use chrono::NaiveDateTime;
 use diesel::prelude::*;
 
 /// A builder to create version records for the purpose of inserting directly into the database.
+#[derive(Debug)]
 pub struct VersionBuilder<'a> {
     created_at: Option<NaiveDateTime>,
     dependencies: Vec<(i32, Option<&'static str>)>,
@@ -16,6 +17,7 @@ pub struct VersionBuilder<'a> {
     license: Option<&'a str>,
     license_file: Option<&'a str>,
     num: semver::Version,
+    rust_version: Option<&'a str>,
     size: i32,
     yanked: bool,
 }
@@ -39,6 +41,7 @@ impl<'a> VersionBuilder<'a> {
             license: None,
             license_file: None,
             num,
+            rust_version: None,
             size: 0,
             yanked: false,
         }
@@ -56,6 +59,12 @@ impl<'a> VersionBuilder<'a> {
         self
     }
 
+    /// Sets the version's `rust_version` value.
+    pub fn rust_version(mut self, rust_version: &'a str) -> Self {
+        self.rust_version = Some(rust_version);
+        self
+    }
+
     /// Adds a dependency to this version.
     pub fn dependency(mut self, dependency: &Crate, target: Option<&'static str>) -> Self {
         self.dependencies.push((dependency.id, target));
@@ -103,6 +112,11 @@ impl<'a> VersionBuilder<'a> {
                 .get_result(connection)?;
         }
 
+        if let Some(rust_version) = self.rust_version {
+            vers.record_rust_version(connection, rust_version)?;
+            vers.rust_version = Some(rust_version.to_owned());
+        }
+
         if let Some(created_at) = self.created_at {
             vers = update(&vers)
                 .set(versions::created_at.eq(created_at))
@@ -5,16 +5,21 @@ extern crate claim;
 #[macro_use]
 extern crate diesel;
 #[macro_use]
-extern crate lazy_static;
-#[macro_use]
 extern crate serde;
 #[macro_use]
 extern crate serde_json;
 
 use crate::util::{Bad, RequestHelper, TestApp};
 use cargo_registry::{
+    config::{
+        CacheConfig, CpuPoolConfig, DbPoolConfig, FastBoot, SearchBackendConfig, SearchConfig,
+        ServerConfig,
+    },
+    http_client::Replayer,
     models::{Crate, CrateOwner, Dependency, NewCategory, NewTeam, NewUser, Team, User, Version},
+    rate_limit::LimitedAction,
     schema::crate_owners,
+    test_util::next_gh_id,
     util::AppResponse,
     views::{
         EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate, EncodableKeyword,
@@ -22,13 +27,7 @@ use cargo_registry::{
     },
     App, Config, Env, Replica, Uploader,
 };
-use std::{
-    borrow::Cow,
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
-};
+use std::{borrow::Cow, sync::Arc};
 
 use conduit::{header, Body};
 use conduit_test::MockRequest;
@@ -38,18 +37,25 @@ use reqwest::{blocking::Client, Proxy};
 mod account_lock;
 mod authentication;
 mod badge;
-mod builders;
+mod cache;
 mod categories;
 mod category;
 mod dump_db;
+mod feature_flags;
 mod git;
 mod keyword;
 mod krate;
 mod owners;
+mod quarantine;
 mod read_only_mode;
+mod readme_preview;
+mod readme_rerenders;
 mod record;
+mod reserved_crate_names;
 mod schema_details;
 mod server;
+mod snapshot;
+mod storage;
 mod team;
 mod token;
 mod user;
@@ -66,6 +72,7 @@ struct CrateMeta {
     total: i32,
     next_page: Option<String>,
     prev_page: Option<String>,
+    did_you_mean: Option<String>,
 }
 #[derive(Deserialize)]
 pub struct CrateResponse {
@@ -105,7 +112,7 @@ pub struct OkBool {
 }
 
 fn app() -> (Arc<App>, conduit_middleware::MiddlewareBuilder) {
-    build_app(simple_config(), None)
+    build_app(simple_config(), None, None)
 }
 
 fn simple_config() -> Config {
@@ -136,16 +143,30 @@ fn simple_config() -> Config {
         // When testing we route all API traffic over HTTP so we can
         // sniff/record it, but everywhere else we use https
         api_protocol: String::from("http"),
-        publish_rate_limit: Default::default(),
+        publish_rate_limit: LimitedAction::PublishNew.default_rate_limit(),
+        readme_preview_rate_limit: LimitedAction::ReadmePreview.default_rate_limit(),
         blocked_traffic: Default::default(),
         domain_name: "crates.io".into(),
         allowed_origins: Vec::new(),
+        upstream: None,
+        oidc: None,
+        render: Default::default(),
+        db_pool: DbPoolConfig::for_env(Env::Test),
+        cpu_pool: CpuPoolConfig::default(),
+        cache: CacheConfig::default(),
+        search: SearchConfig::default(),
+        search_backend: SearchBackendConfig::default(),
+        server: ServerConfig::default(),
+        fastboot: FastBoot::Disabled,
+        maintenance_mode: false,
+        spam_score_threshold: cargo_registry::spam::DEFAULT_THRESHOLD,
     }
 }
 
 fn build_app(
     config: Config,
     proxy: Option<String>,
+    replay: Option<Replayer>,
 ) -> (Arc<App>, conduit_middleware::MiddlewareBuilder) {
     let client = if let Some(proxy) = proxy {
         let mut builder = Client::builder();
@@ -156,7 +177,10 @@ fn build_app(
         None
     };
 
-    let app = App::new(config, client);
+    let mut app = App::new(config, client);
+    if let Some(replay) = replay {
+        app = app.with_api_client(replay);
+    }
     assert_ok!(assert_ok!(app.primary_database.get()).begin_test_transaction());
     let app = Arc::new(app);
     let handler = cargo_registry::build_handler(Arc::clone(&app));
@@ -212,11 +236,9 @@ where
     }
 }
 
-static NEXT_GH_ID: AtomicUsize = AtomicUsize::new(0);
-
 fn new_user(login: &str) -> NewUser<'_> {
     NewUser {
-        gh_id: NEXT_GH_ID.fetch_add(1, Ordering::SeqCst) as i32,
+        gh_id: next_gh_id(),
         gh_login: login,
         name: None,
         gh_avatar: None,
@@ -226,8 +248,8 @@ fn new_user(login: &str) -> NewUser<'_> {
 
 fn new_team(login: &str) -> NewTeam<'_> {
     NewTeam {
-        org_id: NEXT_GH_ID.fetch_add(1, Ordering::SeqCst) as i32,
-        github_id: NEXT_GH_ID.fetch_add(1, Ordering::SeqCst) as i32,
+        org_id: next_gh_id(),
+        github_id: next_gh_id(),
         login,
         name: None,
         avatar: None,
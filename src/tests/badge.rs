@@ -1,5 +1,8 @@
-use crate::{builders::CrateBuilder, TestApp};
-use cargo_registry::models::{Badge, Crate, MaintenanceStatus};
+use crate::TestApp;
+use cargo_registry::{
+    models::{Badge, Crate, MaintenanceStatus},
+    test_util::CrateBuilder,
+};
 use std::collections::HashMap;
 
 struct BadgeRef {
@@ -0,0 +1,108 @@
+use crate::{util::RequestHelper, CrateResponse, GoodCrate, OkBool, TestApp};
+use cargo_registry::{
+    config::CacheBackend,
+    schema::crates,
+    test_util::{CrateBuilder, PublishBuilder},
+    uploaders::{MemoryStorage, Uploader},
+    views::EncodableKeyword,
+};
+
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct KeywordList {
+    keywords: Vec<EncodableKeyword>,
+}
+
+fn cached_app() -> TestApp {
+    let (app, _) = TestApp::full()
+        .with_config(|config| {
+            config.cache.backend = CacheBackend::Memory;
+            config.uploader = Uploader::Memory(MemoryStorage::new());
+        })
+        .empty();
+    app
+}
+
+#[test]
+fn crate_is_cached_until_a_version_is_published() {
+    let app = cached_app();
+    let user = app.db_new_user("foo");
+
+    let _: GoodCrate = user
+        .enqueue_publish(PublishBuilder::new("foo_cached").version("1.0.0"))
+        .good();
+    let json = user.show_crate("foo_cached");
+    assert_eq!(json.krate.downloads, 0);
+
+    app.db(|conn| {
+        diesel::update(crates::table.filter(crates::name.eq("foo_cached")))
+            .set(crates::downloads.eq(100))
+            .execute(conn)
+            .unwrap();
+    });
+    let json = user.show_crate("foo_cached");
+    assert_eq!(json.krate.downloads, 0);
+    // The cache key doesn't depend on how the name is written
+    let json = user.show_crate("Foo-Cached");
+    assert_eq!(json.krate.downloads, 0);
+
+    let _: GoodCrate = user
+        .enqueue_publish(PublishBuilder::new("foo_cached").version("1.1.0"))
+        .good();
+    let json = user.show_crate("foo_cached");
+    assert_eq!(json.krate.downloads, 100);
+    assert_eq!(json.krate.max_version, "1.1.0");
+}
+
+#[test]
+fn yanking_invalidates_the_cached_crate() {
+    let app = cached_app();
+    let user = app.db_new_user("foo");
+    let token = user.db_new_token("bar");
+
+    let _: GoodCrate = token
+        .enqueue_publish(PublishBuilder::new("foo_cached").version("1.0.0"))
+        .good();
+    app.run_pending_background_jobs();
+    let json: CrateResponse = user.show_crate("foo_cached");
+    assert!(!json.versions[0].yanked);
+
+    let _: OkBool = token.delete("/api/v1/crates/foo_cached/1.0.0/yank").good();
+    app.run_pending_background_jobs();
+    let json: CrateResponse = user.show_crate("foo_cached");
+    assert!(json.versions[0].yanked);
+}
+
+#[test]
+fn keywords_are_cached_until_a_version_is_published() {
+    let app = cached_app();
+    let user = app.db_new_user("foo");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_kw", user.as_model().id)
+            .keyword("kw1")
+            .expect_build(conn);
+    });
+    let json: KeywordList = user.get("/api/v1/keywords").good();
+    assert_eq!(json.keywords.len(), 1);
+
+    app.db(|conn| {
+        CrateBuilder::new("bar_kw", user.as_model().id)
+            .keyword("kw2")
+            .expect_build(conn);
+    });
+    let json: KeywordList = user.get("/api/v1/keywords").good();
+    assert_eq!(json.keywords.len(), 1);
+    // Other pages are cached separately
+    let json: KeywordList = user
+        .get_with_query("/api/v1/keywords", "sort=crates")
+        .good();
+    assert_eq!(json.keywords.len(), 2);
+
+    let _: GoodCrate = user
+        .enqueue_publish(PublishBuilder::new("foo_kw").version("1.1.0"))
+        .good();
+    let json: KeywordList = user.get("/api/v1/keywords").good();
+    assert_eq!(json.keywords.len(), 2);
+}
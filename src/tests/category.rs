@@ -1,7 +1,7 @@
-use crate::{
-    builders::CrateBuilder, new_category, util::MockAnonymousUser, RequestHelper, TestApp,
+use crate::{new_category, util::MockAnonymousUser, RequestHelper, TestApp};
+use cargo_registry::{
+    models::Category, test_util::CrateBuilder, views::EncodableCategoryWithSubcategories,
 };
-use cargo_registry::{models::Category, views::EncodableCategoryWithSubcategories};
 
 #[derive(Deserialize)]
 struct CategoryWithSubcategories {
@@ -58,6 +58,50 @@ fn show() {
     assert_eq!(json.category.subcategories[0].category, "Baz");
 }
 
+#[test]
+fn stats() {
+    use cargo_registry::schema::crates;
+    use cargo_registry::tasks;
+    use diesel::dsl::{now, IntervalDsl};
+    use diesel::prelude::*;
+    use swirl::Job;
+
+    let (app, anon, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+    let user = user.as_model();
+    let url = "/api/v1/categories/foo-bar/stats";
+    anon.get(url).assert_not_found();
+
+    app.db(|conn| {
+        assert_ok!(new_category("Foo Bar", "foo-bar", "Foo Bar crates").create_or_update(conn));
+        assert_ok!(
+            new_category("Foo Bar::Baz", "foo-bar::baz", "Baz crates").create_or_update(conn)
+        );
+        CrateBuilder::new("foo_in_category", user.id)
+            .category("foo-bar")
+            .expect_build(conn);
+        CrateBuilder::new("foo_in_subcategory", user.id)
+            .category("foo-bar::baz")
+            .expect_build(conn);
+        diesel::update(crates::table)
+            .set(crates::created_at.eq(now - 2.days()))
+            .execute(conn)
+            .unwrap();
+
+        tasks::update_usage_stats().enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    // The crates of the subcategories aren't counted
+    let json: serde_json::Value = anon.get(url).good();
+    let stats = json["stats"].as_array().unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0]["crates_cnt"], 1);
+    assert_eq!(stats[0]["downloads"], 0);
+}
+
 #[test]
 #[allow(clippy::cognitive_complexity)]
 fn update_crate() {
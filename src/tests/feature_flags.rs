@@ -0,0 +1,140 @@
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use cargo_registry::{
+    test_util::{CrateBuilder, PublishBuilder, VersionBuilder},
+    views::{EncodableFeatureFlag, EncodableVersionDownload},
+};
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct FeatureFlagList {
+    feature_flags: Vec<EncodableFeatureFlag>,
+}
+
+#[derive(Deserialize)]
+struct FeatureFlagResponse {
+    feature_flag: EncodableFeatureFlag,
+}
+
+#[derive(Deserialize)]
+struct Downloads {
+    version_downloads: Vec<EncodableVersionDownload>,
+}
+
+fn make_admin(app: &TestApp, user: &MockCookieUser) {
+    use cargo_registry::schema::users;
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn set_flag(user: &MockCookieUser, name: &str, enabled: bool) -> EncodableFeatureFlag {
+    let url = format!("/api/v1/admin/feature_flags/{}", name);
+    let body = json!({ "enabled": enabled }).to_string();
+    let json: FeatureFlagResponse = user.put(&url, body.as_bytes()).good();
+    json.feature_flag
+}
+
+#[test]
+fn only_admins_can_manage_feature_flags() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    user.get::<()>("/api/v1/admin/feature_flags")
+        .assert_forbidden();
+
+    let body = json!({ "enabled": true }).to_string();
+    user.put::<()>(
+        "/api/v1/admin/feature_flags/degraded_downloads",
+        body.as_bytes(),
+    )
+    .assert_forbidden();
+}
+
+#[test]
+fn toggle_feature_flag() {
+    let (app, _, user) = TestApp::init().with_user();
+    make_admin(&app, &user);
+
+    let json: FeatureFlagList = user.get("/api/v1/admin/feature_flags").good();
+    let flag = json
+        .feature_flags
+        .iter()
+        .find(|flag| flag.name == "degraded_downloads")
+        .unwrap();
+    assert!(!flag.enabled);
+    assert_none!(flag.updated_at);
+
+    let flag = set_flag(&user, "degraded_downloads", true);
+    assert!(flag.enabled);
+    assert_eq!(flag.updated_by.as_deref(), Some("foo"));
+
+    let flag = set_flag(&user, "degraded_downloads", false);
+    assert!(!flag.enabled);
+}
+
+#[test]
+fn unknown_feature_flag() {
+    let (app, _, user) = TestApp::init().with_user();
+    make_admin(&app, &user);
+
+    let body = json!({ "enabled": true }).to_string();
+    user.put::<()>("/api/v1/admin/feature_flags/unknown", body.as_bytes())
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error("unknown feature flag `unknown`");
+}
+
+#[test]
+fn degraded_downloads_are_not_counted() {
+    let (app, anon, user) = TestApp::init().with_user();
+    make_admin(&app, &user);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_degraded", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let assert_dl_count = |count: i32| {
+        let downloads: Downloads = anon.get("/api/v1/crates/foo_degraded/downloads").good();
+        let total_downloads = downloads
+            .version_downloads
+            .iter()
+            .map(|vd| vd.downloads)
+            .sum::<i32>();
+        assert_eq!(total_downloads, count);
+    };
+
+    set_flag(&user, "degraded_downloads", true);
+    anon.get::<()>("/api/v1/crates/foo_degraded/1.0.0/download")
+        .assert_redirect_ends_with("/crates/foo_degraded/foo_degraded-1.0.0.crate");
+    assert_dl_count(0);
+
+    set_flag(&user, "degraded_downloads", false);
+    anon.get::<()>("/api/v1/crates/foo_degraded/1.0.0/download")
+        .assert_status(StatusCode::FOUND);
+    assert_dl_count(1);
+}
+
+#[test]
+fn strict_publish_checks_require_a_repository() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    make_admin(&app, &user);
+    set_flag(&user, "strict_publish_checks", true);
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_strict"))
+        .bad_with_status(StatusCode::OK)
+        .assert_error(
+            "missing or empty metadata fields: repository. The `repository` field \
+             is required to publish crates to this registry.",
+        );
+
+    let crate_to_publish =
+        PublishBuilder::new("foo_strict").repository("https://github.com/foo/foo_strict");
+    token.enqueue_publish(crate_to_publish).good();
+}
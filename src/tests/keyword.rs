@@ -1,5 +1,5 @@
-use crate::{builders::CrateBuilder, RequestHelper, TestApp};
-use cargo_registry::{models::Keyword, views::EncodableKeyword};
+use crate::{RequestHelper, TestApp};
+use cargo_registry::{models::Keyword, test_util::CrateBuilder, views::EncodableKeyword};
 
 #[derive(Deserialize)]
 struct KeywordList {
@@ -110,3 +110,64 @@ fn update_crate() {
     assert_eq!(cnt("kw1"), 0);
     assert_eq!(cnt("kw2"), 0);
 }
+
+#[test]
+fn stats() {
+    use cargo_registry::schema::{crates, version_downloads, versions};
+    use cargo_registry::tasks;
+    use diesel::dsl::{date, now, IntervalDsl};
+    use diesel::prelude::*;
+    use swirl::Job;
+
+    let (app, anon, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+    let user = user.as_model();
+    anon.get("/api/v1/keywords/kw1/stats").assert_not_found();
+
+    app.db(|conn| {
+        let old = CrateBuilder::new("foo_old", user.id)
+            .version("1.0.0")
+            .keyword("kw1")
+            .expect_build(conn);
+        // Crates created today are counted tomorrow
+        CrateBuilder::new("foo_new", user.id)
+            .keyword("kw1")
+            .expect_build(conn);
+        diesel::update(crates::table.find(old.id))
+            .set(crates::created_at.eq(now - 2.days()))
+            .execute(conn)
+            .unwrap();
+
+        let version_id: i32 = versions::table
+            .filter(versions::crate_id.eq(old.id))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        diesel::insert_into(version_downloads::table)
+            .values(&vec![
+                (
+                    version_downloads::version_id.eq(version_id),
+                    version_downloads::downloads.eq(7),
+                    version_downloads::date.eq(date(now - 1.days())),
+                ),
+                (
+                    version_downloads::version_id.eq(version_id),
+                    version_downloads::downloads.eq(100),
+                    version_downloads::date.eq(date(now - 2.days())),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+
+        tasks::update_usage_stats().enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon.get("/api/v1/keywords/kw1/stats").good();
+    let stats = json["stats"].as_array().unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0]["crates_cnt"], 1);
+    assert_eq!(stats[0]["downloads"], 7);
+}
@@ -1,14 +1,14 @@
 use crate::{
-    builders::{CrateBuilder, DependencyBuilder, PublishBuilder, VersionBuilder},
     new_category, new_dependency, new_user, CrateMeta, CrateResponse, GoodCrate, OkBool,
     RequestHelper, TestApp,
 };
 use cargo_registry::{
     models::{krate::MAX_NAME_LENGTH, Category, Crate},
-    schema::{api_tokens, crates, emails, metadata, versions, versions_published_by},
+    schema::{api_tokens, crates, emails, metadata, users, versions, versions_published_by},
+    test_util::{CrateBuilder, DependencyBuilder, PublishBuilder, VersionBuilder},
     views::{
-        EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
-        EncodableVersionDownload,
+        EncodableCategory, EncodableCrate, EncodableDependency, EncodableFullVersion,
+        EncodableKeyword, EncodableVersion, EncodableVersionDownload,
     },
 };
 use std::{
@@ -19,7 +19,7 @@ use std::{
 };
 
 use chrono::Utc;
-use conduit::StatusCode;
+use conduit::{header, StatusCode};
 use diesel::{dsl::*, prelude::*, update};
 use flate2::{write::GzEncoder, Compression};
 
@@ -204,6 +204,70 @@ fn index_queries() {
     assert_eq!(cl.meta.total, 0);
 }
 
+#[test]
+fn search_by_several_categories_and_keywords() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        new_category("Asynchronous", "asynchronous", "Async crates")
+            .create_or_update(conn)
+            .unwrap();
+        new_category("Database", "database", "Database crates")
+            .create_or_update(conn)
+            .unwrap();
+        new_category("Database::Drivers", "database::drivers", "Drivers")
+            .create_or_update(conn)
+            .unwrap();
+
+        let both = CrateBuilder::new("async_db", user.id)
+            .keyword("async")
+            .keyword("sql")
+            .expect_build(conn);
+        let driver = CrateBuilder::new("db_driver", user.id)
+            .keyword("sql")
+            .expect_build(conn);
+        let runtime = CrateBuilder::new("runtime", user.id)
+            .keyword("async")
+            .expect_build(conn);
+        Category::update_crate(conn, &both, &["asynchronous", "database"]).unwrap();
+        Category::update_crate(conn, &driver, &["database::drivers"]).unwrap();
+        Category::update_crate(conn, &runtime, &["asynchronous"]).unwrap();
+    });
+
+    let names = |query| {
+        let mut names = anon
+            .search(query)
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    assert_eq!(
+        names("category=asynchronous&category=database"),
+        ["async_db"]
+    );
+    assert_eq!(
+        names("category=asynchronous&category=database&category_mode=any"),
+        ["async_db", "db_driver", "runtime"]
+    );
+    assert_eq!(names("keyword=async&keyword=SQL"), ["async_db"]);
+    assert_eq!(
+        names("keyword=async&keyword=sql&keyword_mode=any"),
+        ["async_db", "db_driver", "runtime"]
+    );
+    assert_eq!(
+        names("category=database&keyword=sql&keyword=async&keyword_mode=any"),
+        ["async_db", "db_driver"]
+    );
+
+    anon.get::<()>("/api/v1/crates?category=database&category_mode=some")
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn search_includes_crates_where_name_is_stopword() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -219,6 +283,399 @@ fn search_includes_crates_where_name_is_stopword() {
     assert_eq!(json.meta.total, 1);
 }
 
+#[test]
+fn misspelled_searches_find_similar_names() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("tokio", user.id)
+            .description("An event-driven runtime")
+            .expect_build(conn);
+        CrateBuilder::new("serde", user.id)
+            .description("A serialization framework")
+            .expect_build(conn);
+    });
+
+    let json = anon.search("q=tokoi");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "tokio");
+    assert_eq!(json.meta.did_you_mean.as_deref(), Some("tokio"));
+
+    // Similar names aren't searched when something matches, nor suggested for existing names
+    let json = anon.search("q=serd");
+    assert_eq!(json.crates[0].name, "serde");
+    assert_eq!(json.meta.did_you_mean, None);
+
+    let json = anon.search("q=serde");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.meta.did_you_mean, None);
+
+    let json = anon.search("q=unrelated");
+    assert_eq!(json.meta.total, 0);
+    assert_eq!(json.meta.did_you_mean, None);
+}
+
+#[test]
+fn search_by_dependency() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        let serde = CrateBuilder::new("serde", user.id).expect_build(conn);
+        CrateBuilder::new("serde_json", user.id)
+            .downloads(100)
+            .version(VersionBuilder::new("1.0.0").dependency(&serde, None))
+            .expect_build(conn);
+        CrateBuilder::new("toml", user.id)
+            .downloads(200)
+            .version(VersionBuilder::new("0.5.0").dependency(&serde, Some("serde")))
+            .expect_build(conn);
+        // Only the latest version of the crates counts
+        CrateBuilder::new("dropped_serde", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&serde, None))
+            .version("2.0.0")
+            .expect_build(conn);
+        CrateBuilder::new("unrelated", user.id).expect_build(conn);
+    });
+
+    let json = anon.search("depends_on=serde");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "serde_json");
+    assert_eq!(json.crates[1].name, "toml");
+
+    let json = anon.search("depends_on=serde&sort=downloads&per_page=1");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "toml");
+
+    let json = anon.search("depends_on=serde&q=json");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "serde_json");
+
+    let json = anon.search("depends_on=unknown");
+    assert_eq!(json.meta.total, 0);
+}
+
+#[test]
+fn search_facets() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        new_category("Parsing", "parsing", "Parsers")
+            .create_or_update(conn)
+            .unwrap();
+        new_category("Encoding", "encoding", "Encoders")
+            .create_or_update(conn)
+            .unwrap();
+
+        let facet_json = CrateBuilder::new("facet_json", user.id)
+            .keyword("json")
+            .keyword("parser")
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .expect_build(conn);
+        let facet_toml = CrateBuilder::new("facet_toml", user.id)
+            .keyword("parser")
+            .version(VersionBuilder::new("0.1.0").license(Some("GPL-3.0")))
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .expect_build(conn);
+        let facet_yaml = CrateBuilder::new("facet_yaml", user.id)
+            .keyword("parser")
+            .version(VersionBuilder::new("1.0.0").license(Some("Apache-2.0")))
+            .expect_build(conn);
+        CrateBuilder::new("unrelated", user.id)
+            .keyword("parser")
+            .expect_build(conn);
+        Category::update_crate(conn, &facet_json, &["parsing", "encoding"]).unwrap();
+        Category::update_crate(conn, &facet_toml, &["parsing"]).unwrap();
+        Category::update_crate(conn, &facet_yaml, &["parsing"]).unwrap();
+    });
+
+    // The facets are only computed on request
+    let json: serde_json::Value = anon.get_with_query("/api/v1/crates", "q=facet").good();
+    assert!(json.get("facets").is_none());
+
+    let json: serde_json::Value = anon
+        .get_with_query("/api/v1/crates", "q=facet&include=facets")
+        .good();
+    assert_eq!(
+        json["facets"],
+        json!({
+            "categories": [
+                { "slug": "parsing", "category": "Parsing", "crates_cnt": 3 },
+                { "slug": "encoding", "category": "Encoding", "crates_cnt": 1 },
+            ],
+            "keywords": [
+                { "keyword": "parser", "crates_cnt": 3 },
+                { "keyword": "json", "crates_cnt": 1 },
+            ],
+            "licenses": [
+                { "license": "MIT", "crates_cnt": 2 },
+                { "license": "Apache-2.0", "crates_cnt": 1 },
+            ],
+        })
+    );
+
+    let json: serde_json::Value = anon
+        .get_with_query("/api/v1/crates", "q=facet&category=encoding&include=facets")
+        .good();
+    assert_eq!(json["facets"]["keywords"].as_array().unwrap().len(), 2);
+    assert_eq!(json["facets"]["licenses"][0]["crates_cnt"], 1);
+
+    // Nor for the listing of all the crates
+    let json: serde_json::Value = anon
+        .get_with_query("/api/v1/crates", "include=facets")
+        .good();
+    assert!(json.get("facets").is_none());
+}
+
+#[test]
+fn search_highlights_the_matches() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("serde_json", user.id)
+            .description("A <JSON> serialization file format for Serde")
+            .expect_build(conn);
+        CrateBuilder::new("ron", user.id)
+            .description("Rusty Object Notation, a serde format")
+            .expect_build(conn);
+    });
+
+    let json = anon.search("q=serde&sort=alphabetical");
+    assert_eq!(json.meta.total, 2);
+
+    let highlights = json.crates[0].highlights.as_ref().unwrap();
+    assert_eq!(json.crates[0].name, "ron");
+    assert_eq!(highlights.name, None);
+    assert_eq!(
+        highlights.description.as_deref(),
+        Some("Rusty Object Notation, a <mark>serde</mark> format")
+    );
+
+    let highlights = json.crates[1].highlights.as_ref().unwrap();
+    assert_eq!(highlights.name.as_deref(), Some("<mark>serde</mark>_json"));
+    assert_eq!(
+        highlights.description.as_deref(),
+        Some("A &lt;JSON&gt; serialization file format for <mark>Serde</mark>")
+    );
+
+    // Only the results of queries are highlighted
+    let json = anon.search("sort=alphabetical");
+    assert!(json.crates[0].highlights.is_none());
+}
+
+#[test]
+fn suggest_crate_names() {
+    #[derive(Deserialize)]
+    struct Suggestions {
+        crates: Vec<Suggestion>,
+    }
+    #[derive(Deserialize)]
+    struct Suggestion {
+        name: String,
+        description: Option<String>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("serde", user.id)
+            .description("A serialization framework")
+            .downloads(10)
+            .expect_build(conn);
+        CrateBuilder::new("serde_json", user.id)
+            .downloads(100)
+            .expect_build(conn);
+        CrateBuilder::new("serial", user.id)
+            .downloads(50)
+            .expect_build(conn);
+        CrateBuilder::new("serdeXjson", user.id).expect_build(conn);
+        CrateBuilder::new("yanked_serde", user.id)
+            .version(VersionBuilder::new("1.0.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    let suggest = |q: &str| -> Vec<String> {
+        let json: Suggestions = anon
+            .get_with_query("/api/v1/crate_suggestions", &format!("q={}", q))
+            .good();
+        json.crates.into_iter().map(|krate| krate.name).collect()
+    };
+
+    // The exact match comes first, then the most downloaded crates
+    assert_eq!(
+        suggest("SER"),
+        ["serde_json", "serial", "serde", "serdeXjson"]
+    );
+    assert_eq!(suggest("serde"), ["serde", "serde_json", "serdeXjson"]);
+    // `-` and `_` are the same, and don't match other characters
+    assert_eq!(suggest("serde-"), ["serde_json"]);
+    assert!(suggest("").is_empty());
+    assert!(suggest("%").is_empty());
+
+    let json: Suggestions = anon
+        .get_with_query("/api/v1/crate_suggestions", "q=serde")
+        .good();
+    assert_eq!(
+        json.crates[0].description.as_deref(),
+        Some("A serialization framework")
+    );
+}
+
+#[test]
+fn search_by_license() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("license_dual", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT OR Apache-2.0")))
+            .expect_build(conn);
+        CrateBuilder::new("license_legacy", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT/Apache-2.0")))
+            .expect_build(conn);
+        CrateBuilder::new("license_combined", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT AND Zlib")))
+            .expect_build(conn);
+        CrateBuilder::new("license_changed", user.id)
+            .version(VersionBuilder::new("0.1.0").license(Some("MIT")))
+            .version(VersionBuilder::new("1.0.0").license(Some("GPL-3.0")))
+            .expect_build(conn);
+    });
+
+    let names = |query: &str| {
+        anon.search(query)
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("license=MIT"), ["license_dual", "license_legacy"]);
+    assert_eq!(
+        names("license=Apache-2.0"),
+        ["license_dual", "license_legacy"]
+    );
+    assert_eq!(
+        names("license=MIT&license=Zlib"),
+        ["license_combined", "license_dual", "license_legacy"]
+    );
+    assert_eq!(
+        names("license=Zlib+OR+MIT"),
+        ["license_combined", "license_dual", "license_legacy"]
+    );
+    assert_eq!(names("license=GPL-3.0"), ["license_changed"]);
+    assert!(names("license=BSD-3-Clause").is_empty());
+
+    // The licenses of the versions published since the previous searches are found too
+    app.db(|conn| {
+        CrateBuilder::new("license_new", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("BSD-3-Clause")))
+            .expect_build(conn);
+    });
+    assert_eq!(names("license=BSD-3-Clause"), ["license_new"]);
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "license=MIT+OR")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+    assert_eq!(json.errors[0].detail, "invalid license expression `MIT OR`");
+}
+
+#[test]
+fn search_by_minimum_supported_rust_version() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("msrv_old", user.id)
+            .version(VersionBuilder::new("1.0.0").rust_version("1.31"))
+            .expect_build(conn);
+        CrateBuilder::new("msrv_patch", user.id)
+            .version(VersionBuilder::new("1.0.0").rust_version("1.46.1"))
+            .expect_build(conn);
+        CrateBuilder::new("msrv_raised", user.id)
+            .version(VersionBuilder::new("1.0.0").rust_version("1.40"))
+            .version(VersionBuilder::new("2.0.0").rust_version("1.47"))
+            .version(
+                VersionBuilder::new("3.0.0")
+                    .rust_version("1.50")
+                    .yanked(true),
+            )
+            .expect_build(conn);
+        CrateBuilder::new("msrv_unknown", user.id).expect_build(conn);
+    });
+
+    let names = |query: &str| {
+        anon.search(query)
+            .crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("msrv=1.46"), ["msrv_old"]);
+    assert_eq!(names("msrv=1.46.1"), ["msrv_old", "msrv_patch"]);
+    assert_eq!(
+        names("msrv=1.47"),
+        ["msrv_old", "msrv_patch", "msrv_raised"]
+    );
+    assert_eq!(names("msrv=1.30").len(), 0);
+    assert_eq!(names("q=msrv").len(), 4);
+
+    let json: serde_json::Value = anon.get_with_query("/api/v1/crates", "sort=alpha").good();
+    let rust_versions = json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| (krate["name"].clone(), krate["rust_version"].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rust_versions,
+        [
+            (json!("msrv_old"), json!("1.31")),
+            (json!("msrv_patch"), json!("1.46.1")),
+            (json!("msrv_raised"), json!("1.47")),
+            (json!("msrv_unknown"), json!(null)),
+        ]
+    );
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "msrv=latest")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json.errors[0].detail,
+        "msrv must be a Rust version such as `1.46`"
+    );
+}
+
+#[test]
+fn search_within_readmes() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_readme_search")
+        .readme("# Foo\n\nA *lock-free* queue for concurrent programs");
+    token.enqueue_publish(crate_to_publish).good();
+    let crate_to_publish =
+        PublishBuilder::new("bar_readme_search").readme("Parses the arguments of programs");
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let json = anon.search("readme=concurrent");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "foo_readme_search");
+
+    let json = anon.search("readme=programs");
+    assert_eq!(json.meta.total, 2);
+
+    let json = anon.search("readme=programs&q=bar_readme_search");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "bar_readme_search");
+
+    // Only the README of the latest version is searched
+    let crate_to_publish = PublishBuilder::new("foo_readme_search")
+        .version("1.1.0")
+        .readme("A lock-free stack");
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    assert_eq!(anon.search("readme=concurrent").meta.total, 0);
+    assert_eq!(anon.search("readme=stack").meta.total, 1);
+}
+
 #[test]
 fn exact_match_first_on_queries() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -494,6 +951,32 @@ fn exact_match_on_queries_with_sort() {
     assert_eq!(json.crates[3].name, "other_sort");
 }
 
+#[test]
+fn relevance_blends_text_downloads_and_updates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("blend_quiet", user.id)
+            .description("blend")
+            .expect_build(conn);
+        CrateBuilder::new("blend_popular", user.id)
+            .description("blend")
+            .downloads(100_000)
+            .recent_downloads(100_000)
+            .expect_build(conn);
+        CrateBuilder::new("blend", user.id).expect_build(conn);
+    });
+
+    // Exact matches stay first, whatever their downloads
+    let json = anon.search("q=blend");
+    let names = json.crates.iter().map(|c| &*c.name).collect::<Vec<_>>();
+    assert_eq!(names, ["blend", "blend_popular", "blend_quiet"]);
+
+    let json = anon.search("sort=relevance");
+    assert_eq!(json.crates[0].name, "blend_popular");
+}
+
 #[test]
 fn loose_search_order() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -579,47 +1062,130 @@ fn index_include_yanked() {
     assert_eq!(json.crates[0].name, "newest_yanked");
     assert_eq!(json.crates[1].name, "oldest_yanked");
     assert_eq!(json.crates[2].name, "unyanked");
+
+    // Fully yanked crates are left out by default
+    let json = anon.search("sort=alphabetical");
+    assert_eq!(json.meta.total, 3);
+    assert_eq!(json.crates[0].name, "newest_yanked");
 }
 
 #[test]
-fn show() {
-    let (app, anon, user) = TestApp::init().with_user();
+fn yanking_every_version_does_not_touch_the_update_time() {
+    let (app, _, user) = TestApp::init().with_user();
     let user = user.as_model();
 
-    let krate = app.db(|conn| {
-        let krate = CrateBuilder::new("foo_show", user.id)
-            .description("description")
-            .documentation("https://example.com")
-            .homepage("http://example.com")
+    let (krate, updated_at) = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_yanked_updated_at", user.id)
             .version(VersionBuilder::new("1.0.0"))
-            .version(VersionBuilder::new("0.5.0"))
-            .version(VersionBuilder::new("0.5.1"))
-            .keyword("kw1")
-            .downloads(20)
-            .recent_downloads(10)
             .expect_build(conn);
+        let updated_at = update(&krate)
+            .set(crates::updated_at.eq(crates::updated_at - 1.hour()))
+            .returning(crates::updated_at)
+            .get_result::<chrono::NaiveDateTime>(conn)
+            .unwrap();
+        (krate, updated_at)
+    });
 
-        // Make version 1.0.0 mimic a version published before we started recording who published
-        // versions
-        let none: Option<i32> = None;
-        update(versions::table)
-            .filter(versions::num.eq("1.0.0"))
-            .set(versions::published_by.eq(none))
+    app.db(|conn| {
+        update(versions::table.filter(versions::crate_id.eq(krate.id)))
+            .set(versions::yanked.eq(true))
             .execute(conn)
             .unwrap();
-
-        krate
+        let (has_unyanked_versions, updated_at_after) = crates::table
+            .find(krate.id)
+            .select((crates::has_unyanked_versions, crates::updated_at))
+            .first::<(bool, chrono::NaiveDateTime)>(conn)
+            .unwrap();
+        assert!(!has_unyanked_versions);
+        assert_eq!(updated_at_after, updated_at);
     });
+}
 
-    let json = anon.show_crate("foo_show");
-    assert_eq!(json.krate.name, krate.name);
-    assert_eq!(json.krate.id, krate.name);
-    assert_eq!(json.krate.description, krate.description);
-    assert_eq!(json.krate.homepage, krate.homepage);
-    assert_eq!(json.krate.documentation, krate.documentation);
-    assert_eq!(json.krate.keywords, Some(vec!["kw1".into()]));
-    assert_eq!(json.krate.recent_downloads, Some(10));
-    let versions = json.krate.versions.as_ref().unwrap();
+#[test]
+fn indexing_the_readme_does_not_touch_the_update_time() {
+    use diesel::sql_types::Text;
+    use diesel_full_text_search::TsVector;
+
+    let (app, _, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_readme_updated_at", user.id).expect_build(conn);
+        let updated_at = update(&krate)
+            .set(crates::updated_at.eq(crates::updated_at - 1.hour()))
+            .returning(crates::updated_at)
+            .get_result::<chrono::NaiveDateTime>(conn)
+            .unwrap();
+
+        let index = sql::<TsVector>("to_tsvector('pg_catalog.english', ")
+            .bind::<Text, _>("rendered readme")
+            .sql(")");
+        let updated_at_after = update(&krate)
+            .set(crates::readme_textsearchable_index_col.eq(index))
+            .returning(crates::updated_at)
+            .get_result::<chrono::NaiveDateTime>(conn)
+            .unwrap();
+        assert_eq!(updated_at_after, updated_at);
+    });
+}
+
+#[test]
+fn yanking_every_version_removes_the_crate_from_search() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("fyk_search"))
+        .good();
+    app.run_pending_background_jobs();
+    assert_eq!(anon.search("q=fyk_search").meta.total, 1);
+
+    token.yank("fyk_search", "1.0.0").good();
+    assert_eq!(anon.search("q=fyk_search").meta.total, 0);
+    assert_eq!(anon.search("q=fyk_search&include_yanked=yes").meta.total, 1);
+
+    token.unyank("fyk_search", "1.0.0").good();
+    assert_eq!(anon.search("q=fyk_search").meta.total, 1);
+}
+
+#[test]
+fn show() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let krate = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_show", user.id)
+            .description("description")
+            .documentation("https://example.com")
+            .homepage("http://example.com")
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("0.5.0"))
+            .version(VersionBuilder::new("0.5.1"))
+            .keyword("kw1")
+            .downloads(20)
+            .recent_downloads(10)
+            .expect_build(conn);
+
+        // Make version 1.0.0 mimic a version published before we started recording who published
+        // versions
+        let none: Option<i32> = None;
+        update(versions::table)
+            .filter(versions::num.eq("1.0.0"))
+            .set(versions::published_by.eq(none))
+            .execute(conn)
+            .unwrap();
+
+        krate
+    });
+
+    let json = anon.show_crate("foo_show");
+    assert_eq!(json.krate.name, krate.name);
+    assert_eq!(json.krate.id, krate.name);
+    assert_eq!(json.krate.description, krate.description);
+    assert_eq!(json.krate.homepage, krate.homepage);
+    assert_eq!(json.krate.documentation, krate.documentation);
+    assert_eq!(json.krate.keywords, Some(vec!["kw1".into()]));
+    assert_eq!(json.krate.recent_downloads, Some(10));
+    let versions = json.krate.versions.as_ref().unwrap();
     assert_eq!(versions.len(), 3);
     assert_eq!(json.versions.len(), 3);
 
@@ -695,6 +1261,140 @@ fn versions() {
     );
 }
 
+#[test]
+fn full_crate_lists_the_dependencies_of_every_version() {
+    #[derive(Deserialize)]
+    struct FullCrate {
+        versions: Vec<EncodableFullVersion>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        let serde = CrateBuilder::new("serde_full", user.id).expect_build(conn);
+        let rand = CrateBuilder::new("rand_full", user.id).expect_build(conn);
+        CrateBuilder::new("foo_full", user.id)
+            .version(
+                VersionBuilder::new("0.9.0")
+                    .dependency(&serde, None)
+                    .yanked(true),
+            )
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .dependency(&serde, None)
+                    .dependency(&rand, None),
+            )
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<FullCrate>("/api/v1/crates/foo_full/full");
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let json = response.good();
+    let versions = json
+        .versions
+        .iter()
+        .map(|v| {
+            let deps = v.dependencies.iter().map(|d| d.crate_id.as_str());
+            (v.num.as_str(), v.yanked, deps.collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        versions,
+        [
+            ("1.0.0", false, vec!["rand_full", "serde_full"]),
+            ("0.9.0", true, vec!["serde_full"]),
+        ]
+    );
+
+    // The response is only sent again once it changed
+    let mut request = anon.get_request("/api/v1/crates/foo_full/full");
+    request.header(header::IF_NONE_MATCH, &etag);
+    anon.run::<()>(request)
+        .assert_status(StatusCode::NOT_MODIFIED);
+
+    app.db(|conn| {
+        // The tests run in a single transaction, so the `updated_at` trigger can't be relied on
+        update(versions::table)
+            .filter(versions::num.eq("0.9.0"))
+            .set((
+                versions::yanked.eq(false),
+                versions::updated_at.eq(versions::updated_at + 1.second()),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+    let mut request = anon.get_request("/api/v1/crates/foo_full/full");
+    request.header(header::IF_NONE_MATCH, &etag);
+    let json: FullCrate = anon.run(request).good();
+    assert!(!json.versions[1].yanked);
+}
+
+#[test]
+fn show_and_versions_are_not_sent_again_until_they_changed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let krate = app.db(|conn| {
+        CrateBuilder::new("foo_etag", user.id)
+            .version("1.0.0")
+            .expect_build(conn)
+    });
+
+    for url in &[
+        "/api/v1/crates/foo_etag",
+        "/api/v1/crates/foo_etag/versions",
+    ] {
+        let response = anon.get::<()>(url);
+        response.assert_status(StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+
+        let mut request = anon.get_request(url);
+        request.header(header::IF_NONE_MATCH, etag.to_str().unwrap());
+        anon.run::<()>(request)
+            .assert_status(StatusCode::NOT_MODIFIED);
+
+        // Weak comparison is used, and the header may list several entity tags
+        let mut request = anon.get_request(url);
+        let tags = format!("\"other\", W/{}", etag.to_str().unwrap());
+        request.header(header::IF_NONE_MATCH, &tags);
+        anon.run::<()>(request)
+            .assert_status(StatusCode::NOT_MODIFIED);
+    }
+
+    let etag = anon.get::<()>("/api/v1/crates/foo_etag/versions").headers()[header::ETAG].clone();
+    app.db(|conn| {
+        VersionBuilder::new("1.1.0").expect_build(krate.id, user.id, conn);
+    });
+    let mut request = anon.get_request("/api/v1/crates/foo_etag/versions");
+    request.header(header::IF_NONE_MATCH, etag.to_str().unwrap());
+    let json: VersionsList = anon.run(request).good();
+    assert_eq!(json.versions.len(), 2);
+
+    // The versions list the profile of their publisher
+    let etag = anon.get::<()>("/api/v1/crates/foo_etag/versions").headers()[header::ETAG].clone();
+    app.db(|conn| {
+        update(users::table)
+            .filter(users::id.eq(user.id))
+            .set(users::gh_avatar.eq("https://example.com/avatar.png"))
+            .execute(conn)
+            .unwrap();
+    });
+    let mut request = anon.get_request("/api/v1/crates/foo_etag/versions");
+    request.header(header::IF_NONE_MATCH, etag.to_str().unwrap());
+    let json: VersionsList = anon.run(request).good();
+    assert_eq!(
+        json.versions[0]
+            .published_by
+            .as_ref()
+            .unwrap()
+            .avatar
+            .as_deref(),
+        Some("https://example.com/avatar.png")
+    );
+}
+
 #[test]
 fn uploading_new_version_touches_crate() {
     use diesel::dsl::*;
@@ -872,6 +1572,39 @@ fn new_krate_with_dependency() {
     token.enqueue_publish(crate_to_publish).good();
 }
 
+#[test]
+fn new_krate_with_features2() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_features2")
+        .feature("default", &["std"])
+        .feature("std", &[])
+        .feature("serde", &["dep:serde"])
+        .feature("json", &["serde?/std"]);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("fo/o_/foo_features2");
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0].v, Some(2));
+    let mut features = crates[0].features.keys().collect::<Vec<_>>();
+    features.sort();
+    assert_eq!(features, ["default", "std"]);
+    let features2 = crates[0].features2.as_ref().unwrap();
+    assert_eq!(features2.len(), 2);
+    assert_eq!(features2["serde"], ["dep:serde"]);
+    assert_eq!(features2["json"], ["serde?/std"]);
+
+    // Entries without such features keep the original format
+    let crate_to_publish = PublishBuilder::new("foo_features2").version("1.1.0");
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("fo/o_/foo_features2");
+    assert_eq!(crates[1].v, None);
+    assert_none!(&crates[1].features2);
+}
+
 #[test]
 fn reject_new_krate_with_non_exact_dependency() {
     let (app, _, user, token) = TestApp::init().with_token();
@@ -949,6 +1682,48 @@ fn new_krate_with_wildcard_dependency() {
     );
 }
 
+#[test]
+fn new_krate_with_invalid_artifact_dependency() {
+    let (app, _, user, token) = TestApp::init().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_artifact", user.as_model().id).expect_build(conn);
+    });
+
+    let dependency = DependencyBuilder::new("foo_artifact").artifact(&["dylib"]);
+
+    let crate_to_publish = PublishBuilder::new("new_artifact")
+        .version("1.0.0")
+        .dependency(dependency);
+
+    let json = token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("unknown artifact kind `dylib`"),
+        "{:?}",
+        json.errors
+    );
+
+    let dependency = DependencyBuilder::new("foo_artifact").bindep_target("wasm32-wasi");
+    let crate_to_publish = PublishBuilder::new("new_artifact")
+        .version("1.0.0")
+        .dependency(dependency);
+
+    let json = token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("sets `bindep_target` or `lib` without `artifact`"),
+        "{:?}",
+        json.errors
+    );
+}
+
 #[test]
 fn new_krate_twice() {
     let (app, _, user, token) = TestApp::full().with_token();
@@ -1025,6 +1800,22 @@ fn new_krate_too_big() {
     );
 }
 
+#[test]
+fn new_krate_with_too_large_readme() {
+    let (_, _, _, token) = TestApp::init()
+        .with_config(|config| config.render.limits.max_size = 10)
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_readme").readme("# A long README\n");
+    let json = token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK);
+    assert_eq!(
+        json.errors[0].detail,
+        "the README is 17 bytes long, more than the maximum of 10 bytes"
+    );
+}
+
 #[test]
 fn new_krate_too_big_but_whitelisted() {
     let (app, _, user, token) = TestApp::full().with_token();
@@ -1151,6 +1942,31 @@ fn new_crate_similar_name_hyphen() {
     );
 }
 
+#[test]
+fn new_crate_similar_name_of_another_user() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo-bar-taken", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let another_user = app.db_new_user("another").db_new_token("bar");
+    let crate_to_publish = PublishBuilder::new("foo_bar_taken").version("1.0.0");
+    let json = another_user
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK);
+
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("too similar to the existing crate `foo-bar-taken`"),
+        "{:?}",
+        json.errors
+    );
+}
+
 #[test]
 fn new_crate_similar_name_underscore() {
     let (app, _, user, token) = TestApp::init().with_token();
@@ -1470,6 +2286,72 @@ fn download() {
     assert_dl_count("FOO_DOWNLOAD", Some(&query), 2);
 }
 
+#[test]
+fn version_downloads_are_daily() {
+    use cargo_registry::schema::version_downloads;
+    use chrono::{Duration, NaiveDate};
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_daily", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+        let version_id: i32 = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        diesel::insert_into(version_downloads::table)
+            .values(&vec![
+                (
+                    version_downloads::version_id.eq(version_id),
+                    version_downloads::date.eq(NaiveDate::from_ymd(2020, 9, 28)),
+                    version_downloads::downloads.eq(3),
+                ),
+                (
+                    version_downloads::version_id.eq(version_id),
+                    version_downloads::date.eq(NaiveDate::from_ymd(2020, 9, 30)),
+                    version_downloads::downloads.eq(5),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+    });
+
+    let url = "/api/v1/crates/foo_daily/1.0.0/downloads";
+    let downloads: Downloads = anon
+        .get_with_query(url, "before_date=2020-09-30&days=4")
+        .good();
+    let downloads = downloads
+        .version_downloads
+        .iter()
+        .map(|download| (download.date.as_str(), download.downloads))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        downloads,
+        [
+            ("2020-09-27", 0),
+            ("2020-09-28", 3),
+            ("2020-09-29", 0),
+            ("2020-09-30", 5),
+        ]
+    );
+
+    let downloads: Downloads = anon.get_with_query(url, "before_date=2020-09-30").good();
+    assert_eq!(downloads.version_downloads.len(), 90);
+    let first_day = NaiveDate::from_ymd(2020, 9, 30) - Duration::days(89);
+    assert_eq!(downloads.version_downloads[0].date, first_day.to_string());
+
+    let json = anon
+        .get_with_query::<()>(url, "days=366")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+    assert_eq!(json.errors[0].detail, "days must be between 1 and 365");
+    anon.get_with_query::<()>(url, "days=0")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn download_nonexistent_version_of_existing_crate_404s() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -1521,6 +2403,51 @@ fn dependencies() {
         .bad_with_status(StatusCode::OK);
 }
 
+#[test]
+fn dependencies_for_target() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("foo_target", user.id).expect_build(conn);
+        let common = CrateBuilder::new("common", user.id).expect_build(conn);
+        let unix = CrateBuilder::new("unix_only", user.id).expect_build(conn);
+        let windows = CrateBuilder::new("windows_only", user.id).expect_build(conn);
+        let linux = CrateBuilder::new("linux_gnu_only", user.id).expect_build(conn);
+        VersionBuilder::new("1.0.0")
+            .dependency(&common, None)
+            .dependency(&unix, Some("cfg(unix)"))
+            .dependency(&windows, Some(r#"cfg(target_os = "windows")"#))
+            .dependency(&linux, Some("x86_64-unknown-linux-gnu"))
+            .expect_build(c1.id, user.id, conn);
+    });
+
+    let url = "/api/v1/crates/foo_target/1.0.0/dependencies";
+    let names = |query: &str| {
+        let deps: Deps = anon.get_with_query(url, query).good();
+        let mut names = deps
+            .dependencies
+            .into_iter()
+            .map(|dep| dep.crate_id)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    assert_eq!(names("").len(), 4);
+    assert_eq!(
+        names("target=x86_64-unknown-linux-gnu"),
+        vec!["common", "linux_gnu_only", "unix_only"]
+    );
+    assert_eq!(
+        names("target=x86_64-pc-windows-msvc"),
+        vec!["common", "windows_only"]
+    );
+
+    anon.get_with_query::<()>(url, "target=cfg(unix)")
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn diesel_not_found_results_in_404() {
     let (_, _, user) = TestApp::init().with_user();
@@ -1577,6 +2504,41 @@ fn following() {
     assert_eq!(user.search("following=1").crates.len(), 0);
 }
 
+#[test]
+fn followers_can_disable_the_email_notifications() {
+    #[derive(Deserialize)]
+    struct F {
+        following: bool,
+        email_notifications: bool,
+    }
+
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_following_emails", user.as_model().id).expect_build(conn);
+    });
+    let url = "/api/v1/crates/foo_following_emails/follow/email_notifications";
+    let body = br#"{"email_notifications":false}"#;
+
+    user.put::<()>(url, body)
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error("the crate isn't followed");
+
+    user.put::<OkBool>("/api/v1/crates/foo_following_emails/follow", b"")
+        .good();
+    let json: F = user
+        .get("/api/v1/crates/foo_following_emails/following")
+        .good();
+    assert!(json.following);
+    assert!(json.email_notifications);
+
+    user.put::<OkBool>(url, body).good();
+    let json: F = user
+        .get("/api/v1/crates/foo_following_emails/following")
+        .good();
+    assert!(json.following);
+    assert!(!json.email_notifications);
+}
+
 #[test]
 fn yank_works_as_intended() {
     let (app, anon, cookie, token) = TestApp::full().with_token();
@@ -1808,21 +2770,56 @@ fn unyank_records_an_audit_action() {
 }
 
 #[test]
-fn publish_after_removing_documentation() {
-    let (app, anon, user, token) = TestApp::full().with_token();
-    let user = user.as_model();
-
-    // 1. Start with a crate with no documentation
-    app.db(|conn| {
-        CrateBuilder::new("docscrate", user.id)
-            .version("0.2.0")
-            .expect_build(conn);
-    });
+fn yank_reasons_are_recorded_in_the_yank_history() {
+    let (app, anon, _, token) = TestApp::full().with_token();
 
-    // Verify that crates start without any documentation so the next assertion can *prove*
-    // that it was the one that added the documentation
-    let json = anon.show_crate("docscrate");
-    assert_eq!(json.krate.documentation, None);
+    let crate_to_publish = PublishBuilder::new("fyk");
+    token.enqueue_publish(crate_to_publish).good();
+
+    let body = json!({ "reason": " RUSTSEC-2020-0001 " }).to_string();
+    token
+        .delete_with_body::<OkBool>("/api/v1/crates/fyk/1.0.0/yank", body.as_bytes())
+        .good();
+    app.run_pending_background_jobs();
+    token.unyank("fyk", "1.0.0").good();
+
+    let history = anon.show_version("fyk", "1.0.0").version.yank_history;
+    assert_eq!(history.len(), 2);
+    assert!(history[0].yanked);
+    assert_eq!(history[0].reason.as_deref(), Some("RUSTSEC-2020-0001"));
+    assert_eq!(history[0].user.id, token.as_model().user_id);
+    assert!(!history[1].yanked);
+    assert_eq!(history[1].reason, None);
+
+    let crate_json = anon.show_crate("fyk");
+    assert_eq!(crate_json.versions[0].yank_history.len(), 2);
+
+    let body = json!({ "reason": "x".repeat(513) }).to_string();
+    let json = token
+        .delete_with_body::<()>("/api/v1/crates/fyk/1.0.0/yank", body.as_bytes())
+        .bad_with_status(StatusCode::OK);
+    assert_eq!(
+        json.errors[0].detail,
+        "the reason can't be longer than 512 characters"
+    );
+}
+
+#[test]
+fn publish_after_removing_documentation() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    let user = user.as_model();
+
+    // 1. Start with a crate with no documentation
+    app.db(|conn| {
+        CrateBuilder::new("docscrate", user.id)
+            .version("0.2.0")
+            .expect_build(conn);
+    });
+
+    // Verify that crates start without any documentation so the next assertion can *prove*
+    // that it was the one that added the documentation
+    let json = anon.show_crate("docscrate");
+    assert_eq!(json.krate.documentation, None);
 
     // 2. Add documentation
     let crate_to_publish = PublishBuilder::new("docscrate")
@@ -1845,6 +2842,39 @@ fn publish_after_removing_documentation() {
     assert_eq!(json.krate.documentation, None);
 }
 
+#[test]
+fn publish_validates_and_canonicalizes_urls() {
+    let (_, _, _, token) = TestApp::init().with_token();
+
+    let crate_to_publish =
+        PublishBuilder::new("foo_bad_url").documentation("javascript:alert(document.cookie)");
+    token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK)
+        .assert_error(
+            "URL for field `documentation` must begin with http:// or https:// \
+             (url: javascript:alert(document.cookie))",
+        );
+
+    let crate_to_publish =
+        PublishBuilder::new("foo_bad_url").repository("http://localhost:3000/foo.git");
+    token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK)
+        .assert_error(
+            "URL for field `repository` must point to a public host \
+             (url: http://localhost:3000/foo.git)",
+        );
+
+    let crate_to_publish =
+        PublishBuilder::new("foo_bad_url").repository("HTTPS://GitHub.com:443/foo/foo bar");
+    let json = token.enqueue_publish(crate_to_publish).good();
+    assert_eq!(
+        json.krate.repository.as_deref(),
+        Some("https://github.com/foo/foo%20bar")
+    );
+}
+
 #[test]
 fn bad_keywords() {
     let (_, _, _, token) = TestApp::init().with_token();
@@ -2152,6 +3182,68 @@ fn reverse_dependencies_query_supports_u64_version_number_parts() {
     assert_eq!(deps.versions[0].num, large_but_valid_version_number);
 }
 
+#[test]
+fn reverse_dependencies_can_be_sorted() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+        CrateBuilder::new("dependent_a", user.id)
+            .downloads(50)
+            .recent_downloads(20)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(conn);
+        CrateBuilder::new("dependent_b", user.id)
+            .downloads(100)
+            .recent_downloads(5)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(conn);
+        CrateBuilder::new("dependent_c", user.id)
+            .downloads(10)
+            .recent_downloads(50)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(conn);
+    });
+
+    let dependents = |query: &str| {
+        let deps: RevDeps = anon
+            .get_with_query("/api/v1/crates/c1/reverse_dependencies", query)
+            .good();
+        assert_eq!(deps.meta.total, 3);
+        deps.dependencies
+            .iter()
+            .map(|dep| {
+                assert_eq!(dep.req, ">= 0");
+                assert!(!dep.optional);
+                assert!(!dep.default_features);
+                let version = deps.versions.iter().find(|v| v.id == dep.version_id);
+                version.unwrap().krate.clone()
+            })
+            .collect::<Vec<_>>()
+    };
+    let by_downloads = ["dependent_b", "dependent_a", "dependent_c"];
+    assert_eq!(dependents(""), by_downloads);
+    assert_eq!(dependents("sort=downloads"), by_downloads);
+    assert_eq!(
+        dependents("sort=name"),
+        ["dependent_a", "dependent_b", "dependent_c"]
+    );
+    assert_eq!(
+        dependents("sort=recent"),
+        ["dependent_c", "dependent_a", "dependent_b"]
+    );
+    assert_eq!(dependents("sort=name&per_page=1&page=2"), ["dependent_b"]);
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates/c1/reverse_dependencies", "sort=stars")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json.errors[0].detail,
+        "sort must be `downloads`, `name` or `recent`"
+    );
+}
+
 #[test]
 fn author_license_and_description_required() {
     let (_, _, _, token) = TestApp::init().with_token();
@@ -2486,12 +3578,12 @@ fn pagination_links_included_if_applicable() {
         CrateBuilder::new("pagination_links_3", user.id).expect_build(conn);
     });
 
-    let page1 = anon.search("per_page=1");
+    let page1 = anon.search("page=1&per_page=1");
     let page2 = anon.search("page=2&per_page=1");
     let page3 = anon.search("page=3&per_page=1");
     let page4 = anon.search("page=4&per_page=1");
 
-    assert_eq!(Some("?per_page=1&page=2".to_string()), page1.meta.next_page);
+    assert_eq!(Some("?page=2&per_page=1".to_string()), page1.meta.next_page);
     assert_eq!(None, page1.meta.prev_page);
     assert_eq!(Some("?page=3&per_page=1".to_string()), page2.meta.next_page);
     assert_eq!(Some("?page=1&per_page=1".to_string()), page2.meta.prev_page);
@@ -2499,6 +3591,60 @@ fn pagination_links_included_if_applicable() {
     assert_eq!(Some("?page=2&per_page=1".to_string()), page3.meta.prev_page);
 }
 
+#[test]
+fn seek_pagination_follows_the_names() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("seek_c", user.id).expect_build(conn);
+        CrateBuilder::new("seek_a", user.id).expect_build(conn);
+        CrateBuilder::new("seek_b", user.id).expect_build(conn);
+    });
+
+    let mut names = Vec::new();
+    let mut query = "per_page=2".to_string();
+    loop {
+        let page: serde_json::Value = anon.get_with_query("/api/v1/crates", &query).good();
+        // Only the first page counts the crates
+        let total = if names.is_empty() {
+            json!(3)
+        } else {
+            json!(null)
+        };
+        assert_eq!(page["meta"]["total"], total);
+        assert_eq!(page["meta"]["prev_page"], json!(null));
+        for krate in page["crates"].as_array().unwrap() {
+            names.push(krate["name"].as_str().unwrap().to_string());
+        }
+        match page["meta"]["next_page"].as_str() {
+            Some(next_page) => {
+                assert!(next_page.contains("seek="), "{}", next_page);
+                query = next_page.trim_start_matches('?').to_string();
+            }
+            None => break,
+        }
+    }
+    assert_eq!(names, ["seek_a", "seek_b", "seek_c"]);
+
+    // The offset pagination is still used when a page is requested
+    let page = anon.search("page=1&per_page=2");
+    assert_eq!(page.meta.next_page.as_deref(), Some("?page=2&per_page=2"));
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "seek=invalid")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+    assert_eq!(json.errors[0].detail, "invalid seek parameter");
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "q=seek&seek=ImEi")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json.errors[0].detail,
+        "the seek parameter is only supported when sorting by name"
+    );
+}
+
 #[test]
 fn pagination_parameters_only_accept_integers() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -2526,3 +3672,969 @@ fn pagination_parameters_only_accept_integers() {
         "invalid digit found in string"
     );
 }
+
+#[test]
+fn pageviews_are_counted_once_per_visitor() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user_model = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_pageviews", user_model.id).expect_build(conn);
+    });
+
+    anon.post::<OkBool>("/api/v1/crates/foo_pageviews/pageview", b"")
+        .good();
+    anon.post::<OkBool>("/api/v1/crates/foo_pageviews/pageview", b"")
+        .good();
+
+    let json: serde_json::Value = user.get("/api/v1/crates/foo_pageviews/pageviews").good();
+    let pageviews = json["pageviews"].as_array().unwrap();
+    assert_eq!(pageviews.len(), 1);
+    assert_eq!(pageviews[0]["views"], 1);
+
+    anon.get("/api/v1/crates/foo_pageviews/pageviews")
+        .assert_forbidden();
+    let other = app.db_new_user("other");
+    other
+        .get("/api/v1/crates/foo_pageviews/pageviews")
+        .assert_forbidden();
+}
+
+#[test]
+fn most_depended_upon() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+        let c2 = CrateBuilder::new("c2", user.id).expect_build(conn);
+        CrateBuilder::new("c3", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c2, None))
+            .version(VersionBuilder::new("1.1.0").dependency(&c1, None))
+            .expect_build(conn);
+        CrateBuilder::new("c4", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(conn);
+
+        assert_ok!(Crate::update_dependents_counts(conn, &[c1.id, c2.id]));
+    });
+
+    #[derive(Deserialize)]
+    struct Response {
+        crates: Vec<DependedUponCrate>,
+        meta: CrateMeta,
+    }
+    #[derive(Deserialize)]
+    struct DependedUponCrate {
+        name: String,
+        dependents_count: i32,
+    }
+
+    // c2 is only depended upon by an old version of c3
+    let json: Response = anon.get("/api/v1/most_depended_upon").good();
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "c1");
+    assert_eq!(json.crates[0].dependents_count, 2);
+
+    app.db(|conn| {
+        diesel::update(crates::table)
+            .set(crates::dependents_count.eq(5))
+            .filter(crates::name.eq("c2"))
+            .execute(conn)
+            .unwrap();
+        assert_eq!(assert_ok!(Crate::reconcile_dependents_counts(conn)), 1);
+    });
+
+    let json: Response = anon.get("/api/v1/most_depended_upon").good();
+    assert_eq!(json.meta.total, 1);
+}
+
+#[test]
+fn trending() {
+    use cargo_registry::schema::version_downloads;
+    use cargo_registry::tasks;
+    use swirl::Job;
+
+    let (app, anon, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let hot = CrateBuilder::new("hot", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let steady = CrateBuilder::new("steady", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        let version_id = |krate: &Crate| {
+            versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .select(versions::id)
+                .first::<i32>(conn)
+                .unwrap()
+        };
+        let (hot_version, steady_version) = (version_id(&hot), version_id(&steady));
+
+        diesel::insert_into(version_downloads::table)
+            .values(&vec![
+                (
+                    version_downloads::version_id.eq(hot_version),
+                    version_downloads::downloads.eq(5000),
+                    version_downloads::date.eq(date(now)),
+                ),
+                (
+                    version_downloads::version_id.eq(steady_version),
+                    version_downloads::downloads.eq(10_000),
+                    version_downloads::date.eq(date(now)),
+                ),
+                (
+                    version_downloads::version_id.eq(steady_version),
+                    version_downloads::downloads.eq(40_000),
+                    version_downloads::date.eq(date(now - 14.days())),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+
+        tasks::update_trending_scores().enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon.get("/api/v1/trending_crates").good();
+    let crates = json["crates"].as_array().unwrap();
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0]["name"], "hot");
+    assert_eq!(crates[0]["trending"]["score"], 5.0);
+    assert_eq!(crates[0]["trending"]["recent_downloads"], 5000);
+    assert_eq!(json["meta"]["total"], 1);
+}
+
+#[test]
+fn crates_named_like_the_listings_are_shown() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let names = ["trending", "suggest", "compare"];
+    app.db(|conn| {
+        for name in &names {
+            CrateBuilder::new(name, user.id).expect_build(conn);
+        }
+    });
+
+    for name in &names {
+        let json: serde_json::Value = anon.get(&format!("/api/v1/crates/{}", name)).good();
+        assert_eq!(json["crate"]["name"], *name);
+    }
+}
+
+#[test]
+fn quality() {
+    use cargo_registry::schema::version_files;
+    use cargo_registry::tasks;
+    use chrono::NaiveDate;
+    use swirl::Job;
+
+    let (app, anon, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let good = CrateBuilder::new("good", user.id)
+            .documentation("https://docs.rs/good")
+            .expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(good.id, user.id, conn);
+        version.record_rust_version(conn, "1.40").unwrap();
+        diesel::insert_into(version_files::table)
+            .values((
+                version_files::version_id.eq(version.id),
+                version_files::path.eq("tests/it.rs"),
+                version_files::size.eq(12),
+                version_files::sha256.eq(vec![0xab; 32]),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        let old = CrateBuilder::new("old", user.id)
+            .readme("old readme")
+            .expect_build(conn);
+        VersionBuilder::new("1.0.0")
+            .created_at(NaiveDate::from_ymd(2017, 1, 1).and_hms(0, 0, 0))
+            .yanked(true)
+            .expect_build(old.id, user.id, conn);
+
+        tasks::update_quality_scores().enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/good/quality").good();
+    assert_eq!(json["quality"]["total"], 1.0);
+    assert_eq!(
+        json["quality"]["formula_version"],
+        tasks::QUALITY_FORMULA_VERSION
+    );
+    assert_eq!(
+        json["meta"]["formula_version"],
+        tasks::QUALITY_FORMULA_VERSION
+    );
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/old/quality").good();
+    let quality = &json["quality"];
+    assert_eq!(quality["docs"], 0.5);
+    assert_eq!(quality["recent_release"], 0.0);
+    assert_eq!(quality["tests"], 0.0);
+    assert_eq!(quality["msrv"], 0.0);
+    assert_eq!(quality["not_yanked"], 0.0);
+    assert_eq!(quality["total"], 0.1);
+
+    anon.get("/api/v1/crates/missing/quality")
+        .assert_not_found();
+}
+
+#[test]
+fn audit() {
+    use cargo_registry::models::{Badge, NewCrateAdvisory};
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_audit", user.id)
+            .version(VersionBuilder::new("0.1.0").yanked(true))
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("2.0.0-rc.1")
+            .expect_build(conn);
+        NewCrateAdvisory {
+            crate_id: krate.id,
+            identifier: "RUSTSEC-2020-0001",
+            title: "Memory corruption",
+            url: Some("https://rustsec.org/advisories/RUSTSEC-2020-0001"),
+            affected_versions: &["< 1.0.0".to_string()],
+        }
+        .save(conn)
+        .unwrap();
+
+        let old = CrateBuilder::new("old_audit", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let mut badges = HashMap::new();
+        let mut attributes = HashMap::new();
+        attributes.insert("status".to_string(), "deprecated".to_string());
+        badges.insert("maintenance".to_string(), attributes);
+        Badge::update_crate(conn, &old, Some(&badges)).unwrap();
+    });
+
+    let body = json!({
+        "crates": [
+            { "name": "foo-audit", "version": "0.1.0" },
+            { "name": "foo_audit", "version": "1.1.0" },
+            { "name": "old_audit", "version": "1.0.0" },
+            { "name": "missing", "version": "1.0.0" },
+        ]
+    });
+    let json: serde_json::Value = anon
+        .post("/api/v1/crates/audit", body.to_string().as_bytes())
+        .good();
+    let crates = json["crates"].as_array().unwrap();
+    assert_eq!(crates.len(), 4);
+
+    assert_eq!(crates[0]["name"], "foo-audit");
+    assert_eq!(crates[0]["yanked"], true);
+    assert_eq!(crates[0]["latest_version"], "1.1.0");
+    assert_eq!(crates[0]["deprecated"], false);
+    assert_eq!(crates[0]["advisories"][0]["id"], "RUSTSEC-2020-0001");
+
+    assert_eq!(crates[1]["yanked"], false);
+    assert_eq!(crates[1]["advisories"].as_array().unwrap().len(), 0);
+
+    assert_eq!(crates[2]["deprecated"], true);
+
+    assert_eq!(crates[3]["yanked"], serde_json::Value::Null);
+    assert_eq!(crates[3]["latest_version"], serde_json::Value::Null);
+
+    let entries = vec![json!({ "name": "foo_audit", "version": "1.0.0" }); 1001];
+    let body = json!({ "crates": entries }).to_string();
+    anon.post::<()>("/api/v1/crates/audit", body.as_bytes())
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let name = "a".repeat(300 * 1024);
+    let body = json!({ "crates": [{ "name": name, "version": "1.0.0" }] }).to_string();
+    anon.post::<()>("/api/v1/crates/audit", body.as_bytes())
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn batch_of_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_batch", user.id)
+            .description("Foo")
+            .version("1.0.0")
+            .version("1.2.0")
+            .expect_build(conn);
+        CrateBuilder::new("bar_batch", user.id)
+            .recent_downloads(7)
+            .version("0.1.0")
+            .expect_build(conn);
+    });
+
+    let body = json!({ "crates": ["bar-batch", "missing_batch", "foo_batch", "bar_batch"] });
+    let json: serde_json::Value = anon
+        .post("/api/v1/crates/batch", body.to_string().as_bytes())
+        .good();
+    let crates = json["crates"].as_array().unwrap();
+    assert_eq!(crates.len(), 2);
+    assert_eq!(crates[0]["name"], "bar_batch");
+    assert_eq!(crates[0]["recent_downloads"], 7);
+    assert_eq!(crates[1]["name"], "foo_batch");
+    assert_eq!(crates[1]["description"], "Foo");
+    assert_eq!(crates[1]["max_version"], "1.2.0");
+    assert_eq!(json["missing"], json!(["missing_batch"]));
+
+    let names = vec!["foo_batch"; 101];
+    let body = json!({ "crates": names }).to_string();
+    anon.post::<()>("/api/v1/crates/batch", body.as_bytes())
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn similar_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_similar", user.id)
+            .downloads(5)
+            .expect_build(conn);
+        CrateBuilder::new("foosimilar", user.id)
+            .downloads(10)
+            .expect_build(conn);
+        CrateBuilder::new("f00-s1mi1ar", user.id).expect_build(conn);
+        CrateBuilder::new("foo_sirnilar", user.id).expect_build(conn);
+        CrateBuilder::new("foo_similarity", user.id).expect_build(conn);
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/fo-osimilar/similar").good();
+    let crates = json["crates"].as_array().unwrap();
+    let names = crates.iter().map(|c| &c["name"]).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["foosimilar", "foo_similar", "f00-s1mi1ar", "foo_sirnilar"]
+    );
+    assert_eq!(crates[0]["collision"], "separators");
+    assert_eq!(crates[1]["collision"], "separators");
+    assert_eq!(crates[2]["collision"], "homoglyphs");
+    assert_eq!(crates[3]["collision"], "homoglyphs");
+
+    // The crate with the requested name isn't listed, whatever its case and separators
+    let json: serde_json::Value = anon.get("/api/v1/crates/Foo-Similar/similar").good();
+    assert_eq!(json["crates"].as_array().unwrap().len(), 3);
+
+    anon.get::<()>("/api/v1/crates/foo%20similar/similar")
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn compare_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let dep = CrateBuilder::new("dep_compare", user.id).expect_build(conn);
+        CrateBuilder::new("foo_compare", user.id)
+            .downloads(20)
+            .recent_downloads(5)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .version(
+                VersionBuilder::new("1.1.0")
+                    .license(Some("MIT OR Apache-2.0"))
+                    .rust_version("1.56")
+                    .dependency(&dep, None),
+            )
+            .version(VersionBuilder::new("2.0.0").yanked(true))
+            .version("3.0.0-beta.1")
+            .expect_build(conn);
+        CrateBuilder::new("bar_compare", user.id)
+            .version("0.1.0")
+            .expect_build(conn);
+    });
+
+    let json: serde_json::Value = anon
+        .get_with_query(
+            "/api/v1/crate_comparison",
+            "crates=bar-compare,foo_compare,FOO-compare",
+        )
+        .good();
+    let crates = json["crates"].as_array().unwrap();
+    assert_eq!(crates.len(), 2);
+
+    assert_eq!(crates[0]["name"], "bar_compare");
+    assert_eq!(crates[0]["latest_version"], "0.1.0");
+    assert_eq!(crates[0]["dependencies"]["normal"], 0);
+
+    assert_eq!(crates[1]["downloads"], 20);
+    assert_eq!(crates[1]["recent_downloads"], 5);
+    assert_eq!(crates[1]["latest_version"], "1.1.0");
+    assert_eq!(crates[1]["license"], "MIT OR Apache-2.0");
+    assert_eq!(crates[1]["rust_version"], "1.56");
+    assert_eq!(crates[1]["dependencies"]["normal"], 1);
+    assert_eq!(crates[1]["user_owners"], 1);
+    assert_eq!(crates[1]["team_owners"], 0);
+    assert!(crates[1]["last_release_at"].is_string());
+
+    anon.get_with_query::<()>("/api/v1/crate_comparison", "crates=foo_compare,missing")
+        .assert_status(StatusCode::BAD_REQUEST);
+    anon.get::<()>("/api/v1/crate_comparison")
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn deprecate_crate() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("old_crate"))
+        .good();
+    token
+        .enqueue_publish(PublishBuilder::new("new_crate"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let deprecate = |body: serde_json::Value| {
+        token.put::<serde_json::Value>(
+            "/api/v1/crates/old_crate/deprecate",
+            body.to_string().as_bytes(),
+        )
+    };
+
+    let json = deprecate(json!({ "deprecated": true, "superseded_by": "new-crate" })).good();
+    assert_eq!(
+        json,
+        json!({ "deprecated": true, "superseded_by": "new_crate" })
+    );
+    app.run_pending_background_jobs();
+
+    let krate = anon.show_crate("old_crate").krate;
+    assert!(krate.deprecated);
+    assert_eq!(krate.superseded_by.as_deref(), Some("new_crate"));
+    assert!(!anon.show_crate("new_crate").krate.deprecated);
+
+    // The new versions of a deprecated crate are deprecated in the index too
+    token
+        .enqueue_publish(PublishBuilder::new("old_crate").version("1.0.1"))
+        .good();
+    app.run_pending_background_jobs();
+    let entries = app.crates_from_index_head("ol/d_/old_crate");
+    assert_eq!(entries.len(), 2);
+    for entry in &entries {
+        assert_eq!(entry.deprecated, Some(true));
+        assert_eq!(entry.superseded_by.as_deref(), Some("new_crate"));
+    }
+
+    let json = deprecate(json!({ "deprecated": false, "superseded_by": "new_crate" }))
+        .bad_with_status(StatusCode::OK);
+    assert_eq!(
+        json.errors[0].detail,
+        "only deprecated crates can be superseded by another crate"
+    );
+    let json = deprecate(json!({ "deprecated": true, "superseded_by": "old_crate" }))
+        .bad_with_status(StatusCode::OK);
+    assert_eq!(json.errors[0].detail, "a crate can't supersede itself");
+    let json = deprecate(json!({ "deprecated": true, "superseded_by": "missing" }))
+        .bad_with_status(StatusCode::OK);
+    assert_eq!(json.errors[0].detail, "no crate named `missing`");
+
+    deprecate(json!({ "deprecated": false })).good();
+    app.run_pending_background_jobs();
+    let krate = anon.show_crate("old_crate").krate;
+    assert!(!krate.deprecated);
+    assert_eq!(krate.superseded_by, None);
+    for entry in app.crates_from_index_head("ol/d_/old_crate") {
+        assert_eq!(entry.deprecated, None);
+        assert_eq!(entry.superseded_by, None);
+    }
+
+    let other = app.db_new_user("other");
+    let json = other
+        .put::<()>(
+            "/api/v1/crates/old_crate/deprecate",
+            json!({ "deprecated": true }).to_string().as_bytes(),
+        )
+        .bad_with_status(StatusCode::OK);
+    assert_eq!(
+        json.errors[0].detail,
+        "only owners have permission to deprecate a crate"
+    );
+}
+
+#[test]
+fn publish_policy_restricts_api_tokens() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    let other_token = user.db_new_token("other");
+    app.db(|conn| {
+        CrateBuilder::new("foo_policy", user.as_model().id).expect_build(conn);
+    });
+
+    let json: serde_json::Value = user.get("/api/v1/crates/foo_policy/publish_policy").good();
+    assert_eq!(
+        json["publish_policy"]["allowed_token_ids"],
+        serde_json::Value::Null
+    );
+    assert_eq!(json["publish_policy"]["require_user_owner"], false);
+
+    let body = json!({
+        "publish_policy": { "allowed_token_ids": [token.as_model().id] }
+    });
+    let json: serde_json::Value = user
+        .put(
+            "/api/v1/crates/foo_policy/publish_policy",
+            body.to_string().as_bytes(),
+        )
+        .good();
+    assert_eq!(
+        json["publish_policy"]["allowed_token_ids"],
+        json!([token.as_model().id])
+    );
+
+    let crate_to_publish = PublishBuilder::new("foo_policy").version("2.0.0");
+    let json = other_token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("only allows publishing new versions with specific API tokens"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn publish_policy_rejects_unsupported_policies() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_policy", user.as_model().id).expect_build(conn);
+    });
+
+    for (policy, error) in &[
+        (
+            "trusted_publishing_only",
+            "doesn't support trusted publishing",
+        ),
+        (
+            "require_two_factor",
+            "doesn't support two-factor authentication",
+        ),
+    ] {
+        let body = format!(r#"{{ "publish_policy": {{ "{}": true }} }}"#, policy);
+        let json = user
+            .put::<()>("/api/v1/crates/foo_policy/publish_policy", body.as_bytes())
+            .bad_with_status(StatusCode::BAD_REQUEST);
+        assert!(json.errors[0].detail.contains(error), "{:?}", json.errors);
+    }
+}
+
+#[test]
+fn publish_policy_only_allows_tokens_of_owners() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let another_user = app.db_new_user("another");
+    let another_token = another_user.db_new_token("bar");
+    app.db(|conn| {
+        CrateBuilder::new("foo_policy", user.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({
+        "publish_policy": { "allowed_token_ids": [another_token.as_model().id] }
+    })
+    .to_string();
+    let json = user
+        .put::<()>("/api/v1/crates/foo_policy/publish_policy", body.as_bytes())
+        .bad_with_status(StatusCode::OK);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("only API tokens of the user owners"),
+        "{:?}",
+        json.errors
+    );
+
+    let json = another_user
+        .put::<()>("/api/v1/crates/foo_policy/publish_policy", body.as_bytes())
+        .bad_with_status(StatusCode::OK);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("only owners have permission"),
+        "{:?}",
+        json.errors
+    );
+
+    anon.get("/api/v1/crates/foo_policy/publish_policy")
+        .assert_forbidden();
+}
+
+#[test]
+fn show_includes_notable_dependents() {
+    use cargo_registry::tasks;
+    use swirl::Job;
+
+    let (app, anon, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let lib = CrateBuilder::new("popular_lib", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        for (name, downloads) in &[("small_app", 10), ("big_app", 1000), ("medium_app", 100)] {
+            CrateBuilder::new(name, user.id)
+                .downloads(*downloads)
+                .version(VersionBuilder::new("1.0.0").dependency(&lib, None))
+                .expect_build(conn);
+        }
+        CrateBuilder::new("yanked_app", user.id)
+            .downloads(10_000)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .dependency(&lib, None)
+                    .yanked(true),
+            )
+            .expect_build(conn);
+
+        tasks::update_notable_dependents().enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/popular_lib").good();
+    assert_eq!(
+        json["used_by"],
+        json!([
+            { "name": "big_app", "downloads": 1000 },
+            { "name": "medium_app", "downloads": 100 },
+            { "name": "small_app", "downloads": 10 },
+        ])
+    );
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/big_app").good();
+    assert_eq!(json["used_by"], json!([]));
+}
+
+#[test]
+fn yanking_notifies_the_owners_of_dependents() {
+    use cargo_registry::schema::{crate_owners, dependency_yank_notifications};
+    use cargo_registry::uploaders::{MemoryStorage, Uploader};
+
+    let (app, _, user, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(MemoryStorage::new()))
+        .with_token();
+    let user = user.as_model();
+
+    let _: GoodCrate = token
+        .enqueue_publish(PublishBuilder::new("yanked_lib").version("1.0.0"))
+        .good();
+    app.run_pending_background_jobs();
+
+    app.db(|conn| {
+        let lib: Crate = Crate::by_name("yanked_lib").first(conn).unwrap();
+        CrateBuilder::new("current_dependent", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&lib, None))
+            .expect_build(conn);
+        CrateBuilder::new("former_dependent", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&lib, None))
+            .version("2.0.0")
+            .expect_build(conn);
+        let muted = CrateBuilder::new("muted_dependent", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&lib, None))
+            .expect_build(conn);
+        diesel::update(crate_owners::table.filter(crate_owners::crate_id.eq(muted.id)))
+            .set(crate_owners::email_notifications.eq(false))
+            .execute(conn)
+            .unwrap();
+    });
+
+    token.yank("yanked_lib", "1.0.0").good();
+
+    let notified = app.db(|conn| {
+        dependency_yank_notifications::table
+            .inner_join(crates::table)
+            .select(crates::name)
+            .load::<String>(conn)
+            .unwrap()
+    });
+    assert_eq!(notified, vec!["current_dependent"]);
+
+    // The owners are only notified once per yanked version
+    token.unyank("yanked_lib", "1.0.0").good();
+    token.yank("yanked_lib", "1.0.0").good();
+    let count = app.db(|conn| {
+        dependency_yank_notifications::table
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap()
+    });
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn license_changes_are_recorded() {
+    use cargo_registry::schema::follows;
+    use cargo_registry::uploaders::{MemoryStorage, Uploader};
+
+    let (app, anon, user, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(MemoryStorage::new()))
+        .with_token();
+    let user = user.as_model();
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_license").version("1.0.0"))
+        .good();
+    app.db(|conn| {
+        let krate: Crate = Crate::by_name("foo_license").first(conn).unwrap();
+        diesel::insert_into(follows::table)
+            .values((follows::user_id.eq(user.id), follows::crate_id.eq(krate.id)))
+            .execute(conn)
+            .unwrap();
+    });
+    token
+        .enqueue_publish(PublishBuilder::new("foo_license").version("1.0.1"))
+        .good();
+    token
+        .enqueue_publish(
+            PublishBuilder::new("foo_license")
+                .version("1.1.0")
+                .license("MIT OR Apache-2.0"),
+        )
+        .good();
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_license/1.0.1").good();
+    assert_eq!(json["version"]["license_changed"], false);
+    assert_eq!(json["version"]["previous_license"], serde_json::Value::Null);
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_license/1.1.0").good();
+    assert_eq!(json["version"]["license_changed"], true);
+    assert_eq!(json["version"]["previous_license"], "MIT");
+
+    let json: serde_json::Value = anon.get("/api/v1/events").good();
+    let events = json["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["crate"] == "foo_license")
+        .map(|event| (event["kind"].as_str().unwrap(), event["version"].as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            ("publish", Some("1.0.0")),
+            ("publish", Some("1.0.1")),
+            ("publish", Some("1.1.0")),
+            ("license_change", Some("1.1.0")),
+        ]
+    );
+}
+
+#[test]
+fn localized_readmes_are_served_by_language() {
+    use cargo_registry::uploaders::{MemoryStorage, Uploader};
+
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(MemoryStorage::new()))
+        .with_token();
+
+    let manifest = b"[package.metadata.localized-readmes]\nzh-CN = \"README.zh-CN.md\"\n";
+    let files = [
+        ("foo_l10n-1.0.0/Cargo.toml", manifest as &[_]),
+        ("foo_l10n-1.0.0/README.zh-CN.md", b"# Foo zh" as &[_]),
+    ];
+    let crate_to_publish = PublishBuilder::new("foo_l10n")
+        .readme("# Foo")
+        .files(&files);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let mut request = anon.get_request("/api/v1/crates/foo_l10n/1.0.0/readme");
+    request.with_query("lang=zh-CN");
+    request.header(header::ACCEPT, "application/json");
+    let json: serde_json::Value = anon.run(request).good();
+    assert!(json["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("foo_l10n-1.0.0.zh-cn.html"));
+    assert_eq!(json["language"], "zh-cn");
+
+    let mut request = anon.get_request("/api/v1/crates/foo_l10n/1.0.0/readme");
+    request.header(header::ACCEPT, "application/json");
+    request.header(header::ACCEPT_LANGUAGE, "fr, zh;q=0.8");
+    let json: serde_json::Value = anon.run(request).good();
+    assert!(json["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("foo_l10n-1.0.0.zh-cn.html"));
+
+    // The main README is served if no language matches
+    let mut request = anon.get_request("/api/v1/crates/foo_l10n/1.0.0/readme");
+    request.with_query("lang=de");
+    request.header(header::ACCEPT, "application/json");
+    let json: serde_json::Value = anon.run(request).good();
+    assert!(json["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("foo_l10n-1.0.0.html"));
+    assert_eq!(json["language"], serde_json::Value::Null);
+}
+
+#[test]
+fn readmes_are_stored_for_each_version() {
+    use cargo_registry::uploaders::{MemoryStorage, Uploader};
+
+    let storage = MemoryStorage::new();
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_readmes").readme("# Old"))
+        .good();
+    token
+        .enqueue_publish(
+            PublishBuilder::new("foo_readmes")
+                .version("2.0.0")
+                .readme("# New"),
+        )
+        .good();
+    app.run_pending_background_jobs();
+
+    let readme = |version: &str| {
+        let mut request =
+            anon.get_request(&format!("/api/v1/crates/foo_readmes/{}/readme", version));
+        request.header(header::ACCEPT, "application/json");
+        let json: serde_json::Value = anon.run(request).good();
+        let path = json["url"].as_str().unwrap().trim_start_matches('/');
+        String::from_utf8(storage.get(path).unwrap().unwrap().content).unwrap()
+    };
+    assert!(readme("1.0.0").contains("Old</h1>"));
+    assert!(readme("2.0.0").contains("New</h1>"));
+}
+
+#[test]
+fn readme_sources_are_served_with_format_md() {
+    use cargo_registry::uploaders::{MemoryStorage, Uploader};
+
+    let storage = MemoryStorage::new();
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_raw_readme").readme("# Foo *raw*"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let response =
+        anon.get_with_query::<()>("/api/v1/crates/foo_raw_readme/1.0.0/readme", "format=md");
+    response
+        .assert_status(StatusCode::FOUND)
+        .assert_redirect_ends_with("foo_raw_readme-1.0.0.md");
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public,max-age=3600"
+    );
+    let source = storage
+        .get("readmes/foo_raw_readme/foo_raw_readme-1.0.0.md")
+        .unwrap()
+        .unwrap()
+        .content;
+    assert_eq!(source, b"# Foo *raw*");
+
+    anon.get_with_query::<()>("/api/v1/crates/foo_raw_readme/1.0.0/readme", "format=html")
+        .assert_redirect_ends_with("foo_raw_readme-1.0.0.html");
+    anon.get_with_query::<()>("/api/v1/crates/foo_raw_readme/1.0.0/readme", "format=pdf")
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // The versions rendered before the sources were stored have none
+    app.db(|conn| {
+        use cargo_registry::schema::readme_renderings;
+        diesel::update(readme_renderings::table)
+            .set(readme_renderings::has_source.eq(false))
+            .execute(conn)
+            .unwrap();
+    });
+    anon.get_with_query::<()>("/api/v1/crates/foo_raw_readme/1.0.0/readme", "format=md")
+        .assert_status(StatusCode::NOT_FOUND);
+    anon.get_with_query::<()>("/api/v1/crates/foo_raw_readme/2.0.0/readme", "format=md")
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn show_includes_the_checks_of_current_links() {
+    use cargo_registry::models::{CrateLinkCheck, LinkStatus};
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_links", user.id)
+            .homepage("https://foo.example.com")
+            .documentation("https://docs.example.com/foo")
+            .expect_build(conn);
+        CrateLinkCheck::record(
+            conn,
+            krate.id,
+            "homepage",
+            "https://foo.example.com",
+            LinkStatus::Parked,
+        )
+        .unwrap();
+        // The documentation link was changed since it was checked
+        CrateLinkCheck::record(
+            conn,
+            krate.id,
+            "documentation",
+            "https://old.example.com/foo",
+            LinkStatus::NotFound,
+        )
+        .unwrap();
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_links").good();
+    let checks = json["link_checks"].as_array().unwrap();
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0]["field"], "homepage");
+    assert_eq!(checks[0]["url"], "https://foo.example.com");
+    assert_eq!(checks[0]["status"], "parked");
+}
+
+#[test]
+fn readme_headings_are_nested() {
+    use cargo_registry::uploaders::{MemoryStorage, Uploader};
+
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(MemoryStorage::new()))
+        .with_token();
+
+    let crate_to_publish =
+        PublishBuilder::new("foo_toc").readme("# Foo\n\n## Usage\n\n## Usage\n\n# License\n");
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon
+        .get("/api/v1/crates/foo_toc/1.0.0/readme/headings")
+        .good();
+    let headings = json["headings"].as_array().unwrap();
+    assert_eq!(headings.len(), 2);
+    assert_eq!(headings[0]["text"], "Foo");
+    assert_eq!(headings[0]["children"][0]["anchor"], "usage");
+    assert_eq!(headings[0]["children"][1]["anchor"], "usage-1");
+    assert_eq!(headings[1]["anchor"], "license");
+}
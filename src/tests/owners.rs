@@ -1,16 +1,20 @@
 use crate::{
-    add_team_to_crate,
-    builders::{CrateBuilder, PublishBuilder},
-    new_team,
+    add_team_to_crate, new_team,
     util::{MockCookieUser, MockTokenUser, RequestHelper},
-    TestApp,
+    OkBool, TestApp,
 };
 use cargo_registry::{
-    models::Crate,
-    views::{EncodableCrateOwnerInvitation, EncodableOwner, InvitationResponse},
+    models::{Crate, RegistryEventKind},
+    test_util::{CrateBuilder, PublishBuilder},
+    views::{
+        EncodableCrateOwnerInvitation, EncodableCrateOwnershipTransfer, EncodableOwner,
+        EncodableRegistryEvent, InvitationResponse,
+    },
 };
 
-use conduit::StatusCode;
+use chrono::{Duration, Utc};
+
+use conduit::{header, StatusCode};
 use diesel::prelude::*;
 
 #[derive(Deserialize)]
@@ -159,6 +163,54 @@ fn owners_can_remove_self() {
         .contains("only owners have permission to modify owners",));
 }
 
+#[test]
+fn owner_changes_are_recorded_as_events() {
+    #[derive(Deserialize)]
+    struct EventsResponse {
+        events: Vec<EncodableRegistryEvent>,
+    }
+
+    let (app, anon, user, token) = TestApp::init().with_token();
+    let username = &user.as_model().gh_login;
+
+    let krate =
+        app.db(|conn| CrateBuilder::new("owners_events", user.as_model().id).expect_build(conn));
+    create_and_add_owner(&app, &token, "secondowner", &krate);
+    token
+        .remove_named_owner("owners_events", "secondowner")
+        .good();
+
+    let json: EventsResponse = anon.get("/api/v1/events").good();
+    let events = json
+        .events
+        .iter()
+        .map(|event| {
+            let owner = event.owner.as_ref().unwrap();
+            assert_eq!(event.crate_name, "owners_events");
+            assert_eq!(owner.kind, "user");
+            (event.kind, owner.login.as_str())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        vec![
+            (RegistryEventKind::OwnerAdd, username.as_str()),
+            (RegistryEventKind::OwnerAdd, "secondowner"),
+            (RegistryEventKind::OwnerRemove, "secondowner"),
+        ]
+    );
+
+    let since = json.events[1].id;
+    let json: EventsResponse = anon
+        .get_with_query("/api/v1/events", &format!("since={}", since))
+        .good();
+    assert_eq!(json.events.len(), 1);
+    assert_eq!(json.events[0].kind, RegistryEventKind::OwnerRemove);
+
+    anon.get_with_query::<()>("/api/v1/events", "per_page=1000")
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
 // Verify consistency when adidng or removing multiple owners in a single request.
 #[test]
 fn modify_multiple_owners() {
@@ -424,3 +476,206 @@ fn highest_gh_id_is_most_recent_account_we_know_of() {
     let json = invited_user.list_invitations();
     assert_eq!(json.crate_owner_invitations.len(), 1);
 }
+
+#[test]
+fn ownership_transfers_must_be_accepted_by_the_target() {
+    use cargo_registry::schema::crate_ownership_transfers;
+
+    #[derive(Deserialize)]
+    struct TransferResponse {
+        crate_ownership_transfer: EncodableCrateOwnershipTransfer,
+    }
+
+    let (app, anon, owner, token) = TestApp::init().with_token();
+    let krate =
+        app.db(|conn| CrateBuilder::new("transferred", owner.as_model().id).expect_build(conn));
+    create_and_add_owner(&app, &token, "co_owner", &krate);
+    let target = app.db_new_user("target");
+    let body = json!({ "user": "target" }).to_string();
+
+    target
+        .put::<()>("/api/v1/crates/transferred/transfer", body.as_bytes())
+        .bad_with_status(StatusCode::OK)
+        .assert_error("only owners have permission to transfer a crate");
+    owner
+        .put::<()>(
+            "/api/v1/crates/transferred/transfer",
+            json!({ "user": "nobody" }).to_string().as_bytes(),
+        )
+        .bad_with_status(StatusCode::OK)
+        .assert_error("could not find user with login `nobody`");
+
+    let json: TransferResponse = owner
+        .put("/api/v1/crates/transferred/transfer", body.as_bytes())
+        .good();
+    let transfer = json.crate_ownership_transfer;
+    assert_eq!(transfer.crate_name, "transferred");
+    assert_eq!(transfer.target_username, "target");
+    assert_eq!(transfer.expires_at - transfer.created_at, Duration::days(7));
+
+    // Nothing changes until the target accepts the transfer
+    let json: UserResponse = anon.get("/api/v1/crates/transferred/owner_user").good();
+    assert_eq!(json.users.len(), 2);
+
+    let token: String = app.db(|conn| {
+        crate_ownership_transfers::table
+            .select(crate_ownership_transfers::token)
+            .first(conn)
+            .unwrap()
+    });
+    let url = format!("/api/v1/me/crate_ownership_transfers/accept/{}", token);
+    anon.put::<OkBool>(&url, b"").good();
+
+    let json: UserResponse = anon.get("/api/v1/crates/transferred/owner_user").good();
+    let logins = json
+        .users
+        .iter()
+        .map(|u| u.login.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(logins, ["target"]);
+
+    // A transfer can only be accepted once
+    anon.put::<()>(&url, b"").assert_not_found();
+}
+
+#[test]
+fn ownership_transfers_expire() {
+    use cargo_registry::schema::crate_ownership_transfers;
+
+    let (app, anon, owner) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("not_transferred", owner.as_model().id).expect_build(conn));
+    app.db_new_user("target");
+    let body = json!({ "user": "target" }).to_string();
+
+    owner
+        .put::<()>("/api/v1/crates/not_transferred/transfer", body.as_bytes())
+        .assert_status(StatusCode::OK);
+    let token: String = app.db(|conn| {
+        diesel::update(crate_ownership_transfers::table)
+            .set(
+                crate_ownership_transfers::created_at
+                    .eq(Utc::now().naive_utc() - Duration::days(8)),
+            )
+            .returning(crate_ownership_transfers::token)
+            .get_result(conn)
+            .unwrap()
+    });
+
+    let url = format!("/api/v1/me/crate_ownership_transfers/accept/{}", token);
+    anon.put::<()>(&url, b"")
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error("the transfer expired, transfers must be accepted within 7 days");
+    let json: UserResponse = anon.get("/api/v1/crates/not_transferred/owner_user").good();
+    assert_eq!(json.users.len(), 1);
+
+    // Cancelling requires a pending transfer, the expired one was dropped
+    owner
+        .delete::<()>("/api/v1/crates/not_transferred/transfer")
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error("no transfer of this crate is pending");
+}
+
+#[test]
+fn ownership_transfers_are_dropped_once_their_requester_is_removed() {
+    use cargo_registry::schema::crate_ownership_transfers;
+
+    let (app, anon, owner, token) = TestApp::init().with_token();
+    let krate =
+        app.db(|conn| CrateBuilder::new("still_owned", owner.as_model().id).expect_build(conn));
+    let co_owner = create_and_add_owner(&app, &token, "co_owner", &krate);
+    app.db_new_user("target");
+    let body = json!({ "user": "target" }).to_string();
+
+    co_owner
+        .put::<()>("/api/v1/crates/still_owned/transfer", body.as_bytes())
+        .assert_status(StatusCode::OK);
+    token.remove_named_owner("still_owned", "co_owner").good();
+
+    let transfer_token: String = app.db(|conn| {
+        crate_ownership_transfers::table
+            .select(crate_ownership_transfers::token)
+            .first(conn)
+            .unwrap()
+    });
+    let url = format!(
+        "/api/v1/me/crate_ownership_transfers/accept/{}",
+        transfer_token
+    );
+    anon.put::<()>(&url, b"")
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error(
+            "the transfer was requested by a user who isn't an owner of the crate anymore",
+        );
+    let json: UserResponse = anon.get("/api/v1/crates/still_owned/owner_user").good();
+    let logins = json
+        .users
+        .iter()
+        .map(|u| u.login.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(logins, ["foo"]);
+    anon.put::<()>(&url, b"").assert_not_found();
+}
+
+#[test]
+fn owners_are_not_sent_again_until_they_changed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let krate = app.db(|conn| CrateBuilder::new("foo_owners_etag", user.id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo_owners_etag/owners";
+    let response = anon.get::<()>(url);
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let mut request = anon.get_request(url);
+    request.header(header::IF_NONE_MATCH, &etag);
+    anon.run::<()>(request)
+        .assert_status(StatusCode::NOT_MODIFIED);
+
+    app.db(|conn| {
+        let team = new_team("github:test_org:etag")
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, user, conn).unwrap();
+    });
+    let mut request = anon.get_request(url);
+    request.header(header::IF_NONE_MATCH, &etag);
+    let json: UserResponse = anon.run(request).good();
+    assert_eq!(json.users.len(), 2);
+}
+
+#[test]
+fn owners_are_sent_again_when_an_owner_changed_their_profile() {
+    use cargo_registry::schema::users;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| CrateBuilder::new("foo_owners_profile", user.id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo_owners_profile/owners";
+    let response = anon.get::<()>(url);
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.id))
+            .set((
+                users::gh_login.eq("foo_renamed"),
+                users::gh_avatar.eq("https://avatars.example.com/foo_renamed"),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+    let mut request = anon.get_request(url);
+    request.header(header::IF_NONE_MATCH, &etag);
+    let json: UserResponse = anon.run(request).good();
+    assert_eq!(json.users[0].login, "foo_renamed");
+    assert_eq!(
+        json.users[0].avatar.as_deref(),
+        Some("https://avatars.example.com/foo_renamed")
+    );
+}
@@ -0,0 +1,147 @@
+use crate::{
+    util::{MockCookieUser, RequestHelper, TestApp},
+    OkBool,
+};
+use cargo_registry::{
+    test_util::PublishBuilder,
+    uploaders::{MemoryStorage, Uploader},
+    views::EncodableQuarantinedVersion,
+};
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct QuarantinedVersionList {
+    quarantined_versions: Vec<EncodableQuarantinedVersion>,
+}
+
+const SPAM_README: &str = "Best casino bonus! Sports betting at https://a.example and \
+    https://b.example, buy now at https://c.example";
+
+fn make_admin(app: &TestApp, user: &MockCookieUser) {
+    use cargo_registry::schema::users;
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn is_in_index(app: &TestApp, path: &str) -> bool {
+    let index = app.upstream_repository();
+    let tree = index.head().unwrap().peel_to_tree().unwrap();
+    tree.get_path(Path::new(path)).is_ok()
+}
+
+fn list(user: &MockCookieUser) -> Vec<EncodableQuarantinedVersion> {
+    let json: QuarantinedVersionList = user.get("/api/v1/admin/quarantined_versions").good();
+    json.quarantined_versions
+}
+
+#[test]
+fn spam_is_held_until_approved() {
+    let storage = MemoryStorage::new();
+    let (app, anon, user, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("spm")
+        .description("Casino https://a.example")
+        .readme(SPAM_README);
+    let json = token.enqueue_publish(crate_to_publish).good();
+    assert_eq!(json.warnings.other.len(), 1);
+    app.run_pending_background_jobs();
+    assert!(!is_in_index(&app, "3/s/spm"));
+
+    // The version is hidden and its README isn't rendered until it is approved
+    assert!(anon.show_crate("spm").versions.is_empty());
+    anon.get::<()>("/api/v1/crates/spm/1.0.0")
+        .bad_with_status(StatusCode::OK)
+        .assert_error("crate `spm` does not have a version `1.0.0`");
+    assert_eq!(storage.paths(), vec!["crates/spm/spm-1.0.0.crate"]);
+
+    make_admin(&app, &user);
+    let held = list(&user);
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0].crate_name, "spm");
+    assert_eq!(held[0].num, "1.0.0");
+    assert_eq!(held[0].published_by.as_deref(), Some("foo"));
+    assert!(held[0]
+        .reasons
+        .contains(&"contains the spam phrase `casino`".to_string()));
+
+    let url = format!(
+        "/api/v1/admin/quarantined_versions/{}/approve",
+        held[0].version_id
+    );
+    let _: OkBool = user.put(&url, b"").good();
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("3/s/spm");
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0].vers, "1.0.0");
+    assert!(list(&user).is_empty());
+    assert_eq!(anon.show_crate("spm").versions.len(), 1);
+    assert_eq!(
+        storage.paths(),
+        vec![
+            "crates/spm/spm-1.0.0.crate",
+            "readmes/spm/spm-1.0.0.html",
+            "readmes/spm/spm-1.0.0.md",
+        ]
+    );
+}
+
+#[test]
+fn rejecting_spam_deletes_the_crate() {
+    let storage = MemoryStorage::new();
+    let (app, anon, user, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("spm")
+        .description("Casino https://a.example")
+        .readme(SPAM_README);
+    token.enqueue_publish(crate_to_publish).good();
+    make_admin(&app, &user);
+    let held = list(&user);
+    assert_eq!(storage.paths(), vec!["crates/spm/spm-1.0.0.crate"]);
+
+    let url = format!("/api/v1/admin/quarantined_versions/{}", held[0].version_id);
+    let _: OkBool = user.delete(&url).good();
+    app.run_pending_background_jobs();
+
+    assert!(list(&user).is_empty());
+    assert!(!is_in_index(&app, "3/s/spm"));
+    anon.get::<()>("/api/v1/crates/spm").assert_not_found();
+    assert!(storage.paths().is_empty());
+}
+
+#[test]
+fn regular_crates_are_not_held() {
+    let (app, _, user, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(MemoryStorage::new()))
+        .with_token();
+
+    let json = token
+        .enqueue_publish(PublishBuilder::new("foo_reg").readme("A library parsing foo"))
+        .good();
+    assert!(json.warnings.other.is_empty());
+    app.run_pending_background_jobs();
+    assert_eq!(app.crates_from_index_head("fo/o_/foo_reg").len(), 1);
+
+    make_admin(&app, &user);
+    assert!(list(&user).is_empty());
+}
+
+#[test]
+fn only_admins_can_review_quarantined_versions() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    user.get::<()>("/api/v1/admin/quarantined_versions")
+        .assert_forbidden();
+}
@@ -1,5 +1,5 @@
-use crate::builders::CrateBuilder;
 use crate::{RequestHelper, TestApp};
+use cargo_registry::test_util::CrateBuilder;
 
 use conduit::StatusCode;
 use diesel::prelude::*;
@@ -58,6 +58,62 @@ fn can_download_crate_in_read_only_mode() {
     })
 }
 
+#[test]
+fn maintenance_mode_rejects_writes() {
+    let (app, anon, user, token) = TestApp::init()
+        .with_config(|config| config.maintenance_mode = true)
+        .with_token();
+    app.db(|conn| {
+        CrateBuilder::new("foo_maintenance", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    anon.get::<()>("/api/v1/crates/foo_maintenance")
+        .assert_status(StatusCode::OK);
+    anon.get::<()>("/api/v1/crates/foo_maintenance/1.0.0/download")
+        .assert_status(StatusCode::FOUND);
+    token
+        .delete::<()>("/api/v1/crates/foo_maintenance/1.0.0/yank")
+        .bad_with_status(StatusCode::SERVICE_UNAVAILABLE)
+        .assert_error(
+            "Crates.io is currently in read-only mode for maintenance. Please try again later.",
+        );
+}
+
+#[test]
+fn maintenance_mode_can_be_toggled_at_runtime() {
+    use cargo_registry::schema::users;
+
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_maintenance", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let set_maintenance_mode = |enabled: bool| {
+        let body = json!({ "enabled": enabled }).to_string();
+        user.put::<()>(
+            "/api/v1/admin/feature_flags/maintenance_mode",
+            body.as_bytes(),
+        )
+        .assert_status(StatusCode::OK);
+    };
+
+    set_maintenance_mode(true);
+    user.put::<()>("/api/v1/crates/foo_maintenance/follow", b"")
+        .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+
+    set_maintenance_mode(false);
+    user.put::<()>("/api/v1/crates/foo_maintenance/follow", b"")
+        .assert_status(StatusCode::OK);
+}
+
 fn set_read_only(conn: &PgConnection) -> QueryResult<()> {
     diesel::sql_query("SET TRANSACTION READ ONLY").execute(conn)?;
     diesel::sql_query("SAVEPOINT test_post_readonly").execute(conn)?;
@@ -0,0 +1,60 @@
+use crate::util::{RequestHelper, TestApp};
+
+use conduit::StatusCode;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+struct Preview {
+    html: String,
+}
+
+#[test]
+fn previewing_a_readme_requires_a_user() {
+    let (_, anon) = TestApp::init().empty();
+    anon.put::<()>("/api/v1/readme/preview", br##"{"readme": "# Hi"}"##)
+        .assert_forbidden();
+}
+
+#[test]
+fn readmes_are_previewed_as_they_would_be_published() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = br##"{"readme": "# Hi\n\n<script>alert(1)</script>\n\n*a*"}"##;
+    let preview: Preview = user.put("/api/v1/readme/preview", body).good();
+    assert!(preview.html.starts_with(
+        "<h1><a href=\"#hi\" id=\"user-content-hi\" rel=\"nofollow noopener noreferrer\"></a>Hi</h1>\n"
+    ));
+    assert!(!preview.html.contains("script"));
+    assert!(preview.html.ends_with("<p><em>a</em></p>\n"));
+
+    let body = br##"{"readme": "*a*", "readme_file": "README.txt"}"##;
+    let preview: Preview = user.put("/api/v1/readme/preview", body).good();
+    assert_eq!(preview.html, "*a*");
+}
+
+#[test]
+fn readme_previews_are_rate_limited() {
+    let (_, _, user) = TestApp::init()
+        .with_config(|config| {
+            config.readme_preview_rate_limit.rate = Duration::from_secs(60);
+            config.readme_preview_rate_limit.burst = 1;
+        })
+        .with_user();
+
+    let body = br##"{"readme": "# Hi"}"##;
+    user.put::<Preview>("/api/v1/readme/preview", body).good();
+    user.put::<()>("/api/v1/readme/preview", body)
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[test]
+fn readme_previews_are_limited_in_size() {
+    let (_, _, user) = TestApp::init()
+        .with_config(|config| config.render.limits.max_size = 10)
+        .with_user();
+
+    let body = json!({ "readme": "a".repeat(2000) }).to_string();
+    user.put::<()>("/api/v1/readme/preview", body.as_bytes())
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error("the README is too large to be previewed");
+}
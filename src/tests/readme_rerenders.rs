@@ -0,0 +1,89 @@
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use cargo_registry::{
+    test_util::PublishBuilder,
+    uploaders::{MemoryStorage, Uploader},
+    views::EncodableReadmeRerender,
+};
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct ReadmeRerenderList {
+    readme_rerenders: Vec<EncodableReadmeRerender>,
+}
+
+#[derive(Deserialize)]
+struct ReadmeRerenderResponse {
+    readme_rerender: EncodableReadmeRerender,
+}
+
+fn make_admin(app: &TestApp, user: &MockCookieUser) {
+    use cargo_registry::schema::users;
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[test]
+fn only_admins_can_rerender_readmes() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    user.get::<()>("/api/v1/admin/readme_rerenders")
+        .assert_forbidden();
+    user.post::<()>("/api/v1/admin/readme_rerenders", b"")
+        .assert_forbidden();
+}
+
+#[test]
+fn readmes_are_rendered_again_from_the_crate_files() {
+    let storage = MemoryStorage::new();
+    let uploader = Uploader::Memory(storage.clone());
+    let (app, _, user, token) = TestApp::full()
+        .with_config(|config| config.uploader = uploader)
+        .with_token();
+    make_admin(&app, &user);
+
+    let manifest = b"[package]\nname = \"foo_rerender\"\nreadme = \"docs/README.md\"\n";
+    let files = [
+        ("foo_rerender-1.0.0/Cargo.toml", manifest as &[_]),
+        (
+            "foo_rerender-1.0.0/docs/README.md",
+            b"# From the crate file" as &[_],
+        ),
+    ];
+    let crate_to_publish = PublishBuilder::new("foo_rerender")
+        .readme("# From the metadata")
+        .files(&files);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let readme_path = "readmes/foo_rerender/foo_rerender-1.0.0.html";
+    let readme = |storage: &MemoryStorage| {
+        String::from_utf8(storage.get(readme_path).unwrap().unwrap().content).unwrap()
+    };
+    assert!(readme(&storage).contains("From the metadata"));
+
+    let json: ReadmeRerenderResponse = user.post("/api/v1/admin/readme_rerenders", b"").good();
+    assert_eq!(json.readme_rerender.total, 1);
+    assert!(json.readme_rerender.finished_at.is_none());
+
+    // Only one re-rendering runs at a time
+    user.post::<()>("/api/v1/admin/readme_rerenders", b"")
+        .bad_with_status(StatusCode::OK)
+        .assert_error("the READMEs are already being rendered again");
+
+    app.run_pending_background_jobs();
+    assert!(readme(&storage).contains("From the crate file"));
+
+    let json: ReadmeRerenderList = user.get("/api/v1/admin/readme_rerenders").good();
+    assert_eq!(json.readme_rerenders.len(), 1);
+    let rerender = &json.readme_rerenders[0];
+    assert_eq!(rerender.rendered, 1);
+    assert_eq!(rerender.skipped, 0);
+    assert!(rerender.finished_at.is_some());
+}
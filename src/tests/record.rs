@@ -1,8 +1,10 @@
 use crate::new_user;
-use cargo_registry::models::NewUser;
+use cargo_registry::{
+    http_client::{Exchange, RecordedRequest, RecordedResponse},
+    models::NewUser,
+};
 use std::{
     borrow::Cow,
-    collections::HashSet,
     fs::{self, File},
     future::Future,
     io::{self, prelude::*},
@@ -71,7 +73,7 @@ impl Drop for Bomb {
     }
 }
 
-fn cache_file(name: &str) -> PathBuf {
+pub fn cache_file(name: &str) -> PathBuf {
     PathBuf::from(file!())
         .parent()
         .unwrap()
@@ -206,27 +208,6 @@ impl<'a> tower_service::Service<&'a TcpStream> for Proxy {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct Exchange {
-    request: RecordedRequest,
-    response: RecordedResponse,
-}
-
-#[derive(Serialize, Deserialize)]
-struct RecordedRequest {
-    uri: String,
-    method: String,
-    headers: HashSet<(String, String)>,
-    body: String,
-}
-
-#[derive(Serialize, Deserialize)]
-struct RecordedResponse {
-    status: u16,
-    headers: HashSet<(String, String)>,
-    body: String,
-}
-
 type Client = hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
 type ResponseAndExchange = (Response<Body>, Exchange);
 
@@ -0,0 +1,103 @@
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::{
+    test_util::{CrateBuilder, PublishBuilder},
+    views::EncodableReservedCrateName,
+};
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct ReservedCrateNameList {
+    reserved_crate_names: Vec<EncodableReservedCrateName>,
+}
+
+#[derive(Deserialize)]
+struct ReservedCrateNameResponse {
+    reserved_crate_name: EncodableReservedCrateName,
+}
+
+fn make_admin(app: &TestApp, user: &MockCookieUser) {
+    use cargo_registry::schema::users;
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[test]
+fn only_admins_can_reserve_names() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    user.get::<()>("/api/v1/admin/reserved_crate_names")
+        .assert_forbidden();
+    user.put::<()>("/api/v1/admin/reserved_crate_names/foo_reserved", b"{}")
+        .assert_forbidden();
+}
+
+#[test]
+fn reserved_names_are_refused_at_publish() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    make_admin(&app, &user);
+
+    let body = json!({ "reason": "typo of `foo_popular`" }).to_string();
+    let json: ReservedCrateNameResponse = user
+        .put(
+            "/api/v1/admin/reserved_crate_names/foo_reserved",
+            body.as_bytes(),
+        )
+        .good();
+    assert_eq!(json.reserved_crate_name.name, "foo_reserved");
+    assert_eq!(json.reserved_crate_name.reserved_by.as_deref(), Some("foo"));
+
+    // The reason of a reservation can be changed with any form of the name
+    let body = json!({ "reason": "typo of `foo-popular`" }).to_string();
+    let json: ReservedCrateNameResponse = user
+        .put(
+            "/api/v1/admin/reserved_crate_names/Foo-Reserved",
+            body.as_bytes(),
+        )
+        .good();
+    assert_eq!(json.reserved_crate_name.name, "foo_reserved");
+
+    let crate_to_publish = PublishBuilder::new("FOO-reserved");
+    token
+        .enqueue_publish(crate_to_publish)
+        .bad_with_status(StatusCode::OK)
+        .assert_error(
+            "cannot upload a crate with a reserved name: `foo_reserved` is reserved \
+             (typo of `foo-popular`), please choose another name",
+        );
+
+    let json: ReservedCrateNameList = user.get("/api/v1/admin/reserved_crate_names").good();
+    assert!(json
+        .reserved_crate_names
+        .iter()
+        .any(|reserved| reserved.name == "foo_reserved"));
+
+    let _: OkBool = user
+        .delete("/api/v1/admin/reserved_crate_names/foo-reserved")
+        .good();
+    token
+        .enqueue_publish(PublishBuilder::new("FOO-reserved"))
+        .good();
+}
+
+#[test]
+fn names_of_existing_crates_cannot_be_reserved() {
+    let (app, _, user) = TestApp::init().with_user();
+    make_admin(&app, &user);
+    app.db(|conn| {
+        CrateBuilder::new("foo_existing", user.as_model().id).expect_build(conn);
+    });
+
+    user.put::<()>("/api/v1/admin/reserved_crate_names/Foo-Existing", b"{}")
+        .bad_with_status(StatusCode::BAD_REQUEST)
+        .assert_error("the crate `Foo-Existing` already exists");
+    user.put::<()>("/api/v1/admin/reserved_crate_names/foo%20bar", b"{}")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+}
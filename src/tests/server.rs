@@ -1,7 +1,7 @@
 use conduit::{header, Method};
 
-use crate::builders::*;
 use crate::util::*;
+use cargo_registry::test_util::*;
 
 #[test]
 fn user_agent_is_required() {
@@ -79,3 +79,12 @@ fn block_traffic_via_arbitrary_header_and_value() {
     let resp = anon.run::<()>(req);
     resp.assert_status(StatusCode::FOUND);
 }
+
+#[test]
+fn ready_when_all_migrations_ran() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let json: serde_json::Value = anon.get("/api/private/ready").good();
+    assert_eq!(json["ready"], true);
+    assert_eq!(json["missing_migrations"], json!([]));
+}
@@ -0,0 +1,119 @@
+use crate::{util::RequestHelper, TestApp};
+use cargo_registry::{
+    admin::{export_snapshot, import_snapshot, snapshot::Snapshot},
+    git::{Credentials, Repository, RepositoryConfig},
+    models::{Crate, Version},
+    test_util::PublishBuilder,
+    uploaders::{MemoryStorage, Uploader},
+    views::GoodCrate,
+};
+
+use std::fs;
+use std::path::Path;
+
+use diesel::prelude::*;
+use url::Url;
+
+const CRATE_PATH: &str = "crates/foo_snapshot/foo_snapshot-1.0.0.crate";
+
+fn clone_index() -> Repository {
+    let repository_config = RepositoryConfig {
+        index_location: Url::from_file_path(&crate::git::bare()).unwrap(),
+        credentials: Credentials::Missing,
+    };
+    Repository::open(&repository_config).unwrap()
+}
+
+fn export(dir: &Path) -> Snapshot {
+    let storage = MemoryStorage::new();
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_snapshot").version("1.0.0");
+    let _: GoodCrate = token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let index = clone_index();
+    app.db(|conn| {
+        let app = app.as_inner();
+        export_snapshot::export(
+            conn,
+            &index,
+            &app.config.uploader,
+            app.http_client(),
+            dir,
+            &[],
+        )
+    })
+}
+
+#[test]
+fn snapshots_are_exported() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = export(dir.path());
+
+    assert_eq!(snapshot.crates.len(), 1);
+    assert_eq!(snapshot.crates[0].name, "foo_snapshot");
+    assert_eq!(snapshot.crates[0].versions[0].num, "1.0.0");
+    assert!(dir.path().join("snapshot.json").exists());
+    assert!(dir.path().join("index/fo/o_/foo_snapshot").exists());
+    assert!(dir.path().join(CRATE_PATH).exists());
+}
+
+#[test]
+fn snapshots_are_imported() {
+    let dir = tempfile::tempdir().unwrap();
+    export(dir.path());
+
+    let storage = MemoryStorage::new();
+    let (app, _) = TestApp::full()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .empty();
+    let imported = app.db(|conn| {
+        let app = app.as_inner();
+        import_snapshot::import(conn, &app.config.uploader, app.http_client(), dir.path())
+    });
+    assert_eq!(imported, (1, 1));
+    app.run_pending_background_jobs();
+
+    app.db(|conn| {
+        let krate: Crate = Crate::by_name("foo_snapshot").first(conn).unwrap();
+        let versions: Vec<Version> = Version::belonging_to(&krate).load(conn).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].num.to_string(), "1.0.0");
+    });
+    assert_eq!(storage.paths(), vec![CRATE_PATH]);
+    let entries = app.crates_from_index_head("fo/o_/foo_snapshot");
+    assert_eq!(entries.len(), 1);
+
+    // Importing the snapshot again skips the existing versions
+    let imported = app.db(|conn| {
+        let app = app.as_inner();
+        import_snapshot::import(conn, &app.config.uploader, app.http_client(), dir.path())
+    });
+    assert_eq!(imported, (0, 1));
+}
+
+#[test]
+#[should_panic(expected = "Invalid crate name")]
+fn snapshots_with_invalid_crate_names_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = json!({
+        "crates": [{
+            "name": "../../etc",
+            "description": null,
+            "homepage": null,
+            "documentation": null,
+            "repository": null,
+            "versions": [],
+        }],
+    });
+    fs::write(dir.path().join("snapshot.json"), snapshot.to_string()).unwrap();
+
+    let (app, _) = TestApp::init().empty();
+    app.db(|conn| {
+        let app = app.as_inner();
+        import_snapshot::import(conn, &app.config.uploader, app.http_client(), dir.path())
+    });
+}
@@ -0,0 +1,54 @@
+use crate::{util::RequestHelper, GoodCrate, TestApp};
+use cargo_registry::{
+    test_util::PublishBuilder,
+    uploaders::{MemoryStorage, Uploader},
+};
+
+use conduit::{Handler, Method};
+
+const CRATE_PATH: &str = "crates/foo_storage/foo_storage-1.0.0.crate";
+
+#[test]
+fn publish_stores_the_crate_file() {
+    let storage = MemoryStorage::new();
+    let (_, _, user) = TestApp::with_proxy()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo_storage").version("1.0.0");
+    let _: GoodCrate = user.enqueue_publish(crate_to_publish).good();
+
+    assert_eq!(storage.paths(), vec![CRATE_PATH]);
+    let file = storage.get(CRATE_PATH).unwrap().unwrap();
+    assert_eq!(file.content_type, "application/x-tar");
+    assert!(!file.content.is_empty());
+}
+
+#[test]
+fn publish_can_be_retried_after_a_storage_failure() {
+    let storage = MemoryStorage::new();
+    let (app, _, user) = TestApp::with_proxy()
+        .with_config(|config| config.uploader = Uploader::Memory(storage.clone()))
+        .with_user();
+
+    storage.fail_next(1);
+    let crate_to_publish = PublishBuilder::new("foo_storage").version("1.0.0");
+    let mut request = user.request_builder(Method::PUT, "/api/v1/crates/new");
+    request.with_body(&crate_to_publish.body());
+    let error = app
+        .as_middleware()
+        .call(&mut request)
+        .map(|_| ())
+        .unwrap_err();
+    assert!(
+        error.to_string().contains("injected failure"),
+        "unexpected error: {}",
+        error
+    );
+    assert!(storage.paths().is_empty());
+
+    let crate_to_publish = PublishBuilder::new("foo_storage").version("1.0.0");
+    let json: GoodCrate = user.enqueue_publish(crate_to_publish).good();
+    assert_eq!(json.krate.max_version, "1.0.0");
+    assert_eq!(storage.paths(), vec![CRATE_PATH]);
+}
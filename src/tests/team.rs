@@ -1,11 +1,11 @@
 use crate::{
-    add_team_to_crate,
-    builders::{CrateBuilder, PublishBuilder},
-    new_team,
-    record::GhUser,
-    OwnerTeamsResponse, RequestHelper, TestApp,
+    add_team_to_crate, new_team, record::GhUser, OwnerTeamsResponse, RequestHelper, TestApp,
+};
+use cargo_registry::{
+    models::{Crate, NewUser},
+    test_util::{CrateBuilder, PublishBuilder},
+    views::EncodableTeamMember,
 };
-use cargo_registry::models::{Crate, NewUser};
 use std::sync::Once;
 
 use conduit::StatusCode;
@@ -19,6 +19,24 @@ impl crate::util::MockAnonymousUser {
     }
 }
 
+/// List the cached members of a team owning the specified crate.
+fn crate_owner_team_members(
+    requester: &impl RequestHelper,
+    krate_name: &str,
+    team_login: &str,
+) -> crate::util::Response<TeamMembersResponse> {
+    let url = format!(
+        "/api/v1/crates/{}/owner_team/{}/members",
+        krate_name, team_login
+    );
+    requester.get(&url)
+}
+
+#[derive(Deserialize)]
+struct TeamMembersResponse {
+    members: Vec<EncodableTeamMember>,
+}
+
 // Users: `crates-tester-1` and `crates-tester-2`
 // Passwords: ask acrichto or gankro
 // Teams: `crates-test-org:core`, `crates-test-org:just-for-crates-2`
@@ -153,6 +171,110 @@ fn add_team_mixed_case() {
     assert_eq!(json.teams[0].login, "github:crates-test-org:core");
 }
 
+// Test adding a team with the GitHub responses replayed in-process instead of by the proxy
+#[test]
+fn add_team_with_replayed_responses() {
+    let (app, _) = TestApp::init()
+        .with_replay("team_add_team_mixed_case")
+        .empty();
+    let user = app.db_new_user("crates-tester-2");
+    let token = user.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_replayed", user.as_model().id).expect_build(conn);
+    });
+
+    token
+        .add_named_owner("foo_replayed", "github:Crates-Test-Org:Core")
+        .good();
+
+    app.db(|conn| {
+        let krate: Crate = Crate::by_name("foo_replayed").first(conn).unwrap();
+        let owners = krate.owners(conn).unwrap();
+        assert_eq!(owners.len(), 2);
+        assert_eq!(owners[1].login(), "github:crates-test-org:core");
+    });
+}
+
+// Test that the members of a team are cached when their membership is checked
+#[test]
+fn team_members_are_cached() {
+    let (app, anon) = TestApp::init()
+        .with_replay("team_add_team_mixed_case")
+        .empty();
+    let user = app.db_new_user("crates-tester-2");
+    let token = user.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_members", user.as_model().id).expect_build(conn);
+    });
+
+    crate_owner_team_members(&user, "foo_members", "github:crates-test-org:core")
+        .assert_not_found();
+
+    token
+        .add_named_owner("foo_members", "github:Crates-Test-Org:Core")
+        .good();
+
+    let json = crate_owner_team_members(&user, "foo_members", "github:Crates-Test-Org:Core").good();
+    assert_eq!(json.members.len(), 1);
+    assert_eq!(json.members[0].user.login, "crates-tester-2");
+
+    crate_owner_team_members(&anon, "foo_members", "github:crates-test-org:core")
+        .assert_forbidden();
+}
+
+// Test that only the current members of a team are listed, and only to the owners of the crate
+#[test]
+fn team_members_with_ended_memberships() {
+    use cargo_registry::schema::team_memberships;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let former = app.db_new_user("foo_former_member");
+
+    app.db(|conn| {
+        let t = new_team("github:crates-test-org:team_foo")
+            .create_or_update(conn)
+            .unwrap();
+        let krate = CrateBuilder::new("foo_team_members", user.as_model().id).expect_build(conn);
+        add_team_to_crate(&t, &krate, user.as_model(), conn).unwrap();
+        diesel::insert_into(team_memberships::table)
+            .values(&vec![
+                (
+                    team_memberships::team_id.eq(t.id),
+                    team_memberships::user_id.eq(former.as_model().id),
+                    team_memberships::active.eq(false),
+                ),
+                (
+                    team_memberships::team_id.eq(t.id),
+                    team_memberships::user_id.eq(user.as_model().id),
+                    team_memberships::active.eq(true),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json =
+        crate_owner_team_members(&user, "foo_team_members", "github:crates-test-org:team_foo")
+            .good();
+    let members = json
+        .members
+        .iter()
+        .map(|member| member.user.login.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(members, ["foo"]);
+
+    crate_owner_team_members(
+        &former,
+        "foo_team_members",
+        "github:crates-test-org:team_foo",
+    )
+    .assert_forbidden();
+    crate_owner_team_members(&anon, "foo_team_members", "github:crates-test-org:team_foo")
+        .assert_forbidden();
+}
+
 // Test adding team as owner when not on it
 #[test]
 fn add_team_as_non_member() {
@@ -378,3 +500,34 @@ fn crates_by_team_id_not_including_deleted_owners() {
     let json = anon.search(&format!("team_id={}", team.id));
     assert_eq!(json.crates.len(), 0);
 }
+
+// Teams of another authentication provider, which was used before the current one
+#[test]
+fn team_of_another_provider() {
+    let (app, anon, _, token) = TestApp::init().with_token();
+    let owner = app.db_new_user("foo_other_provider_owner");
+    let owner = owner.as_model();
+
+    app.db(|conn| {
+        let t = new_team("oidc:admins").create_or_update(conn).unwrap();
+        let krate = CrateBuilder::new("foo_other_provider", owner.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        add_team_to_crate(&t, &krate, owner, conn).unwrap();
+    });
+
+    let json = anon.crate_owner_teams("foo_other_provider").good();
+    assert_eq!(json.teams[0].login, "oidc:admins");
+    assert_none!(&json.teams[0].url);
+
+    let json = token
+        .yank("foo_other_provider", "1.0.0")
+        .bad_with_status(StatusCode::OK);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("must already be an owner to yank or unyank"),
+        "{:?}",
+        json.errors
+    );
+}
@@ -1,16 +1,17 @@
 use crate::{
-    builders::{CrateBuilder, VersionBuilder},
-    new_user,
+    add_team_to_crate, new_team, new_user,
     util::{MockCookieUser, RequestHelper, Response, StatusCode},
     OkBool, TestApp,
 };
 use cargo_registry::{
     models::{Email, NewUser, User},
     schema::crate_owners,
+    test_util::{CrateBuilder, VersionBuilder},
     views::{EncodablePrivateUser, EncodablePublicUser, EncodableVersion, OwnedCrate},
 };
 
 use diesel::prelude::*;
+use serde_json::Value;
 
 #[derive(Deserialize)]
 struct AuthResponse {
@@ -152,6 +153,24 @@ fn show() {
     assert_eq!(Some("https://github.com/bar".into()), json.user.url);
 }
 
+#[test]
+fn users_without_a_github_id_have_no_github_url() {
+    let (app, anon) = TestApp::init().empty();
+    app.db(|conn| {
+        // Like the users who logged in with an OpenID Connect provider
+        NewUser {
+            gh_id: 0,
+            ..new_user("foo_oidc")
+        }
+        .create_or_update(None, conn)
+        .unwrap();
+    });
+
+    let json: UserShowPublicResponse = anon.get("/api/v1/users/foo_oidc").good();
+    assert_eq!("foo_oidc", json.user.login);
+    assert_eq!(None, json.user.url);
+}
+
 #[test]
 fn show_latest_user_case_insensitively() {
     let (app, anon) = TestApp::init().empty();
@@ -299,6 +318,46 @@ fn following() {
         .bad_with_status(StatusCode::BAD_REQUEST);
 }
 
+#[test]
+fn following_with_filters() {
+    #[derive(Deserialize)]
+    struct R {
+        versions: Vec<EncodableVersion>,
+    }
+
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+    app.db(|conn| {
+        CrateBuilder::new("foo_filters", user_id)
+            .version("0.1.0")
+            .version("0.1.1")
+            .version("0.2.0")
+            .version("1.0.0-beta.1")
+            .version(VersionBuilder::new("1.0.0").yanked(true))
+            .version("1.0.1")
+            .version("2.0.0+build.5")
+            .expect_build(conn);
+    });
+    user.put::<OkBool>("/api/v1/crates/foo_filters/follow", b"")
+        .good();
+
+    let nums = |query: &str| {
+        let r: R = user.get_with_query("/api/v1/me/updates", query).good();
+        let mut nums = r.versions.into_iter().map(|v| v.num).collect::<Vec<_>>();
+        nums.sort();
+        nums
+    };
+    assert_eq!(nums("").len(), 7);
+    assert_eq!(
+        nums("filter=major_only"),
+        ["0.1.0", "0.2.0", "1.0.0", "2.0.0+build.5"]
+    );
+    assert_eq!(nums("filter=security_only"), ["1.0.0"]);
+
+    user.get_with_query::<()>("/api/v1/me/updates", "filter=minor_only")
+        .bad_with_status(StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn user_total_downloads() {
     use diesel::update;
@@ -507,6 +566,90 @@ fn test_empty_email_not_added() {
     );
 }
 
+#[test]
+fn disposable_emails_are_rejected() {
+    let (_app, _anon, user) = TestApp::init().with_user();
+    let model = user.as_model();
+
+    for email in &["spam@mailinator.com", "spam@eu.YOPMAIL.com"] {
+        let json = user
+            .update_email_more_control(model.id, Some(email))
+            .bad_with_status(StatusCode::BAD_REQUEST);
+        assert!(
+            json.errors[0]
+                .detail
+                .contains("disposable email providers are not accepted"),
+            "{:?}",
+            json.errors
+        );
+    }
+
+    user.update_email("someone@mailinator.example.com");
+}
+
+#[test]
+fn admins_can_block_and_allow_email_domains() {
+    use cargo_registry::schema::{emails, users};
+
+    let (app, _anon, user) = TestApp::init().with_user();
+    let model = user.as_model();
+
+    let body = json!({ "blocked": true }).to_string();
+    user.put::<()>(
+        "/api/v1/admin/email_domain_rules/spam.example",
+        body.as_bytes(),
+    )
+    .assert_forbidden();
+
+    app.db(|conn| {
+        diesel::update(users::table.find(model.id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    // An email set before the domain was blocked can't be verified anymore
+    user.update_email("someone@spam.example");
+    let token: String = app.db(|conn| {
+        Email::belonging_to(model)
+            .select(emails::token)
+            .first(conn)
+            .unwrap()
+    });
+    let json: serde_json::Value = user
+        .put(
+            "/api/v1/admin/email_domain_rules/Spam.Example",
+            body.as_bytes(),
+        )
+        .good();
+    assert_eq!(json["email_domain_rule"]["domain"], "spam.example");
+    assert_eq!(json["email_domain_rule"]["updated_by"], "foo");
+    user.put::<()>(&format!("/api/v1/confirm/{}", token), &[])
+        .bad_with_status(StatusCode::BAD_REQUEST);
+
+    // Bundled domains can be allowed
+    let body = json!({ "blocked": false }).to_string();
+    let _: serde_json::Value = user
+        .put(
+            "/api/v1/admin/email_domain_rules/mailinator.com",
+            body.as_bytes(),
+        )
+        .good();
+    user.update_email("someone@mailinator.com");
+
+    let json: serde_json::Value = user.get("/api/v1/admin/email_domain_rules").good();
+    let rules = json["email_domain_rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0]["domain"], "mailinator.com");
+    assert_eq!(rules[0]["blocked"], false);
+
+    let _: OkBool = user
+        .delete("/api/v1/admin/email_domain_rules/mailinator.com")
+        .good();
+    user.update_email_more_control(model.id, Some("someone@mailinator.com"))
+        .bad_with_status(StatusCode::BAD_REQUEST);
+}
+
 /*  Check to make sure that neither other signed in users nor anonymous users can edit another
     user's email address.
 
@@ -748,3 +891,78 @@ fn test_update_email_notifications_not_owned() {
     // There should be no change to the `email_notifications` value for a crate not belonging to me
     assert!(email_notifications);
 }
+
+#[test]
+fn user_crates_are_listed_with_their_roles() {
+    use cargo_registry::schema::team_memberships;
+
+    let (app, anon, cookie) = TestApp::init().with_user();
+    let user = cookie.as_model();
+    let other = app.db_new_user("other");
+    let other = other.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_owned", user.id).expect_build(conn);
+        let team = new_team("github:crates-test-org:team_roles")
+            .create_or_update(conn)
+            .unwrap();
+        let team_owned = CrateBuilder::new("foo_team_owned", other.id).expect_build(conn);
+        add_team_to_crate(&team, &team_owned, other, conn).unwrap();
+        diesel::insert_into(team_memberships::table)
+            .values((
+                team_memberships::team_id.eq(team.id),
+                team_memberships::user_id.eq(user.id),
+                team_memberships::active.eq(true),
+            ))
+            .execute(conn)
+            .unwrap();
+        let published = CrateBuilder::new("foo_published", other.id).expect_build(conn);
+        VersionBuilder::new("2.0.0").expect_build(published.id, user.id, conn);
+        CrateBuilder::new("foo_unrelated", other.id).expect_build(conn);
+    });
+
+    let roles = |json: Value| {
+        json["crates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|krate| {
+                (
+                    krate["name"].as_str().unwrap().to_string(),
+                    krate["role"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let json: Value = cookie
+        .get_with_query("/api/v1/users/foo/crates", "include=role")
+        .good();
+    assert_eq!(
+        roles(json),
+        [
+            ("foo_owned".to_string(), "owner".to_string()),
+            ("foo_published".to_string(), "publisher".to_string()),
+            ("foo_team_owned".to_string(), "team_member".to_string()),
+        ]
+    );
+
+    // The teams of the user are only listed to the user themself
+    let json: Value = anon
+        .get_with_query("/api/v1/users/foo/crates", "include=role")
+        .good();
+    assert_eq!(
+        roles(json),
+        [
+            ("foo_owned".to_string(), "owner".to_string()),
+            ("foo_published".to_string(), "publisher".to_string()),
+        ]
+    );
+
+    let json: Value = anon
+        .get_with_query("/api/v1/users/foo/crates", "per_page=1")
+        .good();
+    assert_eq!(json["meta"]["total"], 2);
+    assert_eq!(json["crates"][0]["name"], "foo_owned");
+    assert_none!(json["crates"][0].get("role"));
+    assert_some!(json["meta"]["next_page"].as_str());
+}
@@ -20,15 +20,17 @@
 //! to the underlying database model value (`User` and `ApiToken` respectively).
 
 use crate::{
-    builders::PublishBuilder, record, CategoryListResponse, CategoryResponse, CrateList,
-    CrateResponse, GoodCrate, OkBool, OwnersResponse, VersionResponse,
+    record, CategoryListResponse, CategoryResponse, CrateList, CrateResponse, GoodCrate, OkBool,
+    OwnersResponse, VersionResponse,
 };
 use cargo_registry::{
     background_jobs::Environment,
     db::DieselPool,
     git::{Credentials, RepositoryConfig},
+    http_client::Replayer,
     middleware::current_user::TrustedUserId,
     models::{ApiToken, CreatedApiToken, User},
+    test_util::{PublishBuilder, UserBuilder},
     util::AppResponse,
     App, Config,
 };
@@ -104,6 +106,7 @@ impl TestApp {
             config: crate::simple_config(),
             proxy: None,
             bomb: None,
+            replay: None,
             index: None,
             build_job_runner: false,
         }
@@ -134,24 +137,10 @@ impl TestApp {
     ///
     /// This method updates the database directly
     pub fn db_new_user(&self, username: &str) -> MockCookieUser {
-        use cargo_registry::schema::emails;
-        use diesel::prelude::*;
-
         let user = self.db(|conn| {
-            let email = "something@example.com";
-
-            let user = crate::new_user(username)
-                .create_or_update(None, conn)
-                .unwrap();
-            diesel::insert_into(emails::table)
-                .values((
-                    emails::user_id.eq(user.id),
-                    emails::email.eq(email),
-                    emails::verified.eq(true),
-                ))
-                .execute(conn)
-                .unwrap();
-            user
+            UserBuilder::new(username)
+                .email("something@example.com")
+                .expect_build(conn)
         });
         MockCookieUser {
             app: TestApp(Rc::clone(&self.0)),
@@ -211,6 +200,7 @@ pub struct TestAppBuilder {
     config: Config,
     proxy: Option<String>,
     bomb: Option<record::Bomb>,
+    replay: Option<Replayer>,
     index: Option<UpstreamRepository>,
     build_job_runner: bool,
 }
@@ -220,7 +210,7 @@ impl TestAppBuilder {
     pub fn empty(self) -> (TestApp, MockAnonymousUser) {
         use crate::git;
 
-        let (app, middle) = crate::build_app(self.config, self.proxy);
+        let (app, middle) = crate::build_app(self.config, self.proxy, self.replay);
 
         let runner = if self.build_job_runner {
             let repository_config = RepositoryConfig {
@@ -232,7 +222,9 @@ impl TestAppBuilder {
                 index,
                 app.config.uploader.clone(),
                 app.http_client().clone(),
-            );
+            )
+            .with_cache(app.cache.clone())
+            .with_render_settings(app.config.render.clone());
 
             Some(
                 Runner::builder(environment)
@@ -269,6 +261,15 @@ impl TestAppBuilder {
         self
     }
 
+    /// Answer the requests to external APIs with the responses saved in a fixture, without
+    /// going through a proxy
+    pub fn with_replay(mut self, fixture: &str) -> Self {
+        let replay = Replayer::from_fixture(&record::cache_file(fixture))
+            .unwrap_or_else(|e| panic!("Unable to load the fixture {}: {}", fixture, e));
+        self.replay = Some(replay);
+        self
+    }
+
     // Create a `TestApp` with a database including a default user
     pub fn with_user(self) -> (TestApp, MockAnonymousUser, MockCookieUser) {
         let (app, anon) = self.empty();
@@ -360,6 +361,16 @@ pub trait RequestHelper {
         self.run(request)
     }
 
+    /// Issue a POST request
+    fn post<T>(&self, path: &str, body: &[u8]) -> Response<T>
+    where
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        let mut request = self.request_builder(Method::POST, path);
+        request.with_body(body);
+        self.run(request)
+    }
+
     /// Issue a DELETE request
     fn delete<T>(&self, path: &str) -> Response<T>
     where
@@ -637,6 +648,10 @@ where
         self
     }
 
+    pub fn headers(&self) -> &header::HeaderMap {
+        self.response.headers()
+    }
+
     #[track_caller]
     pub fn assert_redirect_ends_with(&self, target: &str) -> &Self {
         assert!(self
@@ -1,8 +1,10 @@
-use crate::{
-    builders::{CrateBuilder, PublishBuilder, VersionBuilder},
-    RequestHelper, TestApp, VersionResponse,
+use crate::{RequestHelper, TestApp, VersionResponse};
+use cargo_registry::{
+    models::Version,
+    schema::versions,
+    test_util::{CrateBuilder, PublishBuilder, VersionBuilder},
+    views::EncodableVersion,
 };
-use cargo_registry::{models::Version, schema::versions, views::EncodableVersion};
 
 use diesel::prelude::*;
 use serde_json::Value;
@@ -162,3 +164,302 @@ fn version_size() {
         .expect("Could not find v2.0.0");
     assert_eq!(version2.crate_size, Some(91));
 }
+
+#[test]
+fn files() {
+    use cargo_registry::schema::version_files;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c = CrateBuilder::new("foo_files", user.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(c.id, user.id, conn);
+
+        diesel::insert_into(version_files::table)
+            .values(&[
+                (
+                    version_files::version_id.eq(version.id),
+                    version_files::path.eq("src/lib.rs"),
+                    version_files::size.eq(12),
+                    version_files::sha256.eq(vec![0xab; 32]),
+                ),
+                (
+                    version_files::version_id.eq(version.id),
+                    version_files::path.eq("Cargo.toml"),
+                    version_files::size.eq(34),
+                    version_files::sha256.eq(vec![0x01; 32]),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json: Value = anon.get("/api/v1/crates/foo_files/1.0.0/files").good();
+    let files = json["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0]["path"], "Cargo.toml");
+    assert_eq!(files[0]["size"], 34);
+    assert_eq!(files[0]["sha256"], "01".repeat(32));
+    assert_eq!(files[1]["path"], "src/lib.rs");
+}
+
+#[test]
+fn files_are_recorded_at_publish() {
+    use sha2::{Digest, Sha256};
+
+    let (_, anon, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo_publish_files").files(&[
+        (
+            "foo_publish_files-1.0.0/src/lib.rs",
+            b"pub fn foo() {}" as &[_],
+        ),
+        ("foo_publish_files-1.0.0/README.md", b"# Foo" as &[_]),
+    ]);
+    user.enqueue_publish(crate_to_publish).good();
+
+    let json: Value = anon
+        .get("/api/v1/crates/foo_publish_files/1.0.0/files")
+        .good();
+    let files = json["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0]["path"], "README.md");
+    assert_eq!(files[0]["size"], 5);
+    assert_eq!(files[1]["path"], "src/lib.rs");
+    assert_eq!(files[1]["size"], 15);
+    let checksum = Sha256::digest(b"pub fn foo() {}");
+    assert_eq!(files[1]["sha256"], hex::encode(checksum));
+}
+
+#[test]
+fn sizes_are_recorded_at_publish() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo_sizes").files(&[
+        ("foo_sizes-1.0.0/src/lib.rs", b"pub fn foo() {}" as &[_]),
+        ("foo_sizes-1.0.0/README.md", b"# Foo" as &[_]),
+    ]);
+    user.enqueue_publish(crate_to_publish).good();
+
+    let json: VersionResponse = anon.show_version("foo_sizes", "1.0.0");
+    assert_eq!(json.version.uncompressed_size, Some(20));
+    let crate_size = json.version.crate_size.unwrap();
+    assert!(crate_size > 0);
+
+    let crates = app.crates_from_index_head("fo/o_/foo_sizes");
+    assert_eq!(crates[0].size, Some(crate_size as u64));
+    assert_eq!(crates[0].uncompressed_size, Some(20));
+}
+
+#[test]
+fn features_are_listed_with_their_documentation() {
+    let (_, anon, user) = TestApp::full().with_user();
+
+    let manifest = b"[package]\nname = \"foo_features\"\n\n\
+        [features]\n\
+        default = [\"std\"]\n\
+        ## Uses the standard library\n\
+        std = []\n";
+    let crate_to_publish = PublishBuilder::new("foo_features")
+        .feature("default", &["std"])
+        .feature("std", &[])
+        .feature("unstable", &[])
+        .files(&[("foo_features-1.0.0/Cargo.toml.orig", manifest as &[_])]);
+    user.enqueue_publish(crate_to_publish).good();
+
+    let json: Value = anon
+        .get("/api/v1/crates/foo_features/1.0.0/features")
+        .good();
+    assert_eq!(
+        json["features"],
+        json!([
+            {"name": "default", "enables": ["std"], "default": false, "description": null},
+            {"name": "std", "enables": [], "default": true, "description": "Uses the standard library"},
+            {"name": "unstable", "enables": [], "default": false, "description": null},
+        ])
+    );
+
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_features/1.0.0").good();
+    assert_eq!(
+        json.version.feature_docs,
+        json!({ "std": "Uses the standard library" })
+    );
+}
+
+#[test]
+fn changelog() {
+    use cargo_registry::models::NewVersionChangelog;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c = CrateBuilder::new("foo_changelog", user.id).expect_build(conn);
+        let v1 = VersionBuilder::new("1.0.0").expect_build(c.id, user.id, conn);
+        VersionBuilder::new("1.1.0").expect_build(c.id, user.id, conn);
+
+        NewVersionChangelog {
+            version_id: v1.id,
+            file_name: "CHANGELOG.md",
+            html: "<h1>Changelog</h1>\n",
+            section_html: Some("<h2>1.0.0</h2>\n"),
+        }
+        .save(conn)
+        .unwrap();
+    });
+
+    let json: Value = anon
+        .get("/api/v1/crates/foo_changelog/1.0.0/changelog")
+        .good();
+    assert_eq!(json["changelog"]["file_name"], "CHANGELOG.md");
+    assert_eq!(json["changelog"]["html"], "<h1>Changelog</h1>\n");
+    assert_eq!(json["changelog"]["section_html"], "<h2>1.0.0</h2>\n");
+
+    anon.get("/api/v1/crates/foo_changelog/1.1.0/changelog")
+        .assert_not_found();
+}
+
+#[test]
+fn show_with_vcs_info() {
+    use cargo_registry::uploaders::TarballVcsInfo;
+    use cargo_registry::views::EncodableVersionVcsInfo;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_vcs_info", user.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn);
+        VersionBuilder::new("2.0.0").expect_build(krate.id, user.id, conn);
+
+        let vcs_info = TarballVcsInfo {
+            sha1: "0123456789abcdef0123456789abcdef01234567".into(),
+            dirty: false,
+            path_in_vcs: None,
+        };
+        version.record_vcs_info(conn, &vcs_info).unwrap();
+    });
+
+    let json: VersionResponse = anon.show_version("foo_vcs_info", "1.0.0");
+    assert_eq!(
+        json.version.vcs_info,
+        Some(EncodableVersionVcsInfo {
+            sha1: "0123456789abcdef0123456789abcdef01234567".into(),
+            dirty: false,
+        })
+    );
+
+    let json: VersionResponse = anon.show_version("foo_vcs_info", "2.0.0");
+    assert_none!(json.version.vcs_info);
+}
+
+#[test]
+fn rebuilds() {
+    use crate::util::StatusCode;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user_model = user.as_model();
+    let checksum = "ab".repeat(32);
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_rebuilds", user_model.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(krate.id, user_model.id, conn);
+        version.record_checksum(conn, &checksum).unwrap();
+        VersionBuilder::new("2.0.0").expect_build(krate.id, user_model.id, conn);
+    });
+
+    let url = "/api/v1/crates/foo_rebuilds/1.0.0/rebuilds";
+    let body = json!({ "checksum": checksum.to_uppercase() }).to_string();
+    anon.put::<()>(url, body.as_bytes()).assert_forbidden();
+
+    let json: Value = user.put(url, body.as_bytes()).good();
+    assert_eq!(json["matches"], true);
+
+    let other = app.db_new_user("other");
+    let body = json!({ "checksum": "cd".repeat(32) }).to_string();
+    let json: Value = other.put(url, body.as_bytes()).good();
+    assert_eq!(json["matches"], false);
+
+    let json: Value = anon.get(url).good();
+    assert_eq!(json["meta"]["published_checksum"], checksum);
+    assert_eq!(json["meta"]["matching"], 1);
+    assert_eq!(json["meta"]["mismatching"], 1);
+    assert_eq!(json["rebuilds"].as_array().unwrap().len(), 2);
+
+    let body = json!({ "checksum": "not a checksum" }).to_string();
+    user.put::<()>(url, body.as_bytes())
+        .bad_with_status(StatusCode::BAD_REQUEST);
+
+    let body = json!({ "checksum": checksum }).to_string();
+    user.put::<()>(
+        "/api/v1/crates/foo_rebuilds/2.0.0/rebuilds",
+        body.as_bytes(),
+    )
+    .bad_with_status(StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn diffs_are_computed_in_the_background() {
+    use crate::util::StatusCode;
+    use cargo_registry::test_util::DependencyBuilder;
+
+    let (app, anon, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        let user_id = user.as_model().id;
+        CrateBuilder::new("old_diff_dep", user_id).expect_build(conn);
+        CrateBuilder::new("kept_diff_dep", user_id).expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_diff")
+        .dependency(DependencyBuilder::new("old_diff_dep"))
+        .dependency(DependencyBuilder::new("kept_diff_dep").version_req("^1.0"))
+        .feature("std", &[])
+        .files(&[
+            ("foo_diff-1.0.0/src/lib.rs", b"// old" as &[_]),
+            ("foo_diff-1.0.0/build.rs", b"fn main() {}" as &[_]),
+        ]);
+    user.enqueue_publish(crate_to_publish).good();
+    let crate_to_publish = PublishBuilder::new("foo_diff")
+        .version("1.1.0")
+        .dependency(DependencyBuilder::new("kept_diff_dep").version_req("^1.2"))
+        .feature("std", &["alloc"])
+        .feature("alloc", &[])
+        .files(&[
+            ("foo_diff-1.1.0/src/lib.rs", b"// new" as &[_]),
+            ("foo_diff-1.1.0/src/alloc.rs", b"// alloc" as &[_]),
+        ]);
+    user.enqueue_publish(crate_to_publish).good();
+
+    let url = "/api/v1/crates/foo_diff/diff";
+    anon.get_with_query::<()>(url, "from=1.0.0&to=1.1.0")
+        .assert_forbidden();
+    let response = user.get_with_query::<Value>(url, "from=1.0.0&to=1.1.0");
+    response.assert_status(StatusCode::ACCEPTED);
+    assert_eq!(response.good()["diff"], Value::Null);
+    let response = anon.get_with_query::<Value>(url, "from=1.0.0&to=1.1.0");
+    response.assert_status(StatusCode::ACCEPTED);
+
+    app.run_pending_background_jobs();
+
+    let json: Value = anon.get_with_query(url, "from=1.0.0&to=1.1.0").good();
+    let diff = &json["diff"];
+    assert_eq!(diff["from"], "1.0.0");
+    assert_eq!(diff["to"], "1.1.0");
+    assert_eq!(diff["dependencies"]["added"], json!([]));
+    assert_eq!(diff["dependencies"]["removed"][0]["name"], "old_diff_dep");
+    assert_eq!(diff["dependencies"]["changed"][0]["name"], "kept_diff_dep");
+    assert_eq!(diff["dependencies"]["changed"][0]["req"], "^1.2");
+    assert_eq!(diff["dependencies"]["changed"][0]["previous_req"], "^1.0");
+    assert_eq!(
+        diff["features"],
+        json!({ "added": ["alloc"], "removed": [], "changed": ["std"] })
+    );
+    assert_eq!(
+        diff["files"],
+        json!({ "added": ["src/alloc.rs"], "removed": ["build.rs"], "changed": ["src/lib.rs"] })
+    );
+
+    anon.get::<()>(url).assert_status(StatusCode::BAD_REQUEST);
+}
@@ -3,18 +3,26 @@ use conduit::RequestExt;
 use flate2::read::GzDecoder;
 use reqwest::{blocking::Client, header};
 use sha2::{Digest, Sha256};
+use toml_edit::{value, Document};
 
 use crate::util::errors::{cargo_err, internal, AppResult, ChainError};
+use crate::util::target::Target;
 use crate::util::{LimitErrorReader, Maximums};
 
+use std::collections::BTreeMap;
 use std::env;
 use std::fs::{self, File};
 use std::io::{Cursor, Read};
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::middleware::app::RequestApp;
 use crate::models::Crate;
 
+mod memory;
+
+pub use self::memory::{MemoryStorage, StoredFile};
+
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
 
@@ -30,6 +38,9 @@ pub enum Uploader {
     /// For development usage only: "uploads" crate files to `dist` and serves them
     /// from there as well to enable local publishing and download
     Local,
+
+    /// For test usage only: keeps the files in memory, see the `memory` module.
+    Memory(MemoryStorage),
 }
 
 impl Uploader {
@@ -50,7 +61,9 @@ impl Uploader {
                 let path = Uploader::crate_path(crate_name, version);
                 format!("https://{}/{}", host, path)
             }
-            Uploader::Local => format!("/{}", Uploader::crate_path(crate_name, version)),
+            Uploader::Local | Uploader::Memory(_) => {
+                format!("/{}", Uploader::crate_path(crate_name, version))
+            }
         }
     }
 
@@ -58,6 +71,32 @@ impl Uploader {
     ///
     /// The function doesn't check for the existence of the file.
     pub fn readme_location(&self, crate_name: &str, version: &str) -> String {
+        self.readme_url(&Uploader::readme_path(crate_name, version))
+    }
+
+    /// Returns the URL of an uploaded localized readme of a crate's version, see
+    /// `TarballReadme`.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn localized_readme_location(
+        &self,
+        crate_name: &str,
+        version: &str,
+        language: &str,
+    ) -> String {
+        self.readme_url(&Uploader::localized_readme_path(
+            crate_name, version, language,
+        ))
+    }
+
+    /// Returns the URL of the markdown source of an uploaded crate's version readme.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn raw_readme_location(&self, crate_name: &str, version: &str) -> String {
+        self.readme_url(&Uploader::raw_readme_path(crate_name, version))
+    }
+
+    fn readme_url(&self, path: &str) -> String {
         match *self {
             Uploader::S3 {
                 ref bucket,
@@ -68,10 +107,9 @@ impl Uploader {
                     Some(ref s) => s.clone(),
                     None => bucket.host(),
                 };
-                let path = Uploader::readme_path(crate_name, version);
                 format!("https://{}/{}", host, path)
             }
-            Uploader::Local => format!("/{}", Uploader::readme_path(crate_name, version)),
+            Uploader::Local | Uploader::Memory(_) => format!("/{}", path),
         }
     }
 
@@ -86,7 +124,17 @@ impl Uploader {
         format!("readmes/{}/{}-{}.html", name, name, version)
     }
 
-    /// Uploads a file using the configured uploader (either `S3`, `Local`).
+    /// Returns the internal path of the markdown source of an uploaded crate's version readme.
+    fn raw_readme_path(name: &str, version: &str) -> String {
+        format!("readmes/{}/{}-{}.md", name, name, version)
+    }
+
+    /// Returns the internal path of an uploaded localized readme of a crate's version.
+    fn localized_readme_path(name: &str, version: &str, language: &str) -> String {
+        format!("readmes/{}/{}-{}.{}.html", name, name, version, language)
+    }
+
+    /// Uploads a file using the configured uploader (either `S3`, `Local` or `Memory`).
     ///
     /// It returns the path of the uploaded file.
     ///
@@ -123,23 +171,70 @@ impl Uploader {
                 std::io::copy(&mut content, &mut file)?;
                 Ok(filename.to_str().map(String::from))
             }
+            Uploader::Memory(ref storage) => {
+                let mut body = Vec::new();
+                content.read_to_end(&mut body)?;
+                storage.put(path, body, content_type, extra_headers)?;
+                Ok(Some(String::from(path)))
+            }
         }
     }
 
-    /// Uploads a crate and returns the checksum of the uploaded crate file.
+    /// Deletes an uploaded file, which may not exist.
+    fn delete(&self, client: &Client, path: &str) -> Result<()> {
+        match *self {
+            Uploader::S3 { ref bucket, .. } => {
+                bucket.delete(client, path)?;
+                Ok(())
+            }
+            Uploader::Local => {
+                let filename = env::current_dir()?.join("local_uploads").join(path);
+                match fs::remove_file(filename) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                    _ => Ok(()),
+                }
+            }
+            Uploader::Memory(ref storage) => storage.delete(path),
+        }
+    }
+
+    /// Uploads a crate and returns the checksum of the uploaded crate file, along with the
+    /// information gathered while verifying its contents.
     pub fn upload_crate(
         &self,
         req: &mut dyn RequestExt,
         krate: &Crate,
         maximums: Maximums,
         vers: &semver::Version,
-    ) -> AppResult<[u8; 32]> {
+    ) -> AppResult<([u8; 32], TarballInfo)> {
         let app = Arc::clone(req.app());
-        let path = Uploader::crate_path(&krate.name, &vers.to_string());
         let mut body = Vec::new();
         LimitErrorReader::new(req.body(), maximums.max_upload_size).read_to_end(&mut body)?;
-        verify_tarball(krate, vers, &body, maximums.max_unpack_size)?;
-        let checksum = Sha256::digest(&body);
+
+        // Verifying and hashing the tarball is CPU-heavy, so it runs on the CPU pool
+        let (krate_to_verify, vers_to_verify) = (krate.clone(), vers.clone());
+        let max_unpack_size = maximums.max_unpack_size;
+        let (body, checksum, tarball_info) = app.cpu_pool.run(move || {
+            let tarball_info =
+                verify_tarball(&krate_to_verify, &vers_to_verify, &body, max_unpack_size)?;
+            let checksum = Sha256::digest(&body);
+            AppResult::Ok((body, checksum, tarball_info))
+        })??;
+
+        self.upload_crate_file(app.http_client(), &krate.name, &vers.to_string(), body)
+            .map_err(|e| internal(&format_args!("failed to upload crate: {}", e)))?;
+        Ok((checksum.into(), tarball_info))
+    }
+
+    /// Uploads the `.crate` file of a version, which must already have been verified.
+    pub(crate) fn upload_crate_file(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        body: Vec<u8>,
+    ) -> Result<()> {
+        let path = Uploader::crate_path(crate_name, vers);
         let content_length = body.len() as u64;
         let content = Cursor::new(body);
         let mut extra_headers = header::HeaderMap::new();
@@ -148,25 +243,59 @@ impl Uploader {
             CACHE_CONTROL_IMMUTABLE.parse().unwrap(),
         );
         self.upload(
-            app.http_client(),
+            http_client,
             &path,
             content,
             content_length,
             "application/x-tar",
             extra_headers,
-        )
-        .map_err(|e| internal(&format_args!("failed to upload crate: {}", e)))?;
-        Ok(checksum.into())
+        )?;
+        Ok(())
     }
 
+    /// Downloads the `.crate` file of a version, or returns `None` if it isn't stored.
+    pub(crate) fn download_crate_file(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let path = Uploader::crate_path(crate_name, vers);
+        match *self {
+            Uploader::S3 { .. } => {
+                let response = http_client
+                    .get(&self.crate_location(crate_name, vers))
+                    .send()?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                Ok(Some(response.error_for_status()?.bytes()?.to_vec()))
+            }
+            Uploader::Local => {
+                let filename = env::current_dir()?.join("local_uploads").join(path);
+                match fs::read(filename) {
+                    Ok(body) => Ok(Some(body)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            Uploader::Memory(ref storage) => Ok(storage.get(&path)?.map(|file| file.content)),
+        }
+    }
+
+    /// Uploads a rendered readme, in the given language if it is a localized one.
     pub(crate) fn upload_readme(
         &self,
         http_client: &Client,
         crate_name: &str,
         vers: &str,
+        language: Option<&str>,
         readme: String,
     ) -> Result<()> {
-        let path = Uploader::readme_path(crate_name, vers);
+        let path = match language {
+            Some(language) => Uploader::localized_readme_path(crate_name, vers, language),
+            None => Uploader::readme_path(crate_name, vers),
+        };
         let content_length = readme.len() as u64;
         let content = Cursor::new(readme);
         let mut extra_headers = header::HeaderMap::new();
@@ -181,6 +310,367 @@ impl Uploader {
         )?;
         Ok(())
     }
+
+    /// Uploads the markdown source of a readme, next to the rendered one.
+    pub(crate) fn upload_raw_readme(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        readme: String,
+    ) -> Result<()> {
+        let path = Uploader::raw_readme_path(crate_name, vers);
+        let content_length = readme.len() as u64;
+        let content = Cursor::new(readme);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(header::CACHE_CONTROL, CACHE_CONTROL_README.parse().unwrap());
+        self.upload(
+            http_client,
+            &path,
+            content,
+            content_length,
+            "text/markdown; charset=utf-8",
+            extra_headers,
+        )?;
+        Ok(())
+    }
+
+    /// Deletes the `.crate` file of a version and its READMEs, including the ones localized
+    /// in `languages`.
+    pub(crate) fn delete_version_files(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        languages: &[&str],
+    ) -> Result<()> {
+        self.delete(http_client, &Uploader::crate_path(crate_name, vers))?;
+        self.delete(http_client, &Uploader::readme_path(crate_name, vers))?;
+        self.delete(http_client, &Uploader::raw_readme_path(crate_name, vers))?;
+        for language in languages {
+            let path = Uploader::localized_readme_path(crate_name, vers, language);
+            self.delete(http_client, &path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A regular file contained in an uploaded `.crate` tarball.
+#[derive(Debug, Clone)]
+pub struct TarballFile {
+    /// The path of the file, relative to the `$name-$vers/` directory.
+    pub path: String,
+    /// The uncompressed size of the file in bytes.
+    pub size: u64,
+    /// The SHA-256 checksum of the file contents.
+    pub sha256: [u8; 32],
+}
+
+/// The changelog found at the root of an uploaded `.crate` tarball.
+#[derive(Debug, Clone)]
+pub struct TarballChangelog {
+    pub file_name: String,
+    pub text: String,
+}
+
+/// A README translated in another language, declared in the
+/// `package.metadata.localized-readmes` table of the manifest, e.g.
+///
+/// ```toml
+/// [package.metadata.localized-readmes]
+/// zh-CN = "README.zh-CN.md"
+/// ```
+///
+/// The files must be at the root of the package, and their name must start with `README`.
+#[derive(Debug, Clone)]
+pub struct TarballReadme {
+    /// The language tag, in lowercase
+    pub language: String,
+    pub file_name: String,
+    pub text: String,
+}
+
+/// The maximum number of localized READMEs stored for a version, the others are ignored
+const MAX_LOCALIZED_READMES: usize = 20;
+
+/// The repository commit a crate was packaged from, as recorded by cargo in the
+/// `.cargo_vcs_info.json` file of the tarball.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TarballVcsInfo {
+    pub sha1: String,
+    pub dirty: bool,
+    /// The directory of the package in the repository, such as `crates/foo`, which cargo
+    /// records since 1.59
+    pub path_in_vcs: Option<String>,
+}
+
+impl TarballVcsInfo {
+    /// Parses the contents of a `.cargo_vcs_info.json` file.
+    ///
+    /// Returns `None` if the file is malformed or doesn't reference a git commit, since
+    /// this information is only informational and shouldn't prevent a publish.
+    pub(crate) fn parse(contents: &[u8]) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct CargoVcsInfo {
+            git: Option<GitVcsInfo>,
+            path_in_vcs: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct GitVcsInfo {
+            sha1: String,
+            #[serde(default)]
+            dirty: bool,
+        }
+
+        let info = serde_json::from_slice::<CargoVcsInfo>(contents).ok()?;
+        let git = info.git?;
+        let is_valid_sha1 = !git.sha1.is_empty()
+            && git.sha1.len() <= 64
+            && git.sha1.chars().all(|c| c.is_ascii_hexdigit());
+        if !is_valid_sha1 {
+            return None;
+        }
+
+        Some(TarballVcsInfo {
+            sha1: git.sha1.to_lowercase(),
+            dirty: git.dirty,
+            path_in_vcs: info.path_in_vcs.filter(|path| !path.is_empty()),
+        })
+    }
+}
+
+/// Information collected while verifying an uploaded `.crate` tarball.
+#[derive(Debug, Default)]
+pub struct TarballInfo {
+    pub files: Vec<TarballFile>,
+    pub changelog: Option<TarballChangelog>,
+    pub vcs_info: Option<TarballVcsInfo>,
+    /// The minimum supported Rust version declared in the manifest
+    pub rust_version: Option<String>,
+    /// The targets listed as platform hints in `package.metadata.docs.rs`
+    pub metadata_targets: Vec<String>,
+    pub localized_readmes: Vec<TarballReadme>,
+    /// The documentation of the features, see `parse_feature_docs`
+    pub feature_docs: BTreeMap<String, String>,
+}
+
+impl TarballInfo {
+    /// Returns the total size of the files of the tarball, once unpacked.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// The files of a tarball whose contents are inspected while verifying it.
+#[derive(Debug, Clone, Copy)]
+enum InspectedFile {
+    /// The normalized `Cargo.toml` written by `cargo package`
+    Manifest,
+    /// The original `Cargo.toml` kept by `cargo package` as `Cargo.toml.orig`
+    OriginalManifest,
+    /// The `.cargo_vcs_info.json` file written by `cargo package`
+    VcsInfo,
+    /// A top-level changelog, such as `CHANGELOG.md`
+    Changelog,
+    /// A top-level README, which may be a localized one
+    Readme,
+}
+
+impl InspectedFile {
+    /// Returns how the file at `path` (relative to the `$name-$vers/` directory) is
+    /// inspected, if it is.
+    fn of(path: &Path) -> Option<Self> {
+        if path == Path::new("Cargo.toml") {
+            return Some(InspectedFile::Manifest);
+        }
+        if path == Path::new("Cargo.toml.orig") {
+            return Some(InspectedFile::OriginalManifest);
+        }
+        if path == Path::new(".cargo_vcs_info.json") {
+            return Some(InspectedFile::VcsInfo);
+        }
+
+        let is_changelog = path.parent() == Some(Path::new(""))
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map_or(false, |stem| stem.eq_ignore_ascii_case("changelog"));
+        if is_changelog {
+            return Some(InspectedFile::Changelog);
+        }
+
+        let is_readme = path.parent() == Some(Path::new(""))
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.to_lowercase().starts_with("readme."));
+        if is_readme {
+            return Some(InspectedFile::Readme);
+        }
+
+        None
+    }
+}
+
+/// Returns the minimum supported Rust version declared by the `rust-version` field of a
+/// manifest, if there is a valid one.
+fn parse_rust_version(manifest: &[u8]) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        package: Option<Package>,
+    }
+
+    #[derive(Deserialize)]
+    struct Package {
+        #[serde(rename = "rust-version")]
+        rust_version: Option<String>,
+    }
+
+    let rust_version = toml::from_slice::<Manifest>(manifest)
+        .ok()?
+        .package?
+        .rust_version?;
+    let parts = rust_version.split('.').collect::<Vec<_>>();
+    let is_valid = (1..=3).contains(&parts.len())
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if !is_valid {
+        return None;
+    }
+
+    Some(rust_version)
+}
+
+/// Returns the targets listed as platform hints in the `package.metadata.docs.rs`
+/// table of a manifest, starting with its `default-target`.
+fn parse_metadata_targets(manifest: &[u8]) -> Vec<String> {
+    let manifest = match toml::from_slice::<toml::Value>(manifest) {
+        Ok(manifest) => manifest,
+        Err(_) => return Vec::new(),
+    };
+    let docs_rs = match ["package", "metadata", "docs", "rs"]
+        .iter()
+        .try_fold(&manifest, |value, key| value.get(key))
+    {
+        Some(docs_rs) => docs_rs,
+        None => return Vec::new(),
+    };
+
+    let default_target = docs_rs.get("default-target").and_then(|t| t.as_str());
+    let targets = docs_rs
+        .get("targets")
+        .and_then(|targets| targets.as_array())
+        .map(|targets| targets.iter().filter_map(|t| t.as_str()).collect())
+        .unwrap_or_else(Vec::new);
+
+    let mut metadata_targets: Vec<String> = Vec::new();
+    for target in default_target.into_iter().chain(targets) {
+        let is_new = !metadata_targets.iter().any(|t| t == target);
+        if is_new && Target::from_triple(target).is_some() {
+            metadata_targets.push(target.to_string());
+        }
+    }
+    metadata_targets
+}
+
+/// Returns the documentation of the features of a manifest, written as `##` comments above
+/// them in its `[features]` table, as with the `document-features` crate:
+///
+/// ```toml
+/// [features]
+/// ## Supports `no_std` environments when disabled
+/// std = []
+/// ```
+///
+/// The normalized manifest written by `cargo package` has no comments, so this is the
+/// original one. `toml_edit` keeps the comments above each key, so the values of the features
+/// are replaced by their index before the table is written back, which leaves one line per
+/// feature whatever its key or value looks like.
+fn parse_feature_docs(manifest: &[u8]) -> BTreeMap<String, String> {
+    let mut feature_docs = BTreeMap::new();
+    let mut document = match std::str::from_utf8(manifest).map(str::parse::<Document>) {
+        Ok(Ok(document)) => document,
+        _ => return feature_docs,
+    };
+    let names = match document["features"].as_table() {
+        Some(features) => features
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<_>>(),
+        None => return feature_docs,
+    };
+    for (index, name) in names.iter().enumerate() {
+        document["features"][name.as_str()] = value(index as i64);
+    }
+    let mut features = Document::new();
+    features["features"] = document["features"].clone();
+
+    let mut doc_lines = Vec::new();
+    for line in features.to_string().lines().map(str::trim) {
+        if let Some(doc) = line.strip_prefix("##") {
+            doc_lines.push(doc.strip_prefix(' ').unwrap_or(doc).to_string());
+        } else if line.starts_with('[') {
+            doc_lines.clear();
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            let name = feature_index(line).and_then(|index| names.get(index));
+            if let Some(name) = name.filter(|_| !doc_lines.is_empty()) {
+                feature_docs.insert(name.clone(), doc_lines.join("\n"));
+            }
+            doc_lines.clear();
+        }
+    }
+    feature_docs
+}
+
+/// Returns the index written as the value of a `key = index` line by `parse_feature_docs`.
+/// Quoted keys can contain `=` and `#`, so the value is after the last `=` followed by a number.
+fn feature_index(line: &str) -> Option<usize> {
+    line.rmatch_indices('=').find_map(|(position, _)| {
+        let value = line[position + 1..].split('#').next().unwrap_or_default();
+        value.trim().parse().ok()
+    })
+}
+
+/// Returns the languages and the file names of the localized READMEs declared in the
+/// `package.metadata.localized-readmes` table of a manifest, see `TarballReadme`.
+fn parse_localized_readmes(manifest: &[u8]) -> Vec<(String, String)> {
+    let manifest = match toml::from_slice::<toml::Value>(manifest) {
+        Ok(manifest) => manifest,
+        Err(_) => return Vec::new(),
+    };
+    let readmes = match ["package", "metadata", "localized-readmes"]
+        .iter()
+        .try_fold(&manifest, |value, key| value.get(key))
+        .and_then(|readmes| readmes.as_table())
+    {
+        Some(readmes) => readmes,
+        None => return Vec::new(),
+    };
+
+    readmes
+        .iter()
+        .filter(|(language, _)| is_valid_language_tag(language))
+        .filter_map(|(language, file_name)| {
+            let file_name = file_name.as_str()?.trim_start_matches("./");
+            Some((language.to_lowercase(), file_name.to_string()))
+        })
+        .take(MAX_LOCALIZED_READMES)
+        .collect()
+}
+
+/// Checks that a language tag looks like a BCP 47 one, such as `zh-CN` or `pt-BR`.
+fn is_valid_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (2..=8).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
 }
 
 fn verify_tarball(
@@ -188,7 +678,7 @@ fn verify_tarball(
     vers: &semver::Version,
     tarball: &[u8],
     max_unpack: u64,
-) -> AppResult<()> {
+) -> AppResult<TarballInfo> {
     // All our data is currently encoded with gzip
     let decoder = GzDecoder::new(tarball);
 
@@ -199,8 +689,12 @@ fn verify_tarball(
     // Use this I/O object now to take a peek inside
     let mut archive = tar::Archive::new(decoder);
     let prefix = format!("{}-{}", krate.name, vers);
+    let mut info = TarballInfo::default();
+    // The manifest may come after the READMEs it declares
+    let mut declared_readmes = Vec::new();
+    let mut readmes = Vec::new();
     for entry in archive.entries()? {
-        let entry = entry.chain_error(|| {
+        let mut entry = entry.chain_error(|| {
             cargo_err("uploaded tarball is malformed or too large when decompressed")
         })?;
 
@@ -209,9 +703,11 @@ fn verify_tarball(
         // upload a tarball that contains both `foo-0.1.0/` source code as well
         // as `bar-0.1.0/` source code, and this could overwrite other crates in
         // the registry!
-        if !entry.path()?.starts_with(&prefix) {
-            return Err(cargo_err("invalid tarball uploaded"));
-        }
+        let path = entry.path()?.into_owned();
+        let relative_path = match path.strip_prefix(&prefix) {
+            Ok(relative_path) => relative_path.to_path_buf(),
+            Err(_) => return Err(cargo_err("invalid tarball uploaded")),
+        };
 
         // Historical versions of the `tar` crate which Cargo uses internally
         // don't properly prevent hard links and symlinks from overwriting
@@ -222,6 +718,309 @@ fn verify_tarball(
         if entry_type.is_hard_link() || entry_type.is_symlink() {
             return Err(cargo_err("invalid tarball uploaded"));
         }
+
+        if entry_type.is_file() && !relative_path.as_os_str().is_empty() {
+            let size = entry.header().size()?;
+            let sha256 = if let Some(kind) = InspectedFile::of(&relative_path) {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).chain_error(|| {
+                    cargo_err("uploaded tarball is malformed or too large when decompressed")
+                })?;
+                let sha256: [u8; 32] = Sha256::digest(&contents).into();
+                match kind {
+                    InspectedFile::Manifest => {
+                        info.rust_version = parse_rust_version(&contents);
+                        info.metadata_targets = parse_metadata_targets(&contents);
+                        declared_readmes = parse_localized_readmes(&contents);
+                    }
+                    InspectedFile::OriginalManifest => {
+                        info.feature_docs = parse_feature_docs(&contents);
+                    }
+                    InspectedFile::VcsInfo => {
+                        info.vcs_info = TarballVcsInfo::parse(&contents);
+                    }
+                    InspectedFile::Changelog => {
+                        if let Ok(text) = String::from_utf8(contents) {
+                            info.changelog = Some(TarballChangelog {
+                                file_name: relative_path.to_string_lossy().into_owned(),
+                                text,
+                            });
+                        }
+                    }
+                    InspectedFile::Readme => {
+                        if let Ok(text) = String::from_utf8(contents) {
+                            readmes.push((relative_path.to_string_lossy().into_owned(), text));
+                        }
+                    }
+                }
+                sha256
+            } else {
+                sha256_of(&mut entry).chain_error(|| {
+                    cargo_err("uploaded tarball is malformed or too large when decompressed")
+                })?
+            };
+            info.files.push(TarballFile {
+                path: relative_path.to_string_lossy().into_owned(),
+                size,
+                sha256,
+            });
+        }
+    }
+
+    for (language, file_name) in declared_readmes {
+        if let Some((_, text)) = readmes.iter().find(|(path, _)| *path == file_name) {
+            info.localized_readmes.push(TarballReadme {
+                language,
+                file_name,
+                text: text.clone(),
+            });
+        }
+    }
+    Ok(info)
+}
+
+/// Computes the SHA-256 checksum of everything that can be read from `reader`.
+fn sha256_of<R: Read>(reader: &mut R) -> std::io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 8 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use flate2::{write::GzEncoder, Compression};
+
+    fn krate(name: &str) -> Crate {
+        Crate {
+            id: 1,
+            name: name.into(),
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            downloads: 0,
+            description: None,
+            homepage: None,
+            documentation: None,
+            repository: None,
+            max_upload_size: None,
+            deprecated: false,
+            superseded_by: None,
+        }
+    }
+
+    fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tarball = Vec::new();
+        {
+            let mut ar = tar::Builder::new(GzEncoder::new(&mut tarball, Compression::default()));
+            for &(name, data) in files {
+                let mut header = tar::Header::new_gnu();
+                assert_ok!(header.set_path(name));
+                header.set_size(data.len() as u64);
+                header.set_cksum();
+                assert_ok!(ar.append(&header, data));
+            }
+            assert_ok!(ar.finish());
+        }
+        tarball
+    }
+
+    #[test]
+    fn verify_tarball_lists_files() {
+        let krate = krate("foo");
+        let vers = semver::Version::parse("1.0.0").unwrap();
+        let tarball = tarball(&[
+            ("foo-1.0.0/Cargo.toml", b"[package]"),
+            ("foo-1.0.0/src/lib.rs", b""),
+        ]);
+
+        let info = assert_ok!(verify_tarball(&krate, &vers, &tarball, 512 * 1024));
+        assert_eq!(info.files.len(), 2);
+        assert_eq!(info.files[0].path, "Cargo.toml");
+        assert_eq!(info.files[0].size, 9);
+        assert_eq!(
+            info.files[0].sha256,
+            <[u8; 32]>::from(Sha256::digest(b"[package]"))
+        );
+        assert_eq!(info.files[1].path, "src/lib.rs");
+        assert_eq!(info.files[1].size, 0);
+    }
+
+    #[test]
+    fn verify_tarball_finds_changelog() {
+        let krate = krate("foo");
+        let vers = semver::Version::parse("1.0.0").unwrap();
+        let tarball = tarball(&[
+            ("foo-1.0.0/Cargo.toml", b""),
+            ("foo-1.0.0/docs/CHANGELOG.md", b"# Nested"),
+            ("foo-1.0.0/CHANGELOG.md", b"# Changelog"),
+        ]);
+
+        let info = assert_ok!(verify_tarball(&krate, &vers, &tarball, 512 * 1024));
+        let changelog = assert_some!(info.changelog);
+        assert_eq!(changelog.file_name, "CHANGELOG.md");
+        assert_eq!(changelog.text, "# Changelog");
+        assert_eq!(info.files.len(), 3);
+    }
+
+    #[test]
+    fn verify_tarball_finds_localized_readmes() {
+        let krate = krate("foo");
+        let vers = semver::Version::parse("1.0.0").unwrap();
+        let tarball = tarball(&[
+            ("foo-1.0.0/README.md", b"# Foo"),
+            ("foo-1.0.0/README.zh-CN.md", b"# Foo zh"),
+            ("foo-1.0.0/README.fr.md", b"# Foo fr"),
+            (
+                "foo-1.0.0/Cargo.toml",
+                b"[package.metadata.localized-readmes]\n\
+                zh-CN = \"README.zh-CN.md\"\n\
+                de = \"README.de.md\"\n\
+                \"not a tag!\" = \"README.fr.md\"\n",
+            ),
+        ]);
+
+        let info = assert_ok!(verify_tarball(&krate, &vers, &tarball, 512 * 1024));
+        assert_eq!(info.localized_readmes.len(), 1);
+        assert_eq!(info.localized_readmes[0].language, "zh-cn");
+        assert_eq!(info.localized_readmes[0].file_name, "README.zh-CN.md");
+        assert_eq!(info.localized_readmes[0].text, "# Foo zh");
+    }
+
+    #[test]
+    fn language_tags_must_be_valid() {
+        assert!(is_valid_language_tag("en"));
+        assert!(is_valid_language_tag("zh-Hant-TW"));
+        assert!(!is_valid_language_tag("e"));
+        assert!(!is_valid_language_tag("en-"));
+        assert!(!is_valid_language_tag("en_US"));
+        assert!(!is_valid_language_tag("../../etc"));
+    }
+
+    #[test]
+    fn verify_tarball_reads_vcs_info() {
+        let krate = krate("foo");
+        let vers = semver::Version::parse("1.0.0").unwrap();
+        let vcs_info =
+            br#"{"git":{"sha1":"0123456789ABCDEF0123456789abcdef01234567","dirty":true}}"#;
+        let tarball = tarball(&[("foo-1.0.0/.cargo_vcs_info.json", vcs_info)]);
+
+        let info = assert_ok!(verify_tarball(&krate, &vers, &tarball, 512 * 1024));
+        assert_eq!(
+            info.vcs_info,
+            Some(TarballVcsInfo {
+                sha1: "0123456789abcdef0123456789abcdef01234567".into(),
+                dirty: true,
+                path_in_vcs: None,
+            })
+        );
+    }
+
+    #[test]
+    fn vcs_info_is_optional() {
+        assert_eq!(
+            TarballVcsInfo::parse(br#"{"git":{"sha1":"abc123"}}"#),
+            Some(TarballVcsInfo {
+                sha1: "abc123".into(),
+                dirty: false,
+                path_in_vcs: None,
+            })
+        );
+        assert_eq!(
+            TarballVcsInfo::parse(br#"{"git":{"sha1":"abc123"},"path_in_vcs":"crates/foo"}"#)
+                .and_then(|info| info.path_in_vcs),
+            Some("crates/foo".into())
+        );
+        assert_none!(TarballVcsInfo::parse(b"{}"));
+        assert_none!(TarballVcsInfo::parse(b"not json"));
+        assert_none!(TarballVcsInfo::parse(br#"{"git":{"sha1":"not a sha"}}"#));
+    }
+
+    #[test]
+    fn verify_tarball_reads_rust_version() {
+        let krate = krate("foo");
+        let vers = semver::Version::parse("1.0.0").unwrap();
+        let manifest = b"[package]\nname = \"foo\"\nrust-version = \"1.56\"\n";
+        let tarball = tarball(&[("foo-1.0.0/Cargo.toml", manifest)]);
+
+        let info = assert_ok!(verify_tarball(&krate, &vers, &tarball, 512 * 1024));
+        assert_eq!(info.rust_version.as_deref(), Some("1.56"));
+    }
+
+    #[test]
+    fn reads_metadata_targets() {
+        let manifest = b"[package]\nname = \"foo\"\n\n\
+            [package.metadata.docs.rs]\n\
+            default-target = \"x86_64-unknown-linux-gnu\"\n\
+            targets = [\"x86_64-pc-windows-msvc\", \"x86_64-unknown-linux-gnu\", \"not a target\"]\n";
+        assert_eq!(
+            parse_metadata_targets(manifest),
+            vec!["x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"]
+        );
+        assert!(parse_metadata_targets(b"[package]\nname = \"foo\"\n").is_empty());
+    }
+
+    #[test]
+    fn reads_feature_docs() {
+        let manifest = b"[package]\nname = \"foo\"\n\n\
+            [features]\n\
+            ## The default features\n\
+            default = [\"std\"]\n\n\
+            ## Uses the standard library,\n\
+            ## disable it for `no_std`\n\
+            std = []\n\
+            # alloc = []\n\
+            serde = [\n\
+            ## Not the documentation of `\"serde_derive\"`\n\
+            \"serde_derive\",\n\
+            ]\n\
+            ## Enables `\"a = b\"`\n\
+            \"a = b\" = [] # quoted\n\n\
+            [dependencies]\n\
+            ## Not a feature\n\
+            log = \"0.4\"\n";
+        let feature_docs = parse_feature_docs(manifest);
+        assert_eq!(
+            feature_docs.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("a = b".to_string(), "Enables `\"a = b\"`".to_string()),
+                ("default".to_string(), "The default features".to_string()),
+                (
+                    "std".to_string(),
+                    "Uses the standard library,\ndisable it for `no_std`".to_string()
+                ),
+            ]
+        );
+        assert!(parse_feature_docs(b"[package]\nname = \"foo\"\n").is_empty());
+    }
+
+    #[test]
+    fn rust_version_must_be_valid() {
+        assert_none!(parse_rust_version(b"[package]\nname = \"foo\"\n"));
+        assert_none!(parse_rust_version(b"[package]\nrust-version = \"1.x\"\n"));
+        assert_none!(parse_rust_version(
+            b"[package]\nrust-version = \"1.2.3.4\"\n"
+        ));
+        assert_none!(parse_rust_version(b"not toml ["));
+        assert_eq!(
+            parse_rust_version(b"[package]\nrust-version = \"1.40.0\"\n").as_deref(),
+            Some("1.40.0")
+        );
+    }
+
+    #[test]
+    fn verify_tarball_rejects_foreign_prefix() {
+        let krate = krate("foo");
+        let vers = semver::Version::parse("1.0.0").unwrap();
+        let tarball = tarball(&[("bar-1.0.0/Cargo.toml", b"")]);
+
+        assert_err!(verify_tarball(&krate, &vers, &tarball, 512 * 1024));
     }
-    Ok(())
 }
@@ -0,0 +1,178 @@
+//! An in-memory storage for tests
+//!
+//! `Uploader::Memory` keeps the uploaded files in memory instead of uploading them to S3 or to
+//! the `local_uploads` directory. Clones of a `MemoryStorage` share the same files, so tests can
+//! keep a clone to inspect what was uploaded.
+//!
+//! Failures can be injected to test the error handling of the uploads: every operation first
+//! waits for the configured latency, then fails if one of the next failures was requested with
+//! `fail_next`, or randomly according to the error rate.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use reqwest::header::HeaderMap;
+
+/// A file stored by a `MemoryStorage`.
+#[derive(Clone, Debug)]
+pub struct StoredFile {
+    pub content: Vec<u8>,
+    pub content_type: String,
+    pub headers: HeaderMap,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    files: HashMap<String, StoredFile>,
+    error_rate: f64,
+    latency: Duration,
+    failures: usize,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes operations fail randomly, with a probability between `0.0` and `1.0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate isn't between `0.0` and `1.0`.
+    pub fn set_error_rate(&self, error_rate: f64) {
+        assert!(
+            (0.0..=1.0).contains(&error_rate),
+            "The error rate must be between 0 and 1"
+        );
+        self.inner.lock().unwrap().error_rate = error_rate;
+    }
+
+    /// Delays every operation by the given duration.
+    pub fn set_latency(&self, latency: Duration) {
+        self.inner.lock().unwrap().latency = latency;
+    }
+
+    /// Makes the next `count` operations fail.
+    pub fn fail_next(&self, count: usize) {
+        self.inner.lock().unwrap().failures = count;
+    }
+
+    /// Stores a file, replacing any previous file with the same path.
+    pub fn put(
+        &self,
+        path: &str,
+        content: Vec<u8>,
+        content_type: &str,
+        headers: HeaderMap,
+    ) -> Result<()> {
+        self.inject_failure("upload", path)?;
+        let file = StoredFile {
+            content,
+            content_type: content_type.to_string(),
+            headers,
+        };
+        self.inner
+            .lock()
+            .unwrap()
+            .files
+            .insert(path.to_string(), file);
+        Ok(())
+    }
+
+    /// Returns the file stored with the given path, if there is one.
+    pub fn get(&self, path: &str) -> Result<Option<StoredFile>> {
+        self.inject_failure("download", path)?;
+        Ok(self.inner.lock().unwrap().files.get(path).cloned())
+    }
+
+    /// Removes the file stored with the given path, if there is one.
+    pub fn delete(&self, path: &str) -> Result<()> {
+        self.inject_failure("deletion", path)?;
+        self.inner.lock().unwrap().files.remove(path);
+        Ok(())
+    }
+
+    /// Returns the paths of all the stored files, in alphabetical order.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = self
+            .inner
+            .lock()
+            .unwrap()
+            .files
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    fn inject_failure(&self, operation: &str, path: &str) -> Result<()> {
+        // Sleep without holding the lock, so that concurrent operations are delayed together
+        let latency = self.inner.lock().unwrap().latency;
+        if latency > Duration::from_secs(0) {
+            thread::sleep(latency);
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let fails = if inner.failures > 0 {
+            inner.failures -= 1;
+            true
+        } else {
+            inner.error_rate > 0.0 && rand::thread_rng().gen_bool(inner.error_rate)
+        };
+        if fails {
+            return Err(anyhow!("injected failure of the {} of {}", operation, path));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_files() {
+        let storage = MemoryStorage::new();
+        storage
+            .clone()
+            .put(
+                "crates/foo",
+                b"foo".to_vec(),
+                "text/plain",
+                HeaderMap::new(),
+            )
+            .unwrap();
+
+        let file = storage.get("crates/foo").unwrap().unwrap();
+        assert_eq!(file.content, b"foo");
+        assert_eq!(file.content_type, "text/plain");
+        assert!(storage.get("crates/bar").unwrap().is_none());
+        assert_eq!(storage.paths(), vec!["crates/foo"]);
+
+        storage.delete("crates/foo").unwrap();
+        assert!(storage.paths().is_empty());
+    }
+
+    #[test]
+    fn injected_failures() {
+        let storage = MemoryStorage::new();
+        storage.fail_next(1);
+        assert!(storage.put("a", Vec::new(), "", HeaderMap::new()).is_err());
+        assert!(storage.put("a", Vec::new(), "", HeaderMap::new()).is_ok());
+
+        storage.set_error_rate(1.0);
+        assert!(storage.get("a").is_err());
+        storage.set_error_rate(0.0);
+        assert!(storage.get("a").unwrap().is_some());
+    }
+}
@@ -0,0 +1,721 @@
+//! Functionality for mirroring crates from an upstream registry
+//!
+//! When an upstream registry is configured, crates which are unknown to this
+//! instance are looked up in the index of the upstream registry the first time
+//! they are requested. Their versions are recorded in the database and in the
+//! local index, and the `.crate` file of a version is copied to the local
+//! storage the first time it is downloaded. This turns the instance into a
+//! caching registry which keeps serving the crates it has seen without access to
+//! the upstream registry.
+//!
+//! Crates published to this instance always take precedence, a crate is never
+//! mirrored if a crate with the same name was published locally.
+//!
+//! An instance can also follow the upstream registry, if it is another instance
+//! of crates.io, by replaying the events it exposes at `/api/v1/events`. New
+//! versions, yanks and changes of ownership are then copied within the interval
+//! at which the `follow_upstream_events` job is enqueued.
+//!
+//! The lookups made while serving requests are limited by `UpstreamLookups`, so that requests
+//! for crates unknown to both registries can't make this instance flood the upstream one.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use reqwest::{blocking::Client, header, StatusCode};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use swirl::Job;
+
+use crate::git;
+use crate::models::{Crate, NewCrate, NewUser, OwnerKind, RegistryEventKind, Version};
+use crate::schema::{
+    crate_owners, crates, replayed_registry_events, replication_cursors, teams, upstream_versions,
+    users, versions,
+};
+use crate::uploaders::Uploader;
+use crate::views::EncodableRegistryEvent;
+
+const MIRROR_USER_AGENT: &str = "crates.io mirror (https://github.com/rust-lang/crates.io)";
+
+/// The number of events requested at once when following the upstream registry.
+const EVENTS_PER_PAGE: i64 = 100;
+
+/// How many ids before the last replayed event the events of the upstream registry are read
+/// again. The id of an event is allocated before its transaction commits, so an event can
+/// appear after events with higher ids, as long as its transaction doesn't last longer than
+/// the recording of this many other events.
+const EVENT_ID_LAG: i64 = 500;
+
+/// How long a crate missing upstream isn't looked up again while serving requests
+const MISSING_CRATE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The maximum number of crates remembered as missing upstream
+const MAX_MISSING_CRATES: usize = 10_000;
+
+/// The maximum number of lookups made while serving requests, per minute
+const MAX_LOOKUPS_PER_MINUTE: u32 = 60;
+
+/// The lookups of crates in the upstream registry made by this process while serving requests
+///
+/// The crates missing upstream are remembered for `MISSING_CRATE_TTL`, and at most
+/// `MAX_LOOKUPS_PER_MINUTE` lookups are made per minute, beyond which crates unknown to this
+/// instance are reported as missing. The background jobs aren't limited.
+#[derive(Debug, Default)]
+pub struct UpstreamLookups {
+    state: Mutex<LookupState>,
+}
+
+#[derive(Debug, Default)]
+struct LookupState {
+    /// When the crates, by canonical name, were found to be missing upstream
+    missing: HashMap<String, Instant>,
+    /// The start of the current minute of the rate limit, and its number of lookups
+    window: Option<(Instant, u32)>,
+}
+
+impl UpstreamLookups {
+    /// Returns whether a crate may be looked up, counting the lookup if it may.
+    fn start(&self, name: &str) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let key = canonical_name(name);
+        if let Some(since) = state.missing.get(&key) {
+            if now.duration_since(*since) < MISSING_CRATE_TTL {
+                return false;
+            }
+            state.missing.remove(&key);
+        }
+
+        let (start, lookups) = state.window.get_or_insert((now, 0));
+        if now.duration_since(*start) >= Duration::from_secs(60) {
+            *start = now;
+            *lookups = 0;
+        }
+        if *lookups >= MAX_LOOKUPS_PER_MINUTE {
+            return false;
+        }
+        *lookups += 1;
+        true
+    }
+
+    /// Remembers that a crate is missing upstream.
+    fn record_missing(&self, name: &str) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if state.missing.len() >= MAX_MISSING_CRATES {
+            state
+                .missing
+                .retain(|_, since| now.duration_since(*since) < MISSING_CRATE_TTL);
+        }
+        if state.missing.len() < MAX_MISSING_CRATES {
+            state.missing.insert(canonical_name(name), now);
+        }
+    }
+}
+
+/// Returns the name of a crate as it is compared to the other names, see `canon_crate_name`.
+fn canonical_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpstreamRegistry {
+    /// The base URL of the web API of the upstream registry, such as `https://crates.io`.
+    pub api_url: String,
+    /// The base URL of the sparse index of the upstream registry, such as
+    /// `https://index.crates.io`.
+    pub index_url: String,
+}
+
+impl UpstreamRegistry {
+    /// Reads the upstream registry from the `UPSTREAM_REGISTRY_URL` and `UPSTREAM_INDEX_URL`
+    /// environment variables.
+    ///
+    /// Returns `None` if `UPSTREAM_REGISTRY_URL` isn't set, in which case crates are never
+    /// mirrored.
+    pub fn from_environment() -> Option<Self> {
+        let api_url = crate::config::var("UPSTREAM_REGISTRY_URL").ok()?;
+        let index_url = crate::config::var("UPSTREAM_INDEX_URL")
+            .unwrap_or_else(|_| String::from("https://index.crates.io"));
+        Some(Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            index_url: index_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Mirrors a crate if it is unknown to this instance.
+    ///
+    /// Returns `false` if the crate is known neither locally nor upstream, or if the `lookups`
+    /// of the requests being served are limited, see `UpstreamLookups`.
+    pub fn ensure_crate(
+        &self,
+        conn: &PgConnection,
+        client: &Client,
+        lookups: Option<&UpstreamLookups>,
+        name: &str,
+    ) -> Result<bool> {
+        let exists = diesel::select(diesel::dsl::exists(Crate::by_name(name))).get_result(conn)?;
+        if exists {
+            return Ok(true);
+        }
+        self.sync_unknown_crate(conn, client, lookups, name)
+    }
+
+    /// Looks up a crate unknown to this instance, unless the `lookups` are limited, and
+    /// returns whether it was found.
+    fn sync_unknown_crate(
+        &self,
+        conn: &PgConnection,
+        client: &Client,
+        lookups: Option<&UpstreamLookups>,
+        name: &str,
+    ) -> Result<bool> {
+        if let Some(lookups) = lookups {
+            if !lookups.start(name) {
+                return Ok(false);
+            }
+        }
+        let found = self.sync_crate(conn, client, name)?.is_some();
+        if let (false, Some(lookups)) = (found, lookups) {
+            lookups.record_missing(name);
+        }
+        Ok(found)
+    }
+
+    /// Mirrors a version if it is unknown to this instance, and copies its `.crate` file
+    /// to the local storage unless this was already done.
+    ///
+    /// Versions published to this instance are left untouched, and the crate is only looked up
+    /// upstream if the `lookups` allow it, like `ensure_crate`.
+    pub fn ensure_crate_file(
+        &self,
+        conn: &PgConnection,
+        client: &Client,
+        uploader: &Uploader,
+        lookups: Option<&UpstreamLookups>,
+        name: &str,
+        num: &str,
+    ) -> Result<()> {
+        let version = versions::table
+            .inner_join(crates::table)
+            .filter(Crate::with_name(name))
+            .filter(versions::num.eq(num));
+        let exists = diesel::select(diesel::dsl::exists(version)).get_result(conn)?;
+        if !exists {
+            // The crate might have been mirrored before this version was published upstream
+            self.sync_unknown_crate(conn, client, lookups, name)?;
+        }
+
+        let mirrored: Option<(i32, String, Option<String>, Option<NaiveDateTime>)> =
+            upstream_versions::table
+                .inner_join(versions::table.inner_join(crates::table))
+                .filter(Crate::with_name(name))
+                .filter(versions::num.eq(num))
+                .select((
+                    versions::id,
+                    crates::name,
+                    versions::checksum,
+                    upstream_versions::crate_file_cached_at,
+                ))
+                .first(conn)
+                .optional()?;
+        let (version_id, crate_name, checksum) = match mirrored {
+            Some((version_id, crate_name, checksum, None)) => (version_id, crate_name, checksum),
+            _ => return Ok(()),
+        };
+
+        let url = format!(
+            "{}/api/v1/crates/{}/{}/download",
+            self.api_url, crate_name, num
+        );
+        let body = client
+            .get(&url)
+            .header(header::USER_AGENT, MIRROR_USER_AGENT)
+            .send()?
+            .error_for_status()?
+            .bytes()?
+            .to_vec();
+        let actual_checksum = hex::encode(Sha256::digest(&body));
+        if checksum.as_deref() != Some(actual_checksum.as_str()) {
+            return Err(anyhow!(
+                "the checksum of `{}#{}` doesn't match its index entry",
+                crate_name,
+                num
+            ));
+        }
+        uploader.upload_crate_file(client, &crate_name, num, body)?;
+
+        diesel::update(upstream_versions::table.find(version_id))
+            .set(upstream_versions::crate_file_cached_at.eq(diesel::dsl::now))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the versions of a crate which were published upstream since it was last
+    /// mirrored, and updates whether the mirrored versions are yanked.
+    ///
+    /// Returns `None` if the crate is known neither locally nor upstream. Crates with
+    /// versions published to this instance are returned as is.
+    pub fn sync_crate(
+        &self,
+        conn: &PgConnection,
+        client: &Client,
+        name: &str,
+    ) -> Result<Option<Crate>> {
+        let local = Crate::by_name(name).first::<Crate>(conn).optional()?;
+        if let Some(krate) = &local {
+            let local_versions: i64 = versions::table
+                .left_join(upstream_versions::table)
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(upstream_versions::version_id.is_null())
+                .count()
+                .get_result(conn)?;
+            if local_versions > 0 {
+                return Ok(local);
+            }
+        }
+
+        let entries = match self.index_entries(client, name)? {
+            Some(entries) if !entries.is_empty() => entries,
+            _ => return Ok(local),
+        };
+        let krate = match local {
+            Some(krate) => krate,
+            None => self.insert_crate(conn, client, &entries[0].name)?,
+        };
+
+        conn.transaction(|| {
+            let existing: Vec<(i32, String, bool)> = versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .select((versions::id, versions::num, versions::yanked))
+                .load(conn)?;
+
+            let mut changed = false;
+            let mut dependencies = Vec::new();
+            for entry in &entries {
+                let yanked = entry.yanked.unwrap_or(false);
+                match existing.iter().find(|(_, num, _)| *num == entry.vers) {
+                    Some((_, _, was_yanked)) if *was_yanked == yanked => {}
+                    Some((version_id, _, _)) => {
+                        diesel::update(versions::table.find(*version_id))
+                            .set(versions::yanked.eq(yanked))
+                            .execute(conn)?;
+                        changed = true;
+                    }
+                    None => {
+                        let version = Version::insert_from_index_entry(conn, krate.id, entry)?;
+                        diesel::insert_into(upstream_versions::table)
+                            .values(upstream_versions::version_id.eq(version.id))
+                            .execute(conn)?;
+                        dependencies.extend(
+                            entry
+                                .deps
+                                .iter()
+                                .map(|dep| dep.package.as_ref().unwrap_or(&dep.name).clone()),
+                        );
+                        changed = true;
+                    }
+                }
+            }
+
+            if changed {
+                git::replace_index_entries(krate.name.clone(), entries).enqueue(conn)?;
+            }
+
+            // Cargo needs the index entries of all dependencies to resolve a crate
+            dependencies.sort();
+            dependencies.dedup();
+            for dependency in dependencies {
+                let exists = diesel::select(diesel::dsl::exists(Crate::by_name(&dependency)))
+                    .get_result(conn)?;
+                if !exists {
+                    crate::tasks::mirror_upstream_crate(self.clone(), dependency).enqueue(conn)?;
+                }
+            }
+
+            Ok(Some(krate))
+        })
+    }
+
+    /// Replays the events of the upstream registry which happened since the last replayed
+    /// event, in order.
+    ///
+    /// The events of the last `EVENT_ID_LAG` ids are read again, and the ones which were
+    /// already replayed are skipped, so that the events committed after events with higher ids
+    /// aren't lost.
+    ///
+    /// Stops at the first event which can't be replayed yet, such as the publication of a
+    /// version which isn't in the upstream index yet, so that it is retried the next time.
+    pub fn follow_events(
+        &self,
+        conn: &PgConnection,
+        client: &Client,
+        uploader: &Uploader,
+    ) -> Result<()> {
+        #[derive(Deserialize)]
+        struct R {
+            events: Vec<EncodableRegistryEvent>,
+        }
+
+        let mut last_event_id = self.last_event_id(conn)?;
+        let mut since = (last_event_id - EVENT_ID_LAG).max(0);
+        loop {
+            let url = format!(
+                "{}/api/v1/events?since={}&per_page={}",
+                self.api_url, since, EVENTS_PER_PAGE
+            );
+            let events = client
+                .get(&url)
+                .header(header::USER_AGENT, MIRROR_USER_AGENT)
+                .send()?
+                .error_for_status()?
+                .json::<R>()?
+                .events;
+            let page_end = match events.last() {
+                Some(event) => event.id,
+                None => break,
+            };
+
+            for event in events {
+                if self.was_replayed(conn, event.id)? {
+                    continue;
+                }
+                self.replay_event(conn, client, uploader, &event)?;
+                last_event_id = last_event_id.max(event.id);
+                self.record_replayed(conn, event.id, last_event_id)?;
+            }
+            since = page_end;
+        }
+
+        self.forget_replayed_events(conn, last_event_id)?;
+        Ok(())
+    }
+
+    /// Returns the highest id of the replayed events of the upstream registry.
+    fn last_event_id(&self, conn: &PgConnection) -> QueryResult<i64> {
+        Ok(replication_cursors::table
+            .find(&self.api_url)
+            .select(replication_cursors::last_event_id)
+            .first(conn)
+            .optional()?
+            .unwrap_or(0))
+    }
+
+    fn was_replayed(&self, conn: &PgConnection, event_id: i64) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(
+            replayed_registry_events::table.find((&self.api_url, event_id)),
+        ))
+        .get_result(conn)
+    }
+
+    /// Records that an event was replayed, along with the highest id of the replayed events.
+    fn record_replayed(
+        &self,
+        conn: &PgConnection,
+        event_id: i64,
+        last_event_id: i64,
+    ) -> QueryResult<()> {
+        conn.transaction(|| {
+            diesel::insert_into(replayed_registry_events::table)
+                .values((
+                    replayed_registry_events::upstream_url.eq(&self.api_url),
+                    replayed_registry_events::event_id.eq(event_id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            diesel::insert_into(replication_cursors::table)
+                .values((
+                    replication_cursors::upstream_url.eq(&self.api_url),
+                    replication_cursors::last_event_id.eq(last_event_id),
+                ))
+                .on_conflict(replication_cursors::upstream_url)
+                .do_update()
+                .set((
+                    replication_cursors::last_event_id.eq(last_event_id),
+                    replication_cursors::updated_at.eq(diesel::dsl::now),
+                ))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Forgets the replayed events which won't be read again.
+    fn forget_replayed_events(&self, conn: &PgConnection, last_event_id: i64) -> QueryResult<()> {
+        diesel::delete(
+            replayed_registry_events::table
+                .filter(replayed_registry_events::upstream_url.eq(&self.api_url))
+                .filter(replayed_registry_events::event_id.lt(last_event_id - EVENT_ID_LAG)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn replay_event(
+        &self,
+        conn: &PgConnection,
+        client: &Client,
+        uploader: &Uploader,
+        event: &EncodableRegistryEvent,
+    ) -> Result<()> {
+        let name = &event.crate_name;
+        match event.kind {
+            RegistryEventKind::Publish | RegistryEventKind::Yank | RegistryEventKind::Unyank => {
+                let num = event
+                    .version
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("event {} has no version", event.id))?;
+                self.ensure_crate_file(conn, client, uploader, None, name, num)?;
+
+                let version: Option<(Version, String, Option<i32>)> = versions::table
+                    .inner_join(crates::table)
+                    .left_join(upstream_versions::table)
+                    .filter(Crate::with_name(name))
+                    .filter(versions::num.eq(num))
+                    .select((
+                        versions::all_columns,
+                        crates::name,
+                        upstream_versions::version_id.nullable(),
+                    ))
+                    .first(conn)
+                    .optional()?;
+                let (version, crate_name) = match version {
+                    Some((version, crate_name, Some(_))) => (version, crate_name),
+                    // Versions published to this instance are left untouched
+                    Some((_, _, None)) => return Ok(()),
+                    None => {
+                        return Err(anyhow!(
+                            "`{}#{}` isn't in the upstream index yet",
+                            name,
+                            num
+                        ))
+                    }
+                };
+
+                // The upstream index might not reflect the latest yanks yet
+                let yanked = match event.kind {
+                    RegistryEventKind::Yank => true,
+                    RegistryEventKind::Unyank => false,
+                    _ => return Ok(()),
+                };
+                if version.yanked != yanked {
+                    git::yank(crate_name, version, yanked).enqueue(conn)?;
+                }
+                Ok(())
+            }
+            // The license is mirrored along with the version it was changed by
+            RegistryEventKind::LicenseChange => Ok(()),
+            RegistryEventKind::OwnerAdd | RegistryEventKind::OwnerRemove => {
+                let owner = event
+                    .owner
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("event {} has no owner", event.id))?;
+
+                // The first owner of a crate is added before its first version is published
+                let krate = match Crate::by_name(name).first::<Crate>(conn).optional()? {
+                    Some(krate) => krate,
+                    None => self.insert_crate(conn, client, name)?,
+                };
+
+                let (owner_id, owner_kind) = if owner.kind == "team" {
+                    let team_id: Option<i32> = teams::table
+                        .filter(teams::login.eq(&owner.login))
+                        .select(teams::id)
+                        .first(conn)
+                        .optional()?;
+                    match team_id {
+                        Some(team_id) => (team_id, OwnerKind::Team),
+                        // Teams can't be created without knowing their organization,
+                        // so only the teams which exist on this instance are followed
+                        None => return Ok(()),
+                    }
+                } else if owner.github_id <= 0 {
+                    // Users which didn't log in with GitHub can't be matched across instances
+                    return Ok(());
+                } else {
+                    diesel::insert_into(users::table)
+                        .values(&NewUser::new(owner.github_id, &owner.login, None, None, ""))
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                    let user_id: i32 = users::table
+                        .filter(users::gh_id.eq(owner.github_id))
+                        .select(users::id)
+                        .first(conn)?;
+                    (user_id, OwnerKind::User)
+                };
+
+                let deleted = event.kind == RegistryEventKind::OwnerRemove;
+                diesel::insert_into(crate_owners::table)
+                    .values((
+                        crate_owners::crate_id.eq(krate.id),
+                        crate_owners::owner_id.eq(owner_id),
+                        crate_owners::owner_kind.eq(owner_kind as i32),
+                        crate_owners::deleted.eq(deleted),
+                    ))
+                    .on_conflict(crate_owners::table.primary_key())
+                    .do_update()
+                    .set(crate_owners::deleted.eq(deleted))
+                    .execute(conn)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Creates a mirrored crate, with the metadata it has upstream.
+    fn insert_crate(&self, conn: &PgConnection, client: &Client, name: &str) -> Result<Crate> {
+        #[derive(Deserialize)]
+        struct R {
+            #[serde(rename = "crate")]
+            krate: UpstreamCrate,
+        }
+
+        #[derive(Deserialize)]
+        struct UpstreamCrate {
+            description: Option<String>,
+            homepage: Option<String>,
+            documentation: Option<String>,
+            repository: Option<String>,
+        }
+
+        let url = format!("{}/api/v1/crates/{}", self.api_url, name);
+        let metadata = client
+            .get(&url)
+            .header(header::USER_AGENT, MIRROR_USER_AGENT)
+            .send()?
+            .error_for_status()?
+            .json::<R>()?
+            .krate;
+
+        let new_crate = NewCrate {
+            name,
+            description: metadata.description.as_deref(),
+            homepage: metadata.homepage.as_deref(),
+            documentation: metadata.documentation.as_deref(),
+            repository: metadata.repository.as_deref(),
+            ..NewCrate::default()
+        };
+        diesel::insert_into(crates::table)
+            .values(&new_crate)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(Crate::by_name(name).first(conn)?)
+    }
+
+    /// Fetches the index entries of a crate from the upstream registry.
+    ///
+    /// Returns `None` if the upstream registry doesn't know the crate. Invalid names, which
+    /// can't be published upstream either, are never looked up.
+    fn index_entries(&self, client: &Client, name: &str) -> Result<Option<Vec<git::Crate>>> {
+        if !Crate::valid_name(name) {
+            return Ok(None);
+        }
+
+        let url = format!("{}/{}", self.index_url, index_path(name));
+        let response = client
+            .get(&url)
+            .header(header::USER_AGENT, MIRROR_USER_AGENT)
+            .send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let body = response.error_for_status()?.text()?;
+        let entries = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<git::Crate>, _>>()?;
+        Ok(Some(entries))
+    }
+}
+
+/// Returns the path of the index file of a crate, relative to the root of the index.
+fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[0..2], &name[2..4], name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        index_path, UpstreamLookups, UpstreamRegistry, EVENT_ID_LAG, MAX_LOOKUPS_PER_MINUTE,
+    };
+    use crate::test_util::pg_connection;
+    use reqwest::blocking::Client;
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("Serde"), "se/rd/serde");
+        assert_eq!(index_path("serde_json"), "se/rd/serde_json");
+    }
+
+    #[test]
+    fn lookups_are_limited() {
+        let lookups = UpstreamLookups::default();
+        assert!(lookups.start("foo-bar"));
+        lookups.record_missing("foo-bar");
+        assert!(!lookups.start("foo-bar"));
+        assert!(!lookups.start("Foo_Bar"));
+
+        for _ in 1..MAX_LOOKUPS_PER_MINUTE {
+            assert!(lookups.start("baz"));
+        }
+        assert!(!lookups.start("baz"));
+    }
+
+    #[test]
+    fn invalid_names_are_not_looked_up() {
+        // Nothing listens on this address, the lookup must not get that far
+        let upstream = UpstreamRegistry {
+            api_url: String::from("http://127.0.0.1:9"),
+            index_url: String::from("http://127.0.0.1:9"),
+        };
+        let client = Client::new();
+        for name in &["snöw", "☃☃☃☃", "a☃", "foo bar"] {
+            assert!(upstream.index_entries(&client, name).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn events_committed_late_are_still_replayed() {
+        let conn = pg_connection();
+        let upstream = UpstreamRegistry {
+            api_url: String::from("https://upstream.example"),
+            index_url: String::from("https://index.upstream.example"),
+        };
+
+        // The event 1001 was still being recorded when the event 1002 was replayed
+        upstream.record_replayed(&conn, 1000, 1000).unwrap();
+        upstream.record_replayed(&conn, 1002, 1002).unwrap();
+        assert_eq!(upstream.last_event_id(&conn).unwrap(), 1002);
+        assert!(upstream.was_replayed(&conn, 1000).unwrap());
+        assert!(!upstream.was_replayed(&conn, 1001).unwrap());
+        assert!(upstream.was_replayed(&conn, 1002).unwrap());
+
+        // Replaying it doesn't move the cursor back
+        upstream.record_replayed(&conn, 1001, 1002).unwrap();
+        assert!(upstream.was_replayed(&conn, 1001).unwrap());
+        assert_eq!(upstream.last_event_id(&conn).unwrap(), 1002);
+
+        // The events which won't be read again are forgotten
+        let last_event_id = 1000 + EVENT_ID_LAG + 1;
+        upstream
+            .record_replayed(&conn, last_event_id, last_event_id)
+            .unwrap();
+        upstream
+            .forget_replayed_events(&conn, last_event_id)
+            .unwrap();
+        assert!(!upstream.was_replayed(&conn, 1000).unwrap());
+        assert!(upstream.was_replayed(&conn, 1001).unwrap());
+    }
+}
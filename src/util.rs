@@ -13,6 +13,7 @@ mod io_util;
 mod request_helpers;
 mod request_proxy;
 pub mod rfc3339;
+pub mod target;
 
 pub type AppResponse = Response<conduit::Body>;
 pub type EndpointResult = Result<AppResponse, Box<dyn errors::AppError>>;
@@ -25,11 +26,16 @@ pub type EndpointResult = Result<AppResponse, Box<dyn errors::AppError>>;
 ///
 /// This function will panic if serialization fails.
 pub fn json_response<T: Serialize>(t: &T) -> AppResponse {
-    let json = serde_json::to_string(t).unwrap();
+    let json = serde_json::to_vec(t).unwrap();
+    json_bytes_response(json)
+}
+
+/// Generates a response with JSON which was already serialized, e.g. by a cached response.
+pub fn json_bytes_response(json: Vec<u8>) -> AppResponse {
     Response::builder()
         .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
         .header(header::CONTENT_LENGTH, json.len())
-        .body(Body::from_vec(json.into_bytes()))
+        .body(Body::from_vec(json))
         .unwrap() // Header values are well formed, so should not panic
 }
 
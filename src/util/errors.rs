@@ -23,7 +23,9 @@ use crate::util::AppResponse;
 
 mod json;
 
-pub(crate) use json::{InsecurelyGeneratedTokenRevoked, NotFound, ReadOnlyMode, TooManyRequests};
+pub(crate) use json::{
+    InsecurelyGeneratedTokenRevoked, NotFound, ReadOnlyMode, ServerBusy, TooManyRequests,
+};
 
 /// Returns an error with status 200 and the provided description as JSON
 ///
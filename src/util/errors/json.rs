@@ -52,6 +52,8 @@ impl fmt::Display for NotFound {
 pub(super) struct Forbidden;
 #[derive(Debug)]
 pub(crate) struct ReadOnlyMode;
+#[derive(Debug)]
+pub(crate) struct ServerBusy;
 
 impl AppError for Forbidden {
     fn response(&self) -> Option<AppResponse> {
@@ -80,6 +82,19 @@ impl fmt::Display for ReadOnlyMode {
     }
 }
 
+impl AppError for ServerBusy {
+    fn response(&self) -> Option<AppResponse> {
+        let detail = "The server is busy processing other uploads. Please try again later.";
+        Some(json_error(detail, StatusCode::SERVICE_UNAVAILABLE))
+    }
+}
+
+impl fmt::Display for ServerBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "The queue of the CPU pool is full".fmt(f)
+    }
+}
+
 // The following structs wrap owned data and provide a custom message to the user
 
 #[derive(Debug)]
@@ -0,0 +1,241 @@
+//! Evaluation of the platforms of target-specific dependencies
+//!
+//! Dependencies can be declared for a target triple, as in
+//! `[target.x86_64-pc-windows-gnu.dependencies]`, or for a `cfg` expression, as in
+//! `[target.'cfg(unix)'.dependencies]`. The configuration of a target is derived
+//! from its triple, which approximates what `rustc --print cfg` reports for the
+//! common targets without needing a toolchain, and the platforms are parsed and
+//! matched against it by `cargo-platform`, like cargo does.
+
+use cargo_platform::{Cfg, Platform};
+
+/// The configuration of a compilation target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    triple: String,
+    arch: String,
+    vendor: String,
+    os: String,
+    env: String,
+    family: Option<&'static str>,
+    pointer_width: &'static str,
+    endian: &'static str,
+}
+
+/// The operating systems which can appear right after the architecture in a
+/// triple without vendor, as in `aarch64-linux-android`.
+const VENDORLESS_OS: &[&str] = &["linux", "none", "wasi", "emscripten", "windows"];
+
+/// The operating systems of the `unix` family.
+const UNIX_OS: &[&str] = &[
+    "linux",
+    "android",
+    "macos",
+    "ios",
+    "freebsd",
+    "netbsd",
+    "openbsd",
+    "dragonfly",
+    "solaris",
+    "illumos",
+    "haiku",
+    "redox",
+    "emscripten",
+];
+
+impl Target {
+    /// Derives the configuration of a target from its triple, such as
+    /// `x86_64-unknown-linux-gnu`. Returns `None` if it isn't a valid triple.
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        let valid = triple
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if !valid {
+            return None;
+        }
+
+        let parts = triple.split('-').collect::<Vec<_>>();
+        if parts.iter().any(|part| part.is_empty()) {
+            return None;
+        }
+        let (arch, vendor, os, env) = match parts[..] {
+            [arch, os] => (arch, "unknown", os, ""),
+            [arch, os, env] if VENDORLESS_OS.contains(&os) => (arch, "unknown", os, env),
+            [arch, vendor, os] => (arch, vendor, os, ""),
+            [arch, vendor, os, env] => (arch, vendor, os, env),
+            _ => return None,
+        };
+
+        let (os, env) = match (os, env) {
+            ("darwin", env) => ("macos", env),
+            (_, env) if env.starts_with("android") => ("android", ""),
+            (os, env) => (os, env),
+        };
+        let env = if env.starts_with("gnu") {
+            "gnu"
+        } else if env.starts_with("musl") {
+            "musl"
+        } else if env.starts_with("uclibc") {
+            "uclibc"
+        } else if env == "msvc" || env == "sgx" {
+            env
+        } else {
+            ""
+        };
+
+        let family = if os == "windows" {
+            Some("windows")
+        } else if UNIX_OS.contains(&os) {
+            Some("unix")
+        } else {
+            None
+        };
+
+        let is_64_bit = [
+            "x86_64",
+            "aarch64",
+            "powerpc64",
+            "mips64",
+            "s390x",
+            "sparc64",
+            "sparcv9",
+            "riscv64",
+            "wasm64",
+        ]
+        .iter()
+        .any(|prefix| arch.starts_with(prefix));
+        let pointer_width = if is_64_bit {
+            "64"
+        } else if arch == "msp430" || arch == "avr" {
+            "16"
+        } else {
+            "32"
+        };
+
+        let big_endian = ["powerpc", "mips", "s390x", "sparc"]
+            .iter()
+            .any(|prefix| arch.starts_with(prefix))
+            && !arch.ends_with("le")
+            && !arch.ends_with("el");
+        let endian = if big_endian { "big" } else { "little" };
+
+        let arch = match arch {
+            "i386" | "i586" | "i686" => "x86",
+            "powerpc64le" => "powerpc64",
+            "mipsel" => "mips",
+            "mips64el" => "mips64",
+            arch if arch.starts_with("arm") || arch.starts_with("thumb") => "arm",
+            arch if arch.starts_with("riscv64") => "riscv64",
+            arch if arch.starts_with("riscv32") => "riscv32",
+            arch => arch,
+        };
+
+        Some(Target {
+            triple: triple.to_string(),
+            arch: arch.to_string(),
+            vendor: vendor.to_string(),
+            os: os.to_string(),
+            env: env.to_string(),
+            family,
+            pointer_width,
+            endian,
+        })
+    }
+
+    /// Returns whether a dependency declared for `platform`, which is either a
+    /// target triple or a `cfg(...)` expression, applies to this target.
+    ///
+    /// Returns `None` if the platform can't be parsed.
+    pub fn matches(&self, platform: &str) -> Option<bool> {
+        let platform = platform.trim().parse::<Platform>().ok()?;
+        Some(platform.matches(&self.triple, &self.cfg()))
+    }
+
+    /// Returns the `cfg` values set for this target. `test`, `debug_assertions` and
+    /// the like are never set for dependencies, and features aren't known without
+    /// knowing how the dependent is built.
+    fn cfg(&self) -> Vec<Cfg> {
+        let key_pair = |key: &str, value: &str| Cfg::KeyPair(key.to_string(), value.to_string());
+        let mut cfg = vec![
+            key_pair("target_arch", &self.arch),
+            key_pair("target_vendor", &self.vendor),
+            key_pair("target_os", &self.os),
+            key_pair("target_env", &self.env),
+            key_pair("target_pointer_width", self.pointer_width),
+            key_pair("target_endian", self.endian),
+        ];
+        if let Some(family) = self.family {
+            cfg.push(Cfg::Name(family.to_string()));
+            cfg.push(key_pair("target_family", family));
+        }
+        cfg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    #[test]
+    fn derives_configuration_from_triples() {
+        let linux = target("x86_64-unknown-linux-gnu");
+        assert_eq!(linux.arch, "x86_64");
+        assert_eq!(linux.os, "linux");
+        assert_eq!(linux.env, "gnu");
+        assert_eq!(linux.family, Some("unix"));
+        assert_eq!(linux.pointer_width, "64");
+
+        let macos = target("x86_64-apple-darwin");
+        assert_eq!(macos.vendor, "apple");
+        assert_eq!(macos.os, "macos");
+
+        let android = target("armv7-linux-androideabi");
+        assert_eq!(android.arch, "arm");
+        assert_eq!(android.os, "android");
+        assert_eq!(android.pointer_width, "32");
+
+        let embedded = target("thumbv7em-none-eabihf");
+        assert_eq!(embedded.os, "none");
+        assert_eq!(embedded.family, None);
+
+        assert_none!(Target::from_triple("x86_64"));
+        assert_none!(Target::from_triple("x86_64--linux"));
+        assert_none!(Target::from_triple("cfg(unix)"));
+    }
+
+    #[test]
+    fn matches_triples_and_cfg_expressions() {
+        let linux = target("x86_64-unknown-linux-gnu");
+        let windows = target("x86_64-pc-windows-msvc");
+
+        assert_eq!(linux.matches("x86_64-unknown-linux-gnu"), Some(true));
+        assert_eq!(windows.matches("x86_64-unknown-linux-gnu"), Some(false));
+
+        assert_eq!(linux.matches("cfg(unix)"), Some(true));
+        assert_eq!(windows.matches("cfg(unix)"), Some(false));
+        assert_eq!(windows.matches(r#"cfg(target_env = "msvc")"#), Some(true));
+
+        let expr = r#"cfg(all(target_os = "linux", not(target_arch = "arm")))"#;
+        assert_eq!(linux.matches(expr), Some(true));
+        assert_eq!(windows.matches(expr), Some(false));
+
+        let expr = r#"cfg(any(windows, target_pointer_width = "16"))"#;
+        assert_eq!(linux.matches(expr), Some(false));
+        assert_eq!(windows.matches(expr), Some(true));
+
+        assert_eq!(linux.matches(r#"cfg(feature = "std")"#), Some(false));
+    }
+
+    #[test]
+    fn rejects_invalid_cfg_expressions() {
+        let linux = target("x86_64-unknown-linux-gnu");
+        assert_none!(linux.matches("cfg(not(unix, windows))"));
+        assert_none!(linux.matches("cfg(all(unix)"));
+        assert_none!(linux.matches(r#"cfg(target_os = linux)"#));
+        assert_none!(linux.matches("cfg(unix windows)"));
+    }
+}
@@ -1,7 +1,7 @@
 use chrono::NaiveDateTime;
 use std::collections::HashMap;
 
-use crate::models::DependencyKind;
+use crate::models::{DependencyKind, RegistryEventKind};
 use crate::util::rfc3339;
 
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
@@ -44,6 +44,18 @@ pub struct EncodableCrateOwnerInvitation {
     pub created_at: NaiveDateTime,
 }
 
+/// The serialization format for the `CrateOwnershipTransfer` model.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableCrateOwnershipTransfer {
+    pub crate_name: String,
+    pub requested_by_username: String,
+    pub target_username: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub expires_at: NaiveDateTime,
+}
+
 #[derive(Deserialize, Serialize, Debug, Copy, Clone)]
 pub struct InvitationResponse {
     pub crate_id: i32,
@@ -61,7 +73,172 @@ pub struct EncodableDependency {
     pub features: Vec<String>,
     pub target: Option<String>,
     pub kind: DependencyKind,
+    pub artifact: Option<Vec<String>>,
+    pub bindep_target: Option<String>,
+    pub lib: bool,
+    pub downloads: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCratePageview {
+    pub date: String,
+    pub views: i32,
+}
+
+/// The usage of a keyword or a category on a day
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableUsageStat {
+    pub date: String,
+    pub crates_cnt: i32,
+    pub downloads: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateAdvisory {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAuditEntry {
+    pub name: String,
+    pub version: String,
+    /// Whether the version is yanked, or `None` if it doesn't exist
+    pub yanked: Option<bool>,
+    /// The highest non-yanked version of the crate, or `None` if there is none
+    pub latest_version: Option<String>,
+    pub deprecated: bool,
+    pub advisories: Vec<EncodableCrateAdvisory>,
+}
+
+/// The metrics of a crate compared by the `GET /crate_comparison` endpoint
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableComparedCrate {
+    pub name: String,
+    pub downloads: i32,
+    pub recent_downloads: Option<i64>,
+    /// The publication time of the newest version, yanked or not
+    #[serde(with = "rfc3339::option")]
+    pub last_release_at: Option<NaiveDateTime>,
+    /// The highest non-yanked version, whose license, MSRV and dependencies are compared
+    pub latest_version: Option<String>,
+    pub license: Option<String>,
+    pub rust_version: Option<String>,
+    pub dependencies: EncodableDependencyCounts,
+    pub user_owners: usize,
+    pub team_owners: usize,
+}
+
+/// A crate whose name can be confused with another one, see `GET /crates/:crate_id/similar`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableSimilarCrate {
+    pub name: String,
     pub downloads: i32,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    /// `separators` if the names only differ by their case, `-` and `_`, `homoglyphs` if they
+    /// also differ by characters that look alike
+    pub collision: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct EncodableDependencyCounts {
+    pub normal: usize,
+    pub build: usize,
+    pub dev: usize,
+}
+
+/// The differences between two versions of a crate, see `GET /crates/:crate_id/diff`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionDiff {
+    pub from: String,
+    pub to: String,
+    pub dependencies: EncodableDependenciesDiff,
+    /// The names of the features added, removed or enabling other features
+    pub features: EncodableNamesDiff,
+    /// The paths of the files added, removed or whose checksum changed
+    pub files: EncodableNamesDiff,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependenciesDiff {
+    pub added: Vec<EncodableDiffDependency>,
+    pub removed: Vec<EncodableDiffDependency>,
+    /// The dependencies whose requirement or features changed, as they are in the `to` version
+    pub changed: Vec<EncodableDiffDependency>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDiffDependency {
+    pub name: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub target: Option<String>,
+    pub optional: bool,
+    pub default_features: bool,
+    pub features: Vec<String>,
+    /// The requirement in the `from` version, for changed dependencies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_req: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableNamesDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodablePublishPolicy {
+    pub allowed_token_ids: Option<Vec<i32>>,
+    pub require_user_owner: bool,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateQualityScore {
+    pub formula_version: i32,
+    pub docs: f64,
+    pub recent_release: f64,
+    pub tests: f64,
+    pub msrv: f64,
+    pub not_yanked: f64,
+    pub total: f64,
+    #[serde(with = "rfc3339")]
+    pub computed_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateLinkCheck {
+    pub field: String,
+    pub url: String,
+    pub status: String,
+    #[serde(with = "rfc3339")]
+    pub checked_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReadmeRerender {
+    pub id: i32,
+    pub total: i32,
+    pub rendered: i32,
+    pub skipped: i32,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// A heading of a README, with the headings of its section nested in `children`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReadmeHeading {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+    pub children: Vec<EncodableReadmeHeading>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -71,6 +248,31 @@ pub struct EncodableVersionDownload {
     pub date: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionChangelog {
+    pub file_name: String,
+    pub html: String,
+    pub section_html: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionFile {
+    pub path: String,
+    pub size: i64,
+    pub sha256: String,
+}
+
+/// A feature of a version, as listed by `GET /crates/:crate_id/:version/features`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionFeature {
+    pub name: String,
+    /// The features and optional dependencies the feature enables
+    pub enables: Vec<String>,
+    /// Whether the feature is enabled by the `default` feature
+    pub default: bool,
+    pub description: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableKeyword {
     pub id: String,
@@ -80,6 +282,56 @@ pub struct EncodableKeyword {
     pub crates_cnt: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependedUponCrate {
+    #[serde(flatten)]
+    pub krate: EncodableCrate,
+    pub dependents_count: i32,
+}
+
+/// How a user is related to one of the crates listed by `GET /users/:user_id/crates`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EncodableCrateRole {
+    /// The user owns the crate
+    Owner,
+    /// The user is a member of a team owning the crate
+    TeamMember,
+    /// The user published a version of the crate without being one of its owners
+    Publisher,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableUserCrate {
+    #[serde(flatten)]
+    pub krate: EncodableCrate,
+    /// Only included with `?include=role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<EncodableCrateRole>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTrendingCrate {
+    #[serde(flatten)]
+    pub krate: EncodableCrate,
+    pub trending: EncodableTrendingScore,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTrendingScore {
+    pub score: f64,
+    pub recent_downloads: i64,
+    pub baseline_downloads: i64,
+    pub new_dependents: i32,
+}
+
+/// A crate depending on another one, shown as "used by" on the page of the latter
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableNotableDependent {
+    pub name: String,
+    pub downloads: i64,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableCrate {
     pub id: String,
@@ -104,6 +356,22 @@ pub struct EncodableCrate {
     pub repository: Option<String>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
+    pub deprecated: bool,
+    /// The crate recommended instead of this one, when it's deprecated
+    pub superseded_by: Option<String>,
+    /// The minimum supported Rust version of the latest version, for the results of searches
+    pub rust_version: Option<String>,
+    /// The matches of the query, for the results of searches
+    pub highlights: Option<EncodableCrateHighlights>,
+}
+
+/// The name and description of a crate found by a search, as HTML escaped with the terms of the
+/// query wrapped in `<mark>` elements
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateHighlights {
+    pub name: Option<String>,
+    /// The fragment of the description around its first match
+    pub description: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -189,6 +457,15 @@ pub struct EncodablePublicUser {
     pub url: Option<String>,
 }
 
+/// A member of a team owning a crate, see `TeamMembership`
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableTeamMember {
+    pub user: EncodablePublicUser,
+    /// When the user was last found to belong to the team
+    #[serde(with = "rfc3339")]
+    pub checked_at: NaiveDateTime,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct EncodableAuditAction {
     pub action: String,
@@ -197,6 +474,16 @@ pub struct EncodableAuditAction {
     pub time: NaiveDateTime,
 }
 
+/// A yank or an unyank of a version
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableYankEvent {
+    pub yanked: bool,
+    pub reason: Option<String>,
+    pub user: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersion {
     pub id: i32,
@@ -217,8 +504,33 @@ pub struct EncodableVersion {
     pub license: Option<String>,
     pub links: EncodableVersionLinks,
     pub crate_size: Option<i32>,
+    /// The total size of the files of the `.crate` tarball, unknown for older versions
+    pub uncompressed_size: Option<i64>,
+    pub vcs_info: Option<EncodableVersionVcsInfo>,
+    /// The targets listed as platform hints in `package.metadata.docs.rs`
+    pub metadata_targets: Option<Vec<String>>,
+    /// Whether the license differs from the one of the version published before this one
+    pub license_changed: bool,
+    pub previous_license: Option<String>,
+    /// The documentation of the features, by feature name
+    pub feature_docs: serde_json::Value,
     pub published_by: Option<EncodablePublicUser>,
     pub audit_actions: Vec<EncodableAuditAction>,
+    /// The yanks and unyanks of the version, the oldest first
+    pub yank_history: Vec<EncodableYankEvent>,
+}
+
+/// A version along with its dependencies, as listed by `GET /crates/:crate_id/full`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableFullVersion {
+    pub id: i32,
+    pub num: String,
+    pub yanked: bool,
+    pub features: serde_json::Value,
+    pub rust_version: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub dependencies: Vec<EncodableDependency>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -228,6 +540,89 @@ pub struct EncodableVersionLinks {
     pub authors: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableEmailDomainRule {
+    pub domain: String,
+    pub blocked: bool,
+    pub updated_by: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
+/// A name that can't be used by new crates
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReservedCrateName {
+    pub name: String,
+    pub reason: Option<String>,
+    pub reserved_by: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+/// A version held for review by the admins, see the `spam` module
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableQuarantinedVersion {
+    pub version_id: i32,
+    pub crate_name: String,
+    pub num: String,
+    pub published_by: Option<String>,
+    pub spam_score: i32,
+    pub reasons: Vec<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableFeatureFlag {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+    pub updated_by: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableRegistryEvent {
+    pub id: i64,
+    pub kind: RegistryEventKind,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub version: Option<String>,
+    pub owner: Option<EncodableRegistryEventOwner>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableRegistryEventOwner {
+    pub kind: String,
+    pub login: String,
+    pub github_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionRebuild {
+    pub user: EncodablePublicUser,
+    pub checksum: String,
+    pub matches: bool,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionRebuildSummary {
+    pub published_checksum: Option<String>,
+    pub matching: usize,
+    pub mismatching: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableVersionVcsInfo {
+    pub sha1: String,
+    pub dirty: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GoodCrate {
     #[serde(rename = "crate")]
@@ -318,6 +713,12 @@ mod tests {
                 authors: "".to_string(),
             },
             crate_size: Some(1234),
+            uncompressed_size: Some(4321),
+            vcs_info: None,
+            metadata_targets: None,
+            license_changed: false,
+            previous_license: None,
+            feature_docs: serde_json::from_str("{}").unwrap(),
             published_by: None,
             audit_actions: vec![EncodableAuditAction {
                 action: "publish".to_string(),
@@ -330,6 +731,7 @@ mod tests {
                 },
                 time: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12),
             }],
+            yank_history: vec![],
         };
         let json = serde_json::to_string(&ver).unwrap();
         assert_some!(json
@@ -369,6 +771,10 @@ mod tests {
                 reverse_dependencies: "".to_string(),
             },
             exact_match: false,
+            deprecated: false,
+            superseded_by: None,
+            rust_version: None,
+            highlights: None,
         };
         let json = serde_json::to_string(&crt).unwrap();
         assert_some!(json
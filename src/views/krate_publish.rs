@@ -66,6 +66,15 @@ pub struct EncodableCrateDependency {
     pub kind: Option<DependencyKind>,
     pub explicit_name_in_toml: Option<EncodableCrateName>,
     pub registry: Option<String>,
+    /// The artifact kinds requested by an artifact dependency, e.g. `bin` or `cdylib`
+    #[serde(default)]
+    pub artifact: Option<Vec<String>>,
+    /// The target to build the artifacts of an artifact dependency for
+    #[serde(default)]
+    pub bindep_target: Option<String>,
+    /// Whether an artifact dependency is also used as a library
+    #[serde(default)]
+    pub lib: bool,
 }
 
 impl<'de> Deserialize<'de> for EncodableCrateName {
@@ -239,4 +248,9 @@ fn feature_deserializes_for_valid_features() {
     assert_err!(json::from_str::<EncodableFeature>("\"%/%\""));
     assert_ok!(json::from_str::<EncodableFeature>("\"a/a\""));
     assert_ok!(json::from_str::<EncodableFeature>("\"32-column-tables\""));
+    assert_ok!(json::from_str::<EncodableFeature>("\"dep:serde\""));
+    assert_ok!(json::from_str::<EncodableFeature>("\"serde?/std\""));
+    assert_err!(json::from_str::<EncodableFeature>("\"dep:\""));
+    assert_err!(json::from_str::<EncodableFeature>("\"serde?\""));
+    assert_err!(json::from_str::<EncodableFeature>("\"?/std\""));
 }